@@ -0,0 +1,212 @@
+// a11y.rs — OS accessibility preferences (reduced motion, high contrast,
+// screen reader)
+//
+// Read natively on macOS (NSWorkspace) and Windows (SystemParametersInfo),
+// via gsettings / kreadconfig on Linux desktops. A watcher re-reads them
+// every few seconds; the latest snapshot is cached for Rust-side consumers
+// and changes are pushed to the UI.
+//
+// Tauri commands exposed:
+//   get_a11y_prefs → A11yPrefs
+//
+// Events emitted:
+//   a11y-changed   → A11yPrefs
+
+use crate::power;
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+
+const POLL_SECS: u64 = 5;
+
+static CURRENT: Mutex<Option<A11yPrefs>> = Mutex::new(None);
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+pub struct A11yPrefs {
+    /// Animations should be disabled or minimized
+    pub reduced_motion: bool,
+    pub high_contrast:  bool,
+    /// VoiceOver / Narrator / NVDA / Orca is running
+    pub screen_reader:  bool,
+}
+
+/// Latest known preferences (reads them once if the watcher hasn't yet).
+pub(crate) fn current() -> A11yPrefs {
+    if let Some(p) = CURRENT.lock().ok().and_then(|c| *c) {
+        return p;
+    }
+    let prefs = platform::read();
+    if let Ok(mut c) = CURRENT.lock() {
+        *c = Some(prefs);
+    }
+    prefs
+}
+
+#[tauri::command]
+pub fn get_a11y_prefs() -> A11yPrefs {
+    current()
+}
+
+/// Poll the OS preferences and emit `a11y-changed` when they change.
+pub fn spawn_watcher(app: tauri::AppHandle) {
+    use tauri::Manager;
+
+    std::thread::spawn(move || {
+        let mut last = current();
+        loop {
+            std::thread::sleep(power::scaled(std::time::Duration::from_secs(POLL_SECS)));
+            let prefs = platform::read();
+            if prefs != last {
+                log::info!("a11y: preferences changed: {:?}", prefs);
+                if let Ok(mut c) = CURRENT.lock() {
+                    *c = Some(prefs);
+                }
+                let _ = app.emit_all("a11y-changed", prefs);
+                last = prefs;
+            }
+        }
+    });
+}
+
+// ═══════════════════════════════════════════════════════════════════════
+// macOS — NSWorkspace accessibility properties
+// ═══════════════════════════════════════════════════════════════════════
+#[cfg(target_os = "macos")]
+mod platform {
+    use super::A11yPrefs;
+    use cocoa::base::{id, BOOL, YES};
+    use objc::{class, msg_send, sel, sel_impl};
+
+    pub fn read() -> A11yPrefs {
+        unsafe {
+            let ws: id = msg_send![class!(NSWorkspace), sharedWorkspace];
+            let reduced_motion: BOOL = msg_send![ws, accessibilityDisplayShouldReduceMotion];
+            let high_contrast:  BOOL = msg_send![ws, accessibilityDisplayShouldIncreaseContrast];
+            let screen_reader:  BOOL = msg_send![ws, isVoiceOverEnabled];
+            A11yPrefs {
+                reduced_motion: reduced_motion == YES,
+                high_contrast:  high_contrast == YES,
+                screen_reader:  screen_reader == YES,
+            }
+        }
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════
+// Windows — SystemParametersInfo
+// ═══════════════════════════════════════════════════════════════════════
+#[cfg(target_os = "windows")]
+mod platform {
+    use super::A11yPrefs;
+    use windows::Win32::Foundation::BOOL;
+    use windows::Win32::UI::Accessibility::{HCF_HIGHCONTRASTON, HIGHCONTRASTW};
+    use windows::Win32::UI::WindowsAndMessaging::{
+        SystemParametersInfoW, SPI_GETCLIENTAREAANIMATION, SPI_GETHIGHCONTRAST, SPI_GETSCREENREADER,
+        SYSTEM_PARAMETERS_INFO_ACTION, SYSTEM_PARAMETERS_INFO_UPDATE_FLAGS,
+    };
+
+    fn get_bool(action: SYSTEM_PARAMETERS_INFO_ACTION) -> Option<bool> {
+        let mut value = BOOL(0);
+        unsafe {
+            SystemParametersInfoW(action, 0, Some(&mut value as *mut BOOL as *mut _), SYSTEM_PARAMETERS_INFO_UPDATE_FLAGS(0))
+                .ok()
+                .map(|_| value.as_bool())
+        }
+    }
+
+    pub fn read() -> A11yPrefs {
+        let mut hc = HIGHCONTRASTW {
+            cbSize: std::mem::size_of::<HIGHCONTRASTW>() as u32,
+            ..Default::default()
+        };
+        let high_contrast = unsafe {
+            SystemParametersInfoW(
+                SPI_GETHIGHCONTRAST,
+                hc.cbSize,
+                Some(&mut hc as *mut HIGHCONTRASTW as *mut _),
+                SYSTEM_PARAMETERS_INFO_UPDATE_FLAGS(0),
+            ).is_ok() && (hc.dwFlags & HCF_HIGHCONTRASTON).0 != 0
+        };
+        A11yPrefs {
+            // "Show animations in Windows" off
+            reduced_motion: get_bool(SPI_GETCLIENTAREAANIMATION).map(|on| !on).unwrap_or(false),
+            high_contrast,
+            screen_reader:  get_bool(SPI_GETSCREENREADER).unwrap_or(false),
+        }
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════
+// Linux — GNOME gsettings, KDE kreadconfig5 as fallback
+// ═══════════════════════════════════════════════════════════════════════
+#[cfg(all(not(target_os = "macos"), not(target_os = "windows")))]
+mod platform {
+    use super::A11yPrefs;
+    use std::process::Command;
+
+    fn run(cmd: &str, args: &[&str]) -> Option<String> {
+        let out = Command::new(cmd).args(args).output().ok()?;
+        out.status.success().then(|| String::from_utf8_lossy(&out.stdout).trim().to_string())
+    }
+
+    fn gsettings(schema: &str, key: &str) -> Option<String> {
+        run("gsettings", &["get", schema, key])
+    }
+
+    pub fn read() -> A11yPrefs {
+        let animations = gsettings("org.gnome.desktop.interface", "enable-animations")
+            .and_then(|v| super::parse_bool(&v))
+            .or_else(|| {
+                run("kreadconfig5", &["--group", "KDE", "--key", "AnimationDurationFactor"])
+                    .and_then(|v| v.parse::<f32>().ok())
+                    .map(|factor| factor > 0.0)
+            });
+
+        let high_contrast = gsettings("org.gnome.desktop.a11y.interface", "high-contrast")
+            .and_then(|v| super::parse_bool(&v))
+            .unwrap_or(false)
+            || gsettings("org.gnome.desktop.interface", "gtk-theme")
+                .map(|t| t.to_lowercase().contains("highcontrast"))
+                .unwrap_or(false);
+
+        let screen_reader = gsettings("org.gnome.desktop.a11y.applications", "screen-reader-enabled")
+            .and_then(|v| super::parse_bool(&v))
+            .unwrap_or(false);
+
+        A11yPrefs {
+            reduced_motion: animations.map(|on| !on).unwrap_or(false),
+            high_contrast,
+            screen_reader,
+        }
+    }
+}
+
+/// gsettings prints booleans as `true` / `false`.
+#[cfg_attr(any(target_os = "macos", target_os = "windows"), allow(dead_code))]
+fn parse_bool(value: &str) -> Option<bool> {
+    match value.trim() {
+        "true"  => Some(true),
+        "false" => Some(false),
+        _       => None,
+    }
+}
+
+// ── Unit tests ─────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_bool() {
+        assert_eq!(parse_bool("true\n"), Some(true));
+        assert_eq!(parse_bool("false"), Some(false));
+        assert_eq!(parse_bool("'Adwaita'"), None);
+    }
+
+    #[test]
+    fn test_prefs_serialize_snake_case() {
+        let json = serde_json::to_value(A11yPrefs { reduced_motion: true, ..Default::default() }).unwrap();
+        assert_eq!(json["reduced_motion"], true);
+        assert_eq!(json["screen_reader"], false);
+    }
+}
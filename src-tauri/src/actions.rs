@@ -0,0 +1,162 @@
+// actions.rs — registry of user-triggerable actions
+//
+// Every action the user can trigger (command palette, global hotkey, tray
+// menu, jump list / dock menu) is listed here once with an id, a title and
+// search keywords, and dispatched through `dispatch` so all entry points
+// behave identically.
+// Actions the backend cannot perform itself are forwarded to the UI as events.
+//
+// Tauri commands exposed:
+//   list_actions → Vec<ActionInfo>
+//   run_action   (id, arg?)
+//
+// Events emitted:
+//   trigger-screenshot → ()
+//   open-settings      → ()
+//   quick-ask          → () — focus the prompt input
+//   paste-context      → () — attach the clipboard (ingest_clipboard) to the prompt
+//   switch-model       → model id
+
+use crate::error::AppError;
+use crate::{hotkeys, overlay, session};
+use serde::Serialize;
+use tauri::{AppHandle, Manager};
+
+// ── Types ──────────────────────────────────────────────────────────────────
+
+#[derive(Debug, Serialize, Clone)]
+pub struct ActionInfo {
+    pub id:        &'static str,
+    pub title:     &'static str,
+    pub keywords:  &'static [&'static str],
+    /// Whether `run_action` needs an `arg` (model id)
+    pub takes_arg: bool,
+    /// Global hotkey, including user rebinding, if one is bound
+    pub hotkey:    Option<String>,
+}
+
+/// (id, title, keywords, takes_arg)
+const ACTIONS: &[(&str, &str, &[&str], bool)] = &[
+    ("capture_screen",       "Capture screen and analyze", &["screenshot", "snap", "analyze", "vision"], false),
+    ("toggle_click_through", "Toggle ghost mode",          &["ghost", "click-through", "transparent"],  false),
+    ("toggle_window",        "Show / hide window",         &["hide", "show", "overlay", "window"],      false),
+    ("quick_ask",            "Quick ask",                  &["ask", "question", "prompt", "type"],      false),
+    ("paste_context",        "Paste clipboard as context", &["paste", "clipboard", "attach", "context"], false),
+    ("open_settings",        "Open settings",              &["preferences", "config", "api key"],       false),
+    ("switch_model",         "Switch model",               &["model", "provider", "llm"],               true),
+    ("quit",                 "Quit",                       &["exit", "close"],                          false),
+];
+
+// ── Registry ───────────────────────────────────────────────────────────────
+
+pub fn all() -> Vec<ActionInfo> {
+    let bound = hotkeys::bindings();
+    ACTIONS.iter()
+        .map(|&(id, title, keywords, takes_arg)| ActionInfo {
+            id,
+            title,
+            keywords,
+            takes_arg,
+            hotkey: bound.iter()
+                .find(|(action, _)| action == id)
+                .map(|(_, accel)| accel.clone()),
+        })
+        .collect()
+}
+
+/// Perform action `id`. Hotkeys and the tray call this directly.
+pub fn dispatch(app: &AppHandle, id: &str, arg: Option<String>) -> Result<(), String> {
+    let takes_arg = ACTIONS.iter()
+        .find(|(a, ..)| *a == id)
+        .map(|&(.., takes_arg)| takes_arg)
+        .ok_or_else(|| format!("Unknown action '{}'", id))?;
+    let arg = match (takes_arg, arg) {
+        (true, Some(a)) if !a.trim().is_empty() => Some(a),
+        (true, _)  => return Err(format!("Action '{}' requires an argument", id)),
+        (false, _) => None,
+    };
+
+    let main = app.get_window("main");
+    match id {
+        "capture_screen" => {
+            if let Some(win) = main {
+                win.emit("trigger-screenshot", ()).map_err(|e| e.to_string())?;
+            }
+        }
+        "toggle_click_through" => {
+            if let Some(win) = main {
+                overlay::toggle_click_through(&win);
+            }
+        }
+        "toggle_window" => overlay::toggle_window(app),
+        "quick_ask" => {
+            if let Some(win) = main {
+                let _ = win.show();
+                let _ = win.set_focus();
+                if overlay::get_ghost_mode_state() {
+                    overlay::set_ghost_mode(win.clone(), false)?;
+                }
+                win.emit("quick-ask", ()).map_err(|e| e.to_string())?;
+            }
+        }
+        "paste_context" => {
+            if let Some(win) = main {
+                let _ = win.show();
+                let _ = win.set_focus();
+                win.emit("paste-context", ()).map_err(|e| e.to_string())?;
+            }
+        }
+        "open_settings" => {
+            if let Some(win) = main {
+                let _ = win.show();
+                let _ = win.set_focus();
+                win.emit("open-settings", ()).map_err(|e| e.to_string())?;
+            }
+        }
+        "switch_model" => app.emit_all("switch-model", arg).map_err(|e| e.to_string())?,
+        "quit" => {
+            // AppHandle::exit skips RunEvent::Exit
+            session::mark_clean_exit();
+            app.exit(0);
+        }
+        _ => unreachable!("action table and dispatch out of sync: {}", id),
+    }
+    log::info!("action: {}", id);
+    Ok(())
+}
+
+// ── Tauri commands ─────────────────────────────────────────────────────────
+
+#[tauri::command]
+pub fn list_actions() -> Vec<ActionInfo> {
+    all()
+}
+
+#[tauri::command]
+pub fn run_action(app: AppHandle, id: String, arg: Option<String>) -> Result<(), AppError> {
+    dispatch(&app, &id, arg).map_err(AppError::from)
+}
+
+// ── Unit tests ─────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_action_ids_unique() {
+        let mut ids: Vec<_> = ACTIONS.iter().map(|(id, ..)| *id).collect();
+        ids.sort();
+        ids.dedup();
+        assert_eq!(ids.len(), ACTIONS.len());
+    }
+
+    #[test]
+    fn test_every_hotkey_maps_to_an_action() {
+        for (id, _) in hotkeys::DEFAULT_HOTKEYS {
+            assert!(ACTIONS.iter().any(|(a, ..)| a == id), "hotkey bound to unknown action {}", id);
+        }
+        let capture = all().into_iter().find(|a| a.id == "capture_screen").unwrap();
+        assert_eq!(capture.hotkey.as_deref(), Some("Alt+Shift+S"));
+    }
+}
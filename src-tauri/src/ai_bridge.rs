@@ -1,4 +1,7 @@
-// ai_bridge.rs — HTTP clients for OpenAI Vision, Anthropic Claude, local LLMs + streaming
+// ai_bridge.rs — HTTP clients for OpenAI Vision, Anthropic Claude, DeepSeek, OpenRouter,
+// Together AI, local LLMs + streaming
+use crate::error::AppError;
+use crate::{audit, http, image_prep, moderation, secret_scanner};
 use futures_util::StreamExt;
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
@@ -20,14 +23,55 @@ fn new_cancel_receiver() -> watch::Receiver<u64> {
     cancel_tx().subscribe()
 }
 
+/// Requests started with an id, cancellable one at a time.
+static REQUEST_CANCELS: std::sync::Mutex<Vec<(String, std::sync::Arc<tokio::sync::Notify>)>> =
+    std::sync::Mutex::new(Vec::new());
+
 /// Cancel the in-flight request (if any). Called from the frontend.
+/// With `request_id` only that request is cancelled; without it, all are.
 #[tauri::command]
-pub fn cancel_ai_request() {
+pub fn cancel_ai_request(request_id: Option<String>) {
+    if let Some(id) = request_id {
+        if let Some((_, notify)) = REQUEST_CANCELS.lock().ok()
+            .and_then(|r| r.iter().find(|(r, _)| *r == id).cloned())
+        {
+            notify.notify_one();
+        }
+        return;
+    }
     let tx = cancel_tx();
     let next = *tx.borrow() + 1;
     let _ = tx.send(next);
 }
 
+/// Run `fut` so that `cancel_ai_request(request_id)` cancels it alone
+/// (returns `"__CANCELLED__"`). Without an id it just runs `fut`.
+pub(crate) async fn with_request_id<T, E: From<&'static str>>(
+    request_id: Option<&str>,
+    fut:        impl std::future::Future<Output = Result<T, E>>,
+) -> Result<T, E> {
+    let Some(id) = request_id else { return fut.await };
+
+    struct Unregister<'a>(&'a str);
+    impl Drop for Unregister<'_> {
+        fn drop(&mut self) {
+            if let Ok(mut r) = REQUEST_CANCELS.lock() {
+                r.retain(|(r, _)| r != self.0);
+            }
+        }
+    }
+
+    let notify = std::sync::Arc::new(tokio::sync::Notify::new());
+    if let Ok(mut r) = REQUEST_CANCELS.lock() {
+        r.push((id.to_string(), notify.clone()));
+    }
+    let _unregister = Unregister(id);
+    tokio::select! {
+        result = fut => result,
+        _ = notify.notified() => Err("__CANCELLED__".into()),
+    }
+}
+
 // ── Shared request/response types ───────────────────────────────────────
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -44,6 +88,12 @@ pub struct AiRequest {
     pub model:         Option<String>,
     /// Hard cap on output tokens (None = use provider default)
     pub max_tokens:    Option<u32>,
+    /// Send prompt and context_files as-is, skipping secret redaction
+    #[serde(default)]
+    pub allow_secrets: bool,
+    /// Moderation profile the request is checked under (None = the active one)
+    #[serde(default)]
+    pub profile:       Option<String>,
 }
 
 /// Request for local LLM servers (LM Studio, Ollama, generic OpenAI-compatible).
@@ -61,6 +111,9 @@ pub struct LocalAiRequest {
     pub model:         Option<String>,
     /// Hard cap on output tokens (None = use server default)
     pub max_tokens:    Option<u32>,
+    /// "openai" | "koboldcpp" | "textgen" — None / "auto" probes the server
+    #[serde(default)]
+    pub server_type:   Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -68,8 +121,19 @@ pub struct AiResponse {
     pub text:        String,
     pub model:       String,
     pub tokens_used: Option<u32>,
+    /// Prompt-cache hits (Anthropic `cache_read_input_tokens`)
+    pub cache_read_tokens:  Option<u32>,
+    /// Prompt-cache writes (Anthropic `cache_creation_input_tokens`)
+    pub cache_write_tokens: Option<u32>,
+    /// Second-pass check of the answer (analyze_with_verification only)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub verification:       Option<crate::verification::Verification>,
 }
 
+/// Claude only caches prefixes of at least ~1024 tokens; smaller context
+/// blocks would pay the cache-write premium for nothing.
+const CLAUDE_CACHE_MIN_CHARS: usize = 4_000;
+
 // ── Helpers ─────────────────────────────────────────────────────────────
 
 /// Prepend RAG context to the user prompt
@@ -78,6 +142,7 @@ pub struct AiResponse {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::error::ErrorKind;
 
     #[test]
     fn test_build_prompt_no_context() {
@@ -88,6 +153,9 @@ mod tests {
             image_base64:  None,
             context_files: None,
             model:         None,
+            max_tokens:    None,
+            allow_secrets: false,
+            profile:       None,
         };
         assert_eq!(build_prompt(&req), "What is this?");
     }
@@ -101,6 +169,9 @@ mod tests {
             image_base64:  None,
             context_files: Some(vec!["### main.rs\n```rust\nfn main(){}\n```".into()]),
             model:         None,
+            max_tokens:    None,
+            allow_secrets: false,
+            profile:       None,
         };
         let result = build_prompt(&req);
         assert!(result.contains("PROJECT CONTEXT"));
@@ -117,10 +188,31 @@ mod tests {
             image_base64:  None,
             context_files: Some(vec![]),      // empty vec
             model:         None,
+            max_tokens:    None,
+            allow_secrets: false,
+            profile:       None,
         };
         assert_eq!(build_prompt(&req), "Hello");
     }
 
+    #[test]
+    fn test_outbound_text_covers_system_prompt_and_context() {
+        let req = AiRequest {
+            api_key:       "key".into(),
+            prompt:        "Hello".into(),
+            system_prompt: Some("Be terse".into()),
+            image_base64:  None,
+            context_files: Some(vec!["### notes.md\nsecret plan".into()]),
+            model:         None,
+            max_tokens:    None,
+            allow_secrets: false,
+            profile:       None,
+        };
+        let text = outbound_text(&req);
+        assert!(text.starts_with("Be terse\n\nHello"));
+        assert!(text.contains("secret plan"));
+    }
+
     #[test]
     fn test_missing_api_key_returns_err() {
         let rt = tokio::runtime::Runtime::new().unwrap();
@@ -131,9 +223,13 @@ mod tests {
             image_base64:  None,
             context_files: None,
             model:         None,
+            max_tokens:    None,
+            allow_secrets: false,
+            profile:       None,
         }));
-        assert!(result.is_err());
-        assert!(result.unwrap_err().contains("API key is required"));
+        let err = result.unwrap_err();
+        assert!(err.message.contains("API key is required"));
+        assert_eq!((err.kind, err.provider.as_deref()), (ErrorKind::Auth, Some("openai")));
     }
 
     #[test]
@@ -146,6 +242,9 @@ mod tests {
             image_base64:  None,
             context_files: None,
             model:         None,
+            max_tokens:    None,
+            allow_secrets: false,
+            profile:       None,
         }));
         assert!(result.is_err());
     }
@@ -160,26 +259,293 @@ mod tests {
             image_base64:  None,
             context_files: None,
             model:         None,
+            max_tokens:    None,
+            allow_secrets: false,
+            profile:       None,
         }));
         assert!(result.is_err());
-        assert!(result.unwrap_err().contains("API key is required"));
+        assert!(result.unwrap_err().message.contains("API key is required"));
+    }
+
+    #[test]
+    fn test_missing_api_key_together_returns_err() {
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let result = rt.block_on(analyze_with_together(AiRequest {
+            api_key:       "".into(),
+            prompt:        "test".into(),
+            system_prompt: None,
+            image_base64:  None,
+            context_files: None,
+            model:         None,
+            max_tokens:    None,
+            allow_secrets: false,
+            profile:       None,
+        }));
+        assert!(result.unwrap_err().message.contains("API key is required"));
+    }
+
+    fn stream_req(files: usize, fallback: Option<&str>) -> StreamRequest {
+        StreamRequest {
+            provider:       "openai".into(),
+            api_key:        "k".into(),
+            prompt:         "q".into(),
+            system_prompt:  None,
+            image_base64:   None,
+            context_files:  Some((0..files).map(|i| format!("file{}", i)).collect()),
+            model:          Some("gpt-4o".into()),
+            max_tokens:     None,
+            local_url:      None,
+            history:        None,
+            fallback_model: fallback.map(String::from),
+            tools:          None,
+            allow_secrets:  false,
+            profile:        None,
+        }
+    }
+
+    #[test]
+    fn test_is_context_overflow() {
+        assert!(is_context_overflow("openai 400: This model's maximum context length is 128000 tokens"));
+        assert!(is_context_overflow("Claude 400: prompt is too long: 210000 tokens > 200000 maximum"));
+        assert!(!is_context_overflow("openai 401: Incorrect API key provided"));
+    }
+
+    #[test]
+    fn test_shrink_for_retry_keeps_best_files_and_latest_turns() {
+        let mut req = stream_req(5, None);
+        req.history = Some((0..4).map(|i| format!("User: turn{}", i)).collect());
+        let report = shrink_for_retry(&mut req).unwrap();
+        assert_eq!((report.dropped_files, report.remaining_files, report.dropped_turns), (3, 2, 2));
+        assert_eq!(req.context_files.unwrap(), ["file0", "file1"]);
+        assert_eq!(req.history.unwrap(), ["User: turn2", "User: turn3"]);
+        assert!(shrink_for_retry(&mut stream_req(0, None)).is_none());
+    }
+
+    #[test]
+    fn test_history_is_folded_into_prompt() {
+        let mut req = stream_req(0, None);
+        assert_eq!(req.to_ai_request().prompt, "q");
+        req.history = Some(vec!["User: hi".into(), "Assistant: hello".into()]);
+        assert_eq!(
+            req.to_ai_request().prompt,
+            "[Conversation history]\nUser: hi\n\nAssistant: hello\n\n[Current message]\nUser: q"
+        );
+    }
+
+    #[test]
+    fn test_shrink_for_retry_prefers_fallback_model() {
+        let mut req = stream_req(4, Some("gpt-4.1"));
+        let report = shrink_for_retry(&mut req).unwrap();
+        assert_eq!(report.switched_model.as_deref(), Some("gpt-4.1"));
+        assert_eq!(req.context_files.unwrap().len(), 4);
+    }
+
+    #[test]
+    fn test_openai_tool_call_deltas_assemble() {
+        let chunks = [
+            json!({ "choices": [{ "delta": { "tool_calls": [{ "index": 0, "id": "call_1",
+                "function": { "name": "web_search", "arguments": "" } }] } }] }),
+            json!({ "choices": [{ "delta": { "tool_calls": [{ "index": 0, "function": { "arguments": "{\"query\": " } }] } }] }),
+            json!({ "choices": [{ "delta": { "tool_calls": [{ "index": 0, "function": { "arguments": "\"rust\"}" } }] } }] }),
+        ];
+        let mut asm = ToolCallAssembler::default();
+        chunks.iter().flat_map(openai_tool_deltas).for_each(|d| asm.apply(&d));
+        let calls = asm.finish();
+        assert_eq!(calls.len(), 1);
+        assert_eq!(calls[0].id, "call_1");
+        assert_eq!(calls[0].name, "web_search");
+        assert_eq!(calls[0].arguments, json!({ "query": "rust" }));
+    }
+
+    #[test]
+    fn test_anthropic_tool_deltas_skip_text_blocks() {
+        let events = [
+            json!({ "type": "content_block_start", "index": 0, "content_block": { "type": "text", "text": "" } }),
+            json!({ "type": "content_block_delta", "index": 0, "delta": { "type": "text_delta", "text": "Let me check." } }),
+            json!({ "type": "content_block_start", "index": 1,
+                    "content_block": { "type": "tool_use", "id": "toolu_1", "name": "read_file", "input": {} } }),
+            json!({ "type": "content_block_delta", "index": 1, "delta": { "type": "input_json_delta", "partial_json": "{\"path\":" } }),
+            json!({ "type": "content_block_delta", "index": 1, "delta": { "type": "input_json_delta", "partial_json": "\"a.rs\"}" } }),
+        ];
+        let mut asm = ToolCallAssembler::default();
+        events.iter().filter_map(anthropic_tool_delta).for_each(|d| asm.apply(&d));
+        let calls = asm.finish();
+        assert_eq!(calls, vec![ToolCall { id: "toolu_1".into(), name: "read_file".into(), arguments: json!({ "path": "a.rs" }) }]);
+    }
+
+    #[test]
+    fn test_anthropic_tools_conversion() {
+        let tools = [json!({ "type": "function", "function": { "name": "f", "parameters": { "type": "object" } } })];
+        let out = anthropic_tools(&tools);
+        assert_eq!(out[0]["name"], "f");
+        assert_eq!(out[0]["input_schema"]["type"], "object");
+    }
+
+    #[test]
+    fn test_parse_lmstudio_models_loaded_first() {
+        let j = json!({ "data": [
+            { "id": "qwen2.5-7b", "type": "llm", "quantization": "Q4_K_M", "state": "not-loaded", "max_context_length": 32768 },
+            { "id": "llava-1.6", "type": "vlm", "state": "loaded", "max_context_length": 4096, "loaded_context_length": 4096 },
+        ]});
+        let models = parse_lmstudio_models(&j);
+        assert_eq!(models[0].id, "llava-1.6");
+        assert!(models[0].loaded);
+        assert_eq!(models[0].loaded_context_length, Some(4096));
+        assert_eq!(models[1].quantization.as_deref(), Some("Q4_K_M"));
+        assert_eq!(models[1].max_context_length, Some(32768));
+    }
+
+    #[test]
+    fn test_url_root() {
+        assert_eq!(url_root("http://127.0.0.1:5001/v1/chat/completions"), "http://127.0.0.1:5001");
+        assert_eq!(url_root("http://localhost:5000"), "http://localhost:5000");
+    }
+
+    #[test]
+    fn test_kobold_prompt_and_text() {
+        assert!(kobold_prompt(" hi ").ends_with("### Instruction:\nhi\n\n### Response:\n"));
+        let j = json!({ "results": [{ "text": " Hello!\n### Instruction: more" }] });
+        assert_eq!(kobold_text(&j), "Hello!");
+    }
+
+    #[test]
+    fn test_model_caps_registry() {
+        assert_eq!(model_caps("gpt-4o"), DEFAULT_CAPS);
+        assert_eq!(model_caps("o3-mini").system_role, SystemRole::Developer);
+        assert_eq!(model_caps("openai/o1-mini").system_role, SystemRole::MergeIntoUser);
+        assert_eq!(model_caps("o1-2024-12-17").max_tokens_field, "max_completion_tokens");
+        assert_eq!(model_caps("omni-moderation"), DEFAULT_CAPS);
+    }
+
+    #[test]
+    fn test_apply_model_caps_reasoning() {
+        let mut body = json!({
+            "model": "o1-mini",
+            "messages": [
+                { "role": "system", "content": "Be brief" },
+                { "role": "user",   "content": "Hi" }
+            ],
+            "max_tokens": 100,
+            "temperature": 0.2
+        });
+        apply_model_caps(&mut body, "o1-mini");
+        assert_eq!(body["max_completion_tokens"], 100);
+        assert!(body.get("max_tokens").is_none());
+        assert!(body.get("temperature").is_none());
+        assert_eq!(body["messages"].as_array().unwrap().len(), 1);
+        assert_eq!(body["messages"][0]["content"], "Be brief\n\nHi");
+    }
+
+    #[test]
+    fn test_cosine_similarity() {
+        assert!((cosine_similarity(&[1.0, 0.0], &[1.0, 0.0]) - 1.0).abs() < 1e-6);
+        assert_eq!(cosine_similarity(&[1.0, 0.0], &[0.0, 1.0]), 0.0);
+        assert_eq!(cosine_similarity(&[1.0], &[1.0, 2.0]), 0.0);
+    }
+
+    #[test]
+    fn test_claude_content_caches_large_context() {
+        let req = AiRequest {
+            api_key:       "key".into(),
+            prompt:        "Explain".into(),
+            system_prompt: None,
+            image_base64:  None,
+            context_files: Some(vec!["x".repeat(CLAUDE_CACHE_MIN_CHARS)]),
+            model:         None,
+            max_tokens:    None,
+            allow_secrets: false,
+            profile:       None,
+        };
+        let content = claude_content(&req);
+        assert_eq!(content.len(), 2);
+        assert_eq!(content[0]["cache_control"]["type"], "ephemeral");
+        assert_eq!(content[1]["text"], "Explain");
+    }
+
+    #[test]
+    fn test_claude_content_small_context_not_cached() {
+        let req = AiRequest {
+            api_key:       "key".into(),
+            prompt:        "Explain".into(),
+            system_prompt: None,
+            image_base64:  None,
+            context_files: Some(vec!["fn main(){}".into()]),
+            model:         None,
+            max_tokens:    None,
+            allow_secrets: false,
+            profile:       None,
+        };
+        let content = claude_content(&req);
+        assert!(content.iter().all(|c| c.get("cache_control").is_none()));
     }
 }
 
 fn build_prompt(req: &AiRequest) -> String {
     let mut full = req.prompt.clone();
-    if let Some(files) = &req.context_files {
-        if !files.is_empty() {
-            full.push_str("\n\n---\n**PROJECT CONTEXT (read-only)**\n");
-            for chunk in files {
-                full.push_str(chunk);
-                full.push('\n');
-            }
-        }
+    if let Some(ctx) = context_block(req) {
+        full.push_str("\n\n");
+        full.push_str(&ctx);
     }
     full
 }
 
+/// All text a request sends — system prompt, prompt and context — for
+/// moderation.
+fn outbound_text(req: &AiRequest) -> String {
+    match req.system_prompt.as_deref().filter(|s| !s.trim().is_empty()) {
+        Some(system) => format!("{}\n\n{}", system, build_prompt(req)),
+        None         => build_prompt(req),
+    }
+}
+
+/// The formatted PROJECT CONTEXT block, or None when no files are attached.
+fn context_block(req: &AiRequest) -> Option<String> {
+    let files = req.context_files.as_ref().filter(|f| !f.is_empty())?;
+    let mut block = String::from("---\n**PROJECT CONTEXT (read-only)**\n");
+    for chunk in files {
+        block.push_str(chunk);
+        block.push('\n');
+    }
+    Some(block)
+}
+
+/// Build the user-message content array for Anthropic.
+///
+/// The PROJECT CONTEXT goes first as its own block with an ephemeral
+/// `cache_control` breakpoint, so the (unchanged) indexed project is read
+/// from cache on every follow-up turn instead of billed as fresh input.
+/// The screenshot and the actual question follow, after the breakpoint.
+fn claude_content(req: &AiRequest) -> Vec<Value> {
+    let mut content: Vec<Value> = Vec::new();
+    let ctx = context_block(req);
+    let cached = ctx.as_ref().map(|c| c.len() >= CLAUDE_CACHE_MIN_CHARS).unwrap_or(false);
+
+    if cached {
+        content.push(json!({
+            "type": "text",
+            "text": ctx.as_deref().unwrap_or(""),
+            "cache_control": { "type": "ephemeral" }
+        }));
+    }
+    if let Some(b64) = &req.image_base64 {
+        content.push(json!({
+            "type": "image",
+            "source": { "type": "base64", "media_type": image_prep::mime_type(b64), "data": b64 }
+        }));
+    }
+    let text = if cached { req.prompt.clone() } else { build_prompt(req) };
+    content.push(json!({ "type": "text", "text": text }));
+    content
+}
+
+/// (cache_read, cache_write) token counts from an Anthropic `usage` object.
+fn claude_cache_usage(usage: &Value) -> (Option<u32>, Option<u32>) {
+    (
+        usage["cache_read_input_tokens"].as_u64().map(|n| n as u32),
+        usage["cache_creation_input_tokens"].as_u64().map(|n| n as u32),
+    )
+}
+
 /// Extract the text reply from an OpenAI-compatible JSON response.
 /// Falls back to the `reasoning` field (used by CoT / "thinking" models like
 /// DeepSeek-R1, LM Studio with heretic/opus-class models) when `content` is
@@ -201,11 +567,149 @@ fn extract_content(json: &Value) -> String {
     String::new()
 }
 
+// ── Model capability registry ───────────────────────────────────────────
+
+/// Where a model accepts the system prompt.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum SystemRole {
+    /// Regular `{"role": "system"}` message
+    System,
+    /// o1 / o3 / o4: `{"role": "developer"}` replaces the system role
+    Developer,
+    /// o1-mini / o1-preview: no system-level role at all — prepend to the user turn
+    MergeIntoUser,
+}
+
+/// Request-shape quirks of a chat-completions model.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct ModelCaps {
+    /// JSON field carrying the output-token cap
+    max_tokens_field: &'static str,
+    system_role:      SystemRole,
+    /// temperature / top_p / penalties / logprobs are accepted
+    sampling_params:  bool,
+}
+
+const DEFAULT_CAPS: ModelCaps = ModelCaps {
+    max_tokens_field: "max_tokens",
+    system_role:      SystemRole::System,
+    sampling_params:  true,
+};
+
+/// OpenAI reasoning models, matched by prefix of the bare model id.
+/// Most specific prefixes first.
+static REASONING_MODELS: &[(&str, SystemRole)] = &[
+    ("o1-mini",    SystemRole::MergeIntoUser),
+    ("o1-preview", SystemRole::MergeIntoUser),
+    ("o1",         SystemRole::Developer),
+    ("o3",         SystemRole::Developer),
+    ("o4",         SystemRole::Developer),
+];
+
+static SAMPLING_PARAMS: &[&str] = &[
+    "temperature", "top_p", "presence_penalty", "frequency_penalty",
+    "logprobs", "top_logprobs", "logit_bias", "n",
+];
+
+/// Look up request-shape quirks for `model` (gateway prefixes like
+/// "openai/" are ignored, so OpenRouter ids resolve the same way).
+fn model_caps(model: &str) -> ModelCaps {
+    let bare = model.rsplit('/').next().unwrap_or(model).to_ascii_lowercase();
+    REASONING_MODELS.iter()
+        .find(|(prefix, _)| bare == *prefix || bare.starts_with(&format!("{}-", prefix)))
+        .map(|(_, role)| ModelCaps {
+            max_tokens_field: "max_completion_tokens",
+            system_role:      *role,
+            sampling_params:  false,
+        })
+        .unwrap_or(DEFAULT_CAPS)
+}
+
+/// Rewrite an OpenAI-style chat body in place so `model` accepts it:
+/// rename the token cap, move the system prompt, drop unsupported params.
+fn apply_model_caps(body: &mut Value, model: &str) {
+    let caps = model_caps(model);
+    if caps == DEFAULT_CAPS { return; }
+
+    if let Some(obj) = body.as_object_mut() {
+        if caps.max_tokens_field != "max_tokens" {
+            if let Some(v) = obj.remove("max_tokens") {
+                obj.insert(caps.max_tokens_field.to_string(), v);
+            }
+        }
+        if !caps.sampling_params {
+            for p in SAMPLING_PARAMS { obj.remove(*p); }
+        }
+    }
+
+    let Some(messages) = body["messages"].as_array_mut() else { return };
+    match caps.system_role {
+        SystemRole::System => {}
+        SystemRole::Developer => {
+            for m in messages.iter_mut().filter(|m| m["role"] == "system") {
+                m["role"] = json!("developer");
+            }
+        }
+        SystemRole::MergeIntoUser => {
+            let sys: Vec<String> = messages.iter()
+                .filter(|m| m["role"] == "system")
+                .filter_map(|m| m["content"].as_str().map(String::from))
+                .collect();
+            messages.retain(|m| m["role"] != "system");
+            if sys.is_empty() { return; }
+            let prefix = sys.join("\n\n");
+            if let Some(user) = messages.iter_mut().find(|m| m["role"] == "user") {
+                match &mut user["content"] {
+                    Value::String(text) => *text = format!("{}\n\n{}", prefix, text),
+                    Value::Array(parts) => {
+                        if let Some(first) = parts.iter_mut().find(|p| p["type"] == "text") {
+                            let merged = format!("{}\n\n{}", prefix, first["text"].as_str().unwrap_or(""));
+                            first["text"] = json!(merged);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+}
+
 fn http_client() -> reqwest::Result<Client> {
-    Client::builder()
-        .connect_timeout(std::time::Duration::from_secs(10))
-        .timeout(std::time::Duration::from_secs(600)) // 10 min — local LLMs can be slow
-        .build()
+    http::client(http::Timeout::Llm)
+}
+
+#[cfg(test)]
+thread_local! {
+    /// Scheme + host the provider URLs are rewritten to (mock_provider.rs)
+    static API_BASE_OVERRIDE: std::cell::RefCell<Option<String>> = const { std::cell::RefCell::new(None) };
+}
+
+/// Point this thread's provider calls at `base` (None restores the real APIs).
+/// `#[tokio::test]` runs on one thread, so parallel tests don't interfere.
+#[cfg(all(test, feature = "provider-tests"))]
+pub(crate) fn set_api_base(base: Option<String>) {
+    API_BASE_OVERRIDE.with(|o| *o.borrow_mut() = base);
+}
+
+/// A provider endpoint; in tests the host may be swapped for the mock server.
+fn api_url(url: &str) -> String {
+    #[cfg(test)]
+    if let Some(base) = API_BASE_OVERRIDE.with(|o| o.borrow().clone()) {
+        let path = url.splitn(4, '/').nth(3).unwrap_or("");
+        return format!("{}/{}", base.trim_end_matches('/'), path);
+    }
+    url.to_string()
+}
+
+/// Receiver of streaming events: the calling window, or a recorder in tests.
+pub(crate) trait StreamSink: Sync {
+    fn emit<P: Serialize + Clone>(&self, event: &str, payload: P);
+}
+
+impl StreamSink for tauri::Window {
+    fn emit<P: Serialize + Clone>(&self, event: &str, payload: P) {
+        let _ = tauri::Window::emit(self, event, payload);
+    }
 }
 
 // ═══════════════════════════════════════════════════════════════════════
@@ -213,7 +717,10 @@ fn http_client() -> reqwest::Result<Client> {
 // ═══════════════════════════════════════════════════════════════════════
 
 #[tauri::command]
-pub async fn analyze_with_openai(req: AiRequest) -> Result<AiResponse, String> {
+pub async fn analyze_with_openai(mut req: AiRequest) -> Result<AiResponse, AppError> {
+    req.image_base64 = image_prep::prepare(req.image_base64.take()).await;
+    secret_scanner::scrub_request(&mut req.prompt, &mut req.context_files, req.allow_secrets);
+    moderation::enforce(req.profile.as_deref(), &outbound_text(&req), req.image_base64.as_deref()).await?;
     if req.api_key.is_empty() {
         return Err("OpenAI API key is required".into());
     }
@@ -241,7 +748,7 @@ pub async fn analyze_with_openai(req: AiRequest) -> Result<AiResponse, String> {
                 content.push(json!({
                     "type": "image_url",
                     "image_url": {
-                        "url":    format!("data:image/png;base64,{}", b64),
+                        "url":    format!("data:{};base64,{}", image_prep::mime_type(b64), b64),
                         "detail": "high"
                     }
                 }));
@@ -250,22 +757,21 @@ pub async fn analyze_with_openai(req: AiRequest) -> Result<AiResponse, String> {
             messages.push(json!({ "role": "user", "content": content }));
 
             let max_tok = req.max_tokens.unwrap_or(2048);
-            let body = json!({
+            let mut body = json!({
                 "model":      model,
                 "messages":   messages,
                 "max_tokens": max_tok
             });
+            apply_model_caps(&mut body, model);
 
-            let resp = client
-                .post("https://api.openai.com/v1/chat/completions")
+            let request = client
+                .post(api_url("https://api.openai.com/v1/chat/completions"))
                 .bearer_auth(&req.api_key)
-                .json(&body)
-                .send()
+                .json(&body);
+            let (status, text) = audit::send("openai", request)
                 .await
                 .map_err(|e| format!("Network error: {}", e))?;
-
-            let status = resp.status();
-            let json: Value = resp.json().await.map_err(|e| e.to_string())?;
+            let json: Value = serde_json::from_str(&text).map_err(|e| e.to_string())?;
 
             if !status.is_success() {
                 return Err(format!(
@@ -279,8 +785,11 @@ pub async fn analyze_with_openai(req: AiRequest) -> Result<AiResponse, String> {
                 text:        extract_content(&json),
                 model:       json["model"].as_str().unwrap_or(model).to_string(),
                 tokens_used: json["usage"]["total_tokens"].as_u64().map(|n| n as u32),
+                cache_read_tokens:  None,
+                cache_write_tokens: None,
+                verification:       None,
             })
-        } => result,
+        } => result.map_err(|e| AppError::from(e).with_provider("openai")),
         _ = cancel_rx.changed() => Err("__CANCELLED__".into()),
     }
 }
@@ -290,7 +799,10 @@ pub async fn analyze_with_openai(req: AiRequest) -> Result<AiResponse, String> {
 // ═══════════════════════════════════════════════════════════════════════
 
 #[tauri::command]
-pub async fn analyze_with_claude(req: AiRequest) -> Result<AiResponse, String> {
+pub async fn analyze_with_claude(mut req: AiRequest) -> Result<AiResponse, AppError> {
+    req.image_base64 = image_prep::prepare(req.image_base64.take()).await;
+    secret_scanner::scrub_request(&mut req.prompt, &mut req.context_files, req.allow_secrets);
+    moderation::enforce(req.profile.as_deref(), &outbound_text(&req), req.image_base64.as_deref()).await?;
     if req.api_key.is_empty() {
         return Err("Anthropic API key is required".into());
     }
@@ -301,14 +813,7 @@ pub async fn analyze_with_claude(req: AiRequest) -> Result<AiResponse, String> {
             let client = http_client().map_err(|e| e.to_string())?;
             let model  = req.model.as_deref().unwrap_or("claude-3-5-sonnet-20241022");
 
-            let mut content: Vec<Value> = Vec::new();
-            if let Some(b64) = &req.image_base64 {
-                content.push(json!({
-                    "type": "image",
-                    "source": { "type": "base64", "media_type": "image/png", "data": b64 }
-                }));
-            }
-            content.push(json!({ "type": "text", "text": build_prompt(&req) }));
+            let content = claude_content(&req);
 
             // Claude uses a top-level "system" field, not a message role
             let sys = req.system_prompt.as_deref().unwrap_or("").trim();
@@ -322,18 +827,16 @@ pub async fn analyze_with_claude(req: AiRequest) -> Result<AiResponse, String> {
                 body["system"] = json!(sys);
             }
 
-            let resp = client
-                .post("https://api.anthropic.com/v1/messages")
+            let request = client
+                .post(api_url("https://api.anthropic.com/v1/messages"))
                 .header("x-api-key",         &req.api_key)
                 .header("anthropic-version", "2023-06-01")
                 .header("content-type",      "application/json")
-                .json(&body)
-                .send()
+                .json(&body);
+            let (status, text) = audit::send("claude", request)
                 .await
                 .map_err(|e| format!("Network error: {}", e))?;
-
-            let status = resp.status();
-            let json: Value = resp.json().await.map_err(|e| e.to_string())?;
+            let json: Value = serde_json::from_str(&text).map_err(|e| e.to_string())?;
 
             if !status.is_success() {
                 return Err(format!(
@@ -345,13 +848,17 @@ pub async fn analyze_with_claude(req: AiRequest) -> Result<AiResponse, String> {
 
             let in_tok  = json["usage"]["input_tokens"].as_u64().unwrap_or(0);
             let out_tok = json["usage"]["output_tokens"].as_u64().unwrap_or(0);
+            let (cache_read, cache_write) = claude_cache_usage(&json["usage"]);
 
             Ok(AiResponse {
                 text: json["content"][0]["text"].as_str().unwrap_or("").to_string(),
                 model: json["model"].as_str().unwrap_or(model).to_string(),
                 tokens_used: Some((in_tok + out_tok) as u32),
+                cache_read_tokens:  cache_read,
+                cache_write_tokens: cache_write,
+                verification:       None,
             })
-        } => result,
+        } => result.map_err(|e| AppError::from(e).with_provider("claude")),
         _ = cancel_rx.changed() => Err("__CANCELLED__".into()),
     }
 }
@@ -361,7 +868,10 @@ pub async fn analyze_with_claude(req: AiRequest) -> Result<AiResponse, String> {
 // ═══════════════════════════════════════════════════════════════════════
 
 #[tauri::command]
-pub async fn analyze_with_deepseek(req: AiRequest) -> Result<AiResponse, String> {
+pub async fn analyze_with_deepseek(mut req: AiRequest) -> Result<AiResponse, AppError> {
+    req.image_base64 = image_prep::prepare(req.image_base64.take()).await;
+    secret_scanner::scrub_request(&mut req.prompt, &mut req.context_files, req.allow_secrets);
+    moderation::enforce(req.profile.as_deref(), &outbound_text(&req), req.image_base64.as_deref()).await?;
     if req.api_key.is_empty() {
         return Err("DeepSeek API key is required".into());
     }
@@ -390,16 +900,14 @@ pub async fn analyze_with_deepseek(req: AiRequest) -> Result<AiResponse, String>
                 "max_tokens": max_tok
             });
 
-            let resp = client
-                .post("https://api.deepseek.com/v1/chat/completions")
+            let request = client
+                .post(api_url("https://api.deepseek.com/v1/chat/completions"))
                 .bearer_auth(&req.api_key)
-                .json(&body)
-                .send()
+                .json(&body);
+            let (status, text) = audit::send("deepseek", request)
                 .await
                 .map_err(|e| format!("Network error: {}", e))?;
-
-            let status = resp.status();
-            let json: Value = resp.json().await.map_err(|e| e.to_string())?;
+            let json: Value = serde_json::from_str(&text).map_err(|e| e.to_string())?;
 
             if !status.is_success() {
                 return Err(format!(
@@ -413,8 +921,11 @@ pub async fn analyze_with_deepseek(req: AiRequest) -> Result<AiResponse, String>
                 text:        extract_content(&json),
                 model:       json["model"].as_str().unwrap_or(model).to_string(),
                 tokens_used: json["usage"]["total_tokens"].as_u64().map(|n| n as u32),
+                cache_read_tokens:  None,
+                cache_write_tokens: None,
+                verification:       None,
             })
-        } => result,
+        } => result.map_err(|e| AppError::from(e).with_provider("deepseek")),
         _ = cancel_rx.changed() => Err("__CANCELLED__".into()),
     }
 }
@@ -424,7 +935,10 @@ pub async fn analyze_with_deepseek(req: AiRequest) -> Result<AiResponse, String>
 // ═══════════════════════════════════════════════════════════════════════
 
 #[tauri::command]
-pub async fn analyze_with_openrouter(req: AiRequest) -> Result<AiResponse, String> {
+pub async fn analyze_with_openrouter(mut req: AiRequest) -> Result<AiResponse, AppError> {
+    req.image_base64 = image_prep::prepare(req.image_base64.take()).await;
+    secret_scanner::scrub_request(&mut req.prompt, &mut req.context_files, req.allow_secrets);
+    moderation::enforce(req.profile.as_deref(), &outbound_text(&req), req.image_base64.as_deref()).await?;
     if req.api_key.is_empty() {
         return Err("OpenRouter API key is required".into());
     }
@@ -446,7 +960,7 @@ pub async fn analyze_with_openrouter(req: AiRequest) -> Result<AiResponse, Strin
             let user_msg = if let Some(b64) = &req.image_base64 {
                 json!({ "role": "user", "content": [
                     { "type": "text", "text": build_prompt(&req) },
-                    { "type": "image_url", "image_url": { "url": format!("data:image/png;base64,{}", b64) } }
+                    { "type": "image_url", "image_url": { "url": format!("data:{};base64,{}", image_prep::mime_type(b64), b64) } }
                 ]})
             } else {
                 json!({ "role": "user", "content": build_prompt(&req) })
@@ -454,24 +968,23 @@ pub async fn analyze_with_openrouter(req: AiRequest) -> Result<AiResponse, Strin
             messages.push(user_msg);
 
             let max_tok = req.max_tokens.unwrap_or(2048);
-            let body = json!({
+            let mut body = json!({
                 "model":      model,
                 "messages":   messages,
                 "max_tokens": max_tok
             });
+            apply_model_caps(&mut body, model);
 
-            let resp = client
-                .post("https://openrouter.ai/api/v1/chat/completions")
+            let request = client
+                .post(api_url("https://openrouter.ai/api/v1/chat/completions"))
                 .bearer_auth(&req.api_key)
                 .header("HTTP-Referer", "https://github.com/ai-assistant")
                 .header("X-Title",     "AI Assistant Overlay")
-                .json(&body)
-                .send()
+                .json(&body);
+            let (status, text) = audit::send("openrouter", request)
                 .await
                 .map_err(|e| format!("Network error: {}", e))?;
-
-            let status = resp.status();
-            let json: Value = resp.json().await.map_err(|e| e.to_string())?;
+            let json: Value = serde_json::from_str(&text).map_err(|e| e.to_string())?;
 
             if !status.is_success() {
                 return Err(format!(
@@ -485,8 +998,87 @@ pub async fn analyze_with_openrouter(req: AiRequest) -> Result<AiResponse, Strin
                 text:        extract_content(&json),
                 model:       json["model"].as_str().unwrap_or(model).to_string(),
                 tokens_used: json["usage"]["total_tokens"].as_u64().map(|n| n as u32),
+                cache_read_tokens:  None,
+                cache_write_tokens: None,
+                verification:       None,
+            })
+        } => result.map_err(|e| AppError::from(e).with_provider("openrouter")),
+        _ = cancel_rx.changed() => Err("__CANCELLED__".into()),
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════
+// Together AI (hosted Llama / Qwen / Mixtral, OpenAI-compatible)
+// ═══════════════════════════════════════════════════════════════════════
+
+const TOGETHER_DEFAULT_MODEL: &str = "meta-llama/Llama-3.3-70B-Instruct-Turbo";
+
+#[tauri::command]
+pub async fn analyze_with_together(mut req: AiRequest) -> Result<AiResponse, AppError> {
+    req.image_base64 = image_prep::prepare(req.image_base64.take()).await;
+    secret_scanner::scrub_request(&mut req.prompt, &mut req.context_files, req.allow_secrets);
+    moderation::enforce(req.profile.as_deref(), &outbound_text(&req), req.image_base64.as_deref()).await?;
+    if req.api_key.is_empty() {
+        return Err("Together AI API key is required".into());
+    }
+
+    let mut cancel_rx = new_cancel_receiver();
+    tokio::select! {
+        result = async {
+            let client = http_client().map_err(|e| e.to_string())?;
+            let model  = req.model.as_deref().unwrap_or(TOGETHER_DEFAULT_MODEL);
+
+            let mut messages: Vec<Value> = Vec::new();
+            if let Some(sys) = &req.system_prompt {
+                if !sys.trim().is_empty() {
+                    messages.push(json!({ "role": "system", "content": sys }));
+                }
+            }
+
+            // Text-only models reject content arrays, so only send one with an image
+            let user_msg = if let Some(b64) = &req.image_base64 {
+                json!({ "role": "user", "content": [
+                    { "type": "text", "text": build_prompt(&req) },
+                    { "type": "image_url", "image_url": { "url": format!("data:{};base64,{}", image_prep::mime_type(b64), b64) } }
+                ]})
+            } else {
+                json!({ "role": "user", "content": build_prompt(&req) })
+            };
+            messages.push(user_msg);
+
+            let max_tok = req.max_tokens.unwrap_or(2048);
+            let body = json!({
+                "model":      model,
+                "messages":   messages,
+                "max_tokens": max_tok
+            });
+
+            let request = client
+                .post(api_url("https://api.together.xyz/v1/chat/completions"))
+                .bearer_auth(&req.api_key)
+                .json(&body);
+            let (status, text) = audit::send("together", request)
+                .await
+                .map_err(|e| format!("Network error: {}", e))?;
+            let json: Value = serde_json::from_str(&text).map_err(|e| e.to_string())?;
+
+            if !status.is_success() {
+                return Err(format!(
+                    "Together AI {}: {}",
+                    status,
+                    json["error"]["message"].as_str().unwrap_or("unknown error")
+                ));
+            }
+
+            Ok(AiResponse {
+                text:        extract_content(&json),
+                model:       json["model"].as_str().unwrap_or(model).to_string(),
+                tokens_used: json["usage"]["total_tokens"].as_u64().map(|n| n as u32),
+                cache_read_tokens:  None,
+                cache_write_tokens: None,
+                verification:       None,
             })
-        } => result,
+        } => result.map_err(|e| AppError::from(e).with_provider("together")),
         _ = cancel_rx.changed() => Err("__CANCELLED__".into()),
     }
 }
@@ -496,7 +1088,8 @@ pub async fn analyze_with_openrouter(req: AiRequest) -> Result<AiResponse, Strin
 // ═══════════════════════════════════════════════════════════════════════
 
 #[tauri::command]
-pub async fn analyze_with_local(req: LocalAiRequest) -> Result<AiResponse, String> {
+pub async fn analyze_with_local(mut req: LocalAiRequest) -> Result<AiResponse, AppError> {
+    req.image_base64 = image_prep::prepare(req.image_base64.take()).await;
     let base = req.base_url.trim().trim_end_matches('/');
     if base.is_empty() {
         return Err(
@@ -511,7 +1104,15 @@ pub async fn analyze_with_local(req: LocalAiRequest) -> Result<AiResponse, Strin
         format!("{}/v1/chat/completions", base)
     };
 
-    log::info!("local LLM → {}", url);
+    let root = url_root(base);
+    let kind = match req.server_type.as_deref() {
+        Some("openai")    => LocalServerKind::OpenAi,
+        Some("koboldcpp") => LocalServerKind::KoboldCpp,
+        Some("textgen")   => LocalServerKind::TextGen,
+        _                 => detect_server_kind(&root).await,
+    };
+
+    log::info!("local LLM ({:?}) → {}", kind, url);
 
     let mut cancel_rx = new_cancel_receiver();
     tokio::select! {
@@ -527,6 +1128,8 @@ pub async fn analyze_with_local(req: LocalAiRequest) -> Result<AiResponse, Strin
                 context_files: req.context_files.clone(),
                 model:         req.model.clone(),
                 max_tokens:    req.max_tokens,
+                allow_secrets: false,
+                profile:       None,
             };
 
             // Many local models (e.g. LM Studio with Jinja templates) only
@@ -544,6 +1147,14 @@ pub async fn analyze_with_local(req: LocalAiRequest) -> Result<AiResponse, Strin
                 base_prompt
             };
 
+            let max_tok = req.max_tokens.unwrap_or(4096);
+
+            // KoboldCpp's and legacy text-generation-webui's OpenAI shims drop
+            // images and sampler settings — use their native generate API.
+            if matches!(kind, LocalServerKind::KoboldCpp | LocalServerKind::TextGenLegacy) {
+                return kobold_generate(&root, kind, &user_text, req.image_base64.as_deref(), max_tok, model).await;
+            }
+
             let mut messages: Vec<Value> = Vec::new();
 
             // Use multimodal array only when an image is supplied; otherwise
@@ -552,15 +1163,14 @@ pub async fn analyze_with_local(req: LocalAiRequest) -> Result<AiResponse, Strin
             let user_msg = if let Some(b64) = &req.image_base64 {
                 json!({ "role": "user", "content": [
                     { "type": "text", "text": user_text },
-                    { "type": "image_url", "image_url": { "url": format!("data:image/png;base64,{}", b64) } }
+                    { "type": "image_url", "image_url": { "url": format!("data:{};base64,{}", image_prep::mime_type(b64), b64) } }
                 ]})
             } else {
                 json!({ "role": "user", "content": user_text })
             };
             messages.push(user_msg);
 
-            let max_tok = req.max_tokens.unwrap_or(4096);
-            let body = json!({
+            let mut body = json!({
                 "model":      model,
                 "messages":   messages,
                 "max_tokens": max_tok
@@ -568,6 +1178,11 @@ pub async fn analyze_with_local(req: LocalAiRequest) -> Result<AiResponse, Strin
                 // return 400 when stream:false is present in the body.
                 // Omitting it defaults to non-streaming on all compatible servers.
             });
+            // text-generation-webui defaults to "chat" mode, which wraps the
+            // prompt in its character persona instead of the model's template
+            if kind == LocalServerKind::TextGen {
+                body["mode"] = json!("instruct");
+            }
 
             let mut builder = client.post(&url).json(&body);
             if let Some(key) = &req.api_key {
@@ -617,17 +1232,280 @@ pub async fn analyze_with_local(req: LocalAiRequest) -> Result<AiResponse, Strin
                 text:        extract_content(&json),
                 model:       json["model"].as_str().unwrap_or(model).to_string(),
                 tokens_used: json["usage"]["total_tokens"].as_u64().map(|n| n as u32),
+                cache_read_tokens:  None,
+                cache_write_tokens: None,
+                verification:       None,
             })
-        } => result,
+        } => result.map_err(|e| AppError::from(e).with_provider("local")),
         _ = cancel_rx.changed() => Err("__CANCELLED__".into()),
     }
 }
+// ── Local server detection + KoboldAI-style generate API ────────────────
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum LocalServerKind {
+    /// LM Studio, Ollama, llama.cpp server, vLLM, …
+    OpenAi,
+    KoboldCpp,
+    /// text-generation-webui with the OpenAI-compatible API (2023-11+)
+    TextGen,
+    /// text-generation-webui with the old blocking API (`/api/v1/generate`)
+    TextGenLegacy,
+}
+
+/// Probe results per server root, so only the first request pays for them.
+static SERVER_KINDS: std::sync::Mutex<Vec<(String, LocalServerKind)>> = std::sync::Mutex::new(Vec::new());
+
+/// "http://host:port/v1/chat/completions" → "http://host:port"
+fn url_root(url: &str) -> String {
+    match url.split_once("://") {
+        Some((scheme, rest)) => format!("{}://{}", scheme, rest.split('/').next().unwrap_or(rest)),
+        None                 => url.split('/').next().unwrap_or(url).to_string(),
+    }
+}
+
+async fn detect_server_kind(root: &str) -> LocalServerKind {
+    if let Some((_, kind)) = SERVER_KINDS.lock().ok()
+        .and_then(|k| k.iter().find(|(r, _)| r == root).cloned())
+    {
+        return kind;
+    }
+
+    let Ok(client) = http_client() else { return LocalServerKind::OpenAi };
+    let probe = |path: &str| {
+        client.get(format!("{}{}", root, path))
+            .timeout(std::time::Duration::from_secs(2))
+            .send()
+    };
+    let json_ok = |resp: reqwest::Result<reqwest::Response>| async move {
+        match resp {
+            Ok(r) if r.status().is_success() => r.json::<Value>().await.ok(),
+            _ => None,
+        }
+    };
+
+    let kind = if json_ok(probe("/api/extra/version").await).await
+        .is_some_and(|j| j["result"].as_str().unwrap_or("").eq_ignore_ascii_case("koboldcpp"))
+    {
+        LocalServerKind::KoboldCpp
+    } else if json_ok(probe("/v1/internal/model/info").await).await
+        .is_some_and(|j| j.get("model_name").is_some())
+    {
+        LocalServerKind::TextGen
+    } else if json_ok(probe("/api/v1/model").await).await
+        .is_some_and(|j| j["result"].is_string())
+    {
+        LocalServerKind::TextGenLegacy
+    } else {
+        LocalServerKind::OpenAi
+    };
+
+    if let Ok(mut kinds) = SERVER_KINDS.lock() {
+        kinds.push((root.to_string(), kind));
+    }
+    kind
+}
+
+/// Report what kind of server answers at `base_url`, for the settings UI.
+#[tauri::command]
+pub async fn detect_local_server(base_url: String) -> Result<String, AppError> {
+    let root = url_root(base_url.trim().trim_end_matches('/'));
+    if let Ok(mut kinds) = SERVER_KINDS.lock() {
+        kinds.retain(|(r, _)| r != &root);
+    }
+    Ok(match detect_server_kind(&root).await {
+        LocalServerKind::OpenAi        => "openai",
+        LocalServerKind::KoboldCpp     => "koboldcpp",
+        LocalServerKind::TextGen       => "textgen",
+        LocalServerKind::TextGenLegacy => "textgen_legacy",
+    }.to_string())
+}
+
+/// Raw-completion servers need the chat turn spelled out; Alpaca-style
+/// markers are understood by most instruct fine-tunes.
+fn kobold_prompt(user_text: &str) -> String {
+    format!("### Instruction:\n{}\n\n### Response:\n", user_text.trim())
+}
+
+async fn kobold_generate(
+    root:      &str,
+    kind:      LocalServerKind,
+    user_text: &str,
+    image:     Option<&str>,
+    max_tok:   u32,
+    model:     &str,
+) -> Result<AiResponse, String> {
+    let client = http_client().map_err(|e| e.to_string())?;
+    let mut body = json!({
+        "prompt":        kobold_prompt(user_text),
+        "stop_sequence": ["### Instruction:"],
+        "temperature":   0.7,
+    });
+    if kind == LocalServerKind::KoboldCpp {
+        body["max_length"] = json!(max_tok);
+        if let Some(b64) = image {
+            // Needs a multimodal projector loaded (--mmproj)
+            body["images"] = json!([b64]);
+        }
+    } else {
+        body["max_new_tokens"]  = json!(max_tok);
+        body["stopping_strings"] = json!(["### Instruction:"]);
+    }
+
+    let resp = client.post(format!("{}/api/v1/generate", root))
+        .json(&body)
+        .send().await
+        .map_err(|e| format!("Local LLM недоступна: {}\n\nURL: {}/api/v1/generate", e, root))?;
+    let status = resp.status();
+    let json: Value = resp.json().await.map_err(|e| e.to_string())?;
+    if !status.is_success() {
+        let detail = json["detail"]["msg"].as_str()
+            .or_else(|| json["detail"].as_str())
+            .or_else(|| json["error"]["message"].as_str())
+            .unwrap_or("unknown error");
+        return Err(format!("Local LLM {}: {}", status, detail));
+    }
+
+    Ok(AiResponse {
+        text:        kobold_text(&json),
+        model:       model.to_string(),
+        tokens_used: None,
+        cache_read_tokens:  None,
+        cache_write_tokens: None,
+        verification:       None,
+    })
+}
+
+/// `{ "results": [ { "text": "…" } ] }` — trailing stop marker trimmed.
+fn kobold_text(json: &Value) -> String {
+    let text = json["results"][0]["text"].as_str().unwrap_or("");
+    text.split("### Instruction:").next().unwrap_or(text).trim().to_string()
+}
+
+// ═══════════════════════════════════════════════════════════════════════
+// One-shot completion routed by provider id
+// Used by Rust-side features (summaries, titles, …) that need a plain answer.
+// ═══════════════════════════════════════════════════════════════════════
+
+/// Run a non-streaming completion on `provider`
+/// ("openai" | "claude" | "deepseek" | "openrouter" | "together" | "local").
+/// `local_url` is only used by the local provider.
+pub(crate) async fn complete(
+    provider:  &str,
+    req:       AiRequest,
+    local_url: Option<&str>,
+) -> Result<AiResponse, AppError> {
+    match provider {
+        "openai"     => analyze_with_openai(req).await,
+        "claude"     => analyze_with_claude(req).await,
+        "deepseek"   => analyze_with_deepseek(req).await,
+        "openrouter" => analyze_with_openrouter(req).await,
+        "together"   => analyze_with_together(req).await,
+        "local"      => analyze_with_local(LocalAiRequest {
+            base_url:      local_url.unwrap_or("http://127.0.0.1:1234").to_string(),
+            api_key:       Some(req.api_key).filter(|k| !k.is_empty()),
+            prompt:        req.prompt,
+            system_prompt: req.system_prompt,
+            image_base64:  req.image_base64,
+            context_files: req.context_files,
+            model:         req.model,
+            max_tokens:    req.max_tokens,
+            server_type:   None,
+        }).await,
+        other => Err(format!("Unknown provider: {}", other).into()),
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════
+// Text embeddings (OpenAI or any OpenAI-compatible /v1/embeddings server —
+// LM Studio and Ollama both expose one)
+// ═══════════════════════════════════════════════════════════════════════
+
+/// Embed `texts` with `provider` ("openai" | "local"), preserving order.
+/// Secrets are redacted before texts go to a cloud provider.
+pub(crate) async fn embed(
+    provider: &str,
+    api_key:  Option<&str>,
+    model:    Option<&str>,
+    base_url: Option<&str>,
+    texts:    &[String],
+) -> Result<Vec<Vec<f32>>, String> {
+    if texts.is_empty() { return Ok(Vec::new()); }
+
+    let (url, default_model) = match provider {
+        "openai" => {
+            if api_key.unwrap_or("").is_empty() { return Err("OpenAI API key required for embeddings".into()); }
+            (api_url("https://api.openai.com/v1/embeddings"), "text-embedding-3-small")
+        }
+        "local" => {
+            let base = base_url.unwrap_or("http://127.0.0.1:11434").trim_end_matches('/');
+            (format!("{}/v1/embeddings", base), "nomic-embed-text")
+        }
+        other => return Err(format!("Unknown embedding provider: {}", other)),
+    };
+    let mut scrubbed;
+    let texts = if provider == "local" {
+        texts
+    } else {
+        scrubbed = texts.to_vec();
+        secret_scanner::scrub_texts(&mut scrubbed, "embedding input");
+        &scrubbed[..]
+    };
+
+    let client = http_client().map_err(|e| e.to_string())?;
+    let mut builder = client.post(&url).json(&json!({
+        "model": model.unwrap_or(default_model),
+        "input": texts,
+    }));
+    if let Some(key) = api_key.filter(|k| !k.is_empty()) {
+        builder = builder.bearer_auth(key);
+    }
+
+    let (status, text) = audit::send(provider, builder).await
+        .map_err(|e| format!("Embedding request failed: {}", e))?;
+    let json: Value = serde_json::from_str(&text).map_err(|e| e.to_string())?;
+    if !status.is_success() {
+        return Err(format!("Embeddings {}: {}", status,
+            json["error"]["message"].as_str().unwrap_or("unknown error")));
+    }
+
+    let mut data: Vec<(usize, Vec<f32>)> = json["data"].as_array().unwrap_or(&vec![])
+        .iter()
+        .enumerate()
+        .map(|(i, d)| {
+            let idx = d["index"].as_u64().map(|n| n as usize).unwrap_or(i);
+            let v = d["embedding"].as_array().unwrap_or(&vec![])
+                .iter().filter_map(|x| x.as_f64().map(|f| f as f32)).collect();
+            (idx, v)
+        })
+        .collect();
+    data.sort_by_key(|(i, _)| *i);
+
+    if data.len() != texts.len() {
+        return Err(format!("Embeddings: expected {} vectors, got {}", texts.len(), data.len()));
+    }
+    Ok(data.into_iter().map(|(_, v)| v).collect())
+}
+
+/// Cosine similarity; 0.0 for mismatched or zero-length vectors.
+pub(crate) fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    if a.len() != b.len() || a.is_empty() { return 0.0; }
+    let (mut dot, mut na, mut nb) = (0.0f32, 0.0f32, 0.0f32);
+    for (x, y) in a.iter().zip(b) {
+        dot += x * y;
+        na  += x * x;
+        nb  += y * y;
+    }
+    if na == 0.0 || nb == 0.0 { 0.0 } else { dot / (na.sqrt() * nb.sqrt()) }
+}
+
 // ═══════════════════════════════════════════════════════════════════════
 // Universal SSE streaming
-// Emits: "ai-stream-token" (delta string) and "ai-stream-done" ({text, model})
+// Emits: "ai-stream-token" (delta string), "ai-stream-toolcall" (ToolCallDelta)
+//        and "ai-stream-done" ({text, model, tool_calls})
 // ═══════════════════════════════════════════════════════════════════════
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct StreamRequest {
     pub provider:      String,
     pub api_key:       String,
@@ -638,13 +1516,176 @@ pub struct StreamRequest {
     pub model:         Option<String>,
     pub max_tokens:    Option<u32>,
     pub local_url:     Option<String>,
+    /// Earlier turns ("User: …" / "Assistant: …"), oldest first; folded
+    /// into the prompt, and trimmed when it overflows
+    #[serde(default)]
+    pub history: Option<Vec<String>>,
+    /// Long-context model to retry with when the prompt overflows `model`
+    #[serde(default)]
+    pub fallback_model: Option<String>,
+    /// OpenAI-style tool definitions:
+    /// `[{ "type": "function", "function": { name, description, parameters } }]`
+    #[serde(default)]
+    pub tools: Option<Vec<Value>>,
+    #[serde(default)]
+    pub allow_secrets: bool,
+    /// Moderation profile the request is checked under (None = the active one)
+    #[serde(default)]
+    pub profile:       Option<String>,
+}
+
+impl StreamRequest {
+    /// The one-shot request this stream sends, with `history` ahead of the
+    /// prompt.
+    fn to_ai_request(&self) -> AiRequest {
+        let prompt = match self.history.as_deref() {
+            Some(turns) if !turns.is_empty() => format!(
+                "[Conversation history]\n{}\n\n[Current message]\nUser: {}",
+                turns.join("\n\n"), self.prompt
+            ),
+            _ => self.prompt.clone(),
+        };
+        AiRequest {
+            api_key: self.api_key.clone(), prompt,
+            system_prompt: self.system_prompt.clone(), image_base64: self.image_base64.clone(),
+            context_files: self.context_files.clone(), model: self.model.clone(), max_tokens: self.max_tokens,
+            allow_secrets: self.allow_secrets,
+            profile:       self.profile.clone(),
+        }
+    }
+}
+
+// ── Streamed tool calls ──────────────────────────────────────────────────
+// Tool-call arguments arrive as JSON fragments spread over many SSE events
+// (OpenAI `delta.tool_calls[]`, Anthropic `input_json_delta`). Each fragment
+// is forwarded as "ai-stream-toolcall"; the assembled calls are attached to
+// "ai-stream-done" as `tool_calls`.
+
+/// Payload of the "ai-stream-toolcall" event.
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct ToolCallDelta {
+    pub index:           usize,
+    /// Present on the first fragment of a call
+    pub id:              Option<String>,
+    pub name:            Option<String>,
+    pub arguments_delta: String,
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct ToolCall {
+    pub id:        String,
+    pub name:      String,
+    /// Parsed arguments; the raw string when the model emitted invalid JSON
+    pub arguments: Value,
+}
+
+#[derive(Default)]
+struct ToolCallAssembler {
+    /// (id, name, accumulated arguments) by call index
+    calls: Vec<(String, String, String)>,
+}
+
+impl ToolCallAssembler {
+    fn apply(&mut self, d: &ToolCallDelta) {
+        if self.calls.len() <= d.index {
+            self.calls.resize(d.index + 1, Default::default());
+        }
+        let call = &mut self.calls[d.index];
+        if let Some(id) = &d.id { call.0 = id.clone(); }
+        if let Some(name) = &d.name { call.1.push_str(name); }
+        call.2.push_str(&d.arguments_delta);
+    }
+
+    fn finish(self) -> Vec<ToolCall> {
+        self.calls.into_iter()
+            .filter(|(_, name, _)| !name.is_empty())
+            .map(|(id, name, args)| {
+                let arguments = if args.trim().is_empty() {
+                    json!({})
+                } else {
+                    serde_json::from_str(&args).unwrap_or(Value::String(args))
+                };
+                ToolCall { id, name, arguments }
+            })
+            .collect()
+    }
+}
+
+/// Tool-call fragments in one OpenAI chunk (`choices[0].delta.tool_calls`).
+fn openai_tool_deltas(chunk: &Value) -> Vec<ToolCallDelta> {
+    chunk["choices"][0]["delta"]["tool_calls"].as_array().unwrap_or(&vec![])
+        .iter()
+        .map(|tc| ToolCallDelta {
+            index:           tc["index"].as_u64().unwrap_or(0) as usize,
+            id:              tc["id"].as_str().map(String::from),
+            name:            tc["function"]["name"].as_str().map(String::from),
+            arguments_delta: tc["function"]["arguments"].as_str().unwrap_or("").to_string(),
+        })
+        .collect()
+}
+
+/// Tool-call fragment in one Anthropic event, if any. Content-block indices
+/// count text blocks too, which is harmless — the assembler skips gaps.
+fn anthropic_tool_delta(event: &Value) -> Option<ToolCallDelta> {
+    let index = event["index"].as_u64()? as usize;
+    match event["type"].as_str()? {
+        "content_block_start" if event["content_block"]["type"] == "tool_use" => Some(ToolCallDelta {
+            index,
+            id:              event["content_block"]["id"].as_str().map(String::from),
+            name:            event["content_block"]["name"].as_str().map(String::from),
+            arguments_delta: String::new(),
+        }),
+        "content_block_delta" if event["delta"]["type"] == "input_json_delta" => Some(ToolCallDelta {
+            index,
+            id:              None,
+            name:            None,
+            arguments_delta: event["delta"]["partial_json"].as_str().unwrap_or("").to_string(),
+        }),
+        _ => None,
+    }
+}
+
+/// OpenAI tool definitions → Anthropic `{ name, description, input_schema }`.
+fn anthropic_tools(tools: &[Value]) -> Vec<Value> {
+    tools.iter()
+        .map(|t| {
+            let f = if t["function"].is_object() { &t["function"] } else { t };
+            json!({
+                "name":         f["name"],
+                "description":  f["description"].as_str().unwrap_or(""),
+                "input_schema": if f["parameters"].is_object() { f["parameters"].clone() }
+                                else { json!({ "type": "object", "properties": {} }) },
+            })
+        })
+        .collect()
+}
+
+/// Payload of the "ai-context-trimmed" event.
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct ContextTrimReport {
+    /// Context files removed from the end of `context_files`
+    pub dropped_files:   usize,
+    pub remaining_files: usize,
+    /// Oldest history turns removed
+    pub dropped_turns:   usize,
+    /// Set when the retry switched to `fallback_model` instead of trimming
+    pub switched_model:  Option<String>,
 }
 
 #[tauri::command]
-pub async fn analyze_stream(window: tauri::Window, req: StreamRequest) -> Result<(), String> {
+pub async fn analyze_stream(window: tauri::Window, mut req: StreamRequest) -> Result<(), AppError> {
+    req.image_base64 = image_prep::prepare(req.image_base64.take()).await;
+    if req.provider != "local" {
+        secret_scanner::scrub_request(&mut req.prompt, &mut req.context_files, req.allow_secrets);
+        if !req.allow_secrets {
+            secret_scanner::scrub_texts(req.history.get_or_insert_with(Vec::new), "history");
+        }
+        moderation::enforce(req.profile.as_deref(), &outbound_text(&req.to_ai_request()), req.image_base64.as_deref()).await?;
+    }
+    let provider = req.provider.clone();
     let mut cancel_rx = new_cancel_receiver();
     tokio::select! {
-        result = stream_inner(window.clone(), req) => result,
+        result = stream_with_recovery(&window, req) => result.map_err(|e| AppError::from(e).with_provider(provider)),
         _ = cancel_rx.changed() => {
             let _ = window.emit("ai-stream-done", serde_json::json!({ "cancelled": true }));
             Err("__CANCELLED__".into())
@@ -652,28 +1693,90 @@ pub async fn analyze_stream(window: tauri::Window, req: StreamRequest) -> Result
     }
 }
 
-async fn stream_inner(window: tauri::Window, req: StreamRequest) -> Result<(), String> {
+/// Retry once when the provider rejects the prompt as too long: switch to
+/// the configured long-context model, or drop half of the context files and
+/// history turns. Emits "ai-context-trimmed" describing what changed.
+async fn stream_with_recovery<S: StreamSink>(sink: &S, req: StreamRequest) -> Result<(), String> {
+    let mut retry = req.clone();
+    match stream_inner(sink, req).await {
+        Err(e) if is_context_overflow(&e) => {
+            let Some(report) = shrink_for_retry(&mut retry) else { return Err(e) };
+            log::warn!(
+                "analyze_stream: context overflow, retrying (dropped {} files, {} turns, model {:?})",
+                report.dropped_files, report.dropped_turns, report.switched_model
+            );
+            sink.emit("ai-context-trimmed", &report);
+            stream_inner(sink, retry).await
+        }
+        other => other,
+    }
+}
+
+/// Provider error texts for "prompt exceeds the context window".
+fn is_context_overflow(err: &str) -> bool {
+    const MARKERS: &[&str] = &[
+        "context_length_exceeded", "maximum context length", "context length",
+        "context window", "prompt is too long", "too many tokens",
+        "reduce the length", "n_ctx", "exceeds the available context",
+    ];
+    let lower = err.to_lowercase();
+    MARKERS.iter().any(|m| lower.contains(m))
+}
+
+/// Make `req` smaller for a retry. Context files come best first
+/// (semantic_search ranking, or indexer order), so the back half goes;
+/// history is oldest first, so its front half goes. Returns None when there
+/// is nothing left to cut.
+fn shrink_for_retry(req: &mut StreamRequest) -> Option<ContextTrimReport> {
+    let fallback = req.fallback_model.take()
+        .filter(|m| !m.trim().is_empty() && req.model.as_deref() != Some(m.as_str()));
+    let remaining = req.context_files.as_ref().map(Vec::len).unwrap_or(0);
+
+    if let Some(model) = fallback {
+        req.model = Some(model.clone());
+        return Some(ContextTrimReport {
+            dropped_files: 0, remaining_files: remaining, dropped_turns: 0, switched_model: Some(model),
+        });
+    }
+
+    let files = req.context_files.get_or_insert_with(Vec::new);
+    let dropped_files = files.len().div_ceil(2);
+    files.truncate(files.len() - dropped_files);
+    let remaining_files = files.len();
+    let turns = req.history.get_or_insert_with(Vec::new);
+    let dropped_turns = turns.len().div_ceil(2);
+    turns.drain(..dropped_turns);
+
+    (dropped_files + dropped_turns > 0)
+        .then_some(ContextTrimReport { dropped_files, remaining_files, dropped_turns, switched_model: None })
+}
+
+async fn stream_inner<S: StreamSink>(sink: &S, req: StreamRequest) -> Result<(), String> {
     match req.provider.as_str() {
-        "claude" => stream_claude(window, req).await,
-        _        => stream_openai_compat(window, req).await,
+        "claude" => stream_claude(sink, req).await,
+        _        => stream_openai_compat(sink, req).await,
     }
 }
 
-async fn stream_openai_compat(window: tauri::Window, req: StreamRequest) -> Result<(), String> {
+async fn stream_openai_compat<S: StreamSink>(sink: &S, req: StreamRequest) -> Result<(), String> {
     let client = http_client().map_err(|e| e.to_string())?;
 
     let (url, bearer) = match req.provider.as_str() {
         "openai"     => {
             if req.api_key.is_empty() { return Err("OpenAI API key required".into()); }
-            ("https://api.openai.com/v1/chat/completions".to_string(), req.api_key.clone())
+            (api_url("https://api.openai.com/v1/chat/completions"), req.api_key.clone())
         }
         "deepseek"   => {
             if req.api_key.is_empty() { return Err("DeepSeek API key required".into()); }
-            ("https://api.deepseek.com/v1/chat/completions".to_string(), req.api_key.clone())
+            (api_url("https://api.deepseek.com/v1/chat/completions"), req.api_key.clone())
         }
         "openrouter" => {
             if req.api_key.is_empty() { return Err("OpenRouter API key required".into()); }
-            ("https://openrouter.ai/api/v1/chat/completions".to_string(), req.api_key.clone())
+            (api_url("https://openrouter.ai/api/v1/chat/completions"), req.api_key.clone())
+        }
+        "together"   => {
+            if req.api_key.is_empty() { return Err("Together AI API key required".into()); }
+            (api_url("https://api.together.xyz/v1/chat/completions"), req.api_key.clone())
         }
         "local" => {
             let base = req.local_url.as_deref().unwrap_or("http://127.0.0.1:1234").trim_end_matches('/');
@@ -687,15 +1790,12 @@ async fn stream_openai_compat(window: tauri::Window, req: StreamRequest) -> Resu
     let model = req.model.as_deref().unwrap_or(match req.provider.as_str() {
         "deepseek"   => "deepseek-chat",
         "openrouter" => "openai/gpt-4o",
+        "together"   => TOGETHER_DEFAULT_MODEL,
         "local"      => "local-model",
         _            => "gpt-4o",
     }).to_string();
 
-    let ai_req = AiRequest {
-        api_key: req.api_key.clone(), prompt: req.prompt.clone(),
-        system_prompt: req.system_prompt.clone(), image_base64: req.image_base64.clone(),
-        context_files: req.context_files.clone(), model: req.model.clone(), max_tokens: req.max_tokens,
-    };
+    let ai_req = req.to_ai_request();
     let prompt_text = build_prompt(&ai_req);
 
     let mut messages: Vec<Value> = Vec::new();
@@ -720,7 +1820,7 @@ async fn stream_openai_compat(window: tauri::Window, req: StreamRequest) -> Resu
     let user_msg = if let Some(b64) = &req.image_base64 {
         json!({ "role": "user", "content": [
             { "type": "text",      "text": full_user_text },
-            { "type": "image_url", "image_url": { "url": format!("data:image/png;base64,{}", b64) } }
+            { "type": "image_url", "image_url": { "url": format!("data:{};base64,{}", image_prep::mime_type(b64), b64) } }
         ]})
     } else {
         json!({ "role": "user", "content": full_user_text })
@@ -728,10 +1828,16 @@ async fn stream_openai_compat(window: tauri::Window, req: StreamRequest) -> Resu
     messages.push(user_msg);
 
     let max_tok = req.max_tokens.unwrap_or(4096);
-    let body = json!({
+    let mut body = json!({
         "model": model, "messages": messages,
         "max_tokens": max_tok, "stream": true
     });
+    if req.provider != "local" {
+        apply_model_caps(&mut body, &model);
+    }
+    if let Some(tools) = req.tools.as_ref().filter(|t| !t.is_empty()) {
+        body["tools"] = json!(tools);
+    }
 
     let mut builder = client.post(&url).json(&body);
     if !bearer.is_empty() { builder = builder.bearer_auth(&bearer); }
@@ -741,10 +1847,13 @@ async fn stream_openai_compat(window: tauri::Window, req: StreamRequest) -> Resu
             .header("X-Title", "AI Assistant Overlay");
     }
 
-    let resp = builder.send().await.map_err(|e| format!("Stream failed: {}", e))?;
+    let (resp, mut exchange) = audit::send_stream(&req.provider, builder).await
+        .map_err(|e| format!("Stream failed: {}", e))?;
     let status = resp.status();
     if !status.is_success() {
-        let err_json: Value = resp.json().await.unwrap_or(json!({}));
+        let err_text = resp.text().await.unwrap_or_default();
+        exchange.push(&err_text);
+        let err_json: Value = serde_json::from_str(&err_text).unwrap_or(json!({}));
         return Err(format!("{} {}: {}", req.provider, status,
             err_json["error"]["message"].as_str().unwrap_or("unknown")));
     }
@@ -752,10 +1861,13 @@ async fn stream_openai_compat(window: tauri::Window, req: StreamRequest) -> Resu
     let mut stream = resp.bytes_stream();
     let mut buf = String::new();
     let mut full_text = String::new();
+    let mut tool_calls = ToolCallAssembler::default();
 
     while let Some(chunk) = stream.next().await {
         let chunk = chunk.map_err(|e| format!("Stream read: {}", e))?;
-        buf.push_str(&String::from_utf8_lossy(&chunk));
+        let text = String::from_utf8_lossy(&chunk);
+        exchange.push(&text);
+        buf.push_str(&text);
         while let Some(pos) = buf.find('\n') {
             let line = buf[..pos].trim().to_string();
             buf = buf[pos + 1..].to_string();
@@ -765,33 +1877,31 @@ async fn stream_openai_compat(window: tauri::Window, req: StreamRequest) -> Resu
                     let delta = j["choices"][0]["delta"]["content"].as_str().unwrap_or("");
                     if !delta.is_empty() {
                         full_text.push_str(delta);
-                        let _ = window.emit("ai-stream-token", delta);
+                        sink.emit("ai-stream-token", delta);
+                    }
+                    for d in openai_tool_deltas(&j) {
+                        tool_calls.apply(&d);
+                        sink.emit("ai-stream-toolcall", &d);
                     }
                 }
             }
         }
     }
 
-    let _ = window.emit("ai-stream-done", serde_json::json!({ "text": full_text, "model": model }));
+    sink.emit("ai-stream-done", serde_json::json!({
+        "text": full_text, "model": model, "tool_calls": tool_calls.finish(),
+    }));
     Ok(())
 }
 
-async fn stream_claude(window: tauri::Window, req: StreamRequest) -> Result<(), String> {
+async fn stream_claude<S: StreamSink>(sink: &S, req: StreamRequest) -> Result<(), String> {
     if req.api_key.is_empty() { return Err("Anthropic API key required".into()); }
     let client = http_client().map_err(|e| e.to_string())?;
     let model = req.model.as_deref().unwrap_or("claude-3-5-sonnet-20241022").to_string();
 
-    let ai_req = AiRequest {
-        api_key: req.api_key.clone(), prompt: req.prompt.clone(),
-        system_prompt: req.system_prompt.clone(), image_base64: req.image_base64.clone(),
-        context_files: req.context_files.clone(), model: req.model.clone(), max_tokens: req.max_tokens,
-    };
+    let ai_req = req.to_ai_request();
 
-    let mut content: Vec<Value> = Vec::new();
-    if let Some(b64) = &req.image_base64 {
-        content.push(json!({ "type": "image", "source": { "type": "base64", "media_type": "image/png", "data": b64 } }));
-    }
-    content.push(json!({ "type": "text", "text": build_prompt(&ai_req) }));
+    let content = claude_content(&ai_req);
 
     let sys = req.system_prompt.as_deref().unwrap_or("").trim();
     let max_tok = req.max_tokens.unwrap_or(4096);
@@ -800,15 +1910,21 @@ async fn stream_claude(window: tauri::Window, req: StreamRequest) -> Result<(),
         "messages": [{ "role": "user", "content": content }]
     });
     if !sys.is_empty() { body["system"] = json!(sys); }
+    if let Some(tools) = req.tools.as_ref().filter(|t| !t.is_empty()) {
+        body["tools"] = json!(anthropic_tools(tools));
+    }
 
-    let resp = client.post("https://api.anthropic.com/v1/messages")
+    let request = client.post(api_url("https://api.anthropic.com/v1/messages"))
         .header("x-api-key", &req.api_key).header("anthropic-version", "2023-06-01")
-        .header("content-type", "application/json").json(&body)
-        .send().await.map_err(|e| format!("Stream failed: {}", e))?;
+        .header("content-type", "application/json").json(&body);
+    let (resp, mut exchange) = audit::send_stream("claude", request).await
+        .map_err(|e| format!("Stream failed: {}", e))?;
 
     let status = resp.status();
     if !status.is_success() {
-        let err_json: Value = resp.json().await.unwrap_or(json!({}));
+        let err_text = resp.text().await.unwrap_or_default();
+        exchange.push(&err_text);
+        let err_json: Value = serde_json::from_str(&err_text).unwrap_or(json!({}));
         return Err(format!("Claude {}: {}", status,
             err_json["error"]["message"].as_str().unwrap_or("unknown")));
     }
@@ -816,28 +1932,43 @@ async fn stream_claude(window: tauri::Window, req: StreamRequest) -> Result<(),
     let mut stream = resp.bytes_stream();
     let mut buf = String::new();
     let mut full_text = String::new();
+    let mut cache_usage: (Option<u32>, Option<u32>) = (None, None);
+    let mut tool_calls = ToolCallAssembler::default();
 
     while let Some(chunk) = stream.next().await {
         let chunk = chunk.map_err(|e| format!("Stream read: {}", e))?;
-        buf.push_str(&String::from_utf8_lossy(&chunk));
+        let text = String::from_utf8_lossy(&chunk);
+        exchange.push(&text);
+        buf.push_str(&text);
         while let Some(pos) = buf.find('\n') {
             let line = buf[..pos].trim().to_string();
             buf = buf[pos + 1..].to_string();
             if let Some(data) = line.strip_prefix("data: ") {
                 if let Ok(j) = serde_json::from_str::<Value>(data) {
+                    if j["type"] == "message_start" {
+                        cache_usage = claude_cache_usage(&j["message"]["usage"]);
+                    }
                     if j["type"] == "content_block_delta" {
                         let delta = j["delta"]["text"].as_str().unwrap_or("");
                         if !delta.is_empty() {
                             full_text.push_str(delta);
-                            let _ = window.emit("ai-stream-token", delta);
+                            sink.emit("ai-stream-token", delta);
                         }
                     }
+                    if let Some(d) = anthropic_tool_delta(&j) {
+                        tool_calls.apply(&d);
+                        sink.emit("ai-stream-toolcall", &d);
+                    }
                 }
             }
         }
     }
 
-    let _ = window.emit("ai-stream-done", serde_json::json!({ "text": full_text, "model": model }));
+    sink.emit("ai-stream-done", serde_json::json!({
+        "text": full_text, "model": model,
+        "cache_read_tokens": cache_usage.0, "cache_write_tokens": cache_usage.1,
+        "tool_calls": tool_calls.finish(),
+    }));
     Ok(())
 }
 
@@ -846,7 +1977,7 @@ async fn stream_claude(window: tauri::Window, req: StreamRequest) -> Result<(),
 // ═══════════════════════════════════════════════════════════════════════
 
 #[tauri::command]
-pub async fn list_ollama_models(base_url: Option<String>) -> Result<Vec<String>, String> {
+pub async fn list_ollama_models(base_url: Option<String>) -> Result<Vec<String>, AppError> {
     let base = base_url.as_deref().unwrap_or("http://127.0.0.1:11434").trim_end_matches('/');
     let client = http_client().map_err(|e| e.to_string())?;
     let resp = client.get(format!("{}/api/tags", base))
@@ -858,7 +1989,7 @@ pub async fn list_ollama_models(base_url: Option<String>) -> Result<Vec<String>,
 }
 
 #[tauri::command]
-pub async fn list_lmstudio_models(base_url: Option<String>) -> Result<Vec<String>, String> {
+pub async fn list_lmstudio_models(base_url: Option<String>) -> Result<Vec<String>, AppError> {
     let base = base_url.as_deref().unwrap_or("http://127.0.0.1:1234").trim_end_matches('/');
     let client = http_client().map_err(|e| e.to_string())?;
     let resp = client.get(format!("{}/v1/models", base))
@@ -869,6 +2000,112 @@ pub async fn list_lmstudio_models(base_url: Option<String>) -> Result<Vec<String
         .iter().filter_map(|m| m["id"].as_str().map(String::from)).collect())
 }
 
+// ── LM Studio native REST API (/api/v0) ──────────────────────────────────
+// Unlike /v1/models it reports every downloaded model, whether it is loaded,
+// its context length and quantization.
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct LmStudioModel {
+    pub id:                    String,
+    /// "llm" | "vlm" | "embeddings"
+    pub kind:                  String,
+    pub arch:                  Option<String>,
+    pub quantization:          Option<String>,
+    pub loaded:                bool,
+    pub max_context_length:    Option<u32>,
+    /// Context length the model was loaded with (only when loaded)
+    pub loaded_context_length: Option<u32>,
+}
+
+fn parse_lmstudio_models(json: &Value) -> Vec<LmStudioModel> {
+    let as_u32 = |v: &Value| v.as_u64().map(|n| n as u32);
+    let mut models: Vec<LmStudioModel> = json["data"].as_array().unwrap_or(&vec![])
+        .iter()
+        .filter_map(|m| Some(LmStudioModel {
+            id:                    m["id"].as_str()?.to_string(),
+            kind:                  m["type"].as_str().unwrap_or("llm").to_string(),
+            arch:                  m["arch"].as_str().map(String::from),
+            quantization:          m["quantization"].as_str().map(String::from),
+            loaded:                m["state"].as_str() == Some("loaded"),
+            max_context_length:    as_u32(&m["max_context_length"]),
+            loaded_context_length: as_u32(&m["loaded_context_length"]),
+        }))
+        .collect();
+    // Loaded models first — they answer without a JIT load
+    models.sort_by_key(|m| !m.loaded);
+    models
+}
+
+#[tauri::command]
+pub async fn list_lmstudio_models_detailed(base_url: Option<String>) -> Result<Vec<LmStudioModel>, AppError> {
+    let root = url_root(base_url.as_deref().unwrap_or("http://127.0.0.1:1234").trim_end_matches('/'));
+    let client = http_client().map_err(|e| e.to_string())?;
+    let resp = client.get(format!("{}/api/v0/models", root))
+        .timeout(std::time::Duration::from_secs(4)).send().await
+        .map_err(|e| format!("LM Studio not reachable at {}: {}", root, e))?;
+    if !resp.status().is_success() {
+        return Err(format!("LM Studio at {} has no native API (/api/v0) — update to 0.3.6+", root).into());
+    }
+    let json: Value = resp.json().await.map_err(|e| e.to_string())?;
+    Ok(parse_lmstudio_models(&json))
+}
+
+/// Load `model` into memory. LM Studio 0.4+ has an explicit endpoint; older
+/// servers load on first use, so a one-token completion does the same.
+#[tauri::command]
+pub async fn lmstudio_load_model(base_url: Option<String>, model: String, context_length: Option<u32>) -> Result<(), AppError> {
+    let root = url_root(base_url.as_deref().unwrap_or("http://127.0.0.1:1234").trim_end_matches('/'));
+    let client = http_client().map_err(|e| e.to_string())?;
+
+    let mut body = json!({ "model": model });
+    if let Some(n) = context_length {
+        body["context_length"] = json!(n);
+    }
+    let native = client.post(format!("{}/api/v1/models/load", root)).json(&body).send().await;
+    if matches!(&native, Ok(r) if r.status().is_success()) {
+        log::info!("LM Studio: loaded {}", model);
+        return Ok(());
+    }
+
+    let resp = client.post(format!("{}/api/v0/completions", root))
+        .json(&json!({ "model": model, "prompt": "", "max_tokens": 1 }))
+        .send().await
+        .map_err(|e| format!("LM Studio not reachable at {}: {}", root, e))?;
+    if !resp.status().is_success() {
+        let json: Value = resp.json().await.unwrap_or_default();
+        return Err(format!("LM Studio could not load {}: {}",
+            model, json["error"]["message"].as_str().or_else(|| json["error"].as_str()).unwrap_or("unknown error")).into());
+    }
+    log::info!("LM Studio: loaded {} (JIT)", model);
+    Ok(())
+}
+
+/// Unload `model`. Falls back to the `lms` CLI on servers without the
+/// unload endpoint.
+#[tauri::command]
+pub async fn lmstudio_unload_model(base_url: Option<String>, model: String) -> Result<(), AppError> {
+    let root = url_root(base_url.as_deref().unwrap_or("http://127.0.0.1:1234").trim_end_matches('/'));
+    let client = http_client().map_err(|e| e.to_string())?;
+
+    let native = client.post(format!("{}/api/v1/models/unload", root))
+        .json(&json!({ "instance_id": model }))
+        .send().await;
+    if matches!(&native, Ok(r) if r.status().is_success()) {
+        log::info!("LM Studio: unloaded {}", model);
+        return Ok(());
+    }
+
+    let out = tokio::process::Command::new("lms")
+        .args(["unload", &model])
+        .output().await
+        .map_err(|e| format!("LM Studio has no unload endpoint and the `lms` CLI is not available: {}", e))?;
+    if !out.status.success() {
+        return Err(format!("lms unload failed: {}", String::from_utf8_lossy(&out.stderr).trim()).into());
+    }
+    log::info!("LM Studio: unloaded {} (lms)", model);
+    Ok(())
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct SdModel {
     pub title:      String,
@@ -876,7 +2113,7 @@ pub struct SdModel {
 }
 
 #[tauri::command]
-pub async fn list_sd_models(base_url: Option<String>) -> Result<Vec<SdModel>, String> {
+pub async fn list_sd_models(base_url: Option<String>) -> Result<Vec<SdModel>, AppError> {
     let base = base_url.as_deref().unwrap_or("http://127.0.0.1:7860").trim_end_matches('/');
     let client = http_client().map_err(|e| e.to_string())?;
     let resp = client.get(format!("{}/sdapi/v1/sd-models", base))
@@ -887,4 +2124,182 @@ pub async fn list_sd_models(base_url: Option<String>) -> Result<Vec<SdModel>, St
         title:      m["title"].as_str().unwrap_or("").to_string(),
         model_name: m["model_name"].as_str().unwrap_or("").to_string(),
     }).collect())
-}
\ No newline at end of file
+}
+// ── Provider tests against the mock server ─────────────────────────────────
+// End-to-end through reqwest, SSE parsing, cancellation and error mapping.
+// Opt-in because they open local sockets: cargo test --features provider-tests
+
+#[cfg(all(test, feature = "provider-tests"))]
+mod provider_tests {
+    use super::*;
+    use crate::error::ErrorKind;
+    use crate::mock_provider::{MockResponse, MockServer};
+    use std::time::Duration;
+
+    /// Streaming events captured instead of sent to a window.
+    #[derive(Default)]
+    struct Recorder(std::sync::Mutex<Vec<(String, Value)>>);
+
+    impl StreamSink for Recorder {
+        fn emit<P: Serialize + Clone>(&self, event: &str, payload: P) {
+            let payload = serde_json::to_value(payload).unwrap_or(Value::Null);
+            self.0.lock().unwrap().push((event.to_string(), payload));
+        }
+    }
+
+    impl Recorder {
+        fn events(&self, name: &str) -> Vec<Value> {
+            self.0.lock().unwrap().iter().filter(|(e, _)| e == name).map(|(_, p)| p.clone()).collect()
+        }
+    }
+
+    async fn mock(responses: Vec<MockResponse>) -> MockServer {
+        let server = MockServer::start(responses).await;
+        set_api_base(Some(server.url.clone()));
+        server
+    }
+
+    fn ai_request(prompt: &str) -> AiRequest {
+        AiRequest {
+            api_key: "sk-test".into(), prompt: prompt.into(), system_prompt: None, image_base64: None,
+            context_files: None, model: None, max_tokens: None, allow_secrets: false, profile: None,
+        }
+    }
+
+    fn stream_request(provider: &str) -> StreamRequest {
+        StreamRequest {
+            provider: provider.into(), api_key: "sk-test".into(), prompt: "Hi".into(), system_prompt: None,
+            image_base64: None, context_files: None, model: None, max_tokens: None, local_url: None,
+            history: None, fallback_model: None, tools: None, allow_secrets: false, profile: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_openai_completion_round_trip() {
+        let server = mock(vec![MockResponse::json(200, json!({
+            "model": "gpt-4o-2024-08-06",
+            "choices": [{ "message": { "role": "assistant", "content": "Four." } }],
+            "usage": { "total_tokens": 17 }
+        }))]).await;
+
+        let mut req = ai_request("2 + 2?");
+        req.system_prompt = Some("Be brief.".into());
+        let resp = analyze_with_openai(req).await.unwrap();
+        assert_eq!(resp.text, "Four.");
+        assert_eq!(resp.model, "gpt-4o-2024-08-06");
+        assert_eq!(resp.tokens_used, Some(17));
+
+        let sent = &server.requests()[0];
+        assert_eq!((sent.method.as_str(), sent.path.as_str()), ("POST", "/v1/chat/completions"));
+        assert_eq!(sent.header("authorization"), Some("Bearer sk-test"));
+        assert_eq!(sent.body["messages"][0], json!({ "role": "system", "content": "Be brief." }));
+        assert_eq!(sent.body["model"], "gpt-4o");
+    }
+
+    #[tokio::test]
+    async fn test_provider_errors_are_mapped() {
+        let server = mock(vec![MockResponse::error(401, "invalid x-api-key")]).await;
+        let err = analyze_with_claude(ai_request("hi")).await.unwrap_err();
+        assert_eq!(err.message, "Claude 401 Unauthorized: invalid x-api-key");
+        assert_eq!((err.kind, err.provider.as_deref(), err.retryable), (ErrorKind::Auth, Some("claude"), false));
+        let sent = &server.requests()[0];
+        assert_eq!(sent.path, "/v1/messages");
+        assert_eq!(sent.header("x-api-key"), Some("sk-test"));
+        assert_eq!(sent.header("anthropic-version"), Some("2023-06-01"));
+
+        let _server = mock(vec![MockResponse::error(429, "Rate limit reached")]).await;
+        let err = stream_with_recovery(&Recorder::default(), stream_request("deepseek")).await.unwrap_err();
+        assert_eq!(err, "deepseek 429 Too Many Requests: Rate limit reached");
+    }
+
+    #[tokio::test]
+    async fn test_local_server_error_formats() {
+        // FastAPI-style `detail`, no `error` object
+        let server = MockServer::start(vec![MockResponse::json(500, json!({ "detail": "No model loaded" }))]).await;
+        let err = analyze_with_local(LocalAiRequest {
+            base_url: server.url.clone(), api_key: None, prompt: "hi".into(), system_prompt: None,
+            image_base64: None, context_files: None, model: None, max_tokens: None,
+            server_type: Some("openai".into()),
+        }).await.unwrap_err();
+        assert_eq!(err.message, "Local LLM 500 Internal Server Error: No model loaded");
+        assert_eq!((err.kind, err.provider.as_deref()), (ErrorKind::Provider, Some("local")));
+        assert!(server.requests()[0].header("authorization").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_openai_stream_emits_tokens() {
+        let server = mock(vec![MockResponse::openai_stream(&["Hel", "lo", " world"])]).await;
+        let sink = Recorder::default();
+        stream_with_recovery(&sink, stream_request("openrouter")).await.unwrap();
+
+        assert_eq!(sink.events("ai-stream-token"), vec![json!("Hel"), json!("lo"), json!(" world")]);
+        let done = &sink.events("ai-stream-done")[0];
+        assert_eq!(done["text"], "Hello world");
+        assert_eq!(done["model"], "openai/gpt-4o");
+        let sent = &server.requests()[0];
+        assert_eq!(sent.path, "/api/v1/chat/completions");
+        assert_eq!(sent.body["stream"], true);
+        assert_eq!(sent.header("x-title"), Some("AI Assistant Overlay"));
+    }
+
+    #[tokio::test]
+    async fn test_claude_stream_reports_cache_usage() {
+        let _server = mock(vec![MockResponse::anthropic_stream(&["Bon", "jour"])]).await;
+        let sink = Recorder::default();
+        stream_with_recovery(&sink, stream_request("claude")).await.unwrap();
+
+        assert_eq!(sink.events("ai-stream-token").len(), 2);
+        let done = &sink.events("ai-stream-done")[0];
+        assert_eq!(done["text"], "Bonjour");
+        assert_eq!(done["cache_read_tokens"], 4);
+    }
+
+    #[tokio::test]
+    async fn test_context_overflow_retries_with_fewer_files() {
+        let server = mock(vec![
+            MockResponse::error(400, "This model's maximum context length is 8192 tokens"),
+            MockResponse::openai_stream(&["ok"]),
+        ]).await;
+        let sink = Recorder::default();
+        let mut req = stream_request("openai");
+        req.context_files = Some((1..=4).map(|i| format!("file {}", i)).collect());
+        stream_with_recovery(&sink, req).await.unwrap();
+
+        let trimmed = &sink.events("ai-context-trimmed")[0];
+        assert_eq!((trimmed["dropped_files"].as_u64(), trimmed["remaining_files"].as_u64()), (Some(2), Some(2)));
+        assert_eq!(sink.events("ai-stream-done")[0]["text"], "ok");
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 2);
+        let retried = requests[1].body["messages"][0]["content"].as_str().unwrap();
+        assert!(retried.contains("file 1") && !retried.contains("file 4"));
+    }
+
+    #[tokio::test]
+    async fn test_cancellation_stops_request() {
+        let _server = mock(vec![
+            MockResponse::openai_stream(&["slow", "tokens", "here"]).with_delay(Duration::from_millis(300)),
+        ]).await;
+        let sink = Recorder::default();
+        let cancel = async {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            cancel_ai_request(Some("mock-cancel".into()));
+        };
+        let (result, ()) = tokio::join!(
+            with_request_id(Some("mock-cancel"), stream_with_recovery(&sink, stream_request("together"))),
+            cancel,
+        );
+        assert_eq!(result.unwrap_err(), "__CANCELLED__");
+        assert!(sink.events("ai-stream-done").is_empty());
+
+        // Non-streaming path, same mechanism
+        let (result, ()) = tokio::join!(
+            with_request_id(Some("mock-cancel-2"), analyze_with_openai(ai_request("hi"))),
+            async {
+                tokio::time::sleep(Duration::from_millis(100)).await;
+                cancel_ai_request(Some("mock-cancel-2".into()));
+            },
+        );
+        assert_eq!(result.unwrap_err().kind, ErrorKind::Cancelled);
+    }
+}
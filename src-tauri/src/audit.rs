@@ -0,0 +1,417 @@
+// audit.rs — opt-in log of every request sent to an AI provider
+//
+// For compliance-sensitive setups: when enabled, every request to a cloud
+// provider — chat, streaming and embeddings (ai_bridge.rs), image
+// generation and editing (image_gen.rs, image_edit.rs), moderation and
+// DeepL translation — is appended to <app-data>/audit_log.jsonl with the
+// exact URL, headers and body that went over the wire and what came back.
+// Credentials are never stored — auth headers and key-like query
+// parameters are masked and both bodies go through secret_scanner — and
+// inline base64 images are replaced by their size. Multipart bodies can't
+// be read back, so their callers describe them (describe_request).
+// `request_hash` is the FNV-1a hash of the unmodified body bytes, so an
+// entry can be matched against a captured payload.
+//
+// The log rotates to audit_log.1.jsonl past MAX_LOG_BYTES.
+//
+// Tauri commands exposed:
+//   get_audit_settings / set_audit_settings
+//   get_audit_entries  (provider?, since?, limit?) → Vec<AuditEntry>, newest first
+//   clear_audit_log
+
+use crate::error::AppError;
+use crate::secret_scanner;
+use reqwest::{RequestBuilder, Response, StatusCode};
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Mutex;
+
+const SETTINGS_FILE: &str = "audit.json";
+const LOG_FILE: &str      = "audit_log.jsonl";
+const OLD_LOG_FILE: &str  = "audit_log.1.jsonl";
+const MAX_LOG_BYTES: u64  = 50 * 1024 * 1024;
+/// Per-body cap; long streamed answers are cut here
+const MAX_BODY_CHARS: usize = 200_000;
+/// Base64 runs at least this long are assumed to be images
+const MIN_BASE64_RUN: usize = 512;
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+/// App data dir; None until init (tests, or no data dir) — nothing is logged.
+static DIR: Mutex<Option<PathBuf>> = Mutex::new(None);
+
+// ── Types ──────────────────────────────────────────────────────────────────
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct AuditSettings {
+    pub enabled: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct AuditEntry {
+    pub id:              String,
+    /// Unix timestamp (milliseconds) when the request was sent
+    pub timestamp:       u64,
+    /// "openai" | "claude" | "dalle" | …
+    pub provider:        String,
+    pub method:          String,
+    pub url:             String,
+    pub request_headers: Vec<(String, String)>,
+    pub request_body:    String,
+    pub request_bytes:   usize,
+    pub request_hash:    String,
+    /// None when the request never got a response
+    pub status:          Option<u16>,
+    pub response_body:   String,
+    pub response_bytes:  usize,
+    pub duration_ms:     u64,
+    pub error:           Option<String>,
+    /// Number of secrets masked across both bodies
+    pub redactions:      usize,
+}
+
+/// One request/response pair being recorded; written to the log on drop so
+/// early returns and cancelled streams are still logged. Inert when
+/// auditing is off.
+pub(crate) struct Exchange(Option<Box<Pending>>);
+
+struct Pending {
+    entry:    AuditEntry,
+    response: String,
+    started:  std::time::Instant,
+}
+
+impl Exchange {
+    /// Record part of the response body (streamed chunks arrive one by one).
+    pub fn push(&mut self, text: &str) {
+        if let Some(p) = self.0.as_mut() {
+            p.entry.response_bytes += text.len();
+            if p.response.len() < MAX_BODY_CHARS {
+                p.response.push_str(text);
+            }
+        }
+    }
+
+    /// Stand-in for a body reqwest can't expose (multipart forms).
+    pub fn describe_request(&mut self, text: &str) {
+        if let Some(p) = self.0.as_mut() {
+            let redacted = secret_scanner::redact_for_storage(&clip(text.to_string()));
+            p.entry.request_body = redacted.text;
+            p.entry.redactions += redacted.findings.len();
+        }
+    }
+
+    pub fn fail(&mut self, error: &str) {
+        if let Some(p) = self.0.as_mut() {
+            p.entry.error = Some(error.to_string());
+        }
+    }
+}
+
+impl Drop for Exchange {
+    fn drop(&mut self) {
+        let Some(p) = self.0.take() else { return };
+        let Pending { mut entry, response, started } = *p;
+        let response = clip(elide_base64(&response));
+        let redacted = secret_scanner::redact_for_storage(&response);
+        entry.response_body = redacted.text;
+        entry.redactions += redacted.findings.len();
+        entry.duration_ms = started.elapsed().as_millis() as u64;
+        if let Err(e) = append(&entry) {
+            log::warn!("audit: cannot write entry: {}", e);
+        }
+    }
+}
+
+fn unix_millis() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+// ── Recording ──────────────────────────────────────────────────────────────
+
+/// Send `builder` and read the whole response, logging the exchange when
+/// auditing is on. Transport errors are returned for the caller to word.
+pub(crate) async fn send(provider: &str, builder: RequestBuilder) -> reqwest::Result<(StatusCode, String)> {
+    let (resp, mut exchange) = send_stream(provider, builder).await?;
+    let status = resp.status();
+    match resp.text().await {
+        Ok(text) => {
+            exchange.push(&text);
+            Ok((status, text))
+        }
+        Err(e) => {
+            exchange.fail(&e.to_string());
+            Err(e)
+        }
+    }
+}
+
+/// Send `builder` and hand back the response unread, for streaming; feed
+/// the chunks to `Exchange::push` as they are consumed.
+pub(crate) async fn send_stream(provider: &str, builder: RequestBuilder) -> reqwest::Result<(Response, Exchange)> {
+    let (client, request) = builder.build_split();
+    let request = request?;
+    let mut exchange = begin(provider, &request);
+    match client.execute(request).await {
+        Ok(resp) => {
+            if let Some(p) = exchange.0.as_mut() {
+                p.entry.status = Some(resp.status().as_u16());
+            }
+            Ok((resp, exchange))
+        }
+        Err(e) => {
+            exchange.fail(&e.to_string());
+            Err(e)
+        }
+    }
+}
+
+fn begin(provider: &str, request: &reqwest::Request) -> Exchange {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return Exchange(None);
+    }
+    let bytes = request.body().and_then(|b| b.as_bytes());
+    let body = match bytes {
+        Some(b) => clip(elide_base64(&String::from_utf8_lossy(b))),
+        None    => "[streamed or multipart body not captured]".to_string(),
+    };
+    let redacted = secret_scanner::redact_for_storage(&body);
+    let entry = AuditEntry {
+        id:              format!("{}-{}", unix_millis(), NEXT_ID.fetch_add(1, Ordering::Relaxed)),
+        timestamp:       unix_millis(),
+        provider:        provider.to_string(),
+        method:          request.method().to_string(),
+        url:             mask_url(request.url()),
+        request_headers: request.headers().iter()
+            .map(|(name, value)| {
+                let value = if is_sensitive(name.as_str()) {
+                    "[REDACTED]".to_string()
+                } else {
+                    value.to_str().unwrap_or("[binary]").to_string()
+                };
+                (name.to_string(), value)
+            })
+            .collect(),
+        request_body:    redacted.text,
+        request_bytes:   bytes.map_or(0, |b| b.len()),
+        request_hash:    bytes.map(fnv1a).unwrap_or_default(),
+        status:          None,
+        response_body:   String::new(),
+        response_bytes:  0,
+        duration_ms:     0,
+        error:           None,
+        redactions:      redacted.findings.len(),
+    };
+    Exchange(Some(Box::new(Pending { entry, response: String::new(), started: std::time::Instant::now() })))
+}
+
+fn is_sensitive(name: &str) -> bool {
+    let name = name.to_ascii_lowercase();
+    name == "authorization" || name == "cookie" || name.contains("key") || name.contains("token") || name.contains("secret")
+}
+
+/// URL with the values of key/token/secret query parameters masked.
+fn mask_url(url: &reqwest::Url) -> String {
+    if url.query().is_none() {
+        return url.to_string();
+    }
+    let pairs: Vec<(String, String)> = url.query_pairs()
+        .map(|(k, v)| {
+            let v = if is_sensitive(&k) { "[REDACTED]".to_string() } else { v.into_owned() };
+            (k.into_owned(), v)
+        })
+        .collect();
+    let mut masked = url.clone();
+    masked.query_pairs_mut().clear().extend_pairs(pairs);
+    masked.to_string()
+}
+
+/// Replace long base64 runs (inline images) with "[base64: N bytes]".
+fn elide_base64(text: &str) -> String {
+    let is_b64 = |c: char| c.is_ascii_alphanumeric() || matches!(c, '+' | '/' | '=');
+    let mut out = String::with_capacity(text.len().min(MAX_BODY_CHARS));
+    let mut run_start: Option<usize> = None;
+    for (i, c) in text.char_indices().chain(std::iter::once((text.len(), '"'))) {
+        match (is_b64(c), run_start) {
+            (true, None) => run_start = Some(i),
+            (false, Some(s)) => {
+                if i - s >= MIN_BASE64_RUN {
+                    out.push_str(&format!("[base64: {} bytes]", (i - s) / 4 * 3));
+                } else {
+                    out.push_str(&text[s..i]);
+                }
+                run_start = None;
+                if i < text.len() { out.push(c); }
+            }
+            (false, None) => {
+                if i < text.len() { out.push(c); }
+            }
+            (true, Some(_)) => {}
+        }
+    }
+    out
+}
+
+fn clip(mut s: String) -> String {
+    if s.len() <= MAX_BODY_CHARS {
+        return s;
+    }
+    let mut cut = MAX_BODY_CHARS;
+    while !s.is_char_boundary(cut) { cut -= 1; }
+    s.truncate(cut);
+    s.push_str("[… truncated …]");
+    s
+}
+
+fn fnv1a(bytes: &[u8]) -> String {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for b in bytes {
+        hash ^= *b as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    format!("{:016x}", hash)
+}
+
+// ── Storage ────────────────────────────────────────────────────────────────
+
+fn dir() -> Option<PathBuf> {
+    DIR.lock().ok().and_then(|d| d.clone())
+}
+
+fn append(entry: &AuditEntry) -> Result<(), String> {
+    let Some(dir) = dir() else { return Ok(()) };
+    // Serialise writers; the lock also guards rotation
+    let _guard = DIR.lock().map_err(|e| e.to_string())?;
+    let path = dir.join(LOG_FILE);
+    if std::fs::metadata(&path).map(|m| m.len() > MAX_LOG_BYTES).unwrap_or(false) {
+        std::fs::rename(&path, dir.join(OLD_LOG_FILE)).map_err(|e| e.to_string())?;
+    }
+    let mut line = serde_json::to_string(entry).map_err(|e| e.to_string())?;
+    line.push('\n');
+    std::fs::OpenOptions::new().create(true).append(true).open(&path)
+        .and_then(|mut f| f.write_all(line.as_bytes()))
+        .map_err(|e| e.to_string())
+}
+
+fn read_entries(path: &Path) -> Vec<AuditEntry> {
+    std::fs::read_to_string(path).unwrap_or_default()
+        .lines()
+        .filter_map(|l| serde_json::from_str(l).ok())
+        .collect()
+}
+
+/// Entries from both log files, newest first, filtered.
+fn query(dir: &Path, provider: Option<&str>, since: Option<u64>, limit: usize) -> Vec<AuditEntry> {
+    let mut entries = read_entries(&dir.join(OLD_LOG_FILE));
+    entries.extend(read_entries(&dir.join(LOG_FILE)));
+    entries.into_iter().rev()
+        .filter(|e| provider.map_or(true, |p| e.provider == p))
+        .filter(|e| since.map_or(true, |s| e.timestamp >= s))
+        .take(limit)
+        .collect()
+}
+
+/// Load the saved settings; called once from setup.
+pub fn init(app: &tauri::AppHandle) {
+    let Some(dir) = app.path_resolver().app_data_dir() else {
+        log::warn!("audit: cannot resolve app data directory, audit log unavailable");
+        return;
+    };
+    let settings: AuditSettings = std::fs::read_to_string(dir.join(SETTINGS_FILE)).ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default();
+    ENABLED.store(settings.enabled, Ordering::Relaxed);
+    if let Ok(mut d) = DIR.lock() {
+        *d = Some(dir);
+    }
+}
+
+// ── Tauri commands ─────────────────────────────────────────────────────────
+
+#[tauri::command]
+pub fn get_audit_settings() -> AuditSettings {
+    AuditSettings { enabled: ENABLED.load(Ordering::Relaxed) }
+}
+
+#[tauri::command]
+pub fn set_audit_settings(settings: AuditSettings) -> Result<(), AppError> {
+    let dir = dir().ok_or("App data directory unavailable")?;
+    std::fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    let json = serde_json::to_string_pretty(&settings).map_err(|e| e.to_string())?;
+    let tmp = dir.join(format!("{}.tmp", SETTINGS_FILE));
+    std::fs::write(&tmp, json).map_err(|e| e.to_string())?;
+    std::fs::rename(&tmp, dir.join(SETTINGS_FILE)).map_err(|e| e.to_string())?;
+    ENABLED.store(settings.enabled, Ordering::Relaxed);
+    log::info!("audit: logging {}", if settings.enabled { "enabled" } else { "disabled" });
+    Ok(())
+}
+
+/// `since` is a Unix timestamp in milliseconds; `limit` defaults to 100.
+#[tauri::command]
+pub fn get_audit_entries(provider: Option<String>, since: Option<u64>, limit: Option<usize>) -> Result<Vec<AuditEntry>, AppError> {
+    let dir = dir().ok_or("App data directory unavailable")?;
+    Ok(query(&dir, provider.as_deref(), since, limit.unwrap_or(100)))
+}
+
+#[tauri::command]
+pub fn clear_audit_log() -> Result<(), AppError> {
+    let Some(dir) = dir() else { return Ok(()) };
+    let _guard = DIR.lock().map_err(|e| e.to_string())?;
+    for name in [LOG_FILE, OLD_LOG_FILE] {
+        match std::fs::remove_file(dir.join(name)) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.into()),
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+// ── Unit tests ─────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_elide_base64_keeps_short_runs() {
+        let image = "A".repeat(2_000);
+        let body = format!(r#"{{"model":"gpt-4o","url":"data:image/png;base64,{}"}}"#, image);
+        assert_eq!(elide_base64(&body), r#"{"model":"gpt-4o","url":"data:image/png;base64,[base64: 1500 bytes]"}"#);
+        assert_eq!(elide_base64("plain text, ünïcode"), "plain text, ünïcode");
+    }
+
+    #[test]
+    fn test_mask_url_and_headers() {
+        let url = reqwest::Url::parse("https://example.com/v1/search?q=rust&api_key=abc123").unwrap();
+        assert_eq!(mask_url(&url), "https://example.com/v1/search?q=rust&api_key=%5BREDACTED%5D");
+        assert!(is_sensitive("x-api-key") && is_sensitive("Authorization"));
+        assert!(!is_sensitive("content-type"));
+    }
+
+    #[test]
+    fn test_query_newest_first_with_filters() {
+        let dir = tempfile::tempdir().unwrap();
+        let entry = |id: &str, provider: &str, timestamp: u64| AuditEntry {
+            id: id.into(), timestamp, provider: provider.into(), method: "POST".into(),
+            url: String::new(), request_headers: vec![], request_body: String::new(),
+            request_bytes: 0, request_hash: String::new(), status: Some(200),
+            response_body: String::new(), response_bytes: 0, duration_ms: 0, error: None, redactions: 0,
+        };
+        let write = |name: &str, entries: &[AuditEntry]| {
+            let lines: Vec<String> = entries.iter().map(|e| serde_json::to_string(e).unwrap()).collect();
+            std::fs::write(dir.path().join(name), lines.join("\n")).unwrap();
+        };
+        write(OLD_LOG_FILE, &[entry("a", "openai", 1)]);
+        write(LOG_FILE, &[entry("b", "claude", 2), entry("c", "openai", 3)]);
+
+        let ids = |v: Vec<AuditEntry>| v.into_iter().map(|e| e.id).collect::<Vec<_>>();
+        assert_eq!(ids(query(dir.path(), None, None, 100)), ["c", "b", "a"]);
+        assert_eq!(ids(query(dir.path(), Some("openai"), None, 100)), ["c", "a"]);
+        assert_eq!(ids(query(dir.path(), None, Some(2), 1)), ["c"]);
+    }
+}
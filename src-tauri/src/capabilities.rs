@@ -0,0 +1,366 @@
+// capabilities.rs — which features can work on this platform / install
+//
+// Lets the frontend hide or gray out what would only fail at click time:
+// screenshot tools, Tesseract OCR (suggestions.rs), GPU runtimes for
+// stable-diffusion.cpp, the OS credential store, xdg-desktop-portal and
+// optional compile-time features. Probes look at PATH, env vars and a few
+// library paths (plus one D-Bus query on Linux), so the report is cached;
+// `refresh: true` probes again after the user installs something.
+//
+// Tauri commands exposed:
+//   get_capabilities(refresh?) → Capabilities
+
+use crate::error::AppError;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::sync::Mutex;
+
+static CACHE: Mutex<Option<Capabilities>> = Mutex::new(None);
+
+/// Linux screenshot tools in the order screen_capture.rs tries them
+const WAYLAND_CAPTURE: &[&str] = &["grim", "gnome-screenshot", "spectacle"];
+const X11_CAPTURE: &[&str] = &["scrot", "import"];
+
+// ── Types ──────────────────────────────────────────────────────────────────
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct Capabilities {
+    /// "linux" | "macos" | "windows"
+    pub platform:         &'static str,
+    /// "wayland" | "x11" on Linux, None elsewhere or without a display
+    pub display_server:   Option<&'static str>,
+    /// Screenshot backends usable in this session, in the order they are tried
+    pub capture_backends: Vec<&'static str>,
+    /// Tesseract found (screen suggestions)
+    pub ocr:              bool,
+    /// stable-diffusion.cpp backends whose runtime is present; "cpu" always
+    pub gpu_backends:     Vec<&'static str>,
+    /// OS credential store reachable (Keychain, Credential Manager, Secret Service)
+    pub keychain:         bool,
+    /// xdg-desktop-portal running (Linux; always false elsewhere)
+    pub portal:           bool,
+    /// Optional features compiled into this build ("hotword", …)
+    pub features:         Vec<&'static str>,
+    /// Capability → what to install / enable, for each unavailable one
+    pub missing:          BTreeMap<&'static str, String>,
+}
+
+// ── Tauri command ──────────────────────────────────────────────────────────
+
+#[tauri::command]
+pub async fn get_capabilities(refresh: Option<bool>) -> Result<Capabilities, AppError> {
+    if !refresh.unwrap_or(false) {
+        if let Some(c) = CACHE.lock().ok().and_then(|c| c.clone()) {
+            return Ok(c);
+        }
+    }
+    let caps = tokio::task::spawn_blocking(probe).await?;
+    log::info!(
+        "capabilities: capture {:?}, ocr {}, gpu {:?}, keychain {}, portal {}",
+        caps.capture_backends, caps.ocr, caps.gpu_backends, caps.keychain, caps.portal
+    );
+    if let Ok(mut c) = CACHE.lock() {
+        *c = Some(caps.clone());
+    }
+    Ok(caps)
+}
+
+// ── Probing ────────────────────────────────────────────────────────────────
+
+fn probe() -> Capabilities {
+    let display_server = platform::display_server();
+    let capture_backends = platform::capture_backends(display_server);
+    let ocr = find_tool("tesseract").is_some() || platform::bundled_tesseract();
+    let gpu_backends = platform::gpu_backends();
+    let keychain = platform::keychain();
+    let portal = platform::portal();
+    let features = compiled_features();
+    let missing = missing_hints(display_server, &capture_backends, ocr, keychain, portal);
+    Capabilities {
+        platform: std::env::consts::OS,
+        display_server,
+        capture_backends,
+        ocr,
+        gpu_backends,
+        keychain,
+        portal,
+        features,
+        missing,
+    }
+}
+
+fn compiled_features() -> Vec<&'static str> {
+    let mut features = Vec::new();
+    if cfg!(feature = "hotword") {
+        features.push("hotword");
+    }
+    features
+}
+
+/// `name` on PATH (with the PATHEXT extensions on Windows), without running it.
+fn find_tool(name: &str) -> Option<PathBuf> {
+    let path = std::env::var_os("PATH")?;
+    let exts: Vec<String> = if cfg!(windows) {
+        std::env::var("PATHEXT")
+            .unwrap_or_else(|_| ".EXE;.CMD;.BAT".into())
+            .split(';')
+            .map(str::to_ascii_lowercase)
+            .collect()
+    } else {
+        Vec::new()
+    };
+    find_in(std::env::split_paths(&path), name, &exts)
+}
+
+fn find_in(dirs: impl IntoIterator<Item = PathBuf>, name: &str, exts: &[String]) -> Option<PathBuf> {
+    dirs.into_iter().find_map(|dir| {
+        std::iter::once(dir.join(name))
+            .chain(exts.iter().map(|ext| dir.join(format!("{}{}", name, ext))))
+            .find(|p| p.is_file())
+    })
+}
+
+/// Linux screenshot backends for the session: Wayland tools only under
+/// Wayland, X11 tools only with a DISPLAY (XWayland counts).
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn linux_capture_backends(wayland: bool, x11: bool, has: impl Fn(&str) -> bool) -> Vec<&'static str> {
+    let mut tools = Vec::new();
+    if wayland {
+        tools.extend(WAYLAND_CAPTURE.iter().copied().filter(|t| has(t)));
+    }
+    if x11 {
+        tools.extend(X11_CAPTURE.iter().copied().filter(|t| has(t)));
+    }
+    tools
+}
+
+fn missing_hints(
+    display_server:   Option<&str>,
+    capture_backends: &[&str],
+    ocr:              bool,
+    keychain:         bool,
+    portal:           bool,
+) -> BTreeMap<&'static str, String> {
+    let mut missing = BTreeMap::new();
+    if capture_backends.is_empty() {
+        missing.insert("capture", match display_server {
+            Some("wayland") => "Install grim (wlroots), gnome-screenshot or spectacle".to_string(),
+            Some(_)         => "Install scrot or ImageMagick (import)".to_string(),
+            None            => "No display session found".to_string(),
+        });
+    }
+    if !ocr {
+        missing.insert("ocr", "Install Tesseract OCR (tesseract on PATH)".to_string());
+    }
+    if !keychain {
+        missing.insert("keychain", "No Secret Service running (GNOME Keyring / KWallet) or secret-tool missing".to_string());
+    }
+    if !portal && cfg!(target_os = "linux") {
+        missing.insert("portal", "xdg-desktop-portal is not running".to_string());
+    }
+    missing
+}
+
+/// `program args…` ran and exited 0, output discarded.
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn succeeds(program: &str, args: &[&str]) -> bool {
+    Command::new(program)
+        .args(args)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok_and(|s| s.success())
+}
+
+// ── Platform probes ────────────────────────────────────────────────────────
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use super::*;
+    use crate::local_sd;
+    use std::path::Path;
+
+    /// Where distributions put the Vulkan loader
+    const LIB_DIRS: &[&str] = &[
+        "/usr/lib", "/usr/lib64", "/usr/lib/x86_64-linux-gnu", "/usr/lib/aarch64-linux-gnu", "/usr/local/lib",
+    ];
+
+    pub fn display_server() -> Option<&'static str> {
+        let wayland = std::env::var_os("WAYLAND_DISPLAY").is_some()
+            || std::env::var("XDG_SESSION_TYPE").is_ok_and(|t| t == "wayland");
+        if wayland {
+            Some("wayland")
+        } else if std::env::var_os("DISPLAY").is_some() {
+            Some("x11")
+        } else {
+            None
+        }
+    }
+
+    pub fn capture_backends(display_server: Option<&str>) -> Vec<&'static str> {
+        let wayland = display_server == Some("wayland");
+        let x11 = std::env::var_os("DISPLAY").is_some();
+        linux_capture_backends(wayland, x11, |tool| find_tool(tool).is_some())
+    }
+
+    pub fn bundled_tesseract() -> bool {
+        false
+    }
+
+    pub fn gpu_backends() -> Vec<&'static str> {
+        let mut backends = vec!["cpu"];
+        if find_vulkan() {
+            backends.push("vulkan");
+        }
+        if local_sd::check_cuda_libs()["found"].as_bool().unwrap_or(false) {
+            backends.push("cuda");
+        }
+        if local_sd::check_rocm_libs()["found"].as_bool().unwrap_or(false) {
+            backends.push("rocm");
+        }
+        backends
+    }
+
+    fn find_vulkan() -> bool {
+        LIB_DIRS.iter().any(|d| Path::new(d).join("libvulkan.so.1").exists())
+            || find_tool("vulkaninfo").is_some()
+    }
+
+    pub fn keychain() -> bool {
+        find_tool("secret-tool").is_some() && dbus_name_has_owner("org.freedesktop.secrets")
+    }
+
+    pub fn portal() -> bool {
+        dbus_name_has_owner("org.freedesktop.portal.Desktop")
+    }
+
+    /// A service owns `name` on the session bus (busctl, else dbus-send).
+    fn dbus_name_has_owner(name: &str) -> bool {
+        if std::env::var_os("DBUS_SESSION_BUS_ADDRESS").is_none() && std::env::var_os("XDG_RUNTIME_DIR").is_none() {
+            return false;
+        }
+        if find_tool("busctl").is_some() {
+            return succeeds("busctl", &["--user", "status", name]);
+        }
+        Command::new("dbus-send")
+            .args([
+                "--session", "--print-reply", "--dest=org.freedesktop.DBus", "/org/freedesktop/DBus",
+                "org.freedesktop.DBus.NameHasOwner", &format!("string:{}", name),
+            ])
+            .output()
+            .is_ok_and(|o| String::from_utf8_lossy(&o.stdout).contains("boolean true"))
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use super::*;
+    use std::path::Path;
+
+    pub fn display_server() -> Option<&'static str> {
+        None
+    }
+
+    pub fn capture_backends(_: Option<&str>) -> Vec<&'static str> {
+        vec!["core-graphics"]
+    }
+
+    /// Homebrew's prefix is often missing from a GUI app's PATH
+    pub fn bundled_tesseract() -> bool {
+        ["/opt/homebrew/bin/tesseract", "/usr/local/bin/tesseract"].iter().any(|p| Path::new(p).exists())
+    }
+
+    pub fn gpu_backends() -> Vec<&'static str> {
+        vec!["cpu", "metal"]
+    }
+
+    pub fn keychain() -> bool {
+        true
+    }
+
+    pub fn portal() -> bool {
+        false
+    }
+}
+
+#[cfg(target_os = "windows")]
+mod platform {
+    use super::*;
+
+    pub fn display_server() -> Option<&'static str> {
+        None
+    }
+
+    pub fn capture_backends(_: Option<&str>) -> Vec<&'static str> {
+        vec!["gdi"]
+    }
+
+    /// The UB Mannheim installer does not add itself to PATH
+    pub fn bundled_tesseract() -> bool {
+        std::env::var_os("ProgramFiles")
+            .map(PathBuf::from)
+            .is_some_and(|p| p.join("Tesseract-OCR").join("tesseract.exe").exists())
+    }
+
+    pub fn gpu_backends() -> Vec<&'static str> {
+        let system32 = std::env::var_os("SystemRoot")
+            .map(|r| PathBuf::from(r).join("System32"))
+            .unwrap_or_else(|| PathBuf::from(r"C:\Windows\System32"));
+        let mut backends = vec!["cpu"];
+        if system32.join("vulkan-1.dll").exists() {
+            backends.push("vulkan");
+        }
+        if system32.join("nvcuda.dll").exists() {
+            backends.push("cuda");
+        }
+        backends
+    }
+
+    pub fn keychain() -> bool {
+        true
+    }
+
+    pub fn portal() -> bool {
+        false
+    }
+}
+
+// ── Unit tests ─────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_in_with_extensions() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("tesseract.exe"), b"").unwrap();
+        std::fs::create_dir(dir.path().join("grim")).unwrap();
+        let dirs = || vec![PathBuf::from("/nonexistent-dir"), dir.path().to_path_buf()];
+
+        assert_eq!(find_in(dirs(), "tesseract", &[".exe".into()]), Some(dir.path().join("tesseract.exe")));
+        assert_eq!(find_in(dirs(), "tesseract", &[]), None);
+        // a directory is not a tool
+        assert_eq!(find_in(dirs(), "grim", &[]), None);
+    }
+
+    #[test]
+    fn test_linux_capture_backends_follow_session() {
+        let installed = |t: &str| ["grim", "spectacle", "scrot"].contains(&t);
+        assert_eq!(linux_capture_backends(true, false, installed), vec!["grim", "spectacle"]);
+        assert_eq!(linux_capture_backends(true, true, installed), vec!["grim", "spectacle", "scrot"]);
+        assert_eq!(linux_capture_backends(false, true, installed), vec!["scrot"]);
+        assert!(linux_capture_backends(false, false, installed).is_empty());
+    }
+
+    #[test]
+    fn test_missing_hints() {
+        let missing = missing_hints(Some("wayland"), &[], false, true, true);
+        assert!(missing["capture"].contains("grim"));
+        assert!(missing.contains_key("ocr"));
+        assert!(!missing.contains_key("keychain"));
+        assert!(missing_hints(Some("x11"), &["scrot"], true, true, true).is_empty());
+    }
+}
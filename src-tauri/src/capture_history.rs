@@ -0,0 +1,340 @@
+// capture_history.rs — local screenshot history with captions + semantic search
+//
+// Captures are stored as PNG files under <app-data>/captures/ with a JSON
+// index next to them. Each capture can be captioned by a vision model and
+// the caption embedded, so "the screenshot with the red error dialog" can be
+// found weeks later. Nothing leaves the machine unless a cloud provider is
+// chosen for captioning / embedding.
+//
+// Tauri commands exposed:
+//   save_capture     → stores a base64 PNG, returns its CaptureEntry
+//   list_captures    → newest first
+//   delete_capture   → removes file + index entry
+//   index_captures   → captions + embeds captures that have not been processed
+//   search_captures  → semantic (embedding) search, keyword fallback
+
+use crate::ai_bridge::{self, AiRequest};
+use crate::error::AppError;
+use base64::{engine::general_purpose, Engine};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+/// Serializes read-modify-write cycles on the index file.
+static INDEX_LOCK: Mutex<()> = Mutex::new(());
+
+const INDEX_FILE: &str = "captures.json";
+
+// ── Types ──────────────────────────────────────────────────────────────────
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CaptureEntry {
+    pub id:         String,
+    /// Absolute path of the PNG file
+    pub path:       String,
+    /// Unix timestamp (seconds)
+    pub created_at: u64,
+    pub width:      u32,
+    pub height:     u32,
+    /// Vision-model description used for search (None until indexed)
+    pub caption:    Option<String>,
+    /// Embedding of the caption; never sent to the frontend
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub embedding:  Option<Vec<f32>>,
+    /// Model that produced `embedding` — vectors from different models are not comparable
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub embed_model: Option<String>,
+}
+
+/// Which models caption and embed captures.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct IndexCapturesRequest {
+    /// Vision provider for captions: "openai" | "claude" | "openrouter" | "together" | "local"
+    pub provider:        String,
+    pub api_key:         String,
+    pub model:           Option<String>,
+    pub local_url:       Option<String>,
+    /// Embedding provider: "openai" | "local" (None = captions only, keyword search)
+    pub embed_provider:  Option<String>,
+    pub embed_api_key:   Option<String>,
+    pub embed_model:     Option<String>,
+    pub embed_url:       Option<String>,
+    /// Max captures processed per call (default 20)
+    pub limit:           Option<usize>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SearchCapturesRequest {
+    pub query:          String,
+    pub embed_provider: Option<String>,
+    pub embed_api_key:  Option<String>,
+    pub embed_model:    Option<String>,
+    pub embed_url:      Option<String>,
+    pub max_results:    Option<usize>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CaptureHit {
+    pub entry: CaptureEntry,
+    /// 0.0–1.0 (cosine similarity or keyword overlap)
+    pub score: f32,
+}
+
+// ── Storage helpers ────────────────────────────────────────────────────────
+
+pub(crate) fn captures_dir(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+    app.path_resolver()
+        .app_data_dir()
+        .ok_or_else(|| "Cannot resolve app data directory".to_string())
+        .map(|p| p.join("captures"))
+}
+
+fn load_index(dir: &std::path::Path) -> Vec<CaptureEntry> {
+    std::fs::read_to_string(dir.join(INDEX_FILE))
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
+fn save_index(dir: &std::path::Path, entries: &[CaptureEntry]) -> Result<(), String> {
+    let json = serde_json::to_string(entries).map_err(|e| e.to_string())?;
+    let tmp = dir.join(format!("{}.tmp", INDEX_FILE));
+    std::fs::write(&tmp, json).map_err(|e| format!("Failed to write capture index: {}", e))?;
+    std::fs::rename(&tmp, dir.join(INDEX_FILE)).map_err(|e| e.to_string())
+}
+
+/// Strip embeddings before handing entries to the UI.
+fn public(mut e: CaptureEntry) -> CaptureEntry {
+    e.embedding = None;
+    e
+}
+
+/// Every stored capture with its embedding, for reverse_image's local search.
+pub(crate) fn load_entries(app: &tauri::AppHandle) -> Result<Vec<CaptureEntry>, String> {
+    captures_dir(app).map(|dir| load_index(&dir))
+}
+
+/// The `embed_model` index_captures records for `req`'s embedding settings.
+pub(crate) fn embed_key(req: &IndexCapturesRequest) -> Option<String> {
+    req.embed_model.clone().or_else(|| req.embed_provider.clone())
+}
+
+/// Describe an image the way captures are captioned, with `req`'s vision model.
+pub(crate) async fn caption(req: &IndexCapturesRequest, image_base64: String) -> Result<String, String> {
+    let ai_req = AiRequest {
+        api_key:       req.api_key.clone(),
+        prompt:        CAPTION_PROMPT.to_string(),
+        system_prompt: None,
+        image_base64:  Some(image_base64),
+        context_files: None,
+        model:         req.model.clone(),
+        max_tokens:    Some(300),
+        allow_secrets: false,
+        profile:       None,
+    };
+    let text = ai_bridge::complete(&req.provider, ai_req, req.local_url.as_deref())
+        .await
+        .map_err(|e| e.message)?;
+    Some(text.text.trim().to_string())
+        .filter(|t| !t.is_empty())
+        .ok_or_else(|| "Vision model returned an empty caption".to_string())
+}
+
+/// File of the capture with `id`, for other modules that take capture ids.
+pub(crate) fn capture_path(app: &tauri::AppHandle, id: &str) -> Option<PathBuf> {
+    let dir = captures_dir(app).ok()?;
+    load_index(&dir).into_iter().find(|e| e.id == id).map(|e| PathBuf::from(e.path))
+}
+
+// ── Tauri commands ─────────────────────────────────────────────────────────
+
+/// Store a base64 PNG capture in the history.
+#[tauri::command]
+pub fn save_capture(app_handle: tauri::AppHandle, image_base64: String) -> Result<CaptureEntry, AppError> {
+    let dir = captures_dir(&app_handle)?;
+    std::fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+
+    let bytes = general_purpose::STANDARD
+        .decode(image_base64.trim())
+        .map_err(|e| format!("Invalid base64 image: {}", e))?;
+    let (width, height) = image::load_from_memory(&bytes)
+        .map(|img| (img.width(), img.height()))
+        .map_err(|e| format!("Cannot decode capture: {}", e))?;
+
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default();
+    let id   = format!("cap-{}", now.as_millis());
+    let path = dir.join(format!("{}.png", id));
+    std::fs::write(&path, &bytes).map_err(|e| format!("Failed to save capture: {}", e))?;
+
+    let entry = CaptureEntry {
+        id,
+        path: path.to_string_lossy().to_string(),
+        created_at: now.as_secs(),
+        width,
+        height,
+        caption: None,
+        embedding: None,
+        embed_model: None,
+    };
+
+    let _guard = INDEX_LOCK.lock().map_err(|e| e.to_string())?;
+    let mut entries = load_index(&dir);
+    entries.push(entry.clone());
+    save_index(&dir, &entries)?;
+    Ok(entry)
+}
+
+/// All stored captures, newest first.
+#[tauri::command]
+pub fn list_captures(app_handle: tauri::AppHandle) -> Result<Vec<CaptureEntry>, AppError> {
+    let dir = captures_dir(&app_handle)?;
+    let mut entries = load_index(&dir);
+    entries.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+    Ok(entries.into_iter().map(public).collect())
+}
+
+/// Delete a capture file and its index entry.
+#[tauri::command]
+pub fn delete_capture(app_handle: tauri::AppHandle, id: String) -> Result<(), AppError> {
+    let dir = captures_dir(&app_handle)?;
+    let _guard = INDEX_LOCK.lock().map_err(|e| e.to_string())?;
+    let mut entries = load_index(&dir);
+    let Some(pos) = entries.iter().position(|e| e.id == id) else {
+        return Err(format!("Capture not found: {}", id).into());
+    };
+    let entry = entries.remove(pos);
+    let _ = std::fs::remove_file(&entry.path);
+    save_index(&dir, &entries).map_err(AppError::from)
+}
+
+/// Caption (and optionally embed) captures that have not been indexed yet.
+/// Returns the number of captures processed.
+#[tauri::command]
+pub async fn index_captures(app_handle: tauri::AppHandle, req: IndexCapturesRequest) -> Result<usize, AppError> {
+    let dir   = captures_dir(&app_handle)?;
+    let limit = req.limit.unwrap_or(20);
+
+    let pending: Vec<CaptureEntry> = load_index(&dir)
+        .into_iter()
+        .filter(|e| e.caption.is_none() || (req.embed_provider.is_some() && e.embedding.is_none()))
+        .take(limit)
+        .collect();
+
+    let mut processed: Vec<CaptureEntry> = Vec::new();
+    for mut entry in pending {
+        if entry.caption.is_none() {
+            let bytes = match std::fs::read(&entry.path) {
+                Ok(b)  => b,
+                Err(e) => { log::warn!("index_captures: {} unreadable: {}", entry.id, e); continue; }
+            };
+            match caption(&req, general_purpose::STANDARD.encode(&bytes)).await {
+                Ok(text) => entry.caption = Some(text),
+                Err(e)   => { log::warn!("index_captures: caption {} failed: {}", entry.id, e); continue; }
+            }
+        }
+        processed.push(entry);
+    }
+
+    if let Some(provider) = req.embed_provider.as_deref() {
+        let texts: Vec<String> = processed.iter().map(|e| e.caption.clone().unwrap_or_default()).collect();
+        let vectors = ai_bridge::embed(provider, req.embed_api_key.as_deref(), req.embed_model.as_deref(),
+                                       req.embed_url.as_deref(), &texts).await?;
+        for (entry, v) in processed.iter_mut().zip(vectors) {
+            entry.embedding   = Some(v);
+            entry.embed_model = embed_key(&req);
+        }
+    }
+
+    // Merge back under the lock — captures may have been added meanwhile
+    let _guard = INDEX_LOCK.lock().map_err(|e| e.to_string())?;
+    let mut entries = load_index(&dir);
+    for p in &processed {
+        if let Some(e) = entries.iter_mut().find(|e| e.id == p.id) {
+            *e = p.clone();
+        }
+    }
+    save_index(&dir, &entries)?;
+    log::info!("index_captures: {} captures indexed", processed.len());
+    Ok(processed.len())
+}
+
+/// Find captures matching a natural-language description.
+/// Uses embeddings when an embed provider is given and captures were embedded
+/// with the same model; otherwise falls back to keyword overlap on captions.
+#[tauri::command]
+pub async fn search_captures(app_handle: tauri::AppHandle, req: SearchCapturesRequest) -> Result<Vec<CaptureHit>, AppError> {
+    let dir     = captures_dir(&app_handle)?;
+    let max     = req.max_results.unwrap_or(10);
+    let entries = load_index(&dir);
+
+    let query_vec = match req.embed_provider.as_deref() {
+        Some(provider) => ai_bridge::embed(provider, req.embed_api_key.as_deref(), req.embed_model.as_deref(),
+                                           req.embed_url.as_deref(), &[req.query.clone()])
+            .await?
+            .into_iter()
+            .next(),
+        None => None,
+    };
+    let embed_model = req.embed_model.clone().or(req.embed_provider.clone());
+
+    let mut hits: Vec<CaptureHit> = entries.into_iter().filter_map(|e| {
+        let semantic = match (&query_vec, &e.embedding) {
+            (Some(q), Some(v)) if e.embed_model == embed_model => Some(ai_bridge::cosine_similarity(q, v)),
+            _ => None,
+        };
+        let score = semantic.unwrap_or_else(|| keyword_score(&req.query, e.caption.as_deref().unwrap_or("")));
+        (score > 0.0).then(|| CaptureHit { entry: public(e), score })
+    }).collect();
+
+    hits.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(std::cmp::Ordering::Equal));
+    hits.truncate(max);
+    Ok(hits)
+}
+
+// ── Helpers ────────────────────────────────────────────────────────────────
+
+const CAPTION_PROMPT: &str = "\
+Describe this screenshot so it can be found later by a text search. Mention the \
+application or website, visible windows and dialogs, any error or warning text \
+(quoted verbatim), prominent colors of UI elements, and the key visible text. \
+Plain prose, 2–4 sentences, no preamble.";
+
+/// Fraction of query words (≥ 3 chars) that appear in `text`.
+fn keyword_score(query: &str, text: &str) -> f32 {
+    let text = text.to_lowercase();
+    let words: Vec<String> = query
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| w.chars().count() >= 3)
+        .map(|w| w.to_lowercase())
+        .collect();
+    if words.is_empty() { return 0.0; }
+    let hits = words.iter().filter(|w| text.contains(w.as_str())).count();
+    hits as f32 / words.len() as f32
+}
+
+// ── Unit tests ─────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keyword_score() {
+        let caption = "VS Code window with a red error dialog: Cannot find module 'react'";
+        assert_eq!(keyword_score("red error dialog", caption), 1.0);
+        assert_eq!(keyword_score("blue terminal", caption), 0.0);
+        assert_eq!(keyword_score("a b", caption), 0.0);
+    }
+
+    #[test]
+    fn test_public_strips_embedding() {
+        let e = CaptureEntry {
+            id: "cap-1".into(), path: "/tmp/cap-1.png".into(), created_at: 0,
+            width: 1, height: 1, caption: None,
+            embedding: Some(vec![0.1, 0.2]), embed_model: None,
+        };
+        assert!(public(e).embedding.is_none());
+    }
+}
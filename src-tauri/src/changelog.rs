@@ -0,0 +1,324 @@
+// changelog.rs — changelog draft from git history
+//
+// Commits in the range are read through git.rs. Grouping is done here from
+// Conventional Commit headers (`type(scope)!: description`); the model only
+// rewrites each commit as a user-facing line and classifies commits that
+// don't follow the convention. Commits go to the model in chunks so large
+// ranges stay within the context window.
+//
+// Tauri commands exposed:
+//   generate_changelog → Changelog
+
+use crate::ai_bridge::{self, AiRequest};
+use crate::error::AppError;
+use crate::git::{self, Commit};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::Path;
+
+/// Characters of commit text per model call
+const CHUNK_CHARS: usize = 12_000;
+/// Patch excerpt per commit when diffs are included
+const DIFF_CHARS: usize  = 1_500;
+
+/// (conventional type, section title), in changelog order
+const SECTIONS: &[(&str, &str)] = &[
+    ("feat",     "Features"),
+    ("fix",      "Bug Fixes"),
+    ("perf",     "Performance"),
+    ("refactor", "Refactoring"),
+    ("docs",     "Documentation"),
+    ("other",    "Other Changes"),
+];
+/// Types that are left out of the changelog
+const HIDDEN_TYPES: &[&str] = &["chore", "ci", "build", "test", "style"];
+
+const INSTRUCTIONS: &str = "You write release notes. For each commit below, write one short \
+user-facing changelog line (imperative mood, no trailing period, no commit hash). If the \
+commit has no type, classify it as one of feat, fix, perf, refactor, docs, chore. Reply \
+with a JSON array only: [{\"hash\": \"<short hash>\", \"type\": \"...\", \"description\": \"...\"}]";
+
+// ── Types ──────────────────────────────────────────────────────────────────
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ChangelogRequest {
+    pub repo_path:     String,
+    /// Git revision range; None = latest tag..HEAD (whole history if untagged)
+    pub range:         Option<String>,
+    /// Heading for the draft ("v1.4.0"); defaults to "Unreleased"
+    pub version:       Option<String>,
+    /// Send a short patch excerpt with each commit (better lines, more tokens)
+    #[serde(default)]
+    pub include_diffs: bool,
+    pub provider:      String,
+    pub api_key:       String,
+    pub model:         Option<String>,
+    pub local_url:     Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ChangelogEntry {
+    pub description: String,
+    pub scope:       Option<String>,
+    pub hash:        String,
+    pub breaking:    bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ChangelogSection {
+    pub title:   String,
+    pub entries: Vec<ChangelogEntry>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Changelog {
+    pub range:    String,
+    pub version:  String,
+    /// "Breaking Changes" first (when present), then SECTIONS order
+    pub sections: Vec<ChangelogSection>,
+    pub markdown: String,
+    /// Commits read from git (merges and hidden types included)
+    pub commits:  usize,
+}
+
+/// A commit header split per the Conventional Commits spec.
+#[derive(Debug, Clone, PartialEq)]
+struct Conventional {
+    kind:        String,
+    scope:       Option<String>,
+    breaking:    bool,
+    description: String,
+}
+
+// ── Tauri command ──────────────────────────────────────────────────────────
+
+#[tauri::command]
+pub async fn generate_changelog(req: ChangelogRequest) -> Result<Changelog, AppError> {
+    let repo = Path::new(&req.repo_path);
+    let range = match req.range.as_deref().map(str::trim).filter(|r| !r.is_empty()) {
+        Some(r) => r.to_string(),
+        None    => match git::latest_tag(repo).await {
+            Some(tag) => format!("{}..HEAD", tag),
+            None      => "HEAD".to_string(),
+        },
+    };
+    let all = git::log(repo, &range).await?;
+    let commits: Vec<&Commit> = all.iter().filter(|c| !c.merge).collect();
+    if commits.is_empty() {
+        return Err(format!("No commits in {}", range).into());
+    }
+
+    // Commit text for the model, chunked
+    let mut blocks = Vec::with_capacity(commits.len());
+    for c in &commits {
+        let mut block = format!("[{}] {}", c.short, c.subject);
+        if !c.body.is_empty() {
+            block.push_str(&format!("\n{}", git::truncate(c.body.clone(), 600)));
+        }
+        if req.include_diffs {
+            block.push_str(&format!("\n{}", git::show(repo, &c.hash, DIFF_CHARS).await?));
+        }
+        blocks.push(block);
+    }
+    let mut rewritten: Vec<(String, String, String)> = Vec::new(); // (short hash, type, description)
+    for chunk in chunk_blocks(&blocks, CHUNK_CHARS) {
+        let ai_req = AiRequest {
+            api_key:       req.api_key.clone(),
+            prompt:        chunk.join("\n\n---\n\n"),
+            system_prompt: Some(INSTRUCTIONS.to_string()),
+            image_base64:  None,
+            context_files: None,
+            model:         req.model.clone(),
+            max_tokens:    Some(2_048),
+            allow_secrets: false,
+            profile:       None,
+        };
+        let resp = ai_bridge::complete(&req.provider, ai_req, req.local_url.as_deref()).await?;
+        rewritten.extend(parse_rewrites(&resp.text));
+    }
+
+    let sections = group(&commits, &rewritten);
+    let version = req.version.clone().filter(|v| !v.trim().is_empty()).unwrap_or_else(|| "Unreleased".into());
+    let markdown = render(&version, &sections);
+    log::info!("generate_changelog: {} commits in {} → {} sections", commits.len(), range, sections.len());
+    Ok(Changelog { range, version, sections, markdown, commits: all.len() })
+}
+
+// ── Grouping ───────────────────────────────────────────────────────────────
+
+/// Parse `type(scope)!: description`. None for free-form subjects.
+fn parse_conventional(subject: &str, body: &str) -> Option<Conventional> {
+    let (head, description) = subject.split_once(": ")?;
+    let (head, bang) = match head.strip_suffix('!') {
+        Some(h) => (h, true),
+        None    => (head, false),
+    };
+    let (kind, scope) = match head.split_once('(') {
+        Some((k, rest)) => (k, Some(rest.strip_suffix(')')?.trim().to_string())),
+        None            => (head, None),
+    };
+    if kind.is_empty() || !kind.chars().all(|c| c.is_ascii_alphabetic()) {
+        return None;
+    }
+    Some(Conventional {
+        kind:        kind.to_ascii_lowercase(),
+        scope:       scope.filter(|s| !s.is_empty()),
+        breaking:    bang || body.contains("BREAKING CHANGE:") || body.contains("BREAKING-CHANGE:"),
+        description: description.trim().to_string(),
+    })
+}
+
+/// Build sections from commits plus the model's rewrites. The conventional
+/// type always wins over the model's classification.
+fn group(commits: &[&Commit], rewritten: &[(String, String, String)]) -> Vec<ChangelogSection> {
+    let mut breaking = Vec::new();
+    let mut by_type: Vec<(&str, Vec<ChangelogEntry>)> = SECTIONS.iter().map(|(k, _)| (*k, Vec::new())).collect();
+
+    for c in commits {
+        let conv = parse_conventional(&c.subject, &c.body);
+        let rewrite = rewritten.iter().find(|(h, ..)| c.short.starts_with(h.as_str()) || h.starts_with(c.short.as_str()));
+        let kind = conv.as_ref().map(|v| v.kind.clone())
+            .or_else(|| rewrite.map(|(_, t, _)| t.to_ascii_lowercase()))
+            .unwrap_or_else(|| "other".into());
+        let is_breaking = conv.as_ref().map(|v| v.breaking).unwrap_or(false);
+        if HIDDEN_TYPES.contains(&kind.as_str()) && !is_breaking {
+            continue;
+        }
+        let description = rewrite.map(|(.., d)| d.clone())
+            .filter(|d| !d.trim().is_empty())
+            .or_else(|| conv.as_ref().map(|v| v.description.clone()))
+            .unwrap_or_else(|| c.subject.clone());
+        let entry = ChangelogEntry {
+            description,
+            scope:    conv.as_ref().and_then(|v| v.scope.clone()),
+            hash:     c.short.clone(),
+            breaking: is_breaking,
+        };
+
+        if is_breaking {
+            breaking.push(entry);
+            continue;
+        }
+        let slot = by_type.iter().position(|(k, _)| *k == kind).unwrap_or(by_type.len() - 1);
+        by_type[slot].1.push(entry);
+    }
+
+    let mut sections = Vec::new();
+    if !breaking.is_empty() {
+        sections.push(ChangelogSection { title: "Breaking Changes".into(), entries: breaking });
+    }
+    for ((_, entries), (_, title)) in by_type.into_iter().zip(SECTIONS) {
+        if !entries.is_empty() {
+            sections.push(ChangelogSection { title: title.to_string(), entries });
+        }
+    }
+    sections
+}
+
+fn render(version: &str, sections: &[ChangelogSection]) -> String {
+    let mut md = format!("## {}\n", version);
+    for s in sections {
+        md.push_str(&format!("\n### {}\n\n", s.title));
+        for e in &s.entries {
+            let scope = e.scope.as_deref().map(|s| format!("**{}:** ", s)).unwrap_or_default();
+            md.push_str(&format!("- {}{} ({})\n", scope, e.description, e.hash));
+        }
+    }
+    md
+}
+
+// ── Model I/O ──────────────────────────────────────────────────────────────
+
+/// Group consecutive blocks into chunks of at most `max` chars (a single
+/// oversized block gets a chunk of its own).
+fn chunk_blocks(blocks: &[String], max: usize) -> Vec<Vec<&str>> {
+    let mut chunks: Vec<Vec<&str>> = Vec::new();
+    let mut size = 0;
+    for b in blocks {
+        if chunks.is_empty() || (size + b.len() > max && size > 0) {
+            chunks.push(Vec::new());
+            size = 0;
+        }
+        chunks.last_mut().unwrap().push(b);
+        size += b.len();
+    }
+    chunks
+}
+
+/// Read the model's JSON array, tolerating code fences and surrounding prose.
+/// Anything unparseable yields no rewrites, so the commit subjects are used.
+fn parse_rewrites(text: &str) -> Vec<(String, String, String)> {
+    let (Some(start), Some(end)) = (text.find('['), text.rfind(']')) else { return vec![] };
+    if end < start {
+        return vec![];
+    }
+    let Ok(Value::Array(items)) = serde_json::from_str::<Value>(&text[start..=end]) else { return vec![] };
+    items.iter()
+        .filter_map(|i| Some((
+            i["hash"].as_str()?.trim().to_string(),
+            i["type"].as_str().unwrap_or("other").trim().to_string(),
+            i["description"].as_str()?.trim().to_string(),
+        )))
+        .filter(|(h, ..)| !h.is_empty())
+        .collect()
+}
+
+// ── Unit tests ─────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn commit(short: &str, subject: &str, body: &str) -> Commit {
+        Commit {
+            hash: format!("{}000", short), short: short.into(), author: "a".into(), date: String::new(),
+            subject: subject.into(), body: body.into(), merge: false,
+        }
+    }
+
+    #[test]
+    fn test_parse_conventional() {
+        let c = parse_conventional("feat(api)!: drop v1 endpoints", "").unwrap();
+        assert_eq!((c.kind.as_str(), c.scope.as_deref(), c.breaking), ("feat", Some("api"), true));
+        assert!(parse_conventional("fix: x", "BREAKING CHANGE: y").unwrap().breaking);
+        assert!(parse_conventional("Update README", "").is_none());
+        assert!(parse_conventional("Note: this is prose", "").is_some()); // indistinguishable by shape
+        assert!(parse_conventional("fix(core: oops", "").is_none());
+    }
+
+    #[test]
+    fn test_group_prefers_conventional_type() {
+        let commits = [
+            commit("a1", "feat(ui): add dark mode", ""),
+            commit("b2", "Fix crash when list is empty", ""),
+            commit("c3", "chore: bump deps", ""),
+            commit("d4", "refactor!: rename config keys", ""),
+        ];
+        let refs: Vec<&Commit> = commits.iter().collect();
+        let rewrites = vec![
+            ("a1".into(), "fix".into(), "Add a dark theme".into()),
+            ("b2".into(), "fix".into(), "Fix a crash on empty lists".into()),
+        ];
+        let sections = group(&refs, &rewrites);
+        let titles: Vec<&str> = sections.iter().map(|s| s.title.as_str()).collect();
+        assert_eq!(titles, ["Breaking Changes", "Features", "Bug Fixes"]);
+        assert_eq!(sections[1].entries[0].description, "Add a dark theme");
+        assert_eq!(sections[1].entries[0].scope.as_deref(), Some("ui"));
+        assert_eq!(sections[0].entries[0].description, "rename config keys");
+
+        let md = render("v1.0.0", &sections);
+        assert!(md.starts_with("## v1.0.0\n"));
+        assert!(md.contains("- **ui:** Add a dark theme (a1)"));
+    }
+
+    #[test]
+    fn test_parse_rewrites_and_chunks() {
+        let text = "```json\n[{\"hash\": \"a1\", \"type\": \"feat\", \"description\": \"Add X\"}, {\"type\": \"fix\"}]\n```";
+        assert_eq!(parse_rewrites(text), vec![("a1".into(), "feat".into(), "Add X".into())]);
+        assert!(parse_rewrites("no json here").is_empty());
+
+        let blocks: Vec<String> = vec!["a".repeat(6), "b".repeat(6), "c".repeat(20)];
+        let chunks = chunk_blocks(&blocks, 12);
+        assert_eq!(chunks.iter().map(|c| c.len()).collect::<Vec<_>>(), [2, 1]);
+    }
+}
@@ -1,13 +1,203 @@
-// clipboard.rs — read image from the system clipboard and return base64 PNG
+// clipboard.rs — system clipboard (images and text) with a Wayland fallback
+//
+// arboard covers Windows, macOS, X11 and Wayland compositors that expose the
+// data-control protocol. On other Wayland sessions (GNOME / Mutter, some
+// kiosk compositors) it fails or sees a stale XWayland clipboard, so the
+// wl-clipboard tools (wl-paste / wl-copy) are used instead. A probe run on
+// first use picks the backend; on Wayland a failed arboard call is also
+// retried through wl-clipboard when the tools are installed.
+//
+// Rich text is read as HTML, then RTF, then plain text, and converted to
+// Markdown (rich_text.rs). RTF needs wl-paste, xclip (X11) or pbpaste (macOS);
+// arboard has no RTF flavor.
+//
+// Tauri commands exposed:
+//   get_clipboard_image           → base64 PNG
+//   set_clipboard_image(image_base64)
+//   get_clipboard_text            → String
+//   get_clipboard_rich_text       → ClipboardText (Markdown + source flavor)
+//   set_clipboard_text(text)
+
+use crate::error::AppError;
+use crate::rich_text;
 use arboard::Clipboard;
 use base64::{engine::general_purpose, Engine};
-use image::{ImageBuffer, Rgba, ImageFormat};
+use image::{ImageBuffer, ImageFormat, Rgba};
+use serde::Serialize;
 use std::io::Cursor;
+use std::path::PathBuf;
+use std::process::Stdio;
+use std::sync::OnceLock;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+
+/// wl-paste blocks while the owning app serves the data; a hung owner
+/// shouldn't hang the command
+const WL_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Backend {
+    Arboard,
+    WlClipboard,
+}
+
+struct Probe {
+    backend:  Backend,
+    /// wl-paste / wl-copy usable as a fallback
+    wl_tools: bool,
+}
+
+static PROBE: OnceLock<Probe> = OnceLock::new();
+
+/// Clipboard text, converted to Markdown when it came as HTML or RTF.
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct ClipboardText {
+    /// "html" | "rtf" | "text" — the flavor it was read from
+    pub format: String,
+    pub text:   String,
+}
+
+// ── Tauri commands ─────────────────────────────────────────────────────────
 
 /// Read an image from the system clipboard.
 /// Returns a base64-encoded PNG string, or an error string.
 #[tauri::command]
-pub fn get_clipboard_image() -> Result<String, String> {
+pub async fn get_clipboard_image() -> Result<String, AppError> {
+    let png = with_fallback(
+        || blocking(arboard_get_image),
+        wl_get_image,
+    ).await?;
+    Ok(general_purpose::STANDARD.encode(png))
+}
+
+/// Put a base64 image (PNG, JPEG, … or a data: URL) on the clipboard.
+#[tauri::command]
+pub async fn set_clipboard_image(image_base64: String) -> Result<(), AppError> {
+    let b64 = image_base64.split_once("base64,").map_or(image_base64.as_str(), |(_, d)| d);
+    let bytes = general_purpose::STANDARD.decode(b64.trim()).map_err(|e| format!("Invalid base64 image: {e}"))?;
+    let png = to_png(&bytes)?;
+    with_fallback(
+        || { let png = png.clone(); blocking(move || arboard_set_image(&png)) },
+        || wl_copy(png.clone(), Some("image/png")),
+    ).await.map_err(AppError::from)
+}
+
+#[tauri::command]
+pub async fn get_clipboard_text() -> Result<String, AppError> {
+    with_fallback(
+        || blocking(|| {
+            Clipboard::new()
+                .and_then(|mut c| c.get_text())
+                .map_err(|e| format!("No text in clipboard: {e}"))
+        }),
+        wl_get_text,
+    ).await.map_err(AppError::from)
+}
+
+/// The richest text flavor on the clipboard as Markdown, falling back to
+/// plain text when there is no HTML / RTF or it converts to nothing.
+#[tauri::command]
+pub async fn get_clipboard_rich_text() -> Result<ClipboardText, AppError> {
+    let html = with_fallback(
+        || blocking(|| {
+            Clipboard::new()
+                .and_then(|mut c| c.get().html())
+                .map_err(|e| format!("No HTML in clipboard: {e}"))
+        }),
+        || wl_get_text_type(HTML_TYPES),
+    ).await;
+    if let Some(md) = html.ok().map(|h| rich_text::html_to_markdown(&h)).filter(|m| !m.is_empty()) {
+        return Ok(ClipboardText { format: "html".into(), text: md });
+    }
+
+    let rtf = with_fallback(|| blocking(platform_rtf), || wl_get_text_type(RTF_TYPES)).await;
+    if let Some(md) = rtf.ok().map(|r| rich_text::rtf_to_markdown(&r)).filter(|m| !m.is_empty()) {
+        return Ok(ClipboardText { format: "rtf".into(), text: md });
+    }
+
+    let text = get_clipboard_text().await?;
+    Ok(ClipboardText { format: "text".into(), text })
+}
+
+#[tauri::command]
+pub async fn set_clipboard_text(text: String) -> Result<(), AppError> {
+    with_fallback(
+        || { let text = text.clone(); blocking(move || {
+            Clipboard::new()
+                .and_then(|mut c| c.set_text(text))
+                .map_err(|e| format!("Clipboard write failed: {e}"))
+        }) },
+        || wl_copy(text.clone().into_bytes(), None),
+    ).await.map_err(AppError::from)
+}
+
+// ── Backend selection ──────────────────────────────────────────────────────
+
+fn probe() -> &'static Probe {
+    PROBE.get_or_init(|| {
+        let wayland = std::env::var_os("WAYLAND_DISPLAY").is_some()
+            || std::env::var("XDG_SESSION_TYPE").is_ok_and(|t| t == "wayland");
+        let wl_tools = wayland && tool_available("wl-paste") && tool_available("wl-copy");
+        let backend = choose(wayland, wl_tools, wayland && !arboard_usable());
+        println!("[clipboard] backend {:?} (wayland {}, wl-clipboard {})", backend, wayland, wl_tools);
+        Probe { backend, wl_tools }
+    })
+}
+
+/// wl-clipboard only on Wayland, only when installed, and only when arboard
+/// can't talk to the compositor.
+fn choose(wayland: bool, wl_tools: bool, arboard_broken: bool) -> Backend {
+    if wayland && wl_tools && arboard_broken { Backend::WlClipboard } else { Backend::Arboard }
+}
+
+/// An empty clipboard is fine; "not supported" / connection errors are not.
+fn arboard_usable() -> bool {
+    match Clipboard::new() {
+        Err(_) => false,
+        Ok(mut c) => !matches!(c.get_text(), Err(arboard::Error::ClipboardNotSupported | arboard::Error::Unknown { .. })),
+    }
+}
+
+fn tool_available(name: &str) -> bool {
+    std::process::Command::new(name)
+        .arg("--version")
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok_and(|s| s.success())
+}
+
+/// Runs the probed backend; on Wayland a failed arboard call gets a second
+/// try through wl-clipboard.
+async fn with_fallback<T, A, AF, W, WF>(arboard: A, wl: W) -> Result<T, String>
+where
+    A: FnOnce() -> AF,
+    AF: std::future::Future<Output = Result<T, String>>,
+    W: FnOnce() -> WF,
+    WF: std::future::Future<Output = Result<T, String>>,
+{
+    let probe = tokio::task::spawn_blocking(probe).await.map_err(|e| e.to_string())?;
+    if probe.backend == Backend::WlClipboard {
+        return wl().await;
+    }
+    match arboard().await {
+        Ok(v) => Ok(v),
+        Err(e) if probe.wl_tools => {
+            println!("[clipboard] arboard failed ({}), retrying with wl-clipboard", e);
+            wl().await
+        }
+        Err(e) => Err(e),
+    }
+}
+
+async fn blocking<T: Send + 'static>(f: impl FnOnce() -> Result<T, String> + Send + 'static) -> Result<T, String> {
+    tokio::task::spawn_blocking(f).await.map_err(|e| e.to_string())?
+}
+
+// ── arboard ────────────────────────────────────────────────────────────────
+
+fn arboard_get_image() -> Result<Vec<u8>, String> {
     let mut clipboard = Clipboard::new().map_err(|e| format!("Clipboard init failed: {e}"))?;
 
     let img_data = clipboard
@@ -28,7 +218,277 @@ pub fn get_clipboard_image() -> Result<String, String> {
     image::DynamicImage::ImageRgba8(img_buf)
         .write_to(&mut Cursor::new(&mut png_bytes), ImageFormat::Png)
         .map_err(|e| format!("PNG encode failed: {e}"))?;
+    Ok(png_bytes)
+}
+
+fn arboard_set_image(png: &[u8]) -> Result<(), String> {
+    let rgba = image::load_from_memory(png).map_err(|e| format!("Cannot decode image: {e}"))?.to_rgba8();
+    let data = arboard::ImageData {
+        width:  rgba.width() as usize,
+        height: rgba.height() as usize,
+        bytes:  rgba.into_raw().into(),
+    };
+    Clipboard::new()
+        .and_then(|mut c| c.set_image(data))
+        .map_err(|e| format!("Clipboard write failed: {e}"))
+}
+
+// ── wl-clipboard ───────────────────────────────────────────────────────────
+
+async fn wl_paste(args: &[&str]) -> Result<Vec<u8>, String> {
+    let out = tokio::time::timeout(WL_TIMEOUT, Command::new("wl-paste").args(args).kill_on_drop(true).output())
+        .await
+        .map_err(|_| "wl-paste timed out".to_string())?
+        .map_err(|e| format!("wl-paste: {e}"))?;
+    if !out.status.success() {
+        let err = String::from_utf8_lossy(&out.stderr).trim().to_string();
+        return Err(if err.contains("Nothing is copied") || err.contains("No selection") {
+            "Clipboard is empty".into()
+        } else {
+            format!("wl-paste: {}", err)
+        });
+    }
+    Ok(out.stdout)
+}
+
+async fn wl_get_image() -> Result<Vec<u8>, String> {
+    let types = String::from_utf8_lossy(&wl_paste(&["--list-types"]).await?).to_string();
+    let mime = pick_image_type(&types).ok_or_else(|| "No image in clipboard".to_string())?;
+    let bytes = wl_paste(&["--no-newline", "--type", &mime]).await?;
+    blocking(move || to_png(&bytes)).await
+}
+
+async fn wl_get_text() -> Result<String, String> {
+    let bytes = wl_paste(&["--no-newline", "--type", "text"]).await?;
+    String::from_utf8(bytes).map_err(|_| "Clipboard text is not valid UTF-8".to_string())
+}
+
+/// First of `mime_types` the clipboard offers, as text.
+async fn wl_get_text_type(mime_types: &[&str]) -> Result<String, String> {
+    let types = String::from_utf8_lossy(&wl_paste(&["--list-types"]).await?).to_string();
+    let mime = pick_type(&types, mime_types).ok_or_else(|| format!("No {} in clipboard", mime_types[0]))?;
+    Ok(decode_text(&wl_paste(&["--type", &mime]).await?))
+}
+
+/// wl-copy forks a server for the selection and exits once it has read stdin.
+async fn wl_copy(data: Vec<u8>, mime: Option<&str>) -> Result<(), String> {
+    let mut cmd = Command::new("wl-copy");
+    if let Some(m) = mime {
+        cmd.arg("--type").arg(m);
+    }
+    let mut child = cmd
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("wl-copy: {e}"))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(&data).await.map_err(|e| format!("wl-copy: {e}"))?;
+    }
+    let out = tokio::time::timeout(WL_TIMEOUT, child.wait_with_output())
+        .await
+        .map_err(|_| "wl-copy timed out".to_string())?
+        .map_err(|e| format!("wl-copy: {e}"))?;
+    if !out.status.success() {
+        return Err(format!("wl-copy: {}", String::from_utf8_lossy(&out.stderr).trim()));
+    }
+    Ok(())
+}
+
+// ── Copied files ───────────────────────────────────────────────────────────
+
+/// Files copied in a file manager (text/uri-list), for clipboard_ingest.rs.
+/// Read through wl-paste or xclip; other platforms report none.
+pub(crate) async fn get_file_list() -> Result<Vec<PathBuf>, String> {
+    let list = with_fallback(|| blocking(xclip_uri_list), || wl_get_text_type(URI_LIST_TYPES)).await?;
+    let paths = parse_uri_list(&list);
+    if paths.is_empty() {
+        return Err("No files in clipboard".into());
+    }
+    Ok(paths)
+}
+
+fn xclip_uri_list() -> Result<String, String> {
+    if !cfg!(target_os = "linux") {
+        return Err("No file list in clipboard".into());
+    }
+    let out = std::process::Command::new("xclip")
+        .args(["-selection", "clipboard", "-t", "text/uri-list", "-o"])
+        .stderr(Stdio::null())
+        .output()
+        .map_err(|e| format!("xclip: {e}"))?;
+    if !out.status.success() {
+        return Err("No file list in clipboard".into());
+    }
+    Ok(decode_text(&out.stdout))
+}
+
+/// Local paths of the `file://` URIs in a uri-list (GNOME's "copy" / "cut"
+/// header lines and comments are skipped).
+fn parse_uri_list(list: &str) -> Vec<PathBuf> {
+    list.lines()
+        .map(str::trim)
+        .filter_map(|l| l.strip_prefix("file://"))
+        .map(|rest| rest.strip_prefix("localhost").unwrap_or(rest))
+        .map(percent_decode)
+        .map(|p| {
+            // file:///C:/Users/… on Windows
+            let windows_drive = p.len() > 2 && p.as_bytes()[0] == b'/' && p.as_bytes()[2] == b':';
+            PathBuf::from(if windows_drive { &p[1..] } else { &p })
+        })
+        .collect()
+}
+
+fn percent_decode(s: &str) -> String {
+    let b = s.as_bytes();
+    let mut out = Vec::with_capacity(b.len());
+    let mut i = 0;
+    while i < b.len() {
+        let hex = b.get(i + 1..i + 3)
+            .filter(|_| b[i] == b'%')
+            .and_then(|h| std::str::from_utf8(h).ok())
+            .and_then(|h| u8::from_str_radix(h, 16).ok());
+        match hex {
+            Some(v) => { out.push(v); i += 3; }
+            None => { out.push(b[i]); i += 1; }
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+// ── RTF outside Wayland ────────────────────────────────────────────────────
+
+fn platform_rtf() -> Result<String, String> {
+    let attempts: &[(&str, &[&str])] = if cfg!(target_os = "macos") {
+        &[("pbpaste", &["-Prefer", "rtf"])]
+    } else if cfg!(target_os = "linux") {
+        &[
+            ("xclip", &["-selection", "clipboard", "-t", "text/rtf", "-o"]),
+            ("xclip", &["-selection", "clipboard", "-t", "application/rtf", "-o"]),
+        ]
+    } else {
+        &[]
+    };
+    for (cmd, args) in attempts {
+        let Ok(out) = std::process::Command::new(cmd).args(*args).stderr(Stdio::null()).output() else { continue };
+        let text = decode_text(&out.stdout);
+        // pbpaste falls back to plain text when there is no RTF
+        if out.status.success() && text.trim_start().starts_with("{\\rtf") {
+            return Ok(text);
+        }
+    }
+    Err("No RTF in clipboard".into())
+}
+
+// ── Helpers ────────────────────────────────────────────────────────────────
+
+const HTML_TYPES: &[&str] = &["text/html"];
+const RTF_TYPES: &[&str] = &["text/rtf", "application/rtf", "text/richtext"];
+const URI_LIST_TYPES: &[&str] = &["text/uri-list", "x-special/gnome-copied-files"];
+
+/// First of `wanted` in a `wl-paste --list-types` listing, parameters
+/// ("text/html;charset=utf-8") included.
+fn pick_type(types: &str, wanted: &[&str]) -> Option<String> {
+    wanted.iter().find_map(|w| {
+        types.lines().map(str::trim).find(|t| {
+            t.strip_prefix(w).is_some_and(|rest| rest.is_empty() || rest.starts_with(';'))
+        })
+    }).map(String::from)
+}
+
+/// Clipboard text bytes: UTF-8, or UTF-16 with a BOM (Firefox's text/html).
+fn decode_text(bytes: &[u8]) -> String {
+    let utf16 = |le: bool| {
+        let units: Vec<u16> = bytes[2..]
+            .chunks_exact(2)
+            .map(|c| if le { u16::from_le_bytes([c[0], c[1]]) } else { u16::from_be_bytes([c[0], c[1]]) })
+            .collect();
+        String::from_utf16_lossy(&units)
+    };
+    match bytes {
+        [0xFF, 0xFE, ..] => utf16(true),
+        [0xFE, 0xFF, ..] => utf16(false),
+        _ => String::from_utf8_lossy(bytes.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(bytes)).into_owned(),
+    }
+}
+
+/// The image MIME type to read from `wl-paste --list-types`, PNG first.
+fn pick_image_type(types: &str) -> Option<String> {
+    let images: Vec<&str> = types.lines().map(str::trim).filter(|t| t.starts_with("image/")).collect();
+    images.iter().find(|t| **t == "image/png").or(images.first()).map(|t| t.to_string())
+}
+
+fn to_png(bytes: &[u8]) -> Result<Vec<u8>, String> {
+    if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+        return Ok(bytes.to_vec());
+    }
+    let img = image::load_from_memory(bytes).map_err(|e| format!("Cannot decode clipboard image: {e}"))?;
+    let mut png = Vec::new();
+    img.write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+        .map_err(|e| format!("PNG encode failed: {e}"))?;
+    Ok(png)
+}
+
+// ── Unit tests ─────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_choose_backend() {
+        assert_eq!(choose(true, true, true), Backend::WlClipboard);
+        // arboard works, tools missing, or not Wayland at all → arboard
+        assert_eq!(choose(true, true, false), Backend::Arboard);
+        assert_eq!(choose(true, false, true), Backend::Arboard);
+        assert_eq!(choose(false, true, true), Backend::Arboard);
+    }
+
+    #[test]
+    fn test_pick_image_type() {
+        let types = "text/html\nimage/jpeg\nimage/png\nSAVE_TARGETS\n";
+        assert_eq!(pick_image_type(types).as_deref(), Some("image/png"));
+        assert_eq!(pick_image_type("image/bmp\ntext/plain").as_deref(), Some("image/bmp"));
+        assert_eq!(pick_image_type("text/plain;charset=utf-8\nUTF8_STRING"), None);
+    }
+
+    #[test]
+    fn test_pick_type_and_decode_text() {
+        let types = "text/plain\ntext/html;charset=utf-8\napplication/rtf\n";
+        assert_eq!(pick_type(types, HTML_TYPES).as_deref(), Some("text/html;charset=utf-8"));
+        assert_eq!(pick_type(types, RTF_TYPES).as_deref(), Some("application/rtf"));
+        assert_eq!(pick_type("text/htmlx", HTML_TYPES), None);
+
+        let mut utf16: Vec<u8> = vec![0xFF, 0xFE];
+        utf16.extend("<b>é</b>".encode_utf16().flat_map(u16::to_le_bytes));
+        assert_eq!(decode_text(&utf16), "<b>é</b>");
+        assert_eq!(decode_text("\u{feff}plain".as_bytes()), "plain");
+    }
+
+    #[test]
+    fn test_parse_uri_list() {
+        let list = "copy\nfile:///home/me/My%20Notes/todo.md\r\n# comment\nfile://localhost/tmp/a.rs\nhttps://x.y\nfile:///C:/Users/me/b.txt\n";
+        assert_eq!(
+            parse_uri_list(list),
+            vec![
+                PathBuf::from("/home/me/My Notes/todo.md"),
+                PathBuf::from("/tmp/a.rs"),
+                PathBuf::from("C:/Users/me/b.txt"),
+            ]
+        );
+        assert_eq!(percent_decode("%E2%9C%93 100%"), "✓ 100%");
+    }
 
-    let b64 = general_purpose::STANDARD.encode(&png_bytes);
-    Ok(b64)
+    #[test]
+    fn test_to_png_converts_other_formats() {
+        let mut jpeg = Vec::new();
+        image::DynamicImage::new_rgb8(5, 3)
+            .write_to(&mut Cursor::new(&mut jpeg), ImageFormat::Jpeg)
+            .unwrap();
+        let png = to_png(&jpeg).unwrap();
+        assert!(png.starts_with(b"\x89PNG"));
+        assert_eq!(image::load_from_memory(&png).unwrap().width(), 5);
+        assert_eq!(to_png(&png).unwrap(), png);
+        assert!(to_png(b"garbage").is_err());
+    }
 }
@@ -0,0 +1,329 @@
+// clipboard_ingest.rs — "paste as context": turn whatever is on the
+// clipboard into something the next prompt can use
+//
+// Detection order:
+//   1. files copied in a file manager → indexed like a project folder
+//      (a single image file → vision attachment instead)
+//   2. plain text that is a list of local paths → same as 1
+//   3. a lone http(s) URL → page text fetched (web_search.rs)
+//   4. text that looks like source code → fenced block with a language tag
+//   5. other text → Markdown, from the HTML / RTF flavor when there is one
+//   6. an image → vision attachment
+//
+// Tauri commands exposed:
+//   ingest_clipboard → ClipboardIngest (tagged by `kind`)
+
+use crate::error::AppError;
+use crate::project_indexer::{self, IndexedFile};
+use crate::{clipboard, web_search};
+use base64::{engine::general_purpose, Engine};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+/// Page text kept for a pasted URL
+const URL_MAX_CHARS: usize = 6_000;
+/// Files taken from one paste, across all copied folders
+const MAX_FILES: usize = 250;
+const IMAGE_EXTENSIONS: &[&str] = &["png", "jpg", "jpeg", "webp", "gif", "bmp"];
+
+// ── Types ──────────────────────────────────────────────────────────────────
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ClipboardIngest {
+    /// Base64 PNG / JPEG for the vision attachment
+    Image { image_base64: String },
+    /// Files to add to the index; paths are absolute
+    Files { files: Vec<IndexedFile>, skipped: usize },
+    /// `context` holds the page text, or just the URL when fetching failed
+    Url { url: String, context: String, fetch_error: Option<String> },
+    Code { language: Option<String>, context: String },
+    /// `format` is the flavor the Markdown came from: "html" | "rtf" | "text"
+    Text { format: String, context: String },
+}
+
+#[derive(Debug, PartialEq)]
+enum TextKind {
+    Paths(Vec<PathBuf>),
+    Url(String),
+    Code(Option<&'static str>),
+    Prose,
+}
+
+// ── Tauri command ──────────────────────────────────────────────────────────
+
+#[tauri::command]
+pub async fn ingest_clipboard() -> Result<ClipboardIngest, AppError> {
+    if let Ok(paths) = clipboard::get_file_list().await {
+        return ingest_paths(paths).await.map_err(AppError::from);
+    }
+
+    let plain = clipboard::get_clipboard_text().await.ok().filter(|t| !t.trim().is_empty());
+    if let Some(text) = plain {
+        match classify_text(&text, |p| p.exists()) {
+            TextKind::Paths(paths) => return ingest_paths(paths).await.map_err(AppError::from),
+            TextKind::Url(url) => return Ok(ingest_url(url).await),
+            TextKind::Code(language) => {
+                return Ok(ClipboardIngest::Code {
+                    language: language.map(String::from),
+                    context:  code_block(&text, language),
+                })
+            }
+            TextKind::Prose => {}
+        }
+        let rich = clipboard::get_clipboard_rich_text().await.ok().filter(|r| !r.text.trim().is_empty());
+        return Ok(match rich {
+            Some(r) => ClipboardIngest::Text { format: r.format, context: r.text },
+            None => ClipboardIngest::Text { format: "text".into(), context: text.trim().to_string() },
+        });
+    }
+
+    if let Ok(image_base64) = clipboard::get_clipboard_image().await {
+        return Ok(ClipboardIngest::Image { image_base64 });
+    }
+    // HTML with no plain-text flavor (some browsers' "Copy image")
+    match clipboard::get_clipboard_rich_text().await {
+        Ok(r) if !r.text.trim().is_empty() => Ok(ClipboardIngest::Text { format: r.format, context: r.text }),
+        _ => Err("Clipboard is empty".into()),
+    }
+}
+
+// ── Ingest per kind ────────────────────────────────────────────────────────
+
+async fn ingest_paths(paths: Vec<PathBuf>) -> Result<ClipboardIngest, String> {
+    if let [single] = paths.as_slice() {
+        if is_image(single) {
+            let bytes = tokio::fs::read(single).await.map_err(|e| format!("Cannot read {}: {}", single.display(), e))?;
+            return Ok(ClipboardIngest::Image { image_base64: general_purpose::STANDARD.encode(bytes) });
+        }
+    }
+    tokio::task::spawn_blocking(move || index_paths(&paths))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+fn index_paths(paths: &[PathBuf]) -> ClipboardIngest {
+    let mut files = Vec::new();
+    let mut skipped = 0;
+    for path in paths {
+        let candidates: Vec<PathBuf> = if path.is_dir() {
+            walkdir::WalkDir::new(path)
+                .follow_links(false)
+                .into_iter()
+                .filter_entry(|e| e.depth() == 0 || !project_indexer::is_ignored_dir(e.path()))
+                .filter_map(|e| e.ok())
+                .filter(|e| e.file_type().is_file())
+                .map(|e| e.into_path())
+                .collect()
+        } else {
+            vec![path.clone()]
+        };
+        for file in candidates {
+            if files.len() >= MAX_FILES {
+                skipped += 1;
+                continue;
+            }
+            let display = file.to_string_lossy().replace('\\', "/");
+            match project_indexer::index_file(&file, display) {
+                Some(f) => files.push(f),
+                None => skipped += 1,
+            }
+        }
+    }
+    ClipboardIngest::Files { files, skipped }
+}
+
+async fn ingest_url(url: String) -> ClipboardIngest {
+    match web_search::fetch_url_content(url.clone(), Some(URL_MAX_CHARS), Some(true)).await {
+        Ok(text) => ClipboardIngest::Url {
+            context: format!("Content of {}:\n\n{}", url, text.trim()),
+            url,
+            fetch_error: None,
+        },
+        Err(e) => ClipboardIngest::Url { context: url.clone(), url, fetch_error: Some(e.message) },
+    }
+}
+
+fn is_image(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| IMAGE_EXTENSIONS.contains(&e.to_ascii_lowercase().as_str()))
+}
+
+// ── Detection ──────────────────────────────────────────────────────────────
+
+/// What pasted text is. `exists` is the filesystem check (swapped in tests).
+fn classify_text(text: &str, exists: impl Fn(&Path) -> bool) -> TextKind {
+    let trimmed = text.trim();
+    let lines: Vec<&str> = trimmed.lines().map(str::trim).filter(|l| !l.is_empty()).collect();
+
+    let paths: Vec<PathBuf> = lines.iter().filter_map(|l| local_path(l)).collect();
+    if !paths.is_empty() && paths.len() == lines.len() && paths.iter().all(|p| exists(p)) {
+        return TextKind::Paths(paths);
+    }
+
+    if lines.len() == 1
+        && (trimmed.starts_with("http://") || trimmed.starts_with("https://"))
+        && !trimmed.contains(char::is_whitespace)
+    {
+        return TextKind::Url(trimmed.to_string());
+    }
+
+    if (trimmed.starts_with('{') || trimmed.starts_with('[')) && serde_json::from_str::<serde_json::Value>(trimmed).is_ok() {
+        return TextKind::Code(Some("json"));
+    }
+    let raw_lines: Vec<&str> = trimmed.lines().filter(|l| !l.trim().is_empty()).collect();
+    if looks_like_code(&raw_lines) {
+        return TextKind::Code(guess_language(trimmed));
+    }
+    TextKind::Prose
+}
+
+/// An absolute path or file:// URI on one line.
+fn local_path(line: &str) -> Option<PathBuf> {
+    let line = line.trim_matches(|c| c == '"' || c == '\'');
+    if let Some(rest) = line.strip_prefix("file://") {
+        return Some(PathBuf::from(rest.strip_prefix("localhost").unwrap_or(rest)));
+    }
+    let windows_drive = line.len() > 2 && line.as_bytes()[1] == b':' && matches!(line.as_bytes()[2], b'\\' | b'/');
+    (line.starts_with('/') || line.starts_with("~/") || windows_drive).then(|| {
+        match line.strip_prefix("~/") {
+            Some(rest) => std::env::var_os("HOME")
+                .or_else(|| std::env::var_os("USERPROFILE"))
+                .map_or_else(|| PathBuf::from(line), |h| PathBuf::from(h).join(rest)),
+            None => PathBuf::from(line),
+        }
+    })
+}
+
+/// Share of lines that are indented or carry code-only punctuation / keywords.
+fn looks_like_code(lines: &[&str]) -> bool {
+    const STARTS: &[&str] = &[
+        "fn ", "pub ", "let ", "const ", "use ", "impl ", "struct ", "enum ", "def ", "class ", "import ",
+        "from ", "return ", "if (", "for (", "while (", "function ", "export ", "package ", "func ",
+        "#include", "#!", "//", "/*", "SELECT ", "select ", "var ", "async ", "@", "}",
+    ];
+    if lines.is_empty() {
+        return false;
+    }
+    let code_lines = lines
+        .iter()
+        .filter(|raw| {
+            let l = raw.trim();
+            raw.starts_with("    ")
+                || raw.starts_with('\t')
+                || STARTS.iter().any(|s| l.starts_with(s))
+                || l.ends_with(';')
+                || l.ends_with('{')
+                || l.ends_with(':') && !l.contains(' ')
+                || l.contains("=>")
+                || l.contains("::")
+                || l.contains(" = ") && !l.ends_with('.')
+        })
+        .count();
+    if let [line] = lines {
+        let line = line.trim();
+        return code_lines == 1 && !line.ends_with('.') && line.split_whitespace().count() <= 12;
+    }
+    code_lines * 5 >= lines.len() * 2
+}
+
+/// Markdown fence language from tell-tale tokens; None when unsure.
+fn guess_language(code: &str) -> Option<&'static str> {
+    let has = |needles: &[&str]| needles.iter().any(|n| code.contains(n));
+    let first = code.lines().next().unwrap_or("").trim();
+    if first.starts_with("#!") {
+        return Some(if first.contains("python") { "python" } else if first.contains("node") { "javascript" } else { "bash" });
+    }
+    if has(&["fn ", "let mut ", "impl ", "pub struct", "::new(", "#[derive"]) {
+        return Some("rust");
+    }
+    if has(&["#include", "std::", "nullptr"]) {
+        return Some("cpp");
+    }
+    if has(&["package main", "func ", ":= "]) {
+        return Some("go");
+    }
+    if has(&["def ", "elif ", "self.", "import numpy", "print("]) && !code.contains(';') {
+        return Some("python");
+    }
+    if has(&["interface ", ": string", ": number", "import type", "<React."]) {
+        return Some("typescript");
+    }
+    if has(&["function ", "const ", "=> {", "console.log", "require("]) {
+        return Some("javascript");
+    }
+    if has(&["public class", "public static void", "System.out"]) {
+        return Some("java");
+    }
+    if has(&["SELECT ", "INSERT INTO", "CREATE TABLE"]) {
+        return Some("sql");
+    }
+    None
+}
+
+fn code_block(code: &str, language: Option<&str>) -> String {
+    // A longer fence than any run of backticks inside
+    let longest = code.split(|c| c != '`').map(str::len).max().unwrap_or(0);
+    let fence = "`".repeat(longest.max(2) + 1);
+    format!("{}{}\n{}\n{}", fence, language.unwrap_or(""), code.trim_matches('\n'), fence)
+}
+
+// ── Unit tests ─────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn classify(text: &str) -> TextKind {
+        classify_text(text, |p| p.starts_with("/home/me"))
+    }
+
+    #[test]
+    fn test_classify_paths_and_urls() {
+        assert_eq!(
+            classify("/home/me/notes.md\n/home/me/src\n"),
+            TextKind::Paths(vec![PathBuf::from("/home/me/notes.md"), PathBuf::from("/home/me/src")])
+        );
+        // one path that doesn't exist → not a file list
+        assert_eq!(classify("/home/me/a.md\n/etc/missing"), TextKind::Prose);
+        assert_eq!(classify("  https://example.com/docs?q=1 "), TextKind::Url("https://example.com/docs?q=1".into()));
+        assert_eq!(classify("see https://example.com for details"), TextKind::Prose);
+    }
+
+    #[test]
+    fn test_classify_code_and_prose() {
+        let rust = "fn main() {\n    let x = 5;\n    println!(\"{}\", x);\n}";
+        assert_eq!(classify(rust), TextKind::Code(Some("rust")));
+        let py = "def greet(name):\n    print(f\"hi {name}\")\n\ngreet('bob')";
+        assert_eq!(classify(py), TextKind::Code(Some("python")));
+        assert_eq!(classify("{\"a\": [1, 2]}"), TextKind::Code(Some("json")));
+        assert_eq!(classify("const total = items.reduce((a, b) => a + b, 0);"), TextKind::Code(Some("javascript")));
+        assert_eq!(
+            classify("Meeting moved to Thursday.\nPlease bring the quarterly numbers.\nThanks!"),
+            TextKind::Prose
+        );
+        assert_eq!(classify("The result x = 5 is correct."), TextKind::Prose);
+    }
+
+    #[test]
+    fn test_code_block_fence_outlasts_content() {
+        assert_eq!(code_block("let a = 1;\n", Some("rust")), "```rust\nlet a = 1;\n```");
+        assert_eq!(code_block("x = \"```\"", None), "````\nx = \"```\"\n````");
+    }
+
+    #[test]
+    fn test_index_paths_walks_folders() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("a.rs"), "fn a() {}").unwrap();
+        std::fs::write(dir.path().join("b.bin"), [0u8, 1, 2]).unwrap();
+        std::fs::create_dir(dir.path().join("node_modules")).unwrap();
+        std::fs::write(dir.path().join("node_modules").join("c.js"), "x").unwrap();
+        let ClipboardIngest::Files { files, skipped } = index_paths(&[dir.path().to_path_buf()]) else {
+            panic!("expected files")
+        };
+        assert_eq!(files.len(), 1);
+        assert!(files[0].path.ends_with("/a.rs"));
+        assert_eq!(skipped, 1);
+    }
+}
@@ -0,0 +1,312 @@
+// conversation.rs — chat-history maintenance for long sessions
+//
+// Tauri commands exposed:
+//   summarize_conversation → folds old turns into a compact summary once the
+//                            history exceeds a token budget, returns the
+//                            trimmed message list the frontend should keep
+//   generate_title         → short chat title from the first turns, using the
+//                            provider's cheapest model
+
+use crate::ai_bridge::{self, AiRequest};
+use crate::error::AppError;
+use serde::{Deserialize, Serialize};
+
+/// Marker that identifies a summary message produced by this module, so a
+/// later pass folds the previous summary into the new one instead of
+/// summarizing a summary of a summary.
+const SUMMARY_PREFIX: &str = "[Conversation summary]";
+
+const DEFAULT_MAX_CONTEXT_TOKENS: u32 = 24_000;
+const DEFAULT_KEEP_RECENT: usize      = 6;
+
+/// Messages / characters of the conversation shown to the title model
+const TITLE_MAX_MESSAGES: usize = 4;
+const TITLE_MAX_CHARS: usize    = 1_500;
+const TITLE_MAX_LEN: usize      = 60;
+
+/// Cheapest capable model per provider, used for titles and path summaries
+const CHEAP_MODELS: &[(&str, &str)] = &[
+    ("openai",     "gpt-4o-mini"),
+    ("claude",     "claude-3-5-haiku-latest"),
+    ("deepseek",   "deepseek-chat"),
+    ("openrouter", "openai/gpt-4o-mini"),
+    ("together",   "meta-llama/Llama-3.2-3B-Instruct-Turbo"),
+];
+
+// ── Types ──────────────────────────────────────────────────────────────────
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ChatMessage {
+    /// "system" | "user" | "assistant"
+    pub role:    String,
+    pub content: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SummarizeRequest {
+    pub messages:           Vec<ChatMessage>,
+    /// "openai" | "claude" | "deepseek" | "openrouter" | "together" | "local"
+    pub provider:           String,
+    pub api_key:            String,
+    pub model:              Option<String>,
+    /// Local server URL (local provider only)
+    pub local_url:          Option<String>,
+    /// Summarize once the estimated history size exceeds this (default 24 000)
+    pub max_context_tokens: Option<u32>,
+    /// Most recent messages always kept verbatim (default 6)
+    pub keep_recent:        Option<usize>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TitleRequest {
+    pub messages:   Vec<ChatMessage>,
+    pub provider:   String,
+    pub api_key:    String,
+    /// Used as-is for the local provider; cloud providers use CHEAP_MODELS
+    pub model:      Option<String>,
+    pub local_url:  Option<String>,
+    /// Lets the frontend cancel just this request (see cancel_ai_request)
+    pub request_id: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SummarizeResponse {
+    /// The new summary text (None when the history was under budget)
+    pub summary:          Option<String>,
+    /// Message list to use from now on: pinned system messages + summary + recent turns
+    pub messages:         Vec<ChatMessage>,
+    /// How many original messages were folded into the summary
+    pub summarized_count: usize,
+    pub tokens_before:    u32,
+    pub tokens_after:     u32,
+}
+
+// ── Tauri command ──────────────────────────────────────────────────────────
+
+/// Compact a long chat history. Returns the input unchanged when it already
+/// fits `max_context_tokens`.
+#[tauri::command]
+pub async fn summarize_conversation(req: SummarizeRequest) -> Result<SummarizeResponse, AppError> {
+    let budget = req.max_context_tokens.unwrap_or(DEFAULT_MAX_CONTEXT_TOKENS);
+    let keep   = req.keep_recent.unwrap_or(DEFAULT_KEEP_RECENT);
+    let tokens_before = estimate_messages_tokens(&req.messages);
+
+    let (pinned, older, recent) = split_for_summary(&req.messages, keep);
+    if tokens_before <= budget || older.is_empty() {
+        return Ok(SummarizeResponse {
+            summary:          None,
+            messages:         req.messages,
+            summarized_count: 0,
+            tokens_before,
+            tokens_after:     tokens_before,
+        });
+    }
+
+    let ai_req = AiRequest {
+        api_key:       req.api_key.clone(),
+        prompt:        transcript(older),
+        system_prompt: Some(SUMMARY_INSTRUCTIONS.to_string()),
+        image_base64:  None,
+        context_files: None,
+        model:         req.model.clone(),
+        max_tokens:    Some(800),
+        allow_secrets: false,
+        profile:       None,
+    };
+    let resp = ai_bridge::complete(&req.provider, ai_req, req.local_url.as_deref()).await?;
+    let summary = resp.text.trim().to_string();
+    if summary.is_empty() {
+        return Err("Summarization returned an empty response".into());
+    }
+
+    let mut messages = pinned.to_vec();
+    messages.push(ChatMessage {
+        role:    "system".into(),
+        content: format!("{}\n{}", SUMMARY_PREFIX, summary),
+    });
+    messages.extend_from_slice(recent);
+    let tokens_after = estimate_messages_tokens(&messages);
+
+    log::info!(
+        "summarize_conversation: {} msgs folded, ~{} → ~{} tokens",
+        older.len(), tokens_before, tokens_after
+    );
+
+    Ok(SummarizeResponse {
+        summary: Some(summary),
+        messages,
+        summarized_count: older.len(),
+        tokens_before,
+        tokens_after,
+    })
+}
+
+/// The provider's entry in CHEAP_MODELS, else `fallback` (local servers).
+pub(crate) fn cheap_model(provider: &str, fallback: Option<String>) -> Option<String> {
+    CHEAP_MODELS.iter()
+        .find(|(p, _)| *p == provider)
+        .map(|(_, m)| m.to_string())
+        .or(fallback)
+}
+
+/// Name a chat session. Runs alongside the main stream; cancelling it by
+/// `request_id` leaves the stream alone.
+#[tauri::command]
+pub async fn generate_title(req: TitleRequest) -> Result<String, AppError> {
+    let excerpt = title_excerpt(&req.messages);
+    if excerpt.trim().is_empty() {
+        return Err("Nothing to title yet".into());
+    }
+
+    let model = cheap_model(&req.provider, req.model.clone());
+    let ai_req = AiRequest {
+        api_key:       req.api_key.clone(),
+        prompt:        excerpt,
+        system_prompt: Some(TITLE_INSTRUCTIONS.to_string()),
+        image_base64:  None,
+        context_files: None,
+        model,
+        max_tokens:    Some(24),
+        allow_secrets: false,
+        profile:       None,
+    };
+    let resp = ai_bridge::with_request_id(
+        req.request_id.as_deref(),
+        ai_bridge::complete(&req.provider, ai_req, req.local_url.as_deref()),
+    ).await?;
+
+    let title = clean_title(&resp.text);
+    if title.is_empty() {
+        return Err("Title generation returned an empty response".into());
+    }
+    Ok(title)
+}
+
+// ── Helpers ────────────────────────────────────────────────────────────────
+
+const TITLE_INSTRUCTIONS: &str = "\
+Write a title for the conversation below: 3 to 6 words, in the language of the \
+conversation, no quotes, no trailing punctuation. Output only the title.";
+
+fn title_excerpt(messages: &[ChatMessage]) -> String {
+    let mut out = String::new();
+    for m in messages.iter().filter(|m| m.role != "system").take(TITLE_MAX_MESSAGES) {
+        out.push_str(&format!("{}: {}\n", m.role, m.content.trim()));
+    }
+    out.chars().take(TITLE_MAX_CHARS).collect()
+}
+
+/// First line, without "Title:" prefixes, quotes, Markdown or a final period.
+fn clean_title(raw: &str) -> String {
+    let line = raw.lines().map(str::trim).find(|l| !l.is_empty()).unwrap_or("");
+    let line = line.strip_prefix("Title:").or_else(|| line.strip_prefix("title:")).unwrap_or(line);
+    let line = line.trim().trim_matches(|c: char| matches!(c, '"' | '\'' | '«' | '»' | '*' | '#' | '`')).trim();
+    let line = line.trim_end_matches(['.', '!', '?', ':']);
+    match line.char_indices().nth(TITLE_MAX_LEN) {
+        Some((cut, _)) => format!("{}…", line[..cut].trim_end()),
+        None           => line.to_string(),
+    }
+}
+
+const SUMMARY_INSTRUCTIONS: &str = "\
+You compress chat histories. Summarize the conversation below so the assistant \
+can continue it without the original messages. Keep: the user's goals, decisions \
+made, facts and numbers, file names, code identifiers, commands, and any open \
+questions or TODOs. Drop pleasantries and repeated content. If the transcript \
+starts with an earlier summary, merge it in. Answer in the language of the \
+conversation, as terse bullet points, at most 250 words.";
+
+/// Rough token estimate (~4 chars per token, plus per-message overhead).
+/// Good enough for budget decisions without shipping a tokenizer.
+pub(crate) fn estimate_tokens(text: &str) -> u32 {
+    (text.chars().count() as u32).div_ceil(4)
+}
+
+fn estimate_messages_tokens(messages: &[ChatMessage]) -> u32 {
+    messages.iter().map(|m| estimate_tokens(&m.content) + 4).sum()
+}
+
+/// Split into (pinned, to summarize, kept verbatim).
+/// Leading system messages (persona, instructions) are pinned and never
+/// summarized away; a previous summary is not pinned so it gets merged.
+fn split_for_summary(
+    messages:    &[ChatMessage],
+    keep_recent: usize,
+) -> (&[ChatMessage], &[ChatMessage], &[ChatMessage]) {
+    let pinned = messages.iter()
+        .take_while(|m| m.role == "system" && !m.content.starts_with(SUMMARY_PREFIX))
+        .count();
+    let (pinned, rest) = messages.split_at(pinned);
+    let cut = rest.len().saturating_sub(keep_recent);
+    let (older, recent) = rest.split_at(cut);
+    (pinned, older, recent)
+}
+
+fn transcript(messages: &[ChatMessage]) -> String {
+    let mut out = String::from("Conversation transcript:\n\n");
+    for m in messages {
+        let content = m.content.strip_prefix(SUMMARY_PREFIX).map(str::trim).unwrap_or(&m.content);
+        let label = if m.content.starts_with(SUMMARY_PREFIX) { "EARLIER SUMMARY" } else { m.role.as_str() };
+        out.push_str(&format!("### {}\n{}\n\n", label.to_uppercase(), content));
+    }
+    out
+}
+
+// ── Unit tests ─────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn msg(role: &str, content: &str) -> ChatMessage {
+        ChatMessage { role: role.into(), content: content.into() }
+    }
+
+    #[test]
+    fn test_clean_title() {
+        assert_eq!(clean_title("Title: \"Fixing the Rust borrow checker error.\"\n"), "Fixing the Rust borrow checker error");
+        assert_eq!(clean_title("\n**Настройка Docker**"), "Настройка Docker");
+        assert!(clean_title(&"word ".repeat(30)).ends_with('…'));
+    }
+
+    #[test]
+    fn test_title_excerpt_skips_system() {
+        let msgs = vec![msg("system", "persona"), msg("user", "How do I bake bread?"), msg("assistant", "Mix flour")];
+        assert_eq!(title_excerpt(&msgs), "user: How do I bake bread?\nassistant: Mix flour\n");
+    }
+
+    #[test]
+    fn test_estimate_tokens() {
+        assert_eq!(estimate_tokens(""), 0);
+        assert_eq!(estimate_tokens("abcd"), 1);
+        assert_eq!(estimate_tokens("abcde"), 2);
+    }
+
+    #[test]
+    fn test_split_keeps_recent_and_pins_system() {
+        let mut msgs = vec![msg("system", "You are a pirate")];
+        msgs.extend((0..10).map(|i| msg("user", &i.to_string())));
+        let (pinned, older, recent) = split_for_summary(&msgs, 4);
+        assert_eq!(pinned.len(), 1);
+        assert_eq!(older.len(), 6);
+        assert_eq!(recent[0].content, "6");
+    }
+
+    #[test]
+    fn test_previous_summary_is_not_pinned() {
+        let msgs = vec![
+            msg("system", &format!("{}\n- old", SUMMARY_PREFIX)),
+            msg("user", "a"),
+            msg("assistant", "b"),
+        ];
+        let (pinned, older, _) = split_for_summary(&msgs, 1);
+        assert!(pinned.is_empty());
+        assert_eq!(older.len(), 2);
+    }
+
+    #[test]
+    fn test_transcript_marks_previous_summary() {
+        let t = transcript(&[msg("system", &format!("{}\n- user likes Rust", SUMMARY_PREFIX))]);
+        assert!(t.contains("### EARLIER SUMMARY\n- user likes Rust"));
+    }
+}
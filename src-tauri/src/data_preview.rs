@@ -0,0 +1,357 @@
+// data_preview.rs — header, column types and sample rows of tabular files
+//
+// Lets data questions carry a compact description of a CSV / TSV / JSONL
+// file instead of the whole file. Types are inferred from the first
+// INFER_ROWS records; the file is streamed, so size is not a concern.
+//
+// Tauri commands exposed:
+//   preview_data_file → DataPreview
+
+use crate::error::AppError;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::io::BufRead;
+use std::path::Path;
+
+const DEFAULT_SAMPLE_ROWS: usize = 10;
+const MAX_SAMPLE_ROWS: usize     = 200;
+/// Records examined for type inference
+const INFER_ROWS: usize          = 1_000;
+/// Cells longer than this are shortened in samples
+const MAX_CELL_CHARS: usize      = 200;
+
+// ── Types ──────────────────────────────────────────────────────────────────
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ColumnInfo {
+    pub name:     String,
+    /// "integer" | "float" | "boolean" | "date" | "datetime" | "string" |
+    /// "object" | "array" | "empty"
+    pub dtype:    String,
+    /// Some examined record had no value for this column
+    pub nullable: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DataPreview {
+    /// "csv" | "tsv" | "jsonl"
+    pub format:      String,
+    pub columns:     Vec<ColumnInfo>,
+    pub rows:        Vec<Vec<String>>,
+    /// Data records in the whole file (header excluded)
+    pub total_rows:  usize,
+    /// Compact Markdown description, ready to drop into a prompt
+    pub markdown:    String,
+}
+
+// ── Tauri command ──────────────────────────────────────────────────────────
+
+#[tauri::command]
+pub async fn preview_data_file(path: String, rows: Option<usize>) -> Result<DataPreview, AppError> {
+    let sample = rows.unwrap_or(DEFAULT_SAMPLE_ROWS).min(MAX_SAMPLE_ROWS);
+    tokio::task::spawn_blocking(move || preview(Path::new(&path), sample))
+        .await?
+        .map_err(AppError::from)
+}
+
+fn preview(path: &Path, sample: usize) -> Result<DataPreview, String> {
+    let file = std::fs::File::open(path)
+        .map_err(|e| format!("Cannot open '{}': {}", path.display(), e))?;
+    let reader = std::io::BufReader::new(file);
+    let ext = path.extension().and_then(|e| e.to_str()).unwrap_or("").to_lowercase();
+
+    let mut preview = match ext.as_str() {
+        "jsonl" | "ndjson" => preview_jsonl(reader, sample)?,
+        "tsv" | "tab"      => preview_delimited(reader, '\t', "tsv", sample)?,
+        "csv"              => preview_delimited(reader, ',', "csv", sample)?,
+        other => return Err(format!("Unsupported data file type '.{}' (csv, tsv, jsonl)", other)),
+    };
+    preview.markdown = to_markdown(path, &preview);
+    Ok(preview)
+}
+
+// ── Delimited text ─────────────────────────────────────────────────────────
+
+fn preview_delimited(reader: impl BufRead, delim: char, format: &str, sample: usize) -> Result<DataPreview, String> {
+    let mut records = Records { lines: reader.lines(), delim };
+    let header = records.next().ok_or("File is empty")??;
+
+    let mut inferred = vec![TypeAcc::default(); header.len()];
+    let mut rows = Vec::new();
+    let mut total = 0;
+    for record in records {
+        let record = record?;
+        if record.len() == 1 && record[0].is_empty() {
+            continue; // blank line
+        }
+        if total < INFER_ROWS {
+            for (i, acc) in inferred.iter_mut().enumerate() {
+                acc.add_text(record.get(i).map(String::as_str).unwrap_or(""));
+            }
+        }
+        if rows.len() < sample {
+            rows.push(record.iter().map(|c| shorten(c)).collect());
+        }
+        total += 1;
+    }
+
+    let columns = header.into_iter().zip(inferred)
+        .map(|(name, acc)| acc.finish(name))
+        .collect();
+    Ok(DataPreview { format: format.into(), columns, rows, total_rows: total, markdown: String::new() })
+}
+
+/// Iterator over RFC 4180 records; quoted fields may span lines.
+struct Records<L> {
+    lines: L,
+    delim: char,
+}
+
+impl<L: Iterator<Item = std::io::Result<String>>> Iterator for Records<L> {
+    type Item = Result<Vec<String>, String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut line = match self.lines.next()? {
+            Ok(l)  => l,
+            Err(e) => return Some(Err(e.to_string())),
+        };
+        loop {
+            match parse_record(&line, self.delim) {
+                Some(fields) => return Some(Ok(fields)),
+                // open quote — the field continues on the next line
+                None => match self.lines.next() {
+                    Some(Ok(more)) => { line.push('\n'); line.push_str(&more); }
+                    Some(Err(e))   => return Some(Err(e.to_string())),
+                    None           => return Some(Err("Unterminated quoted field at end of file".into())),
+                },
+            }
+        }
+    }
+}
+
+/// Split one record; None while a quoted field is still open.
+fn parse_record(line: &str, delim: char) -> Option<Vec<String>> {
+    let line = line.strip_suffix('\r').unwrap_or(line);
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if in_quotes && chars.peek() == Some(&'"') => { field.push('"'); chars.next(); }
+            '"' if in_quotes                              => in_quotes = false,
+            '"' if field.is_empty()                        => in_quotes = true,
+            c if c == delim && !in_quotes                  => fields.push(std::mem::take(&mut field)),
+            c                                              => field.push(c),
+        }
+    }
+    if in_quotes {
+        return None;
+    }
+    fields.push(field);
+    Some(fields)
+}
+
+// ── JSON Lines ─────────────────────────────────────────────────────────────
+
+fn preview_jsonl(reader: impl BufRead, sample: usize) -> Result<DataPreview, String> {
+    let mut names: Vec<String> = Vec::new();
+    let mut inferred: Vec<TypeAcc> = Vec::new();
+    let mut objects: Vec<serde_json::Map<String, Value>> = Vec::new();
+    let mut total = 0;
+
+    for (i, line) in reader.lines().enumerate() {
+        let line = line.map_err(|e| e.to_string())?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        total += 1;
+        if total > INFER_ROWS && objects.len() >= sample {
+            continue; // only counting from here on
+        }
+        let value: Value = serde_json::from_str(&line)
+            .map_err(|e| format!("Line {}: invalid JSON: {}", i + 1, e))?;
+        let Value::Object(obj) = value else {
+            return Err(format!("Line {}: expected a JSON object", i + 1));
+        };
+
+        if total <= INFER_ROWS {
+            for key in obj.keys() {
+                if !names.contains(key) {
+                    names.push(key.clone());
+                    // earlier records lacked this key
+                    inferred.push(TypeAcc { missing: total > 1, ..Default::default() });
+                }
+            }
+            for (name, acc) in names.iter().zip(inferred.iter_mut()) {
+                match obj.get(name) {
+                    Some(v) => acc.add_json(v),
+                    None    => acc.missing = true,
+                }
+            }
+        }
+        if objects.len() < sample {
+            objects.push(obj);
+        }
+    }
+
+    let rows = objects.iter()
+        .map(|obj| names.iter().map(|n| match obj.get(n) {
+            None | Some(Value::Null) => String::new(),
+            Some(Value::String(s))   => shorten(s),
+            Some(other)              => shorten(&other.to_string()),
+        }).collect())
+        .collect();
+    let columns = names.into_iter().zip(inferred).map(|(n, acc)| acc.finish(n)).collect();
+    Ok(DataPreview { format: "jsonl".into(), columns, rows, total_rows: total, markdown: String::new() })
+}
+
+// ── Type inference ─────────────────────────────────────────────────────────
+
+/// Narrowest type seen so far for one column.
+#[derive(Clone, Default)]
+struct TypeAcc {
+    dtype:   Option<&'static str>,
+    missing: bool,
+}
+
+impl TypeAcc {
+    fn add_text(&mut self, cell: &str) {
+        let cell = cell.trim();
+        if cell.is_empty() || cell.eq_ignore_ascii_case("null") || cell.eq_ignore_ascii_case("na") {
+            self.missing = true;
+            return;
+        }
+        self.merge(text_type(cell));
+    }
+
+    fn add_json(&mut self, value: &Value) {
+        let t = match value {
+            Value::Null      => { self.missing = true; return; }
+            Value::Bool(_)   => "boolean",
+            Value::Number(n) => if n.is_f64() { "float" } else { "integer" },
+            Value::String(s) => match text_type(s) {
+                t @ ("date" | "datetime") => t,
+                _                         => "string",
+            },
+            Value::Array(_)  => "array",
+            Value::Object(_) => "object",
+        };
+        self.merge(t);
+    }
+
+    fn merge(&mut self, t: &'static str) {
+        self.dtype = Some(match (self.dtype, t) {
+            (None, t)              => t,
+            (Some(a), b) if a == b => a,
+            (Some("integer"), "float") | (Some("float"), "integer") => "float",
+            (Some("date"), "datetime") | (Some("datetime"), "date") => "datetime",
+            _                      => "string",
+        });
+    }
+
+    fn finish(self, name: String) -> ColumnInfo {
+        ColumnInfo { name, dtype: self.dtype.unwrap_or("empty").into(), nullable: self.missing }
+    }
+}
+
+fn text_type(cell: &str) -> &'static str {
+    if cell.parse::<i64>().is_ok() {
+        "integer"
+    } else if cell.parse::<f64>().is_ok_and(|f| f.is_finite()) {
+        "float"
+    } else if matches!(cell.to_ascii_lowercase().as_str(), "true" | "false") {
+        "boolean"
+    } else if is_date(cell) {
+        if cell.len() > 10 { "datetime" } else { "date" }
+    } else {
+        "string"
+    }
+}
+
+/// `YYYY-MM-DD`, optionally followed by `T` or a space and a time.
+fn is_date(s: &str) -> bool {
+    let b = s.as_bytes();
+    b.len() >= 10
+        && b[..4].iter().all(u8::is_ascii_digit)
+        && b[4] == b'-' && b[5..7].iter().all(u8::is_ascii_digit)
+        && b[7] == b'-' && b[8..10].iter().all(u8::is_ascii_digit)
+        && (b.len() == 10 || ((b[10] == b'T' || b[10] == b' ') && b.len() >= 16 && b[13] == b':'))
+}
+
+fn shorten(cell: &str) -> String {
+    if cell.chars().count() <= MAX_CELL_CHARS {
+        return cell.to_string();
+    }
+    format!("{}…", cell.chars().take(MAX_CELL_CHARS).collect::<String>())
+}
+
+fn to_markdown(path: &Path, p: &DataPreview) -> String {
+    let name = path.file_name().map(|n| n.to_string_lossy()).unwrap_or_default();
+    let mut out = format!("Data file `{}` ({}, {} rows)\n\nColumns:\n", name, p.format, p.total_rows);
+    for c in &p.columns {
+        out.push_str(&format!("- {}: {}{}\n", c.name, c.dtype, if c.nullable { " (nullable)" } else { "" }));
+    }
+    if !p.rows.is_empty() {
+        let escape = |s: &str| s.replace('|', "\\|").replace('\n', " ");
+        out.push_str(&format!("\nFirst {} rows:\n\n", p.rows.len()));
+        out.push_str(&format!("| {} |\n", p.columns.iter().map(|c| escape(&c.name)).collect::<Vec<_>>().join(" | ")));
+        out.push_str(&format!("|{}\n", "---|".repeat(p.columns.len())));
+        for row in &p.rows {
+            out.push_str(&format!("| {} |\n", row.iter().map(|c| escape(c)).collect::<Vec<_>>().join(" | ")));
+        }
+    }
+    out
+}
+
+// ── Unit tests ─────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_record_quotes() {
+        assert_eq!(parse_record(r#"a,"b, c","say ""hi""""#, ','), Some(vec!["a".into(), "b, c".into(), r#"say "hi""#.into()]));
+        assert_eq!(parse_record(r#"1,"open"#, ','), None);
+        assert_eq!(parse_record("x\ty\r", '\t'), Some(vec!["x".into(), "y".into()]));
+    }
+
+    #[test]
+    fn test_csv_preview_infers_types() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("sales.csv");
+        std::fs::write(&path, "id,price,active,day,note\n\
+                               1,9.5,true,2024-01-02,\"multi\nline\"\n\
+                               2,10,false,2024-01-03,\n").unwrap();
+
+        let p = preview(&path, 1).unwrap();
+        let types: Vec<_> = p.columns.iter().map(|c| c.dtype.as_str()).collect();
+        assert_eq!(types, ["integer", "float", "boolean", "date", "string"]);
+        assert!(p.columns[4].nullable);
+        assert_eq!(p.total_rows, 2);
+        assert_eq!(p.rows, vec![vec!["1", "9.5", "true", "2024-01-02", "multi\nline"]]);
+        assert!(p.markdown.contains("- price: float"));
+    }
+
+    #[test]
+    fn test_jsonl_preview_unions_keys() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("events.jsonl");
+        std::fs::write(&path, "{\"a\":1,\"b\":\"x\"}\n\n{\"a\":2.5,\"c\":[1]}\n").unwrap();
+
+        let p = preview(&path, 10).unwrap();
+        let cols: Vec<_> = p.columns.iter().map(|c| (c.name.as_str(), c.dtype.as_str(), c.nullable)).collect();
+        assert_eq!(cols, [("a", "float", false), ("b", "string", true), ("c", "array", true)]);
+        assert_eq!(p.total_rows, 2);
+        assert_eq!(p.rows[1], vec!["2.5", "", "[1]"]);
+    }
+
+    #[test]
+    fn test_is_date() {
+        assert!(is_date("2024-05-01"));
+        assert!(is_date("2024-05-01T10:20:30Z"));
+        assert!(!is_date("2024/05/01"));
+        assert!(!is_date("20240501"));
+    }
+}
@@ -0,0 +1,328 @@
+// deep_research.rs — multi-round web research with a written report
+//
+// One web search answers simple questions; comparisons, "state of X" and
+// debugging questions need several rounds. deep_research repeats:
+//
+//   search → fetch the new pages → ask the model what is still missing
+//          → search again with the queries it suggests
+//
+// until the model reports the question covered or `max_iterations` rounds
+// have run, then has the model write an answer citing the pages as [n].
+// Each stage emits a `deep-research-progress` event so the UI can show
+// where the run is; cancel_ai_request(request_id) stops it.
+//
+// Tauri commands exposed:
+//   deep_research(req) → ResearchReport
+
+use crate::ai_bridge::{self, AiRequest};
+use crate::error::AppError;
+use crate::web_search::{self, SearchResult};
+use serde::{Deserialize, Serialize};
+use tauri::Manager;
+
+const DEFAULT_ITERATIONS: usize = 3;
+const MAX_ITERATIONS: usize = 6;
+/// Search results per query
+const RESULTS_PER_QUERY: usize = 5;
+/// Follow-up queries the model may ask for per round
+const QUERIES_PER_ROUND: usize = 3;
+/// Pages fetched per round
+const PAGES_PER_ROUND: usize = 5;
+/// Characters of each page shown to the model during gap analysis
+const ANALYSIS_EXCERPT: usize = 1_200;
+/// Characters of each page shown to the model for the final answer
+const SYNTHESIS_EXCERPT: usize = 3_000;
+/// Total page text in the final prompt
+const SYNTHESIS_BUDGET: usize = 36_000;
+
+const GAP_INSTRUCTIONS: &str = "You direct a web research process. Given a question and \
+the sources found so far, decide whether they answer the question fully. Reply with JSON \
+only: {\"done\": true|false, \"missing\": \"<what is still unknown>\", \"queries\": [\"<up to \
+3 short web search queries that would find it>\"]}";
+
+const SYNTHESIS_INSTRUCTIONS: &str = "You write research answers from numbered web sources. \
+Answer the question in markdown using only the sources. Cite every claim with the source \
+number in brackets, e.g. [2]. Say so when the sources disagree or leave something open. Do \
+not add a source list — it is appended automatically.";
+
+// ── Types ──────────────────────────────────────────────────────────────────
+
+#[derive(Debug, Deserialize)]
+pub struct DeepResearchRequest {
+    pub query:          String,
+    /// Search → analyze rounds before writing the answer (default 3, max 6)
+    pub max_iterations: Option<usize>,
+    /// Search backend and its settings, as in WebSearchRequest
+    pub backend:        String,
+    pub search_api_key: Option<String>,
+    pub base_url:       Option<String>,
+    #[serde(default)]
+    pub market:         Option<String>,
+    /// LLM that analyzes and writes, as in ai_bridge::complete
+    pub provider:       String,
+    pub api_key:        String,
+    pub model:          Option<String>,
+    pub local_url:      Option<String>,
+    /// Lets the frontend cancel the run (see cancel_ai_request); also tags
+    /// the progress events
+    pub request_id:     Option<String>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct ResearchProgress {
+    pub request_id:     Option<String>,
+    /// 1-based round
+    pub iteration:      usize,
+    pub max_iterations: usize,
+    /// "searching" | "reading" | "analyzing" | "writing" | "done"
+    pub stage:          &'static str,
+    /// Human-readable line for the UI
+    pub detail:         String,
+    pub sources:        usize,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ResearchSource {
+    /// Citation number used in the answer
+    pub n:     usize,
+    pub title: String,
+    pub url:   String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ResearchReport {
+    /// Markdown answer with [n] citations and a trailing source list
+    pub answer:     String,
+    pub sources:    Vec<ResearchSource>,
+    /// Every query that was searched, in order
+    pub queries:    Vec<String>,
+    pub iterations: usize,
+}
+
+/// The model's verdict after a round.
+#[derive(Debug, Deserialize, Default, PartialEq)]
+struct GapAnalysis {
+    #[serde(default)]
+    done:    bool,
+    #[serde(default)]
+    missing: String,
+    #[serde(default)]
+    queries: Vec<String>,
+}
+
+/// A source with the page text read for it.
+struct Page {
+    source: ResearchSource,
+    text:   String,
+}
+
+// ── Tauri command ──────────────────────────────────────────────────────────
+
+#[tauri::command]
+pub async fn deep_research(app_handle: tauri::AppHandle, req: DeepResearchRequest) -> Result<ResearchReport, AppError> {
+    if req.query.trim().is_empty() {
+        return Err("Research question is empty".into());
+    }
+    ai_bridge::with_request_id(req.request_id.as_deref(), research(&app_handle, &req)).await
+}
+
+// ── Research loop ──────────────────────────────────────────────────────────
+
+async fn research(app: &tauri::AppHandle, req: &DeepResearchRequest) -> Result<ResearchReport, AppError> {
+    let max_iterations = req.max_iterations.unwrap_or(DEFAULT_ITERATIONS).clamp(1, MAX_ITERATIONS);
+    let progress = |iteration: usize, stage: &'static str, detail: String, sources: usize| {
+        let _ = app.emit_all("deep-research-progress", ResearchProgress {
+            request_id: req.request_id.clone(),
+            iteration,
+            max_iterations,
+            stage,
+            detail,
+            sources,
+        });
+    };
+
+    let mut pages: Vec<Page> = Vec::new();
+    let mut searched: Vec<String> = Vec::new();
+    let mut queries = vec![req.query.trim().to_string()];
+    let mut iterations = 0;
+
+    for iteration in 1..=max_iterations {
+        iterations = iteration;
+        progress(iteration, "searching", queries.join(" | "), pages.len());
+        let found = search_all(req, &queries).await;
+        searched.append(&mut queries);
+
+        let fresh: Vec<SearchResult> = found
+            .into_iter()
+            .filter(|r| !pages.iter().any(|p| same_url(&p.source.url, &r.url)))
+            .take(PAGES_PER_ROUND)
+            .collect();
+        if fresh.is_empty() && !pages.is_empty() {
+            log::info!("deep_research: round {} found nothing new", iteration);
+            break;
+        }
+
+        progress(iteration, "reading", format!("{} new pages", fresh.len()), pages.len());
+        let plan = web_search::FetchPlan { count: fresh.len(), ..Default::default() };
+        for r in web_search::fetch_results_content(fresh, plan).await {
+            let n = pages.len() + 1;
+            let text = r.content.filter(|c| !c.trim().is_empty()).unwrap_or(r.snippet);
+            pages.push(Page { source: ResearchSource { n, title: r.title, url: r.url }, text });
+        }
+        if iteration == max_iterations {
+            break;
+        }
+
+        progress(iteration, "analyzing", "Checking what is still missing".into(), pages.len());
+        let gap = analyze_gaps(req, &pages).await?;
+        if gap.done {
+            break;
+        }
+        queries = gap.queries
+            .into_iter()
+            .map(|q| q.trim().to_string())
+            .filter(|q| !q.is_empty() && !searched.iter().any(|s| s.eq_ignore_ascii_case(q)))
+            .take(QUERIES_PER_ROUND)
+            .collect();
+        if queries.is_empty() {
+            break;
+        }
+        log::info!("deep_research: round {} missing \"{}\" → {:?}", iteration, gap.missing, queries);
+    }
+
+    if pages.is_empty() {
+        return Err("Web search found no sources for this question".into());
+    }
+    progress(iterations, "writing", format!("Writing the answer from {} sources", pages.len()), pages.len());
+    let answer = synthesize(req, &pages).await?;
+    let sources: Vec<ResearchSource> = pages.into_iter().map(|p| p.source).collect();
+    progress(iterations, "done", format!("{} sources, {} queries", sources.len(), searched.len()), sources.len());
+
+    Ok(ResearchReport {
+        answer: format!("{}\n\n{}", answer.trim(), render_sources(&sources)),
+        sources,
+        queries: searched,
+        iterations,
+    })
+}
+
+/// Results of all `queries`, interleaved; a failing query only logs.
+async fn search_all(req: &DeepResearchRequest, queries: &[String]) -> Vec<SearchResult> {
+    let searches = queries.iter().map(|q| {
+        web_search::dispatch_search(
+            &req.backend, q, req.search_api_key.as_deref(), req.base_url.as_deref(),
+            req.market.as_deref(), RESULTS_PER_QUERY, 1,
+        )
+    });
+    let mut lists = Vec::new();
+    for (q, res) in queries.iter().zip(futures_util::future::join_all(searches).await) {
+        match res {
+            Ok(r)  => lists.push(r.results),
+            Err(e) => log::warn!("deep_research: search '{}' failed: {}", q, e),
+        }
+    }
+    web_search::merge_results(lists, RESULTS_PER_QUERY * queries.len())
+}
+
+async fn analyze_gaps(req: &DeepResearchRequest, pages: &[Page]) -> Result<GapAnalysis, AppError> {
+    let prompt = format!("Question: {}\n\nSources so far:\n\n{}", req.query.trim(), source_blocks(pages, ANALYSIS_EXCERPT, usize::MAX));
+    let text = ask(req, prompt, GAP_INSTRUCTIONS, 400).await?;
+    // A reply that isn't the requested JSON ends the loop rather than the run
+    Ok(parse_gap_analysis(&text).unwrap_or(GapAnalysis { done: true, ..Default::default() }))
+}
+
+async fn synthesize(req: &DeepResearchRequest, pages: &[Page]) -> Result<String, AppError> {
+    let prompt = format!("Question: {}\n\nSources:\n\n{}", req.query.trim(), source_blocks(pages, SYNTHESIS_EXCERPT, SYNTHESIS_BUDGET));
+    ask(req, prompt, SYNTHESIS_INSTRUCTIONS, 2_048).await
+}
+
+async fn ask(req: &DeepResearchRequest, prompt: String, instructions: &str, max_tokens: u32) -> Result<String, AppError> {
+    let ai_req = AiRequest {
+        api_key:       req.api_key.clone(),
+        prompt,
+        system_prompt: Some(instructions.to_string()),
+        image_base64:  None,
+        context_files: None,
+        model:         req.model.clone(),
+        max_tokens:    Some(max_tokens),
+        allow_secrets: false,
+        profile:       None,
+    };
+    Ok(ai_bridge::complete(&req.provider, ai_req, req.local_url.as_deref()).await?.text)
+}
+
+// ── Prompt / report text ───────────────────────────────────────────────────
+
+/// `[n] title (url)` + excerpt per page, `excerpt` chars each, at most
+/// `budget` chars of page text in total.
+fn source_blocks(pages: &[Page], excerpt: usize, budget: usize) -> String {
+    let mut left = budget;
+    let mut blocks = Vec::with_capacity(pages.len());
+    for p in pages {
+        let take = excerpt.min(left);
+        let text: String = p.text.chars().take(take).collect();
+        left = left.saturating_sub(text.chars().count());
+        blocks.push(format!("[{}] {} ({})\n{}", p.source.n, p.source.title, p.source.url, text.trim()));
+    }
+    blocks.join("\n\n")
+}
+
+/// The JSON object in the model's reply, tolerating prose or code fences
+/// around it.
+fn parse_gap_analysis(text: &str) -> Option<GapAnalysis> {
+    let start = text.find('{')?;
+    let end = text.rfind('}')?;
+    serde_json::from_str(text.get(start..=end)?).ok()
+}
+
+fn render_sources(sources: &[ResearchSource]) -> String {
+    let mut out = String::from("## Sources\n");
+    for s in sources {
+        out.push_str(&format!("\n{}. [{}]({})", s.n, s.title.replace(['[', ']'], ""), s.url));
+    }
+    out
+}
+
+fn same_url(a: &str, b: &str) -> bool {
+    a.trim_end_matches('/').eq_ignore_ascii_case(b.trim_end_matches('/'))
+}
+
+// ── Unit tests ─────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn page(n: usize, text: &str) -> Page {
+        Page {
+            source: ResearchSource { n, title: format!("Page {}", n), url: format!("https://example.com/{}", n) },
+            text:   text.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_parse_gap_analysis() {
+        let reply = "Sure:\n```json\n{\"done\": false, \"missing\": \"benchmarks\", \"queries\": [\"tokio vs async-std benchmark\"]}\n```";
+        assert_eq!(parse_gap_analysis(reply), Some(GapAnalysis {
+            done:    false,
+            missing: "benchmarks".into(),
+            queries: vec!["tokio vs async-std benchmark".into()],
+        }));
+        assert_eq!(parse_gap_analysis("{\"done\": true}").map(|g| g.done), Some(true));
+        assert_eq!(parse_gap_analysis("I think we are done."), None);
+    }
+
+    #[test]
+    fn test_source_blocks_respect_budget() {
+        let pages = [page(1, "abcdef"), page(2, "ghijkl"), page(3, "mnopqr")];
+        let text = source_blocks(&pages, 4, 6);
+        assert_eq!(text, "[1] Page 1 (https://example.com/1)\nabcd\n\n[2] Page 2 (https://example.com/2)\ngh\n\n[3] Page 3 (https://example.com/3)\n");
+    }
+
+    #[test]
+    fn test_render_sources() {
+        let sources = [ResearchSource { n: 1, title: "[RFC] Async".into(), url: "https://a.dev".into() }];
+        assert_eq!(render_sources(&sources), "## Sources\n\n1. [RFC Async](https://a.dev)");
+        assert!(same_url("https://a.dev/x/", "https://A.dev/x"));
+    }
+}
@@ -0,0 +1,175 @@
+// disk.rs — free-space lookups and pre-flight checks
+//
+// Downloads, archive extraction and generations call `ensure_space` before
+// they write, so a full disk fails up front with the sizes involved instead
+// of mid-stream with "No space left on device (os error 28)".
+// Free space is read with GetDiskFreeSpaceExW on Windows and `df -Pk`
+// elsewhere.
+//
+// Tauri commands exposed:
+//   get_disk_usage(path) → DiskUsage for the volume holding `path`
+
+use crate::error::AppError;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+/// Kept free on top of what an operation needs — the OS, swap and the
+/// .part → final rename all want some room
+const HEADROOM: u64 = 256 * 1024 * 1024;
+
+#[derive(Debug, Serialize, Clone, PartialEq, Eq)]
+pub struct DiskUsage {
+    /// The existing directory that was measured (nearest ancestor of the request)
+    pub path:        String,
+    pub total_bytes: u64,
+    pub free_bytes:  u64,
+    pub used_bytes:  u64,
+}
+
+/// Usage of the volume holding `path`; a path that doesn't exist yet is
+/// measured at its nearest existing ancestor.
+pub(crate) fn usage(path: &Path) -> Result<DiskUsage, String> {
+    let dir = existing_ancestor(path)
+        .ok_or_else(|| format!("Cannot find a directory for {}", path.display()))?;
+    let (total, free) = platform::space(&dir)?;
+    Ok(DiskUsage {
+        path:        dir.to_string_lossy().to_string(),
+        total_bytes: total,
+        free_bytes:  free,
+        used_bytes:  total.saturating_sub(free),
+    })
+}
+
+/// Fails when writing `needed` bytes under `path` would leave less than
+/// HEADROOM free. `what` names the data in the error ("the model", …).
+/// An unreadable volume is let through — the write itself will tell.
+pub(crate) fn ensure_space(path: &Path, needed: u64, what: &str) -> Result<(), String> {
+    let u = match usage(path) {
+        Ok(u) => u,
+        Err(e) => {
+            println!("[disk] cannot check free space at {}: {}", path.display(), e);
+            return Ok(());
+        }
+    };
+    check(&u, needed, what)
+}
+
+#[tauri::command]
+pub async fn get_disk_usage(path: String) -> Result<DiskUsage, AppError> {
+    tokio::task::spawn_blocking(move || usage(Path::new(&path)))
+        .await?
+        .map_err(AppError::from)
+}
+
+// ── Pure helpers ───────────────────────────────────────────────────────────
+
+fn check(u: &DiskUsage, needed: u64, what: &str) -> Result<(), String> {
+    if u.free_bytes >= needed.saturating_add(HEADROOM) {
+        return Ok(());
+    }
+    Err(format!(
+        "Not enough disk space in {}: {} needs {} but only {} is free. \
+         Free up space or choose a folder on another drive.",
+        u.path, what, human(needed.saturating_add(HEADROOM)), human(u.free_bytes),
+    ))
+}
+
+fn existing_ancestor(path: &Path) -> Option<PathBuf> {
+    path.ancestors().find(|p| p.is_dir()).map(Path::to_path_buf)
+}
+
+fn human(bytes: u64) -> String {
+    const GB: f64 = 1024.0 * 1024.0 * 1024.0;
+    let b = bytes as f64;
+    if b >= GB { format!("{:.1} GB", b / GB) } else { format!("{:.0} MB", b / 1_048_576.0) }
+}
+
+/// `df -Pk` output: header, then "fs 1024-blocks used available capacity mount".
+/// Both the filesystem name and the mount point may contain spaces, so the
+/// numbers are located relative to the "NN%" capacity field.
+#[cfg_attr(target_os = "windows", allow(dead_code))]
+fn parse_df(text: &str) -> Option<(u64, u64)> {
+    let line = text.lines().nth(1)?;
+    let fields: Vec<&str> = line.split_whitespace().collect();
+    let cap = fields.iter().rposition(|f| f.ends_with('%') && f[..f.len() - 1].parse::<u8>().is_ok())?;
+    let total: u64 = fields.get(cap.checked_sub(3)?)?.parse().ok()?;
+    let avail: u64 = fields.get(cap - 1)?.parse().ok()?;
+    Some((total * 1024, avail * 1024))
+}
+
+// ═══════════════════════════════════════════════════════════════════════
+// Windows — GetDiskFreeSpaceExW
+// ═══════════════════════════════════════════════════════════════════════
+#[cfg(target_os = "windows")]
+mod platform {
+    use std::os::windows::ffi::OsStrExt;
+    use std::path::Path;
+    use windows::core::PCWSTR;
+    use windows::Win32::Storage::FileSystem::GetDiskFreeSpaceExW;
+
+    /// (total, free to the current user) in bytes
+    pub fn space(dir: &Path) -> Result<(u64, u64), String> {
+        let wide: Vec<u16> = dir.as_os_str().encode_wide().chain(std::iter::once(0)).collect();
+        let mut free = 0u64;
+        let mut total = 0u64;
+        unsafe { GetDiskFreeSpaceExW(PCWSTR(wide.as_ptr()), Some(&mut free as *mut u64), Some(&mut total as *mut u64), None) }
+            .map_err(|e| e.to_string())?;
+        Ok((total, free))
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════
+// macOS / Linux — df
+// ═══════════════════════════════════════════════════════════════════════
+#[cfg(not(target_os = "windows"))]
+mod platform {
+    use std::path::Path;
+    use std::process::Command;
+
+    /// (total, available to unprivileged users) in bytes
+    pub fn space(dir: &Path) -> Result<(u64, u64), String> {
+        let out = Command::new("df").arg("-Pk").arg(dir).output().map_err(|e| format!("df: {}", e))?;
+        if !out.status.success() {
+            return Err(format!("df: {}", String::from_utf8_lossy(&out.stderr).trim()));
+        }
+        super::parse_df(&String::from_utf8_lossy(&out.stdout))
+            .ok_or_else(|| "df: unexpected output".to_string())
+    }
+}
+
+// ── Unit tests ─────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_df() {
+        let linux = "Filesystem     1024-blocks      Used Available Capacity Mounted on\n\
+                     /dev/nvme0n1p2   490691512 312345678 153319384      68% /\n";
+        assert_eq!(parse_df(linux), Some((490691512 * 1024, 153319384 * 1024)));
+        // Mount points can contain spaces
+        let mac = "Filesystem 1024-blocks Used Available Capacity Mounted on\n\
+                   /dev/disk4s1 1000 400 600 40% /Volumes/My Models\n";
+        assert_eq!(parse_df(mac), Some((1000 * 1024, 600 * 1024)));
+        assert_eq!(parse_df("Filesystem\n"), None);
+    }
+
+    #[test]
+    fn test_check_reports_sizes() {
+        let gb = 1024 * 1024 * 1024;
+        let u = DiskUsage { path: "/models".into(), total_bytes: 100 * gb, free_bytes: 2 * gb, used_bytes: 98 * gb };
+        assert!(check(&u, gb, "the model").is_ok());
+        let err = check(&u, 6 * gb, "the model").unwrap_err();
+        assert!(err.contains("/models") && err.contains("the model") && err.contains("2.0 GB"), "{}", err);
+    }
+
+    #[test]
+    fn test_usage_walks_up_to_existing_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        let missing = dir.path().join("a/b/c.safetensors");
+        assert_eq!(existing_ancestor(&missing).as_deref(), Some(dir.path()));
+        let u = usage(&missing).unwrap();
+        assert!(u.total_bytes >= u.free_bytes);
+    }
+}
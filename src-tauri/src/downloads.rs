@@ -0,0 +1,688 @@
+// downloads.rs — shared fetcher for large artifacts (SD binaries, models)
+//
+// Every component that pulls hundreds of MB goes through `fetch`:
+//   • the body streams to `<file>.part`, never into RAM
+//   • servers that honour Range get the file in parallel segments; segment
+//     offsets are kept in `<file>.part.json`, so an interrupted download
+//     continues where each segment stopped (a bare .part from a single-stream
+//     download resumes too)
+//   • free space is checked against the size before the first byte lands
+//   • an expected SHA-256 is checked before the .part is renamed into place —
+//     a mismatch deletes it so the next attempt starts clean
+//   • a paused download drops its connections; resuming re-probes the URL
+//     and carries on from the segment map like a restart would
+//   • one global bandwidth limit is shared by all running downloads
+//   • progress goes out as a single `download-progress` schema, tagged with
+//     the component that started it
+//
+// Tauri commands exposed:
+//   list_downloads     → Vec<DownloadProgress> (downloads in flight)
+//   cancel_download    → stops a download, keeping the .part for a later resume
+//   pause_download     → stops the transfer but keeps the download listed
+//   resume_download    → continues a paused download from its .part
+//   set_download_limit → global cap in bytes/s (None or 0 = unlimited)
+//
+// Events emitted:
+//   download-progress  → DownloadProgress
+
+use crate::disk;
+use crate::error::AppError;
+use futures_util::StreamExt;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, AtomicU8, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tauri::Manager;
+
+/// Segments smaller than this aren't worth an extra connection
+const MIN_SEGMENT: u64        = 8 * 1024 * 1024;
+pub(crate) const DEFAULT_SEGMENTS: usize = 4;
+const MAX_RETRIES: u32        = 3;
+const EMIT_EVERY: Duration    = Duration::from_millis(250);
+
+/// Download control states: segment tasks stream only while RUNNING
+const RUNNING: u8          = 0;
+const PAUSED: u8           = 1;
+const CANCEL_REQUESTED: u8 = 2;
+/// A segment failed; the other segments stop too
+const FAILED: u8           = 3;
+
+static ACTIVE: Mutex<BTreeMap<String, Active>> = Mutex::new(BTreeMap::new());
+/// Global cap in bytes/s; 0 = unlimited
+static LIMIT: AtomicU64 = AtomicU64::new(0);
+/// Start of the current pacing window and the bytes let through since
+static PACE: Mutex<Option<(Instant, u64)>> = Mutex::new(None);
+
+// ── Types ──────────────────────────────────────────────────────────────────
+
+/// What to fetch and where to put it.
+pub(crate) struct Download {
+    pub url:       String,
+    /// Final path; the data lands in `<dest>.part` until it is complete
+    pub dest:      PathBuf,
+    /// Which part of the app asked — "sd-binary", "sd-model", …
+    pub component: &'static str,
+    /// Sent only to the host of `url`, never to a redirect target
+    pub bearer:    Option<String>,
+    /// Lower- or upper-case hex
+    pub sha256:    Option<String>,
+    pub segments:  usize,
+}
+
+/// What a 1-byte Range request tells about the file.
+#[derive(Debug, Clone)]
+pub(crate) struct Probe {
+    pub status:    reqwest::StatusCode,
+    /// URL after redirects
+    pub final_url: String,
+    pub total:     Option<u64>,
+    pub ranges:    bool,
+    /// From Content-Disposition, unsanitized
+    pub file_name: Option<String>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct DownloadProgress {
+    /// "<component>:<file>"
+    pub id:            String,
+    pub component:     String,
+    pub file:          String,
+    /// "downloading" | "paused" | "verifying" | "done" | "failed" | "cancelled"
+    pub state:         &'static str,
+    /// Human-readable line for the UI
+    pub status:        String,
+    /// 0-100; 0 while the size is unknown
+    pub progress:      u8,
+    pub downloaded:    u64,
+    pub total:         Option<u64>,
+    pub bytes_per_sec: u64,
+    pub segments:      usize,
+    /// The server honours Range, so pausing keeps what is already on disk
+    pub resumable:     bool,
+}
+
+struct Active {
+    control: Arc<AtomicU8>,
+    last:    DownloadProgress,
+}
+
+/// One byte range of the file; `end` is exclusive.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+struct Segment {
+    start: u64,
+    end:   u64,
+    done:  u64,
+}
+
+impl Segment {
+    fn finished(&self) -> bool {
+        self.finished_with(self.done)
+    }
+
+    fn finished_with(&self, done: u64) -> bool {
+        self.start + done >= self.end
+    }
+}
+
+/// Contents of `<file>.part.json`.
+#[derive(Debug, Serialize, Deserialize)]
+struct PartState {
+    url:      String,
+    total:    u64,
+    segments: Vec<Segment>,
+}
+
+// ── Public helpers ─────────────────────────────────────────────────────────
+
+/// Client for artifact downloads: long connect timeout and keep-alive, but NO
+/// total-request timeout — a 500 MB archive or a 20 GB checkpoint would be
+/// cut off mid-stream.
+pub(crate) fn client() -> Result<reqwest::Client, String> {
+    reqwest::Client::builder()
+        .user_agent("ai-assistant/0.1")
+        .connect_timeout(Duration::from_secs(30))
+        .tcp_keepalive(Duration::from_secs(30))
+        .build()
+        .map_err(|e| e.to_string())
+}
+
+/// `<path>.part`
+pub(crate) fn part_path(path: &Path) -> PathBuf {
+    suffixed(path, ".part")
+}
+
+/// Asks for the first byte: a 206 with Content-Range gives the size and
+/// proves Range support without downloading anything. (HEAD is not used —
+/// presigned S3 / CDN links are often signed for GET only.)
+pub(crate) async fn probe(client: &reqwest::Client, url: &str, bearer: Option<&str>) -> Result<Probe, String> {
+    let mut req = client.get(url).header(reqwest::header::RANGE, "bytes=0-0");
+    if let Some(t) = bearer { req = req.bearer_auth(t); }
+    let resp = req.send().await.map_err(|e| format!("Download failed: {}", e))?;
+    let headers = resp.headers();
+    let header = |name: reqwest::header::HeaderName| headers.get(name).and_then(|h| h.to_str().ok());
+
+    let content_range_total = header(reqwest::header::CONTENT_RANGE).and_then(content_range_total);
+    let ranges = resp.status() == reqwest::StatusCode::PARTIAL_CONTENT && content_range_total.is_some();
+    Ok(Probe {
+        status:    resp.status(),
+        final_url: resp.url().to_string(),
+        total:     if ranges { content_range_total } else { resp.content_length() },
+        ranges,
+        file_name: header(reqwest::header::CONTENT_DISPOSITION).and_then(disposition_name),
+    })
+}
+
+/// Downloads `dl.url` to `dl.dest` and returns the path. Resumes a previous
+/// partial download of the same file; fails if the same file is already
+/// being downloaded.
+pub(crate) async fn fetch(app: &tauri::AppHandle, client: &reqwest::Client, dl: Download) -> Result<PathBuf, String> {
+    let file = dl.dest.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+    let id = format!("{}:{}", dl.component, file);
+    let control = Arc::new(AtomicU8::new(RUNNING));
+    let mut progress = DownloadProgress {
+        id: id.clone(), component: dl.component.to_string(), file: file.clone(),
+        state: "downloading", status: format!("Connecting to {}…", host_of(&dl.url)),
+        progress: 0, downloaded: 0, total: None, bytes_per_sec: 0, segments: 1, resumable: false,
+    };
+    {
+        let mut active = ACTIVE.lock().map_err(|e| e.to_string())?;
+        if active.contains_key(&id) {
+            return Err(format!("{} is already downloading", file));
+        }
+        active.insert(id.clone(), Active { control: control.clone(), last: progress.clone() });
+    }
+    report(app, &progress);
+
+    let result = loop {
+        let result = run(app, client, &dl, &control, &mut progress).await;
+        if !matches!(&result, Err(e) if e == CANCELLED) || control.load(Ordering::SeqCst) != PAUSED {
+            break result;
+        }
+        progress.state = "paused";
+        progress.bytes_per_sec = 0;
+        progress.status = format!("Paused {} at {}%", file, progress.progress);
+        report(app, &progress);
+        while control.load(Ordering::SeqCst) == PAUSED {
+            tokio::time::sleep(EMIT_EVERY).await;
+        }
+        if control.load(Ordering::SeqCst) != RUNNING {
+            break Err(CANCELLED.to_string());
+        }
+        progress.state = "downloading";
+        progress.status = format!("Resuming {}…", file);
+        report(app, &progress);
+    };
+    match &result {
+        Ok(_) => {
+            progress.state = "done";
+            progress.progress = 100;
+            progress.bytes_per_sec = 0;
+            progress.status = format!("{} downloaded", file);
+        }
+        Err(e) => {
+            progress.state = if e == CANCELLED { "cancelled" } else { "failed" };
+            progress.bytes_per_sec = 0;
+            progress.status = e.clone();
+        }
+    }
+    report(app, &progress);
+    if let Ok(mut active) = ACTIVE.lock() {
+        active.remove(&id);
+    }
+    result
+}
+
+// ── Tauri commands ─────────────────────────────────────────────────────────
+
+#[tauri::command]
+pub fn list_downloads() -> Vec<DownloadProgress> {
+    ACTIVE.lock().map(|a| a.values().map(|d| d.last.clone()).collect()).unwrap_or_default()
+}
+
+/// The .part (and its segment map) stay on disk; starting the same download
+/// again resumes it.
+#[tauri::command]
+pub fn cancel_download(id: String) -> Result<(), AppError> {
+    let active = ACTIVE.lock().map_err(|e| e.to_string())?;
+    let d = active.get(&id).ok_or_else(|| format!("No download {}", id))?;
+    d.control.store(CANCEL_REQUESTED, Ordering::SeqCst);
+    Ok(())
+}
+
+/// Only for servers with Range support — anything else would start over.
+#[tauri::command]
+pub fn pause_download(id: String) -> Result<(), AppError> {
+    let active = ACTIVE.lock().map_err(|e| e.to_string())?;
+    let d = active.get(&id).ok_or_else(|| format!("No download {}", id))?;
+    if !d.last.resumable {
+        return Err(format!("{} can't be paused — the server does not support resuming", d.last.file).into());
+    }
+    d.control.compare_exchange(RUNNING, PAUSED, Ordering::SeqCst, Ordering::SeqCst)
+        .map_err(|_| format!("{} is not downloading", d.last.file))?;
+    Ok(())
+}
+
+#[tauri::command]
+pub fn resume_download(id: String) -> Result<(), AppError> {
+    let active = ACTIVE.lock().map_err(|e| e.to_string())?;
+    let d = active.get(&id).ok_or_else(|| format!("No download {}", id))?;
+    d.control.compare_exchange(PAUSED, RUNNING, Ordering::SeqCst, Ordering::SeqCst)
+        .map_err(|_| format!("{} is not paused", d.last.file))?;
+    Ok(())
+}
+
+#[tauri::command]
+pub fn set_download_limit(bytes_per_sec: Option<u64>) {
+    LIMIT.store(bytes_per_sec.unwrap_or(0), Ordering::Relaxed);
+    if let Ok(mut p) = PACE.lock() {
+        *p = None;
+    }
+}
+
+// ── Download loop ──────────────────────────────────────────────────────────
+
+async fn run(
+    app:      &tauri::AppHandle,
+    client:   &reqwest::Client,
+    dl:       &Download,
+    control:  &Arc<AtomicU8>,
+    progress: &mut DownloadProgress,
+) -> Result<PathBuf, String> {
+    if let Some(dir) = dl.dest.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| format!("Cannot create {}: {}", dir.display(), e))?;
+    }
+    let part = part_path(&dl.dest);
+    let state_path = suffixed(&part, ".json");
+
+    let probe = probe(client, &dl.url, dl.bearer.as_deref()).await?;
+    let status = probe.status;
+    if status == reqwest::StatusCode::UNAUTHORIZED || status == reqwest::StatusCode::FORBIDDEN {
+        return Err(format!("Access denied (HTTP {})", status));
+    }
+    if !status.is_success() {
+        return Err(format!("Download failed: HTTP {}", status));
+    }
+    if let Some(total) = probe.total {
+        // Bytes already in a resumable .part don't need new room
+        let have = if probe.ranges { std::fs::metadata(&part).map_or(0, |m| m.len()) } else { 0 };
+        disk::ensure_space(&dl.dest, total.saturating_sub(have), &progress.file)?;
+    }
+    // Segments go straight to the redirect target; the token must not follow
+    let bearer = dl.bearer.clone().filter(|_| host_of(&probe.final_url) == host_of(&dl.url));
+    let url = probe.final_url.clone();
+
+    let segments = match (probe.ranges, probe.total) {
+        (true, Some(total)) => {
+            let saved = std::fs::read_to_string(&state_path).ok()
+                .and_then(|s| serde_json::from_str::<PartState>(&s).ok())
+                .filter(|s| s.total == total && part.exists());
+            let part_len = std::fs::metadata(&part).map_or(0, |m| m.len());
+            let segments = match saved {
+                Some(s) => s.segments,
+                // A .part without a segment map was written front to back
+                None if part_len > 0 && part_len <= total => vec![Segment { start: 0, end: total, done: part_len }],
+                None => {
+                    let _ = std::fs::remove_file(&part);
+                    split(total, dl.segments)
+                }
+            };
+            let f = std::fs::OpenOptions::new().create(true).write(true).truncate(false).open(&part)
+                .map_err(|e| format!("Cannot write {}: {}", part.display(), e))?;
+            f.set_len(total).map_err(|e| format!("Cannot write {}: {}", part.display(), e))?;
+            segments
+        }
+        // No Range support: one stream from the start, nothing to resume
+        _ => {
+            std::fs::File::create(&part).map_err(|e| format!("Cannot write {}: {}", part.display(), e))?;
+            let _ = std::fs::remove_file(&state_path);
+            vec![Segment { start: 0, end: probe.total.unwrap_or(u64::MAX), done: 0 }]
+        }
+    };
+    let ranged = probe.ranges && probe.total.is_some();
+    progress.resumable = ranged;
+    let resumed: u64 = segments.iter().map(|s| s.done).sum();
+    if resumed > 0 {
+        println!("[downloads] Resuming {} at {:.1} MB", progress.file, resumed as f64 / 1_048_576.0);
+    }
+
+    progress.total = probe.total;
+    progress.segments = segments.iter().filter(|s| !s.finished()).count().max(1);
+    let done: Arc<Vec<AtomicU64>> = Arc::new(segments.iter().map(|s| AtomicU64::new(s.done)).collect());
+    let mut handles: Vec<_> = segments.iter().enumerate()
+        .filter(|(_, s)| !s.finished())
+        .map(|(i, s)| Some(tokio::spawn(segment_task(
+            client.clone(), url.clone(), bearer.clone(), part.clone(),
+            s.clone(), i, ranged, done.clone(), control.clone(),
+        ))))
+        .collect();
+
+    // ── Report progress and persist the segment map until every task ends ──
+    let mut error: Option<String> = None;
+    let mut last_bytes = resumed;
+    let mut last_tick = Instant::now();
+    let mut speed = 0f64;
+    while handles.iter().any(Option::is_some) {
+        tokio::time::sleep(EMIT_EVERY).await;
+        for slot in handles.iter_mut() {
+            if slot.as_ref().is_some_and(|h| h.is_finished()) {
+                let outcome = slot.take().unwrap().await
+                    .unwrap_or_else(|e| Err(format!("Download task failed: {}", e)));
+                if let Err(e) = outcome {
+                    // One broken segment stops the rest; the map keeps their progress
+                    let _ = control.compare_exchange(RUNNING, FAILED, Ordering::SeqCst, Ordering::SeqCst);
+                    error.get_or_insert(e);
+                }
+            }
+        }
+        let downloaded: u64 = done.iter().map(|d| d.load(Ordering::Relaxed)).sum();
+        let dt = last_tick.elapsed().as_secs_f64().max(0.001);
+        speed = 0.7 * speed + 0.3 * ((downloaded - last_bytes) as f64 / dt);
+        last_bytes = downloaded;
+        last_tick = Instant::now();
+
+        progress.downloaded = downloaded;
+        progress.bytes_per_sec = speed as u64;
+        progress.progress = percent(downloaded, probe.total);
+        progress.status = status_line(&progress.file, downloaded, probe.total, progress.bytes_per_sec);
+        report(app, progress);
+        if ranged {
+            save_state(&state_path, &url, probe.total.unwrap_or(0), &segments, &done);
+        }
+    }
+    if let Some(e) = error {
+        // Without a segment map a cancelled single stream can't be resumed
+        if e == CANCELLED && !ranged {
+            let _ = std::fs::remove_file(&part);
+        }
+        return Err(e);
+    }
+
+    let downloaded: u64 = done.iter().map(|d| d.load(Ordering::Relaxed)).sum();
+    if let Some(t) = probe.total {
+        if downloaded != t {
+            return Err(format!("Download incomplete ({} of {} bytes) — run it again to resume", downloaded, t));
+        }
+    }
+
+    if let Some(expected) = dl.sha256.as_deref().map(|h| h.trim().to_ascii_lowercase()).filter(|h| !h.is_empty()) {
+        progress.state = "verifying";
+        progress.status = format!("Verifying {}…", progress.file);
+        progress.bytes_per_sec = 0;
+        report(app, progress);
+        let p = part.clone();
+        let actual = tokio::task::spawn_blocking(move || sha256_file(&p)).await
+            .map_err(|e| e.to_string())??;
+        if actual != expected {
+            let _ = std::fs::remove_file(&part);
+            let _ = std::fs::remove_file(&state_path);
+            return Err(format!(
+                "Checksum mismatch for {} (expected {}, got {}) — the corrupted file was removed, download it again",
+                progress.file, expected, actual,
+            ));
+        }
+    }
+
+    std::fs::rename(&part, &dl.dest).map_err(|e| e.to_string())?;
+    let _ = std::fs::remove_file(&state_path);
+    println!("[downloads] {} → {:?}", progress.id, dl.dest);
+    Ok(dl.dest.clone())
+}
+
+pub(crate) const CANCELLED: &str = "Download cancelled — start it again to resume";
+
+/// Streams one segment into its slice of the .part, retrying dropped
+/// connections from where it stopped.
+#[allow(clippy::too_many_arguments)]
+async fn segment_task(
+    client: reqwest::Client,
+    url:    String,
+    bearer: Option<String>,
+    part:   PathBuf,
+    seg:    Segment,
+    index:  usize,
+    ranged: bool,
+    done:    Arc<Vec<AtomicU64>>,
+    control: Arc<AtomicU8>,
+) -> Result<(), String> {
+    let mut file = std::fs::OpenOptions::new().write(true).open(&part)
+        .map_err(|e| format!("Cannot write {}: {}", part.display(), e))?;
+    let mut attempt = 0;
+    loop {
+        let from = seg.start + done[index].load(Ordering::Relaxed);
+        if ranged && from >= seg.end {
+            return Ok(());
+        }
+        match stream_range(&client, &url, bearer.as_deref(), &mut file, from, seg.end, ranged, &done[index], &control).await {
+            Ok(()) if !ranged => return Ok(()),
+            // The body ended short of the range: loop around and fetch the rest
+            Ok(()) if attempt < MAX_RETRIES => attempt += 1,
+            Ok(()) if seg.finished_with(done[index].load(Ordering::Relaxed)) => return Ok(()),
+            Ok(()) => return Err("Download interrupted (run it again to resume): connection closed early".into()),
+            Err(e) if e == CANCELLED => return Err(e),
+            // Without Range support a retry would restart the whole file anyway
+            Err(e) if !ranged || attempt >= MAX_RETRIES => {
+                return Err(format!("Download interrupted (run it again to resume): {}", e));
+            }
+            Err(e) => {
+                attempt += 1;
+                println!("[downloads] segment {} failed ({}), retry {}/{}", index, e, attempt, MAX_RETRIES);
+                tokio::time::sleep(Duration::from_secs(2 * attempt as u64)).await;
+            }
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn stream_range(
+    client: &reqwest::Client,
+    url:    &str,
+    bearer: Option<&str>,
+    file:   &mut std::fs::File,
+    from:   u64,
+    end:    u64,
+    ranged: bool,
+    done:    &AtomicU64,
+    control: &AtomicU8,
+) -> Result<(), String> {
+    let mut req = client.get(url);
+    if let Some(t) = bearer { req = req.bearer_auth(t); }
+    if ranged { req = req.header(reqwest::header::RANGE, format!("bytes={}-{}", from, end - 1)); }
+    let resp = req.send().await.map_err(|e| e.to_string())?;
+    if ranged && resp.status() != reqwest::StatusCode::PARTIAL_CONTENT {
+        return Err(format!("server ignored the byte range (HTTP {})", resp.status()));
+    }
+    if !resp.status().is_success() {
+        return Err(format!("HTTP {}", resp.status()));
+    }
+    file.seek(SeekFrom::Start(from)).map_err(|e| e.to_string())?;
+
+    let mut stream = resp.bytes_stream();
+    while let Some(chunk) = stream.next().await {
+        if control.load(Ordering::Relaxed) != RUNNING {
+            return Err(CANCELLED.into());
+        }
+        let chunk = chunk.map_err(|e| e.to_string())?;
+        throttle(chunk.len() as u64).await;
+        file.write_all(&chunk).map_err(|e| e.to_string())?;
+        done.fetch_add(chunk.len() as u64, Ordering::Relaxed);
+    }
+    file.flush().map_err(|e| e.to_string())
+}
+
+/// Sleeps long enough to keep all downloads together under the global limit.
+async fn throttle(bytes: u64) {
+    let limit = LIMIT.load(Ordering::Relaxed);
+    if limit == 0 {
+        return;
+    }
+    let wait = {
+        let Ok(mut pace) = PACE.lock() else { return };
+        let (start, sent) = pace.get_or_insert_with(|| (Instant::now(), 0));
+        *sent += bytes;
+        let elapsed = start.elapsed();
+        let wait = pace_delay(*sent, limit, elapsed);
+        // Restart the window once caught up so idle time doesn't bank a burst
+        if wait.is_zero() && elapsed > Duration::from_secs(1) {
+            *pace = None;
+        }
+        wait
+    };
+    if !wait.is_zero() {
+        tokio::time::sleep(wait).await;
+    }
+}
+
+// ── Pure helpers ───────────────────────────────────────────────────────────
+
+/// How long to wait so that `sent` bytes over `elapsed` stay at `limit` bytes/s.
+fn pace_delay(sent: u64, limit: u64, elapsed: Duration) -> Duration {
+    let due = Duration::from_secs_f64(sent as f64 / limit.max(1) as f64);
+    due.saturating_sub(elapsed)
+}
+
+/// Splits `total` bytes into at most `n` contiguous segments of at least
+/// MIN_SEGMENT each.
+fn split(total: u64, n: usize) -> Vec<Segment> {
+    let n = (n.max(1) as u64).min(total.div_ceil(MIN_SEGMENT)).max(1);
+    let size = total.div_ceil(n);
+    (0..n)
+        .map(|i| Segment { start: i * size, end: ((i + 1) * size).min(total), done: 0 })
+        .filter(|s| s.start < s.end || total == 0)
+        .collect()
+}
+
+/// Total size from `Content-Range: bytes 0-0/12345`.
+fn content_range_total(header: &str) -> Option<u64> {
+    header.trim().strip_prefix("bytes")?.rsplit('/').next()?.trim().parse().ok()
+}
+
+/// File name from a Content-Disposition header (`filename="x.safetensors"`).
+pub(crate) fn disposition_name(header: &str) -> Option<String> {
+    header.split(';')
+        .map(str::trim)
+        .find_map(|p| p.strip_prefix("filename="))
+        .map(|n| n.trim_matches('"').to_string())
+}
+
+fn percent(downloaded: u64, total: Option<u64>) -> u8 {
+    total.filter(|&t| t > 0).map_or(0, |t| (downloaded.min(t) * 100 / t) as u8)
+}
+
+fn status_line(file: &str, downloaded: u64, total: Option<u64>, bytes_per_sec: u64) -> String {
+    let mb = |b: u64| b as f64 / 1_048_576.0;
+    let size = match total {
+        Some(t) => format!("{:.1} / {:.1} MB", mb(downloaded), mb(t)),
+        None    => format!("{:.1} MB", mb(downloaded)),
+    };
+    format!("Downloading {} — {} ({:.1} MB/s)", file, size, mb(bytes_per_sec))
+}
+
+fn host_of(url: &str) -> String {
+    reqwest::Url::parse(url).ok().and_then(|u| u.host_str().map(String::from)).unwrap_or_default()
+}
+
+fn suffixed(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(suffix);
+    path.with_file_name(name)
+}
+
+fn sha256_file(path: &Path) -> Result<String, String> {
+    let mut file = std::fs::File::open(path).map_err(|e| format!("Cannot read {}: {}", path.display(), e))?;
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0u8; 1024 * 1024];
+    loop {
+        let n = file.read(&mut buf).map_err(|e| e.to_string())?;
+        if n == 0 { break; }
+        hasher.update(&buf[..n]);
+    }
+    Ok(hasher.finalize().iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+fn save_state(path: &Path, url: &str, total: u64, segments: &[Segment], done: &[AtomicU64]) {
+    let state = PartState {
+        url: url.to_string(),
+        total,
+        segments: segments.iter().zip(done)
+            .map(|(s, d)| Segment { done: d.load(Ordering::Relaxed), ..s.clone() })
+            .collect(),
+    };
+    if let Ok(json) = serde_json::to_string(&state) {
+        let tmp = path.with_extension("tmp");
+        if std::fs::write(&tmp, json).is_ok() {
+            let _ = std::fs::rename(&tmp, path);
+        }
+    }
+}
+
+fn report(app: &tauri::AppHandle, progress: &DownloadProgress) {
+    if let Ok(mut active) = ACTIVE.lock() {
+        if let Some(a) = active.get_mut(&progress.id) {
+            a.last = progress.clone();
+        }
+    }
+    let _ = app.emit_all("download-progress", progress);
+}
+
+// ── Unit tests ─────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_segments() {
+        let mb = 1024 * 1024;
+        // Small files stay in one piece
+        assert_eq!(split(3 * mb, 4), vec![Segment { start: 0, end: 3 * mb, done: 0 }]);
+        let segs = split(100 * mb + 1, 4);
+        assert_eq!(segs.len(), 4);
+        assert_eq!(segs[0].start, 0);
+        assert_eq!(segs[3].end, 100 * mb + 1);
+        assert!(segs.windows(2).all(|w| w[0].end == w[1].start));
+        // Never more segments than MIN_SEGMENT-sized pieces
+        assert_eq!(split(20 * mb, 8).len(), 3);
+        assert_eq!(split(0, 4).len(), 1);
+    }
+
+    #[test]
+    fn test_content_range_and_disposition() {
+        assert_eq!(content_range_total("bytes 0-0/123456"), Some(123456));
+        assert_eq!(content_range_total("bytes 0-0/*"), None);
+        assert_eq!(disposition_name(r#"attachment; filename="dream.safetensors""#).as_deref(), Some("dream.safetensors"));
+        assert_eq!(disposition_name("inline"), None);
+    }
+
+    #[test]
+    fn test_pace_delay() {
+        // 2 MB at 1 MB/s after half a second → wait the remaining 1.5 s
+        assert_eq!(pace_delay(2_000_000, 1_000_000, Duration::from_millis(500)), Duration::from_millis(1500));
+        assert_eq!(pace_delay(500_000, 1_000_000, Duration::from_secs(1)), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_sha256_file_and_paths() {
+        let dir = tempfile::tempdir().unwrap();
+        let p = dir.path().join("x.bin");
+        std::fs::write(&p, b"abc").unwrap();
+        assert_eq!(
+            sha256_file(&p).unwrap(),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert_eq!(part_path(Path::new("/m/x.safetensors")), Path::new("/m/x.safetensors.part"));
+        assert_eq!(suffixed(&part_path(&p), ".json").file_name().unwrap(), "x.bin.part.json");
+    }
+
+    #[test]
+    fn test_percent_and_segment_state() {
+        assert_eq!(percent(50, Some(200)), 25);
+        assert_eq!(percent(50, None), 0);
+        assert_eq!(percent(300, Some(200)), 100);
+        assert!(Segment { start: 10, end: 20, done: 10 }.finished());
+        assert!(!Segment { start: 10, end: 20, done: 9 }.finished());
+    }
+}
@@ -0,0 +1,309 @@
+// error.rs — structured errors returned by Tauri commands
+//
+// Commands return `Result<_, AppError>`, which reaches the frontend as
+//   { kind, message, provider, retryable, hint }
+// so the UI can branch on `kind` instead of matching (partly localized)
+// message text.
+//
+// Internals keep using `Result<_, String>`; `?` converts at the command
+// boundary, and `AppError::from(String)` classifies the message by the
+// conventions the modules already follow ("<Provider> <status>: …",
+// "… API key required", "Network error: …", "__CANCELLED__"). Code that
+// knows better builds the error directly (`AppError::new(kind, …)`).
+
+use serde::Serialize;
+use std::fmt;
+
+/// Message of work the user stopped (cancel_ai_request, SD interrupt, queue cancel)
+pub(crate) const CANCELLED: &str = "__CANCELLED__";
+
+/// Message prefixes of provider errors → provider id
+const PROVIDER_PREFIXES: &[(&str, &str)] = &[
+    ("OpenAI", "openai"),
+    ("Claude", "claude"),
+    ("Anthropic", "claude"),
+    ("DeepSeek", "deepseek"),
+    ("deepseek", "deepseek"),
+    ("OpenRouter", "openrouter"),
+    ("openrouter", "openrouter"),
+    ("Together AI", "together"),
+    ("together", "together"),
+    ("openai", "openai"),
+    ("DALL-E", "dalle"),
+    ("Stability AI", "stability"),
+    ("Replicate", "replicate"),
+    ("fal.ai", "fal"),
+    ("Local LLM", "local"),
+    ("Local SD", "local_sd"),
+    ("Brave", "brave"),
+    ("Bing", "bing"),
+    ("Serper", "serper"),
+    ("SerpAPI", "serpapi"),
+    ("SearXNG", "searxng"),
+    ("DuckDuckGo", "duckduckgo"),
+    ("Stack Exchange", "stackexchange"),
+    ("GitHub", "github"),
+];
+
+// ── Types ──────────────────────────────────────────────────────────────────
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorKind {
+    /// Missing, invalid or unauthorized credentials (401 / 403)
+    Auth,
+    /// 429, quota exhausted
+    RateLimit,
+    /// Could not connect / connection dropped
+    Network,
+    Timeout,
+    /// Bad input from the caller (missing field, invalid value, 400 / 422)
+    Validation,
+    NotFound,
+    Cancelled,
+    /// The provider failed on its side (5xx, refused content, bad response)
+    Provider,
+    /// Local file system / process failure
+    Io,
+    Internal,
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct AppError {
+    pub kind:      ErrorKind,
+    pub message:   String,
+    /// Provider id ("openai", "claude", …) when a provider call failed
+    pub provider:  Option<String>,
+    /// Whether the same request may succeed if repeated
+    pub retryable: bool,
+    /// Short suggestion for the user
+    pub hint:      Option<String>,
+}
+
+impl AppError {
+    pub fn new(kind: ErrorKind, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            message:   message.into(),
+            provider:  None,
+            retryable: default_retryable(kind),
+            hint:      default_hint(kind).map(String::from),
+        }
+    }
+
+    pub fn with_provider(mut self, provider: impl Into<String>) -> Self {
+        self.provider = Some(provider.into());
+        self
+    }
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for AppError {}
+
+// ── Conversions ────────────────────────────────────────────────────────────
+
+impl From<String> for AppError {
+    fn from(message: String) -> Self {
+        let kind = classify(&message);
+        let mut err = AppError::new(kind, message);
+        err.provider = provider_of(&err.message).map(String::from);
+        err
+    }
+}
+
+impl From<&str> for AppError {
+    fn from(message: &str) -> Self {
+        AppError::from(message.to_string())
+    }
+}
+
+impl From<std::io::Error> for AppError {
+    fn from(e: std::io::Error) -> Self {
+        let kind = match e.kind() {
+            std::io::ErrorKind::NotFound => ErrorKind::NotFound,
+            std::io::ErrorKind::TimedOut => ErrorKind::Timeout,
+            std::io::ErrorKind::InvalidInput | std::io::ErrorKind::InvalidData => ErrorKind::Validation,
+            _ => ErrorKind::Io,
+        };
+        AppError::new(kind, e.to_string())
+    }
+}
+
+impl From<reqwest::Error> for AppError {
+    fn from(e: reqwest::Error) -> Self {
+        let kind = if e.is_timeout() {
+            ErrorKind::Timeout
+        } else if e.is_connect() || e.is_request() {
+            ErrorKind::Network
+        } else if let Some(status) = e.status() {
+            status_kind(status.as_u16())
+        } else {
+            ErrorKind::Provider
+        };
+        AppError::new(kind, e.to_string())
+    }
+}
+
+impl From<tokio::task::JoinError> for AppError {
+    fn from(e: tokio::task::JoinError) -> Self {
+        AppError::new(ErrorKind::Internal, e.to_string())
+    }
+}
+
+/// Lets command functions be called from code that still returns String errors.
+impl From<AppError> for String {
+    fn from(e: AppError) -> String {
+        e.message
+    }
+}
+
+// ── Classification ─────────────────────────────────────────────────────────
+
+fn status_kind(status: u16) -> ErrorKind {
+    match status {
+        401 | 403 => ErrorKind::Auth,
+        404 => ErrorKind::NotFound,
+        408 | 504 => ErrorKind::Timeout,
+        429 => ErrorKind::RateLimit,
+        400 | 409 | 413 | 422 => ErrorKind::Validation,
+        _ => ErrorKind::Provider,
+    }
+}
+
+/// First HTTP status code in `message` ("Claude 401 Unauthorized: …").
+fn status_in(message: &str) -> Option<u16> {
+    message
+        .split(|c: char| !c.is_ascii_digit())
+        .filter(|w| w.len() == 3)
+        .filter_map(|w| w.parse::<u16>().ok())
+        .find(|s| (400..600).contains(s))
+}
+
+fn classify(message: &str) -> ErrorKind {
+    let lower = message.to_lowercase();
+    let has = |needles: &[&str]| needles.iter().any(|n| lower.contains(n));
+
+    // Only explicit stops; "interrupted" / "canceled" also show up in network
+    // and server failures the user must see
+    if message == CANCELLED || message == crate::downloads::CANCELLED {
+        return ErrorKind::Cancelled;
+    }
+    let rate_limited = has(&["rate limit", "too many requests", "quota"]);
+    if has(&["api key", "api token", "unauthorized", "invalid key", "authentication", "forbidden"]) && !rate_limited {
+        return ErrorKind::Auth;
+    }
+    if rate_limited {
+        return ErrorKind::RateLimit;
+    }
+    if let Some(status) = status_in(message).filter(|_| has(&[" 4", " 5"])) {
+        return status_kind(status);
+    }
+    if has(&["timed out", "timeout", "таймаут"]) {
+        return ErrorKind::Timeout;
+    }
+    if has(&[
+        "network error", "cannot reach", "connection refused", "failed to connect", "не удалось подключиться",
+        "dns error", "error sending request", "not reachable", "unreachable", "недоступна",
+        "download interrupted", "connection closed",
+    ]) {
+        return ErrorKind::Network;
+    }
+    if has(&["not found", "no such file", "does not exist"]) {
+        return ErrorKind::NotFound;
+    }
+    if has(&["required", "invalid", "must be", "unknown ", "empty", "too large", "exceeds", "not allowed"]) {
+        return ErrorKind::Validation;
+    }
+    if has(&["permission denied", "cannot read", "cannot write", "failed to write", "failed to read", "os error"]) {
+        return ErrorKind::Io;
+    }
+    if provider_of(message).is_some() {
+        return ErrorKind::Provider;
+    }
+    ErrorKind::Internal
+}
+
+fn provider_of(message: &str) -> Option<&'static str> {
+    PROVIDER_PREFIXES
+        .iter()
+        .find(|(prefix, _)| {
+            message.strip_prefix(prefix).is_some_and(|rest| rest.starts_with([' ', ':']))
+        })
+        .map(|&(_, id)| id)
+}
+
+fn default_retryable(kind: ErrorKind) -> bool {
+    matches!(kind, ErrorKind::RateLimit | ErrorKind::Network | ErrorKind::Timeout | ErrorKind::Provider)
+}
+
+fn default_hint(kind: ErrorKind) -> Option<&'static str> {
+    Some(match kind {
+        ErrorKind::Auth      => "Check the API key in Settings",
+        ErrorKind::RateLimit => "Wait a moment and retry, or switch provider",
+        ErrorKind::Network   => "Check your connection, or that the local server is running",
+        ErrorKind::Timeout   => "The server took too long; retry or pick a faster model",
+        _ => return None,
+    })
+}
+
+// ── Unit tests ─────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kind(message: &str) -> ErrorKind {
+        AppError::from(message).kind
+    }
+
+    #[test]
+    fn test_classify_provider_messages() {
+        let e = AppError::from("Claude 401 Unauthorized: invalid x-api-key");
+        assert_eq!((e.kind, e.provider.as_deref(), e.retryable), (ErrorKind::Auth, Some("claude"), false));
+        assert!(e.hint.is_some());
+
+        let e = AppError::from("deepseek 429 Too Many Requests: Rate limit reached");
+        assert_eq!((e.kind, e.provider.as_deref(), e.retryable), (ErrorKind::RateLimit, Some("deepseek"), true));
+
+        assert_eq!(kind("OpenAI API key is required"), ErrorKind::Auth);
+        assert_eq!(kind("Local LLM 500 Internal Server Error: No model loaded"), ErrorKind::Provider);
+        assert_eq!(kind("Together AI 400 Bad Request: prompt too long"), ErrorKind::Validation);
+        assert_eq!(kind("Network error: error sending request for url"), ErrorKind::Network);
+        assert_eq!(kind(CANCELLED), ErrorKind::Cancelled);
+    }
+
+    #[test]
+    fn test_classify_interrupted_download_as_network() {
+        let e = AppError::from("Download interrupted (run it again to resume): connection closed early");
+        assert_eq!((e.kind, e.retryable), (ErrorKind::Network, true));
+        assert_eq!(kind(crate::downloads::CANCELLED), ErrorKind::Cancelled);
+        assert_eq!(kind("A1111 500 Internal Server Error: Interrupted"), ErrorKind::Provider);
+    }
+
+    #[test]
+    fn test_classify_local_messages() {
+        assert_eq!(kind("Unknown provider: foo"), ErrorKind::Validation);
+        assert_eq!(kind("File not found: /tmp/x"), ErrorKind::NotFound);
+        assert_eq!(kind("Cannot write /etc/x: Permission denied (os error 13)"), ErrorKind::Io);
+        assert_eq!(kind("something odd happened"), ErrorKind::Internal);
+        // a year or a size is not a status code
+        assert_eq!(kind("Cannot parse 1024x1024 image from 2024"), ErrorKind::Internal);
+        assert_eq!(AppError::from("OpenAI 401: bad").provider.as_deref(), Some("openai"));
+        assert_eq!(AppError::from("OpenAIsh thing failed").provider, None);
+    }
+
+    #[test]
+    fn test_serialized_shape() {
+        let json = serde_json::to_value(AppError::new(ErrorKind::RateLimit, "slow down").with_provider("openai")).unwrap();
+        assert_eq!(json["kind"], "rate_limit");
+        assert_eq!(json["provider"], "openai");
+        assert_eq!(json["retryable"], true);
+        let back: String = AppError::from("plain").into();
+        assert_eq!(back, "plain");
+    }
+}
@@ -0,0 +1,512 @@
+// focus_session.rs — pomodoro / focus-time tracker with end-of-session summaries
+//
+// While a session runs, the foreground application and window title are
+// sampled every few seconds and aggregated in memory. When the session ends
+// (timer elapsed or stopped by the user) the totals are written to
+// <app-data>/focus_sessions/<id>.json. An AI summary can then be generated
+// from the aggregated titles — no screenshots are involved, and nothing
+// leaves the machine unless a cloud provider is chosen for the summary.
+//
+// Tauri commands exposed:
+//   start_focus_session      → starts the timer + sampler
+//   stop_focus_session       → ends it early, returns the saved session
+//   get_focus_session_status → elapsed / remaining for the running session
+//   list_focus_sessions      → newest first
+//   summarize_focus_session  → AI summary, stored in the session file
+//
+// Events emitted:
+//   focus-session-complete   → FocusSession (planned time elapsed)
+
+use crate::ai_bridge::{self, AiRequest};
+use crate::error::AppError;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+const SAMPLE_SECS: u64           = 5;
+const DEFAULT_MINUTES: u32       = 25;
+const MAX_TITLE_CHARS: usize     = 120;
+/// Distinct (app, title) pairs kept per session; the rest fold into "other".
+const MAX_USAGE_ENTRIES: usize   = 500;
+/// Lines of per-window detail handed to the summarizer.
+const REPORT_TOP_WINDOWS: usize  = 15;
+
+static ACTIVE: Mutex<Option<ActiveSession>> = Mutex::new(None);
+
+struct ActiveSession {
+    id:              String,
+    started:         Instant,
+    planned_minutes: u32,
+    stop:            Arc<AtomicBool>,
+    handle:          std::thread::JoinHandle<Result<FocusSession, String>>,
+}
+
+// ── Types ──────────────────────────────────────────────────────────────────
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct WindowUsage {
+    pub app:      String,
+    pub title:    String,
+    /// "editor" | "terminal" | "browser" | "chat" | "docs" | "media" | "other"
+    pub category: String,
+    pub seconds:  u64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FocusSession {
+    pub id:              String,
+    pub label:           Option<String>,
+    /// Unix timestamps (seconds)
+    pub started_at:      u64,
+    pub ended_at:        u64,
+    pub planned_minutes: u32,
+    /// True when the planned time elapsed (false = stopped early)
+    pub completed:       bool,
+    /// Most-used first
+    pub usage:           Vec<WindowUsage>,
+    pub summary:         Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FocusSessionStatus {
+    pub running:           bool,
+    pub id:                Option<String>,
+    pub elapsed_seconds:   u64,
+    pub remaining_seconds: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SummarizeFocusRequest {
+    pub id:        String,
+    /// "openai" | "claude" | "deepseek" | "openrouter" | "together" | "local"
+    pub provider:  String,
+    pub api_key:   String,
+    pub model:     Option<String>,
+    pub local_url: Option<String>,
+}
+
+// ── Storage helpers ────────────────────────────────────────────────────────
+
+fn sessions_dir(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+    app.path_resolver()
+        .app_data_dir()
+        .ok_or_else(|| "Cannot resolve app data directory".to_string())
+        .map(|p| p.join("focus_sessions"))
+}
+
+fn session_path(dir: &Path, id: &str) -> Result<PathBuf, String> {
+    if id.is_empty() || !id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+        return Err(format!("Invalid session id: {}", id));
+    }
+    Ok(dir.join(format!("{}.json", id)))
+}
+
+fn save_session(dir: &Path, session: &FocusSession) -> Result<(), String> {
+    std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    let json = serde_json::to_string_pretty(session).map_err(|e| e.to_string())?;
+    std::fs::write(session_path(dir, &session.id)?, json)
+        .map_err(|e| format!("Failed to save focus session: {}", e))
+}
+
+fn load_session(dir: &Path, id: &str) -> Result<FocusSession, String> {
+    let s = std::fs::read_to_string(session_path(dir, id)?)
+        .map_err(|_| format!("Focus session not found: {}", id))?;
+    serde_json::from_str(&s).map_err(|e| e.to_string())
+}
+
+fn unix_now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+// ── Sampler ────────────────────────────────────────────────────────────────
+
+/// In-memory per-window totals for the running session.
+#[derive(Default)]
+struct UsageTally {
+    entries: HashMap<(String, String), u64>,
+}
+
+impl UsageTally {
+    fn add(&mut self, app: &str, title: &str, seconds: u64) {
+        let title: String = title.trim().chars().take(MAX_TITLE_CHARS).collect();
+        let mut key = (app.trim().to_string(), title);
+        if !self.entries.contains_key(&key) && self.entries.len() >= MAX_USAGE_ENTRIES {
+            key.1 = "(other windows)".into();
+        }
+        *self.entries.entry(key).or_default() += seconds;
+    }
+
+    fn into_usage(self) -> Vec<WindowUsage> {
+        let mut usage: Vec<WindowUsage> = self.entries.into_iter()
+            .map(|((app, title), seconds)| WindowUsage { category: categorize(&app).into(), app, title, seconds })
+            .collect();
+        usage.sort_by(|a, b| b.seconds.cmp(&a.seconds).then_with(|| a.app.cmp(&b.app)));
+        usage
+    }
+}
+
+fn session_loop(
+    app:         tauri::AppHandle,
+    mut session: FocusSession,
+    stop:        Arc<AtomicBool>,
+) -> Result<FocusSession, String> {
+    use tauri::Manager;
+
+    let planned = Duration::from_secs(session.planned_minutes as u64 * 60);
+    let started = Instant::now();
+    let mut tally = UsageTally::default();
+
+    while !stop.load(Ordering::Relaxed) && started.elapsed() < planned {
+        std::thread::sleep(Duration::from_secs(SAMPLE_SECS));
+        match platform::active_window() {
+            Some((app_name, title)) => tally.add(&app_name, &title, SAMPLE_SECS),
+            None                    => tally.add("unknown", "", SAMPLE_SECS),
+        }
+    }
+
+    session.completed = started.elapsed() >= planned;
+    session.ended_at  = unix_now();
+    session.usage     = tally.into_usage();
+    save_session(&sessions_dir(&app)?, &session)?;
+
+    if session.completed {
+        let _ = app.emit_all("focus-session-complete", &session);
+    }
+    log::info!("focus session {} ended ({} windows tracked)", session.id, session.usage.len());
+    Ok(session)
+}
+
+// ── Tauri commands ─────────────────────────────────────────────────────────
+
+/// Start a focus session of `minutes` (default 25).
+#[tauri::command]
+pub fn start_focus_session(
+    app_handle: tauri::AppHandle,
+    minutes:    Option<u32>,
+    label:      Option<String>,
+) -> Result<FocusSession, AppError> {
+    let mut active = ACTIVE.lock().map_err(|e| e.to_string())?;
+    if active.as_ref().is_some_and(|a| !a.handle.is_finished()) {
+        return Err("A focus session is already running".into());
+    }
+
+    let planned_minutes = minutes.unwrap_or(DEFAULT_MINUTES).clamp(1, 8 * 60);
+    let now = unix_now();
+    let session = FocusSession {
+        id:         format!("focus-{}", now),
+        label:      label.filter(|l| !l.trim().is_empty()),
+        started_at: now,
+        ended_at:   0,
+        planned_minutes,
+        completed:  false,
+        usage:      Vec::new(),
+        summary:    None,
+    };
+
+    let stop = Arc::new(AtomicBool::new(false));
+    let handle = {
+        let (session, stop) = (session.clone(), stop.clone());
+        std::thread::spawn(move || session_loop(app_handle, session, stop))
+    };
+    *active = Some(ActiveSession {
+        id: session.id.clone(),
+        started: Instant::now(),
+        planned_minutes,
+        stop,
+        handle,
+    });
+    Ok(session)
+}
+
+/// End the running session early and return it as saved.
+#[tauri::command]
+pub async fn stop_focus_session() -> Result<FocusSession, AppError> {
+    let active = ACTIVE.lock().map_err(|e| e.to_string())?.take()
+        .ok_or("No focus session running")?;
+    active.stop.store(true, Ordering::Relaxed);
+
+    let id = active.id;
+    tokio::task::spawn_blocking(move || active.handle.join())
+        .await?
+        .map_err(|_| format!("Focus session thread for {} panicked", id))?
+        .map_err(AppError::from)
+}
+
+#[tauri::command]
+pub fn get_focus_session_status() -> FocusSessionStatus {
+    let guard  = ACTIVE.lock().ok();
+    let active = guard.as_deref()
+        .and_then(Option::as_ref)
+        .filter(|a| !a.handle.is_finished());
+    match active {
+        Some(a) => {
+            let elapsed = a.started.elapsed().as_secs();
+            FocusSessionStatus {
+                running:           true,
+                id:                Some(a.id.clone()),
+                elapsed_seconds:   elapsed,
+                remaining_seconds: (a.planned_minutes as u64 * 60).saturating_sub(elapsed),
+            }
+        }
+        None => FocusSessionStatus { running: false, id: None, elapsed_seconds: 0, remaining_seconds: 0 },
+    }
+}
+
+/// All saved sessions, newest first.
+#[tauri::command]
+pub fn list_focus_sessions(app_handle: tauri::AppHandle) -> Result<Vec<FocusSession>, AppError> {
+    let dir = sessions_dir(&app_handle)?;
+    let Ok(read) = std::fs::read_dir(&dir) else { return Ok(vec![]) };
+
+    let mut out: Vec<FocusSession> = read
+        .filter_map(|e| e.ok())
+        .filter_map(|e| std::fs::read_to_string(e.path()).ok())
+        .filter_map(|s| serde_json::from_str(&s).ok())
+        .collect();
+    out.sort_by(|a, b| b.started_at.cmp(&a.started_at));
+    Ok(out)
+}
+
+/// Ask the model for a short recap of a finished session and store it.
+#[tauri::command]
+pub async fn summarize_focus_session(
+    app_handle: tauri::AppHandle,
+    req:        SummarizeFocusRequest,
+) -> Result<FocusSession, AppError> {
+    let dir = sessions_dir(&app_handle)?;
+    let mut session = load_session(&dir, &req.id)?;
+    if session.usage.is_empty() {
+        return Err("Nothing was tracked in this session".into());
+    }
+
+    let ai_req = AiRequest {
+        api_key:       req.api_key,
+        prompt:        usage_report(&session),
+        system_prompt: Some(SUMMARY_INSTRUCTIONS.to_string()),
+        image_base64:  None,
+        context_files: None,
+        model:         req.model,
+        max_tokens:    Some(400),
+        allow_secrets: false,
+        profile:       None,
+    };
+    let resp = ai_bridge::complete(&req.provider, ai_req, req.local_url.as_deref()).await?;
+    session.summary = Some(resp.text.trim().to_string());
+    save_session(&dir, &session)?;
+    Ok(session)
+}
+
+// ── Reporting ──────────────────────────────────────────────────────────────
+
+const SUMMARY_INSTRUCTIONS: &str = "\
+You review a focus session from window-usage data. In 3–5 short sentences, say \
+where the time went (by activity, with minutes), what the user most likely \
+worked on judging by the window titles, and note context switching if it was \
+frequent. Be concrete and friendly; no bullet lists, no advice unless asked.";
+
+/// Rough activity bucket from the application name.
+fn categorize(app: &str) -> &'static str {
+    const RULES: &[(&str, &[&str])] = &[
+        ("editor",   &["code", "cursor", "zed", "idea", "pycharm", "webstorm", "clion", "goland",
+                       "rustrover", "sublime", "vim", "emacs", "xcode", "studio", "kate", "gedit"]),
+        ("terminal", &["terminal", "iterm", "alacritty", "kitty", "wezterm", "konsole", "gnome-terminal",
+                       "windowsterminal", "powershell", "cmd.exe", "foot", "ghostty"]),
+        ("browser",  &["firefox", "chrome", "chromium", "safari", "edge", "brave", "opera", "vivaldi", "arc"]),
+        ("chat",     &["slack", "discord", "telegram", "teams", "zoom", "signal", "whatsapp", "mattermost"]),
+        ("docs",     &["word", "excel", "powerpoint", "libreoffice", "notion", "obsidian", "acrobat",
+                       "preview", "evince", "okular", "pages", "keynote"]),
+        ("media",    &["spotify", "vlc", "music", "mpv", "youtube"]),
+    ];
+    let lower = app.to_lowercase();
+    RULES.iter()
+        .find(|(_, needles)| needles.iter().any(|n| lower.contains(n)))
+        .map(|(cat, _)| *cat)
+        .unwrap_or("other")
+}
+
+/// Plain-text usage table handed to the summarizer.
+fn usage_report(session: &FocusSession) -> String {
+    let mut by_category: Vec<(&str, u64)> = Vec::new();
+    for u in &session.usage {
+        match by_category.iter_mut().find(|(c, _)| *c == u.category) {
+            Some((_, secs)) => *secs += u.seconds,
+            None            => by_category.push((&u.category, u.seconds)),
+        }
+    }
+    by_category.sort_by(|a, b| b.1.cmp(&a.1));
+
+    let total: u64 = session.usage.iter().map(|u| u.seconds).sum();
+    let mut out = format!(
+        "Focus session{}: {} min planned, {} min tracked{}.\n\nBy activity:\n",
+        session.label.as_ref().map(|l| format!(" \"{}\"", l)).unwrap_or_default(),
+        session.planned_minutes,
+        total.div_ceil(60),
+        if session.completed { "" } else { " (stopped early)" },
+    );
+    for (cat, secs) in &by_category {
+        out.push_str(&format!("- {}: {} min\n", cat, secs.div_ceil(60)));
+    }
+
+    out.push_str("\nTop windows:\n");
+    for u in session.usage.iter().take(REPORT_TOP_WINDOWS) {
+        let title = if u.title.is_empty() { "(no title)" } else { &u.title };
+        out.push_str(&format!("- [{}] {} — {} ({} min)\n", u.category, u.app, title, u.seconds.div_ceil(60)));
+    }
+    out
+}
+
+// ═══════════════════════════════════════════════════════════════════════
+// Foreground window lookup — returns (application, window title)
+// ═══════════════════════════════════════════════════════════════════════
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use cocoa::base::{id, nil};
+    use cocoa::foundation::NSString;
+    use objc::{class, msg_send, sel, sel_impl};
+
+    /// Window titles need the Accessibility permission, so macOS reports
+    /// the frontmost application only.
+    pub fn active_window() -> Option<(String, String)> {
+        unsafe {
+            let workspace: id = msg_send![class!(NSWorkspace), sharedWorkspace];
+            let app: id = msg_send![workspace, frontmostApplication];
+            if app == nil { return None; }
+            let name: id = msg_send![app, localizedName];
+            if name == nil { return None; }
+            let c = std::ffi::CStr::from_ptr(name.UTF8String());
+            Some((c.to_string_lossy().to_string(), String::new()))
+        }
+    }
+}
+
+#[cfg(target_os = "windows")]
+mod platform {
+    use windows::core::PWSTR;
+    use windows::Win32::Foundation::CloseHandle;
+    use windows::Win32::System::Threading::{
+        OpenProcess, QueryFullProcessImageNameW, PROCESS_NAME_WIN32, PROCESS_QUERY_LIMITED_INFORMATION,
+    };
+    use windows::Win32::UI::WindowsAndMessaging::{GetForegroundWindow, GetWindowTextW, GetWindowThreadProcessId};
+
+    pub fn active_window() -> Option<(String, String)> {
+        unsafe {
+            let hwnd = GetForegroundWindow();
+            if hwnd.0 == 0 { return None; }
+
+            let mut buf = [0u16; 512];
+            let len   = GetWindowTextW(hwnd, &mut buf).max(0) as usize;
+            let title = String::from_utf16_lossy(&buf[..len]);
+
+            let mut pid = 0u32;
+            GetWindowThreadProcessId(hwnd, Some(&mut pid as *mut u32));
+            let app = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, false, pid).ok()
+                .and_then(|handle| {
+                    let mut path = [0u16; 1024];
+                    let mut size = path.len() as u32;
+                    let ok = QueryFullProcessImageNameW(handle, PROCESS_NAME_WIN32, PWSTR(path.as_mut_ptr()), &mut size).is_ok();
+                    let _ = CloseHandle(handle);
+                    ok.then(|| String::from_utf16_lossy(&path[..size as usize]))
+                })
+                .and_then(|p| p.rsplit('\\').next().map(String::from))
+                .unwrap_or_default();
+
+            Some((app, title))
+        }
+    }
+}
+
+/// X11 via xdotool; Hyprland and Sway via their IPC tools. Other Wayland
+/// compositors don't expose the focused window to clients.
+#[cfg(all(not(target_os = "macos"), not(target_os = "windows")))]
+mod platform {
+    use std::process::Command;
+
+    pub fn active_window() -> Option<(String, String)> {
+        xdotool().or_else(hyprctl).or_else(swaymsg)
+    }
+
+    fn run(cmd: &str, args: &[&str]) -> Option<String> {
+        let out = Command::new(cmd).args(args).output().ok()?;
+        out.status.success().then(|| String::from_utf8_lossy(&out.stdout).trim().to_string())
+    }
+
+    fn xdotool() -> Option<(String, String)> {
+        let title = run("xdotool", &["getactivewindow", "getwindowname"])?;
+        let app = run("xdotool", &["getactivewindow", "getwindowpid"])
+            .and_then(|pid| std::fs::read_to_string(format!("/proc/{}/comm", pid.trim())).ok())
+            .map(|c| c.trim().to_string())
+            .unwrap_or_default();
+        Some((app, title))
+    }
+
+    fn hyprctl() -> Option<(String, String)> {
+        let json: serde_json::Value = serde_json::from_str(&run("hyprctl", &["activewindow", "-j"])?).ok()?;
+        Some((json["class"].as_str()?.to_string(), json["title"].as_str().unwrap_or("").to_string()))
+    }
+
+    fn swaymsg() -> Option<(String, String)> {
+        let tree: serde_json::Value = serde_json::from_str(&run("swaymsg", &["-t", "get_tree"])?).ok()?;
+        fn focused(node: &serde_json::Value) -> Option<&serde_json::Value> {
+            if node["focused"].as_bool() == Some(true) { return Some(node); }
+            node["nodes"].as_array().into_iter().flatten()
+                .chain(node["floating_nodes"].as_array().into_iter().flatten())
+                .find_map(focused)
+        }
+        let node = focused(&tree)?;
+        let app = node["app_id"].as_str()
+            .or_else(|| node["window_properties"]["class"].as_str())
+            .unwrap_or("");
+        Some((app.to_string(), node["name"].as_str().unwrap_or("").to_string()))
+    }
+}
+
+// ── Unit tests ─────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_categorize() {
+        assert_eq!(categorize("Code"), "editor");
+        assert_eq!(categorize("firefox"), "browser");
+        assert_eq!(categorize("gnome-terminal-server"), "terminal");
+        assert_eq!(categorize("Calculator"), "other");
+    }
+
+    #[test]
+    fn test_tally_merges_and_sorts() {
+        let mut t = UsageTally::default();
+        t.add("code", "main.rs", 5);
+        t.add("firefox", "Docs", 5);
+        t.add("code", "main.rs", 5);
+        let usage = t.into_usage();
+        assert_eq!(usage[0].app, "code");
+        assert_eq!(usage[0].seconds, 10);
+        assert_eq!(usage[0].category, "editor");
+    }
+
+    #[test]
+    fn test_usage_report_groups_by_category() {
+        let session = FocusSession {
+            id: "focus-1".into(), label: Some("API work".into()),
+            started_at: 0, ended_at: 0, planned_minutes: 25, completed: true,
+            usage: vec![
+                WindowUsage { app: "code".into(), title: "lib.rs".into(), category: "editor".into(), seconds: 1200 },
+                WindowUsage { app: "kitty".into(), title: "".into(), category: "terminal".into(), seconds: 300 },
+            ],
+            summary: None,
+        };
+        let r = usage_report(&session);
+        assert!(r.contains("\"API work\""));
+        assert!(r.contains("- editor: 20 min"));
+        assert!(r.contains("kitty — (no title)"));
+    }
+}
@@ -0,0 +1,236 @@
+// gallery.rs — history of generated images
+//
+// Every image that comes back from generate_image (cloud / WebUI) or
+// run_local_sd (stable-diffusion.cpp) is written to <app-data>/gallery/ as
+// <id>.png (or .jpg) with a sidecar <id>.json holding the settings that made
+// it, so results survive restarts and can be re-run with the same seed.
+// Saving is best-effort: a full disk or unwritable folder is logged and never
+// fails the generation itself.
+//
+// Tauri commands exposed:
+//   list_generated_images(limit?)  → newest first
+//   delete_generated_image(id)     → removes the image and its sidecar
+
+use crate::disk;
+use crate::error::AppError;
+use base64::{engine::general_purpose, Engine};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Default page size of list_generated_images
+const DEFAULT_LIMIT: usize = 200;
+
+// ── Types ──────────────────────────────────────────────────────────────────
+
+/// Settings a generation was made with; the caller fills what it knows.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct GenerationRecord {
+    /// "native_sd" | "local_sd" | "dalle" | "stability" | "together" | "openrouter" | …
+    pub provider:        String,
+    pub model:           Option<String>,
+    pub prompt:          String,
+    pub negative_prompt: Option<String>,
+    pub seed:            Option<i64>,
+    pub sampler:         Option<String>,
+    pub steps:           Option<u32>,
+    pub cfg_scale:       Option<f32>,
+    /// Prompt as rewritten by the provider (DALL-E 3)
+    pub revised_prompt:  Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct GeneratedImage {
+    pub id:         String,
+    /// Absolute path of the image file
+    pub path:       String,
+    /// Unix timestamp (seconds)
+    pub created_at: u64,
+    pub width:      u32,
+    pub height:     u32,
+    #[serde(flatten)]
+    pub record:     GenerationRecord,
+}
+
+// ── Storage helpers ────────────────────────────────────────────────────────
+
+fn gallery_dir(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+    app.path_resolver()
+        .app_data_dir()
+        .ok_or_else(|| "Cannot resolve app data directory".to_string())
+        .map(|p| p.join("gallery"))
+}
+
+/// Stores a generated image; logs and returns None when it can't.
+pub(crate) fn record(app: &tauri::AppHandle, image_base64: &str, record: GenerationRecord) -> Option<GeneratedImage> {
+    let result = gallery_dir(app).and_then(|dir| save(&dir, image_base64, record));
+    match result {
+        Ok(entry) => Some(entry),
+        Err(e) => {
+            println!("[gallery] not saved: {}", e);
+            None
+        }
+    }
+}
+
+fn save(dir: &Path, image_base64: &str, record: GenerationRecord) -> Result<GeneratedImage, String> {
+    let bytes = general_purpose::STANDARD
+        .decode(image_base64.trim())
+        .map_err(|e| format!("Invalid base64 image: {}", e))?;
+    let format = image::guess_format(&bytes).map_err(|e| format!("Unknown image format: {}", e))?;
+    let (width, height) = image::load_from_memory_with_format(&bytes, format)
+        .map(|img| (img.width(), img.height()))
+        .map_err(|e| format!("Cannot decode image: {}", e))?;
+    std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    disk::ensure_space(dir, bytes.len() as u64, "the generated image")?;
+
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default();
+    // Several images can land in the same millisecond (batch / grid runs)
+    let mut id = format!("gen-{}", now.as_millis());
+    let mut n = 1;
+    while dir.join(format!("{}.json", id)).exists() {
+        n += 1;
+        id = format!("gen-{}-{}", now.as_millis(), n);
+    }
+    let ext = format.extensions_str().first().copied().unwrap_or("png");
+    let path = dir.join(format!("{}.{}", id, ext));
+    std::fs::write(&path, &bytes).map_err(|e| format!("Failed to save image: {}", e))?;
+
+    let entry = GeneratedImage {
+        id,
+        path: path.to_string_lossy().to_string(),
+        created_at: now.as_secs(),
+        width,
+        height,
+        record,
+    };
+    let json = serde_json::to_string_pretty(&entry).map_err(|e| e.to_string())?;
+    if let Err(e) = std::fs::write(dir.join(format!("{}.json", entry.id)), json) {
+        let _ = std::fs::remove_file(&path);
+        return Err(format!("Failed to save image metadata: {}", e));
+    }
+    Ok(entry)
+}
+
+/// Sidecars whose image still exists, newest first.
+fn load_all(dir: &Path) -> Vec<GeneratedImage> {
+    let Ok(read) = std::fs::read_dir(dir) else { return Vec::new() };
+    let mut entries: Vec<GeneratedImage> = read
+        .flatten()
+        .filter(|e| e.path().extension().is_some_and(|x| x == "json"))
+        .filter_map(|e| std::fs::read_to_string(e.path()).ok())
+        .filter_map(|s| serde_json::from_str::<GeneratedImage>(&s).ok())
+        .filter(|g| Path::new(&g.path).exists())
+        .collect();
+    entries.sort_by(|a, b| b.created_at.cmp(&a.created_at).then_with(|| b.id.cmp(&a.id)));
+    entries
+}
+
+/// The newest `limit` generated images, for other modules (blocking).
+pub(crate) fn recent(app: &tauri::AppHandle, limit: usize) -> Vec<GeneratedImage> {
+    gallery_dir(app).map(|dir| load_all(&dir).into_iter().take(limit).collect()).unwrap_or_default()
+}
+
+/// File of the generated image with `id`, for other modules that take ids.
+pub(crate) fn image_path(app: &tauri::AppHandle, id: &str) -> Option<PathBuf> {
+    if !valid_id(id) {
+        return None;
+    }
+    let json = std::fs::read_to_string(gallery_dir(app).ok()?.join(format!("{}.json", id))).ok()?;
+    serde_json::from_str::<GeneratedImage>(&json).ok().map(|g| PathBuf::from(g.path))
+}
+
+/// Ids are generated here; anything else (paths, "..") is refused.
+fn valid_id(id: &str) -> bool {
+    id.starts_with("gen-") && id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+}
+
+// ── Tauri commands ─────────────────────────────────────────────────────────
+
+#[tauri::command]
+pub async fn list_generated_images(app_handle: tauri::AppHandle, limit: Option<usize>) -> Result<Vec<GeneratedImage>, AppError> {
+    let dir = gallery_dir(&app_handle)?;
+    let limit = limit.unwrap_or(DEFAULT_LIMIT);
+    tokio::task::spawn_blocking(move || load_all(&dir).into_iter().take(limit).collect())
+        .await
+        .map_err(AppError::from)
+}
+
+#[tauri::command]
+pub fn delete_generated_image(app_handle: tauri::AppHandle, id: String) -> Result<(), AppError> {
+    let dir = gallery_dir(&app_handle)?;
+    delete(&dir, &id).map_err(AppError::from)
+}
+
+fn delete(dir: &Path, id: &str) -> Result<(), String> {
+    if !valid_id(id) {
+        return Err(format!("Invalid image id: {}", id));
+    }
+    let sidecar = dir.join(format!("{}.json", id));
+    let entry: GeneratedImage = std::fs::read_to_string(&sidecar)
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .ok_or_else(|| format!("Generated image not found: {}", id))?;
+    let _ = std::fs::remove_file(&entry.path);
+    std::fs::remove_file(&sidecar).map_err(|e| e.to_string())
+}
+
+// ── Unit tests ─────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn png_b64(w: u32, h: u32) -> String {
+        let mut png = Vec::new();
+        image::DynamicImage::new_rgb8(w, h)
+            .write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+            .unwrap();
+        general_purpose::STANDARD.encode(png)
+    }
+
+    #[test]
+    fn test_save_list_delete_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let record = GenerationRecord {
+            provider: "native_sd".into(),
+            model:    Some("dreamshaper_8.safetensors".into()),
+            prompt:   "a lighthouse".into(),
+            seed:     Some(42),
+            steps:    Some(20),
+            ..Default::default()
+        };
+        let first = save(dir.path(), &png_b64(16, 8), record.clone()).unwrap();
+        let second = save(dir.path(), &png_b64(8, 8), GenerationRecord { prompt: "a boat".into(), ..record.clone() }).unwrap();
+        assert_ne!(first.id, second.id);
+        assert!(first.path.ends_with(".png"));
+        assert_eq!((first.width, first.height), (16, 8));
+
+        let all = load_all(dir.path());
+        assert_eq!(all.len(), 2);
+        assert_eq!(all[0].id, second.id);
+        assert_eq!(all[1].record, record);
+
+        delete(dir.path(), &first.id).unwrap();
+        assert!(!Path::new(&first.path).exists());
+        assert_eq!(load_all(dir.path()).len(), 1);
+        assert!(delete(dir.path(), &first.id).unwrap_err().contains("not found"));
+    }
+
+    #[test]
+    fn test_sidecar_is_flat_json_and_ids_are_checked() {
+        let dir = tempfile::tempdir().unwrap();
+        let entry = save(dir.path(), &png_b64(4, 4), GenerationRecord { prompt: "x".into(), ..Default::default() }).unwrap();
+        let json: serde_json::Value = serde_json::from_str(
+            &std::fs::read_to_string(dir.path().join(format!("{}.json", entry.id))).unwrap(),
+        ).unwrap();
+        assert_eq!(json["prompt"], "x");
+        assert_eq!(json["width"], 4);
+
+        assert!(valid_id("gen-1700000000000-2"));
+        assert!(!valid_id("gen-../../etc"));
+        assert!(!valid_id("cap-1"));
+        assert!(save(dir.path(), "not base64!", GenerationRecord::default()).is_err());
+    }
+}
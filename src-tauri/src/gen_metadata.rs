@@ -0,0 +1,599 @@
+// gen_metadata.rs — generation parameters embedded in images
+//
+// Reads what other Stable Diffusion front-ends write into their output, so a
+// dropped-in image can be re-run or tweaked with local_sd:
+//   • A1111 / Forge — PNG text chunk "parameters" (JPEG / WebP: EXIF
+//     UserComment) in the "prompt\nNegative prompt: …\nSteps: 20, …" format
+//   • ComfyUI       — PNG text chunk "prompt" holding the API graph (JSON);
+//     the sampler node is followed back to its text encoders and loaders
+// tEXt, zTXt and iTXt chunks are read anywhere in the file, before or after
+// the image data.
+//
+// The other direction: `embed_parameters` writes the A1111 "parameters"
+// chunk into PNGs from run_local_sd and generate_image, so our own images
+// carry their settings into other tools and back into this one.
+//
+// Tauri commands exposed:
+//   read_generation_metadata(path_or_base64) → GenerationMetadata
+
+use crate::error::AppError;
+use crate::gallery::GenerationRecord;
+use base64::{engine::general_purpose, Engine};
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::io::Read;
+
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+// ── Types ──────────────────────────────────────────────────────────────────
+
+#[derive(Debug, Serialize, Default, PartialEq)]
+pub struct GenerationMetadata {
+    /// "a1111" | "forge" | "comfyui"
+    pub source:          String,
+    pub prompt:          String,
+    pub negative_prompt: String,
+    pub seed:            Option<i64>,
+    pub steps:           Option<u32>,
+    pub cfg_scale:       Option<f32>,
+    /// As written by the tool ("DPM++ 2M", "dpmpp_2m", …)
+    pub sampler:         Option<String>,
+    /// The same sampler as a local_sd `--sampling-method` name, when sd.cpp has it
+    pub sd_sampler:      Option<String>,
+    pub scheduler:       Option<String>,
+    pub width:           Option<u32>,
+    pub height:          Option<u32>,
+    pub model:           Option<String>,
+    pub model_hash:      Option<String>,
+    /// img2img / hires denoising strength
+    pub denoise:         Option<f32>,
+    /// Remaining A1111 settings (Lora hashes, Clip skip, Version, …)
+    pub extra:           BTreeMap<String, String>,
+    /// The text the parameters were read from
+    pub raw:             String,
+}
+
+// ── Tauri commands ─────────────────────────────────────────────────────────
+
+/// `path_or_base64`: a file path, raw base64 or a data: URL.
+#[tauri::command]
+pub async fn read_generation_metadata(path_or_base64: String) -> Result<GenerationMetadata, AppError> {
+    tokio::task::spawn_blocking(move || {
+        let bytes = load(&path_or_base64)?;
+        parse_image(&bytes)
+    })
+    .await?
+    .map_err(AppError::from)
+}
+
+fn load(input: &str) -> Result<Vec<u8>, String> {
+    let input = input.trim();
+    let path = std::path::Path::new(input);
+    if input.len() < 4096 && path.is_file() {
+        return std::fs::read(path).map_err(|e| format!("Cannot read {}: {}", input, e));
+    }
+    let b64 = input.split_once("base64,").map_or(input, |(_, d)| d);
+    general_purpose::STANDARD.decode(b64.trim())
+        .map_err(|_| "Not an existing file or valid base64 image data".to_string())
+}
+
+fn parse_image(bytes: &[u8]) -> Result<GenerationMetadata, String> {
+    let texts = if bytes.starts_with(PNG_SIGNATURE) {
+        png_text_chunks(bytes)
+    } else {
+        exif_user_comment(bytes).map(|c| vec![("parameters".to_string(), c)]).unwrap_or_default()
+    };
+    let find = |key: &str| texts.iter().find(|(k, _)| k == key).map(|(_, v)| v.as_str());
+
+    if let Some(params) = find("parameters").filter(|p| !p.trim().is_empty()) {
+        return Ok(parse_a1111(params));
+    }
+    if let Some(graph) = find("prompt").and_then(|p| serde_json::from_str::<Value>(p).ok()) {
+        if let Some(meta) = parse_comfy(&graph) {
+            return Ok(meta);
+        }
+    }
+    if find("workflow").is_some() {
+        return Err("The image only carries a ComfyUI UI workflow, without the executed prompt graph".into());
+    }
+    Err("No generation parameters found in this image (A1111, Forge and ComfyUI PNGs are supported; \
+         many sites strip metadata on upload)".into())
+}
+
+// ── PNG / EXIF ─────────────────────────────────────────────────────────────
+
+/// (keyword, text) of every tEXt / zTXt / iTXt chunk.
+fn png_text_chunks(bytes: &[u8]) -> Vec<(String, String)> {
+    let mut out = Vec::new();
+    let mut pos = PNG_SIGNATURE.len();
+    while pos + 8 <= bytes.len() {
+        let len = u32::from_be_bytes([bytes[pos], bytes[pos + 1], bytes[pos + 2], bytes[pos + 3]]) as usize;
+        let kind = &bytes[pos + 4..pos + 8];
+        let start = pos + 8;
+        let Some(data) = bytes.get(start..start.saturating_add(len)) else { break };
+        match kind {
+            b"tEXt" => {
+                if let Some((key, text)) = split_nul(data) {
+                    out.push((latin1(key), latin1(text)));
+                }
+            }
+            b"zTXt" => {
+                // keyword \0 method(1) zlib-data
+                if let Some((key, rest)) = split_nul(data) {
+                    if let Some(text) = rest.get(1..).and_then(inflate) {
+                        out.push((latin1(key), latin1(&text)));
+                    }
+                }
+            }
+            b"iTXt" => {
+                // keyword \0 compressed(1) method(1) language \0 translated \0 text
+                if let Some((key, rest)) = split_nul(data) {
+                    let compressed = rest.first() == Some(&1);
+                    let text = rest.get(2..)
+                        .and_then(split_nul).map(|(_, r)| r)
+                        .and_then(split_nul).map(|(_, r)| r);
+                    let text = match (text, compressed) {
+                        (Some(t), true)  => inflate(t),
+                        (Some(t), false) => Some(t.to_vec()),
+                        (None, _)        => None,
+                    };
+                    if let Some(t) = text {
+                        out.push((latin1(key), String::from_utf8_lossy(&t).to_string()));
+                    }
+                }
+            }
+            b"IEND" => break,
+            _ => {}
+        }
+        pos = start + len + 4; // data + CRC
+    }
+    out
+}
+
+/// A1111 writes JPEG / WebP parameters to EXIF UserComment, which starts
+/// with an 8-byte charset tag; "UNICODE" text is UTF-16 (big-endian from
+/// piexif, little-endian from some other writers).
+fn exif_user_comment(bytes: &[u8]) -> Option<String> {
+    if let Some(i) = find(bytes, b"UNICODE\0") {
+        let data = &bytes[i + 8..];
+        let decode = |be: bool| -> String {
+            let units: Vec<u16> = data.chunks_exact(2)
+                .map(|c| if be { u16::from_be_bytes([c[0], c[1]]) } else { u16::from_le_bytes([c[0], c[1]]) })
+                .take_while(|&u| u != 0)
+                .collect();
+            String::from_utf16_lossy(&units)
+        };
+        // The wrong byte order turns ASCII into CJK-range code units
+        let (be, le) = (decode(true), decode(false));
+        let ascii = |s: &str| s.chars().filter(char::is_ascii).count();
+        let text = if ascii(&be) >= ascii(&le) { be } else { le };
+        return (!text.trim().is_empty()).then_some(text);
+    }
+    let i = find(bytes, b"ASCII\0\0\0")?;
+    let text: Vec<u8> = bytes[i + 8..].iter().copied().take_while(|&b| b != 0).collect();
+    let text = String::from_utf8_lossy(&text).to_string();
+    (!text.trim().is_empty()).then_some(text)
+}
+
+fn split_nul(data: &[u8]) -> Option<(&[u8], &[u8])> {
+    let i = data.iter().position(|&b| b == 0)?;
+    Some((&data[..i], &data[i + 1..]))
+}
+
+fn latin1(bytes: &[u8]) -> String {
+    bytes.iter().map(|&b| b as char).collect()
+}
+
+fn inflate(data: &[u8]) -> Option<Vec<u8>> {
+    let mut out = Vec::new();
+    flate2::read::ZlibDecoder::new(data).read_to_end(&mut out).ok()?;
+    Some(out)
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|w| w == needle)
+}
+
+/// Re-encodes a base64 PNG with a "parameters" chunk describing `record`,
+/// replacing any the generator wrote itself (sd.cpp has its own format).
+/// Anything that isn't a PNG is returned unchanged.
+pub(crate) fn embed_parameters(png_base64: &str, record: &GenerationRecord) -> String {
+    let Ok(bytes) = general_purpose::STANDARD.decode(png_base64.trim()) else { return png_base64.to_string() };
+    if !bytes.starts_with(PNG_SIGNATURE) || bytes.len() < 33 {
+        return png_base64.to_string();
+    }
+    let width = u32::from_be_bytes([bytes[16], bytes[17], bytes[18], bytes[19]]);
+    let height = u32::from_be_bytes([bytes[20], bytes[21], bytes[22], bytes[23]]);
+    let text = format_a1111(record, width, height);
+    match insert_text_chunk(&bytes, "parameters", &text) {
+        Some(out) => general_purpose::STANDARD.encode(out),
+        None => png_base64.to_string(),
+    }
+}
+
+/// Copies the PNG with `key` set to `text`: old chunks with that keyword
+/// are dropped and the new one goes right after IHDR. tEXt when the text is
+/// Latin-1, iTXt (UTF-8) otherwise.
+fn insert_text_chunk(bytes: &[u8], key: &str, text: &str) -> Option<Vec<u8>> {
+    let mut out = PNG_SIGNATURE.to_vec();
+    let mut pos = PNG_SIGNATURE.len();
+    while pos + 8 <= bytes.len() {
+        let len = u32::from_be_bytes([bytes[pos], bytes[pos + 1], bytes[pos + 2], bytes[pos + 3]]) as usize;
+        let kind = &bytes[pos + 4..pos + 8];
+        let end = pos.checked_add(12 + len).filter(|&e| e <= bytes.len())?;
+        let keyword = bytes.get(pos + 8..end - 4).and_then(split_nul).map(|(k, _)| k);
+        let is_old = matches!(kind, b"tEXt" | b"zTXt" | b"iTXt") && keyword == Some(key.as_bytes());
+        if !is_old {
+            out.extend_from_slice(&bytes[pos..end]);
+        }
+        if kind == b"IHDR" {
+            let latin = text.chars().all(|c| (c as u32) < 256);
+            let mut data = key.as_bytes().to_vec();
+            data.push(0);
+            if latin {
+                data.extend(text.chars().map(|c| c as u8));
+                out.extend(chunk(b"tEXt", &data));
+            } else {
+                // compression flag, method, empty language and translated keyword
+                data.extend_from_slice(&[0, 0, 0, 0]);
+                data.extend_from_slice(text.as_bytes());
+                out.extend(chunk(b"iTXt", &data));
+            }
+        }
+        if kind == b"IEND" {
+            return Some(out);
+        }
+        pos = end;
+    }
+    None
+}
+
+fn chunk(kind: &[u8; 4], data: &[u8]) -> Vec<u8> {
+    let mut crc = flate2::Crc::new();
+    crc.update(kind);
+    crc.update(data);
+    let mut c = (data.len() as u32).to_be_bytes().to_vec();
+    c.extend_from_slice(kind);
+    c.extend_from_slice(data);
+    c.extend_from_slice(&crc.sum().to_be_bytes());
+    c
+}
+
+/// The A1111 text: prompt, "Negative prompt: …", then one settings line.
+fn format_a1111(r: &GenerationRecord, width: u32, height: u32) -> String {
+    let mut text = r.prompt.trim().to_string();
+    if let Some(neg) = r.negative_prompt.as_deref().map(str::trim).filter(|n| !n.is_empty()) {
+        text.push_str(&format!("\nNegative prompt: {}", neg));
+    }
+    let mut fields: Vec<(&str, String)> = Vec::new();
+    if let Some(steps) = r.steps { fields.push(("Steps", steps.to_string())); }
+    if let Some(sampler) = &r.sampler { fields.push(("Sampler", a1111_sampler_name(sampler))); }
+    if let Some(cfg) = r.cfg_scale { fields.push(("CFG scale", format!("{}", cfg))); }
+    if let Some(seed) = r.seed { fields.push(("Seed", seed.to_string())); }
+    // Without "Steps" first, "Size" starts the line (see parse_a1111)
+    let size = ("Size", format!("{}x{}", width, height));
+    if r.steps.is_some() { fields.push(size) } else { fields.insert(0, size) }
+    if let Some(model) = &r.model {
+        let stem = model.rsplit_once('.').filter(|(_, ext)| ext.len() <= 12).map_or(model.as_str(), |(s, _)| s);
+        fields.push(("Model", stem.to_string()));
+    }
+    fields.push(("Provider", r.provider.clone()));
+    let settings: Vec<String> = fields.into_iter()
+        .map(|(k, v)| if v.contains(',') || v.contains(':') { format!("{}: \"{}\"", k, v.replace('"', "'")) } else { format!("{}: {}", k, v) })
+        .collect();
+    format!("{}\n{}", text, settings.join(", "))
+}
+
+// ── A1111 / Forge ──────────────────────────────────────────────────────────
+
+/// "prompt…\nNegative prompt: …\nSteps: 20, Sampler: Euler a, CFG scale: 7, …".
+/// The settings line is the last one starting with "Steps:" (or a final
+/// "Size:" line, as written for cloud images); everything between
+/// "Negative prompt:" and it is the negative prompt.
+fn parse_a1111(text: &str) -> GenerationMetadata {
+    let text = text.trim();
+    let lines: Vec<&str> = text.lines().collect();
+    // Cloud images we stamped have no steps; their line starts at "Size:"
+    let settings_at = lines.iter().rposition(|l| l.trim_start().starts_with("Steps:"))
+        .or_else(|| lines.len().checked_sub(1).filter(|&i| i > 0 && lines[i].starts_with("Size: ")));
+    let body = &lines[..settings_at.unwrap_or(lines.len())];
+    let neg_at = body.iter().position(|l| l.trim_start().starts_with("Negative prompt:"));
+
+    let mut meta = GenerationMetadata { source: "a1111".into(), raw: text.to_string(), ..Default::default() };
+    meta.prompt = body[..neg_at.unwrap_or(body.len())].join("\n").trim().to_string();
+    if let Some(i) = neg_at {
+        let mut neg = body[i..].join("\n");
+        neg = neg.trim_start().trim_start_matches("Negative prompt:").to_string();
+        meta.negative_prompt = neg.trim().to_string();
+    }
+
+    let Some(settings) = settings_at.map(|i| lines[i]) else { return meta };
+    for (key, value) in settings_fields(settings) {
+        match key.as_str() {
+            "Steps"              => meta.steps = value.parse().ok(),
+            "Sampler"            => meta.sampler = Some(value),
+            "Schedule type"      => meta.scheduler = Some(value),
+            "CFG scale"          => meta.cfg_scale = value.parse().ok(),
+            "Seed"               => meta.seed = value.parse().ok(),
+            "Size"               => {
+                if let Some((w, h)) = value.split_once('x') {
+                    meta.width = w.trim().parse().ok();
+                    meta.height = h.trim().parse().ok();
+                }
+            }
+            "Model"              => meta.model = Some(value),
+            "Model hash"         => meta.model_hash = Some(value),
+            "Denoising strength" => meta.denoise = value.parse().ok(),
+            _ => {
+                // Forge stamps its version as "f2.0.1…"
+                if key == "Version" && value.starts_with('f') {
+                    meta.source = "forge".into();
+                }
+                meta.extra.insert(key, value);
+            }
+        }
+    }
+    // Older A1111 folds the scheduler into the sampler name ("DPM++ 2M Karras")
+    if meta.scheduler.is_none() {
+        if let Some(s) = meta.sampler.as_deref().and_then(|s| s.strip_suffix(" Karras")) {
+            meta.sampler = Some(s.to_string());
+            meta.scheduler = Some("Karras".into());
+        }
+    }
+    meta.sd_sampler = meta.sampler.as_deref().and_then(sd_sampler_name);
+    meta
+}
+
+/// Splits "Key: value, Key2: \"quoted, value\", …" into pairs.
+fn settings_fields(line: &str) -> Vec<(String, String)> {
+    let mut out = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut push = |field: &mut String| {
+        if let Some((k, v)) = field.split_once(':') {
+            out.push((k.trim().to_string(), v.trim().trim_matches('"').to_string()));
+        }
+        field.clear();
+    };
+    for c in line.chars() {
+        match c {
+            '"' => { quoted = !quoted; field.push(c); }
+            ',' if !quoted => push(&mut field),
+            _ => field.push(c),
+        }
+    }
+    push(&mut field);
+    out
+}
+
+// ── ComfyUI ────────────────────────────────────────────────────────────────
+
+/// Reads the first sampler node of an API-format graph
+/// (`{ "3": { "class_type": "KSampler", "inputs": { … } }, … }`). Inputs
+/// that are links (`["6", 0]`) are followed to the producing node.
+fn parse_comfy(graph: &Value) -> Option<GenerationMetadata> {
+    let nodes = graph.as_object()?;
+    let (_, sampler) = nodes.iter()
+        .filter(|(_, n)| n["class_type"].as_str().is_some_and(|c| c.starts_with("KSampler") || c == "SamplerCustom"))
+        .min_by_key(|(id, _)| id.parse::<u64>().unwrap_or(u64::MAX))?;
+    let inputs = &sampler["inputs"];
+    let node = |link: &Value| -> Option<&Value> { nodes.get(link[0].as_str()?) };
+    // A widget value, or the value of a primitive / constant node it links to
+    let value = |v: &Value| -> Option<Value> {
+        if v.is_array() {
+            let n = node(v)?;
+            ["value", "seed", "noise_seed", "int", "float", "string", "text"].iter()
+                .find_map(|k| n["inputs"].get(*k).filter(|x| !x.is_array()).cloned())
+        } else {
+            Some(v.clone())
+        }
+    };
+    let text_of = |link: &Value| -> String {
+        // CLIPTextEncode(.text), SDXL encoders (.text_g), or a text node chain
+        let Some(n) = node(link) else { return String::new() };
+        ["text", "text_g", "prompt"].iter()
+            .find_map(|k| n["inputs"].get(*k).and_then(|t| value(t)).and_then(|t| t.as_str().map(String::from)))
+            .unwrap_or_default()
+    };
+
+    let mut meta = GenerationMetadata { source: "comfyui".into(), raw: graph.to_string(), ..Default::default() };
+    meta.prompt = text_of(&inputs["positive"]);
+    meta.negative_prompt = text_of(&inputs["negative"]);
+    meta.seed = ["seed", "noise_seed"].iter()
+        .find_map(|k| inputs.get(*k).and_then(|v| value(v)).and_then(|v| v.as_i64()));
+    meta.steps = inputs.get("steps").and_then(|v| value(v)).and_then(|v| v.as_u64()).map(|s| s as u32);
+    meta.cfg_scale = inputs.get("cfg").and_then(|v| value(v)).and_then(|v| v.as_f64()).map(|c| c as f32);
+    meta.sampler = inputs.get("sampler_name").and_then(|v| value(v)).and_then(|v| v.as_str().map(String::from));
+    meta.scheduler = inputs.get("scheduler").and_then(|v| value(v)).and_then(|v| v.as_str().map(String::from));
+    meta.denoise = inputs.get("denoise").and_then(|v| value(v)).and_then(|v| v.as_f64()).map(|d| d as f32);
+    meta.sd_sampler = meta.sampler.as_deref().and_then(sd_sampler_name);
+
+    if let Some(latent) = node(&inputs["latent_image"]) {
+        meta.width = latent["inputs"].get("width").and_then(|v| value(v)).and_then(|v| v.as_u64()).map(|w| w as u32);
+        meta.height = latent["inputs"].get("height").and_then(|v| value(v)).and_then(|v| v.as_u64()).map(|h| h as u32);
+    }
+    meta.model = nodes.values().find_map(|n| {
+        ["ckpt_name", "unet_name"].iter().find_map(|k| n["inputs"].get(*k).and_then(Value::as_str).map(String::from))
+    });
+    Some(meta)
+}
+
+// ── Samplers ───────────────────────────────────────────────────────────────
+
+/// A1111 / ComfyUI sampler name → stable-diffusion.cpp --sampling-method.
+fn sd_sampler_name(name: &str) -> Option<String> {
+    let key: String = name.to_lowercase().chars().filter(|c| c.is_ascii_alphanumeric() || *c == '+').collect();
+    let sd = match key.as_str() {
+        "eulera" | "eulerancestral"                  => "euler_a",
+        "euler"                                      => "euler",
+        "heun"                                       => "heun",
+        "dpm2"                                       => "dpm2",
+        "dpm++2sa" | "dpmpp2sancestral"              => "dpm++2s_a",
+        "dpm++2m" | "dpmpp2m"                        => "dpm++2m",
+        "dpm++2mv2"                                  => "dpm++2mv2",
+        "ipndm"                                      => "ipndm",
+        "ipndmv"                                     => "ipndm_v",
+        "lcm"                                        => "lcm",
+        "ddim" | "ddimtrailing"                      => "ddim_trailing",
+        "tcd"                                        => "tcd",
+        _                                            => return None,
+    };
+    Some(sd.to_string())
+}
+
+/// stable-diffusion.cpp --sampling-method → A1111 display name.
+pub(crate) fn a1111_sampler_name(name: &str) -> String {
+    match name {
+        "euler_a"       => "Euler a",
+        "euler"         => "Euler",
+        "heun"          => "Heun",
+        "dpm2"          => "DPM2",
+        "dpm++2s_a"     => "DPM++ 2S a",
+        "dpm++2m"       => "DPM++ 2M",
+        "lcm"           => "LCM",
+        "ipndm"         => "iPNDM",
+        "ddim_trailing" => "DDIM",
+        other           => other,
+    }
+    .to_string()
+}
+
+// ── Unit tests ─────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    fn png(chunks: &[Vec<u8>]) -> Vec<u8> {
+        let mut out = PNG_SIGNATURE.to_vec();
+        out.extend(chunk(b"IHDR", &[0; 13]));
+        for c in chunks { out.extend_from_slice(c); }
+        out.extend(chunk(b"IEND", &[]));
+        out
+    }
+
+    const A1111: &str = "masterpiece, a red fox\nin the snow\nNegative prompt: blurry,\nlowres\n\
+        Steps: 28, Sampler: DPM++ 2M Karras, CFG scale: 6.5, Seed: 1234567890, Size: 832x1216, \
+        Model hash: 31e35c80fc, Model: sd_xl_base_1.0, Lora hashes: \"fox: abc123, snow: def456\", Version: v1.10.1";
+
+    #[test]
+    fn test_parse_a1111_parameters() {
+        let m = parse_a1111(A1111);
+        assert_eq!(m.source, "a1111");
+        assert_eq!(m.prompt, "masterpiece, a red fox\nin the snow");
+        assert_eq!(m.negative_prompt, "blurry,\nlowres");
+        assert_eq!((m.steps, m.seed, m.cfg_scale), (Some(28), Some(1234567890), Some(6.5)));
+        assert_eq!((m.width, m.height), (Some(832), Some(1216)));
+        assert_eq!(m.sampler.as_deref(), Some("DPM++ 2M"));
+        assert_eq!(m.scheduler.as_deref(), Some("Karras"));
+        assert_eq!(m.sd_sampler.as_deref(), Some("dpm++2m"));
+        assert_eq!(m.model.as_deref(), Some("sd_xl_base_1.0"));
+        assert_eq!(m.extra.get("Lora hashes").map(String::as_str), Some("fox: abc123, snow: def456"));
+
+        let forge = parse_a1111("a cat\nSteps: 20, Sampler: Euler a, Version: f2.0.1v1.10.1");
+        assert_eq!(forge.source, "forge");
+        assert_eq!(forge.sd_sampler.as_deref(), Some("euler_a"));
+        assert_eq!(forge.negative_prompt, "");
+    }
+
+    #[test]
+    fn test_png_text_chunks_all_kinds() {
+        let mut z = flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+        z.write_all(b"compressed text").unwrap();
+        let mut ztxt = b"ztext\0\0".to_vec();
+        ztxt.extend(z.finish().unwrap());
+        let bytes = png(&[
+            chunk(b"tEXt", b"parameters\0a fox\nSteps: 5"),
+            chunk(b"zTXt", &ztxt),
+            chunk(b"iTXt", "itext\0\0\0en\0\0caf\u{e9}".as_bytes()),
+        ]);
+        let texts = png_text_chunks(&bytes);
+        assert_eq!(texts[0], ("parameters".into(), "a fox\nSteps: 5".into()));
+        assert_eq!(texts[1], ("ztext".into(), "compressed text".into()));
+        assert_eq!(texts[2], ("itext".into(), "caf\u{e9}".into()));
+        assert_eq!(parse_image(&bytes).unwrap().steps, Some(5));
+    }
+
+    #[test]
+    fn test_parse_comfy_graph() {
+        let graph = serde_json::json!({
+            "3": { "class_type": "KSampler", "inputs": {
+                "seed": 42, "steps": 25, "cfg": 4.5, "sampler_name": "dpmpp_2m", "scheduler": "karras",
+                "denoise": 1.0, "model": ["4", 0], "positive": ["6", 0], "negative": ["7", 0], "latent_image": ["5", 0] } },
+            "4": { "class_type": "CheckpointLoaderSimple", "inputs": { "ckpt_name": "dreamshaper_8.safetensors" } },
+            "5": { "class_type": "EmptyLatentImage", "inputs": { "width": 768, "height": 512, "batch_size": 1 } },
+            "6": { "class_type": "CLIPTextEncode", "inputs": { "text": ["8", 0], "clip": ["4", 1] } },
+            "7": { "class_type": "CLIPTextEncode", "inputs": { "text": "ugly", "clip": ["4", 1] } },
+            "8": { "class_type": "PrimitiveString", "inputs": { "value": "a lighthouse at dusk" } },
+        });
+        let bytes = png(&[chunk(b"tEXt", format!("prompt\0{}", graph).as_bytes())]);
+        let m = parse_image(&bytes).unwrap();
+        assert_eq!(m.source, "comfyui");
+        assert_eq!(m.prompt, "a lighthouse at dusk");
+        assert_eq!(m.negative_prompt, "ugly");
+        assert_eq!((m.seed, m.steps, m.cfg_scale), (Some(42), Some(25), Some(4.5)));
+        assert_eq!(m.sd_sampler.as_deref(), Some("dpm++2m"));
+        assert_eq!((m.width, m.height), (Some(768), Some(512)));
+        assert_eq!(m.model.as_deref(), Some("dreamshaper_8.safetensors"));
+    }
+
+    #[test]
+    fn test_exif_user_comment_and_errors() {
+        let mut jpeg = b"\xff\xd8\xff\xe1....Exif\0\0UNICODE\0".to_vec();
+        for u in "a dog\nSteps: 12".encode_utf16() {
+            jpeg.extend_from_slice(&u.to_be_bytes());
+        }
+        jpeg.extend_from_slice(&[0, 0, 0xff, 0xd9]);
+        assert_eq!(parse_image(&jpeg).unwrap().steps, Some(12));
+
+        assert!(parse_image(&png(&[])).unwrap_err().contains("No generation parameters"));
+        let b64 = general_purpose::STANDARD.encode(png(&[chunk(b"tEXt", b"parameters\0x\nSteps: 3")]));
+        assert_eq!(load(&format!("data:image/png;base64,{}", b64)).unwrap().len(), general_purpose::STANDARD.decode(&b64).unwrap().len());
+    }
+
+    #[test]
+    fn test_embed_parameters_roundtrip() {
+        let mut png = Vec::new();
+        image::DynamicImage::new_rgb8(6, 4)
+            .write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+            .unwrap();
+        // A chunk the generator wrote itself is replaced
+        let mut with_old = png[..33].to_vec();
+        with_old.extend(chunk(b"tEXt", b"parameters\0sd.cpp text"));
+        with_old.extend_from_slice(&png[33..]);
+
+        let record = GenerationRecord {
+            provider:        "native_sd".into(),
+            model:           Some("dreamshaper_8.safetensors".into()),
+            prompt:          "a red fox".into(),
+            negative_prompt: Some("blurry".into()),
+            seed:            Some(1234),
+            sampler:         Some("euler_a".into()),
+            steps:           Some(20),
+            cfg_scale:       Some(7.5),
+            revised_prompt:  None,
+        };
+        let out = general_purpose::STANDARD.decode(
+            embed_parameters(&general_purpose::STANDARD.encode(&with_old), &record),
+        ).unwrap();
+        // CRCs are valid: the png decoder accepts the file
+        assert_eq!(image::load_from_memory(&out).unwrap().width(), 6);
+        assert_eq!(png_text_chunks(&out).iter().filter(|(k, _)| k == "parameters").count(), 1);
+
+        let m = parse_image(&out).unwrap();
+        assert_eq!((m.prompt.as_str(), m.negative_prompt.as_str()), ("a red fox", "blurry"));
+        assert_eq!((m.seed, m.steps, m.cfg_scale), (Some(1234), Some(20), Some(7.5)));
+        assert_eq!((m.sd_sampler.as_deref(), m.width, m.height), (Some("euler_a"), Some(6), Some(4)));
+        assert_eq!(m.model.as_deref(), Some("dreamshaper_8"));
+
+        // Cloud images: no steps, UTF-8 prompt goes to iTXt
+        let cloud = GenerationRecord { provider: "dalle".into(), prompt: "кот в шляпе".into(), ..Default::default() };
+        let out = general_purpose::STANDARD.decode(embed_parameters(&general_purpose::STANDARD.encode(&png), &cloud)).unwrap();
+        let m = parse_image(&out).unwrap();
+        assert_eq!(m.prompt, "кот в шляпе");
+        assert_eq!(m.extra.get("Provider").map(String::as_str), Some("dalle"));
+        assert_eq!(embed_parameters("not a png", &cloud), "not a png");
+    }
+}
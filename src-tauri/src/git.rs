@@ -0,0 +1,211 @@
+// git.rs — thin wrapper over the `git` CLI for the repository-aware commands
+//
+// Shells out to the user's git rather than linking libgit2: it honours their
+// config (safe.directory, hooks path, credential helpers) and needs no extra
+// native dependency. Used by changelog.rs and git_assist.rs; no Tauri
+// commands of its own.
+
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Field / record separators for `git log --format`; never appear in messages.
+const FIELD_SEP: char  = '\u{1f}';
+const RECORD_SEP: char = '\u{1e}';
+
+// ── Types ──────────────────────────────────────────────────────────────────
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Commit {
+    pub hash:    String,
+    pub short:   String,
+    pub author:  String,
+    /// ISO-8601 author date
+    pub date:    String,
+    pub subject: String,
+    pub body:    String,
+    /// More than one parent
+    pub merge:   bool,
+}
+
+// ── Commands ───────────────────────────────────────────────────────────────
+
+/// Run `git -C <repo> <args…>` and return stdout.
+pub(crate) async fn run(repo: &Path, args: &[&str]) -> Result<String, String> {
+    let out = tokio::process::Command::new("git")
+        .arg("-C").arg(repo)
+        .args(args)
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .output()
+        .await
+        .map_err(|e| format!("Cannot run git (is it installed and on PATH?): {}", e))?;
+    if !out.status.success() {
+        let stderr = String::from_utf8_lossy(&out.stderr);
+        return Err(format!("git {} failed: {}", args.first().unwrap_or(&""), stderr.trim()));
+    }
+    Ok(String::from_utf8_lossy(&out.stdout).into_owned())
+}
+
+/// Commits in `range` ("v1.2.0..HEAD", "main..feature", a single ref…),
+/// newest first.
+pub(crate) async fn log(repo: &Path, range: &str) -> Result<Vec<Commit>, String> {
+    let format = format!("--format=%H{f}%h{f}%an{f}%aI{f}%P{f}%s{f}%b{r}", f = FIELD_SEP, r = RECORD_SEP);
+    let out = run(repo, &["log", &format, revision(range)?, "--"]).await?;
+    Ok(parse_log(&out))
+}
+
+/// `git show --stat` summary plus the patch, cut to `max_chars`.
+pub(crate) async fn show(repo: &Path, hash: &str, max_chars: usize) -> Result<String, String> {
+    let out = run(repo, &["show", "--stat", "--patch", "--format=", "--no-color", revision(hash)?, "--"]).await?;
+    Ok(truncate(out, max_chars))
+}
+
+/// Staged changes: (`--stat` summary, full patch).
+pub(crate) async fn staged_diff(repo: &Path) -> Result<(String, String), String> {
+    let stat = run(repo, &["diff", "--cached", "--stat", "--no-color"]).await?;
+    let patch = run(repo, &["diff", "--cached", "--no-color", "--no-ext-diff"]).await?;
+    Ok((stat, patch))
+}
+
+/// Changes in `range` ("main...HEAD", "HEAD~3..HEAD"): (`--stat`, patch).
+pub(crate) async fn diff(repo: &Path, range: &str) -> Result<(String, String), String> {
+    let range = revision(range)?;
+    let stat = run(repo, &["diff", "--stat", "--no-color", range, "--"]).await?;
+    let patch = run(repo, &["diff", "--no-color", "--no-ext-diff", range, "--"]).await?;
+    Ok((stat, patch))
+}
+
+/// A user-supplied revision or range; one starting with '-' would be
+/// parsed as an option (e.g. `--output=<file>` writes a file).
+fn revision(rev: &str) -> Result<&str, String> {
+    let rev = rev.trim();
+    if rev.is_empty() || rev.starts_with('-') {
+        return Err(format!("Invalid git revision: '{}'", rev));
+    }
+    Ok(rev)
+}
+
+/// Split a unified diff into (path, per-file patch) at `diff --git` headers.
+pub(crate) fn split_files(patch: &str) -> Vec<(String, &str)> {
+    let mut starts: Vec<usize> = patch.match_indices("diff --git ").map(|(i, _)| i)
+        .filter(|&i| i == 0 || patch.as_bytes()[i - 1] == b'\n')
+        .collect();
+    starts.push(patch.len());
+    starts.windows(2)
+        .map(|w| {
+            let chunk = &patch[w[0]..w[1]];
+            let header = chunk.lines().next().unwrap_or("");
+            // "diff --git a/x b/x" → "x" (the b/ side names renames' new path)
+            let path = header.rsplit_once(" b/").map(|(_, p)| p).unwrap_or(header).to_string();
+            (path, chunk)
+        })
+        .collect()
+}
+
+/// Commit the staged changes with `message`; returns the new short hash.
+pub(crate) async fn commit(repo: &Path, message: &str) -> Result<String, String> {
+    run(repo, &["commit", "-q", "-m", message]).await?;
+    Ok(run(repo, &["rev-parse", "--short", "HEAD"]).await?.trim().to_string())
+}
+
+/// Latest tag reachable from HEAD, if any.
+pub(crate) async fn latest_tag(repo: &Path) -> Option<String> {
+    run(repo, &["describe", "--tags", "--abbrev=0"]).await.ok()
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+}
+
+// ── Parsing ────────────────────────────────────────────────────────────────
+
+fn parse_log(out: &str) -> Vec<Commit> {
+    out.split(RECORD_SEP)
+        .filter_map(|record| {
+            let f: Vec<&str> = record.trim_start_matches('\n').splitn(7, FIELD_SEP).collect();
+            if f.len() < 7 {
+                return None;
+            }
+            Some(Commit {
+                hash:    f[0].to_string(),
+                short:   f[1].to_string(),
+                author:  f[2].to_string(),
+                date:    f[3].to_string(),
+                merge:   f[4].split_whitespace().count() > 1,
+                subject: f[5].to_string(),
+                body:    f[6].trim().to_string(),
+            })
+        })
+        .collect()
+}
+
+pub(crate) fn truncate(mut s: String, max_chars: usize) -> String {
+    if s.len() <= max_chars {
+        return s;
+    }
+    let mut cut = max_chars;
+    while !s.is_char_boundary(cut) { cut -= 1; }
+    s.truncate(cut);
+    s.push_str("\n[… truncated …]");
+    s
+}
+
+// ── Unit tests ─────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_log() {
+        let out = format!(
+            "abc123{f}abc{f}Ann{f}2024-05-01T10:00:00+02:00{f}p1{f}feat: add X{f}Longer body\n\nwith lines\n{r}\n\
+             def456{f}def{f}Bob{f}2024-05-02T10:00:00+02:00{f}p1 p2{f}Merge branch 'x'{f}{r}\n",
+            f = FIELD_SEP, r = RECORD_SEP
+        );
+        let commits = parse_log(&out);
+        assert_eq!(commits.len(), 2);
+        assert_eq!(commits[0].subject, "feat: add X");
+        assert_eq!(commits[0].body, "Longer body\n\nwith lines");
+        assert!(!commits[0].merge);
+        assert!(commits[1].merge);
+        assert_eq!(commits[1].body, "");
+    }
+
+    #[test]
+    fn test_revision_rejects_options() {
+        assert_eq!(revision(" v1.2.0..HEAD "), Ok("v1.2.0..HEAD"));
+        assert!(revision("--output=/tmp/x").is_err());
+        assert!(revision("-p").is_err());
+        assert!(revision("").is_err());
+    }
+
+    #[test]
+    fn test_split_files() {
+        let patch = "diff --git a/src/a.rs b/src/a.rs\n@@ -1 +1 @@\n-x\n+y\n\
+                     diff --git a/old.md b/new.md\nrename from old.md\n";
+        let files = split_files(patch);
+        assert_eq!(files.iter().map(|(p, _)| p.as_str()).collect::<Vec<_>>(), ["src/a.rs", "new.md"]);
+        assert!(files[0].1.ends_with("+y\n"));
+        assert!(split_files("").is_empty());
+    }
+
+    #[test]
+    fn test_truncate_on_char_boundary() {
+        assert_eq!(truncate("short".into(), 10), "short");
+        assert!(truncate("ééééé".into(), 3).starts_with('é'));
+    }
+
+    #[tokio::test]
+    async fn test_log_in_temp_repo() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = dir.path();
+        if run(repo, &["init", "-q"]).await.is_err() {
+            return; // git not installed
+        }
+        std::fs::write(repo.join("a.txt"), "a").unwrap();
+        run(repo, &["add", "."]).await.unwrap();
+        run(repo, &["-c", "user.name=T", "-c", "user.email=t@t", "commit", "-q", "-m", "fix(core): handle y"]).await.unwrap();
+        let commits = log(repo, "HEAD").await.unwrap();
+        assert_eq!(commits.len(), 1);
+        assert_eq!(commits[0].subject, "fix(core): handle y");
+        assert!(show(repo, &commits[0].hash, 10_000).await.unwrap().contains("a.txt"));
+    }
+}
@@ -0,0 +1,354 @@
+// git_assist.rs — model-assisted git chores for the open project
+//
+// Diffs are read through git.rs, capped in size and passed through
+// secret_scanner before they reach the model. PR drafts and reviews also get
+// the cached project overview from path_summary.rs as context.
+//
+// Tauri commands exposed:
+//   suggest_commit_message → CommitSuggestions (2–3 candidates for the staged diff)
+//   git_commit             → short hash of the new commit
+//   draft_pr_description   → DiffReport (title + markdown body + per-file notes)
+//   review_diff            → DiffReport (review summary + per-file comments)
+
+use crate::ai_bridge::{self, AiRequest};
+use crate::error::AppError;
+use crate::{git, path_summary, secret_scanner};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::Path;
+
+/// Patch characters sent to the model; the --stat summary always goes in full
+const MAX_DIFF_CHARS: usize = 24_000;
+const DEFAULT_CANDIDATES: usize = 3;
+/// Per-file patch cap when packing a branch diff
+const MAX_FILE_DIFF_CHARS: usize = 6_000;
+/// Project overview characters included as context
+const MAX_OVERVIEW_CHARS: usize = 6_000;
+
+const PR_INSTRUCTIONS: &str = "You write pull request descriptions. From the commits and \
+diff, reply with JSON only: {\"title\": \"<short PR title>\", \"markdown\": \"<PR body in \
+Markdown: a 1–2 sentence summary, a Changes list, and a Testing section with what a \
+reviewer should check>\", \"comments\": [{\"file\": \"<path>\", \"line\": null, \
+\"severity\": \"info\", \"comment\": \"<what changed in this file and why>\"}]}";
+
+const REVIEW_INSTRUCTIONS: &str = "You are a careful code reviewer. Review the diff for bugs, \
+missing error handling, security problems and unclear code; skip style nits. Reply with \
+JSON only: {\"markdown\": \"<overall review in Markdown>\", \"comments\": [{\"file\": \
+\"<path>\", \"line\": <line number in the new file or null>, \"severity\": \"issue\" | \
+\"warning\" | \"suggestion\", \"comment\": \"...\"}]}";
+
+// ── Types ──────────────────────────────────────────────────────────────────
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CommitMessageRequest {
+    pub repo_path:  String,
+    /// Number of candidates, 2–3 (default 3)
+    pub candidates: Option<usize>,
+    /// "conventional" (default) | "plain"
+    pub style:      Option<String>,
+    pub provider:   String,
+    pub api_key:    String,
+    pub model:      Option<String>,
+    pub local_url:  Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CommitSuggestions {
+    pub candidates: Vec<String>,
+    /// The patch was cut to MAX_DIFF_CHARS
+    pub truncated:  bool,
+    /// Secrets removed from the diff before it was sent
+    pub redacted:   Vec<secret_scanner::SecretFinding>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DiffRequest {
+    pub repo_path:   String,
+    /// draft_pr_description: branch the PR targets (default "main")
+    pub base_branch: Option<String>,
+    /// review_diff: revision range (default: staged changes)
+    pub range:       Option<String>,
+    pub provider:    String,
+    pub api_key:     String,
+    pub model:       Option<String>,
+    pub local_url:   Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct FileComment {
+    pub file:     String,
+    /// Line in the new version of the file, when the model gave one
+    pub line:     Option<u32>,
+    /// "info" | "suggestion" | "warning" | "issue"
+    pub severity: String,
+    pub comment:  String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DiffReport {
+    /// PR title (draft_pr_description only)
+    pub title:         Option<String>,
+    pub markdown:      String,
+    pub comments:      Vec<FileComment>,
+    pub files_changed: usize,
+    /// Some file patches were cut or left out
+    pub truncated:     bool,
+    pub redacted:      Vec<secret_scanner::SecretFinding>,
+}
+
+// ── Tauri commands ─────────────────────────────────────────────────────────
+
+#[tauri::command]
+pub async fn suggest_commit_message(req: CommitMessageRequest) -> Result<CommitSuggestions, AppError> {
+    let repo = Path::new(&req.repo_path);
+    let (stat, patch) = git::staged_diff(repo).await?;
+    if patch.trim().is_empty() {
+        return Err("Nothing is staged — stage changes with `git add` first".into());
+    }
+
+    let truncated = patch.len() > MAX_DIFF_CHARS;
+    let scanned = secret_scanner::redact(&git::truncate(patch, MAX_DIFF_CHARS));
+    let count = req.candidates.unwrap_or(DEFAULT_CANDIDATES).clamp(2, 3);
+    let style = match req.style.as_deref() {
+        Some("plain") => "a short imperative subject line (max 72 characters)",
+        _             => "a Conventional Commits subject line (`type(scope): description`, max 72 characters)",
+    };
+    let system = format!(
+        "You write git commit messages. Given a staged diff, write {} alternative commit \
+         messages. Each has {}, then optionally a blank line and a body of 1–3 lines \
+         explaining why, wrapped at 72 columns. Describe what changed, not how you \
+         found it. Reply with a JSON array of strings only.",
+        count, style
+    );
+
+    let ai_req = AiRequest {
+        api_key:       req.api_key.clone(),
+        prompt:        format!("Files:\n{}\nDiff:\n{}", stat, scanned.text),
+        system_prompt: Some(system),
+        image_base64:  None,
+        context_files: None,
+        model:         req.model.clone(),
+        max_tokens:    Some(600),
+        allow_secrets: false,
+        profile:       None,
+    };
+    let resp = ai_bridge::complete(&req.provider, ai_req, req.local_url.as_deref()).await?;
+    let mut candidates = parse_candidates(&resp.text);
+    candidates.truncate(count);
+    if candidates.is_empty() {
+        return Err("The model returned no commit message".into());
+    }
+
+    if !scanned.findings.is_empty() {
+        log::warn!("suggest_commit_message: redacted {} secret(s) from the staged diff", scanned.findings.len());
+    }
+    Ok(CommitSuggestions { candidates, truncated, redacted: scanned.findings })
+}
+
+/// Commit the staged changes with the chosen message.
+#[tauri::command]
+pub async fn git_commit(repo_path: String, message: String) -> Result<String, AppError> {
+    let message = message.trim();
+    if message.is_empty() {
+        return Err("Commit message is empty".into());
+    }
+    let hash = git::commit(Path::new(&repo_path), message).await?;
+    log::info!("git_commit: {} in {}", hash, repo_path);
+    Ok(hash)
+}
+
+/// Title and description for the current branch against `base_branch`.
+#[tauri::command]
+pub async fn draft_pr_description(app_handle: tauri::AppHandle, req: DiffRequest) -> Result<DiffReport, AppError> {
+    let repo = Path::new(&req.repo_path);
+    let base = req.base_branch.as_deref().map(str::trim).filter(|b| !b.is_empty()).unwrap_or("main");
+    let commits = git::log(repo, &format!("{}..HEAD", base)).await?;
+    if commits.is_empty() {
+        return Err(format!("No commits on this branch since {}", base).into());
+    }
+    let (stat, patch) = git::diff(repo, &format!("{}...HEAD", base)).await?;
+    let log_text = commits.iter()
+        .filter(|c| !c.merge)
+        .map(|c| format!("- {}{}", c.subject, if c.body.is_empty() { String::new() } else { format!("\n  {}", c.body.replace('\n', "\n  ")) }))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let report = analyze_diff(&app_handle, &req, PR_INSTRUCTIONS, &format!("Commits:\n{}", log_text), &stat, &patch).await?;
+    log::info!("draft_pr_description: {} commits, {} files vs {}", commits.len(), report.files_changed, base);
+    Ok(report)
+}
+
+/// Review of `range` (or the staged changes) with per-file comments.
+#[tauri::command]
+pub async fn review_diff(app_handle: tauri::AppHandle, req: DiffRequest) -> Result<DiffReport, AppError> {
+    let repo = Path::new(&req.repo_path);
+    let (label, (stat, patch)) = match req.range.as_deref().map(str::trim).filter(|r| !r.is_empty()) {
+        Some(range) => (range.to_string(), git::diff(repo, range).await?),
+        None        => ("staged changes".to_string(), git::staged_diff(repo).await?),
+    };
+    if patch.trim().is_empty() {
+        return Err(format!("No changes in {}", label).into());
+    }
+    let report = analyze_diff(&app_handle, &req, REVIEW_INSTRUCTIONS, &format!("Reviewing: {}", label), &stat, &patch).await?;
+    log::info!("review_diff: {} — {} comments on {} files", label, report.comments.len(), report.files_changed);
+    Ok(report)
+}
+
+// ── Helpers ────────────────────────────────────────────────────────────────
+
+/// Pack overview + diff into one prompt, run the model and parse its JSON.
+async fn analyze_diff(
+    app:          &tauri::AppHandle,
+    req:          &DiffRequest,
+    instructions: &str,
+    preamble:     &str,
+    stat:         &str,
+    patch:        &str,
+) -> Result<DiffReport, String> {
+    let files = git::split_files(patch);
+    let (packed, truncated) = pack_diff(&files, MAX_DIFF_CHARS);
+    let scanned = secret_scanner::redact(&packed);
+
+    let mut prompt = String::new();
+    if let Some(overview) = path_summary::overview(app, &req.repo_path) {
+        prompt.push_str(&format!("Project overview:\n{}\n\n", git::truncate(overview, MAX_OVERVIEW_CHARS)));
+    }
+    prompt.push_str(&format!("{}\n\nFiles:\n{}\nDiff:\n{}", preamble, stat, scanned.text));
+
+    let ai_req = AiRequest {
+        api_key:       req.api_key.clone(),
+        prompt,
+        system_prompt: Some(instructions.to_string()),
+        image_base64:  None,
+        context_files: None,
+        model:         req.model.clone(),
+        max_tokens:    Some(3_000),
+        allow_secrets: false,
+        profile:       None,
+    };
+    let resp = ai_bridge::complete(&req.provider, ai_req, req.local_url.as_deref()).await?;
+    let (title, markdown, comments) = parse_report(&resp.text);
+    Ok(DiffReport { title, markdown, comments, files_changed: files.len(), truncated, redacted: scanned.findings })
+}
+
+/// Concatenate per-file patches, each capped at MAX_FILE_DIFF_CHARS, until
+/// `budget` is used; files that don't fit are named only.
+fn pack_diff(files: &[(String, &str)], budget: usize) -> (String, bool) {
+    let mut out = String::new();
+    let mut truncated = false;
+    let mut omitted = Vec::new();
+    for (path, chunk) in files {
+        let part = git::truncate(chunk.to_string(), MAX_FILE_DIFF_CHARS);
+        truncated |= part.len() < chunk.len();
+        if out.len() + part.len() > budget {
+            omitted.push(path.as_str());
+            continue;
+        }
+        out.push_str(&part);
+        if !out.ends_with('\n') { out.push('\n'); }
+    }
+    if !omitted.is_empty() {
+        truncated = true;
+        out.push_str(&format!("\n[diff omitted for: {}]\n", omitted.join(", ")));
+    }
+    (out, truncated)
+}
+
+/// (title, markdown, comments) from the model's JSON; a non-JSON reply is
+/// used as the markdown with no comments.
+fn parse_report(text: &str) -> (Option<String>, String, Vec<FileComment>) {
+    let parsed = match (text.find('{'), text.rfind('}')) {
+        (Some(s), Some(e)) if s < e => serde_json::from_str::<Value>(&text[s..=e]).ok(),
+        _                           => None,
+    };
+    let Some(json) = parsed.filter(|j| j["markdown"].is_string()) else {
+        return (None, text.trim().to_string(), Vec::new());
+    };
+    let comments = json["comments"].as_array()
+        .map(|items| items.iter().filter_map(|c| {
+            let comment = c["comment"].as_str()?.trim();
+            if comment.is_empty() { return None; }
+            Some(FileComment {
+                file:     c["file"].as_str()?.trim().to_string(),
+                line:     c["line"].as_u64().map(|n| n as u32),
+                severity: match c["severity"].as_str() {
+                    Some(s @ ("info" | "suggestion" | "warning" | "issue")) => s.to_string(),
+                    _                                                       => "info".into(),
+                },
+                comment:  comment.to_string(),
+            })
+        }).collect())
+        .unwrap_or_default();
+    let title = json["title"].as_str().map(|t| t.trim().to_string()).filter(|t| !t.is_empty());
+    (title, json["markdown"].as_str().unwrap_or("").trim().to_string(), comments)
+}
+
+/// Candidates from the model's JSON array; a reply that is not JSON counts
+/// as one candidate.
+fn parse_candidates(text: &str) -> Vec<String> {
+    let parsed = match (text.find('['), text.rfind(']')) {
+        (Some(s), Some(e)) if s < e => serde_json::from_str::<Value>(&text[s..=e]).ok(),
+        _                           => None,
+    };
+    let list: Vec<String> = match parsed {
+        Some(Value::Array(items)) => items.iter().filter_map(|v| v.as_str()).map(clean_message).collect(),
+        _                         => vec![clean_message(text)],
+    };
+    list.into_iter().filter(|m| !m.is_empty()).collect()
+}
+
+/// Strip code fences and quotes models like to wrap messages in.
+fn clean_message(msg: &str) -> String {
+    msg.trim()
+        .trim_start_matches("```")
+        .trim_end_matches("```")
+        .trim()
+        .trim_matches('"')
+        .trim()
+        .to_string()
+}
+
+// ── Unit tests ─────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_candidates() {
+        let text = "```json\n[\"feat(ui): add dark mode\", \"fix: handle empty list\\n\\nAvoids a panic.\", \"\"]\n```";
+        assert_eq!(parse_candidates(text), vec![
+            "feat(ui): add dark mode".to_string(),
+            "fix: handle empty list\n\nAvoids a panic.".to_string(),
+        ]);
+        assert_eq!(parse_candidates("\"Update docs\""), vec!["Update docs".to_string()]);
+    }
+
+    #[test]
+    fn test_parse_report() {
+        let text = r#"Sure: {"title": "Add X", "markdown": "## Summary\nAdds X.", "comments": [
+            {"file": "src/x.rs", "line": 12, "severity": "issue", "comment": "unwrap on user input"},
+            {"file": "src/y.rs", "line": null, "severity": "nit", "comment": "rename"},
+            {"file": "src/z.rs", "comment": ""}]}"#;
+        let (title, md, comments) = parse_report(text);
+        assert_eq!(title.as_deref(), Some("Add X"));
+        assert_eq!(md, "## Summary\nAdds X.");
+        assert_eq!(comments.len(), 2);
+        assert_eq!((comments[0].line, comments[0].severity.as_str()), (Some(12), "issue"));
+        assert_eq!((comments[1].line, comments[1].severity.as_str()), (None, "info"));
+
+        let (title, md, comments) = parse_report("Looks good overall.");
+        assert_eq!((title, md.as_str(), comments.len()), (None, "Looks good overall.", 0));
+    }
+
+    #[test]
+    fn test_pack_diff_budget() {
+        let a = format!("diff --git a/a b/a\n{}", "+x\n".repeat(10));
+        let b = format!("diff --git a/b b/b\n{}", "+y\n".repeat(100));
+        let files = vec![("a".to_string(), a.as_str()), ("b".to_string(), b.as_str())];
+        let (packed, truncated) = pack_diff(&files, 100);
+        assert!(truncated);
+        assert!(packed.starts_with(&a));
+        assert!(packed.contains("[diff omitted for: b]"));
+        assert_eq!(pack_diff(&files, 10_000), (format!("{}{}", a, b), false));
+    }
+}
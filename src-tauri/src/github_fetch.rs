@@ -0,0 +1,270 @@
+// github_fetch.rs — GitHub pages as markdown via the REST API
+//
+// Scraping github.com returns navigation, sign-in prompts and diff chrome
+// around the part that matters, and long issue threads are cut off behind
+// "Load more". fetch_github recognizes the common URL shapes and asks the
+// API for the content itself:
+//
+//   github.com/<owner>/<repo>                    → README
+//   github.com/<owner>/<repo>/issues/<n>         → issue + comments
+//   github.com/<owner>/<repo>/pull/<n>           → PR description + comments
+//   github.com/<owner>/<repo>/blob/<ref>/<path>  → file contents
+//   github.com/<owner>/<repo>/tree/<ref>/<path>  → directory listing
+//   raw.githubusercontent.com/<owner>/<repo>/<ref>/<path> → file contents
+//
+// A token is optional; without one GitHub allows 60 requests an hour.
+// fetch_url_content (web_search.rs) also routes these URLs here, falling
+// back to the HTML scraper when the API call fails.
+//
+// Tauri commands exposed:
+//   fetch_github(url, token?, max_chars?) → markdown
+
+use crate::error::AppError;
+use crate::{http, web_search};
+use serde_json::Value;
+
+const GITHUB_API: &str = "https://api.github.com";
+const DEFAULT_MAX_CHARS: usize = 8_000;
+/// Comments read from an issue / PR thread (one API page)
+const MAX_COMMENTS: usize = 100;
+
+// ── Types ──────────────────────────────────────────────────────────────────
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum GithubTarget {
+    Repo  { owner: String, repo: String },
+    /// Issues and pull requests share the issues API
+    Issue { owner: String, repo: String, number: u64, pull: bool },
+    File  { owner: String, repo: String, reference: String, path: String },
+    Dir   { owner: String, repo: String, reference: String, path: String },
+}
+
+// ── Tauri command ──────────────────────────────────────────────────────────
+
+/// README, issue / PR thread or file behind a GitHub URL, as markdown.
+#[tauri::command]
+pub async fn fetch_github(
+    url:       String,
+    token:     Option<String>,
+    max_chars: Option<usize>,
+) -> Result<String, AppError> {
+    let target = parse_github_url(&url)
+        .ok_or_else(|| format!("Not a GitHub repository, issue, pull request or file URL: {}", url))?;
+    let text = fetch_target(&target, token.as_deref()).await?;
+    Ok(web_search::truncate_text(text, max_chars.unwrap_or(DEFAULT_MAX_CHARS)))
+}
+
+/// Markdown for `target`.
+pub(crate) async fn fetch_target(target: &GithubTarget, token: Option<&str>) -> Result<String, String> {
+    match target {
+        GithubTarget::Repo { owner, repo } => {
+            let readme = get_raw(&format!("/repos/{}/{}/readme", owner, repo), token).await?;
+            Ok(format!("# {}/{}\n\n{}", owner, repo, readme.trim()))
+        }
+        GithubTarget::Issue { owner, repo, number, pull } => {
+            let base = format!("/repos/{}/{}/issues/{}", owner, repo, number);
+            let issue = get_json(&base, token).await?;
+            let comments = get_json(&format!("{}/comments?per_page={}", base, MAX_COMMENTS), token).await?;
+            Ok(format_thread(&issue, &comments, *pull))
+        }
+        GithubTarget::File { owner, repo, reference, path } => {
+            let body = get_raw(&format!("/repos/{}/{}/contents/{}?ref={}", owner, repo, path, reference), token).await?;
+            Ok(format_file(path, &body))
+        }
+        GithubTarget::Dir { owner, repo, reference, path } => {
+            let listing = get_json(&format!("/repos/{}/{}/contents/{}?ref={}", owner, repo, path, reference), token).await?;
+            Ok(format_dir(owner, repo, path, &listing))
+        }
+    }
+}
+
+// ── API requests ───────────────────────────────────────────────────────────
+
+fn request(path: &str, token: Option<&str>, accept: &str) -> Result<reqwest::RequestBuilder, String> {
+    let client = http::client(http::Timeout::Api).map_err(|e| e.to_string())?;
+    let mut req = client
+        .get(format!("{}{}", GITHUB_API, path))
+        .header("Accept", accept)
+        .header("X-GitHub-Api-Version", "2022-11-28");
+    if let Some(token) = token.map(str::trim).filter(|t| !t.is_empty()) {
+        req = req.header("Authorization", format!("Bearer {}", token));
+    }
+    Ok(req)
+}
+
+async fn send(req: reqwest::RequestBuilder) -> Result<reqwest::Response, String> {
+    let resp = req.send().await.map_err(|e| format!("GitHub API error: {}", e))?;
+    let status = resp.status();
+    if status.is_success() {
+        return Ok(resp);
+    }
+    let body: Value = resp.json().await.unwrap_or(Value::Null);
+    let message = body["message"].as_str().unwrap_or("request failed");
+    Err(format!("GitHub {}: {}", status, message))
+}
+
+async fn get_json(path: &str, token: Option<&str>) -> Result<Value, String> {
+    send(request(path, token, "application/vnd.github+json")?)
+        .await?
+        .json()
+        .await
+        .map_err(|e| format!("GitHub API error: {}", e))
+}
+
+/// File bodies (README, blob) without the base64 JSON wrapper.
+async fn get_raw(path: &str, token: Option<&str>) -> Result<String, String> {
+    send(request(path, token, "application/vnd.github.raw")?)
+        .await?
+        .text()
+        .await
+        .map_err(|e| format!("GitHub API error: {}", e))
+}
+
+// ── URL parsing ────────────────────────────────────────────────────────────
+
+/// The content a GitHub URL points at; None for other hosts and for pages
+/// such as /pulls, /actions or /settings.
+pub(crate) fn parse_github_url(url: &str) -> Option<GithubTarget> {
+    let rest = url.trim().split_once("://")?.1;
+    let rest = rest.split(['?', '#']).next()?;
+    let (host, path) = rest.split_once('/').unwrap_or((rest, ""));
+    let host = host.to_ascii_lowercase();
+    let parts: Vec<&str> = path.split('/').filter(|p| !p.is_empty()).collect();
+    let owned = |i: usize| parts[i].to_string();
+
+    if host == "raw.githubusercontent.com" {
+        return (parts.len() >= 4).then(|| GithubTarget::File {
+            owner: owned(0), repo: owned(1), reference: owned(2), path: parts[3..].join("/"),
+        });
+    }
+    if host != "github.com" && host != "www.github.com" {
+        return None;
+    }
+    match parts.as_slice() {
+        [_, _] => Some(GithubTarget::Repo { owner: owned(0), repo: repo_name(parts[1]) }),
+        [_, _, kind @ ("issues" | "pull"), number, ..] => Some(GithubTarget::Issue {
+            owner: owned(0),
+            repo: owned(1),
+            number: number.parse().ok()?,
+            pull: *kind == "pull",
+        }),
+        [_, _, "blob", reference, file @ ..] if !file.is_empty() => Some(GithubTarget::File {
+            owner: owned(0), repo: owned(1), reference: reference.to_string(), path: file.join("/"),
+        }),
+        [_, _, "tree", _] => Some(GithubTarget::Repo { owner: owned(0), repo: owned(1) }),
+        [_, _, "tree", reference, dir @ ..] => Some(GithubTarget::Dir {
+            owner: owned(0), repo: owned(1), reference: reference.to_string(), path: dir.join("/"),
+        }),
+        _ => None,
+    }
+}
+
+/// "repo.git" (clone URLs) → "repo"
+fn repo_name(segment: &str) -> String {
+    segment.strip_suffix(".git").unwrap_or(segment).to_string()
+}
+
+// ── Formatting ─────────────────────────────────────────────────────────────
+
+/// Issue / PR body followed by its comments, oldest first.
+fn format_thread(issue: &Value, comments: &Value, pull: bool) -> String {
+    let kind = if pull { "Pull request" } else { "Issue" };
+    let mut out = format!(
+        "# {} (#{})\n\n{} · {} · opened by @{} on {}\n\n{}\n",
+        issue["title"].as_str().unwrap_or(""),
+        issue["number"].as_u64().unwrap_or(0),
+        kind,
+        issue["state"].as_str().unwrap_or("unknown"),
+        issue["user"]["login"].as_str().unwrap_or("ghost"),
+        date(&issue["created_at"]),
+        issue["body"].as_str().unwrap_or("").trim(),
+    );
+    for c in comments.as_array().into_iter().flatten() {
+        out.push_str(&format!(
+            "\n---\n\n**@{}** on {}:\n\n{}\n",
+            c["user"]["login"].as_str().unwrap_or("ghost"),
+            date(&c["created_at"]),
+            c["body"].as_str().unwrap_or("").trim(),
+        ));
+    }
+    out
+}
+
+/// "2024-03-01T12:00:00Z" → "2024-03-01"
+fn date(v: &Value) -> &str {
+    v.as_str().map_or("?", |s| s.split('T').next().unwrap_or(s))
+}
+
+/// Markdown files as-is, everything else in a fenced block.
+fn format_file(path: &str, body: &str) -> String {
+    let ext = path.rsplit_once('.').map_or("", |(_, e)| e).to_ascii_lowercase();
+    if matches!(ext.as_str(), "md" | "markdown" | "mdx" | "txt" | "rst") {
+        return format!("# {}\n\n{}", path, body.trim());
+    }
+    format!("# {}\n\n```{}\n{}\n```", path, ext, body.trim_end())
+}
+
+fn format_dir(owner: &str, repo: &str, path: &str, listing: &Value) -> String {
+    let mut out = format!("# {}/{}/{}\n\n", owner, repo, path);
+    for entry in listing.as_array().into_iter().flatten() {
+        let name = entry["name"].as_str().unwrap_or("");
+        let suffix = if entry["type"] == "dir" { "/" } else { "" };
+        out.push_str(&format!("- {}{}\n", name, suffix));
+    }
+    out
+}
+
+// ── Unit tests ─────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_github_url() {
+        assert_eq!(
+            parse_github_url("https://github.com/tauri-apps/tauri.git"),
+            Some(GithubTarget::Repo { owner: "tauri-apps".into(), repo: "tauri".into() })
+        );
+        assert_eq!(
+            parse_github_url("https://github.com/tauri-apps/tauri/pull/42/files#diff"),
+            Some(GithubTarget::Issue { owner: "tauri-apps".into(), repo: "tauri".into(), number: 42, pull: true })
+        );
+        assert_eq!(
+            parse_github_url("https://github.com/tauri-apps/tauri/blob/dev/core/tauri/src/lib.rs?plain=1"),
+            Some(GithubTarget::File {
+                owner: "tauri-apps".into(), repo: "tauri".into(),
+                reference: "dev".into(), path: "core/tauri/src/lib.rs".into(),
+            })
+        );
+        assert_eq!(
+            parse_github_url("https://raw.githubusercontent.com/o/r/main/README.md"),
+            Some(GithubTarget::File { owner: "o".into(), repo: "r".into(), reference: "main".into(), path: "README.md".into() })
+        );
+        assert!(matches!(parse_github_url("https://github.com/o/r/tree/main/src"), Some(GithubTarget::Dir { .. })));
+        assert_eq!(parse_github_url("https://github.com/o/r/issues/new"), None);
+        assert_eq!(parse_github_url("https://github.com/o/r/actions"), None);
+        assert_eq!(parse_github_url("https://gitlab.com/o/r"), None);
+    }
+
+    #[test]
+    fn test_format_thread() {
+        let issue = serde_json::json!({
+            "title": "Crash on start", "number": 7, "state": "open",
+            "user": { "login": "alice" }, "created_at": "2024-03-01T12:00:00Z",
+            "body": "It crashes.\n",
+        });
+        let comments = serde_json::json!([
+            { "user": { "login": "bob" }, "created_at": "2024-03-02T08:00:00Z", "body": "Fixed in #8" },
+        ]);
+        let md = format_thread(&issue, &comments, false);
+        assert!(md.starts_with("# Crash on start (#7)\n\nIssue · open · opened by @alice on 2024-03-01\n\nIt crashes.\n"));
+        assert!(md.ends_with("**@bob** on 2024-03-02:\n\nFixed in #8\n"));
+    }
+
+    #[test]
+    fn test_format_file_fences_code() {
+        assert_eq!(format_file("src/main.rs", "fn main() {}\n"), "# src/main.rs\n\n```rs\nfn main() {}\n```");
+        assert_eq!(format_file("docs/README.md", "# Hi\n"), "# docs/README.md\n\n# Hi");
+    }
+}
@@ -0,0 +1,223 @@
+// glossary.rs — user terminology lists enforced during translation
+//
+// Glossaries live in `<app_data>/glossaries.json`. A term maps a source
+// phrase to the exact target phrase that must appear in the translation;
+// translation.rs puts the applicable terms into the prompt and checks the
+// result against them.
+//
+// Tauri commands exposed:
+//   list_glossaries → Vec<Glossary>
+//   save_glossary   → Glossary (id assigned on first save)
+//   delete_glossary
+
+use crate::error::AppError;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+const GLOSSARY_FILE: &str = "glossaries.json";
+
+/// Serializes read-modify-write of the glossary file.
+static FILE_LOCK: Mutex<()> = Mutex::new(());
+
+// ── Types ──────────────────────────────────────────────────────────────────
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct GlossaryTerm {
+    pub source:         String,
+    pub target:         String,
+    /// Match `source` case-sensitively (acronyms, product names)
+    #[serde(default)]
+    pub case_sensitive: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Glossary {
+    /// Empty on first save; assigned by save_glossary
+    #[serde(default)]
+    pub id:          String,
+    pub name:        String,
+    /// Informational; None = any language pair
+    pub source_lang: Option<String>,
+    pub target_lang: Option<String>,
+    pub terms:       Vec<GlossaryTerm>,
+    #[serde(default)]
+    pub updated_at:  u64,
+}
+
+// ── Storage ────────────────────────────────────────────────────────────────
+
+fn glossary_path(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+    app.path_resolver()
+        .app_data_dir()
+        .ok_or_else(|| "Cannot resolve app data directory".to_string())
+        .map(|p| p.join(GLOSSARY_FILE))
+}
+
+fn load_all(path: &Path) -> Vec<Glossary> {
+    std::fs::read_to_string(path)
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
+fn save_all(path: &Path, glossaries: &[Glossary]) -> Result<(), String> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    }
+    let json = serde_json::to_string_pretty(glossaries).map_err(|e| e.to_string())?;
+    let tmp = path.with_extension("json.tmp");
+    std::fs::write(&tmp, json).map_err(|e| format!("Failed to write glossaries: {}", e))?;
+    std::fs::rename(&tmp, path).map_err(|e| e.to_string())
+}
+
+/// Glossary by id, for translation.rs.
+pub(crate) fn load(app: &tauri::AppHandle, id: &str) -> Result<Glossary, String> {
+    load_all(&glossary_path(app)?)
+        .into_iter()
+        .find(|g| g.id == id)
+        .ok_or_else(|| format!("Glossary not found: {}", id))
+}
+
+// ── Tauri commands ─────────────────────────────────────────────────────────
+
+#[tauri::command]
+pub fn list_glossaries(app_handle: tauri::AppHandle) -> Result<Vec<Glossary>, AppError> {
+    let mut all = load_all(&glossary_path(&app_handle)?);
+    all.sort_by(|a, b| a.name.to_lowercase().cmp(&b.name.to_lowercase()));
+    Ok(all)
+}
+
+/// Create or replace a glossary. Blank and duplicate source terms are dropped.
+#[tauri::command]
+pub fn save_glossary(app_handle: tauri::AppHandle, mut glossary: Glossary) -> Result<Glossary, AppError> {
+    if glossary.name.trim().is_empty() {
+        return Err("Glossary name is required".into());
+    }
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default();
+    if glossary.id.is_empty() {
+        glossary.id = format!("gl-{}", now.as_millis());
+    }
+    glossary.updated_at = now.as_secs();
+    glossary.terms = normalize_terms(glossary.terms);
+
+    let path = glossary_path(&app_handle)?;
+    let _guard = FILE_LOCK.lock().map_err(|e| e.to_string())?;
+    let mut all = load_all(&path);
+    all.retain(|g| g.id != glossary.id);
+    all.push(glossary.clone());
+    save_all(&path, &all)?;
+    Ok(glossary)
+}
+
+#[tauri::command]
+pub fn delete_glossary(app_handle: tauri::AppHandle, id: String) -> Result<(), AppError> {
+    let path = glossary_path(&app_handle)?;
+    let _guard = FILE_LOCK.lock().map_err(|e| e.to_string())?;
+    let mut all = load_all(&path);
+    let before = all.len();
+    all.retain(|g| g.id != id);
+    if all.len() == before {
+        return Err(format!("Glossary not found: {}", id).into());
+    }
+    save_all(&path, &all).map_err(AppError::from)
+}
+
+// ── Matching ───────────────────────────────────────────────────────────────
+
+fn normalize_terms(terms: Vec<GlossaryTerm>) -> Vec<GlossaryTerm> {
+    let mut out: Vec<GlossaryTerm> = Vec::new();
+    for mut t in terms {
+        t.source = t.source.trim().to_string();
+        t.target = t.target.trim().to_string();
+        if t.source.is_empty() || t.target.is_empty() {
+            continue;
+        }
+        if !out.iter().any(|o| o.source.to_lowercase() == t.source.to_lowercase()) {
+            out.push(t);
+        }
+    }
+    out
+}
+
+fn contains(haystack: &str, needle: &str, case_sensitive: bool) -> bool {
+    if case_sensitive {
+        haystack.contains(needle)
+    } else {
+        haystack.to_lowercase().contains(&needle.to_lowercase())
+    }
+}
+
+impl Glossary {
+    /// Terms whose source phrase occurs in `text`.
+    pub(crate) fn terms_in(&self, text: &str) -> Vec<&GlossaryTerm> {
+        self.terms.iter().filter(|t| contains(text, &t.source, t.case_sensitive)).collect()
+    }
+}
+
+/// Terms whose required target phrase is absent from `translation`. Target
+/// matching ignores case — sentence-initial capitalisation is not a miss.
+pub(crate) fn missing_terms<'a>(terms: &[&'a GlossaryTerm], translation: &str) -> Vec<&'a GlossaryTerm> {
+    terms.iter().copied().filter(|t| !contains(translation, &t.target, false)).collect()
+}
+
+/// Prompt lines listing the mandatory translations.
+pub(crate) fn prompt_block(terms: &[&GlossaryTerm]) -> String {
+    terms.iter()
+        .map(|t| format!("- \"{}\" → \"{}\"", t.source, t.target))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+// ── Unit tests ─────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn term(source: &str, target: &str, case_sensitive: bool) -> GlossaryTerm {
+        GlossaryTerm { source: source.into(), target: target.into(), case_sensitive }
+    }
+
+    fn glossary(terms: Vec<GlossaryTerm>) -> Glossary {
+        Glossary { id: "g".into(), name: "g".into(), source_lang: None, target_lang: None, terms, updated_at: 0 }
+    }
+
+    #[test]
+    fn test_terms_in_and_missing() {
+        let g = glossary(vec![
+            term("pull request", "Pull-Request", false),
+            term("IT", "IT-Abteilung", true),
+            term("deploy", "ausrollen", false),
+        ]);
+        let hits = g.terms_in("Open a Pull Request before it ships.");
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].target, "Pull-Request");
+
+        let missing = missing_terms(&hits, "Öffne einen Merge-Request, bevor es live geht.");
+        assert_eq!(missing.len(), 1);
+        assert!(missing_terms(&hits, "Öffne einen pull-request.").is_empty());
+    }
+
+    #[test]
+    fn test_normalize_drops_blank_and_duplicate_terms() {
+        let terms = normalize_terms(vec![
+            term(" API ", " API ", true),
+            term("api", "Schnittstelle", false),
+            term("", "x", false),
+        ]);
+        assert_eq!(terms, vec![term("API", "API", true)]);
+    }
+
+    #[test]
+    fn test_store_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(GLOSSARY_FILE);
+        assert!(load_all(&path).is_empty());
+        let g = glossary(vec![term("a", "b", false)]);
+        save_all(&path, &[g.clone()]).unwrap();
+        assert_eq!(load_all(&path), vec![g]);
+    }
+}
@@ -0,0 +1,187 @@
+// gpu_devices.rs — GPUs stable-diffusion.cpp can be pointed at
+//
+// Machines with more than one GPU (a gaming card next to an iGPU, or two
+// cards) run sd on whatever the driver lists first. get_gpu_info lists the
+// devices per backend so the user can pick one; run_local_sd and sd-server
+// then restrict the process to it through the backend's visibility variable
+// (CUDA_VISIBLE_DEVICES, GGML_VK_VISIBLE_DEVICES, HIP_VISIBLE_DEVICES), which
+// also makes it device 0 inside sd.
+//
+// Devices come from the vendor tools (nvidia-smi, vulkaninfo, rocminfo); a
+// backend whose tool is missing simply lists nothing.
+//
+// Tauri commands exposed:
+//   get_gpu_info → GpuInfo
+
+use crate::error::AppError;
+use serde::Serialize;
+use std::process::{Command, Stdio};
+use tokio::process::Command as AsyncCommand;
+
+// ── Types ──────────────────────────────────────────────────────────────────
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct GpuDevice {
+    /// "cuda" | "vulkan" | "rocm"
+    pub backend:   &'static str,
+    /// Index to pass as LocalSdRequest.gpu_device for this backend
+    pub index:     u32,
+    pub name:      String,
+    /// Dedicated memory, when the tool reports it
+    pub memory_mb: Option<u64>,
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct GpuInfo {
+    pub devices: Vec<GpuDevice>,
+}
+
+// ── Tauri command ──────────────────────────────────────────────────────────
+
+#[tauri::command]
+pub async fn get_gpu_info() -> Result<GpuInfo, AppError> {
+    let devices = tokio::task::spawn_blocking(|| {
+        let mut devices = Vec::new();
+        if let Some(out) = tool_output("nvidia-smi", &["--query-gpu=index,name,memory.total", "--format=csv,noheader,nounits"]) {
+            devices.extend(parse_nvidia_smi(&out));
+        }
+        if let Some(out) = tool_output("vulkaninfo", &["--summary"]) {
+            devices.extend(parse_vulkaninfo(&out));
+        }
+        if let Some(out) = tool_output("rocminfo", &[]) {
+            devices.extend(parse_rocminfo(&out));
+        }
+        devices
+    })
+    .await?;
+    Ok(GpuInfo { devices })
+}
+
+// ── Process environment ────────────────────────────────────────────────────
+
+/// Environment variable that limits `gpu_backend` to a set of devices.
+fn visibility_var(gpu_backend: &str) -> Option<&'static str> {
+    match gpu_backend {
+        "cuda"   => Some("CUDA_VISIBLE_DEVICES"),
+        "vulkan" => Some("GGML_VK_VISIBLE_DEVICES"),
+        "rocm"   => Some("HIP_VISIBLE_DEVICES"),
+        _        => None,
+    }
+}
+
+/// Restricts an sd / sd-server process to `device` (None = driver default).
+pub(crate) fn apply_gpu_device(cmd: &mut AsyncCommand, gpu_backend: &str, device: Option<u32>) {
+    let (Some(device), Some(var)) = (device, visibility_var(gpu_backend)) else {
+        return;
+    };
+    println!("[SD] GPU device: {}={}", var, device);
+    cmd.env(var, device.to_string());
+}
+
+// ── Parsing ────────────────────────────────────────────────────────────────
+
+/// stdout of a tool that exited 0; None when it is missing or failed.
+fn tool_output(program: &str, args: &[&str]) -> Option<String> {
+    let out = Command::new(program).args(args).stderr(Stdio::null()).output().ok()?;
+    out.status.success().then(|| String::from_utf8_lossy(&out.stdout).to_string())
+}
+
+/// `0, NVIDIA GeForce RTX 3090, 24576` per line.
+fn parse_nvidia_smi(out: &str) -> Vec<GpuDevice> {
+    out.lines()
+        .filter_map(|line| {
+            let mut cols = line.split(',').map(str::trim);
+            let index = cols.next()?.parse().ok()?;
+            let name = cols.next().filter(|n| !n.is_empty())?.to_string();
+            let memory_mb = cols.next().and_then(|m| m.parse().ok());
+            Some(GpuDevice { backend: "cuda", index, name, memory_mb })
+        })
+        .collect()
+}
+
+/// `GPU<n>:` blocks of `vulkaninfo --summary`. Software renderers (llvmpipe)
+/// are skipped but keep their index, which ggml counts too.
+fn parse_vulkaninfo(out: &str) -> Vec<GpuDevice> {
+    let mut devices = Vec::new();
+    let mut current: Option<(u32, Option<String>, bool)> = None;
+    let mut flush = |current: &mut Option<(u32, Option<String>, bool)>| {
+        if let Some((index, Some(name), false)) = current.take() {
+            devices.push(GpuDevice { backend: "vulkan", index, name, memory_mb: None });
+        }
+    };
+    for line in out.lines().map(str::trim) {
+        if let Some(index) = line.strip_prefix("GPU").and_then(|r| r.strip_suffix(':')).and_then(|n| n.parse().ok()) {
+            flush(&mut current);
+            current = Some((index, None, false));
+        } else if let Some((_, name, is_cpu)) = current.as_mut() {
+            match line.split_once('=').map(|(k, v)| (k.trim(), v.trim())) {
+                Some(("deviceName", v)) => *name = Some(v.to_string()),
+                Some(("deviceType", v)) => *is_cpu = v.ends_with("_CPU"),
+                _ => {}
+            }
+        }
+    }
+    flush(&mut current);
+    devices
+}
+
+/// GPU agents of `rocminfo`, numbered in the order HIP sees them.
+fn parse_rocminfo(out: &str) -> Vec<GpuDevice> {
+    let mut devices = Vec::new();
+    let mut name: Option<String> = None;
+    for line in out.lines().map(str::trim) {
+        if line.starts_with("Agent ") {
+            name = None;
+        } else if let Some(v) = line.strip_prefix("Marketing Name:") {
+            name = Some(v.trim().to_string());
+        } else if line.strip_prefix("Device Type:").is_some_and(|t| t.trim() == "GPU") {
+            let index = devices.len() as u32;
+            let name = name.take().filter(|n| !n.is_empty()).unwrap_or_else(|| format!("AMD GPU {}", index));
+            devices.push(GpuDevice { backend: "rocm", index, name, memory_mb: None });
+        }
+    }
+    devices
+}
+
+// ── Unit tests ─────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_nvidia_smi() {
+        let devices = parse_nvidia_smi("0, NVIDIA GeForce RTX 3090, 24576\n1, NVIDIA GeForce GTX 1080, [N/A]\n\n");
+        assert_eq!(devices.len(), 2);
+        assert_eq!((devices[0].index, devices[0].name.as_str(), devices[0].memory_mb), (0, "NVIDIA GeForce RTX 3090", Some(24576)));
+        assert_eq!((devices[1].index, devices[1].memory_mb), (1, None));
+    }
+
+    #[test]
+    fn test_parse_vulkaninfo_skips_cpu_devices() {
+        let out = "Devices:\n========\nGPU0:\n\tapiVersion = 1.3.260\n\tdeviceType = PHYSICAL_DEVICE_TYPE_DISCRETE_GPU\n\
+                   \tdeviceName = AMD Radeon RX 7900 XTX\nGPU1:\n\tdeviceType = PHYSICAL_DEVICE_TYPE_CPU\n\
+                   \tdeviceName = llvmpipe (LLVM 17.0.6, 256 bits)\nGPU2:\n\tdeviceType = PHYSICAL_DEVICE_TYPE_INTEGRATED_GPU\n\
+                   \tdeviceName = AMD Radeon Graphics\n";
+        let devices = parse_vulkaninfo(out);
+        let found: Vec<_> = devices.iter().map(|d| (d.index, d.name.as_str())).collect();
+        assert_eq!(found, vec![(0, "AMD Radeon RX 7900 XTX"), (2, "AMD Radeon Graphics")]);
+    }
+
+    #[test]
+    fn test_parse_rocminfo_counts_gpu_agents() {
+        let out = "Agent 1\n  Name: AMD Ryzen 9 7950X\n  Marketing Name: AMD Ryzen 9 7950X\n  Device Type: CPU\n\
+                   Agent 2\n  Name: gfx1100\n  Marketing Name: Radeon RX 7900 XTX\n  Device Type: GPU\n\
+                   Agent 3\n  Name: gfx1036\n  Marketing Name: \n  Device Type: GPU\n";
+        let devices = parse_rocminfo(out);
+        let found: Vec<_> = devices.iter().map(|d| (d.index, d.name.as_str())).collect();
+        assert_eq!(found, vec![(0, "Radeon RX 7900 XTX"), (1, "AMD GPU 1")]);
+    }
+
+    #[test]
+    fn test_visibility_var() {
+        assert_eq!(visibility_var("cuda"), Some("CUDA_VISIBLE_DEVICES"));
+        assert_eq!(visibility_var("vulkan"), Some("GGML_VK_VISIBLE_DEVICES"));
+        assert_eq!(visibility_var("cpu"), None);
+    }
+}
@@ -0,0 +1,686 @@
+// hotkeys.rs — global hotkey table, keyboard-layout normalization
+//
+// Accelerators are written for a US QWERTY keyboard ("Alt+M"). Two things
+// break them on other layouts:
+//   • accelerators typed with a Cyrillic layout active ("Alt+Ь") name keys
+//     the shortcut backend cannot parse — they are mapped to the Latin key
+//     on the same physical position;
+//   • macOS binds shortcuts to physical key positions, so on AZERTY / QWERTZ
+//     "Alt+A" would fire on the key labelled Q — letters are remapped to the
+//     position that carries that label in the active layout. Windows and
+//     X11 resolve letters through the layout already and need no remap.
+//
+// Layout switches are reported by the OS — WM_INPUTLANGCHANGE on Windows,
+// the TIS input-source notification on macOS, XkbStateNotify on X11 — and
+// every hotkey is re-registered, since X11 keycodes are looked up at
+// registration time. Only this module's own accelerators are unregistered;
+// other global shortcuts stay in place.
+//
+// Users can rebind or unbind any action; overrides live in
+// <app-data>/hotkeys.json and win over DEFAULT_HOTKEYS.
+//
+// Tauri commands exposed:
+//   get_shortcut_cheatsheet → Vec<ShortcutEntry> (global hotkeys, then in-window keys)
+//   set_hotkey              (action, accelerator?) — None resets to the default,
+//                           "" unbinds; → updated cheatsheet
+//
+// Events emitted:
+//   keyboard-layout-changed → layout id ("us", "fr", "de", "ru", …)
+//   hotkeys-changed         → Vec<ShortcutEntry> after set_hotkey
+
+use crate::error::AppError;
+use crate::actions;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::{AppHandle, GlobalShortcutManager, Manager};
+
+const HOTKEYS_FILE: &str = "hotkeys.json";
+
+/// action id → accelerator; "" means unbound
+static OVERRIDES: Mutex<BTreeMap<String, String>> = Mutex::new(BTreeMap::new());
+/// Layout the hotkeys were last registered for
+static LAYOUT: Mutex<String> = Mutex::new(String::new());
+/// Accelerators the shortcut backend refused at the last registration
+static FAILED: Mutex<Vec<String>> = Mutex::new(Vec::new());
+/// Accelerators registered by register_all, as handed to the backend
+static REGISTERED: Mutex<Vec<String>> = Mutex::new(Vec::new());
+static PATH: Mutex<Option<PathBuf>> = Mutex::new(None);
+
+/// (action id from `actions`, default accelerator)
+pub const DEFAULT_HOTKEYS: &[(&str, &str)] = &[
+    ("toggle_click_through", "Alt+M"),        // toggle click-through
+    ("capture_screen",       "Alt+Shift+S"),  // capture screen and analyze
+    ("toggle_window",        "Alt+Shift+H"),  // hide/show window
+    ("paste_context",        "Alt+Shift+V"),  // paste clipboard as context
+];
+
+/// Keys handled inside the window by the frontend: (id, title, accelerator).
+/// Listed so the help overlay shows them next to the global hotkeys.
+pub const WINDOW_SHORTCUTS: &[(&str, &str, &str)] = &[
+    ("send_message", "Send message",               "Ctrl+Enter"),
+    ("paste_image",  "Paste image from clipboard", "Ctrl+V"),
+    ("save_file",    "Save open file",             "Ctrl+S"),
+];
+
+/// Whether the shortcut backend matches physical positions (see header).
+const POSITIONAL_KEYS: bool = cfg!(target_os = "macos");
+
+// ── Types ──────────────────────────────────────────────────────────────────
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct ShortcutEntry {
+    pub action:      String,
+    pub title:       String,
+    /// As the user types it ("Alt+M"); empty when unbound
+    pub accelerator: String,
+    /// "global" (works while another app is focused) | "window"
+    pub scope:       &'static str,
+    /// Differs from the default binding
+    pub customized:  bool,
+    /// False when the desktop environment refused the global hotkey
+    pub registered:  bool,
+}
+
+// ── Bindings ───────────────────────────────────────────────────────────────
+
+/// Defaults with `overrides` applied, in DEFAULT_HOTKEYS order followed by
+/// actions that only have a user binding. Unbound actions are left out.
+fn merge(overrides: &BTreeMap<String, String>) -> Vec<(String, String)> {
+    let defaults = DEFAULT_HOTKEYS.iter()
+        .map(|(id, accel)| (id.to_string(), overrides.get(*id).cloned().unwrap_or_else(|| accel.to_string())));
+    let extra = overrides.iter()
+        .filter(|(id, _)| !DEFAULT_HOTKEYS.iter().any(|(d, _)| d == id))
+        .map(|(id, accel)| (id.clone(), accel.clone()));
+    defaults.chain(extra).filter(|(_, accel)| !accel.is_empty()).collect()
+}
+
+/// Effective (action id, accelerator) bindings.
+pub fn bindings() -> Vec<(String, String)> {
+    OVERRIDES.lock().map(|o| merge(&o)).unwrap_or_default()
+}
+
+/// Effective accelerator for action `id`, if bound.
+pub fn binding(id: &str) -> Option<String> {
+    bindings().into_iter().find(|(a, _)| a == id).map(|(_, accel)| accel)
+}
+
+fn default_binding(id: &str) -> Option<&'static str> {
+    DEFAULT_HOTKEYS.iter().find(|(a, _)| *a == id).map(|(_, accel)| *accel)
+}
+
+/// Compare accelerators ignoring case, spacing and modifier order.
+fn same_accelerator(a: &str, b: &str) -> bool {
+    let parts = |s: &str| {
+        let mut p: Vec<String> = s.split('+').map(|k| k.trim().to_ascii_lowercase()).collect();
+        let key = p.pop();
+        p.sort();
+        (p, key)
+    };
+    parts(a) == parts(b)
+}
+
+/// Check `accel` for action `id` against the current bindings.
+fn validate(id: &str, accel: &str, current: &[(String, String)]) -> Result<(), String> {
+    let action = actions::all().into_iter().find(|a| a.id == id)
+        .ok_or_else(|| format!("Unknown action '{}'", id))?;
+    // A hotkey dispatches without an argument
+    if action.takes_arg {
+        return Err(format!("'{}' needs an argument and cannot be bound to a hotkey", id));
+    }
+    if accel.is_empty() {
+        return Ok(());
+    }
+    if accel.split('+').any(|k| k.trim().is_empty()) {
+        return Err(format!("Invalid accelerator '{}'", accel));
+    }
+    if let Some((other, _)) = current.iter().find(|(a, b)| a != id && same_accelerator(b, accel)) {
+        return Err(format!("{} is already bound to '{}'", accel, other));
+    }
+    if let Some((other, ..)) = WINDOW_SHORTCUTS.iter().find(|(_, _, b)| same_accelerator(b, accel)) {
+        return Err(format!("{} is already used in the window for '{}'", accel, other));
+    }
+    Ok(())
+}
+
+fn cheatsheet() -> Vec<ShortcutEntry> {
+    let failed = FAILED.lock().map(|f| f.clone()).unwrap_or_default();
+    let overrides = OVERRIDES.lock().map(|o| o.clone()).unwrap_or_default();
+    let bound = merge(&overrides);
+
+    let global = actions::all().into_iter().filter_map(|a| {
+        let accel = bound.iter().find(|(id, _)| id == a.id).map(|(_, accel)| accel.clone());
+        // Actions that were never bound don't belong on a cheatsheet;
+        // unbound defaults stay listed so the user can find them again
+        if accel.is_none() && default_binding(a.id).is_none() {
+            return None;
+        }
+        let accel = accel.unwrap_or_default();
+        Some(ShortcutEntry {
+            action:      a.id.to_string(),
+            title:       a.title.to_string(),
+            registered:  !accel.is_empty() && !failed.iter().any(|f| f == a.id),
+            customized:  overrides.contains_key(a.id),
+            scope:       "global",
+            accelerator: accel,
+        })
+    });
+    let window = WINDOW_SHORTCUTS.iter().map(|(id, title, accel)| ShortcutEntry {
+        action:      id.to_string(),
+        title:       title.to_string(),
+        accelerator: accel.to_string(),
+        scope:       "window",
+        customized:  false,
+        registered:  true,
+    });
+    global.chain(window).collect()
+}
+
+// ── Storage ────────────────────────────────────────────────────────────────
+
+fn load(path: &Path) -> BTreeMap<String, String> {
+    std::fs::read_to_string(path).ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
+fn store(path: &Path, overrides: &BTreeMap<String, String>) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(overrides).map_err(|e| e.to_string())?;
+    let tmp = path.with_extension("json.tmp");
+    std::fs::write(&tmp, json).map_err(|e| e.to_string())?;
+    std::fs::rename(&tmp, path).map_err(|e| e.to_string())
+}
+
+// ── Registration ───────────────────────────────────────────────────────────
+
+/// (Re-)register every hotkey for `layout`.
+/// Registration is best-effort: some keys may be claimed by the desktop
+/// environment (e.g. Alt+Space on GNOME). A failure is logged as a warning
+/// instead of crashing the app.
+pub fn register_all(app: &AppHandle, layout: &str) {
+    let mut shortcuts = app.global_shortcut_manager();
+    let previous = REGISTERED.lock().map(|mut r| std::mem::take(&mut *r)).unwrap_or_default();
+    for accel in previous {
+        if let Err(e) = shortcuts.unregister(&accel) {
+            log::warn!("hotkeys: could not unregister {}: {}", accel, e);
+        }
+    }
+    let mut registered = Vec::new();
+    let mut failed = Vec::new();
+    for (id, accel) in bindings() {
+        let accel = normalize_accelerator(&accel, layout, POSITIONAL_KEYS);
+        let handle = app.clone();
+        let action = id.clone();
+        match shortcuts.register(&accel, move || {
+            if let Err(e) = actions::dispatch(&handle, &action, None) {
+                log::warn!("hotkeys: {}", e);
+            }
+        }) {
+            Ok(())  => registered.push(accel),
+            Err(e) => {
+                log::warn!("Could not register {}: {}", accel, e);
+                failed.push(id);
+            }
+        }
+    }
+    if let Ok(mut r) = REGISTERED.lock() { *r = registered; }
+    if let Ok(mut f) = FAILED.lock() { *f = failed; }
+    if let Ok(mut l) = LAYOUT.lock() { *l = layout.to_string(); }
+}
+
+/// Register hotkeys for the current layout and keep them in sync with
+/// layout switches.
+pub fn spawn_layout_watcher(app: AppHandle) {
+    if let Some(dir) = app.path_resolver().app_data_dir() {
+        let path = dir.join(HOTKEYS_FILE);
+        if let Ok(mut o) = OVERRIDES.lock() { *o = load(&path); }
+        if let Ok(mut p) = PATH.lock() { *p = Some(path); }
+    }
+    let mut current = platform::current_layout();
+    register_all(&app, &current);
+    log::info!("hotkeys: registered for layout '{}'", current);
+
+    // Notifications may arrive on the main thread, where registering would
+    // wait on the event loop it is running in; a worker re-registers instead
+    let (tx, rx) = std::sync::mpsc::channel::<String>();
+    if let Err(e) = platform::watch(&app, tx) {
+        log::warn!("hotkeys: layout switches will not be followed: {}", e);
+        return;
+    }
+    std::thread::spawn(move || {
+        while let Ok(layout) = rx.recv() {
+            if layout != current {
+                log::info!("hotkeys: layout changed {} → {}, re-registering", current, layout);
+                register_all(&app, &layout);
+                let _ = app.emit_all("keyboard-layout-changed", &layout);
+                current = layout;
+            }
+        }
+    });
+}
+
+// ── Tauri commands ─────────────────────────────────────────────────────────
+
+#[tauri::command]
+pub fn get_shortcut_cheatsheet() -> Vec<ShortcutEntry> {
+    cheatsheet()
+}
+
+#[tauri::command]
+pub fn set_hotkey(app_handle: AppHandle, action: String, accelerator: Option<String>) -> Result<Vec<ShortcutEntry>, AppError> {
+    let mut overrides = OVERRIDES.lock().map_err(|e| e.to_string())?.clone();
+    match accelerator.map(|a| a.trim().to_string()) {
+        None => { overrides.remove(&action); }
+        Some(accel) => {
+            validate(&action, &accel, &merge(&overrides))?;
+            if default_binding(&action).is_some_and(|d| same_accelerator(d, &accel)) {
+                overrides.remove(&action);
+            } else {
+                overrides.insert(action.clone(), accel);
+            }
+        }
+    }
+
+    if let Some(path) = PATH.lock().map_err(|e| e.to_string())?.as_ref() {
+        store(path, &overrides)?;
+    }
+    *OVERRIDES.lock().map_err(|e| e.to_string())? = overrides;
+
+    let layout = LAYOUT.lock().map(|l| l.clone()).unwrap_or_default();
+    let layout = if layout.is_empty() { platform::current_layout() } else { layout };
+    register_all(&app_handle, &layout);
+    log::info!("hotkeys: rebound '{}'", action);
+
+    let sheet = cheatsheet();
+    let _ = app_handle.emit_all("hotkeys-changed", &sheet);
+    Ok(sheet)
+}
+
+// ── Normalization ──────────────────────────────────────────────────────────
+
+/// Rewrite the key part of `accel` for `layout`; modifiers are kept as-is.
+fn normalize_accelerator(accel: &str, layout: &str, positional: bool) -> String {
+    let (mods, key) = match accel.rsplit_once('+') {
+        Some((m, k)) => (Some(m), k),
+        None         => (None, accel),
+    };
+
+    let mut key = cyrillic_to_latin(key).unwrap_or_else(|| key.to_string());
+    if positional {
+        if let Some(physical) = positional_key(&key, layout) {
+            key = physical.to_string();
+        }
+    }
+
+    match mods {
+        Some(m) => format!("{}+{}", m, key),
+        None    => key,
+    }
+}
+
+/// ЙЦУКЕН letter → QWERTY letter on the same key.
+fn cyrillic_to_latin(key: &str) -> Option<String> {
+    const MAP: &[(char, &str)] = &[
+        ('Й', "Q"), ('Ц', "W"), ('У', "E"), ('К', "R"), ('Е', "T"), ('Н', "Y"), ('Г', "U"),
+        ('Ш', "I"), ('Щ', "O"), ('З', "P"), ('Х', "BracketLeft"), ('Ъ', "BracketRight"),
+        ('Ї', "BracketRight"), ('Ф', "A"), ('Ы', "S"), ('І', "S"), ('В', "D"), ('А', "F"),
+        ('П', "G"), ('Р', "H"), ('О', "J"), ('Л', "K"), ('Д', "L"), ('Ж', "Semicolon"),
+        ('Э', "Quote"), ('Є', "Quote"), ('Я', "Z"), ('Ч', "X"), ('С', "C"), ('М', "V"),
+        ('И', "B"), ('Т', "N"), ('Ь', "M"), ('Б', "Comma"), ('Ю', "Period"), ('Ё', "Backquote"),
+    ];
+    let mut chars = key.chars();
+    let c = chars.next()?.to_uppercase().next()?;
+    if chars.next().is_some() { return None; }
+    MAP.iter().find(|(k, _)| *k == c).map(|(_, v)| v.to_string())
+}
+
+/// US-position key that carries `key`'s label on `layout`.
+fn positional_key(key: &str, layout: &str) -> Option<&'static str> {
+    const AZERTY: &[(&str, &str)] = &[("A", "Q"), ("Q", "A"), ("Z", "W"), ("W", "Z"), ("M", "Semicolon")];
+    const QWERTZ: &[(&str, &str)] = &[("Y", "Z"), ("Z", "Y")];
+
+    let table = match layout_family(layout) {
+        "azerty" => AZERTY,
+        "qwertz" => QWERTZ,
+        _        => return None,
+    };
+    let key = key.to_ascii_uppercase();
+    table.iter().find(|(k, _)| *k == key).map(|(_, v)| *v)
+}
+
+fn layout_family(layout: &str) -> &'static str {
+    match layout.split(['(', '-', '_']).next().unwrap_or("") {
+        "fr" | "be" => "azerty",
+        "de" | "ch" | "at" | "cz" | "hu" | "sk" | "si" | "hr" => "qwertz",
+        _ => "qwerty",
+    }
+}
+
+/// Map a macOS input-source id ("com.apple.keylayout.French") to a short id.
+#[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+fn layout_from_mac_source(source: &str) -> String {
+    const NAMES: &[(&str, &str)] = &[
+        ("Belgian", "be"), ("French", "fr"), ("Swiss", "ch"), ("Austrian", "at"),
+        ("German", "de"), ("Czech", "cz"), ("Hungarian", "hu"), ("Russian", "ru"),
+        ("Ukrainian", "ua"), ("Belarusian", "by"),
+    ];
+    let name = source.rsplit('.').next().unwrap_or(source);
+    NAMES.iter()
+        .find(|(n, _)| name.contains(n))
+        .map(|(_, id)| id.to_string())
+        .unwrap_or_else(|| "us".into())
+}
+
+/// Layout of XKB group `group` from an _XKB_RULES_NAMES value
+/// ("evdev\0pc105\0us,ru\0,\0grp:alt_shift_toggle\0").
+#[cfg_attr(any(target_os = "macos", target_os = "windows"), allow(dead_code))]
+fn layout_from_rules_names(value: &[u8], group: usize) -> Option<String> {
+    let layouts = value.split(|b| *b == 0).nth(2)?;
+    let layouts: Vec<String> = String::from_utf8_lossy(layouts).split(',').map(|l| l.trim().to_string()).collect();
+    let first = layouts.first().filter(|l| !l.is_empty())?;
+    Some(layouts.get(group).filter(|l| !l.is_empty()).unwrap_or(first).clone())
+}
+
+// ═══════════════════════════════════════════════════════════════════════
+// Active layout lookup — returns a short id ("us", "fr", "ru", …) — and
+// layout-switch notifications, sent as layout ids to `watch`'s channel
+// ═══════════════════════════════════════════════════════════════════════
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use cocoa::base::{id, nil};
+    use cocoa::foundation::NSString;
+    use objc::declare::ClassDecl;
+    use objc::runtime::{Object, Sel};
+    use objc::{class, msg_send, sel, sel_impl};
+    use std::ffi::{c_void, CStr};
+    use std::os::raw::c_char;
+    use std::sync::mpsc::Sender;
+    use std::sync::{Mutex, OnceLock};
+
+    /// kTISNotifySelectedKeyboardInputSourceChanged
+    const INPUT_SOURCE_CHANGED: &str = "com.apple.Carbon.TISNotifySelectedKeyboardInputSourceChanged";
+
+    static CHANGES: OnceLock<Mutex<Sender<String>>> = OnceLock::new();
+
+    #[link(name = "Carbon", kind = "framework")]
+    extern "C" {
+        static kTISPropertyInputSourceID: *const c_void;
+        fn TISCopyCurrentKeyboardInputSource() -> *mut c_void;
+        fn TISGetInputSourceProperty(source: *mut c_void, key: *const c_void) -> *const c_void;
+    }
+
+    #[link(name = "CoreFoundation", kind = "framework")]
+    extern "C" {
+        fn CFRelease(cf: *const c_void);
+    }
+
+    /// Callable from any thread, unlike the TIS calls in the observer.
+    pub fn current_layout() -> String {
+        std::process::Command::new("defaults")
+            .args(["read", "com.apple.HIToolbox", "AppleCurrentKeyboardLayoutInputSourceID"])
+            .output()
+            .ok()
+            .filter(|o| o.status.success())
+            .map(|o| super::layout_from_mac_source(String::from_utf8_lossy(&o.stdout).trim()))
+            .unwrap_or_else(|| "us".into())
+    }
+
+    /// Observe the distributed input-source notification; it is delivered on
+    /// the run loop of the registering thread, so that is the main thread.
+    pub fn watch(app: &tauri::AppHandle, tx: Sender<String>) -> Result<(), String> {
+        CHANGES.set(Mutex::new(tx)).map_err(|_| "layout watcher already installed")?;
+        app.run_on_main_thread(|| unsafe {
+            let Some(mut decl) = ClassDecl::new("AIAssistantLayoutObserver", class!(NSObject)) else {
+                log::warn!("hotkeys: layout observer class already registered");
+                return;
+            };
+            decl.add_method(sel!(inputSourceChanged:), input_source_changed as extern "C" fn(&Object, Sel, id));
+            let observer: id = msg_send![decl.register(), new];
+            let center: id = msg_send![class!(NSDistributedNotificationCenter), defaultCenter];
+            let name = NSString::alloc(nil).init_str(INPUT_SOURCE_CHANGED);
+            let _: () = msg_send![center, addObserver: observer selector: sel!(inputSourceChanged:) name: name object: nil];
+        })
+        .map_err(|e| e.to_string())
+    }
+
+    extern "C" fn input_source_changed(_this: &Object, _sel: Sel, _notification: id) {
+        let source_id = unsafe {
+            let source = TISCopyCurrentKeyboardInputSource();
+            if source.is_null() {
+                return;
+            }
+            // CFStringRef owned by `source`; toll-free bridged to NSString
+            let name = TISGetInputSourceProperty(source, kTISPropertyInputSourceID) as id;
+            let utf8: *const c_char = if name.is_null() { std::ptr::null() } else { msg_send![name, UTF8String] };
+            let source_id = (!utf8.is_null()).then(|| CStr::from_ptr(utf8).to_string_lossy().into_owned());
+            CFRelease(source);
+            source_id
+        };
+        if let (Some(source_id), Some(tx)) = (source_id, CHANGES.get().and_then(|t| t.lock().ok())) {
+            let _ = tx.send(super::layout_from_mac_source(&source_id));
+        }
+    }
+}
+
+#[cfg(target_os = "windows")]
+mod platform {
+    use std::sync::mpsc::Sender;
+    use std::sync::{Mutex, OnceLock};
+    use tauri::Manager;
+    use windows::Win32::Foundation::{HWND, LPARAM, LRESULT, WPARAM};
+    use windows::Win32::UI::Input::KeyboardAndMouse::GetKeyboardLayout;
+    use windows::Win32::UI::Shell::{DefSubclassProc, SetWindowSubclass};
+    use windows::Win32::UI::WindowsAndMessaging::{GetForegroundWindow, GetWindowThreadProcessId, WM_INPUTLANGCHANGE};
+
+    static CHANGES: OnceLock<Mutex<Sender<String>>> = OnceLock::new();
+
+    /// Layout of the foreground thread — each thread has its own on Windows.
+    pub fn current_layout() -> String {
+        let hkl = unsafe { GetKeyboardLayout(GetWindowThreadProcessId(GetForegroundWindow(), None)) };
+        layout_from_hkl(hkl.0)
+    }
+
+    fn layout_from_hkl(hkl: isize) -> String {
+        let primary_lang = (hkl as usize) & 0x3FF;
+        match primary_lang {
+            0x0C => "fr",
+            0x07 => "de",
+            0x05 => "cz",
+            0x0E => "hu",
+            0x19 => "ru",
+            0x22 => "ua",
+            0x23 => "by",
+            _    => "us",
+        }.to_string()
+    }
+
+    /// WM_INPUTLANGCHANGE goes to the windows of the thread whose layout
+    /// changed; the main window is subclassed to see it. A subclass can only
+    /// be installed from the thread that owns the window.
+    pub fn watch(app: &tauri::AppHandle, tx: Sender<String>) -> Result<(), String> {
+        let win = app.get_window("main").ok_or("no main window")?;
+        let hwnd = HWND(win.hwnd().map_err(|e| e.to_string())?.0);
+        CHANGES.set(Mutex::new(tx)).map_err(|_| "layout watcher already installed")?;
+        app.run_on_main_thread(move || {
+            if !unsafe { SetWindowSubclass(hwnd, Some(on_message), 1, 0) }.as_bool() {
+                log::warn!("hotkeys: could not subclass the main window for layout changes");
+            }
+        })
+        .map_err(|e| e.to_string())
+    }
+
+    unsafe extern "system" fn on_message(hwnd: HWND, msg: u32, wparam: WPARAM, lparam: LPARAM, _id: usize, _data: usize) -> LRESULT {
+        if msg == WM_INPUTLANGCHANGE {
+            // lParam is the new HKL
+            if let Some(tx) = CHANGES.get().and_then(|t| t.lock().ok()) {
+                let _ = tx.send(layout_from_hkl(lparam.0));
+            }
+        }
+        DefSubclassProc(hwnd, msg, wparam, lparam)
+    }
+}
+
+#[cfg(all(not(target_os = "macos"), not(target_os = "windows")))]
+mod platform {
+    use std::process::Command;
+    use std::sync::mpsc::Sender;
+    use x11rb::connection::Connection;
+    use x11rb::protocol::xkb::{self, ConnectionExt as _, EventType, MapPart, SelectEventsAux, SelectEventsAuxStateNotify, StatePart};
+    use x11rb::protocol::xproto::{AtomEnum, ConnectionExt as _};
+    use x11rb::protocol::Event;
+    use x11rb::rust_connection::RustConnection;
+
+    /// Active XKB group; without an X display (pure Wayland) the first
+    /// layout configured for `setxkbmap` is the fallback.
+    pub fn current_layout() -> String {
+        connect()
+            .and_then(|conn| {
+                let state = conn.xkb_get_state(xkb::ID::USE_CORE_KBD.into()).ok()?.reply().ok()?;
+                group_layout(&conn, state.group.into())
+            })
+            .or_else(|| {
+                let out = Command::new("setxkbmap").arg("-query").output().ok()?;
+                out.status.success().then_some(())?;
+                String::from_utf8_lossy(&out.stdout)
+                    .lines()
+                    .find_map(|l| l.strip_prefix("layout:"))
+                    .and_then(|l| l.trim().split(',').next().map(str::to_string))
+            })
+            .unwrap_or_else(|| "us".into())
+    }
+
+    /// Follow XkbStateNotify group changes on a connection of our own.
+    pub fn watch(_app: &tauri::AppHandle, tx: Sender<String>) -> Result<(), String> {
+        let conn = connect().ok_or("no X11 display with the XKB extension")?;
+        let group_changes = SelectEventsAux::new().state_notify(SelectEventsAuxStateNotify {
+            affect_state:  StatePart::GROUP_STATE,
+            state_details: StatePart::GROUP_STATE,
+        });
+        conn.xkb_select_events(
+            xkb::ID::USE_CORE_KBD.into(),
+            EventType::from(0u16),
+            EventType::from(0u16),
+            MapPart::from(0u16),
+            MapPart::from(0u16),
+            &group_changes,
+        )
+        .map_err(|e| e.to_string())?
+        .check()
+        .map_err(|e| e.to_string())?;
+
+        std::thread::spawn(move || {
+            while let Ok(event) = conn.wait_for_event() {
+                let Event::XkbStateNotify(state) = event else { continue };
+                if let Some(layout) = group_layout(&conn, state.group.into()) {
+                    if tx.send(layout).is_err() {
+                        break;
+                    }
+                }
+            }
+        });
+        Ok(())
+    }
+
+    fn connect() -> Option<RustConnection> {
+        let (conn, _) = x11rb::connect(None).ok()?;
+        let xkb = conn.xkb_use_extension(1, 0).ok()?.reply().ok()?;
+        xkb.supported.then_some(conn)
+    }
+
+    /// The configured layouts ("us,ru") live in the root window's
+    /// _XKB_RULES_NAMES property; the group indexes them.
+    fn group_layout(conn: &RustConnection, group: u8) -> Option<String> {
+        let root = conn.setup().roots.first()?.root;
+        let atom = conn.intern_atom(true, b"_XKB_RULES_NAMES").ok()?.reply().ok()?.atom;
+        let names = conn.get_property(false, root, atom, AtomEnum::STRING, 0, 1024).ok()?.reply().ok()?;
+        super::layout_from_rules_names(&names.value, group as usize)
+    }
+}
+
+// ── Unit tests ─────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cyrillic_accelerator_maps_to_latin_position() {
+        assert_eq!(normalize_accelerator("Alt+Ь", "ru", false), "Alt+M");
+        assert_eq!(normalize_accelerator("Alt+Shift+ы", "ru", false), "Alt+Shift+S");
+    }
+
+    #[test]
+    fn test_positional_remap_only_when_enabled() {
+        assert_eq!(normalize_accelerator("Alt+A", "fr", true), "Alt+Q");
+        assert_eq!(normalize_accelerator("Alt+M", "fr", true), "Alt+Semicolon");
+        assert_eq!(normalize_accelerator("Alt+Z", "de", true), "Alt+Y");
+        assert_eq!(normalize_accelerator("Alt+A", "fr", false), "Alt+A");
+        assert_eq!(normalize_accelerator("Alt+Shift+S", "us", true), "Alt+Shift+S");
+    }
+
+    #[test]
+    fn test_layout_family() {
+        assert_eq!(layout_family("fr(azerty)"), "azerty");
+        assert_eq!(layout_family("de"), "qwertz");
+        assert_eq!(layout_family("ru"), "qwerty");
+    }
+
+    fn overrides(pairs: &[(&str, &str)]) -> BTreeMap<String, String> {
+        pairs.iter().map(|(a, b)| (a.to_string(), b.to_string())).collect()
+    }
+
+    #[test]
+    fn test_merge_applies_overrides() {
+        let bound = merge(&overrides(&[("toggle_window", ""), ("capture_screen", "Ctrl+Alt+C"), ("open_settings", "Alt+Comma")]));
+        assert!(bound.iter().any(|(a, b)| a == "toggle_click_through" && b == "Alt+M"));
+        assert!(bound.iter().any(|(a, b)| a == "capture_screen" && b == "Ctrl+Alt+C"));
+        assert!(bound.iter().any(|(a, b)| a == "open_settings" && b == "Alt+Comma"));
+        assert!(!bound.iter().any(|(a, _)| a == "toggle_window"));
+    }
+
+    #[test]
+    fn test_validate_rejects_conflicts() {
+        let current = merge(&BTreeMap::new());
+        assert!(validate("open_settings", "shift+alt+s", &current).is_err());
+        assert!(validate("open_settings", "Ctrl+Enter", &current).is_err());
+        assert!(validate("capture_screen", "Alt+Shift+S", &current).is_ok()); // its own binding
+        assert!(validate("open_settings", "Alt+", &current).is_err());
+        assert!(validate("no_such_action", "Alt+K", &current).is_err());
+        assert!(validate("open_settings", "Alt+K", &current).is_ok());
+    }
+
+    #[test]
+    fn test_validate_rejects_actions_with_arguments() {
+        let current = merge(&BTreeMap::new());
+        assert!(validate("switch_model", "Alt+K", &current).is_err());
+        assert!(validate("switch_model", "", &current).is_err());
+    }
+
+    #[test]
+    fn test_cheatsheet_lists_global_and_window_keys() {
+        let sheet = cheatsheet();
+        for (id, accel) in DEFAULT_HOTKEYS {
+            assert!(sheet.iter().any(|e| e.action == *id && e.scope == "global" && !e.title.is_empty()), "{} {}", id, accel);
+        }
+        assert!(sheet.iter().any(|e| e.action == "send_message" && e.scope == "window"));
+        assert!(!sheet.iter().any(|e| e.action == "quit"));
+    }
+
+    #[test]
+    fn test_layout_from_mac_source() {
+        assert_eq!(layout_from_mac_source("com.apple.keylayout.French"), "fr");
+        assert_eq!(layout_from_mac_source("com.apple.keylayout.Russian-Phonetic"), "ru");
+        assert_eq!(layout_from_mac_source("com.apple.keylayout.ABC"), "us");
+    }
+
+    #[test]
+    fn test_layout_from_rules_names() {
+        let names = b"evdev\0pc105\0us,ru\0,\0grp:alt_shift_toggle\0";
+        assert_eq!(layout_from_rules_names(names, 0).as_deref(), Some("us"));
+        assert_eq!(layout_from_rules_names(names, 1).as_deref(), Some("ru"));
+        assert_eq!(layout_from_rules_names(names, 3).as_deref(), Some("us"));
+        assert_eq!(layout_from_rules_names(b"evdev\0pc105\0\0", 0), None);
+    }
+}
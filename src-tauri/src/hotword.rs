@@ -0,0 +1,450 @@
+// hotword.rs — offline wake-word detection ("hey assistant")
+//
+// Listens on the default microphone and runs an openWakeWord ONNX pipeline
+// entirely on-device:
+//
+//   16 kHz PCM ──► melspectrogram.onnx ──► embedding_model.onnx ──► <wakeword>.onnx ──► score
+//                  (80 ms chunks)          (76 mel frames → 96-d)    (16 embeddings)
+//
+// Porcupine is not bundled: its SDK validates an AccessKey online, which
+// conflicts with the fully-offline requirement.
+//
+// The audio/ONNX engine is compiled only with the `hotword` cargo feature
+// (pulls in cpal + ONNX Runtime via ort 2). Without it the commands return an error and
+// the UI hides the setting.
+//
+// Tauri commands exposed:
+//   start_hotword      → starts the listener thread
+//   stop_hotword       → stops it
+//   get_hotword_status → { running, model }
+//
+// Events emitted:
+//   hotword-detected   → { score, action }  (the main window is also shown + focused)
+
+// The detection helpers below are only reachable from the feature-gated engine.
+#![cfg_attr(not(feature = "hotword"), allow(dead_code))]
+
+use crate::error::AppError;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// openWakeWord operates on 16 kHz mono audio in 1280-sample (80 ms) chunks.
+const SAMPLE_RATE: u32     = 16_000;
+const CHUNK_SAMPLES: usize = 1_280;
+const CHUNK_MS: u32        = 80;
+
+const DEFAULT_SENSITIVITY: f32 = 0.5;
+const DEFAULT_COOLDOWN_MS: u32 = 2_000;
+
+static LISTENER: Mutex<Option<Listener>> = Mutex::new(None);
+
+struct Listener {
+    model:  String,
+    stop:   Arc<AtomicBool>,
+    handle: std::thread::JoinHandle<()>,
+}
+
+impl Listener {
+    /// Signal the thread and wait for it to drop its audio stream
+    /// (it polls the flag every 100 ms).
+    fn shutdown(self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if self.handle.join().is_err() {
+            log::warn!("hotword: listener thread panicked");
+        }
+    }
+}
+
+// ── Types ──────────────────────────────────────────────────────────────────
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct HotwordConfig {
+    /// Path to the wake-word model (e.g. hey_assistant.onnx)
+    pub model_path:       String,
+    /// openWakeWord feature models; default to melspectrogram.onnx /
+    /// embedding_model.onnx next to `model_path`
+    pub melspec_path:     Option<String>,
+    pub embedding_path:   Option<String>,
+    /// 0.0 (strict) – 1.0 (trigger-happy), default 0.5
+    pub sensitivity:      Option<f32>,
+    /// Ignore further detections for this long after a trigger (default 2000)
+    pub cooldown_ms:      Option<u32>,
+    /// What the frontend should do on detection: "quick_ask" | "push_to_talk"
+    pub action:           Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct HotwordStatus {
+    pub running:   bool,
+    pub model:     Option<String>,
+    /// False when the binary was built without the `hotword` feature
+    pub available: bool,
+}
+
+// ── Tauri commands ─────────────────────────────────────────────────────────
+
+#[tauri::command]
+pub fn start_hotword(app_handle: tauri::AppHandle, config: HotwordConfig) -> Result<(), AppError> {
+    let mut listener = LISTENER.lock().map_err(|e| e.to_string())?;
+    // Release the microphone before opening it again
+    if let Some(l) = listener.take() {
+        l.shutdown();
+    }
+
+    let stop   = Arc::new(AtomicBool::new(false));
+    let handle = engine::spawn(app_handle, config.clone(), stop.clone())?;
+    *listener = Some(Listener { model: config.model_path, stop, handle });
+    Ok(())
+}
+
+#[tauri::command]
+pub fn stop_hotword() -> Result<(), AppError> {
+    if let Some(l) = LISTENER.lock().map_err(|e| e.to_string())?.take() {
+        l.shutdown();
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_hotword_status() -> HotwordStatus {
+    let guard  = LISTENER.lock().ok();
+    let active = guard.as_deref()
+        .and_then(Option::as_ref)
+        .filter(|l| !l.handle.is_finished());
+    HotwordStatus {
+        running:   active.is_some(),
+        model:     active.map(|l| l.model.clone()),
+        available: cfg!(feature = "hotword"),
+    }
+}
+
+// ── Detection helpers (feature-independent) ────────────────────────────────
+
+/// Map the user-facing sensitivity to an openWakeWord score threshold.
+/// 0.5 → 0.5, the threshold openWakeWord's own examples use.
+fn threshold_for(sensitivity: f32) -> f32 {
+    (1.0 - sensitivity).clamp(0.05, 0.95)
+}
+
+/// Turns a stream of per-chunk scores into discrete triggers.
+struct Trigger {
+    threshold:       f32,
+    cooldown_chunks: u32,
+    remaining:       u32,
+}
+
+impl Trigger {
+    fn new(sensitivity: f32, cooldown_ms: u32) -> Self {
+        Self {
+            threshold:       threshold_for(sensitivity),
+            cooldown_chunks: cooldown_ms.div_ceil(CHUNK_MS),
+            remaining:       0,
+        }
+    }
+
+    fn update(&mut self, score: f32) -> bool {
+        if self.remaining > 0 {
+            self.remaining -= 1;
+            return false;
+        }
+        if score >= self.threshold {
+            self.remaining = self.cooldown_chunks;
+            return true;
+        }
+        false
+    }
+}
+
+/// Average interleaved channels down to mono.
+fn mixdown(samples: &[f32], channels: usize) -> Vec<f32> {
+    if channels <= 1 {
+        return samples.to_vec();
+    }
+    samples.chunks(channels)
+        .map(|frame| frame.iter().sum::<f32>() / frame.len() as f32)
+        .collect()
+}
+
+/// Streaming linear-interpolation resampler. Wake-word models are trained on
+/// band-limited speech, so the missing anti-alias filter is not noticeable.
+struct Resampler {
+    step: f64,
+    pos:  f64,
+    prev: f32,
+}
+
+impl Resampler {
+    fn new(from_rate: u32, to_rate: u32) -> Self {
+        Self { step: from_rate as f64 / to_rate as f64, pos: 0.0, prev: 0.0 }
+    }
+
+    fn process(&mut self, input: &[f32]) -> Vec<f32> {
+        // Positions index the virtual buffer [prev, input…]
+        let mut out = Vec::with_capacity((input.len() as f64 / self.step) as usize + 1);
+        while self.pos < input.len() as f64 {
+            let i    = self.pos.floor() as usize;
+            let frac = (self.pos - i as f64) as f32;
+            let a    = if i == 0 { self.prev } else { input[i - 1] };
+            out.push(a + (input[i] - a) * frac);
+            self.pos += self.step;
+        }
+        self.pos -= input.len() as f64;
+        if let Some(&last) = input.last() {
+            self.prev = last;
+        }
+        out
+    }
+}
+
+fn on_detect(app: &tauri::AppHandle, score: f32, action: &str) {
+    use tauri::Manager;
+    log::info!("hotword: detected (score {:.2})", score);
+    if let Some(win) = app.get_window("main") {
+        let _ = win.show();
+        let _ = win.set_focus();
+    }
+    let _ = app.emit_all("hotword-detected", serde_json::json!({ "score": score, "action": action }));
+}
+
+// ═══════════════════════════════════════════════════════════════════════
+// Engine — microphone + openWakeWord ONNX models
+// ═══════════════════════════════════════════════════════════════════════
+#[cfg(feature = "hotword")]
+mod engine {
+    use super::*;
+    use anyhow::{anyhow, bail, Result};
+    use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+    use cpal::SampleFormat;
+    use ort::session::builder::GraphOptimizationLevel;
+    use ort::session::Session;
+    use ort::value::Tensor;
+    use std::collections::VecDeque;
+    use std::path::{Path, PathBuf};
+    use std::sync::mpsc;
+    use std::time::Duration;
+
+    const MEL_BINS: usize        = 32;
+    const MEL_WINDOW: usize      = 76;
+    const EMBED_WINDOW: usize    = 16;
+    /// The melspectrogram model needs 3 hops of left context per chunk.
+    const MEL_CONTEXT: usize     = 160 * 3;
+
+    pub fn spawn(
+        app:    tauri::AppHandle,
+        config: HotwordConfig,
+        stop:   Arc<AtomicBool>,
+    ) -> Result<std::thread::JoinHandle<()>, String> {
+        // Load models up front so a bad path is reported to the caller
+        let mut model = OwwModel::load(&config).map_err(|e| format!("Cannot load wake-word model: {}", e))?;
+
+        Ok(std::thread::spawn(move || {
+            if let Err(e) = listen(&app, &config, &mut model, &stop) {
+                log::error!("hotword: listener stopped: {}", e);
+                let _ = tauri::Manager::emit_all(&app, "hotword-error", e.to_string());
+            }
+        }))
+    }
+
+    fn listen(app: &tauri::AppHandle, config: &HotwordConfig, model: &mut OwwModel, stop: &AtomicBool) -> Result<()> {
+        let device = cpal::default_host()
+            .default_input_device()
+            .ok_or_else(|| anyhow!("No microphone found"))?;
+        let supported = device.default_input_config()?;
+        let rate      = supported.sample_rate().0;
+        let channels  = supported.channels() as usize;
+        let format    = supported.sample_format();
+
+        // cpal streams are !Send, so the stream lives on this thread and
+        // hands samples over through a channel.
+        let (tx, rx) = mpsc::channel::<Vec<f32>>();
+        let err_fn = |e: cpal::StreamError| log::warn!("hotword: audio stream error: {}", e);
+        let stream_cfg = supported.into();
+        let stream = match format {
+            SampleFormat::F32 => device.build_input_stream(
+                &stream_cfg, move |d: &[f32], _: &_| { let _ = tx.send(d.to_vec()); }, err_fn, None)?,
+            SampleFormat::I16 => device.build_input_stream(
+                &stream_cfg, move |d: &[i16], _: &_| {
+                    let _ = tx.send(d.iter().map(|s| *s as f32 / 32_768.0).collect());
+                }, err_fn, None)?,
+            SampleFormat::U16 => device.build_input_stream(
+                &stream_cfg, move |d: &[u16], _: &_| {
+                    let _ = tx.send(d.iter().map(|s| (*s as f32 - 32_768.0) / 32_768.0).collect());
+                }, err_fn, None)?,
+            other => bail!("Unsupported microphone sample format: {:?}", other),
+        };
+        stream.play()?;
+        log::info!("hotword: listening ({} Hz, {} ch)", rate, channels);
+
+        let action = config.action.clone().unwrap_or_else(|| "quick_ask".into());
+        let mut trigger = Trigger::new(
+            config.sensitivity.unwrap_or(DEFAULT_SENSITIVITY),
+            config.cooldown_ms.unwrap_or(DEFAULT_COOLDOWN_MS),
+        );
+        let mut resampler = Resampler::new(rate, SAMPLE_RATE);
+        let mut pending: Vec<f32> = Vec::new();
+
+        while !stop.load(Ordering::Relaxed) {
+            let data = match rx.recv_timeout(Duration::from_millis(100)) {
+                Ok(d) => d,
+                Err(mpsc::RecvTimeoutError::Timeout) => continue,
+                Err(mpsc::RecvTimeoutError::Disconnected) => bail!("Microphone stream closed"),
+            };
+            // openWakeWord expects int16-scaled amplitudes
+            pending.extend(resampler.process(&mixdown(&data, channels)).into_iter().map(|s| s * 32_767.0));
+
+            while pending.len() >= CHUNK_SAMPLES {
+                let chunk: Vec<f32> = pending.drain(..CHUNK_SAMPLES).collect();
+                if let Some(score) = model.push_chunk(&chunk)? {
+                    if trigger.update(score) {
+                        on_detect(app, score, &action);
+                        model.reset();
+                    }
+                }
+            }
+        }
+        drop(stream);
+        log::info!("hotword: stopped");
+        Ok(())
+    }
+
+    struct OwwModel {
+        mel:        Session,
+        embed:      Session,
+        wakeword:   Session,
+        raw_tail:   Vec<f32>,
+        mel_frames: VecDeque<[f32; MEL_BINS]>,
+        embeddings: VecDeque<Vec<f32>>,
+    }
+
+    impl OwwModel {
+        fn load(config: &HotwordConfig) -> Result<Self> {
+            let model_path = PathBuf::from(&config.model_path);
+            let dir = model_path.parent().map(Path::to_path_buf).unwrap_or_default();
+            let mel_path = config.melspec_path.clone().map(PathBuf::from)
+                .unwrap_or_else(|| dir.join("melspectrogram.onnx"));
+            let emb_path = config.embedding_path.clone().map(PathBuf::from)
+                .unwrap_or_else(|| dir.join("embedding_model.onnx"));
+
+            let open = |p: &Path| -> Result<Session> {
+                if !p.is_file() {
+                    bail!("'{}' not found", p.display());
+                }
+                Ok(Session::builder()?
+                    .with_optimization_level(GraphOptimizationLevel::Level3)?
+                    .with_intra_threads(1)?
+                    .commit_from_file(p)?)
+            };
+
+            Ok(Self {
+                mel:        open(&mel_path)?,
+                embed:      open(&emb_path)?,
+                wakeword:   open(&model_path)?,
+                raw_tail:   Vec::new(),
+                mel_frames: VecDeque::new(),
+                embeddings: VecDeque::new(),
+            })
+        }
+
+        /// Clear the feature buffers so one utterance cannot trigger twice.
+        fn reset(&mut self) {
+            self.mel_frames.clear();
+            self.embeddings.clear();
+        }
+
+        /// Feed one 80 ms chunk; returns a score once enough context exists.
+        fn push_chunk(&mut self, chunk: &[f32]) -> Result<Option<f32>> {
+            let mut input = std::mem::take(&mut self.raw_tail);
+            input.extend_from_slice(chunk);
+            self.raw_tail = input[input.len().saturating_sub(MEL_CONTEXT)..].to_vec();
+
+            let mel = run(&mut self.mel, vec![1, input.len()], input)?;
+            for frame in mel.chunks_exact(MEL_BINS) {
+                let mut bins = [0f32; MEL_BINS];
+                for (b, v) in bins.iter_mut().zip(frame) {
+                    *b = v / 10.0 + 2.0;
+                }
+                self.mel_frames.push_back(bins);
+            }
+            while self.mel_frames.len() > MEL_WINDOW {
+                self.mel_frames.pop_front();
+            }
+            if self.mel_frames.len() < MEL_WINDOW {
+                return Ok(None);
+            }
+
+            let window: Vec<f32> = self.mel_frames.iter().flatten().copied().collect();
+            let embedding = run(&mut self.embed, vec![1, MEL_WINDOW, MEL_BINS, 1], window)?;
+            self.embeddings.push_back(embedding);
+            while self.embeddings.len() > EMBED_WINDOW {
+                self.embeddings.pop_front();
+            }
+            if self.embeddings.len() < EMBED_WINDOW {
+                return Ok(None);
+            }
+
+            let features: Vec<f32> = self.embeddings.iter().flatten().copied().collect();
+            let dim = features.len() / EMBED_WINDOW;
+            let score = run(&mut self.wakeword, vec![1, EMBED_WINDOW, dim], features)?;
+            Ok(score.first().copied())
+        }
+    }
+
+    /// Run a single-input, single-output model and flatten the result.
+    fn run(session: &mut Session, shape: Vec<usize>, data: Vec<f32>) -> Result<Vec<f32>> {
+        let input   = Tensor::from_array((shape, data))?;
+        let outputs = session.run(ort::inputs![input])?;
+        let (_, values) = outputs[0].try_extract_tensor::<f32>()?;
+        Ok(values.to_vec())
+    }
+}
+
+#[cfg(not(feature = "hotword"))]
+mod engine {
+    use super::*;
+
+    pub fn spawn(
+        _app:    tauri::AppHandle,
+        _config: HotwordConfig,
+        _stop:   Arc<AtomicBool>,
+    ) -> Result<std::thread::JoinHandle<()>, String> {
+        Err("This build does not include wake-word support (enable the `hotword` feature)".into())
+    }
+}
+
+// ── Unit tests ─────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_threshold_for_sensitivity() {
+        assert_eq!(threshold_for(0.5), 0.5);
+        assert!(threshold_for(0.9) < threshold_for(0.1));
+        assert_eq!(threshold_for(2.0), 0.05);
+    }
+
+    #[test]
+    fn test_trigger_respects_cooldown() {
+        let mut t = Trigger::new(0.5, 160); // 2 chunks
+        assert!(t.update(0.9));
+        assert!(!t.update(0.9));
+        assert!(!t.update(0.9));
+        assert!(t.update(0.9));
+        assert!(!Trigger::new(0.5, 0).update(0.2));
+    }
+
+    #[test]
+    fn test_mixdown_stereo() {
+        assert_eq!(mixdown(&[1.0, 0.0, 0.5, 0.5], 2), vec![0.5, 0.5]);
+        assert_eq!(mixdown(&[0.25], 1), vec![0.25]);
+    }
+
+    #[test]
+    fn test_resampler_48k_to_16k() {
+        let mut r = Resampler::new(48_000, SAMPLE_RATE);
+        let total: usize = (0..10).map(|_| r.process(&[0.1; 4_800]).len()).sum();
+        assert_eq!(total, 16_000);
+    }
+}
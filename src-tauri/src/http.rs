@@ -0,0 +1,62 @@
+// http.rs — process-wide reqwest clients
+//
+// A `Client` owns the connection pool and TLS session cache, so building one
+// per call throws both away and every request pays a fresh TCP + TLS
+// handshake. Clients are created lazily, one per timeout class, and cloned
+// (an Arc bump) by the callers.
+
+use reqwest::Client;
+use std::sync::OnceLock;
+use std::time::Duration;
+
+const APP_USER_AGENT: &str = "ai-assistant/0.1";
+/// Search engines and docs sites serve stripped-down or blocked pages to
+/// non-browser agents.
+const BROWSER_USER_AGENT: &str = "Mozilla/5.0 (X11; Linux x86_64) AppleWebKit/537.36 \
+                                  (KHTML, like Gecko) Chrome/122.0.0.0 Safari/537.36";
+
+/// Timeout class of a shared client.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Timeout {
+    /// Chat / completion calls — 10 min, local LLMs can be slow
+    Llm,
+    /// Image generation APIs — 2 min
+    ImageGen,
+    /// Search APIs and JSON endpoints — 30 s
+    Api,
+    /// Search result pages (browser UA) — 20 s
+    Search,
+    /// Fetching result pages for their content (browser UA) — 8 s
+    Page,
+}
+
+static CLIENTS: [OnceLock<Client>; 5] = [
+    OnceLock::new(), OnceLock::new(), OnceLock::new(), OnceLock::new(), OnceLock::new(),
+];
+
+/// Shared client for `class`. Only the first call per class builds one.
+pub(crate) fn client(class: Timeout) -> reqwest::Result<Client> {
+    let cell = &CLIENTS[class as usize];
+    if let Some(c) = cell.get() {
+        return Ok(c.clone());
+    }
+    let built = build(class)?;
+    Ok(cell.get_or_init(|| built).clone())
+}
+
+fn build(class: Timeout) -> reqwest::Result<Client> {
+    let (timeout, user_agent) = match class {
+        Timeout::Llm      => (600, APP_USER_AGENT),
+        Timeout::ImageGen => (120, APP_USER_AGENT),
+        Timeout::Api      => (30,  APP_USER_AGENT),
+        Timeout::Search   => (20,  BROWSER_USER_AGENT),
+        Timeout::Page     => (8,   BROWSER_USER_AGENT),
+    };
+    Client::builder()
+        .connect_timeout(Duration::from_secs(10))
+        .timeout(Duration::from_secs(timeout))
+        .pool_idle_timeout(Duration::from_secs(90))
+        .tcp_keepalive(Duration::from_secs(60))
+        .user_agent(user_agent)
+        .build()
+}
@@ -0,0 +1,299 @@
+// image_edit.rs — Stability AI editing endpoints (v2beta stable-image/edit)
+//
+// Cleans up screenshots and generated images in place instead of
+// regenerating them: repaint a masked region from a prompt, extend the
+// canvas, erase objects, or cut the subject out. The mask is a grayscale
+// image the size of the input — white is edited, black is kept; without
+// one, inpaint and erase use the input's alpha channel. Results are always
+// requested as PNG (remove-background keeps its transparency) and are kept
+// in the gallery like generated images.
+//
+// Tauri commands exposed:
+//   stability_inpaint(req)           → ImageGenResponse  (prompt required)
+//   stability_outpaint(req)          → ImageGenResponse  (left/right/up/down px)
+//   stability_erase(req)             → ImageGenResponse
+//   stability_remove_background(req) → ImageGenResponse
+
+use crate::error::AppError;
+use crate::gallery;
+use crate::{audit, http};
+use crate::image_gen::ImageGenResponse;
+use base64::{engine::general_purpose, Engine};
+use reqwest::multipart::{Form, Part};
+use serde::Deserialize;
+
+const STABILITY_EDIT: &str = "https://api.stability.ai/v2beta/stable-image/edit";
+/// Outpaint limit per side, in pixels
+const MAX_OUTPAINT: u32 = 2000;
+
+// ── Types ──────────────────────────────────────────────────────────────────
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum EditOp {
+    Inpaint,
+    Outpaint,
+    Erase,
+    RemoveBackground,
+}
+
+impl EditOp {
+    /// Path segment under /v2beta/stable-image/edit/
+    fn endpoint(self) -> &'static str {
+        match self {
+            EditOp::Inpaint          => "inpaint",
+            EditOp::Outpaint         => "outpaint",
+            EditOp::Erase            => "erase",
+            EditOp::RemoveBackground => "remove-background",
+        }
+    }
+}
+
+/// Shared by all four commands; each reads the fields its endpoint takes.
+#[derive(Debug, Deserialize, Default)]
+pub struct StabilityEditRequest {
+    pub api_key:         Option<String>,
+    /// Input image, base64 or data: URI
+    pub image_base64:    String,
+    /// White = edit, black = keep (inpaint, erase)
+    #[serde(default)]
+    pub mask_base64:     Option<String>,
+    /// What to paint (required for inpaint, optional for outpaint)
+    #[serde(default)]
+    pub prompt:          Option<String>,
+    #[serde(default)]
+    pub negative_prompt: Option<String>,
+    /// Fixed seed; None / negative = random
+    #[serde(default)]
+    pub seed:            Option<i64>,
+    /// Pixels to grow the mask edges by, 0–100 (inpaint, erase)
+    #[serde(default)]
+    pub grow_mask:       Option<u32>,
+    /// Pixels to add on each side (outpaint)
+    #[serde(default)]
+    pub left:            Option<u32>,
+    #[serde(default)]
+    pub right:           Option<u32>,
+    #[serde(default)]
+    pub up:              Option<u32>,
+    #[serde(default)]
+    pub down:            Option<u32>,
+    /// How freely the new area is invented, 0–1 (outpaint)
+    #[serde(default)]
+    pub creativity:      Option<f32>,
+}
+
+// ── Tauri commands ─────────────────────────────────────────────────────────
+
+#[tauri::command]
+pub async fn stability_inpaint(app_handle: tauri::AppHandle, req: StabilityEditRequest) -> Result<ImageGenResponse, AppError> {
+    stability_edit(&app_handle, EditOp::Inpaint, req).await
+}
+
+#[tauri::command]
+pub async fn stability_outpaint(app_handle: tauri::AppHandle, req: StabilityEditRequest) -> Result<ImageGenResponse, AppError> {
+    stability_edit(&app_handle, EditOp::Outpaint, req).await
+}
+
+#[tauri::command]
+pub async fn stability_erase(app_handle: tauri::AppHandle, req: StabilityEditRequest) -> Result<ImageGenResponse, AppError> {
+    stability_edit(&app_handle, EditOp::Erase, req).await
+}
+
+#[tauri::command]
+pub async fn stability_remove_background(app_handle: tauri::AppHandle, req: StabilityEditRequest) -> Result<ImageGenResponse, AppError> {
+    stability_edit(&app_handle, EditOp::RemoveBackground, req).await
+}
+
+// ── Request ────────────────────────────────────────────────────────────────
+
+async fn stability_edit(app: &tauri::AppHandle, op: EditOp, req: StabilityEditRequest) -> Result<ImageGenResponse, AppError> {
+    let key = req.api_key.as_deref().unwrap_or("").trim().to_string();
+    if key.is_empty() {
+        return Err("Stability AI API key required".into());
+    }
+    let fields = edit_fields(op, &req)?;
+    let image = decode_image(&req.image_base64).map_err(|e| format!("Input image: {}", e))?;
+    // The multipart body can't be read back; the audit log gets this instead
+    let mut described = vec![format!("image: {} bytes", image.len())];
+    let mut form = Form::new().part("image", image_part(image, "image.png")?);
+    if let Some(mask) = req.mask_base64.as_deref().filter(|_| matches!(op, EditOp::Inpaint | EditOp::Erase)) {
+        let mask = decode_image(mask).map_err(|e| format!("Mask: {}", e))?;
+        described.push(format!("mask: {} bytes", mask.len()));
+        form = form.part("mask", image_part(mask, "mask.png")?);
+    }
+    for (name, value) in fields {
+        described.push(format!("{}: {}", name, value));
+        form = form.text(name, value);
+    }
+
+    let client = http::client(http::Timeout::ImageGen)?;
+    let request = client
+        .post(format!("{}/{}", STABILITY_EDIT, op.endpoint()))
+        .header("Authorization", format!("Bearer {}", key))
+        .header("Accept", "image/*")
+        .multipart(form);
+    let (resp, mut exchange) = audit::send_stream("stability", request).await
+        .map_err(|e| format!("Stability AI request failed: {}", e))?;
+    exchange.describe_request(&described.join("\n"));
+
+    let status = resp.status();
+    if !status.is_success() {
+        let text = resp.text().await.unwrap_or_default();
+        exchange.push(&text);
+        return Err(format!("Stability AI {}: {}", status, text).into());
+    }
+    let bytes = resp.bytes().await.map_err(|e| {
+        exchange.fail(&e.to_string());
+        e.to_string()
+    })?;
+    exchange.push(&format!("[image: {} bytes]", bytes.len()));
+    let image_base64 = general_purpose::STANDARD.encode(&bytes);
+
+    gallery::record(app, &image_base64, gallery::GenerationRecord {
+        provider:        "stability".into(),
+        model:           Some(op.endpoint().into()),
+        prompt:          req.prompt.clone().unwrap_or_default(),
+        negative_prompt: req.negative_prompt.clone(),
+        seed:            req.seed.filter(|s| *s >= 0),
+        ..Default::default()
+    });
+    Ok(ImageGenResponse {
+        image_base64,
+        revised_prompt: None,
+        format: "png".into(),
+        seed: req.seed.filter(|s| *s >= 0 && op != EditOp::RemoveBackground),
+    })
+}
+
+/// Text fields of the multipart form, validated for `op`.
+fn edit_fields(op: EditOp, req: &StabilityEditRequest) -> Result<Vec<(&'static str, String)>, String> {
+    let mut fields = vec![("output_format", "png".to_string())];
+    let prompt = req.prompt.as_deref().map(str::trim).filter(|p| !p.is_empty());
+    let negative = req.negative_prompt.as_deref().map(str::trim).filter(|n| !n.is_empty());
+
+    match op {
+        EditOp::Inpaint => {
+            let prompt = prompt.ok_or("Inpainting needs a prompt describing the new content")?;
+            fields.push(("prompt", prompt.to_string()));
+        }
+        EditOp::Outpaint => {
+            let sides = [("left", req.left), ("right", req.right), ("up", req.up), ("down", req.down)];
+            if sides.iter().all(|(_, px)| px.unwrap_or(0) == 0) {
+                return Err("Outpainting needs at least one of left/right/up/down".into());
+            }
+            for (side, px) in sides {
+                match px.unwrap_or(0) {
+                    0 => {}
+                    px if px > MAX_OUTPAINT => {
+                        return Err(format!("Outpaint {} is {} px, at most {} allowed", side, px, MAX_OUTPAINT));
+                    }
+                    px => fields.push((side, px.to_string())),
+                }
+            }
+            if let Some(prompt) = prompt {
+                fields.push(("prompt", prompt.to_string()));
+            }
+            if let Some(creativity) = req.creativity {
+                fields.push(("creativity", creativity.clamp(0.0, 1.0).to_string()));
+            }
+        }
+        EditOp::Erase | EditOp::RemoveBackground => {}
+    }
+    if op == EditOp::Inpaint {
+        if let Some(negative) = negative {
+            fields.push(("negative_prompt", negative.to_string()));
+        }
+    }
+    if matches!(op, EditOp::Inpaint | EditOp::Erase) {
+        if let Some(grow) = req.grow_mask {
+            fields.push(("grow_mask", grow.min(100).to_string()));
+        }
+    }
+    if op != EditOp::RemoveBackground {
+        if let Some(seed) = req.seed.filter(|s| *s >= 0) {
+            fields.push(("seed", seed.to_string()));
+        }
+    }
+    Ok(fields)
+}
+
+fn decode_image(data: &str) -> Result<Vec<u8>, String> {
+    let b64 = data.split_once("base64,").map_or(data, |(_, d)| d);
+    let bytes = general_purpose::STANDARD
+        .decode(b64.trim())
+        .map_err(|e| format!("invalid base64: {}", e))?;
+    image::guess_format(&bytes).map_err(|_| "not a PNG, JPEG or WebP image".to_string())?;
+    Ok(bytes)
+}
+
+fn image_part(bytes: Vec<u8>, file_name: &'static str) -> Result<Part, String> {
+    let mime = match image::guess_format(&bytes) {
+        Ok(image::ImageFormat::Jpeg) => "image/jpeg",
+        Ok(image::ImageFormat::WebP) => "image/webp",
+        _ => "image/png",
+    };
+    Part::bytes(bytes).file_name(file_name).mime_str(mime).map_err(|e| e.to_string())
+}
+
+// ── Unit tests ─────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get<'a>(fields: &'a [(&'static str, String)], name: &str) -> Option<&'a str> {
+        fields.iter().find(|(n, _)| *n == name).map(|(_, v)| v.as_str())
+    }
+
+    #[test]
+    fn test_inpaint_and_erase_fields() {
+        let req = StabilityEditRequest { prompt: Some("  ".into()), ..Default::default() };
+        assert!(edit_fields(EditOp::Inpaint, &req).is_err());
+
+        let req = StabilityEditRequest {
+            prompt: Some("a blue sky".into()),
+            negative_prompt: Some("clouds".into()),
+            seed: Some(42),
+            grow_mask: Some(250),
+            ..Default::default()
+        };
+        let fields = edit_fields(EditOp::Inpaint, &req).unwrap();
+        assert_eq!(get(&fields, "prompt"), Some("a blue sky"));
+        assert_eq!(get(&fields, "negative_prompt"), Some("clouds"));
+        assert_eq!(get(&fields, "grow_mask"), Some("100"));
+        assert_eq!(get(&fields, "seed"), Some("42"));
+        assert_eq!(get(&fields, "output_format"), Some("png"));
+
+        let fields = edit_fields(EditOp::Erase, &req).unwrap();
+        assert_eq!(get(&fields, "prompt"), None);
+        assert_eq!(get(&fields, "grow_mask"), Some("100"));
+
+        let fields = edit_fields(EditOp::RemoveBackground, &req).unwrap();
+        assert_eq!(fields, vec![("output_format", "png".to_string())]);
+    }
+
+    #[test]
+    fn test_outpaint_fields() {
+        assert!(edit_fields(EditOp::Outpaint, &StabilityEditRequest::default()).is_err());
+        let too_far = StabilityEditRequest { left: Some(4000), ..Default::default() };
+        assert!(edit_fields(EditOp::Outpaint, &too_far).is_err());
+
+        let req = StabilityEditRequest { right: Some(512), down: Some(0), creativity: Some(1.5), ..Default::default() };
+        let fields = edit_fields(EditOp::Outpaint, &req).unwrap();
+        assert_eq!(get(&fields, "right"), Some("512"));
+        assert_eq!(get(&fields, "down"), None);
+        assert_eq!(get(&fields, "creativity"), Some("1"));
+        assert_eq!(get(&fields, "prompt"), None);
+    }
+
+    #[test]
+    fn test_decode_image_accepts_data_uri() {
+        let mut png = Vec::new();
+        image::DynamicImage::new_rgba8(2, 2)
+            .write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+            .unwrap();
+        let b64 = general_purpose::STANDARD.encode(&png);
+        assert_eq!(decode_image(&format!("data:image/png;base64,{}", b64)).unwrap(), png);
+        assert!(decode_image("bm90IGFuIGltYWdl").is_err());
+    }
+}
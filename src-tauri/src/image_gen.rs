@@ -1,17 +1,76 @@
 // image_gen.rs — AI image generation via multiple backends
 //
 // Backends:
-//   dalle       — OpenAI DALL-E 3 (uses OpenAI API key)
+//   dalle       — OpenAI Images API: dall-e-3 / dall-e-2, or gpt-image-1
+//                 (quality, transparent background, output format)
 //   stability   — Stability AI stable-image-core (v2beta REST)
 //   together    — Together AI FLUX / SDXL (requires Together API key)
 //   local_sd    — Local Automatic1111 / FORGE WebUI (no key, http://localhost:7860)
 //   openrouter  — OpenRouter image generation (uses OpenRouter key)
+//   replicate   — any Replicate-hosted model: `owner/name` (latest version)
+//                 or `owner/name:version`; the prediction is polled until done
+//   fal         — fal.ai queue API (FLUX by default): submit, follow the status
+//                 stream (reported as `sd-progress`), fetch the result
+//   imagen      — Google Imagen 3 through the Gemini API (Google AI Studio key);
+//                 the size becomes the nearest supported aspect ratio
+//
+// A random seed is drawn here for providers that take one (everything but
+// DALL-E, OpenRouter and Imagen) and returned in the response, so any cloud result
+// can be reproduced. Providers without a negative prompt parameter get it
+// appended to the prompt as "Avoid: …".
+//
+// With `init_image_base64` the request becomes image-to-image: Stability
+// switches to the SD3 endpoint, gpt-image-1 to /v1/images/edits, and
+// Together, Replicate and fal get the image as a data: URI. dall-e-2/3,
+// OpenRouter, Imagen and local_sd reject it (native SD has its own img2img).
+//
+// While a local_sd request runs, /sdapi/v1/progress is polled and reported
+// with the same `sd-progress` / `sd-preview` events as stable-diffusion.cpp
+// (local_sd.rs); interrupt_webui_generation stops it.
 
+use crate::error::{AppError, ErrorKind};
+use crate::local_sd::{self, SdPreview, SdProgress};
+use crate::{audit, gallery, gen_metadata, http, image_queue, style_presets};
 use base64::{engine::general_purpose, Engine};
+use futures_util::StreamExt;
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
-use std::time::Duration;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tauri::Manager;
+
+/// How often a running WebUI job is asked for progress
+const WEBUI_POLL: std::time::Duration = std::time::Duration::from_millis(500);
+
+/// Interval between Replicate prediction polls, and how long to keep polling
+const REPLICATE_POLL: std::time::Duration = std::time::Duration::from_secs(1);
+const REPLICATE_MAX_WAIT: std::time::Duration = std::time::Duration::from_secs(300);
+const REPLICATE_DEFAULT_MODEL: &str = "black-forest-labs/flux-schnell";
+/// Flux-family models take an aspect ratio from this set instead of a size
+const REPLICATE_ASPECT_RATIOS: &[(u32, u32)] = &[
+    (1, 1), (16, 9), (21, 9), (3, 2), (2, 3), (4, 5), (5, 4), (3, 4), (4, 3), (9, 16), (9, 21),
+];
+
+const IMAGEN_API: &str = "https://generativelanguage.googleapis.com/v1beta/models";
+const IMAGEN_DEFAULT_MODEL: &str = "imagen-3.0-generate-002";
+/// The only aspect ratios Imagen accepts
+const IMAGEN_ASPECT_RATIOS: &[(u32, u32)] = &[(1, 1), (3, 4), (4, 3), (9, 16), (16, 9)];
+
+/// Stability's img2img runs on the SD3 endpoint; generate/core has no image input
+const STABILITY_IMG2IMG_MODEL: &str = "sd3.5-large";
+/// Default image-to-image strength when the request gives none
+const DEFAULT_STRENGTH: f32 = 0.65;
+
+const FAL_QUEUE: &str = "https://queue.fal.run";
+const FAL_DEFAULT_MODEL: &str = "fal-ai/flux/schnell";
+/// Status poll interval when the status stream is unavailable, and how long
+/// a queued request may take in total
+const FAL_POLL: std::time::Duration = std::time::Duration::from_secs(1);
+const FAL_MAX_WAIT: std::time::Duration = std::time::Duration::from_secs(300);
+
+/// Set by interrupt_webui_generation; the running local_sd request then
+/// fails instead of returning A1111's half-finished image.
+static WEBUI_INTERRUPTED: AtomicBool = AtomicBool::new(false);
 
 // ── Public types ─────────────────────────────────────────────────────────
 
@@ -19,11 +78,12 @@ use std::time::Duration;
 pub struct ImageGenRequest {
     /// The visual prompt describing the image
     pub prompt: String,
-    /// "dalle" | "stability" | "together" | "local_sd" | "openrouter"
+    /// "dalle" | "stability" | "together" | "local_sd" | "openrouter" | "replicate" | "fal" | "imagen"
     pub provider: String,
     /// API key (not needed for local_sd)
     pub api_key: Option<String>,
-    /// Model name override
+    /// Model name override; for replicate `owner/name` or `owner/name:version`,
+    /// for fal an endpoint id ("fal-ai/flux/dev")
     pub model: Option<String>,
     /// Base URL override (required for local_sd, optional for others)
     pub url: Option<String>,
@@ -31,6 +91,61 @@ pub struct ImageGenRequest {
     pub width: Option<u32>,
     /// Image height in pixels
     pub height: Option<u32>,
+    /// Caller-chosen id for image-queue events (local_sd only)
+    #[serde(default)]
+    pub queue_id: Option<String>,
+    /// Things to keep out of the image; appended to the prompt for dalle,
+    /// openrouter and imagen, which have no such parameter
+    #[serde(default)]
+    pub negative_prompt: Option<String>,
+    /// Guidance scale (local_sd only)
+    #[serde(default)]
+    pub cfg_scale: Option<f32>,
+    /// Style preset id (style_presets.rs) applied before the provider is called
+    #[serde(default)]
+    pub style: Option<String>,
+    /// Sampling steps (local_sd, replicate, fal)
+    #[serde(default)]
+    pub steps: Option<u32>,
+    /// Sampler — A1111 name ("DPM++ 2M Karras") or stable-diffusion.cpp
+    /// name ("dpm++2m"), which is translated (local_sd only)
+    #[serde(default)]
+    pub sampler: Option<String>,
+    /// Fixed seed; None / negative = random (ignored by dalle, openrouter and imagen)
+    #[serde(default)]
+    pub seed: Option<i64>,
+    /// Second upscaled pass (local_sd only)
+    #[serde(default)]
+    pub hires: Option<HiresFix>,
+    /// dalle: "standard" | "hd" for dall-e-3, "low" | "medium" | "high" | "auto"
+    /// for gpt-image-1
+    #[serde(default)]
+    pub quality: Option<String>,
+    /// "transparent" | "opaque" | "auto" (gpt-image-1 only)
+    #[serde(default)]
+    pub background: Option<String>,
+    /// "png" | "jpeg" | "webp" (gpt-image-1 only; WebP comes back as PNG)
+    #[serde(default)]
+    pub output_format: Option<String>,
+    /// Source image for image-to-image (base64 or data: URI; stability,
+    /// together, replicate, fal, gpt-image-1)
+    #[serde(default)]
+    pub init_image_base64: Option<String>,
+    /// How far the result may move from the source, 0–1 (default 0.65)
+    #[serde(default)]
+    pub strength: Option<f32>,
+}
+
+/// A1111 / Forge "Hires. fix" settings.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct HiresFix {
+    /// Upscale factor (WebUI default 2)
+    pub scale: Option<f32>,
+    /// "Latent", "R-ESRGAN 4x+", … (WebUI default "Latent")
+    pub upscaler: Option<String>,
+    /// Steps of the second pass; None / 0 = same as the first
+    pub steps: Option<u32>,
+    pub denoising_strength: Option<f32>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -41,34 +156,100 @@ pub struct ImageGenResponse {
     pub revised_prompt: Option<String>,
     /// "png" or "jpeg"
     pub format: String,
+    /// Seed the image was made with; None when the provider takes no seed
+    #[serde(default)]
+    pub seed: Option<i64>,
 }
 
 // ── HTTP client ───────────────────────────────────────────────────────────
 
 fn http_client() -> reqwest::Result<Client> {
-    Client::builder()
-        .timeout(Duration::from_secs(120))
-        .user_agent("ai-assistant/0.1")
-        .build()
+    http::client(http::Timeout::ImageGen)
 }
 
 // ── Tauri command ─────────────────────────────────────────────────────────
 
 /// Generate an image using the configured provider.
 /// Returns base64-encoded PNG/JPEG without the data: URI prefix.
+/// local_sd requests wait their turn in the GPU queue (image_queue.rs).
+/// A `style` preset is applied first; PNG results get an A1111 "parameters"
+/// chunk (gen_metadata.rs) and are kept in the gallery (gallery.rs).
 #[tauri::command]
-pub async fn generate_image(req: ImageGenRequest) -> Result<ImageGenResponse, String> {
-    match req.provider.as_str() {
+pub async fn generate_image(app_handle: tauri::AppHandle, mut req: ImageGenRequest) -> Result<ImageGenResponse, AppError> {
+    if let Some(style) = style_presets::resolve(&app_handle, req.style.as_deref())? {
+        req.prompt = style.prompt(&req.prompt);
+        req.negative_prompt = style.negative(req.negative_prompt.as_deref());
+        req.cfg_scale = style.cfg_scale.or(req.cfg_scale);
+        req.sampler = style.sampler.or(req.sampler);
+        req.width = style.width.or(req.width);
+        req.height = style.height.or(req.height);
+    }
+    let provider = req.provider.clone();
+    if has_init_image(&req) && !supports_init_image(&req) {
+        let name = if provider == "dalle" { req.model.as_deref().unwrap_or("dall-e-3") } else { provider.as_str() };
+        return Err(AppError::new(
+            ErrorKind::Validation,
+            format!("{} cannot start from an image; use stability, together, replicate, fal or gpt-image-1", name),
+        ).with_provider(provider));
+    }
+    if takes_seed(&provider) && req.seed.is_none_or(|s| s < 0) {
+        req.seed = Some(local_sd::random_seed());
+    }
+    let seed = req.seed.filter(|_| takes_seed(&provider));
+    let record = gallery::GenerationRecord {
+        provider:        req.provider.clone(),
+        model:           req.model.clone(),
+        prompt:          req.prompt.clone(),
+        negative_prompt: req.negative_prompt.clone(),
+        cfg_scale:       req.cfg_scale,
+        seed:            seed,
+        sampler:         req.sampler.clone(),
+        steps:           req.steps,
+        ..Default::default()
+    };
+    let result = match req.provider.as_str() {
         "dalle"      => dalle_generate(req).await,
         "stability"  => stability_generate(req).await,
         "together"   => together_generate(req).await,
-        "local_sd"   => local_sd_generate(req).await,
+        "local_sd"   => {
+            let _slot = image_queue::acquire(&app_handle, req.queue_id.clone(), "webui", &req.prompt).await?;
+            local_sd_generate(&app_handle, req).await
+        }
         "openrouter" => openrouter_generate(req).await,
+        "replicate"  => replicate_generate(req).await,
+        "fal"        => fal_generate(&app_handle, req).await,
+        "imagen"     => imagen_generate(req).await,
         other => Err(format!("Unknown image generation provider: {}", other)),
+    };
+    result.map(|mut resp| {
+        resp.seed = seed;
+        let record = gallery::GenerationRecord { revised_prompt: resp.revised_prompt.clone(), ..record };
+        if resp.format == "png" {
+            resp.image_base64 = gen_metadata::embed_parameters(&resp.image_base64, &record);
+        }
+        gallery::record(&app_handle, &resp.image_base64, record);
+        resp
+    }).map_err(|e| AppError::from(e).with_provider(provider))
+}
+
+/// Stop the running A1111 / Forge job (`url` = WebUI base URL).
+#[tauri::command]
+pub async fn interrupt_webui_generation(url: Option<String>) -> Result<(), AppError> {
+    let base_url = webui_base_url(url.as_deref());
+    WEBUI_INTERRUPTED.store(true, Ordering::SeqCst);
+    let client = http::client(http::Timeout::Page).map_err(|e| e.to_string())?;
+    let resp = client
+        .post(format!("{}/sdapi/v1/interrupt", base_url))
+        .send()
+        .await
+        .map_err(|e| format!("Cannot reach local SD server at {} — {}", base_url, e))?;
+    if !resp.status().is_success() {
+        return Err(format!("Local SD interrupt: {}", resp.status()).into());
     }
+    Ok(())
 }
 
-// ── DALL-E 3 ─────────────────────────────────────────────────────────────
+// ── OpenAI Images (DALL-E 2/3, gpt-image-1) ──────────────────────────────
 
 async fn dalle_generate(req: ImageGenRequest) -> Result<ImageGenResponse, String> {
     let key = req.api_key.as_deref().unwrap_or("").trim().to_string();
@@ -76,34 +257,32 @@ async fn dalle_generate(req: ImageGenRequest) -> Result<ImageGenResponse, String
         return Err("OpenAI API key required for DALL-E".into());
     }
 
-    let model = req.model.as_deref().unwrap_or("dall-e-3");
-
-    // DALL-E 3 supported sizes: 1024×1024, 1792×1024, 1024×1792
-    let w = req.width.unwrap_or(1024);
-    let h = req.height.unwrap_or(1024);
-    let size = if w > h { "1792x1024" } else if h > w { "1024x1792" } else { "1024x1024" };
-
+    let body = dalle_body(&req)?;
     let client = http_client().map_err(|e| e.to_string())?;
-    let body = json!({
-        "model": model,
-        "prompt": req.prompt,
-        "n": 1,
-        "size": size,
-        "response_format": "b64_json",
-        "quality": "standard",
-    });
-
-    let resp = client
-        .post("https://api.openai.com/v1/images/generations")
-        .header("Authorization", format!("Bearer {}", key))
-        .header("Content-Type", "application/json")
-        .json(&body)
-        .send()
+    // With a source image gpt-image-1 goes to /edits, which takes the same
+    // options as multipart fields
+    let request = match init_image(&req)? {
+        Some((bytes, mime)) => {
+            let mut form = reqwest::multipart::Form::new().part("image", image_part(bytes, mime)?);
+            for (name, value) in body.as_object().into_iter().flatten() {
+                let value = value.as_str().map(String::from).unwrap_or_else(|| value.to_string());
+                form = form.text(name.clone(), value);
+            }
+            client
+                .post("https://api.openai.com/v1/images/edits")
+                .header("Authorization", format!("Bearer {}", key))
+                .multipart(form)
+        }
+        None => client
+            .post("https://api.openai.com/v1/images/generations")
+            .header("Authorization", format!("Bearer {}", key))
+            .header("Content-Type", "application/json")
+            .json(&body),
+    };
+    let (status, text) = audit::send("dalle", request)
         .await
         .map_err(|e| format!("DALL-E request failed: {}", e))?;
-
-    let status = resp.status();
-    let json: Value = resp.json().await.map_err(|e| e.to_string())?;
+    let json: Value = serde_json::from_str(&text).map_err(|e| e.to_string())?;
 
     if !status.is_success() {
         let err = json["error"]["message"].as_str().unwrap_or("Unknown DALL-E error");
@@ -112,20 +291,98 @@ async fn dalle_generate(req: ImageGenRequest) -> Result<ImageGenResponse, String
 
     let b64 = json["data"][0]["b64_json"]
         .as_str()
-        .ok_or("No image returned by DALL-E")?
-        .to_string();
+        .ok_or("No image returned by DALL-E")?;
 
+    // dall-e-3 rewrites the prompt; gpt-image-1 has no revised_prompt but may
+    // answer in JPEG or WebP
     let revised = json["data"][0]["revised_prompt"]
         .as_str()
         .map(|s| s.to_string());
+    let (image_base64, format) = if is_gpt_image(&body["model"]) {
+        let bytes = general_purpose::STANDARD.decode(b64).map_err(|e| e.to_string())?;
+        let (bytes, format) = png_or_jpeg(&bytes)?;
+        (general_purpose::STANDARD.encode(bytes), format)
+    } else {
+        (b64.to_string(), "png")
+    };
 
     Ok(ImageGenResponse {
-        image_base64: b64,
+        image_base64,
         revised_prompt: revised,
-        format: "png".into(),
+        format: format.into(),
+        seed: None,
     })
 }
 
+fn is_gpt_image(model: &Value) -> bool {
+    model.as_str().is_some_and(|m| m.starts_with("gpt-image"))
+}
+
+/// Request body for /v1/images/generations. The DALL-E models take a fixed
+/// size and `response_format`; gpt-image-1 always answers in base64, rejects
+/// `response_format` and has its own quality / background / format options.
+fn dalle_body(req: &ImageGenRequest) -> Result<Value, String> {
+    let model = req.model.as_deref().filter(|m| !m.trim().is_empty()).unwrap_or("dall-e-3");
+    let size = openai_size(model, req.width, req.height);
+
+    let prompt = prompt_with_negative(req);
+    if !model.starts_with("gpt-image") {
+        return Ok(json!({
+            "model": model,
+            "prompt": prompt,
+            "n": 1,
+            "size": size,
+            "response_format": "b64_json",
+            "quality": req.quality.as_deref().filter(|q| *q == "hd").unwrap_or("standard"),
+        }));
+    }
+
+    let mut body = json!({
+        "model": model,
+        "prompt": prompt,
+        "n": 1,
+        "size": size,
+        "quality": req.quality.as_deref().unwrap_or("auto"),
+    });
+    let format = req.output_format.as_deref();
+    if let Some(format) = format {
+        if !["png", "jpeg", "webp"].contains(&format) {
+            return Err(format!("Unsupported output format for {}: {}", model, format));
+        }
+        body["output_format"] = json!(format);
+    }
+    if let Some(background) = req.background.as_deref() {
+        if background == "transparent" && format == Some("jpeg") {
+            return Err("A transparent background needs PNG or WebP output, not JPEG".into());
+        }
+        body["background"] = json!(background);
+    }
+    Ok(body)
+}
+
+/// Closest size the model accepts. dall-e-3: 1024², 1792×1024, 1024×1792;
+/// dall-e-2: 256², 512², 1024²; gpt-image-1: 1024², 1536×1024, 1024×1536,
+/// or "auto" when the caller gave no size.
+fn openai_size(model: &str, width: Option<u32>, height: Option<u32>) -> String {
+    if model.starts_with("gpt-image") {
+        let (Some(w), Some(h)) = (width, height) else {
+            return "auto".into();
+        };
+        return if w * 5 > h * 6 { "1536x1024" } else if h * 5 > w * 6 { "1024x1536" } else { "1024x1024" }.into();
+    }
+    let w = width.unwrap_or(1024);
+    let h = height.unwrap_or(1024);
+    if model == "dall-e-2" {
+        let side = match w.max(h) {
+            0..=256 => 256,
+            257..=512 => 512,
+            _ => 1024,
+        };
+        return format!("{}x{}", side, side);
+    }
+    if w > h { "1792x1024" } else if h > w { "1024x1792" } else { "1024x1024" }.into()
+}
+
 // ── Stability AI (stable-image-core v2beta) ───────────────────────────────
 
 async fn stability_generate(req: ImageGenRequest) -> Result<ImageGenResponse, String> {
@@ -136,12 +393,30 @@ async fn stability_generate(req: ImageGenRequest) -> Result<ImageGenResponse, St
 
     let client = http_client().map_err(|e| e.to_string())?;
 
-    let form = reqwest::multipart::Form::new()
+    let mut form = reqwest::multipart::Form::new()
         .text("prompt", req.prompt.clone())
         .text("output_format", "png");
+    if let Some(neg) = req.negative_prompt.as_deref().filter(|n| !n.trim().is_empty()) {
+        form = form.text("negative_prompt", neg.to_string());
+    }
+    if let Some(seed) = req.seed.filter(|s| *s >= 0) {
+        form = form.text("seed", seed.to_string());
+    }
+    let endpoint = match init_image(&req)? {
+        Some((bytes, mime)) => {
+            let model = req.model.as_deref().filter(|m| m.starts_with("sd3")).unwrap_or(STABILITY_IMG2IMG_MODEL);
+            form = form
+                .text("mode", "image-to-image")
+                .text("model", model.to_string())
+                .text("strength", format!("{:.2}", init_strength(&req)))
+                .part("image", image_part(bytes, mime)?);
+            "https://api.stability.ai/v2beta/stable-image/generate/sd3"
+        }
+        None => "https://api.stability.ai/v2beta/stable-image/generate/core",
+    };
 
     let resp = client
-        .post("https://api.stability.ai/v2beta/stable-image/generate/core")
+        .post(endpoint)
         .header("Authorization", format!("Bearer {}", key))
         .header("Accept", "image/*")
         .multipart(form)
@@ -162,6 +437,7 @@ async fn stability_generate(req: ImageGenRequest) -> Result<ImageGenResponse, St
         image_base64: b64,
         revised_prompt: None,
         format: "png".into(),
+        seed: None,
     })
 }
 
@@ -182,7 +458,7 @@ async fn together_generate(req: ImageGenRequest) -> Result<ImageGenResponse, Str
     let height = req.height.unwrap_or(1024);
 
     let client = http_client().map_err(|e| e.to_string())?;
-    let body = json!({
+    let mut body = json!({
         "model": model,
         "prompt": req.prompt,
         "width":  width,
@@ -191,18 +467,24 @@ async fn together_generate(req: ImageGenRequest) -> Result<ImageGenResponse, Str
         "n":      1,
         "response_format": "b64_json",
     });
+    if let Some(neg) = req.negative_prompt.as_deref().filter(|n| !n.trim().is_empty()) {
+        body["negative_prompt"] = json!(neg);
+    }
+    if let Some(seed) = req.seed.filter(|s| *s >= 0) {
+        body["seed"] = json!(seed);
+    }
+    // Kontext / Redux / depth FLUX models read the source image from image_url
+    add_init_image(&mut body, &req, "image_url", None)?;
 
-    let resp = client
+    let request = client
         .post("https://api.together.xyz/v1/images/generations")
         .header("Authorization", format!("Bearer {}", key))
         .header("Content-Type", "application/json")
-        .json(&body)
-        .send()
+        .json(&body);
+    let (status, text) = audit::send("together", request)
         .await
         .map_err(|e| format!("Together AI request failed: {}", e))?;
-
-    let status = resp.status();
-    let json: Value = resp.json().await.map_err(|e| e.to_string())?;
+    let json: Value = serde_json::from_str(&text).map_err(|e| e.to_string())?;
 
     if !status.is_success() {
         let json_str = json.to_string();
@@ -221,6 +503,7 @@ async fn together_generate(req: ImageGenRequest) -> Result<ImageGenResponse, Str
         image_base64: b64,
         revised_prompt: None,
         format: "jpeg".into(),
+        seed: None,
     })
 }
 
@@ -241,20 +524,18 @@ async fn openrouter_generate(req: ImageGenRequest) -> Result<ImageGenResponse, S
     let client = http_client().map_err(|e| e.to_string())?;
     let body = json!({
         "model": model,
-        "prompt": req.prompt,
+        "prompt": prompt_with_negative(&req),
     });
 
-    let resp = client
+    let request = client
         .post("https://openrouter.ai/api/v1/images/generations")
         .header("Authorization", format!("Bearer {}", key))
         .header("Content-Type", "application/json")
-        .json(&body)
-        .send()
+        .json(&body);
+    let (status, text) = audit::send("openrouter", request)
         .await
         .map_err(|e| format!("OpenRouter request failed: {}", e))?;
-
-    let status = resp.status();
-    let json: Value = resp.json().await.map_err(|e| e.to_string())?;
+    let json: Value = serde_json::from_str(&text).map_err(|e| e.to_string())?;
 
     if !status.is_success() {
         let json_str = json.to_string();
@@ -283,47 +564,573 @@ async fn openrouter_generate(req: ImageGenRequest) -> Result<ImageGenResponse, S
         image_base64: b64,
         revised_prompt: None,
         format: "png".into(),
+        seed: None,
     })
 }
 
-// ── Local Automatic1111 / Forge WebUI ────────────────────────────────────
+// ── Replicate (any hosted model) ──────────────────────────────────────────
 
-async fn local_sd_generate(req: ImageGenRequest) -> Result<ImageGenResponse, String> {
-    let base_url = req
-        .url
-        .as_deref()
-        .unwrap_or("http://127.0.0.1:7860")
-        .trim_end_matches('/')
-        .to_string();
+async fn replicate_generate(req: ImageGenRequest) -> Result<ImageGenResponse, String> {
+    let key = req.api_key.as_deref().unwrap_or("").trim().to_string();
+    if key.is_empty() {
+        return Err("Replicate API token required".into());
+    }
+    let model = req.model.as_deref().map(str::trim).filter(|m| !m.is_empty()).unwrap_or(REPLICATE_DEFAULT_MODEL);
+    let mut input = replicate_input(&req);
+    add_init_image(&mut input, &req, "image", Some("prompt_strength"))?;
+    let (url, body) = replicate_prediction(model, input)?;
+
+    let client = http_client().map_err(|e| e.to_string())?;
+    let request = client
+        .post(&url)
+        .bearer_auth(&key)
+        .header("Content-Type", "application/json")
+        .json(&body);
+    let (status, text) = audit::send("replicate", request)
+        .await
+        .map_err(|e| format!("Replicate request failed: {}", e))?;
+    let mut prediction: Value = serde_json::from_str(&text).map_err(|e| e.to_string())?;
+    if !status.is_success() {
+        return Err(format!("Replicate {}: {}", status, replicate_error(&prediction)));
+    }
+
+    // Poll until the prediction leaves starting / processing
+    let started = std::time::Instant::now();
+    while matches!(prediction["status"].as_str(), Some("starting" | "processing")) {
+        let poll_url = prediction["urls"]["get"].as_str().ok_or("Replicate: prediction has no status URL")?.to_string();
+        if started.elapsed() > REPLICATE_MAX_WAIT {
+            if let Some(cancel) = prediction["urls"]["cancel"].as_str() {
+                let _ = client.post(cancel).bearer_auth(&key).send().await;
+            }
+            return Err(format!("Replicate: no result after {} s", REPLICATE_MAX_WAIT.as_secs()));
+        }
+        tokio::time::sleep(REPLICATE_POLL).await;
+        let resp = client
+            .get(&poll_url)
+            .bearer_auth(&key)
+            .send()
+            .await
+            .map_err(|e| format!("Replicate poll failed: {}", e))?;
+        let status = resp.status();
+        prediction = resp.json().await.map_err(|e| e.to_string())?;
+        if !status.is_success() {
+            return Err(format!("Replicate {}: {}", status, replicate_error(&prediction)));
+        }
+    }
+
+    match prediction["status"].as_str() {
+        Some("succeeded") => {}
+        Some("canceled") => return Err("Replicate: prediction was canceled".into()),
+        _ => return Err(format!("Replicate: {}", replicate_error(&prediction))),
+    }
+    let output_url = replicate_output_url(&prediction["output"]).ok_or("No image returned by Replicate")?;
+    let bytes = client
+        .get(output_url)
+        .send()
+        .await
+        .map_err(|e| format!("Failed to fetch image from Replicate: {}", e))?
+        .bytes()
+        .await
+        .map_err(|e| e.to_string())?;
+    let (bytes, format) = png_or_jpeg(&bytes)?;
+
+    Ok(ImageGenResponse {
+        image_base64: general_purpose::STANDARD.encode(bytes),
+        revised_prompt: None,
+        format: format.into(),
+        seed: None,
+    })
+}
 
-    let width  = req.width.unwrap_or(512);
-    let height = req.height.unwrap_or(512);
+/// Endpoint and body for a prediction. `owner/name` runs the model's
+/// latest version; `owner/name:version` pins one.
+fn replicate_prediction(model: &str, input: Value) -> Result<(String, Value), String> {
+    let (name, version) = match model.split_once(':') {
+        Some((name, version)) => (name, Some(version)),
+        None => (model, None),
+    };
+    if name.split('/').filter(|p| !p.is_empty()).count() != 2 {
+        return Err(format!("Replicate model must be owner/name[:version], got '{}'", model));
+    }
+    Ok(match version {
+        Some(version) => (
+            "https://api.replicate.com/v1/predictions".to_string(),
+            json!({ "version": version, "input": input }),
+        ),
+        None => (
+            format!("https://api.replicate.com/v1/models/{}/predictions", name),
+            json!({ "input": input }),
+        ),
+    })
+}
+
+/// Inputs under the names most image models use; Replicate drops the ones
+/// a model does not declare.
+fn replicate_input(req: &ImageGenRequest) -> Value {
+    let width  = req.width.unwrap_or(1024);
+    let height = req.height.unwrap_or(1024);
+    let mut input = json!({
+        "prompt":        req.prompt,
+        "width":         width,
+        "height":        height,
+        "aspect_ratio":  closest_aspect_ratio(REPLICATE_ASPECT_RATIOS, width, height),
+        "num_outputs":   1,
+        "output_format": "png",
+    });
+    if let Some(neg) = req.negative_prompt.as_deref().filter(|n| !n.trim().is_empty()) {
+        input["negative_prompt"] = json!(neg);
+    }
+    if let Some(seed) = req.seed.filter(|s| *s >= 0) {
+        input["seed"] = json!(seed);
+    }
+    if let Some(steps) = req.steps {
+        input["num_inference_steps"] = json!(steps);
+    }
+    if let Some(cfg) = req.cfg_scale {
+        input["guidance_scale"] = json!(cfg);
+    }
+    input
+}
+
+/// The aspect ratio in `ratios` closest to width:height.
+fn closest_aspect_ratio(ratios: &[(u32, u32)], width: u32, height: u32) -> String {
+    let target = width.max(1) as f32 / height.max(1) as f32;
+    let (w, h) = ratios
+        .iter()
+        .min_by(|a, b| {
+            let da = (a.0 as f32 / a.1 as f32 - target).abs();
+            let db = (b.0 as f32 / b.1 as f32 - target).abs();
+            da.total_cmp(&db)
+        })
+        .copied()
+        .unwrap_or((1, 1));
+    format!("{}:{}", w, h)
+}
+
+/// Output is a URL or a list of URLs depending on the model.
+fn replicate_output_url(output: &Value) -> Option<&str> {
+    match output {
+        Value::String(url) => Some(url.as_str()),
+        Value::Array(items) => items.iter().find_map(Value::as_str),
+        _ => None,
+    }
+}
+
+fn replicate_error(json: &Value) -> String {
+    json["error"].as_str()
+        .or_else(|| json["detail"].as_str())
+        .or_else(|| json["title"].as_str())
+        .map(String::from)
+        .unwrap_or_else(|| json.to_string())
+}
+
+// ── fal.ai (queue API) ────────────────────────────────────────────────────
+
+/// Submit to the queue, follow the status until the request completes
+/// (emitting `sd-progress` like the local backends), then fetch the result.
+async fn fal_generate(app: &tauri::AppHandle, req: ImageGenRequest) -> Result<ImageGenResponse, String> {
+    let key = req.api_key.as_deref().unwrap_or("").trim().to_string();
+    if key.is_empty() {
+        return Err("fal.ai API key required".into());
+    }
+    let model = req.model.as_deref().map(str::trim).filter(|m| !m.is_empty()).unwrap_or(FAL_DEFAULT_MODEL);
+    let auth = format!("Key {}", key);
+
+    // …/image-to-image endpoints take the source as image_url
+    let mut input = fal_input(&req);
+    add_init_image(&mut input, &req, "image_url", Some("strength"))?;
 
     let client = http_client().map_err(|e| e.to_string())?;
-    let body = json!({
-        "prompt":            req.prompt,
-        "negative_prompt":   "blurry, low quality, distorted, deformed",
-        "steps":             25,
-        "cfg_scale":         7,
-        "width":             width,
-        "height":            height,
-        "sampler_name":      "DPM++ 2M Karras",
-        "save_images":       false,
-        "send_images":       true,
+    let request = client
+        .post(format!("{}/{}", FAL_QUEUE, model))
+        .header("Authorization", &auth)
+        .json(&input);
+    let (status, text) = audit::send("fal", request)
+        .await
+        .map_err(|e| format!("fal.ai request failed: {}", e))?;
+    let submitted: Value = serde_json::from_str(&text).map_err(|e| e.to_string())?;
+    if !status.is_success() {
+        return Err(format!("fal.ai {}: {}", status, fal_error(&submitted)));
+    }
+    let urls = fal_urls(model, &submitted)?;
+
+    let wait = async {
+        match fal_stream_status(app, &client, &auth, &urls.status).await {
+            Ok(true) => Ok(()),
+            _ => fal_poll_status(app, &client, &auth, &urls.status).await,
+        }
+    };
+    match tokio::time::timeout(FAL_MAX_WAIT, wait).await {
+        Ok(result) => result?,
+        Err(_) => {
+            let _ = client.put(&urls.cancel).header("Authorization", &auth).send().await;
+            return Err(format!("fal.ai: no result after {} s", FAL_MAX_WAIT.as_secs()));
+        }
+    }
+
+    let resp = client
+        .get(&urls.response)
+        .header("Authorization", &auth)
+        .send()
+        .await
+        .map_err(|e| format!("fal.ai result request failed: {}", e))?;
+    let status = resp.status();
+    let result: Value = resp.json().await.map_err(|e| e.to_string())?;
+    if !status.is_success() {
+        return Err(format!("fal.ai {}: {}", status, fal_error(&result)));
+    }
+    let image_url = fal_image_url(&result).ok_or("No image returned by fal.ai")?;
+    let bytes = match image_url.strip_prefix("data:") {
+        Some(data_url) => {
+            let b64 = data_url.split_once("base64,").map_or(data_url, |(_, d)| d);
+            general_purpose::STANDARD.decode(b64).map_err(|e| e.to_string())?
+        }
+        None => client
+            .get(image_url)
+            .send()
+            .await
+            .map_err(|e| format!("Failed to fetch image from fal.ai: {}", e))?
+            .bytes()
+            .await
+            .map_err(|e| e.to_string())?
+            .to_vec(),
+    };
+    let (bytes, format) = png_or_jpeg(&bytes)?;
+
+    Ok(ImageGenResponse {
+        image_base64: general_purpose::STANDARD.encode(bytes),
+        revised_prompt: None,
+        format: format.into(),
+        seed: None,
+    })
+}
+
+#[derive(Debug, PartialEq)]
+struct FalUrls {
+    status:   String,
+    response: String,
+    cancel:   String,
+}
+
+/// Queue URLs of a submitted request. fal returns them; when it does not,
+/// they are built from the app id (the first two segments of the endpoint —
+/// "fal-ai/flux/dev" is queued under "fal-ai/flux").
+fn fal_urls(model: &str, submitted: &Value) -> Result<FalUrls, String> {
+    let request_id = submitted["request_id"].as_str().ok_or("fal.ai: no request_id in the queue response")?;
+    let app_id = model.split('/').take(2).collect::<Vec<_>>().join("/");
+    let base = format!("{}/{}/requests/{}", FAL_QUEUE, app_id, request_id);
+    let url = |field: &str, fallback: String| submitted[field].as_str().map(String::from).unwrap_or(fallback);
+    Ok(FalUrls {
+        status:   url("status_url", format!("{}/status", base)),
+        response: url("response_url", base.clone()),
+        cancel:   url("cancel_url", format!("{}/cancel", base)),
+    })
+}
+
+/// Follow the server-sent status stream. Ok(true) once it reports
+/// COMPLETED; Ok(false) when the stream is unavailable or ends early.
+async fn fal_stream_status(app: &tauri::AppHandle, client: &Client, auth: &str, status_url: &str) -> Result<bool, String> {
+    let resp = client
+        .get(format!("{}/stream?logs=1", status_url))
+        .header("Authorization", auth)
+        .send()
+        .await
+        .map_err(|e| e.to_string())?;
+    if !resp.status().is_success() {
+        return Ok(false);
+    }
+    let mut stream = resp.bytes_stream();
+    let mut buf = String::new();
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk.map_err(|e| e.to_string())?;
+        buf.push_str(&String::from_utf8_lossy(&chunk));
+        while let Some(pos) = buf.find('\n') {
+            let line = buf[..pos].trim().to_string();
+            buf = buf[pos + 1..].to_string();
+            let Some(json) = line.strip_prefix("data:").and_then(|d| serde_json::from_str::<Value>(d.trim()).ok()) else {
+                continue;
+            };
+            let (progress, done) = fal_progress(&json);
+            let _ = app.emit_all("sd-progress", progress);
+            if done {
+                return Ok(true);
+            }
+        }
+    }
+    Ok(false)
+}
+
+async fn fal_poll_status(app: &tauri::AppHandle, client: &Client, auth: &str, status_url: &str) -> Result<(), String> {
+    loop {
+        let resp = client
+            .get(format!("{}?logs=1", status_url))
+            .header("Authorization", auth)
+            .send()
+            .await
+            .map_err(|e| format!("fal.ai status poll failed: {}", e))?;
+        let status = resp.status();
+        let json: Value = resp.json().await.map_err(|e| e.to_string())?;
+        if !status.is_success() {
+            return Err(format!("fal.ai {}: {}", status, fal_error(&json)));
+        }
+        let (progress, done) = fal_progress(&json);
+        let _ = app.emit_all("sd-progress", progress);
+        if done {
+            return Ok(());
+        }
+        tokio::time::sleep(FAL_POLL).await;
+    }
+}
+
+/// Queue status → progress event, and whether the request is finished.
+/// Steps come from the newest "n/m" in the logs (the sampler's progress bar).
+fn fal_progress(json: &Value) -> (SdProgress, bool) {
+    let logs: Vec<&str> = json["logs"]
+        .as_array()
+        .map(|l| l.iter().filter_map(|e| e["message"].as_str()).collect())
+        .unwrap_or_default();
+    let (step, total) = logs.iter().rev().find_map(|l| fal_step(l)).unwrap_or((0, 0));
+    let last_log = logs.last().map(|l| l.trim().to_string()).filter(|l| !l.is_empty());
+    match json["status"].as_str().unwrap_or("") {
+        "IN_QUEUE" => {
+            let position = json["queue_position"].as_u64().unwrap_or(0);
+            let line = format!("In fal.ai queue (position {})", position);
+            (SdProgress { phase: "queued".into(), step: 0, total: 0, eta_seconds: None, line }, false)
+        }
+        "COMPLETED" => {
+            let line = last_log.unwrap_or_else(|| "Completed".into());
+            (SdProgress { phase: "saving".into(), step: total, total, eta_seconds: None, line }, true)
+        }
+        _ => {
+            let phase = if total > 0 { "sampling" } else { "loading" };
+            let line = last_log.unwrap_or_else(|| "Running on fal.ai".into());
+            (SdProgress { phase: phase.into(), step, total, eta_seconds: None, line }, false)
+        }
+    }
+}
+
+/// "n/m" from a tqdm-style line (" 50%|█████     | 2/4 [00:01<00:01]").
+fn fal_step(line: &str) -> Option<(u32, u32)> {
+    line.split(|c: char| c.is_whitespace() || c == '|' || c == '[')
+        .filter_map(|w| w.split_once('/'))
+        .find_map(|(a, b)| Some((a.parse::<u32>().ok()?, b.parse::<u32>().ok()?)))
+        .filter(|&(step, total)| total > 0 && step <= total)
+}
+
+/// Arguments FLUX and the other fal image endpoints share; unknown ones
+/// are ignored by the endpoint.
+fn fal_input(req: &ImageGenRequest) -> Value {
+    let mut input = json!({
+        "prompt":                req.prompt,
+        "image_size":            { "width": req.width.unwrap_or(1024), "height": req.height.unwrap_or(1024) },
+        "num_images":            1,
+        "output_format":         "png",
+        "enable_safety_checker": true,
     });
+    if let Some(neg) = req.negative_prompt.as_deref().filter(|n| !n.trim().is_empty()) {
+        input["negative_prompt"] = json!(neg);
+    }
+    if let Some(seed) = req.seed.filter(|s| *s >= 0) {
+        input["seed"] = json!(seed);
+    }
+    if let Some(steps) = req.steps {
+        input["num_inference_steps"] = json!(steps);
+    }
+    if let Some(cfg) = req.cfg_scale {
+        input["guidance_scale"] = json!(cfg);
+    }
+    input
+}
+
+fn fal_image_url(result: &Value) -> Option<&str> {
+    result["images"][0]["url"].as_str().or_else(|| result["image"]["url"].as_str())
+}
+
+/// fal errors are `{"detail": "…"}` or `{"detail": [{"msg": …}, …]}` (validation).
+fn fal_error(json: &Value) -> String {
+    match &json["detail"] {
+        Value::String(detail) => detail.clone(),
+        Value::Array(items) => items.iter()
+            .filter_map(|i| i["msg"].as_str())
+            .collect::<Vec<_>>()
+            .join("; "),
+        _ => json["error"].as_str().map(String::from).unwrap_or_else(|| json.to_string()),
+    }
+}
+
+// ── Google Imagen (Gemini API) ────────────────────────────────────────────
+
+async fn imagen_generate(req: ImageGenRequest) -> Result<ImageGenResponse, String> {
+    let key = req.api_key.as_deref().unwrap_or("").trim().to_string();
+    if key.is_empty() {
+        return Err("Google AI Studio (Gemini) API key required".into());
+    }
+    let model = req.model.as_deref().map(str::trim).filter(|m| !m.is_empty()).unwrap_or(IMAGEN_DEFAULT_MODEL);
+
+    let client = http_client().map_err(|e| e.to_string())?;
+    let request = client
+        .post(format!("{}/{}:predict", IMAGEN_API, model))
+        .header("x-goog-api-key", &key)
+        .header("Content-Type", "application/json")
+        .json(&imagen_body(&req));
+    let (status, text) = audit::send("imagen", request)
+        .await
+        .map_err(|e| format!("Imagen request failed: {}", e))?;
+    let json: Value = serde_json::from_str(&text).map_err(|e| e.to_string())?;
+
+    if !status.is_success() {
+        let err = json["error"]["message"].as_str().map(String::from).unwrap_or_else(|| json.to_string());
+        return Err(format!("Imagen {}: {}", status, err));
+    }
+    let prediction = &json["predictions"][0];
+    let Some(b64) = prediction["bytesBase64Encoded"].as_str() else {
+        // Filtered prompts come back as 200 with no image
+        return Err(match prediction["raiFilteredReason"].as_str() {
+            Some(reason) => format!("Imagen blocked the image: {}", reason),
+            None => "No image returned by Imagen (the prompt may have been filtered)".into(),
+        });
+    };
+    let format = match prediction["mimeType"].as_str() {
+        Some("image/jpeg") => "jpeg",
+        _ => "png",
+    };
+
+    Ok(ImageGenResponse {
+        image_base64: b64.to_string(),
+        revised_prompt: prediction["prompt"].as_str().map(String::from),
+        format: format.into(),
+        seed: None,
+    })
+}
+
+/// `:predict` payload: one sample at the aspect ratio nearest the requested size.
+fn imagen_body(req: &ImageGenRequest) -> Value {
+    let width  = req.width.unwrap_or(1024);
+    let height = req.height.unwrap_or(1024);
+    json!({
+        "instances": [{ "prompt": prompt_with_negative(req) }],
+        "parameters": {
+            "sampleCount":      1,
+            "aspectRatio":      closest_aspect_ratio(IMAGEN_ASPECT_RATIOS, width, height),
+            "personGeneration": "allow_adult",
+        },
+    })
+}
+
+// ── Seed / negative prompt ────────────────────────────────────────────────
+
+/// Providers with a seed parameter; the others are not reproducible.
+fn takes_seed(provider: &str) -> bool {
+    !matches!(provider, "dalle" | "openrouter" | "imagen")
+}
+
+/// Prompt for providers without a negative prompt parameter.
+fn prompt_with_negative(req: &ImageGenRequest) -> String {
+    match req.negative_prompt.as_deref().map(str::trim).filter(|n| !n.is_empty()) {
+        Some(neg) => format!("{}\n\nAvoid: {}", req.prompt, neg),
+        None => req.prompt.clone(),
+    }
+}
+
+// ── Image-to-image input ──────────────────────────────────────────────────
+
+fn has_init_image(req: &ImageGenRequest) -> bool {
+    req.init_image_base64.as_deref().is_some_and(|d| !d.trim().is_empty())
+}
+
+fn supports_init_image(req: &ImageGenRequest) -> bool {
+    match req.provider.as_str() {
+        "stability" | "together" | "replicate" | "fal" => true,
+        "dalle" => req.model.as_deref().is_some_and(|m| m.starts_with("gpt-image")),
+        _ => false,
+    }
+}
+
+/// Clamped to 0–1 and rounded to two decimals, so 0.65f32 is sent as 0.65
+fn init_strength(req: &ImageGenRequest) -> f64 {
+    let strength = f64::from(req.strength.unwrap_or(DEFAULT_STRENGTH).clamp(0.0, 1.0));
+    (strength * 100.0).round() / 100.0
+}
 
+/// The decoded source image and its MIME type, if the request has one.
+fn init_image(req: &ImageGenRequest) -> Result<Option<(Vec<u8>, &'static str)>, String> {
+    let Some(data) = req.init_image_base64.as_deref().filter(|_| has_init_image(req)) else {
+        return Ok(None);
+    };
+    let b64 = data.split_once("base64,").map_or(data, |(_, d)| d);
+    let bytes = general_purpose::STANDARD
+        .decode(b64.trim())
+        .map_err(|e| format!("Invalid init image: {}", e))?;
+    let mime = match image::guess_format(&bytes) {
+        Ok(image::ImageFormat::Png)  => "image/png",
+        Ok(image::ImageFormat::Jpeg) => "image/jpeg",
+        Ok(image::ImageFormat::WebP) => "image/webp",
+        _ => return Err("Init image must be a PNG, JPEG or WebP image".into()),
+    };
+    Ok(Some((bytes, mime)))
+}
+
+/// Sets `image_key` to the source image as a data: URI (and `strength_key`
+/// to the strength) on JSON-input providers.
+fn add_init_image(input: &mut Value, req: &ImageGenRequest, image_key: &str, strength_key: Option<&str>) -> Result<(), String> {
+    if let Some((bytes, mime)) = init_image(req)? {
+        input[image_key] = json!(format!("data:{};base64,{}", mime, general_purpose::STANDARD.encode(bytes)));
+        if let Some(key) = strength_key {
+            input[key] = json!(init_strength(req));
+        }
+    }
+    Ok(())
+}
+
+fn image_part(bytes: Vec<u8>, mime: &str) -> Result<reqwest::multipart::Part, String> {
+    let ext = mime.trim_start_matches("image/");
+    reqwest::multipart::Part::bytes(bytes)
+        .file_name(format!("init.{}", ext))
+        .mime_str(mime)
+        .map_err(|e| e.to_string())
+}
+
+/// Pass PNG and JPEG through; anything else (WebP, …) is re-encoded as PNG.
+fn png_or_jpeg(bytes: &[u8]) -> Result<(Vec<u8>, &'static str), String> {
+    match image::guess_format(bytes).map_err(|e| e.to_string())? {
+        image::ImageFormat::Png  => Ok((bytes.to_vec(), "png")),
+        image::ImageFormat::Jpeg => Ok((bytes.to_vec(), "jpeg")),
+        _ => {
+            let img = image::load_from_memory(bytes).map_err(|e| e.to_string())?;
+            let mut png = Vec::new();
+            img.write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+                .map_err(|e| e.to_string())?;
+            Ok((png, "png"))
+        }
+    }
+}
+
+// ── Local Automatic1111 / Forge WebUI ────────────────────────────────────
+
+async fn local_sd_generate(app: &tauri::AppHandle, req: ImageGenRequest) -> Result<ImageGenResponse, String> {
+    let base_url = webui_base_url(req.url.as_deref());
+
+    let client = http_client().map_err(|e| e.to_string())?;
+    let body = webui_txt2img_body(&req);
+
+    WEBUI_INTERRUPTED.store(false, Ordering::SeqCst);
+    let watcher = tokio::spawn(watch_webui_progress(app.clone(), base_url.clone()));
     let resp = client
         .post(format!("{}/sdapi/v1/txt2img", base_url))
         .json(&body)
         .send()
-        .await
-        .map_err(|e| {
-            format!(
-                "Cannot reach local SD server at {} — {}.\n\
-                 Make sure Automatic1111/Forge is running with --api flag.",
-                base_url, e
-            )
-        })?;
+        .await;
+    watcher.abort();
+    let resp = resp.map_err(|e| {
+        format!(
+            "Cannot reach local SD server at {} — {}.\n\
+             Make sure Automatic1111/Forge is running with --api flag.",
+            base_url, e
+        )
+    })?;
+    if WEBUI_INTERRUPTED.swap(false, Ordering::SeqCst) {
+        return Err(crate::error::CANCELLED.into());
+    }
 
     let status = resp.status();
     let json: Value = resp.json().await.map_err(|e| e.to_string())?;
@@ -346,5 +1153,290 @@ async fn local_sd_generate(req: ImageGenRequest) -> Result<ImageGenResponse, Str
         image_base64: b64,
         revised_prompt: None,
         format: "png".into(),
+        seed: None,
     })
 }
+
+pub(crate) fn webui_base_url(url: Option<&str>) -> String {
+    url.unwrap_or("http://127.0.0.1:7860").trim_end_matches('/').to_string()
+}
+
+/// Emits `sd-progress` and, when the frame changed, `sd-preview` for the
+/// running WebUI job; runs until aborted.
+pub(crate) async fn watch_webui_progress(app: tauri::AppHandle, base_url: String) {
+    let Ok(client) = http::client(http::Timeout::Page) else { return };
+    let url = format!("{}/sdapi/v1/progress?skip_current_image=false", base_url);
+    let mut last_frame = String::new();
+    let mut seq = 0;
+    loop {
+        tokio::time::sleep(WEBUI_POLL).await;
+        let Ok(resp) = client.get(&url).send().await else { continue };
+        let Ok(json) = resp.json::<Value>().await else { continue };
+        let (progress, frame) = webui_progress(&json);
+        let _ = app.emit_all("sd-progress", progress);
+        let Some(frame) = frame.filter(|f| *f != last_frame) else { continue };
+        let Ok(bytes) = general_purpose::STANDARD.decode(&frame) else { continue };
+        let Ok(Ok(jpeg)) = tokio::task::spawn_blocking(move || local_sd::preview_jpeg_bytes(&bytes)).await else { continue };
+        last_frame = frame;
+        seq += 1;
+        let _ = app.emit_all("sd-preview", SdPreview { seq, image_base64: jpeg });
+    }
+}
+
+/// `/sdapi/v1/progress` → progress event + the current frame (base64), if any.
+fn webui_progress(json: &Value) -> (SdProgress, Option<String>) {
+    let state = &json["state"];
+    let step = state["sampling_step"].as_u64().unwrap_or(0) as u32;
+    let total = state["sampling_steps"].as_u64().unwrap_or(0) as u32;
+    let job = state["job"].as_str().unwrap_or("");
+    let phase = if total > 0 {
+        "sampling"
+    } else if job.is_empty() {
+        "loading"
+    } else {
+        "decoding"
+    };
+    let eta = json["eta_relative"].as_f64().filter(|e| *e > 0.0).map(|e| e as f32);
+    let line = json["textinfo"]
+        .as_str()
+        .filter(|t| !t.trim().is_empty())
+        .map(String::from)
+        .unwrap_or_else(|| format!("{:.0}%", json["progress"].as_f64().unwrap_or(0.0) * 100.0));
+    let frame = json["current_image"]
+        .as_str()
+        .filter(|f| !f.is_empty())
+        .map(|f| f.split_once("base64,").map_or(f, |(_, d)| d).to_string());
+    (
+        SdProgress { phase: phase.into(), step, total, eta_seconds: eta, line },
+        frame,
+    )
+}
+
+/// `/sdapi/v1/txt2img` payload. `model` picks the checkpoint through
+/// override_settings; it stays loaded afterwards so back-to-back requests
+/// don't reload it twice each.
+fn webui_txt2img_body(req: &ImageGenRequest) -> Value {
+    let mut body = json!({
+        "prompt":            req.prompt,
+        "negative_prompt":   req.negative_prompt.as_deref().unwrap_or("blurry, low quality, distorted, deformed"),
+        "steps":             req.steps.unwrap_or(25),
+        "cfg_scale":         req.cfg_scale.unwrap_or(7.0),
+        "width":             req.width.unwrap_or(512),
+        "height":            req.height.unwrap_or(512),
+        "sampler_name":      req.sampler.as_deref().filter(|s| !s.is_empty()).map_or("DPM++ 2M Karras".into(), gen_metadata::a1111_sampler_name),
+        "seed":              req.seed.unwrap_or(-1),
+        "save_images":       false,
+        "send_images":       true,
+    });
+    if let Some(model) = req.model.as_deref().map(str::trim).filter(|m| !m.is_empty()) {
+        body["override_settings"] = json!({ "sd_model_checkpoint": model });
+        body["override_settings_restore_afterwards"] = json!(false);
+    }
+    if let Some(hr) = &req.hires {
+        body["enable_hr"] = json!(true);
+        body["hr_scale"] = json!(hr.scale.unwrap_or(2.0));
+        body["hr_upscaler"] = json!(hr.upscaler.as_deref().unwrap_or("Latent"));
+        body["hr_second_pass_steps"] = json!(hr.steps.unwrap_or(0));
+        body["denoising_strength"] = json!(hr.denoising_strength.unwrap_or(0.5));
+    }
+    body
+}
+
+// ── Unit tests ─────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn req(json: Value) -> ImageGenRequest {
+        let mut base = json!({ "prompt": "a lighthouse", "provider": "local_sd" });
+        base.as_object_mut().unwrap().extend(json.as_object().unwrap().clone());
+        serde_json::from_value(base).unwrap()
+    }
+
+    #[test]
+    fn test_webui_progress() {
+        let (p, frame) = webui_progress(&json!({
+            "progress": 0.4, "eta_relative": 3.5, "textinfo": null,
+            "state": { "job": "txt2img", "sampling_step": 8, "sampling_steps": 20 },
+            "current_image": "iVBORw0KGgo=",
+        }));
+        assert_eq!((p.phase.as_str(), p.step, p.total), ("sampling", 8, 20));
+        assert_eq!(p.eta_seconds, Some(3.5));
+        assert_eq!(p.line, "40%");
+        assert_eq!(frame.as_deref(), Some("iVBORw0KGgo="));
+
+        let (idle, frame) = webui_progress(&json!({
+            "progress": 0.0, "eta_relative": 0.0, "textinfo": "Waiting...",
+            "state": { "job": "", "sampling_step": 0, "sampling_steps": 0 },
+            "current_image": null,
+        }));
+        assert_eq!(idle.phase, "loading");
+        assert_eq!(idle.eta_seconds, None);
+        assert_eq!(idle.line, "Waiting...");
+        assert!(frame.is_none());
+    }
+
+    #[test]
+    fn test_webui_body_defaults_and_passthrough() {
+        let body = webui_txt2img_body(&req(json!({})));
+        assert_eq!(body["steps"], 25);
+        assert_eq!(body["seed"], -1);
+        assert_eq!(body["sampler_name"], "DPM++ 2M Karras");
+        assert!(body.get("override_settings").is_none());
+        assert!(body.get("enable_hr").is_none());
+
+        let body = webui_txt2img_body(&req(json!({
+            "steps": 30, "seed": 1234, "sampler": "dpm++2m", "cfg_scale": 5.5,
+            "negative_prompt": "text", "model": "sdxl_base.safetensors [31e35c80fc]",
+            "hires": { "scale": 1.5, "denoising_strength": 0.25 },
+        })));
+        assert_eq!(body["steps"], 30);
+        assert_eq!(body["seed"], 1234);
+        assert_eq!(body["sampler_name"], "DPM++ 2M");
+        assert_eq!(body["cfg_scale"], 5.5);
+        assert_eq!(body["negative_prompt"], "text");
+        assert_eq!(body["override_settings"]["sd_model_checkpoint"], "sdxl_base.safetensors [31e35c80fc]");
+        assert_eq!(body["enable_hr"], true);
+        assert_eq!(body["hr_scale"], 1.5);
+        assert_eq!(body["hr_upscaler"], "Latent");
+        assert_eq!(body["denoising_strength"], 0.25);
+    }
+
+    #[test]
+    fn test_dalle_body_per_model() {
+        let body = dalle_body(&req(json!({ "provider": "dalle", "width": 1280, "height": 720, "quality": "hd" }))).unwrap();
+        assert_eq!(body["model"], "dall-e-3");
+        assert_eq!(body["size"], "1792x1024");
+        assert_eq!(body["quality"], "hd");
+        assert_eq!(body["response_format"], "b64_json");
+        assert!(!is_gpt_image(&body["model"]));
+
+        let body = dalle_body(&req(json!({
+            "provider": "dalle", "model": "gpt-image-1", "width": 720, "height": 1280,
+            "quality": "high", "background": "transparent", "output_format": "webp",
+        }))).unwrap();
+        assert!(is_gpt_image(&body["model"]));
+        assert_eq!(body["size"], "1024x1536");
+        assert_eq!(body["quality"], "high");
+        assert_eq!(body["background"], "transparent");
+        assert_eq!(body["output_format"], "webp");
+        assert!(body.get("response_format").is_none());
+
+        let body = dalle_body(&req(json!({ "provider": "dalle", "model": "gpt-image-1" }))).unwrap();
+        assert_eq!((body["size"].as_str(), body["quality"].as_str()), (Some("auto"), Some("auto")));
+        assert!(dalle_body(&req(json!({
+            "provider": "dalle", "model": "gpt-image-1", "background": "transparent", "output_format": "jpeg",
+        }))).is_err());
+        assert!(dalle_body(&req(json!({ "provider": "dalle", "model": "gpt-image-1", "output_format": "gif" }))).is_err());
+        assert_eq!(openai_size("dall-e-2", Some(500), Some(400)), "512x512");
+        assert_eq!(openai_size("gpt-image-1", Some(1100), Some(1000)), "1024x1024");
+    }
+
+    #[test]
+    fn test_seed_and_negative_mapping() {
+        assert!(takes_seed("stability") && takes_seed("together") && takes_seed("local_sd"));
+        assert!(!takes_seed("dalle") && !takes_seed("openrouter") && !takes_seed("imagen"));
+
+        let body = dalle_body(&req(json!({ "provider": "dalle", "negative_prompt": "text, watermark" }))).unwrap();
+        assert_eq!(body["prompt"], "a lighthouse\n\nAvoid: text, watermark");
+        assert_eq!(prompt_with_negative(&req(json!({ "negative_prompt": "  " }))), "a lighthouse");
+    }
+
+    #[test]
+    fn test_init_image_routing() {
+        let mut png = Vec::new();
+        image::DynamicImage::new_rgb8(2, 2)
+            .write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+            .unwrap();
+        let b64 = general_purpose::STANDARD.encode(&png);
+        let r = req(json!({ "provider": "replicate", "init_image_base64": format!("data:image/png;base64,{}", b64), "strength": 1.4 }));
+        assert!(supports_init_image(&r));
+        let mut input = replicate_input(&r);
+        add_init_image(&mut input, &r, "image", Some("prompt_strength")).unwrap();
+        assert_eq!(input["image"], format!("data:image/png;base64,{}", b64));
+        assert_eq!(input["prompt_strength"], 1.0);
+        assert_eq!(init_strength(&req(json!({}))), 0.65);
+
+        let none = req(json!({ "provider": "together", "init_image_base64": " " }));
+        let mut body = json!({});
+        add_init_image(&mut body, &none, "image_url", None).unwrap();
+        assert!(!has_init_image(&none));
+        assert!(body.get("image_url").is_none());
+
+        assert!(init_image(&req(json!({ "init_image_base64": "aGVsbG8=" }))).is_err());
+        assert!(!supports_init_image(&req(json!({ "provider": "dalle", "model": "dall-e-3" }))));
+        assert!(supports_init_image(&req(json!({ "provider": "dalle", "model": "gpt-image-1" }))));
+        assert!(!supports_init_image(&req(json!({}))));
+    }
+
+    #[test]
+    fn test_replicate_prediction_endpoint() {
+        let (url, body) = replicate_prediction("black-forest-labs/flux-schnell", json!({ "prompt": "x" })).unwrap();
+        assert_eq!(url, "https://api.replicate.com/v1/models/black-forest-labs/flux-schnell/predictions");
+        assert!(body.get("version").is_none());
+
+        let (url, body) = replicate_prediction("stability-ai/sdxl:39ed52f2a78e", json!({})).unwrap();
+        assert_eq!(url, "https://api.replicate.com/v1/predictions");
+        assert_eq!(body["version"], "39ed52f2a78e");
+        assert!(replicate_prediction("sdxl", json!({})).is_err());
+    }
+
+    #[test]
+    fn test_replicate_input_and_output() {
+        let mut r = req(json!({ "provider": "replicate", "width": 1280, "height": 720, "seed": 7, "negative_prompt": " " }));
+        let input = replicate_input(&r);
+        assert_eq!(input["aspect_ratio"], "16:9");
+        assert_eq!(input["seed"], 7);
+        assert!(input.get("negative_prompt").is_none());
+        r.seed = Some(-1);
+        assert!(replicate_input(&r).get("seed").is_none());
+        assert_eq!(closest_aspect_ratio(REPLICATE_ASPECT_RATIOS, 832, 1216), "2:3");
+
+        assert_eq!(replicate_output_url(&json!("https://r.to/a.png")), Some("https://r.to/a.png"));
+        assert_eq!(replicate_output_url(&json!(["https://r.to/b.webp"])), Some("https://r.to/b.webp"));
+        assert_eq!(replicate_output_url(&Value::Null), None);
+    }
+
+    #[test]
+    fn test_fal_queue_urls_and_input() {
+        let urls = fal_urls("fal-ai/flux/dev", &json!({ "request_id": "r1" })).unwrap();
+        assert_eq!(urls.status, "https://queue.fal.run/fal-ai/flux/requests/r1/status");
+        assert_eq!(urls.response, "https://queue.fal.run/fal-ai/flux/requests/r1");
+        let given = fal_urls("fal-ai/flux/dev", &json!({ "request_id": "r1", "status_url": "https://q/s" })).unwrap();
+        assert_eq!(given.status, "https://q/s");
+        assert!(fal_urls("fal-ai/flux/dev", &json!({})).is_err());
+
+        let input = fal_input(&req(json!({ "provider": "fal", "width": 768, "height": 512, "seed": 3 })));
+        assert_eq!(input["image_size"], json!({ "width": 768, "height": 512 }));
+        assert_eq!(input["seed"], 3);
+        assert_eq!(fal_image_url(&json!({ "images": [{ "url": "https://fal.media/a.png" }] })), Some("https://fal.media/a.png"));
+        assert_eq!(fal_error(&json!({ "detail": [{ "msg": "bad size" }, { "msg": "bad seed" }] })), "bad size; bad seed");
+    }
+
+    #[test]
+    fn test_fal_progress_mapping() {
+        let (p, done) = fal_progress(&json!({ "status": "IN_QUEUE", "queue_position": 2 }));
+        assert_eq!((p.phase.as_str(), done), ("queued", false));
+        assert!(p.line.contains("position 2"));
+
+        let (p, done) = fal_progress(&json!({ "status": "IN_PROGRESS", "logs": [
+            { "message": "Loading model" }, { "message": " 50%|█████     | 2/4 [00:01<00:01,  1.9it/s]" },
+        ] }));
+        assert_eq!((p.phase.as_str(), p.step, p.total, done), ("sampling", 2, 4, false));
+
+        let (p, done) = fal_progress(&json!({ "status": "COMPLETED", "logs": [] }));
+        assert_eq!((p.phase.as_str(), done), ("saving", true));
+        assert_eq!(fal_step("[00:01<00:01]"), None);
+    }
+
+    #[test]
+    fn test_imagen_body() {
+        let body = imagen_body(&req(json!({ "provider": "imagen", "width": 1216, "height": 832, "negative_prompt": "fog" })));
+        assert_eq!(body["instances"][0]["prompt"], "a lighthouse\n\nAvoid: fog");
+        assert_eq!(body["parameters"]["aspectRatio"], "16:9");
+        assert_eq!(body["parameters"]["sampleCount"], 1);
+        let portrait = imagen_body(&req(json!({ "provider": "imagen", "width": 768, "height": 1024 })));
+        assert_eq!(portrait["parameters"]["aspectRatio"], "3:4");
+    }
+}
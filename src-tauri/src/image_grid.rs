@@ -0,0 +1,246 @@
+// image_grid.rs — contact sheets for comparing generations
+//
+// Puts several images (different seeds, models or providers) side by side
+// in one PNG, each with a caption strip underneath, so variations can be
+// judged at a glance or shared as a single file. Images are fitted into
+// equal cells; captions use a built-in 5×7 bitmap font (ASCII only, other
+// characters show as '?'), so no font files are needed.
+//
+// Tauri commands exposed:
+//   compose_grid(image_ids, cols, labels) → base64 PNG
+//     image_ids: gallery ids (gallery.rs), capture ids (capture_history.rs),
+//                image file paths, or base64 images
+
+use crate::error::AppError;
+use crate::{capture_history, gallery};
+use base64::{engine::general_purpose, Engine};
+use image::{imageops, DynamicImage, Rgba, RgbaImage};
+use std::path::Path;
+
+const MAX_IMAGES: usize = 64;
+/// Longest cell side; larger sources are scaled down to it
+const MAX_CELL: u32     = 768;
+const GAP: u32          = 8;
+/// Glyph pixels are drawn as SCALE×SCALE blocks
+const SCALE: u32        = 2;
+const GLYPH_W: u32      = 6 * SCALE;  // 5 columns + 1 spacing
+const GLYPH_H: u32      = 7 * SCALE;
+const LABEL_PAD: u32    = 6;
+
+const BACKGROUND: Rgba<u8> = Rgba([24, 24, 27, 255]);
+const LABEL_TEXT: Rgba<u8> = Rgba([228, 228, 231, 255]);
+
+// ── Tauri command ──────────────────────────────────────────────────────────
+
+/// `cols` defaults to a near-square layout; `labels[i]` captions image i.
+#[tauri::command]
+pub async fn compose_grid(
+    app_handle: tauri::AppHandle,
+    image_ids:  Vec<String>,
+    cols:       Option<u32>,
+    labels:     Option<Vec<String>>,
+) -> Result<String, AppError> {
+    if image_ids.is_empty() {
+        return Err("No images to compose".into());
+    }
+    if image_ids.len() > MAX_IMAGES {
+        return Err(format!("At most {} images fit in one grid ({} given)", MAX_IMAGES, image_ids.len()).into());
+    }
+    // Gallery and capture ids are resolved here, where the AppHandle is
+    let sources: Vec<String> = image_ids.iter()
+        .map(|id| gallery::image_path(&app_handle, id)
+            .or_else(|| capture_history::capture_path(&app_handle, id))
+            .map(|p| p.to_string_lossy().to_string())
+            .unwrap_or_else(|| id.clone()))
+        .collect();
+
+    tokio::task::spawn_blocking(move || {
+        let images = sources.iter().enumerate()
+            .map(|(i, s)| load(s).map_err(|e| format!("Image {}: {}", i + 1, e)))
+            .collect::<Result<Vec<_>, _>>()?;
+        let sheet = compose(&images, cols, labels.as_deref().unwrap_or_default());
+        let mut png = Vec::new();
+        DynamicImage::ImageRgba8(sheet)
+            .write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+            .map_err(|e| format!("Cannot encode grid: {}", e))?;
+        Ok(general_purpose::STANDARD.encode(png))
+    })
+    .await?
+    .map_err(AppError::from)
+}
+
+fn load(source: &str) -> Result<DynamicImage, String> {
+    let path = Path::new(source);
+    if source.len() < 4096 && path.is_file() {
+        return image::open(path).map_err(|e| format!("Cannot open {}: {}", source, e));
+    }
+    let b64 = source.split_once("base64,").map_or(source, |(_, d)| d);
+    let bytes = general_purpose::STANDARD.decode(b64.trim())
+        .map_err(|_| "not a gallery or capture id, image file or base64 image".to_string())?;
+    image::load_from_memory(&bytes).map_err(|e| format!("cannot decode image: {}", e))
+}
+
+// ── Layout ─────────────────────────────────────────────────────────────────
+
+/// (cols, rows) for `n` images; `cols` = None picks the smallest square-ish grid.
+fn grid_shape(n: usize, cols: Option<u32>) -> (u32, u32) {
+    let n = n.max(1) as u32;
+    let cols = cols.filter(|&c| c > 0)
+        .unwrap_or_else(|| (n as f64).sqrt().ceil() as u32)
+        .min(n);
+    (cols, n.div_ceil(cols))
+}
+
+fn compose(images: &[DynamicImage], cols: Option<u32>, labels: &[String]) -> RgbaImage {
+    let (cols, rows) = grid_shape(images.len(), cols);
+    let cell_w = images.iter().map(|i| i.width()).max().unwrap_or(1).min(MAX_CELL);
+    let cell_h = images.iter().map(|i| i.height()).max().unwrap_or(1).min(MAX_CELL);
+    let has_labels = labels.iter().any(|l| !l.trim().is_empty());
+    let label_h = if has_labels { GLYPH_H + 2 * LABEL_PAD } else { 0 };
+
+    let width = cols * cell_w + (cols + 1) * GAP;
+    let height = rows * (cell_h + label_h) + (rows + 1) * GAP;
+    let mut sheet = RgbaImage::from_pixel(width, height, BACKGROUND);
+
+    for (i, img) in images.iter().enumerate() {
+        let (col, row) = (i as u32 % cols, i as u32 / cols);
+        let x = GAP + col * (cell_w + GAP);
+        let y = GAP + row * (cell_h + label_h + GAP);
+
+        // Fit into the cell keeping the aspect ratio, centred
+        let fitted = if img.width() > cell_w || img.height() > cell_h {
+            img.resize(cell_w, cell_h, imageops::FilterType::Triangle)
+        } else {
+            img.clone()
+        };
+        let (ox, oy) = ((cell_w - fitted.width()) / 2, (cell_h - fitted.height()) / 2);
+        imageops::overlay(&mut sheet, &fitted.to_rgba8(), (x + ox) as i64, (y + oy) as i64);
+
+        if let Some(label) = labels.get(i).filter(|l| !l.trim().is_empty()) {
+            let text = fit_label(label.trim(), cell_w);
+            let tx = x + (cell_w.saturating_sub(text.chars().count() as u32 * GLYPH_W)) / 2;
+            draw_text(&mut sheet, tx, y + cell_h + LABEL_PAD, &text, LABEL_TEXT);
+        }
+    }
+    sheet
+}
+
+/// Shortens `label` with "..." so it fits `width` pixels.
+fn fit_label(label: &str, width: u32) -> String {
+    let max = (width / GLYPH_W) as usize;
+    if label.chars().count() <= max {
+        return label.to_string();
+    }
+    let keep = max.saturating_sub(3);
+    format!("{}...", label.chars().take(keep).collect::<String>())
+}
+
+// ── Bitmap font ────────────────────────────────────────────────────────────
+
+fn draw_text(img: &mut RgbaImage, x: u32, y: u32, text: &str, color: Rgba<u8>) {
+    for (i, c) in text.chars().enumerate() {
+        let gx = x + i as u32 * GLYPH_W;
+        for (col, bits) in glyph(c).iter().enumerate() {
+            for row in 0..7 {
+                if bits & (1 << row) == 0 {
+                    continue;
+                }
+                for dy in 0..SCALE {
+                    for dx in 0..SCALE {
+                        let (px, py) = (gx + col as u32 * SCALE + dx, y + row * SCALE + dy);
+                        if px < img.width() && py < img.height() {
+                            img.put_pixel(px, py, color);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Column bitmaps (bit 0 = top row) of printable ASCII.
+fn glyph(c: char) -> [u8; 5] {
+    let c = if (' '..='~').contains(&c) { c } else { '?' };
+    FONT_5X7[(c as u8 - b' ') as usize]
+}
+
+#[rustfmt::skip]
+const FONT_5X7: [[u8; 5]; 95] = [
+    [0x00,0x00,0x00,0x00,0x00], [0x00,0x00,0x5F,0x00,0x00], [0x00,0x07,0x00,0x07,0x00], [0x14,0x7F,0x14,0x7F,0x14], // ' ' ! " #
+    [0x24,0x2A,0x7F,0x2A,0x12], [0x23,0x13,0x08,0x64,0x62], [0x36,0x49,0x55,0x22,0x50], [0x00,0x05,0x03,0x00,0x00], // $ % & '
+    [0x00,0x1C,0x22,0x41,0x00], [0x00,0x41,0x22,0x1C,0x00], [0x08,0x2A,0x1C,0x2A,0x08], [0x08,0x08,0x3E,0x08,0x08], // ( ) * +
+    [0x00,0x50,0x30,0x00,0x00], [0x08,0x08,0x08,0x08,0x08], [0x00,0x60,0x60,0x00,0x00], [0x20,0x10,0x08,0x04,0x02], // , - . /
+    [0x3E,0x51,0x49,0x45,0x3E], [0x00,0x42,0x7F,0x40,0x00], [0x42,0x61,0x51,0x49,0x46], [0x21,0x41,0x45,0x4B,0x31], // 0 1 2 3
+    [0x18,0x14,0x12,0x7F,0x10], [0x27,0x45,0x45,0x45,0x39], [0x3C,0x4A,0x49,0x49,0x30], [0x01,0x71,0x09,0x05,0x03], // 4 5 6 7
+    [0x36,0x49,0x49,0x49,0x36], [0x06,0x49,0x49,0x29,0x1E], [0x00,0x36,0x36,0x00,0x00], [0x00,0x56,0x36,0x00,0x00], // 8 9 : ;
+    [0x08,0x14,0x22,0x41,0x00], [0x14,0x14,0x14,0x14,0x14], [0x00,0x41,0x22,0x14,0x08], [0x02,0x01,0x51,0x09,0x06], // < = > ?
+    [0x32,0x49,0x79,0x41,0x3E], [0x7E,0x11,0x11,0x11,0x7E], [0x7F,0x49,0x49,0x49,0x36], [0x3E,0x41,0x41,0x41,0x22], // @ A B C
+    [0x7F,0x41,0x41,0x22,0x1C], [0x7F,0x49,0x49,0x49,0x41], [0x7F,0x09,0x09,0x01,0x01], [0x3E,0x41,0x41,0x51,0x32], // D E F G
+    [0x7F,0x08,0x08,0x08,0x7F], [0x00,0x41,0x7F,0x41,0x00], [0x20,0x40,0x41,0x3F,0x01], [0x7F,0x08,0x14,0x22,0x41], // H I J K
+    [0x7F,0x40,0x40,0x40,0x40], [0x7F,0x02,0x04,0x02,0x7F], [0x7F,0x04,0x08,0x10,0x7F], [0x3E,0x41,0x41,0x41,0x3E], // L M N O
+    [0x7F,0x09,0x09,0x09,0x06], [0x3E,0x41,0x51,0x21,0x5E], [0x7F,0x09,0x19,0x29,0x46], [0x46,0x49,0x49,0x49,0x31], // P Q R S
+    [0x01,0x01,0x7F,0x01,0x01], [0x3F,0x40,0x40,0x40,0x3F], [0x1F,0x20,0x40,0x20,0x1F], [0x7F,0x20,0x18,0x20,0x7F], // T U V W
+    [0x63,0x14,0x08,0x14,0x63], [0x03,0x04,0x78,0x04,0x03], [0x61,0x51,0x49,0x45,0x43], [0x00,0x7F,0x41,0x41,0x00], // X Y Z [
+    [0x02,0x04,0x08,0x10,0x20], [0x00,0x41,0x41,0x7F,0x00], [0x04,0x02,0x01,0x02,0x04], [0x40,0x40,0x40,0x40,0x40], // \ ] ^ _
+    [0x00,0x01,0x02,0x04,0x00], [0x20,0x54,0x54,0x54,0x78], [0x7F,0x48,0x44,0x44,0x38], [0x38,0x44,0x44,0x44,0x20], // ` a b c
+    [0x38,0x44,0x44,0x48,0x7F], [0x38,0x54,0x54,0x54,0x18], [0x08,0x7E,0x09,0x01,0x02], [0x08,0x14,0x54,0x54,0x3C], // d e f g
+    [0x7F,0x08,0x04,0x04,0x78], [0x00,0x44,0x7D,0x40,0x00], [0x20,0x40,0x44,0x3D,0x00], [0x00,0x7F,0x10,0x28,0x44], // h i j k
+    [0x00,0x41,0x7F,0x40,0x00], [0x7C,0x04,0x18,0x04,0x78], [0x7C,0x08,0x04,0x04,0x78], [0x38,0x44,0x44,0x44,0x38], // l m n o
+    [0x7C,0x14,0x14,0x14,0x08], [0x08,0x14,0x14,0x18,0x7C], [0x7C,0x08,0x04,0x04,0x08], [0x48,0x54,0x54,0x54,0x20], // p q r s
+    [0x04,0x3F,0x44,0x40,0x20], [0x3C,0x40,0x40,0x20,0x7C], [0x1C,0x20,0x40,0x20,0x1C], [0x3C,0x40,0x30,0x40,0x3C], // t u v w
+    [0x44,0x28,0x10,0x28,0x44], [0x0C,0x50,0x50,0x50,0x3C], [0x44,0x64,0x54,0x4C,0x44], [0x00,0x08,0x36,0x41,0x00], // x y z {
+    [0x00,0x00,0x7F,0x00,0x00], [0x00,0x41,0x36,0x08,0x00], [0x02,0x01,0x02,0x04,0x02],                             // | } ~
+];
+
+// ── Unit tests ─────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn solid(w: u32, h: u32, v: u8) -> DynamicImage {
+        DynamicImage::ImageRgba8(RgbaImage::from_pixel(w, h, Rgba([v, v, v, 255])))
+    }
+
+    #[test]
+    fn test_grid_shape() {
+        assert_eq!(grid_shape(1, None), (1, 1));
+        assert_eq!(grid_shape(4, None), (2, 2));
+        assert_eq!(grid_shape(5, None), (3, 2));
+        assert_eq!(grid_shape(5, Some(5)), (5, 1));
+        // More columns than images collapse; 0 means auto
+        assert_eq!(grid_shape(3, Some(8)), (3, 1));
+        assert_eq!(grid_shape(3, Some(0)), (2, 2));
+    }
+
+    #[test]
+    fn test_compose_sizes_cells_and_labels() {
+        let images = [solid(64, 32, 255), solid(32, 64, 128), solid(64, 64, 0)];
+        let plain = compose(&images, Some(3), &[]);
+        assert_eq!((plain.width(), plain.height()), (3 * 64 + 4 * GAP, 64 + 2 * GAP));
+        // The 64×32 image is centred vertically in its 64×64 cell
+        assert_eq!(*plain.get_pixel(GAP, GAP), BACKGROUND);
+        assert_eq!(*plain.get_pixel(GAP, GAP + 16), Rgba([255, 255, 255, 255]));
+
+        let labelled = compose(&images, Some(3), &["seed 1".into(), String::new(), "seed 3".into()]);
+        assert_eq!(labelled.height(), plain.height() + GLYPH_H + 2 * LABEL_PAD);
+        let strip = (GAP..GAP + 64).flat_map(|x| (GAP + 64..labelled.height()).map(move |y| (x, y)));
+        assert!(strip.into_iter().any(|(x, y)| *labelled.get_pixel(x, y) == LABEL_TEXT));
+    }
+
+    #[test]
+    fn test_large_images_are_scaled_into_cells() {
+        let sheet = compose(&[solid(2000, 1000, 200)], None, &[]);
+        assert_eq!((sheet.width(), sheet.height()), (MAX_CELL + 2 * GAP, MAX_CELL + 2 * GAP));
+    }
+
+    #[test]
+    fn test_font_and_label_fitting() {
+        assert_eq!(glyph('A'), [0x7E, 0x11, 0x11, 0x11, 0x7E]);
+        assert_eq!(glyph('~'), FONT_5X7[94]);
+        assert_eq!(glyph('é'), glyph('?'));
+        assert_eq!(fit_label("short", 512), "short");
+        let fitted = fit_label("a very long model name that will not fit", 10 * GLYPH_W);
+        assert_eq!(fitted, "a very ...");
+    }
+}
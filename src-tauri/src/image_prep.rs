@@ -0,0 +1,196 @@
+// image_prep.rs — shrink images before they are sent to vision APIs
+//
+// A 4K screenshot is a multi-MB PNG; providers downscale it server-side
+// anyway (Claude to ~1568 px, OpenAI "high" detail to 2048 px) but bill the
+// upload and tokens first. Images are resized to a max dimension and
+// re-encoded as JPEG or WebP before any provider call.
+//
+// The `image` WebP encoder is lossless only. Asking for WebP with a quality
+// below 100 means asking for lossy compression, so such images go out as
+// JPEG at that quality; quality 100 gives lossless WebP.
+//
+// Tauri commands exposed:
+//   get_image_upload_options → ImageUploadOptions
+//   set_image_upload_options (options)
+
+use crate::error::AppError;
+use base64::{engine::general_purpose, Engine};
+use image::codecs::jpeg::JpegEncoder;
+use image::codecs::webp::WebPEncoder;
+use image::imageops::FilterType;
+use image::{ColorType, DynamicImage};
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+
+static OPTIONS: Mutex<Option<ImageUploadOptions>> = Mutex::new(None);
+
+// ── Types ──────────────────────────────────────────────────────────────────
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ImageUploadOptions {
+    /// Longest side in pixels; 0 disables resizing
+    pub max_dimension: u32,
+    /// "jpeg" | "webp" | "png" | "original"
+    pub format:        String,
+    /// JPEG quality, 1–100; "webp" below 100 is sent as JPEG (see header)
+    pub quality:       u8,
+}
+
+impl Default for ImageUploadOptions {
+    fn default() -> Self {
+        Self { max_dimension: 1568, format: "jpeg".into(), quality: 85 }
+    }
+}
+
+fn options() -> ImageUploadOptions {
+    OPTIONS.lock().ok().and_then(|o| o.clone()).unwrap_or_default()
+}
+
+#[tauri::command]
+pub fn get_image_upload_options() -> ImageUploadOptions {
+    options()
+}
+
+#[tauri::command]
+pub fn set_image_upload_options(options: ImageUploadOptions) -> Result<(), AppError> {
+    if !matches!(options.format.as_str(), "jpeg" | "webp" | "png" | "original") {
+        return Err(format!("Unknown image format '{}'", options.format).into());
+    }
+    let options = ImageUploadOptions { quality: options.quality.clamp(1, 100), ..options };
+    *OPTIONS.lock().map_err(|e| e.to_string())? = Some(options);
+    Ok(())
+}
+
+// ── Preparation ────────────────────────────────────────────────────────────
+
+/// Downscale / re-encode `image_base64` with the current options. Runs on
+/// the blocking pool; on any decode error the original is sent unchanged.
+pub(crate) async fn prepare(image_base64: Option<String>) -> Option<String> {
+    let b64 = image_base64?;
+    let opts = options();
+    let result = tokio::task::spawn_blocking(move || {
+        let out = shrink(&b64, &opts);
+        out.unwrap_or_else(|e| {
+            log::warn!("image_prep: sending original image: {}", e);
+            b64
+        })
+    }).await;
+    result.ok()
+}
+
+fn shrink(b64: &str, opts: &ImageUploadOptions) -> Result<String, String> {
+    if opts.format == "original" && opts.max_dimension == 0 {
+        return Ok(b64.to_string());
+    }
+    let bytes = general_purpose::STANDARD.decode(b64.trim()).map_err(|e| e.to_string())?;
+    let mut img = image::load_from_memory(&bytes).map_err(|e| e.to_string())?;
+
+    let resized = opts.max_dimension > 0 && img.width().max(img.height()) > opts.max_dimension;
+    if resized {
+        img = img.resize(opts.max_dimension, opts.max_dimension, FilterType::CatmullRom);
+    }
+
+    let encoded = match opts.format.as_str() {
+        "jpeg" => encode_jpeg(&img, opts.quality)?,
+        "webp" if opts.quality < 100 => encode_jpeg(&img, opts.quality)?,
+        "webp" => encode_webp(&img)?,
+        "png"  => encode_png(&img)?,
+        _ if resized => match mime_type(b64) {
+            "image/jpeg" => encode_jpeg(&img, opts.quality)?,
+            "image/webp" => encode_webp(&img)?,
+            _            => encode_png(&img)?,
+        },
+        _ => return Ok(b64.to_string()),
+    };
+
+    // Re-encoding an already small image can make it bigger
+    if !resized && encoded.len() >= bytes.len() {
+        return Ok(b64.to_string());
+    }
+    log::info!("image_prep: {} KB → {} KB", bytes.len() / 1024, encoded.len() / 1024);
+    Ok(general_purpose::STANDARD.encode(encoded))
+}
+
+fn encode_jpeg(img: &DynamicImage, quality: u8) -> Result<Vec<u8>, String> {
+    // JPEG has no alpha channel
+    let rgb = img.to_rgb8();
+    let mut out = Vec::new();
+    JpegEncoder::new_with_quality(&mut out, quality)
+        .encode(&rgb, rgb.width(), rgb.height(), ColorType::Rgb8)
+        .map_err(|e| e.to_string())?;
+    Ok(out)
+}
+
+fn encode_webp(img: &DynamicImage) -> Result<Vec<u8>, String> {
+    let rgba = img.to_rgba8();
+    let mut out = Vec::new();
+    WebPEncoder::new_lossless(&mut out)
+        .encode(&rgba, rgba.width(), rgba.height(), ColorType::Rgba8)
+        .map_err(|e| e.to_string())?;
+    Ok(out)
+}
+
+fn encode_png(img: &DynamicImage) -> Result<Vec<u8>, String> {
+    let mut out = std::io::Cursor::new(Vec::new());
+    img.write_to(&mut out, image::ImageFormat::Png).map_err(|e| e.to_string())?;
+    Ok(out.into_inner())
+}
+
+/// MIME type of a base64 image, sniffed from its magic bytes.
+pub(crate) fn mime_type(b64: &str) -> &'static str {
+    let b64 = b64.trim_start();
+    if b64.starts_with("/9j/") {
+        "image/jpeg"
+    } else if b64.starts_with("UklGR") {
+        "image/webp"
+    } else if b64.starts_with("R0lGOD") {
+        "image/gif"
+    } else {
+        "image/png"
+    }
+}
+
+// ── Unit tests ─────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgba, RgbaImage};
+
+    fn png_b64(w: u32, h: u32) -> String {
+        let img = DynamicImage::ImageRgba8(RgbaImage::from_fn(w, h, |x, y| {
+            Rgba([(x % 256) as u8, (y % 256) as u8, ((x * y) % 256) as u8, 255])
+        }));
+        general_purpose::STANDARD.encode(encode_png(&img).unwrap())
+    }
+
+    #[test]
+    fn test_large_image_downscaled_to_jpeg() {
+        let opts = ImageUploadOptions { max_dimension: 400, ..Default::default() };
+        let out = shrink(&png_b64(1600, 900), &opts).unwrap();
+        assert_eq!(mime_type(&out), "image/jpeg");
+        let img = image::load_from_memory(&general_purpose::STANDARD.decode(out).unwrap()).unwrap();
+        assert_eq!((img.width(), img.height()), (400, 225));
+    }
+
+    #[test]
+    fn test_lossy_webp_falls_back_to_jpeg() {
+        let webp = |quality| ImageUploadOptions { max_dimension: 400, format: "webp".into(), quality };
+        assert_eq!(mime_type(&shrink(&png_b64(1600, 900), &webp(80)).unwrap()), "image/jpeg");
+        assert_eq!(mime_type(&shrink(&png_b64(1600, 900), &webp(100)).unwrap()), "image/webp");
+    }
+
+    #[test]
+    fn test_original_format_kept_when_within_limits() {
+        let opts = ImageUploadOptions { format: "original".into(), ..Default::default() };
+        let b64 = png_b64(64, 64);
+        assert_eq!(shrink(&b64, &opts).unwrap(), b64);
+    }
+
+    #[test]
+    fn test_mime_type() {
+        assert_eq!(mime_type(&png_b64(2, 2)), "image/png");
+        assert_eq!(mime_type("/9j/4AAQSkZJRg"), "image/jpeg");
+        assert_eq!(mime_type("UklGRiQAAABXRUJQ"), "image/webp");
+    }
+}
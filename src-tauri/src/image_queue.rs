@@ -0,0 +1,305 @@
+// image_queue.rs — one-at-a-time queue for GPU image jobs
+//
+// The GPU can only run one diffusion job at a time: a second sd process
+// either fails with out-of-memory or slows both to a crawl. run_local_sd,
+// upscale_image, run_local_video and generate_image with the local WebUI
+// provider take a slot here before they start and give it back when they
+// finish (the slot is released on drop, so errors and cancelled futures
+// free it too). Cloud providers don't touch the GPU and are not queued.
+//
+// Tauri commands exposed:
+//   list_image_queue     → Vec<QueueEntry> (running job first)
+//   reorder_image_queue  (id, position) — move a waiting job, 0 = next
+//   cancel_queued_image  (id) — drop a waiting job; its command returns an error
+//
+// Events emitted:
+//   image-queue-changed   → Vec<QueueEntry> on every change
+//   image-queue-position  → QueueEntry for each waiting job whose position changed
+
+use crate::error::AppError;
+use serde::Serialize;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use tauri::Manager;
+use tokio::sync::oneshot;
+
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+static QUEUE: Mutex<Queue> = Mutex::new(Queue { running: None, waiting: Vec::new() });
+
+struct Queue {
+    running: Option<Job>,
+    waiting: Vec<Waiter>,
+}
+
+#[derive(Clone)]
+struct Job {
+    id:        String,
+    kind:      String,
+    label:     String,
+    queued_at: u64,
+}
+
+struct Waiter {
+    job:   Job,
+    start: oneshot::Sender<()>,
+}
+
+// ── Types ──────────────────────────────────────────────────────────────────
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct QueueEntry {
+    pub id:          String,
+    /// "sd" | "upscale" | "webui" | "video"
+    pub kind:        String,
+    pub label:       String,
+    /// 0 = running, 1 = next, …
+    pub position:    usize,
+    pub running:     bool,
+    pub queued_secs: u64,
+}
+
+/// The GPU slot; the next waiting job starts when this is dropped.
+pub(crate) struct Slot {
+    id:  String,
+    app: tauri::AppHandle,
+}
+
+impl Drop for Slot {
+    fn drop(&mut self) {
+        release(&self.app, &self.id);
+    }
+}
+
+/// Removes a job that stopped waiting (future dropped) from the queue.
+struct Pending<'a> {
+    id:    &'a str,
+    app:   &'a tauri::AppHandle,
+    armed: bool,
+}
+
+impl Drop for Pending<'_> {
+    fn drop(&mut self) {
+        if !self.armed {
+            return;
+        }
+        let (before, promoted) = {
+            let Ok(mut q) = QUEUE.lock() else { return };
+            let before = positions(&q);
+            q.waiting.retain(|w| w.job.id != self.id);
+            (before, q.running.as_ref().is_some_and(|j| j.id == self.id))
+        };
+        if promoted {
+            // Started between the send and our drop: pass the slot on
+            release(self.app, self.id);
+        } else {
+            emit_changes(self.app, &before);
+        }
+    }
+}
+
+fn unix_now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+// ── Queue ──────────────────────────────────────────────────────────────────
+
+/// Wait for the GPU. `id` is the caller's queue id (the frontend passes one
+/// so it can match events and cancel); one is generated when None.
+pub(crate) async fn acquire(app: &tauri::AppHandle, id: Option<String>, kind: &str, label: &str) -> Result<Slot, String> {
+    let job = Job {
+        id:        id.filter(|s| !s.is_empty())
+                     .unwrap_or_else(|| format!("{}-{}", kind, NEXT_ID.fetch_add(1, Ordering::Relaxed))),
+        kind:      kind.to_string(),
+        label:     label.chars().take(120).collect(),
+        queued_at: unix_now(),
+    };
+    let id = job.id.clone();
+    let (before, started) = {
+        let mut q = QUEUE.lock().map_err(|e| e.to_string())?;
+        if q.running.as_ref().is_some_and(|j| j.id == id) || q.waiting.iter().any(|w| w.job.id == id) {
+            return Err(format!("Image job '{}' is already queued", id));
+        }
+        let before = positions(&q);
+        if q.running.is_none() {
+            q.running = Some(job);
+            (before, None)
+        } else {
+            let (tx, rx) = oneshot::channel();
+            q.waiting.push(Waiter { job, start: tx });
+            (before, Some(rx))
+        }
+    };
+    emit_changes(app, &before);
+
+    let Some(rx) = started else {
+        return Ok(Slot { id, app: app.clone() });
+    };
+    log::info!("image_queue: {} waiting for the GPU", id);
+    let mut pending = Pending { id: &id, app, armed: true };
+    let result = rx.await;
+    pending.armed = false;
+    drop(pending);
+    match result {
+        Ok(()) => Ok(Slot { id, app: app.clone() }),
+        Err(_) => Err(crate::error::CANCELLED.into()),
+    }
+}
+
+/// Free the slot held by `id` and start the next waiter that is still alive.
+fn release(app: &tauri::AppHandle, id: &str) {
+    let before = {
+        let Ok(mut q) = QUEUE.lock() else { return };
+        if !q.running.as_ref().is_some_and(|j| j.id == id) {
+            return;
+        }
+        let before = positions(&q);
+        q.running = None;
+        while !q.waiting.is_empty() {
+            let w = q.waiting.remove(0);
+            if w.start.send(()).is_ok() {
+                q.running = Some(w.job);
+                break;
+            }
+        }
+        before
+    };
+    emit_changes(app, &before);
+}
+
+fn snapshot(q: &Queue) -> Vec<QueueEntry> {
+    let now = unix_now();
+    let entry = |job: &Job, position: usize| QueueEntry {
+        id:          job.id.clone(),
+        kind:        job.kind.clone(),
+        label:       job.label.clone(),
+        position,
+        running:     position == 0,
+        queued_secs: now.saturating_sub(job.queued_at),
+    };
+    q.running.iter().map(|j| entry(j, 0))
+        .chain(q.waiting.iter().enumerate().map(|(i, w)| entry(&w.job, i + 1)))
+        .collect()
+}
+
+/// (id, position) of every job, to tell which positions an edit changed.
+fn positions(q: &Queue) -> Vec<(String, usize)> {
+    snapshot(q).into_iter().map(|e| (e.id, e.position)).collect()
+}
+
+/// Emit the full queue, plus a position event for each waiting job that
+/// moved relative to `before`.
+fn emit_changes(app: &tauri::AppHandle, before: &[(String, usize)]) {
+    let Ok(entries) = QUEUE.lock().map(|q| snapshot(&q)) else { return };
+    for e in moved(before, &entries) {
+        let _ = app.emit_all("image-queue-position", e);
+    }
+    let _ = app.emit_all("image-queue-changed", &entries);
+}
+
+fn moved<'a>(before: &[(String, usize)], after: &'a [QueueEntry]) -> Vec<&'a QueueEntry> {
+    after.iter()
+        .filter(|e| !e.running)
+        .filter(|e| !before.iter().any(|(id, pos)| *id == e.id && *pos == e.position))
+        .collect()
+}
+
+/// Move waiting job `id` to `position` among the waiters (0 = next).
+fn move_waiter(q: &mut Queue, id: &str, position: usize) -> Result<(), String> {
+    let from = q.waiting.iter().position(|w| w.job.id == id)
+        .ok_or_else(|| format!("No queued image job '{}'", id))?;
+    let w = q.waiting.remove(from);
+    let to = position.min(q.waiting.len());
+    q.waiting.insert(to, w);
+    Ok(())
+}
+
+// ── Tauri commands ─────────────────────────────────────────────────────────
+
+#[tauri::command]
+pub fn list_image_queue() -> Result<Vec<QueueEntry>, AppError> {
+    Ok(snapshot(&*QUEUE.lock().map_err(|e| e.to_string())?))
+}
+
+/// Move a waiting job; the running job can't be moved.
+#[tauri::command]
+pub fn reorder_image_queue(app_handle: tauri::AppHandle, id: String, position: usize) -> Result<(), AppError> {
+    let before = {
+        let mut q = QUEUE.lock().map_err(|e| e.to_string())?;
+        let before = positions(&q);
+        move_waiter(&mut q, &id, position)?;
+        before
+    };
+    emit_changes(&app_handle, &before);
+    Ok(())
+}
+
+/// Remove a waiting job. Running jobs are stopped with kill_job instead.
+#[tauri::command]
+pub fn cancel_queued_image(app_handle: tauri::AppHandle, id: String) -> Result<(), AppError> {
+    let before = {
+        let mut q = QUEUE.lock().map_err(|e| e.to_string())?;
+        let before = positions(&q);
+        let len = q.waiting.len();
+        // Dropping the waiter's sender wakes its command with an error
+        q.waiting.retain(|w| w.job.id != id);
+        if q.waiting.len() == len {
+            return Err(format!("No queued image job '{}'", id).into());
+        }
+        before
+    };
+    emit_changes(&app_handle, &before);
+    Ok(())
+}
+
+// ── Unit tests ─────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn job(id: &str) -> Job {
+        Job { id: id.into(), kind: "sd".into(), label: id.into(), queued_at: unix_now() }
+    }
+
+    fn queue(running: &str, waiting: &[&str]) -> Queue {
+        Queue {
+            running: Some(job(running)),
+            waiting: waiting.iter().map(|id| Waiter { job: job(id), start: oneshot::channel().0 }).collect(),
+        }
+    }
+
+    fn ids(q: &Queue) -> Vec<String> {
+        snapshot(q).into_iter().map(|e| e.id).collect()
+    }
+
+    #[test]
+    fn test_snapshot_positions() {
+        let q = queue("a", &["b", "c"]);
+        let entries = snapshot(&q);
+        assert_eq!(entries.iter().map(|e| e.position).collect::<Vec<_>>(), [0, 1, 2]);
+        assert!(entries[0].running && !entries[1].running);
+    }
+
+    #[test]
+    fn test_move_waiter() {
+        let mut q = queue("a", &["b", "c", "d"]);
+        move_waiter(&mut q, "d", 0).unwrap();
+        assert_eq!(ids(&q), ["a", "d", "b", "c"]);
+        move_waiter(&mut q, "d", 99).unwrap();
+        assert_eq!(ids(&q), ["a", "b", "c", "d"]);
+        assert!(move_waiter(&mut q, "a", 0).is_err()); // running
+    }
+
+    #[test]
+    fn test_moved_reports_only_changed_waiters() {
+        let mut q = queue("a", &["b", "c", "d"]);
+        let before = positions(&q);
+        move_waiter(&mut q, "c", 0).unwrap();
+        let after = snapshot(&q);
+        let changed: Vec<&str> = moved(&before, &after).iter().map(|e| e.id.as_str()).collect();
+        assert_eq!(changed, ["c", "b"]);
+    }
+}
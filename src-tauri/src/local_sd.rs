@@ -1,21 +1,38 @@
 // local_sd.rs — Runs stable-diffusion.cpp locally, no WebUI required.
 //
 // Downloads the sd binary from the GitHub releases of leejet/stable-diffusion.cpp
-// into the Tauri app-data directory on first use.
+// into the Tauri app-data directory on first use (via downloads.rs).
 //
 // Tauri commands exposed:
 //   get_sd_binary_status  → { installed: bool, path: string }
-//   download_sd_binary    → streams "sd-download-progress" events, returns final path
+//   download_sd_binary    → streams "sd-download-progress" events for its phases (bytes
+//                           arrive as "download-progress", component "sd-binary"),
+//                           returns final path
 //   list_local_sd_models  → lists .safetensors / .ckpt / .gguf files in a directory
-//   run_local_sd          → spawns the sd process, streams "sd-progress" events, returns base64 PNG
-
+//   run_local_sd          → waits for the GPU (image_queue.rs), spawns the sd process,
+//                           streams structured "sd-progress" events (and "sd-preview"
+//                           frames with a taesd_path), returns base64 PNG
+//                           (inpaints when init_image_base64 + mask_base64 are given,
+//                           ControlNet-guided when control_net_path + control_image_base64 are;
+//                           FLUX / SD3 get --diffusion-model and text-encoder flags)
+//   upscale_image         → ESRGAN upscale of an existing image, returns base64 PNG
+
+use crate::error::AppError;
+use crate::{disk, downloads, gallery, gen_metadata, gpu_devices, image_queue, sd_inspect, sd_server, style_presets, watchdog};
 use base64::{engine::general_purpose, Engine};
-use futures_util::StreamExt;
 use serde::{Deserialize, Serialize};
 use std::path::{Path, PathBuf};
 use std::process::Stdio;
 use tokio::process::Command;
 
+/// Free space wanted next to an output image (a 2048² PNG is ~12 MB; sd also
+/// writes intermediates there)
+const OUTPUT_HEADROOM: u64 = 64 * 1024 * 1024;
+/// How often the preview file is checked for a new frame
+const PREVIEW_POLL: std::time::Duration = std::time::Duration::from_millis(400);
+/// Longest side of the `sd-preview` JPEG
+const PREVIEW_MAX: u32 = 256;
+
 // ── Types ──────────────────────────────────────────────────────────────────
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -31,30 +48,119 @@ pub struct LocalSdRequest {
     /// e.g. "euler_a", "dpm2", "lms" — maps to --sampling-method
     pub sampler:          Option<String>,
     pub vae_path:         Option<String>,
+    /// Separate text encoders for FLUX / SD3 models shipped without them
+    /// (--clip_l, --clip_g, --t5xxl). FLUX needs clip_l + t5xxl; SD3 all three.
+    #[serde(default)]
+    pub clip_l_path:      Option<String>,
+    #[serde(default)]
+    pub clip_g_path:      Option<String>,
+    #[serde(default)]
+    pub t5xxl_path:       Option<String>,
+    /// Pass the model as --diffusion-model (UNet / DiT weights only) instead
+    /// of -m. None = decide from the file header (sd_inspect.rs).
+    #[serde(default)]
+    pub diffusion_model:  Option<bool>,
     /// Number of CPU threads (0 = auto-detect)
     pub threads:          Option<u32>,
     /// Extra raw CLI flags passed verbatim (advanced users)
     pub extra_args:       Option<String>,
-    /// GPU backend: "cpu" | "cuda" | "vulkan" (default: "cpu")
+    /// GPU backend: "cpu" | "cuda" | "vulkan" | "rocm" | "metal" (default: "cpu")
     pub gpu_backend:      Option<String>,
+    /// Device index for the backend, from get_gpu_info (gpu_devices.rs);
+    /// None = whatever the driver lists first
+    #[serde(default)]
+    pub gpu_device:       Option<u32>,
     /// Pass --vae-on-cpu to the sd binary (offloads VAE decode to RAM, prevents VRAM OOM)
     pub vae_on_cpu:       Option<bool>,
     /// Pass --vae-tiling to the sd binary (tiles the VAE decode, greatly reduces VRAM usage)
     pub vae_tiling:       Option<bool>,
     /// Pass --offload-to-cpu: places model weights in RAM, loads to VRAM on-demand (prevents OOM during model load)
     pub offload_to_cpu:   Option<bool>,
+    /// Source image for inpainting (base64 PNG/JPEG); resized to width×height
+    pub init_image_base64: Option<String>,
+    /// Inpainting mask (base64): white = repaint, black = keep.
+    /// Needs `init_image_base64` and an inpainting checkpoint.
+    pub mask_base64:      Option<String>,
+    /// How far the masked region may move from the source, 0–1 (--strength)
+    pub strength:         Option<f32>,
+    /// ControlNet model (.safetensors / .gguf) — --control-net
+    pub control_net_path: Option<String>,
+    /// Guide image (pose / edge / depth map, or a screenshot with
+    /// `control_canny`); resized to width×height
+    pub control_image_base64: Option<String>,
+    /// ControlNet weight, 0–2 (default 0.9)
+    pub control_strength: Option<f32>,
+    /// Run sd.cpp's Canny preprocessor on the control image first, so a
+    /// plain screenshot can guide an edge (canny) ControlNet
+    pub control_canny:    Option<bool>,
+    /// ESRGAN model applied to the result (--upscale-model), e.g.
+    /// RealESRGAN_x4plus_anime_6B.pth
+    pub upscale_model_path: Option<String>,
+    /// Times the upscaler runs (--upscale-repeats, default 1)
+    pub upscale_repeats:  Option<u32>,
+    /// Caller-chosen id for image-queue events and cancel_queued_image
+    #[serde(default)]
+    pub queue_id:         Option<String>,
+    /// Tiny AutoEncoder (taesd_*.safetensors) for live previews: sd writes
+    /// a cheap decode of the latents every `preview_interval` steps and
+    /// each one is sent as an `sd-preview` event. The final image still
+    /// uses the full VAE. CLI only — the persistent server doesn't preview.
+    #[serde(default)]
+    pub taesd_path:       Option<String>,
+    /// Steps between previews (default 2)
+    #[serde(default)]
+    pub preview_interval: Option<u32>,
+    /// Style preset id (style_presets.rs); its prompt words, negative prompt,
+    /// sampler, CFG and size take precedence over the request's
+    #[serde(default)]
+    pub style:            Option<String>,
+    /// Generate through the warm sd-server (sd_server.rs) instead of a fresh
+    /// CLI process; requests the server can't handle still use the CLI
+    #[serde(default)]
+    pub server_mode:      Option<bool>,
+}
+
+/// `sd-preview` payload: the latest intermediate frame.
+#[derive(Debug, Serialize, Clone)]
+pub struct SdPreview {
+    /// 1 for the first frame of a generation, counting up
+    pub seq:          u32,
+    /// JPEG, at most PREVIEW_MAX px on the long side
+    pub image_base64: String,
+}
+
+/// `sd-progress` payload, updated from each stderr line.
+#[derive(Debug, Serialize, Clone, Default, PartialEq)]
+pub struct SdProgress {
+    /// "loading" | "encoding" | "sampling" | "decoding" | "upscaling" | "saving"
+    /// ("queued" while a fal.ai request waits for a worker)
+    /// (empty until the first recognised line)
+    pub phase:       String,
+    /// Current / total steps of the phase's progress bar (0 / 0 when none)
+    pub step:        u32,
+    pub total:       u32,
+    /// From the bar's s/it or it/s rate
+    pub eta_seconds: Option<f32>,
+    /// The raw stderr line, for the log view
+    pub line:        String,
 }
 
 // ── Helpers ────────────────────────────────────────────────────────────────
 
-/// Returns the binary filename for the requested backend.
-/// Each backend gets its own file so switching backends forces a fresh download.
-fn sd_bin_name_for(backend: &str) -> String {
-    let suffix = match backend {
+/// File-name suffix of the build for a backend.
+fn backend_suffix(backend: &str) -> &'static str {
+    match backend {
         "cuda"   => "cuda",
         "vulkan" => "vulkan",
+        "rocm"   => "rocm",
+        "metal"  => "metal",
         _        => "cpu",
-    };
+    }
+}
+
+/// Returns the binary filename for the requested backend.
+fn sd_bin_name_for(backend: &str) -> String {
+    let suffix = backend_suffix(backend);
     if cfg!(target_os = "windows") {
         format!("sd-{}.exe", suffix)
     } else {
@@ -62,26 +168,41 @@ fn sd_bin_name_for(backend: &str) -> String {
     }
 }
 
-fn get_sd_data_dir(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+/// The HTTP server from the same release archive, renamed per backend so
+/// builds for different GPUs don't overwrite each other's `sd-server`.
+fn sd_server_name_for(backend: &str) -> String {
+    let suffix = backend_suffix(backend);
+    if cfg!(target_os = "windows") {
+        format!("sd-server-{}.exe", suffix)
+    } else {
+        format!("sd-server-{}", suffix)
+    }
+}
+
+pub(crate) fn get_sd_data_dir(app: &tauri::AppHandle) -> Result<PathBuf, String> {
     app.path_resolver()
         .app_data_dir()
         .ok_or_else(|| "Cannot resolve app data directory".to_string())
         .map(|p| p.join("sd_runtime"))
 }
 
-fn get_sd_bin_path_for(app: &tauri::AppHandle, backend: &str) -> Result<PathBuf, String> {
+pub(crate) fn get_sd_bin_path_for(app: &tauri::AppHandle, backend: &str) -> Result<PathBuf, String> {
     Ok(get_sd_data_dir(app)?.join(sd_bin_name_for(backend)))
 }
 
+pub(crate) fn get_sd_server_path_for(app: &tauri::AppHandle, backend: &str) -> Result<PathBuf, String> {
+    Ok(get_sd_data_dir(app)?.join(sd_server_name_for(backend)))
+}
+
 // ── Tauri commands ─────────────────────────────────────────────────────────
 
 /// Returns { installed: bool, path: string }
-/// `backend`: "cpu" | "cuda" | "vulkan" — checks the binary for that specific backend.
+/// `backend`: "cpu" | "cuda" | "vulkan" | "rocm" | "metal" — checks the binary for that specific backend.
 #[tauri::command]
 pub fn get_sd_binary_status(
     app_handle:   tauri::AppHandle,
     backend_pref: Option<String>,
-) -> Result<serde_json::Value, String> {
+) -> Result<serde_json::Value, AppError> {
     let backend = backend_pref.as_deref().unwrap_or("cpu").to_lowercase();
     let p = get_sd_bin_path_for(&app_handle, &backend)?;
     let installed = p.exists();
@@ -107,15 +228,18 @@ pub fn get_sd_binary_status(
 
 /// Downloads the sd binary from GitHub releases.
 /// Emits `sd-download-progress` → { status: string, progress: number 0-100 }
-/// `backend_pref`: "cpu" (default) | "cuda" | "vulkan"
+/// `backend_pref`: "cpu" (default) | "cuda" | "vulkan" | "rocm" | "metal"
 #[tauri::command]
 pub async fn download_sd_binary(
     window:       tauri::Window,
     app_handle:   tauri::AppHandle,
     backend_pref: Option<String>,
-) -> Result<String, String> {
+) -> Result<String, AppError> {
     let backend = backend_pref.as_deref().unwrap_or("cpu").to_lowercase();
     println!("[SD] download_sd_binary called — requested backend: {}", backend);
+    if backend == "metal" && !cfg!(target_os = "macos") {
+        return Err("The Metal backend is only available on macOS.".into());
+    }
     let data_dir = get_sd_data_dir(&app_handle)?;
     std::fs::create_dir_all(&data_dir).map_err(|e| e.to_string())?;
 
@@ -135,15 +259,6 @@ pub async fn download_sd_binary(
         .timeout(std::time::Duration::from_secs(30))
         .build()
         .map_err(|e| e.to_string())?;
-    // Download client: long connect timeout, NO total-request timeout.
-    // CUDA archives can be 200–500 MB; a global timeout will abort mid-stream.
-    let dl_client = reqwest::Client::builder()
-        .user_agent("ai-assistant/0.1")
-        .connect_timeout(std::time::Duration::from_secs(30))
-        .tcp_keepalive(std::time::Duration::from_secs(30))
-        .build()
-        .map_err(|e| e.to_string())?;
-
     let release: serde_json::Value = api_client
         .get("https://api.github.com/repos/leejet/stable-diffusion.cpp/releases/latest")
         .send().await
@@ -157,6 +272,12 @@ pub async fn download_sd_binary(
     //       The Vulkan build works on NVIDIA, AMD, and Intel GPUs via the Vulkan API.
     //       Users should select "Vulkan" on Linux for GPU acceleration.
     //       On Windows, a native CUDA binary is available.
+    //       ROCm (HIP) builds are matched by "rocm" or "hip" in the asset name;
+    //       when a release has none for this platform, the Vulkan build is used
+    //       — it also runs on AMD GPUs.
+    //       Metal is macOS-only. Releases that don't name a Metal asset ship
+    //       the Apple Silicon build with Metal compiled in, so the plain
+    //       macOS asset is used then.
     let platform_keys: &[&str] = if cfg!(target_os = "windows") {
         &["win-avx2", "win-avx", "win-x64", "windows"]
     } else if cfg!(target_os = "macos") {
@@ -184,14 +305,16 @@ pub async fn download_sd_binary(
         backend.clone()
     };
 
-    let asset = platform_keys.iter().find_map(|kw| {
+    let find_asset = |backend: &str| platform_keys.iter().find_map(|kw| {
         assets.iter().find(|a| {
             let name = a["name"].as_str().unwrap_or("").to_lowercase();
             if !(name.ends_with(".zip") || name.ends_with(".tar.gz")) { return false; }
             if !name.contains(kw) { return false; }
-            match effective_backend.as_str() {
+            match backend {
                 "cuda"   => name.contains("cuda"),
                 "vulkan" => name.contains("vulkan"),
+                "rocm"   => name.contains("rocm") || name.contains("hip"),
+                "metal"  => name.contains("metal"),
                 _ => {
                     // cpu: skip any GPU build
                     !name.contains("cuda") && !name.contains("metal")
@@ -200,6 +323,16 @@ pub async fn download_sd_binary(
                 }
             }
         })
+    });
+    let asset = find_asset(&effective_backend).or_else(|| {
+        if effective_backend != "rocm" { return None; }
+        println!("[SD] INFO: No ROCm build for this platform in the release — using the Vulkan build, \
+            which also runs on AMD GPUs.");
+        find_asset("vulkan")
+    }).or_else(|| {
+        if effective_backend != "metal" { return None; }
+        println!("[SD] INFO: No separate Metal asset — using the macOS build (Metal is built in on Apple Silicon).");
+        find_asset("cpu")
     }).or_else(|| {
         // Fallback: any platform match regardless of backend keyword
         println!("[SD] Exact backend match not found — falling back to any platform asset");
@@ -227,40 +360,33 @@ pub async fn download_sd_binary(
         &format!("Downloading {} ({:.1} MB)…", name, size as f64 / 1_048_576.0),
         5);
 
-    // ── Streaming download with real progress ──────────────────────────
-    let response = dl_client.get(url).send().await
-        .map_err(|e| format!("Download failed: {}", e))?;
+    // The archive and its extracted contents sit side by side until cleanup
+    disk::ensure_space(&data_dir, size.saturating_mul(3), &format!("{} and its extracted files", name))?;
 
-    let total_bytes = response.content_length().unwrap_or(size);
-    let mut downloaded: u64 = 0;
-    let mut bytes_buf: Vec<u8> =
-        Vec::with_capacity(total_bytes.min(512 * 1024 * 1024) as usize);
-
-    let mut stream = response.bytes_stream();
-    while let Some(chunk_result) = stream.next().await {
-        let chunk = chunk_result.map_err(|e| format!("Download stream error: {}", e))?;
-        bytes_buf.extend_from_slice(&chunk);
-        downloaded += chunk.len() as u64;
-
-        if total_bytes > 0 {
-            // Map downloaded bytes to the 5 %–78 % window
-            let pct = (downloaded * 73 / total_bytes) as u8 + 5;
-            emit_progress(
-                &window,
-                &format!(
-                    "Downloading… {:.1} / {:.1} MB",
-                    downloaded as f64 / 1_048_576.0,
-                    total_bytes as f64 / 1_048_576.0,
-                ),
-                pct.min(78),
-            );
-        }
+    // ── Download (streamed to disk, resumable, per-byte progress on the
+    //    shared "download-progress" event) ──────────────────────────────────
+    // Newer releases publish the asset digest as "sha256:<hex>"; fetch
+    // verifies it and deletes the file on mismatch.
+    let sha256 = asset_sha256(asset);
+    if sha256.is_none() {
+        println!("[SD] WARNING: release publishes no sha256 digest for {}; only its size is checked", name);
+    }
+    let archive = downloads::fetch(&app_handle, &downloads::client()?, downloads::Download {
+        url:       url.to_string(),
+        dest:      data_dir.join(name),
+        component: "sd-binary",
+        bearer:    None,
+        sha256,
+        segments:  downloads::DEFAULT_SEGMENTS,
+    }).await?;
+    let actual_size = std::fs::metadata(&archive).map(|m| m.len()).unwrap_or(0);
+    if size > 0 && actual_size != size {
+        let _ = std::fs::remove_file(&archive);
+        return Err(format!(
+            "Size mismatch for {} (release lists {} bytes, got {}) — the file was removed, download it again",
+            name, size, actual_size
+        ).into());
     }
-
-    emit_progress(&window, "Saving archive…", 79);
-
-    let archive = data_dir.join(name);
-    std::fs::write(&archive, &bytes_buf).map_err(|e| e.to_string())?;
 
     emit_progress(&window, "Extracting archive…", 80);
 
@@ -281,6 +407,12 @@ pub async fn download_sd_binary(
         }
     }
 
+    // Keep this build's HTTP server too (persistent mode, sd_server.rs)
+    let server_name = if cfg!(target_os = "windows") { "sd-server.exe" } else { "sd-server" };
+    if let Some(found) = find_binary(&data_dir, server_name) {
+        let _ = std::fs::rename(&found, data_dir.join(sd_server_name_for(&backend)));
+    }
+
     // Make all extracted executable files executable on Unix
     #[cfg(unix)]
     {
@@ -326,7 +458,7 @@ pub async fn download_sd_binary(
                                 if meta.permissions().mode() & 0o111 != 0 {
                                     let ep_name = ep.file_name().and_then(|n| n.to_str()).unwrap_or("");
                                     // Skip files already named for another backend, and skip sd-server
-                                    let already_named = ep_name.starts_with("sd-cli-") || ep_name.starts_with("sd-cpu") || ep_name.starts_with("sd-cuda") || ep_name.starts_with("sd-vulkan");
+                                    let already_named = ep_name.starts_with("sd-cli-") || ep_name.starts_with("sd-cpu") || ep_name.starts_with("sd-cuda") || ep_name.starts_with("sd-vulkan") || ep_name.starts_with("sd-rocm");
                                     let is_server = ep_name == "sd-server" || ep_name.starts_with("sd-server-");
                                     if !already_named && !is_server && (ep_name.starts_with("sd") || ep_name == "stable-diffusion") {
                                         std::fs::rename(&ep, &bin_path).map_err(|e| e.to_string())?;
@@ -340,7 +472,7 @@ pub async fn download_sd_binary(
                     {
                         if ep.is_file() && ep.extension().and_then(|e| e.to_str()) == Some("exe") {
                             let ep_name = ep.file_name().and_then(|n| n.to_str()).unwrap_or("");
-                            let already_named = ep_name.starts_with("sd-cpu") || ep_name.starts_with("sd-cuda") || ep_name.starts_with("sd-vulkan");
+                            let already_named = ep_name.starts_with("sd-cpu") || ep_name.starts_with("sd-cuda") || ep_name.starts_with("sd-vulkan") || ep_name.starts_with("sd-rocm");
                             let is_server = ep_name.starts_with("sd-server");
                             if !already_named && !is_server && ep_name.starts_with("sd") {
                                 std::fs::rename(&ep, &bin_path).map_err(|e| e.to_string())?;
@@ -352,7 +484,7 @@ pub async fn download_sd_binary(
             }
         }
         if !bin_path.exists() {
-            return Err(format!("Binary not found after extraction. Expected: {:?}", bin_path));
+            return Err(format!("Binary not found after extraction. Expected: {:?}", bin_path).into());
         }
     }
 
@@ -448,12 +580,115 @@ pub fn check_cuda_libs() -> serde_json::Value {
     }
 }
 
+/// Directories under the runtime dir that contain .dylib files.
+#[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+fn dylib_dirs(root: &Path) -> Vec<PathBuf> {
+    let mut dirs: Vec<PathBuf> = walkdir::WalkDir::new(root).max_depth(4).into_iter().flatten()
+        .filter(|e| e.path().extension().is_some_and(|x| x == "dylib"))
+        .filter_map(|e| e.path().parent().map(Path::to_path_buf))
+        .collect();
+    dirs.sort();
+    dirs.dedup();
+    dirs
+}
+
+/// Directories that may hold the HIP runtime (libamdhip64.so), most specific
+/// first: ROCM_PATH / HIP_PATH, /opt/rocm, versioned /opt/rocm-x.y, then
+/// wherever ldconfig knows it from.
+#[cfg(target_os = "linux")]
+fn rocm_lib_dirs() -> Vec<String> {
+    let mut dirs = Vec::new();
+    for env_var in &["ROCM_PATH", "HIP_PATH"] {
+        if let Ok(v) = std::env::var(env_var) {
+            dirs.push(format!("{}/lib", v));
+            dirs.push(format!("{}/lib64", v));
+        }
+    }
+    dirs.push("/opt/rocm/lib".to_string());
+    if let Ok(entries) = std::fs::read_dir("/opt") {
+        let mut versioned: Vec<String> = entries.flatten()
+            .map(|e| e.file_name().to_string_lossy().to_string())
+            .filter(|n| n.starts_with("rocm-"))
+            .map(|n| format!("/opt/{}/lib", n))
+            .collect();
+        versioned.sort_by(|a, b| b.cmp(a)); // newest version first
+        dirs.extend(versioned);
+    }
+    if let Ok(out) = std::process::Command::new("ldconfig").arg("-p").output() {
+        let text = String::from_utf8_lossy(&out.stdout);
+        for line in text.lines().filter(|l| l.contains("libamdhip64.so")) {
+            if let Some(dir) = line.split_once("=>").and_then(|(_, p)| std::path::Path::new(p.trim()).parent()) {
+                dirs.push(dir.to_string_lossy().to_string());
+            }
+        }
+    }
+    dirs.push("/usr/lib64".to_string());
+    dirs.push("/usr/lib/x86_64-linux-gnu".to_string());
+    dirs
+}
+
+#[cfg(target_os = "linux")]
+fn dir_has_lib(dir: &str, prefix: &str) -> bool {
+    std::fs::read_dir(dir)
+        .map(|rd| rd.flatten().any(|e| e.file_name().to_string_lossy().starts_with(prefix)))
+        .unwrap_or(false)
+}
+
+/// Checks whether the ROCm / HIP runtime is usable for the ROCm build.
+/// Returns { found: bool, path: string | null, gpu: string | null, suggestion: string | null }.
+/// `gpu` is the first gfx target rocminfo reports (e.g. "gfx1030").
+#[tauri::command]
+pub fn check_rocm_libs() -> serde_json::Value {
+    #[cfg(not(target_os = "linux"))]
+    {
+        return serde_json::json!({ "found": false, "path": null, "gpu": null,
+            "suggestion": "ROCm library check only supported on Linux. On Windows install the AMD HIP SDK and make sure HIP_PATH is set." });
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        let path = rocm_lib_dirs().into_iter().find(|d| dir_has_lib(d, "libamdhip64.so"));
+        let gpu = std::process::Command::new("rocminfo").output().ok()
+            .and_then(|o| {
+                String::from_utf8_lossy(&o.stdout)
+                    .split_whitespace()
+                    .find(|w| w.starts_with("gfx"))
+                    .map(str::to_string)
+            });
+        // The HIP runtime talks to the GPU through /dev/kfd; users outside the
+        // render/video groups get "no devices found" instead of a clear error
+        let kfd_ok = std::fs::OpenOptions::new().read(true).write(true).open("/dev/kfd").is_ok();
+
+        let suggestion = if path.is_none() {
+            Some("HIP runtime (libamdhip64.so) not found. On Ubuntu install ROCm with amdgpu-install --usecase=rocm;\n\
+                  on Fedora/Nobara: sudo dnf install rocm-hip rocm-runtime;\n\
+                  on Arch: sudo pacman -S rocm-hip-runtime.\n\
+                  Or set ROCM_PATH if ROCm is installed in a non-standard path.")
+        } else if !kfd_ok {
+            Some("/dev/kfd is not accessible — add yourself to the render and video groups:\n  \
+                  sudo usermod -aG render,video $USER\nthen log out and back in.")
+        } else if gpu.is_none() {
+            Some("rocminfo reports no AMD GPU. Consumer cards that ROCm does not list officially may need \
+                  HSA_OVERRIDE_GFX_VERSION (e.g. 10.3.0 for RDNA2) set in the environment.")
+        } else {
+            None
+        };
+
+        return serde_json::json!({
+            "found": path.is_some() && kfd_ok,
+            "path": path,
+            "gpu": gpu,
+            "suggestion": suggestion,
+        });
+    }
+}
+
 /// Deletes the installed binary for the given backend so it can be re-downloaded.
 #[tauri::command]
 pub fn delete_sd_binary(
     app_handle:   tauri::AppHandle,
     backend_pref: Option<String>,
-) -> Result<(), String> {
+) -> Result<(), AppError> {
     let backend = backend_pref.as_deref().unwrap_or("cpu").to_lowercase();
     let bin_path = get_sd_bin_path_for(&app_handle, &backend)?;
     if bin_path.exists() {
@@ -465,7 +700,7 @@ pub fn delete_sd_binary(
 
 /// Lists all .safetensors / .ckpt / .gguf / .bin model files in `models_dir`.
 #[tauri::command]
-pub fn list_local_sd_models(models_dir: String) -> Result<Vec<String>, String> {
+pub fn list_local_sd_models(models_dir: String) -> Result<Vec<String>, AppError> {
     let dir = Path::new(&models_dir);
     if !dir.exists() { return Ok(vec![]); }
 
@@ -476,14 +711,28 @@ pub fn list_local_sd_models(models_dir: String) -> Result<Vec<String>, String> {
 }
 
 /// Runs stable-diffusion.cpp inference.
-/// Emits `sd-progress` → { line: string } for each stderr line.
-/// Returns base64-encoded PNG.
+/// Emits `sd-progress` → SdProgress { phase, step, total, eta_seconds, line } per stderr line.
+/// Returns base64-encoded PNG carrying its settings in an A1111 "parameters"
+/// chunk, which is also kept in the gallery (gallery.rs).
 #[tauri::command]
 pub async fn run_local_sd(
     window:     tauri::Window,
     app_handle: tauri::AppHandle,
-    req:        LocalSdRequest,
-) -> Result<String, String> {
+    mut req:    LocalSdRequest,
+) -> Result<String, AppError> {
+    if let Some(style) = style_presets::resolve(&app_handle, req.style.as_deref())? {
+        req.prompt = style.prompt(&req.prompt);
+        req.negative_prompt = style.negative(req.negative_prompt.as_deref());
+        req.sampler = style.sampler.or(req.sampler);
+        req.cfg_scale = style.cfg_scale.or(req.cfg_scale);
+        req.width = style.width.or(req.width);
+        req.height = style.height.or(req.height);
+    }
+    // A random seed is picked here rather than by sd so the gallery records
+    // the one that was actually used
+    if !req.seed.is_some_and(|s| s >= 0) {
+        req.seed = Some(random_seed());
+    }
     let gpu_backend = req.gpu_backend.as_deref().unwrap_or("cpu").to_lowercase();
     let bin = get_sd_bin_path_for(&app_handle, &gpu_backend)?;
     if !bin.exists() {
@@ -492,10 +741,10 @@ pub async fn run_local_sd(
              Go to Settings → Image Generation → Native SD, select the {} backend, \
              and click \"Download binary\".",
             gpu_backend.to_uppercase(), gpu_backend.to_uppercase()
-        ));
+        ).into());
     }
-    let t_start = std::time::Instant::now();
-
+    // One generation on the GPU at a time; held until this command returns
+    let _slot = image_queue::acquire(&app_handle, req.queue_id.clone(), "sd", &req.prompt).await?;
     println!("╔══════════════════════════════════════════════════════════════");
     println!("║ [SD] run_local_sd — NEW GENERATION REQUEST");
     println!("║  binary    : {}", bin.display());
@@ -510,6 +759,28 @@ pub async fn run_local_sd(
     println!("║  prompt    : {}", &req.prompt.chars().take(200).collect::<String>());
     println!("╚══════════════════════════════════════════════════════════════");
 
+    // ── Model family ──────────────────────────────────────────────────────
+    // FLUX / SD3 files often hold only the diffusion weights and load their
+    // text encoders separately; the header says which (unreadable .ckpt
+    // files are treated as classic all-in-one checkpoints).
+    let model_path = PathBuf::from(&req.model_path);
+    let info = tokio::task::spawn_blocking(move || sd_inspect::inspect(&model_path))
+        .await
+        .map_err(|e| e.to_string())?
+        .ok();
+    let family = model_args(&req, info.as_ref())?;
+    println!("[SD] Model family: {} ({})", family.base_model, family.args.join(" "));
+
+    if req.server_mode.unwrap_or(false) {
+        // The server loads a plain -m checkpoint; FLUX and split-encoder
+        // models go through the CLI with their own flags
+        if sd_server::supports(&req) && family.args.len() == 2 && family.args[0] == "-m" && family.base_model != "flux" {
+            let result = sd_server::txt2img(&app_handle, &gpu_backend, &req).await;
+            return finish(&app_handle, &req, &family, result);
+        }
+        println!("[SD] Persistent server handles plain txt2img only — using the CLI for this request");
+    }
+
     // Temp output path
     let out_path = std::env::temp_dir().join(format!(
         "sd_out_{}.png",
@@ -520,11 +791,11 @@ pub async fn run_local_sd(
     ));
 
     let mut cmd = Command::new(&bin);
-    cmd.arg("-m").arg(&req.model_path)
+    cmd.args(&family.args)
        .arg("-p").arg(&req.prompt)
        .arg("-o").arg(&out_path)
        .arg("--steps").arg(req.steps.unwrap_or(20).to_string())
-       .arg("--cfg-scale").arg(format!("{:.1}", req.cfg_scale.unwrap_or(7.0)))
+       .arg("--cfg-scale").arg(format!("{:.1}", family.cfg_scale))
        .arg("-W").arg(req.width.unwrap_or(512).to_string())
        .arg("-H").arg(req.height.unwrap_or(512).to_string());
 
@@ -533,12 +804,13 @@ pub async fn run_local_sd(
         cmd.arg("-t").arg(threads.to_string());
     }
     if let Some(neg) = &req.negative_prompt {
-        if !neg.trim().is_empty() { cmd.arg("-n").arg(neg); }
+        // Ignored at CFG 1 (FLUX) — sd.cpp would only warn about it
+        if !neg.trim().is_empty() && family.cfg_scale > 1.0 { cmd.arg("-n").arg(neg); }
     }
     if let Some(seed) = req.seed {
         cmd.arg("-s").arg(seed.to_string());
     }
-    if let Some(sampler) = &req.sampler {
+    if let Some(sampler) = req.sampler.as_deref().or(family.sampler) {
         cmd.arg("--sampling-method").arg(sampler);
     }
     if let Some(vae) = &req.vae_path {
@@ -568,10 +840,91 @@ pub async fn run_local_sd(
         "vulkan" => {
             println!("[SD] GPU backend: Vulkan (baked into binary, no extra flags needed)");
         }
+        "rocm" => {
+            println!("[SD] GPU backend: ROCm/HIP (baked into binary, no extra flags needed)");
+        }
+        "metal" => {
+            println!("[SD] GPU backend: Metal (baked into binary, no extra flags needed)");
+        }
         _ => {
             println!("[SD] GPU backend: CPU");
         }
     }
+    gpu_devices::apply_gpu_device(&mut cmd, &gpu_backend, req.gpu_device);
+
+    // ── Inpainting / ControlNet inputs ────────────────────────────────────
+    // Images go to temp files that live until this function returns; sd.cpp
+    // switches to img2img on its own when --init-img is set.
+    let (w, h) = (req.width.unwrap_or(512), req.height.unwrap_or(512));
+    let mut temp_files = TempFiles(Vec::new());
+    match (&req.init_image_base64, &req.mask_base64) {
+        (Some(init), Some(mask)) => {
+            if is_inpaint_model(Path::new(&req.model_path), info.as_ref()) == Some(false) {
+                return Err(format!(
+                    "{} is not an inpainting checkpoint. Inpainting needs a model trained \
+                     for it (e.g. sd-v1-5-inpainting, sd_xl_inpainting).",
+                    Path::new(&req.model_path).file_name().map(|n| n.to_string_lossy()).unwrap_or_default()
+                ).into());
+            }
+            let init_path = temp_files.add(write_temp_image(init, "init", Some((w, h)), false)?);
+            let mask_path = temp_files.add(write_temp_image(mask, "mask", Some((w, h)), true)?);
+            let strength = req.strength.unwrap_or(0.75).clamp(0.0, 1.0);
+            cmd.arg("--init-img").arg(&init_path)
+               .arg("--mask").arg(&mask_path)
+               .arg("--strength").arg(format!("{:.2}", strength));
+            println!("[SD] Inpainting: strength {:.2}, mask {}", strength, mask_path.display());
+        }
+        (None, Some(_)) => return Err("Inpainting needs the source image (init_image_base64) as well as the mask.".into()),
+        _ => {}
+    }
+
+    match (req.control_net_path.as_deref().filter(|p| !p.trim().is_empty()), &req.control_image_base64) {
+        (Some(control_net), Some(control_image)) => {
+            if !Path::new(control_net).exists() {
+                return Err(format!("ControlNet model not found: {}", control_net).into());
+            }
+            let image_path = temp_files.add(write_temp_image(control_image, "control", Some((w, h)), false)?);
+            let strength = req.control_strength.unwrap_or(0.9).clamp(0.0, 2.0);
+            cmd.arg("--control-net").arg(control_net)
+               .arg("--control-image").arg(&image_path)
+               .arg("--control-strength").arg(format!("{:.2}", strength));
+            if req.control_canny.unwrap_or(false) {
+                cmd.arg("--canny");
+            }
+            println!("[SD] ControlNet: {} (strength {:.2}, canny {})",
+                control_net, strength, req.control_canny.unwrap_or(false));
+        }
+        (Some(_), None) => return Err("ControlNet needs a control image (control_image_base64).".into()),
+        (None, Some(_)) => return Err("A control image was given but no ControlNet model (control_net_path).".into()),
+        (None, None)    => {}
+    }
+
+    if let Some(upscaler) = req.upscale_model_path.as_deref().filter(|p| !p.trim().is_empty()) {
+        if !Path::new(upscaler).exists() {
+            return Err(format!("Upscale model not found: {}", upscaler).into());
+        }
+        let repeats = req.upscale_repeats.unwrap_or(1).clamp(1, 4);
+        cmd.arg("--upscale-model").arg(upscaler)
+           .arg("--upscale-repeats").arg(repeats.to_string());
+        println!("[SD] Upscaler: {} ×{}", upscaler, repeats);
+    }
+
+    // ── Live preview ──────────────────────────────────────────────────────
+    let mut preview_path = None;
+    if let Some(taesd) = req.taesd_path.as_deref().filter(|p| !p.trim().is_empty()) {
+        if !Path::new(taesd).exists() {
+            return Err(format!("TAESD model not found: {}", taesd).into());
+        }
+        let path = temp_files.add(out_path.with_extension("preview.png"));
+        let every = req.preview_interval.unwrap_or(2).max(1);
+        cmd.arg("--taesd").arg(taesd)
+           .arg("--taesd-preview-only")
+           .arg("--preview").arg("tae")
+           .arg("--preview-path").arg(&path)
+           .arg("--preview-interval").arg(every.to_string());
+        println!("[SD] Live preview: every {} step(s) via {}", every, taesd);
+        preview_path = Some(path);
+    }
 
     if let Some(extra) = &req.extra_args {
         for part in extra.split_whitespace() {
@@ -580,6 +933,91 @@ pub async fn run_local_sd(
         println!("[SD] Extra args: {}", extra);
     }
 
+    let watcher = preview_path.map(|p| tokio::spawn(watch_preview(window.clone(), p)));
+    let result = run_sd_process(&window, &app_handle, cmd, &bin, &gpu_backend, &out_path, &req.prompt).await;
+    if let Some(w) = watcher {
+        w.abort();
+    }
+    finish(&app_handle, &req, &family, result).map_err(AppError::from)
+}
+
+/// Upscale an existing image with an ESRGAN model (sd's `upscale` mode), so a
+/// fast low-res generation can be enlarged without re-running diffusion.
+/// Emits `sd-progress` like run_local_sd. Returns base64 PNG.
+#[tauri::command]
+pub async fn upscale_image(
+    window:       tauri::Window,
+    app_handle:   tauri::AppHandle,
+    image_base64: String,
+    model_path:   String,
+    repeats:      Option<u32>,
+    gpu_backend:  Option<String>,
+    queue_id:     Option<String>,
+) -> Result<String, AppError> {
+    let gpu_backend = gpu_backend.as_deref().unwrap_or("cpu").to_lowercase();
+    let bin = get_sd_bin_path_for(&app_handle, &gpu_backend)?;
+    if !bin.exists() {
+        return Err(format!("stable-diffusion.cpp {} binary not installed.", gpu_backend.to_uppercase()).into());
+    }
+    if !Path::new(&model_path).exists() {
+        return Err(format!("Upscale model not found: {}", model_path).into());
+    }
+    let _slot = image_queue::acquire(&app_handle, queue_id, "upscale", &model_path).await?;
+
+    let mut temp_files = TempFiles(Vec::new());
+    let input = temp_files.add(write_temp_image(&image_base64, "upscale_in", None, false)?);
+    let out_path = input.with_file_name(format!(
+        "sd_upscaled_{}.png",
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis()
+    ));
+    let repeats = repeats.unwrap_or(1).clamp(1, 4);
+
+    let mut cmd = Command::new(&bin);
+    cmd.arg("-M").arg("upscale")
+       .arg("-i").arg(&input)
+       .arg("--upscale-model").arg(&model_path)
+       .arg("--upscale-repeats").arg(repeats.to_string())
+       .arg("-o").arg(&out_path);
+    println!("[SD] upscale_image — model {} ×{}", model_path, repeats);
+
+    let label = format!("upscale ×{}", repeats);
+    run_sd_process(&window, &app_handle, cmd, &bin, &gpu_backend, &out_path, &label).await.map_err(AppError::from)
+}
+
+/// Spawn the prepared sd command, stream its progress as `sd-progress`
+/// events and return the image it wrote to `out_path` as base64.
+async fn run_sd_process(
+    window:      &tauri::Window,
+    app_handle:  &tauri::AppHandle,
+    cmd:         Command,
+    bin:         &Path,
+    gpu_backend: &str,
+    out_path:    &Path,
+    label:       &str,
+) -> Result<String, String> {
+    let bytes = run_sd_output(window, app_handle, cmd, bin, gpu_backend, out_path, label).await?;
+    Ok(general_purpose::STANDARD.encode(&bytes))
+}
+
+/// run_sd_process without the base64 step: the raw bytes of whatever sd
+/// wrote to `out_path` (a PNG, or the AVI of a video run).
+pub(crate) async fn run_sd_output(
+    window:      &tauri::Window,
+    app_handle:  &tauri::AppHandle,
+    mut cmd:     Command,
+    bin:         &Path,
+    gpu_backend: &str,
+    out_path:    &Path,
+    label:       &str,
+) -> Result<Vec<u8>, String> {
+    let t_start = std::time::Instant::now();
+    if let Some(dir) = out_path.parent() {
+        disk::ensure_space(dir, OUTPUT_HEADROOM, "the generated image")?;
+    }
+
     // Log the full command line for easy debugging
     let full_cmd = format!(
         "{} {}",
@@ -594,122 +1032,14 @@ pub async fn run_local_sd(
 
     cmd.stderr(Stdio::piped()).stdout(Stdio::piped());
 
-    // Ensure libstable-diffusion.so (next to the binary) is on the library path.
-    // For CUDA builds also add common system CUDA library directories so the
-    // binary can find libcudart.so / libcublas.so without requiring the user to
-    // configure LD_LIBRARY_PATH manually.
-    let data_dir = get_sd_data_dir(&app_handle)?;
-    #[cfg(target_os = "linux")]
-    {
-        let prev = std::env::var("LD_LIBRARY_PATH").unwrap_or_default();
-        let mut paths: Vec<String> = vec![data_dir.to_string_lossy().to_string()];
-
-        if gpu_backend == "cuda" {
-            // Common CUDA runtime library locations on Linux.
-            // Try CUDA_HOME / CUDA_PATH env vars first, then common fixed paths.
-            let cuda_candidates: Vec<String> = {
-                let mut c = Vec::new();
-                for env_var in &["CUDA_HOME", "CUDA_PATH", "CUDA_ROOT"] {
-                    if let Ok(v) = std::env::var(env_var) {
-                        c.push(format!("{}/lib64", v));
-                        c.push(format!("{}/lib", v));
-                    }
-                }
-                // Fixed well-known paths (Ubuntu/Fedora/Arch/Nobara)
-                for p in &[
-                    "/usr/local/cuda/lib64",
-                    "/usr/local/cuda/targets/x86_64-linux/lib",  // Nobara / CUDA 12+
-                    "/usr/lib/x86_64-linux-gnu",
-                    "/usr/lib64",
-                    "/lib64",
-                ] {
-                    c.push(p.to_string());
-                }
-                // Glob-expand versioned CUDA dirs: /usr/local/cuda-12.x/lib64
-                // and /usr/local/cuda-12.x/targets/x86_64-linux/lib
-                if let Ok(entries) = std::fs::read_dir("/usr/local") {
-                    let mut cuda_dirs: Vec<String> = entries
-                        .flatten()
-                        .filter_map(|e| {
-                            let n = e.file_name().to_string_lossy().to_string();
-                            if n.starts_with("cuda-") {
-                                Some(vec![
-                                    format!("/usr/local/{}/lib64", n),
-                                    format!("/usr/local/{}/targets/x86_64-linux/lib", n),
-                                ])
-                            } else { None }
-                        })
-                        .flatten()
-                        .collect();
-                    cuda_dirs.sort_by(|a, b| b.cmp(a)); // newest version first
-                    c.extend(cuda_dirs);
-                }
-                // Dynamically find libcudart.so via ldconfig -p
-                if let Ok(out) = std::process::Command::new("ldconfig").arg("-p").output() {
-                    let text = String::from_utf8_lossy(&out.stdout);
-                    for line in text.lines() {
-                        if line.contains("libcudart.so") {
-                            if let Some(path) = line.splitn(2, "=>").nth(1) {
-                                let lib_path = path.trim();
-                                if let Some(dir) = std::path::Path::new(lib_path).parent() {
-                                    let dir_str = dir.to_string_lossy().to_string();
-                                    println!("[SD] ldconfig found libcudart at: {}", lib_path);
-                                    c.push(dir_str);
-                                }
-                            }
-                        }
-                    }
-                }
-                c
-            };
-
-            let mut found_cuda = false;
-            for candidate in &cuda_candidates {
-                let libcudart = std::path::Path::new(candidate).join("libcudart.so");
-                // Also check libcudart.so.12, libcudart.so.11, etc.
-                let found = libcudart.exists() || {
-                    std::fs::read_dir(candidate)
-                        .map(|rd| rd.flatten().any(|e| {
-                            e.file_name().to_string_lossy().starts_with("libcudart.so")
-                        }))
-                        .unwrap_or(false)
-                };
-                if found {
-                    println!("[SD] Found CUDA runtime at: {}", candidate);
-                    found_cuda = true;
-                }
-                if std::path::Path::new(candidate).exists() {
-                    paths.push(candidate.clone());
-                }
-            }
-            if !found_cuda {
-                println!("[SD] WARNING: libcudart.so not found in any common path. \
-                    GPU may fall back to CPU. Install NVIDIA CUDA Toolkit or set CUDA_HOME.");
-            }
-        }
-
-        if !prev.is_empty() {
-            paths.push(prev);
-        }
-        let new_ld = paths.join(":");
-        println!("[SD] LD_LIBRARY_PATH={}", new_ld);
-        cmd.env("LD_LIBRARY_PATH", new_ld);
-    }
-    #[cfg(target_os = "macos")]
-    {
-        let prev = std::env::var("DYLD_LIBRARY_PATH").unwrap_or_default();
-        let new_path = if prev.is_empty() {
-            data_dir.to_string_lossy().to_string()
-        } else {
-            format!("{}:{}", data_dir.display(), prev)
-        };
-        cmd.env("DYLD_LIBRARY_PATH", new_path);
-    }
+    let data_dir = get_sd_data_dir(app_handle)?;
+    apply_runtime_env(&mut cmd, &data_dir, gpu_backend);
 
     let mut child = cmd.spawn()
         .map_err(|e| format!("Failed to start sd binary: {}", e))?;
 
     println!("[SD] Process spawned (PID: {:?})", child.id());
+    let job = watchdog::register("sd", label, child.id());
 
     // Stream stderr lines as progress events.
     // stable-diffusion.cpp uses \r to overwrite progress in a terminal, so we
@@ -724,6 +1054,7 @@ pub async fn run_local_sd(
     let stderr = child.stderr.take().unwrap();
     let stdout = child.stdout.take().unwrap();
     let win = window.clone();
+    let (err_activity, out_activity) = (job.activity(), job.activity());
 
     // ── stderr reader — streams progress events and collects lines ──────────
     let stderr_task: tokio::task::JoinHandle<Vec<String>> = tokio::spawn(async move {
@@ -732,16 +1063,18 @@ pub async fn run_local_sd(
         let mut raw = Vec::<u8>::with_capacity(256);
         let mut tmp = [0u8; 256];
         let mut collected: Vec<String> = Vec::new();
+        let mut progress = SdProgress::default();
         loop {
             match reader.read(&mut tmp).await {
                 Ok(0) | Err(_) => break,
                 Ok(n) => {
+                    err_activity.touch();
                     for &b in &tmp[..n] {
                         if b == b'\n' || b == b'\r' {
                             if !raw.is_empty() {
                                 let line = String::from_utf8_lossy(&raw).to_string();
                                 println!("[SD stderr] {}", line);
-                                let _ = win.emit("sd-progress", serde_json::json!({ "line": line.clone() }));
+                                let _ = win.emit("sd-progress", progress.update(&line));
                                 collected.push(line);
                                 raw.clear();
                             }
@@ -755,7 +1088,7 @@ pub async fn run_local_sd(
         if !raw.is_empty() {
             let line = String::from_utf8_lossy(&raw).to_string();
             println!("[SD stderr] {}", line);
-            let _ = win.emit("sd-progress", serde_json::json!({ "line": line.clone() }));
+            let _ = win.emit("sd-progress", progress.update(&line));
             collected.push(line);
         }
         collected
@@ -772,6 +1105,7 @@ pub async fn run_local_sd(
             match reader.read(&mut tmp).await {
                 Ok(0) | Err(_) => break,
                 Ok(n) => {
+                    out_activity.touch();
                     for &b in &tmp[..n] {
                         if b == b'\n' || b == b'\r' {
                             if !raw.is_empty() {
@@ -795,8 +1129,17 @@ pub async fn run_local_sd(
         collected
     });
 
-    // Wait for process exit, then for both readers to flush completely.
-    let status       = child.wait().await.map_err(|e| e.to_string())?;
+    // Wait for process exit (or a kill from the watchdog UI), then for both
+    // readers to flush completely.
+    let status = tokio::select! {
+        status = child.wait() => status.map_err(|e| e.to_string())?,
+        _ = job.killed() => {
+            let _ = child.kill().await;
+            let _ = std::fs::remove_file(out_path);
+            println!("[SD] KILLED by user after {:.1}s", t_start.elapsed().as_secs_f32());
+            return Err("sd process was stopped (no output — it appeared to hang).".into());
+        }
+    };
     let stderr_lines = stderr_task.await.unwrap_or_default();
     let stdout_lines = stdout_task.await.unwrap_or_default();
 
@@ -833,16 +1176,429 @@ pub async fn run_local_sd(
         return Err("sd finished but no output image was created.".into());
     }
 
-    let bytes = std::fs::read(&out_path).map_err(|e| e.to_string())?;
-    let _ = std::fs::remove_file(&out_path);
+    let bytes = std::fs::read(out_path).map_err(|e| e.to_string())?;
+    let _ = std::fs::remove_file(out_path);
     let elapsed = t_start.elapsed();
     println!("[SD] SUCCESS — {} bytes, elapsed {:.1}s, output removed from tmp",
         bytes.len(), elapsed.as_secs_f32());
-    Ok(general_purpose::STANDARD.encode(&bytes))
+    Ok(bytes)
 }
 
 // ── Private helpers ────────────────────────────────────────────────────────
 
+/// Library-path environment for an sd binary (CLI or server) in `data_dir`.
+/// Ensures libstable-diffusion.so (next to the binary) is on the library path.
+/// For CUDA builds also adds common system CUDA library directories so the
+/// binary can find libcudart.so / libcublas.so without requiring the user to
+/// configure LD_LIBRARY_PATH manually.
+#[cfg_attr(target_os = "windows", allow(unused_variables))]
+pub(crate) fn apply_runtime_env(cmd: &mut Command, data_dir: &Path, gpu_backend: &str) {
+    #[cfg(target_os = "linux")]
+    {
+        let prev = std::env::var("LD_LIBRARY_PATH").unwrap_or_default();
+        let mut paths: Vec<String> = vec![data_dir.to_string_lossy().to_string()];
+
+        if gpu_backend == "cuda" {
+            // Common CUDA runtime library locations on Linux.
+            // Try CUDA_HOME / CUDA_PATH env vars first, then common fixed paths.
+            let cuda_candidates: Vec<String> = {
+                let mut c = Vec::new();
+                for env_var in &["CUDA_HOME", "CUDA_PATH", "CUDA_ROOT"] {
+                    if let Ok(v) = std::env::var(env_var) {
+                        c.push(format!("{}/lib64", v));
+                        c.push(format!("{}/lib", v));
+                    }
+                }
+                // Fixed well-known paths (Ubuntu/Fedora/Arch/Nobara)
+                for p in &[
+                    "/usr/local/cuda/lib64",
+                    "/usr/local/cuda/targets/x86_64-linux/lib",  // Nobara / CUDA 12+
+                    "/usr/lib/x86_64-linux-gnu",
+                    "/usr/lib64",
+                    "/lib64",
+                ] {
+                    c.push(p.to_string());
+                }
+                // Glob-expand versioned CUDA dirs: /usr/local/cuda-12.x/lib64
+                // and /usr/local/cuda-12.x/targets/x86_64-linux/lib
+                if let Ok(entries) = std::fs::read_dir("/usr/local") {
+                    let mut cuda_dirs: Vec<String> = entries
+                        .flatten()
+                        .filter_map(|e| {
+                            let n = e.file_name().to_string_lossy().to_string();
+                            if n.starts_with("cuda-") {
+                                Some(vec![
+                                    format!("/usr/local/{}/lib64", n),
+                                    format!("/usr/local/{}/targets/x86_64-linux/lib", n),
+                                ])
+                            } else { None }
+                        })
+                        .flatten()
+                        .collect();
+                    cuda_dirs.sort_by(|a, b| b.cmp(a)); // newest version first
+                    c.extend(cuda_dirs);
+                }
+                // Dynamically find libcudart.so via ldconfig -p
+                if let Ok(out) = std::process::Command::new("ldconfig").arg("-p").output() {
+                    let text = String::from_utf8_lossy(&out.stdout);
+                    for line in text.lines() {
+                        if line.contains("libcudart.so") {
+                            if let Some(path) = line.splitn(2, "=>").nth(1) {
+                                let lib_path = path.trim();
+                                if let Some(dir) = std::path::Path::new(lib_path).parent() {
+                                    let dir_str = dir.to_string_lossy().to_string();
+                                    println!("[SD] ldconfig found libcudart at: {}", lib_path);
+                                    c.push(dir_str);
+                                }
+                            }
+                        }
+                    }
+                }
+                c
+            };
+
+            let mut found_cuda = false;
+            for candidate in &cuda_candidates {
+                let libcudart = std::path::Path::new(candidate).join("libcudart.so");
+                // Also check libcudart.so.12, libcudart.so.11, etc.
+                let found = libcudart.exists() || {
+                    std::fs::read_dir(candidate)
+                        .map(|rd| rd.flatten().any(|e| {
+                            e.file_name().to_string_lossy().starts_with("libcudart.so")
+                        }))
+                        .unwrap_or(false)
+                };
+                if found {
+                    println!("[SD] Found CUDA runtime at: {}", candidate);
+                    found_cuda = true;
+                }
+                if std::path::Path::new(candidate).exists() {
+                    paths.push(candidate.clone());
+                }
+            }
+            if !found_cuda {
+                println!("[SD] WARNING: libcudart.so not found in any common path. \
+                    GPU may fall back to CPU. Install NVIDIA CUDA Toolkit or set CUDA_HOME.");
+            }
+        }
+
+        if gpu_backend == "rocm" {
+            let dirs: Vec<String> = rocm_lib_dirs().into_iter()
+                .filter(|d| std::path::Path::new(d).exists())
+                .collect();
+            match dirs.iter().find(|d| dir_has_lib(d, "libamdhip64.so")) {
+                Some(d) => println!("[SD] Found HIP runtime at: {}", d),
+                None => println!("[SD] WARNING: libamdhip64.so not found in any common path. \
+                    Install ROCm (HIP runtime) or set ROCM_PATH."),
+            }
+            paths.extend(dirs);
+        }
+
+        if !prev.is_empty() {
+            paths.push(prev);
+        }
+        let new_ld = paths.join(":");
+        println!("[SD] LD_LIBRARY_PATH={}", new_ld);
+        cmd.env("LD_LIBRARY_PATH", new_ld);
+    }
+    // The Windows ROCm build loads amdhip64.dll from the HIP SDK's bin dir
+    #[cfg(target_os = "windows")]
+    {
+        if gpu_backend == "rocm" {
+            if let Ok(hip) = std::env::var("HIP_PATH") {
+                let prev = std::env::var("PATH").unwrap_or_default();
+                cmd.env("PATH", format!("{}\\bin;{}", hip.trim_end_matches('\\'), prev));
+            } else {
+                println!("[SD] WARNING: HIP_PATH is not set — install the AMD HIP SDK for the ROCm build.");
+            }
+        }
+    }
+    #[cfg(target_os = "macos")]
+    {
+        // Release archives keep libstable-diffusion.dylib / libggml*.dylib
+        // next to the binary or in a lib/ sub-directory of the extraction
+        let mut paths: Vec<String> = vec![data_dir.to_string_lossy().to_string()];
+        for dir in dylib_dirs(&data_dir) {
+            let dir = dir.to_string_lossy().to_string();
+            if !paths.contains(&dir) {
+                paths.push(dir);
+            }
+        }
+        let prev = std::env::var("DYLD_LIBRARY_PATH").unwrap_or_default();
+        if !prev.is_empty() {
+            paths.push(prev);
+        }
+        let new_path = paths.join(":");
+        println!("[SD] DYLD_LIBRARY_PATH={}", new_path);
+        cmd.env("DYLD_LIBRARY_PATH", new_path);
+
+        // Builds that don't embed the Metal shader library load
+        // ggml-metal.metal from this directory
+        if gpu_backend == "metal" {
+            let shader = walkdir::WalkDir::new(&data_dir).max_depth(4).into_iter().flatten()
+                .find(|e| e.file_name() == "ggml-metal.metal" || e.file_name() == "default.metallib");
+            if let Some(dir) = shader.as_ref().and_then(|e| e.path().parent()) {
+                println!("[SD] Metal shaders: {}", dir.display());
+                cmd.env("GGML_METAL_PATH_RESOURCES", dir);
+            }
+        }
+    }
+}
+
+impl SdProgress {
+    /// Fold one stderr line into the state and return the event payload.
+    /// Phase lines switch the phase and reset the step counter; bar lines
+    /// (`|=====>   | 5/20 - 1.23s/it`) update step, total and ETA.
+    pub(crate) fn update(&mut self, line: &str) -> SdProgress {
+        let lower = line.to_lowercase();
+        let phase = if lower.contains("upscal") {
+            Some("upscaling")
+        } else if lower.contains("loading") || lower.contains("load model") {
+            Some("loading")
+        } else if lower.contains("encode") || lower.contains("encoding") {
+            Some("encoding")
+        } else if lower.contains("sampling") || lower.contains("generating image") {
+            Some("sampling")
+        } else if lower.contains("decod") {
+            Some("decoding")
+        } else if lower.contains("save result") || lower.contains("saving") {
+            Some("saving")
+        } else {
+            None
+        };
+        if let Some(p) = phase.filter(|p| *p != self.phase) {
+            self.phase = p.to_string();
+            self.step = 0;
+            self.total = 0;
+            self.eta_seconds = None;
+        }
+
+        if let Some((step, total, rate)) = parse_step(line) {
+            if self.phase.is_empty() {
+                self.phase = "sampling".into();
+            }
+            self.step = step;
+            self.total = total;
+            self.eta_seconds = rate.map(|secs_per_it| secs_per_it * total.saturating_sub(step) as f32);
+        }
+        self.line = line.to_string();
+        self.clone()
+    }
+}
+
+/// `(step, total, seconds per iteration)` from a progress-bar line.
+fn parse_step(line: &str) -> Option<(u32, u32, Option<f32>)> {
+    // the bar is the last "N/M" on the line; the rate follows " - "
+    let bar_end = line.rfind('|').map(|i| i + 1).unwrap_or(0);
+    let rest = line[bar_end..].trim();
+    let (counts, rate) = rest.split_once(" - ").unwrap_or((rest, ""));
+    let (step, total) = counts.trim().split_once('/')?;
+    let (step, total): (u32, u32) = (step.trim().parse().ok()?, total.trim().parse().ok()?);
+    if total == 0 || step > total {
+        return None;
+    }
+    let rate = rate.trim();
+    let secs_per_it = if let Some(v) = rate.strip_suffix("s/it") {
+        v.trim().parse::<f32>().ok()
+    } else if let Some(v) = rate.strip_suffix("it/s") {
+        v.trim().parse::<f32>().ok().filter(|v| *v > 0.0).map(|v| 1.0 / v)
+    } else {
+        None
+    };
+    Some((step, total, secs_per_it))
+}
+
+/// Temp files removed when dropped, so every early return cleans up.
+pub(crate) struct TempFiles(pub(crate) Vec<PathBuf>);
+
+impl TempFiles {
+    pub(crate) fn add(&mut self, path: PathBuf) -> PathBuf {
+        self.0.push(path.clone());
+        path
+    }
+}
+
+impl Drop for TempFiles {
+    fn drop(&mut self) {
+        for p in &self.0 {
+            let _ = std::fs::remove_file(p);
+        }
+    }
+}
+
+/// Emits `sd-preview` each time sd rewrites the preview file; runs until aborted.
+async fn watch_preview(window: tauri::Window, path: PathBuf) {
+    let mut last = None;
+    let mut seq = 0;
+    loop {
+        tokio::time::sleep(PREVIEW_POLL).await;
+        let Ok(modified) = std::fs::metadata(&path).and_then(|m| m.modified()) else { continue };
+        if last == Some(modified) {
+            continue;
+        }
+        // sd may still be writing the file; a failed decode is retried next tick
+        let p = path.clone();
+        let Ok(Ok(jpeg)) = tokio::task::spawn_blocking(move || preview_jpeg(&p)).await else { continue };
+        last = Some(modified);
+        seq += 1;
+        let _ = window.emit("sd-preview", SdPreview { seq, image_base64: jpeg });
+    }
+}
+
+/// Small base64 JPEG of the preview frame.
+fn preview_jpeg(path: &Path) -> Result<String, String> {
+    preview_jpeg_bytes(&std::fs::read(path).map_err(|e| e.to_string())?)
+}
+
+/// Same for an encoded image in memory (A1111 progress frames, image_gen.rs).
+pub(crate) fn preview_jpeg_bytes(bytes: &[u8]) -> Result<String, String> {
+    let img = image::load_from_memory(bytes).map_err(|e| e.to_string())?;
+    let thumb = img.thumbnail(PREVIEW_MAX, PREVIEW_MAX).to_rgb8();
+    let mut jpeg = Vec::new();
+    image::codecs::jpeg::JpegEncoder::new_with_quality(&mut jpeg, 70)
+        .encode_image(&thumb)
+        .map_err(|e| e.to_string())?;
+    Ok(general_purpose::STANDARD.encode(jpeg))
+}
+
+/// Decode a base64 image, fit it to `size` (the generation size) if given
+/// and save it as PNG in the temp dir. Masks are stored as greyscale.
+pub(crate) fn write_temp_image(b64: &str, kind: &str, size: Option<(u32, u32)>, grey: bool) -> Result<PathBuf, String> {
+    let data = b64.trim();
+    let data = data.split_once(";base64,").map(|(_, d)| d).unwrap_or(data);
+    let bytes = general_purpose::STANDARD.decode(data)
+        .map_err(|e| format!("Invalid {} image: {}", kind, e))?;
+    let mut img = image::load_from_memory(&bytes)
+        .map_err(|e| format!("Invalid {} image: {}", kind, e))?;
+    if let Some((width, height)) = size.filter(|&wh| wh != (img.width(), img.height())) {
+        img = img.resize_exact(width, height, image::imageops::FilterType::Lanczos3);
+    }
+    if grey {
+        img = image::DynamicImage::ImageLuma8(img.to_luma8());
+    }
+    let path = std::env::temp_dir().join(format!(
+        "sd_{}_{}.png",
+        kind,
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos()
+    ));
+    img.save_with_format(&path, image::ImageFormat::Png)
+        .map_err(|e| format!("Cannot write {} image: {}", kind, e))?;
+    Ok(path)
+}
+
+/// Whether `model` is an inpainting checkpoint: its first UNet conv takes 9
+/// input channels (sd_inspect) instead of 4. Without a readable header the
+/// file name decides. None = cannot tell, let sd.cpp decide.
+fn is_inpaint_model(model: &Path, info: Option<&sd_inspect::ModelInfo>) -> Option<bool> {
+    let name = model.file_name()?.to_string_lossy().to_lowercase();
+    if name.contains("inpaint") {
+        return Some(true);
+    }
+    info?.in_channels.map(|channels| channels == 9)
+}
+
+/// Model-loading flags for the detected architecture.
+#[derive(Debug, PartialEq)]
+struct ModelArgs {
+    /// "sd1" | "sd2" | "sdxl" | "sd3" | "flux" | "unknown"
+    base_model: &'static str,
+    /// -m / --diffusion-model plus any --clip_l / --clip_g / --t5xxl
+    args:       Vec<String>,
+    cfg_scale:  f32,
+    /// Used when the request names no sampler
+    sampler:    Option<&'static str>,
+}
+
+/// Builds the model flags from the request and the header inspection.
+/// Fails up front when a FLUX / SD3 model lacks an encoder or VAE it needs,
+/// or the size doesn't suit the architecture (sd_inspect::check_resolution),
+/// rather than after sd.cpp has spent minutes loading the weights.
+fn model_args(req: &LocalSdRequest, info: Option<&sd_inspect::ModelInfo>) -> Result<ModelArgs, String> {
+    let base_model = info.map_or("unknown", |i| i.base_model);
+    let required = info.map_or(&[][..], |i| i.required.as_slice());
+    let diffusion_only = req.diffusion_model.unwrap_or(info.is_some_and(|i| i.kind == "diffusion_model"));
+    let mut args = vec![
+        if diffusion_only { "--diffusion-model" } else { "-m" }.to_string(),
+        req.model_path.clone(),
+    ];
+
+    let given = |v: &Option<String>| v.as_deref().map(str::trim).filter(|p| !p.is_empty()).map(String::from);
+    for (name, path) in [("clip_l", given(&req.clip_l_path)), ("clip_g", given(&req.clip_g_path)), ("t5xxl", given(&req.t5xxl_path))] {
+        match path {
+            Some(path) => {
+                if !Path::new(&path).exists() {
+                    return Err(format!("{} text encoder not found: {}", name, path));
+                }
+                args.push(format!("--{}", name));
+                args.push(path);
+            }
+            None if required.contains(&name) => return Err(format!(
+                "This {} model has no built-in {} text encoder. Download it (e.g. {}) and set it \
+                 under Native SD → Text encoders.",
+                base_model.to_uppercase(), name, encoder_file(name),
+            )),
+            None => {}
+        }
+    }
+    if matches!(base_model, "flux" | "sd3") && required.contains(&"vae") && given(&req.vae_path).is_none() {
+        return Err(format!(
+            "This {} model has no built-in VAE. Download {} and select it as the VAE.",
+            base_model.to_uppercase(), if base_model == "flux" { "ae.safetensors" } else { "the SD3 VAE" },
+        ));
+    }
+
+    if info.is_some_and(|i| matches!(i.kind, "checkpoint" | "diffusion_model")) {
+        sd_inspect::check_resolution(base_model, req.width.unwrap_or(512), req.height.unwrap_or(512))?;
+    }
+
+    // FLUX dev / schnell are guidance-distilled: real CFG only doubles the
+    // time and burns the image, so it runs at 1 with the euler sampler
+    let (cfg_scale, sampler) = match base_model {
+        "flux" => (1.0, Some("euler")),
+        "sd3"  => (req.cfg_scale.unwrap_or(4.5), Some("euler")),
+        _      => (req.cfg_scale.unwrap_or(7.0), None),
+    };
+    Ok(ModelArgs { base_model, args, cfg_scale, sampler })
+}
+
+/// Writes the settings sd ran with into the PNG (gen_metadata.rs) and adds
+/// it to the gallery.
+fn finish(app: &tauri::AppHandle, req: &LocalSdRequest, family: &ModelArgs, result: Result<String, String>) -> Result<String, String> {
+    let record = gallery::GenerationRecord {
+        provider:        "native_sd".into(),
+        model:           Path::new(&req.model_path).file_name().map(|n| n.to_string_lossy().to_string()),
+        prompt:          req.prompt.clone(),
+        negative_prompt: req.negative_prompt.clone().filter(|n| !n.trim().is_empty() && family.cfg_scale > 1.0),
+        seed:            req.seed,
+        sampler:         req.sampler.clone().or(family.sampler.map(String::from)),
+        steps:           Some(req.steps.unwrap_or(20)),
+        cfg_scale:       Some(family.cfg_scale),
+        revised_prompt:  None,
+    };
+    let b64 = gen_metadata::embed_parameters(&result?, &record);
+    gallery::record(app, &b64, record);
+    Ok(b64)
+}
+
+/// 31-bit seed from the std hasher's per-process random keys.
+pub(crate) fn random_seed() -> i64 {
+    use std::hash::{BuildHasher, Hasher};
+    let mut h = std::collections::hash_map::RandomState::new().build_hasher();
+    h.write_u128(std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap_or_default().as_nanos());
+    (h.finish() & 0x7FFF_FFFF) as i64
+}
+
+fn encoder_file(name: &str) -> &'static str {
+    match name {
+        "clip_l" => "clip_l.safetensors",
+        "clip_g" => "clip_g.safetensors",
+        _        => "t5xxl_fp16.safetensors",
+    }
+}
+
 fn emit_progress(win: &tauri::Window, status: &str, progress: u8) {
     let _ = win.emit("sd-download-progress", serde_json::json!({
         "status":   status,
@@ -883,12 +1639,35 @@ fn collect_models(dir: &Path, out: &mut Vec<String>) {
     }
 }
 
+/// Hex sha256 from a GitHub release asset's `digest` ("sha256:<hex>").
+fn asset_sha256(asset: &serde_json::Value) -> Option<String> {
+    let digest = asset["digest"].as_str()?;
+    match digest.strip_prefix("sha256:") {
+        Some(hex) if hex.len() == 64 && hex.bytes().all(|b| b.is_ascii_hexdigit()) => Some(hex.to_ascii_lowercase()),
+        _ => {
+            println!("[SD] WARNING: unsupported asset digest {:?}", digest);
+            None
+        }
+    }
+}
+
+/// Every entry name is checked before anything is written: an absolute path
+/// or `..` that would land outside `dest` (zip-slip) aborts the extraction
+/// with nothing extracted.
 fn extract_zip(archive: &Path, dest: &Path) -> Result<(), String> {
     let file = std::fs::File::open(archive).map_err(|e| e.to_string())?;
     let mut zip = zip::ZipArchive::new(file).map_err(|e| e.to_string())?;
+    let mut targets = Vec::with_capacity(zip.len());
     for i in 0..zip.len() {
+        let entry = zip.by_index_raw(i).map_err(|e| e.to_string())?;
+        let relative = entry.enclosed_name().map(Path::to_path_buf).ok_or_else(|| {
+            format!("Archive entry {:?} points outside the install directory — refusing to extract", entry.name())
+        })?;
+        targets.push(dest.join(relative));
+    }
+
+    for (i, out_path) in targets.into_iter().enumerate() {
         let mut entry = zip.by_index(i).map_err(|e| e.to_string())?;
-        let out_path = dest.join(entry.name());
         if entry.is_dir() {
             std::fs::create_dir_all(&out_path).map_err(|e| e.to_string())?;
         } else {
@@ -902,10 +1681,208 @@ fn extract_zip(archive: &Path, dest: &Path) -> Result<(), String> {
     Ok(())
 }
 
+/// Like extract_zip, fails on the first entry that would escape `dest`
+/// (unpack_in also refuses links that point outside it).
 fn extract_targz(archive: &Path, dest: &Path) -> Result<(), String> {
     let file = std::fs::File::open(archive).map_err(|e| e.to_string())?;
     let gz   = flate2::read::GzDecoder::new(file);
     let mut tar = tar::Archive::new(gz);
-    tar.unpack(dest).map_err(|e| e.to_string())?;
+    std::fs::create_dir_all(dest).map_err(|e| e.to_string())?;
+    for entry in tar.entries().map_err(|e| e.to_string())? {
+        let mut entry = entry.map_err(|e| e.to_string())?;
+        let name = entry.path().map(|p| p.display().to_string()).unwrap_or_default();
+        if !entry.unpack_in(dest).map_err(|e| format!("Cannot extract {}: {}", name, e))? {
+            return Err(format!("Archive entry {:?} points outside the install directory — refusing to extract", name));
+        }
+    }
     Ok(())
 }
+
+// ── Unit tests ─────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fake_safetensors(dir: &Path, name: &str, in_channels: u64) -> PathBuf {
+        let header = serde_json::json!({
+            "model.diffusion_model.input_blocks.0.0.weight": {
+                "dtype": "F16", "shape": [320, in_channels, 3, 3], "data_offsets": [0, 0]
+            }
+        }).to_string();
+        let mut bytes = (header.len() as u64).to_le_bytes().to_vec();
+        bytes.extend_from_slice(header.as_bytes());
+        let path = dir.join(name);
+        std::fs::write(&path, bytes).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_progress_phases_and_steps() {
+        let mut p = SdProgress::default();
+        assert_eq!(p.update("[INFO ] stable-diffusion.cpp:200 - loading model from 'x.safetensors'").phase, "loading");
+        let s = p.update("[INFO ] stable-diffusion.cpp:1500 - sampling using Euler A method");
+        assert_eq!((s.phase.as_str(), s.step, s.total), ("sampling", 0, 0));
+
+        let s = p.update("  |==========>                                       | 5/20 - 2.00s/it");
+        assert_eq!((s.phase.as_str(), s.step, s.total, s.eta_seconds), ("sampling", 5, 20, Some(30.0)));
+        let s = p.update("  |==================================================| 20/20 - 4.00it/s");
+        assert_eq!((s.step, s.eta_seconds), (20, Some(0.0)));
+
+        let s = p.update("[INFO ] stable-diffusion.cpp:1600 - decoding 1 latents");
+        assert_eq!((s.phase.as_str(), s.step, s.total), ("decoding", 0, 0));
+        assert_eq!(p.update("some unrelated line").phase, "decoding");
+    }
+
+    #[test]
+    fn test_extract_zip_rejects_escaping_entries() {
+        use std::io::Write;
+        let dir = tempfile::tempdir().unwrap();
+        let write_zip = |name: &str, entries: &[&str]| {
+            let path = dir.path().join(name);
+            let mut zip = zip::ZipWriter::new(std::fs::File::create(&path).unwrap());
+            for entry in entries {
+                zip.start_file(*entry, zip::write::FileOptions::default()).unwrap();
+                zip.write_all(b"x").unwrap();
+            }
+            zip.finish().unwrap();
+            path
+        };
+        let dest = dir.path().join("runtime");
+
+        let good = write_zip("good.zip", &["bin/sd-cli", "README.txt"]);
+        extract_zip(&good, &dest).unwrap();
+        assert!(dest.join("bin/sd-cli").is_file());
+
+        let evil = write_zip("evil.zip", &["ok.txt", "../escaped.txt"]);
+        let err = extract_zip(&evil, &dest).unwrap_err();
+        assert!(err.contains("outside the install directory"));
+        assert!(!dir.path().join("escaped.txt").exists());
+        assert!(!dest.join("ok.txt").exists(), "entries before the bad one were written");
+    }
+
+    #[test]
+    fn test_asset_sha256() {
+        let hex = "AB".repeat(32);
+        assert_eq!(asset_sha256(&serde_json::json!({ "digest": format!("sha256:{}", hex) })), Some("ab".repeat(32)));
+        assert_eq!(asset_sha256(&serde_json::json!({ "digest": "sha512:abcd" })), None);
+        assert_eq!(asset_sha256(&serde_json::json!({ "digest": "sha256:xyz" })), None);
+        assert_eq!(asset_sha256(&serde_json::json!({})), None);
+    }
+
+    #[test]
+    fn test_parse_step_rejects_non_bars() {
+        assert_eq!(parse_step("| 3/10"), Some((3, 10, None)));
+        assert_eq!(parse_step("loaded 12/0 tensors"), None);
+        assert_eq!(parse_step("version 1.2"), None);
+    }
+
+    #[test]
+    fn test_dylib_dirs() {
+        let dir = tempfile::tempdir().unwrap();
+        let lib = dir.path().join("build").join("lib");
+        std::fs::create_dir_all(&lib).unwrap();
+        std::fs::write(lib.join("libggml-metal.dylib"), b"").unwrap();
+        std::fs::write(lib.join("libstable-diffusion.dylib"), b"").unwrap();
+        std::fs::write(dir.path().join("sd-cli-metal"), b"").unwrap();
+        assert_eq!(dylib_dirs(dir.path()), [lib]);
+    }
+
+    #[test]
+    fn test_bin_name_per_backend() {
+        assert!(sd_bin_name_for("rocm").contains("rocm"));
+        assert!(sd_bin_name_for("vulkan").contains("vulkan"));
+        assert!(sd_bin_name_for("metal").contains("metal"));
+        assert_eq!(sd_bin_name_for("metal-ish"), sd_bin_name_for("cpu"));
+        assert!(sd_server_name_for("cuda").starts_with("sd-server-cuda"));
+        assert_ne!(sd_server_name_for("cpu"), sd_bin_name_for("cpu"));
+    }
+
+    #[test]
+    fn test_inpaint_model_detection() {
+        let dir = tempfile::tempdir().unwrap();
+        let detect = |path: PathBuf| is_inpaint_model(&path, sd_inspect::inspect(&path).ok().as_ref());
+        assert_eq!(detect(fake_safetensors(dir.path(), "a.safetensors", 9)), Some(true));
+        assert_eq!(detect(fake_safetensors(dir.path(), "b.safetensors", 4)), Some(false));
+        assert_eq!(detect(PathBuf::from("/models/realistic-inpainting.ckpt")), Some(true));
+        assert_eq!(detect(PathBuf::from("/models/model.gguf")), None);
+    }
+
+    #[test]
+    fn test_temp_mask_is_resized_and_grey() {
+        let mut png = Vec::new();
+        image::DynamicImage::new_rgb8(64, 32)
+            .write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+            .unwrap();
+        let b64 = format!("data:image/png;base64,{}", general_purpose::STANDARD.encode(&png));
+
+        let files = TempFiles(vec![write_temp_image(&b64, "mask", Some((128, 128)), true).unwrap()]);
+        let img = image::open(&files.0[0]).unwrap();
+        assert_eq!((img.width(), img.height()), (128, 128));
+        assert!(matches!(img, image::DynamicImage::ImageLuma8(_)));
+        let path = files.0[0].clone();
+        drop(files);
+        assert!(!path.exists());
+    }
+
+    fn model_info(base_model: &'static str, kind: &'static str, required: Vec<&'static str>) -> sd_inspect::ModelInfo {
+        sd_inspect::ModelInfo {
+            format: "safetensors", base_model, kind, precision: None, tensor_count: 0, weights_bytes: 0,
+            has_vae: !required.contains(&"vae"), in_channels: None, required, recommended_vae: None, vram_mb: 0, resolutions: Vec::new(),
+            metadata: Default::default(), warnings: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_model_args_per_family() {
+        let dir = tempfile::tempdir().unwrap();
+        let encoder = |name: &str| {
+            let path = dir.path().join(name);
+            std::fs::write(&path, b"").unwrap();
+            Some(path.to_string_lossy().to_string())
+        };
+        let mut req: LocalSdRequest = serde_json::from_value(serde_json::json!({
+            "model_path": "/m/flux1-dev.gguf", "prompt": "a cat", "cfg_scale": 7.0
+        })).unwrap();
+
+        // Classic checkpoint: -m, request CFG, no sampler override
+        let sd15 = model_args(&req, Some(&model_info("sd1", "checkpoint", vec![]))).unwrap();
+        assert_eq!(sd15.args, ["-m", "/m/flux1-dev.gguf"]);
+        assert_eq!((sd15.cfg_scale, sd15.sampler), (7.0, None));
+        assert_eq!(model_args(&req, None).unwrap().args[0], "-m");
+        let too_big = LocalSdRequest { width: Some(1536), height: Some(1536), ..req.clone() };
+        assert!(model_args(&too_big, Some(&model_info("sd1", "checkpoint", vec![]))).unwrap_err().contains("too large"));
+
+        // FLUX diffusion-only file needs its encoders and VAE
+        let flux = model_info("flux", "diffusion_model", vec!["vae", "clip_l", "t5xxl"]);
+        let err = model_args(&req, Some(&flux)).unwrap_err();
+        assert!(err.contains("clip_l") && err.contains("FLUX"), "{}", err);
+        req.clip_l_path = encoder("clip_l.safetensors");
+        req.t5xxl_path = encoder("t5xxl_fp16.safetensors");
+        assert!(model_args(&req, Some(&flux)).unwrap_err().contains("ae.safetensors"));
+        req.vae_path = Some("/m/ae.safetensors".into());
+        let args = model_args(&req, Some(&flux)).unwrap();
+        assert_eq!(&args.args[..3], ["--diffusion-model", "/m/flux1-dev.gguf", "--clip_l"]);
+        assert_eq!(args.args[4], "--t5xxl");
+        assert_eq!((args.cfg_scale, args.sampler), (1.0, Some("euler")));
+
+        // Explicit flag style wins over the header; missing files fail early
+        req.diffusion_model = Some(false);
+        assert_eq!(model_args(&req, Some(&flux)).unwrap().args[0], "-m");
+        req.clip_g_path = Some("/nowhere/clip_g.safetensors".into());
+        assert!(model_args(&req, Some(&flux)).unwrap_err().contains("clip_g text encoder not found"));
+    }
+
+    #[test]
+    fn test_preview_jpeg_is_small() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("preview.png");
+        image::DynamicImage::new_rgb8(1024, 512).save(&path).unwrap();
+        let jpeg = general_purpose::STANDARD.decode(preview_jpeg(&path).unwrap()).unwrap();
+        let img = image::load_from_memory_with_format(&jpeg, image::ImageFormat::Jpeg).unwrap();
+        assert_eq!((img.width(), img.height()), (PREVIEW_MAX, PREVIEW_MAX / 2));
+        // A half-written file is skipped, not fatal
+        std::fs::write(&path, b"\x89PNG\r\n").unwrap();
+        assert!(preview_jpeg(&path).is_err());
+    }
+}
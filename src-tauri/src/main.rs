@@ -3,20 +3,87 @@
     windows_subsystem = "windows"
 )]
 
+mod a11y;
+mod actions;
 mod ai_bridge;
+mod audit;
+mod capabilities;
+mod capture_history;
+mod changelog;
 mod clipboard;
+mod clipboard_ingest;
+mod conversation;
+mod data_preview;
+mod deep_research;
+mod disk;
+mod downloads;
+mod error;
+mod focus_session;
+mod gallery;
+mod gen_metadata;
+mod git;
+mod git_assist;
+mod github_fetch;
+mod glossary;
+mod gpu_devices;
+mod hotkeys;
+mod hotword;
+mod http;
+mod image_edit;
 mod image_gen;
+mod image_grid;
+mod image_prep;
+mod image_queue;
 mod local_sd;
+#[cfg(all(test, feature = "provider-tests"))]
+mod mock_provider;
+mod moderation;
 mod overlay;
+mod page_render;
+mod path_summary;
+mod power;
 mod project_indexer;
+mod project_vectors;
+mod project_watch;
+mod prompt_layers;
+mod provider_status;
+mod query_prep;
+mod quick_actions;
+mod recording;
+mod reverse_image;
+mod rich_text;
 mod screen_capture;
+mod sd_inspect;
+mod sd_models;
+mod sd_server;
+mod search_cache;
+mod secret_scanner;
+mod session;
+mod site_cookies;
+mod startup;
+mod style_presets;
+mod suggestions;
+mod translation;
+mod verification;
+mod video_gen;
+mod watchdog;
 mod web_search;
 
-use tauri::{GlobalShortcutManager, Manager, SystemTray, SystemTrayEvent, SystemTrayMenu, SystemTrayMenuItem};
+use tauri::{Manager, SystemTray, SystemTrayEvent, SystemTrayMenu, SystemTrayMenuItem};
 
 fn main() {
+    startup::mark_process_start();
     env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
 
+    // Jump-list launches (`--action <id>`) go to the running instance if any
+    let launch_action = quick_actions::action_from_args();
+    if let Some(action) = &launch_action {
+        if quick_actions::forward_to_running(action) {
+            log::info!("Forwarded '{}' to the running instance", action);
+            return;
+        }
+    }
+
     let tray_menu = SystemTrayMenu::new()
         .add_item(tauri::CustomMenuItem::new("toggle", "Toggle Overlay"))
         .add_native_item(SystemTrayMenuItem::Separator)
@@ -29,65 +96,75 @@ fn main() {
         // ── Tray event handler ────────────────────────────────────────
         .on_system_tray_event(|app, event| match event {
             SystemTrayEvent::MenuItemClick { id, .. } => match id.as_str() {
-                "toggle" => overlay::toggle_window(app),
-                "quit"   => std::process::exit(0),
+                "toggle" => { let _ = actions::dispatch(app, "toggle_window", None); }
+                "quit"   => { let _ = actions::dispatch(app, "quit", None); }
                 _        => {}
             },
-            SystemTrayEvent::DoubleClick { .. } => overlay::toggle_window(app),
+            SystemTrayEvent::DoubleClick { .. } => { let _ = actions::dispatch(app, "toggle_window", None); }
             _ => {}
         })
-        .setup(|app| {
+        .setup(move |app| {
             let app_handle = app.handle();
 
+            // Only what the first frame needs runs here; the window appears
+            // once setup returns (timings: get_startup_timings)
+
             // ── Cursor tracker (auto click-through on transparent areas) ──
             let win_tracker = app_handle.get_window("main").unwrap();
-            overlay::spawn_cursor_tracker(win_tracker);
-
-            // ── Global hotkeys ────────────────────────────────────────
-            // Registration is best-effort: some keys may be claimed by the
-            // desktop environment (e.g. Alt+Space on GNOME). A failure is
-            // logged as a warning instead of crashing the app.
-            let mut shortcuts = app.global_shortcut_manager();
-
-            // Alt+M → toggle click-through
-            let win = app_handle.get_window("main").unwrap();
-            if let Err(e) = shortcuts.register("Alt+M", move || {
-                overlay::toggle_click_through(&win);
-            }) {
-                log::warn!("Could not register Alt+M: {}", e);
-            }
-
-            // Alt+Shift+S → capture screen and analyze
-            let win_s = app_handle.get_window("main").unwrap();
-            if let Err(e) = shortcuts.register("Alt+Shift+S", move || {
-                let _ = win_s.emit("trigger-screenshot", ());
-            }) {
-                log::warn!("Could not register Alt+Shift+S: {}", e);
-            }
+            startup::timed("cursor_tracker", || overlay::spawn_cursor_tracker(win_tracker));
 
-            // Alt+Shift+H → hide/show window
-            let app_h = app_handle.clone();
-            if let Err(e) = shortcuts.register("Alt+Shift+H", move || {
-                overlay::toggle_window(&app_h);
-            }) {
-                log::warn!("Could not register Alt+Shift+H: {}", e);
-            }
+            // ── Crash-recovery checkpoints (get_recovery on first render) ──
+            startup::timed("session", || session::spawn_checkpointer(&app_handle));
 
             // ── macOS: keep process as accessory so no dock icon ──────
             #[cfg(target_os = "macos")]
-            {
+            startup::timed("activation_policy", || {
                 use cocoa::appkit::{NSApp, NSApplicationActivationPolicy};
                 unsafe {
                     NSApp().setActivationPolicy_(
                         NSApplicationActivationPolicy::NSApplicationActivationPolicyAccessory,
                     );
                 }
+            });
+
+            if let Some(action) = launch_action.as_deref() {
+                if let Err(e) = actions::dispatch(&app_handle, action, None) {
+                    log::warn!("{}", e);
+                }
             }
 
-            log::info!("AI Assistant started – hotkeys registered");
+            // ── Deferred: off the window's critical path ─────────────
+            let deferred = app_handle.clone();
+            std::thread::spawn(move || {
+                // Global hotkeys (re-registered on keyboard-layout change)
+                startup::deferred("hotkeys", || hotkeys::spawn_layout_watcher(deferred.clone()));
+                // Jump list (Windows) / dock menu (macOS); AppKit wants the main thread
+                let main_thread = deferred.clone();
+                let _ = deferred.run_on_main_thread(move || {
+                    startup::deferred("quick_actions", || quick_actions::install(&main_thread));
+                });
+                // Opt-in request/response audit log
+                startup::deferred("audit", || audit::init(&deferred));
+                // Accessibility preferences (a11y-changed events)
+                startup::deferred("a11y_watcher", || a11y::spawn_watcher(deferred.clone()));
+                // Battery / power-saver state (power-changed events)
+                startup::deferred("power_watcher", || power::spawn_watcher(deferred.clone()));
+                // Stall detection for child-process jobs (job-stalled)
+                startup::deferred("watchdog", || watchdog::spawn_monitor(deferred.clone()));
+                log::info!("AI Assistant started – hotkeys registered");
+            });
+
+            startup::mark_setup_done();
             Ok(())
         })
         .invoke_handler(tauri::generate_handler![
+            a11y::get_a11y_prefs,
+            power::get_power_state,
+            capabilities::get_capabilities,
+            actions::list_actions,
+            actions::run_action,
+            hotkeys::get_shortcut_cheatsheet,
+            hotkeys::set_hotkey,
             overlay::set_click_through,
             overlay::set_always_on_top,
             overlay::set_dialog_open,
@@ -99,36 +176,164 @@ fn main() {
             overlay::set_panel_x,
             screen_capture::capture_screen,
             screen_capture::capture_window_under_cursor,
+            capture_history::save_capture,
+            capture_history::list_captures,
+            capture_history::delete_capture,
+            capture_history::index_captures,
+            capture_history::search_captures,
+            session::save_draft,
+            session::set_pending_jobs,
+            session::get_recovery,
+            session::dismiss_recovery,
+            recording::start_recording,
+            recording::stop_recording,
+            recording::list_recordings,
+            recording::delete_recording,
+            recording::export_recording,
+            hotword::start_hotword,
+            hotword::stop_hotword,
+            hotword::get_hotword_status,
+            suggestions::start_suggestions,
+            suggestions::stop_suggestions,
+            suggestions::snooze_suggestions,
+            suggestions::dismiss_suggestion,
+            focus_session::start_focus_session,
+            focus_session::stop_focus_session,
+            focus_session::get_focus_session_status,
+            focus_session::list_focus_sessions,
+            focus_session::summarize_focus_session,
+            image_prep::get_image_upload_options,
+            image_prep::set_image_upload_options,
             ai_bridge::analyze_with_openai,
             ai_bridge::analyze_with_claude,
             ai_bridge::analyze_with_deepseek,
             ai_bridge::analyze_with_openrouter,
+            ai_bridge::analyze_with_together,
             ai_bridge::analyze_with_local,
             ai_bridge::cancel_ai_request,
             ai_bridge::analyze_stream,
             ai_bridge::list_ollama_models,
             ai_bridge::list_lmstudio_models,
+            ai_bridge::list_lmstudio_models_detailed,
+            ai_bridge::lmstudio_load_model,
+            ai_bridge::lmstudio_unload_model,
+            ai_bridge::detect_local_server,
             ai_bridge::list_sd_models,
+            provider_status::get_provider_status,
+            moderation::moderate_prompt,
+            moderation::set_moderation_config,
+            translation::translate_text,
+            translation::translate,
+            glossary::list_glossaries,
+            glossary::save_glossary,
+            glossary::delete_glossary,
+            conversation::generate_title,
+            conversation::summarize_conversation,
+            verification::verify_answer,
+            verification::analyze_with_verification,
             project_indexer::index_directory,
             project_indexer::read_file_content,
+            project_indexer::read_file_lines,
+            path_summary::summarize_path,
+            path_summary::get_path_overview,
+            changelog::generate_changelog,
+            git_assist::suggest_commit_message,
+            git_assist::git_commit,
+            git_assist::draft_pr_description,
+            git_assist::review_diff,
+            secret_scanner::get_secret_scan_config,
+            secret_scanner::set_secret_scan_config,
+            secret_scanner::scan_text,
+            image_queue::list_image_queue,
+            image_queue::reorder_image_queue,
+            image_queue::cancel_queued_image,
+            audit::get_audit_settings,
+            audit::set_audit_settings,
+            audit::get_audit_entries,
+            audit::clear_audit_log,
+            prompt_layers::get_prompt_layer_settings,
+            prompt_layers::save_prompt_layer_settings,
+            prompt_layers::compose_system_prompt,
+            data_preview::preview_data_file,
+            disk::get_disk_usage,
             project_indexer::write_file,
             project_indexer::patch_file,
             project_indexer::delete_file,
             project_indexer::list_dir,
             project_indexer::create_dir_cmd,
             project_indexer::rename_path,
+            project_watch::watch_project,
+            project_watch::unwatch_project,
+            project_watch::get_index_delta,
+            project_vectors::index_project_vectors,
+            project_vectors::update_project_vectors,
+            project_vectors::semantic_search,
+            project_vectors::clear_project_vectors,
             web_search::web_search,
             web_search::fetch_url_content,
+            page_render::fetch_url_rendered,
+            github_fetch::fetch_github,
             web_search::search_and_fetch,
+            web_search::search_error,
+            web_search::image_search,
+            web_search::set_fetch_user_agent,
+            site_cookies::list_site_cookies,
+            site_cookies::save_site_cookie,
+            site_cookies::delete_site_cookie,
+            deep_research::deep_research,
+            search_cache::clear_search_cache,
+            reverse_image::reverse_image_search,
             clipboard::get_clipboard_image,
+            clipboard::set_clipboard_image,
+            clipboard::get_clipboard_text,
+            clipboard::get_clipboard_rich_text,
+            clipboard::set_clipboard_text,
+            clipboard_ingest::ingest_clipboard,
             image_gen::generate_image,
+            image_gen::interrupt_webui_generation,
+            image_edit::stability_inpaint,
+            image_edit::stability_outpaint,
+            image_edit::stability_erase,
+            image_edit::stability_remove_background,
+            image_grid::compose_grid,
+            gallery::list_generated_images,
+            gallery::delete_generated_image,
+            style_presets::list_style_presets,
+            style_presets::save_style_preset,
+            style_presets::delete_style_preset,
             local_sd::get_sd_binary_status,
             local_sd::download_sd_binary,
             local_sd::delete_sd_binary,
             local_sd::list_local_sd_models,
+            sd_models::download_sd_model,
+            downloads::list_downloads,
+            downloads::cancel_download,
+            downloads::pause_download,
+            downloads::resume_download,
+            downloads::set_download_limit,
+            sd_inspect::inspect_sd_model,
             local_sd::check_cuda_libs,
+            local_sd::check_rocm_libs,
+            gpu_devices::get_gpu_info,
             local_sd::run_local_sd,
+            local_sd::upscale_image,
+            video_gen::run_local_video,
+            sd_server::start_sd_server,
+            sd_server::stop_sd_server,
+            sd_server::get_sd_server_status,
+            gen_metadata::read_generation_metadata,
+            watchdog::list_jobs,
+            watchdog::kill_job,
+            watchdog::set_watchdog_timeout,
+            startup::get_startup_timings,
+            startup::mark_first_paint,
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|_app, event| {
+            if let tauri::RunEvent::Exit = event {
+                sd_server::shutdown();
+                session::mark_clean_exit();
+            }
+        });
 }
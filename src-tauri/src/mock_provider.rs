@@ -0,0 +1,202 @@
+// mock_provider.rs — in-process stand-in for OpenAI / Anthropic-style APIs
+//
+// A tiny HTTP/1.1 server on 127.0.0.1 that answers with scripted responses
+// (plain JSON, or SSE streamed chunk by chunk with optional delays) and
+// records every request it receives. Only built for the `provider-tests`
+// feature:
+//
+//   cargo test --features provider-tests
+//
+// Provider URLs are redirected to it with `ai_bridge::set_api_base`.
+
+use serde_json::Value;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+// ── Types ──────────────────────────────────────────────────────────────────
+
+#[derive(Debug, Clone)]
+pub(crate) struct MockResponse {
+    status:       u16,
+    content_type: &'static str,
+    /// Written one after another; the body has no length, the connection
+    /// closes when the last chunk is out
+    chunks:       Vec<String>,
+    /// Pause before each chunk
+    delay:        Duration,
+}
+
+impl MockResponse {
+    pub(crate) fn json(status: u16, body: Value) -> Self {
+        Self { status, content_type: "application/json", chunks: vec![body.to_string()], delay: Duration::ZERO }
+    }
+
+    /// `{"error": {"message": …}}`, the shape both OpenAI and Anthropic use
+    pub(crate) fn error(status: u16, message: &str) -> Self {
+        Self::json(status, serde_json::json!({ "error": { "type": "mock_error", "message": message } }))
+    }
+
+    /// One `data:` line per event, then `data: [DONE]`.
+    pub(crate) fn sse(events: &[Value]) -> Self {
+        let mut chunks: Vec<String> = events.iter().map(|e| format!("data: {}\n\n", e)).collect();
+        chunks.push("data: [DONE]\n\n".into());
+        Self { status: 200, content_type: "text/event-stream", chunks, delay: Duration::ZERO }
+    }
+
+    /// OpenAI chat-completion chunks carrying `tokens` as content deltas.
+    pub(crate) fn openai_stream(tokens: &[&str]) -> Self {
+        let events: Vec<Value> = tokens.iter()
+            .map(|t| serde_json::json!({ "choices": [{ "index": 0, "delta": { "content": t } }] }))
+            .collect();
+        Self::sse(&events)
+    }
+
+    /// Anthropic message events carrying `tokens` as text deltas.
+    pub(crate) fn anthropic_stream(tokens: &[&str]) -> Self {
+        let mut events = vec![serde_json::json!({
+            "type": "message_start",
+            "message": { "usage": { "input_tokens": 10, "cache_read_input_tokens": 4 } }
+        })];
+        events.extend(tokens.iter().map(|t| serde_json::json!({
+            "type": "content_block_delta", "index": 0, "delta": { "type": "text_delta", "text": t }
+        })));
+        events.push(serde_json::json!({ "type": "message_stop" }));
+        Self::sse(&events)
+    }
+
+    pub(crate) fn with_delay(mut self, delay: Duration) -> Self {
+        self.delay = delay;
+        self
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct RecordedRequest {
+    pub method:  String,
+    pub path:    String,
+    /// Header names lowercased
+    pub headers: Vec<(String, String)>,
+    pub body:    Value,
+}
+
+impl RecordedRequest {
+    pub(crate) fn header(&self, name: &str) -> Option<&str> {
+        self.headers.iter().find(|(n, _)| n == name).map(|(_, v)| v.as_str())
+    }
+}
+
+pub(crate) struct MockServer {
+    pub url:  String,
+    requests: Arc<Mutex<Vec<RecordedRequest>>>,
+    task:     tokio::task::JoinHandle<()>,
+}
+
+// ── Server ─────────────────────────────────────────────────────────────────
+
+impl MockServer {
+    /// Serve `responses` in order; the last one repeats for further requests.
+    pub(crate) async fn start(responses: Vec<MockResponse>) -> Self {
+        assert!(!responses.is_empty(), "mock server needs at least one response");
+        let listener = TcpListener::bind("127.0.0.1:0").await.expect("bind mock server");
+        let url = format!("http://{}", listener.local_addr().expect("mock server address"));
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let recorded = requests.clone();
+        let task = tokio::spawn(async move {
+            let mut served = 0;
+            while let Ok((stream, _)) = listener.accept().await {
+                let response = responses[served.min(responses.len() - 1)].clone();
+                served += 1;
+                let recorded = recorded.clone();
+                tokio::spawn(async move {
+                    let _ = handle(stream, response, recorded).await;
+                });
+            }
+        });
+        Self { url, requests, task }
+    }
+
+    pub(crate) fn requests(&self) -> Vec<RecordedRequest> {
+        self.requests.lock().map(|r| r.clone()).unwrap_or_default()
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+async fn handle(mut stream: TcpStream, response: MockResponse, recorded: Arc<Mutex<Vec<RecordedRequest>>>) -> std::io::Result<()> {
+    let request = read_request(&mut stream).await?;
+    if let Ok(mut r) = recorded.lock() {
+        r.push(request);
+    }
+
+    let head = format!(
+        "HTTP/1.1 {} {}\r\ncontent-type: {}\r\nconnection: close\r\n\r\n",
+        response.status,
+        reason(response.status),
+        response.content_type,
+    );
+    stream.write_all(head.as_bytes()).await?;
+    for chunk in &response.chunks {
+        if !response.delay.is_zero() {
+            tokio::time::sleep(response.delay).await;
+        }
+        stream.write_all(chunk.as_bytes()).await?;
+        stream.flush().await?;
+    }
+    stream.shutdown().await
+}
+
+async fn read_request(stream: &mut TcpStream) -> std::io::Result<RecordedRequest> {
+    let mut buf = Vec::new();
+    let mut chunk = [0u8; 4096];
+    let header_end = loop {
+        let n = stream.read(&mut chunk).await?;
+        if n == 0 {
+            return Err(std::io::ErrorKind::UnexpectedEof.into());
+        }
+        buf.extend_from_slice(&chunk[..n]);
+        if let Some(pos) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+            break pos + 4;
+        }
+    };
+
+    let head = String::from_utf8_lossy(&buf[..header_end]).to_string();
+    let mut lines = head.lines();
+    let mut start = lines.next().unwrap_or("").split_whitespace();
+    let method = start.next().unwrap_or("").to_string();
+    let path = start.next().unwrap_or("").to_string();
+    let headers: Vec<(String, String)> = lines
+        .filter_map(|l| l.split_once(':'))
+        .map(|(n, v)| (n.trim().to_ascii_lowercase(), v.trim().to_string()))
+        .collect();
+
+    let length = headers.iter()
+        .find(|(n, _)| n == "content-length")
+        .and_then(|(_, v)| v.parse::<usize>().ok())
+        .unwrap_or(0);
+    while buf.len() < header_end + length {
+        let n = stream.read(&mut chunk).await?;
+        if n == 0 {
+            break;
+        }
+        buf.extend_from_slice(&chunk[..n]);
+    }
+    let body = serde_json::from_slice(&buf[header_end..]).unwrap_or(Value::Null);
+    Ok(RecordedRequest { method, path, headers, body })
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        429 => "Too Many Requests",
+        500 => "Internal Server Error",
+        _   => "Mock",
+    }
+}
@@ -0,0 +1,321 @@
+// moderation.rs — optional guardrail check before a prompt leaves the machine
+//
+// Backends:
+//   openai — OpenAI moderation endpoint (omni-moderation-latest, also
+//            classifies the screenshot)
+//   local  — the caller's blocked terms and regex patterns; fully offline
+//
+// Configs are kept per profile, so a machine shared by several people can
+// moderate each of them differently. The frontend pushes every profile's
+// ModerationConfig with set_moderation_config and marks the one in use as
+// active; from then on ai_bridge runs `enforce` on every cloud-bound
+// request (next to secret_scanner::scrub_request) under the profile the
+// request names, or the active one. The whole outbound text is checked —
+// system prompt, prompt, context files and history — and a flagged request
+// is refused instead of sent. Off until configured. moderate_prompt checks
+// a prompt against an explicit config on demand.
+//
+// Tauri commands exposed:
+//   moderate_prompt                                   → ModerationResult
+//   set_moderation_config(config, profile?, active?)  (outbound checks)
+
+use crate::error::AppError;
+use crate::{audit, http, image_prep};
+use regex::RegexBuilder;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+const OPENAI_MODERATION_MODEL: &str = "omni-moderation-latest";
+const DEFAULT_PROFILE: &str = "default";
+
+/// Configs applied to outbound requests; empty = moderation off.
+static PROFILES: Mutex<Profiles> = Mutex::new(Profiles { configs: BTreeMap::new(), active: String::new() });
+
+// ── Types ──────────────────────────────────────────────────────────────────
+
+struct Profiles {
+    configs: BTreeMap<String, ModerationConfig>,
+    /// Profile for requests that name none (or an unknown one); "" = DEFAULT_PROFILE
+    active:  String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct ModerationConfig {
+    /// "openai" | "local" | "off"
+    pub mode:          String,
+    pub api_key:       Option<String>,
+    /// Only these OpenAI categories block ("violence", "self-harm", …);
+    /// empty = every category the endpoint flags
+    #[serde(default)]
+    pub categories:    Vec<String>,
+    /// Minimum OpenAI category score (0–1) that blocks; None = use the
+    /// endpoint's own `flagged` verdict
+    pub threshold:     Option<f32>,
+    /// Case-insensitive substrings (local mode, also checked in openai mode)
+    #[serde(default)]
+    pub blocked_terms: Vec<String>,
+    /// Case-insensitive regexes (local mode, also checked in openai mode)
+    #[serde(default)]
+    pub patterns:      Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ModerationRequest {
+    pub prompt:       String,
+    pub image_base64: Option<String>,
+    pub config:       ModerationConfig,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct FlaggedCategory {
+    /// OpenAI category name, or "blocked_term" / "pattern" for local rules
+    pub category: String,
+    /// Category score (openai) or 1.0 for a rule hit
+    pub score:    f32,
+    /// The matching term / pattern for local rules
+    pub detail:   Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct ModerationResult {
+    pub flagged:    bool,
+    pub categories: Vec<FlaggedCategory>,
+    /// Which backends ran ("local", "openai")
+    pub checked_by: Vec<String>,
+}
+
+// ── Tauri command ──────────────────────────────────────────────────────────
+
+#[tauri::command]
+pub async fn moderate_prompt(req: ModerationRequest) -> Result<ModerationResult, AppError> {
+    let image = image_prep::prepare(req.image_base64.clone()).await;
+    Ok(check(&req.prompt, image.as_deref(), &req.config).await?)
+}
+
+/// Store `config` for `profile` (default "default"; "off" disables it);
+/// `active` makes it the profile for requests that name none.
+#[tauri::command]
+pub fn set_moderation_config(
+    config:  ModerationConfig,
+    profile: Option<String>,
+    active:  Option<bool>,
+) -> Result<(), AppError> {
+    if !matches!(config.mode.as_str(), "off" | "local" | "openai") {
+        return Err(format!("Unknown moderation mode: {}", config.mode).into());
+    }
+    // Surface bad patterns now rather than on the next send
+    check_local("", &config)?;
+    let profile = profile
+        .map(|p| p.trim().to_string())
+        .filter(|p| !p.is_empty())
+        .unwrap_or_else(|| DEFAULT_PROFILE.to_string());
+    log::info!("moderation: outbound checks for profile '{}' set to '{}'", profile, config.mode);
+    let mut profiles = PROFILES.lock().map_err(|e| e.to_string())?;
+    if active.unwrap_or(false) {
+        profiles.active = profile.clone();
+    }
+    profiles.configs.insert(profile, config);
+    Ok(())
+}
+
+/// Refuse outbound `text` (everything the request sends) that the config of
+/// `profile` flags. `image` is already prepared (image_prep) by the caller.
+pub(crate) async fn enforce(profile: Option<&str>, text: &str, image: Option<&str>) -> Result<(), String> {
+    let cfg = PROFILES.lock().ok().and_then(|p| config_for(&p, profile).cloned());
+    let Some(cfg) = cfg else { return Ok(()) };
+    let result = check(text, image, &cfg).await?;
+    if !result.flagged {
+        return Ok(());
+    }
+    let categories: Vec<&str> = result.categories.iter().map(|c| c.category.as_str()).collect();
+    Err(format!("Prompt not allowed by the moderation settings ({})", categories.join(", ")))
+}
+
+/// The config `profile` is checked under: its own, else the active
+/// profile's. None when moderation is off for it.
+fn config_for<'a>(profiles: &'a Profiles, profile: Option<&str>) -> Option<&'a ModerationConfig> {
+    let active = if profiles.active.is_empty() { DEFAULT_PROFILE } else { profiles.active.as_str() };
+    profile
+        .and_then(|p| profiles.configs.get(p))
+        .or_else(|| profiles.configs.get(active))
+        .filter(|c| c.mode != "off")
+}
+
+async fn check(prompt: &str, image: Option<&str>, cfg: &ModerationConfig) -> Result<ModerationResult, String> {
+    let mut result = ModerationResult::default();
+    if cfg.mode == "off" {
+        return Ok(result);
+    }
+
+    if !cfg.blocked_terms.is_empty() || !cfg.patterns.is_empty() || cfg.mode == "local" {
+        result.categories.extend(check_local(prompt, cfg)?);
+        result.checked_by.push("local".into());
+    }
+
+    match cfg.mode.as_str() {
+        "local" => {}
+        "openai" => {
+            let api_key = cfg.api_key.as_deref().filter(|k| !k.trim().is_empty())
+                .ok_or("OpenAI API key required for moderation")?;
+            let json = call_openai(api_key, prompt, image).await?;
+            result.categories.extend(openai_categories(&json, cfg));
+            result.checked_by.push("openai".into());
+        }
+        other => return Err(format!("Unknown moderation mode: {}", other)),
+    }
+
+    result.flagged = !result.categories.is_empty();
+    if result.flagged {
+        log::info!("moderation: prompt flagged ({})",
+            result.categories.iter().map(|c| c.category.as_str()).collect::<Vec<_>>().join(", "));
+    }
+    Ok(result)
+}
+
+// ── Local rules ────────────────────────────────────────────────────────────
+
+fn check_local(prompt: &str, cfg: &ModerationConfig) -> Result<Vec<FlaggedCategory>, String> {
+    let lower = prompt.to_lowercase();
+    let mut hits: Vec<FlaggedCategory> = cfg.blocked_terms.iter()
+        .map(|t| t.trim())
+        .filter(|t| !t.is_empty() && lower.contains(&t.to_lowercase()))
+        .map(|t| FlaggedCategory { category: "blocked_term".into(), score: 1.0, detail: Some(t.to_string()) })
+        .collect();
+
+    for pattern in cfg.patterns.iter().filter(|p| !p.trim().is_empty()) {
+        let re = RegexBuilder::new(pattern)
+            .case_insensitive(true)
+            .build()
+            .map_err(|e| format!("Invalid moderation pattern '{}': {}", pattern, e))?;
+        if re.is_match(prompt) {
+            hits.push(FlaggedCategory { category: "pattern".into(), score: 1.0, detail: Some(pattern.clone()) });
+        }
+    }
+    Ok(hits)
+}
+
+// ── OpenAI moderation endpoint ─────────────────────────────────────────────
+
+async fn call_openai(api_key: &str, prompt: &str, image: Option<&str>) -> Result<Value, String> {
+    let mut input = vec![json!({ "type": "text", "text": prompt })];
+    if let Some(b64) = image {
+        input.push(json!({
+            "type": "image_url",
+            "image_url": { "url": format!("data:{};base64,{}", image_prep::mime_type(b64), b64) }
+        }));
+    }
+
+    let client = http::client(http::Timeout::Api).map_err(|e| e.to_string())?;
+    let request = client.post("https://api.openai.com/v1/moderations")
+        .bearer_auth(api_key)
+        .json(&json!({ "model": OPENAI_MODERATION_MODEL, "input": input }));
+    let (status, text) = audit::send("openai", request).await
+        .map_err(|e| format!("Moderation request failed: {}", e))?;
+    let json: Value = serde_json::from_str(&text).map_err(|e| e.to_string())?;
+    if !status.is_success() {
+        return Err(format!("OpenAI moderation {}: {}", status,
+            json["error"]["message"].as_str().unwrap_or("unknown error")));
+    }
+    Ok(json)
+}
+
+/// Categories of `results[0]` that block under `cfg`.
+fn openai_categories(json: &Value, cfg: &ModerationConfig) -> Vec<FlaggedCategory> {
+    let result = &json["results"][0];
+    let Some(scores) = result["category_scores"].as_object() else { return vec![] };
+
+    let mut out: Vec<FlaggedCategory> = scores.iter()
+        .filter(|(name, _)| cfg.categories.is_empty() || cfg.categories.iter().any(|c| c == *name))
+        .filter_map(|(name, score)| {
+            let score = score.as_f64()? as f32;
+            let hit = match cfg.threshold {
+                Some(t) => score >= t,
+                None    => result["categories"][name].as_bool().unwrap_or(false),
+            };
+            hit.then(|| FlaggedCategory { category: name.clone(), score, detail: None })
+        })
+        .collect();
+    out.sort_by(|a, b| b.score.total_cmp(&a.score));
+    out
+}
+
+// ── Unit tests ─────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn local_cfg(terms: &[&str], patterns: &[&str]) -> ModerationConfig {
+        ModerationConfig {
+            mode:          "local".into(),
+            blocked_terms: terms.iter().map(|s| s.to_string()).collect(),
+            patterns:      patterns.iter().map(|s| s.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_local_terms_and_patterns() {
+        let cfg = local_cfg(&["Project Falcon"], &[r"\bacct-[0-9]{6}\b"]);
+        let hits = check_local("status of project falcon for ACCT-123456?", &cfg).unwrap();
+        assert_eq!(hits.len(), 2);
+        assert_eq!(hits[0].detail.as_deref(), Some("Project Falcon"));
+        assert!(check_local("unrelated question", &cfg).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_invalid_pattern_is_an_error() {
+        assert!(check_local("x", &local_cfg(&[], &["(unclosed"])).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_enforce_uses_active_config() {
+        set_moderation_config(local_cfg(&["Project Falcon"], &[]), None, Some(true)).unwrap();
+        let blocked = enforce(None, "roadmap for project falcon", None).await;
+        let allowed = enforce(None, "unrelated question", None).await;
+        set_moderation_config(ModerationConfig { mode: "off".into(), ..Default::default() }, None, Some(true)).unwrap();
+
+        assert!(blocked.unwrap_err().contains("blocked_term"));
+        assert!(allowed.is_ok());
+        assert!(enforce(None, "roadmap for project falcon", None).await.is_ok());
+    }
+
+    #[test]
+    fn test_config_for_profile_falls_back_to_active() {
+        let off = ModerationConfig { mode: "off".into(), ..Default::default() };
+        let mut profiles = Profiles { configs: BTreeMap::new(), active: String::new() };
+        assert!(config_for(&profiles, None).is_none());
+
+        profiles.configs.insert("default".into(), local_cfg(&["a"], &[]));
+        profiles.configs.insert("kids".into(), local_cfg(&["b"], &[]));
+        profiles.configs.insert("admin".into(), off);
+        let terms = |p: Option<&str>| config_for(&profiles, p).map(|c| c.blocked_terms[0].clone());
+        assert_eq!(terms(None).as_deref(), Some("a"));
+        assert_eq!(terms(Some("kids")).as_deref(), Some("b"));
+        assert_eq!(terms(Some("unknown")).as_deref(), Some("a"));
+        assert_eq!(terms(Some("admin")), None);
+
+        profiles.active = "kids".into();
+        assert_eq!(config_for(&profiles, None).map(|c| c.blocked_terms[0].as_str()), Some("b"));
+    }
+
+    #[test]
+    fn test_openai_categories_filter_and_threshold() {
+        let json = json!({ "results": [{
+            "flagged": true,
+            "categories":      { "violence": true, "harassment": false, "self-harm": false },
+            "category_scores": { "violence": 0.91, "harassment": 0.40, "self-harm": 0.02 }
+        }]});
+
+        let verdict = openai_categories(&json, &ModerationConfig::default());
+        assert_eq!(verdict.iter().map(|c| c.category.as_str()).collect::<Vec<_>>(), ["violence"]);
+
+        let cfg = ModerationConfig { threshold: Some(0.3), ..Default::default() };
+        assert_eq!(openai_categories(&json, &cfg).len(), 2);
+
+        let cfg = ModerationConfig { categories: vec!["self-harm".into()], threshold: Some(0.3), ..Default::default() };
+        assert!(openai_categories(&json, &cfg).is_empty());
+    }
+}
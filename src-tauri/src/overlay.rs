@@ -1,4 +1,6 @@
 // overlay.rs — window transparency, click-through, cursor-area tracking
+use crate::error::AppError;
+use crate::{power, quick_actions};
 use std::sync::atomic::{AtomicBool, AtomicI32, Ordering};
 use tauri::{AppHandle, Manager, PhysicalPosition, PhysicalSize, Size, Position, Window};
 
@@ -33,7 +35,7 @@ static WINDOWED_MODE: AtomicBool = AtomicBool::new(false);
 
 /// Enable or disable click-through (cursor event passthrough).
 #[tauri::command]
-pub fn set_click_through(window: Window, enabled: bool) -> Result<(), String> {
+pub fn set_click_through(window: Window, enabled: bool) -> Result<(), AppError> {
     CLICK_THROUGH.store(enabled, Ordering::SeqCst);
     window
         .emit("click-through-changed", enabled)
@@ -52,7 +54,7 @@ pub fn set_click_through(window: Window, enabled: bool) -> Result<(), String> {
 /// Emits the event FIRST so the UI updates instantly, then calls the
 /// potentially slow set_ignore_cursor_events in a background thread.
 #[tauri::command]
-pub fn toggle_ghost_mode(window: Window) -> Result<bool, String> {
+pub fn toggle_ghost_mode(window: Window) -> Result<bool, AppError> {
     let next = !GHOST_MODE.load(Ordering::SeqCst);
     GHOST_MODE.store(next, Ordering::SeqCst);
     CLICK_THROUGH.store(next, Ordering::SeqCst);
@@ -75,7 +77,7 @@ pub fn toggle_ghost_mode(window: Window) -> Result<bool, String> {
 /// Using set instead of toggle avoids the race condition where both the JS keydown
 /// listener and the Rust global shortcut fire at the same time.
 #[tauri::command]
-pub fn set_ghost_mode(window: Window, value: bool) -> Result<(), String> {
+pub fn set_ghost_mode(window: Window, value: bool) -> Result<(), AppError> {
     GHOST_MODE.store(value, Ordering::SeqCst);
     CLICK_THROUGH.store(value, Ordering::SeqCst);
     window
@@ -112,7 +114,7 @@ pub fn get_ghost_mode_state() -> bool {
 /// Shrinks the overlay window to the panel strip so the OS dialog can open
 /// freely in the remaining screen space, then restores fullscreen after.
 #[tauri::command]
-pub fn set_dialog_open(window: Window, open: bool) -> Result<(), String> {
+pub fn set_dialog_open(window: Window, open: bool) -> Result<(), AppError> {
     DIALOG_OPEN.store(open, Ordering::SeqCst);
 
     if open {
@@ -167,10 +169,10 @@ pub fn set_dialog_open(window: Window, open: bool) -> Result<(), String> {
 
 /// Pin / unpin the window above all others.
 #[tauri::command]
-pub fn set_always_on_top(window: Window, on_top: bool) -> Result<(), String> {
+pub fn set_always_on_top(window: Window, on_top: bool) -> Result<(), AppError> {
     window
         .set_always_on_top(on_top)
-        .map_err(|e| e.to_string())
+        .map_err(|e| e.to_string().into())
 }
 
 /// Switch between overlay (fullscreen, transparent) and windowed (floating panel) modes.
@@ -179,7 +181,7 @@ pub fn set_always_on_top(window: Window, on_top: bool) -> Result<(), String> {
 ///   disable click-through and cursor tracker, set always-on-top according to `on_top`.
 /// - `windowed = false`: restore full-monitor size, re-enable cursor tracker, always-on-top.
 #[tauri::command]
-pub fn set_window_mode(window: Window, windowed: bool, on_top: Option<bool>) -> Result<(), String> {
+pub fn set_window_mode(window: Window, windowed: bool, on_top: Option<bool>) -> Result<(), AppError> {
     let monitor = window
         .current_monitor()
         .map_err(|e| e.to_string())?
@@ -188,6 +190,8 @@ pub fn set_window_mode(window: Window, windowed: bool, on_top: Option<bool>) ->
     let sh = monitor.size().height;
 
     WINDOWED_MODE.store(windowed, Ordering::SeqCst);
+    // A normal window gets a dock icon (and the dock menu) on macOS
+    quick_actions::set_dock_visible(windowed);
 
     if windowed {
         // ── Floating window mode ─────────────────────────────────────
@@ -262,7 +266,7 @@ pub fn toggle_window(app: &AppHandle) {
 pub fn spawn_cursor_tracker(window: Window) {
     std::thread::spawn(move || {
         loop {
-            std::thread::sleep(std::time::Duration::from_millis(40));
+            std::thread::sleep(power::scaled(std::time::Duration::from_millis(40)));
 
             if GHOST_MODE.load(Ordering::SeqCst) {
                 continue;
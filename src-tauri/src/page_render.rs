@@ -0,0 +1,163 @@
+// page_render.rs — page text from a real browser engine
+//
+// Single-page apps answer reqwest with an empty <div id="root"> shell; their
+// text only exists after JavaScript runs. fetch_url_rendered loads the URL in
+// a hidden webview window, waits until the page has stopped loading
+// resources for a moment (network idle), reads document.body.innerText and
+// closes the window.
+//
+// External pages get no Tauri IPC, so the injected script hands the text
+// back by navigating to a sentinel URL with the text base64-encoded in the
+// fragment; on_navigation catches that navigation and cancels it. Anything
+// that goes wrong (no display, timeout, empty page) falls back to the plain
+// fetcher in web_search.rs.
+//
+// Tauri commands exposed:
+//   fetch_url_rendered(url, max_chars?, timeout_secs?) → plain text
+
+use crate::error::AppError;
+use crate::web_search;
+use base64::{engine::general_purpose, Engine};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Host the injected script navigates to with the result; never resolved
+const SENTINEL_HOST: &str = "rendered-text.invalid";
+/// Quiet period without new resource loads that counts as network idle
+const IDLE_MS: u32 = 800;
+const DEFAULT_TIMEOUT_SECS: u64 = 20;
+/// Characters read from the page before truncation to `max_chars`
+const MAX_PAGE_CHARS: usize = 200_000;
+
+static NEXT_WINDOW: AtomicU32 = AtomicU32::new(1);
+
+/// Waits for network idle (or the deadline), then reports innerText.
+/// Placeholders: __IDLE__, __WAIT__ (ms), __LIMIT__ (chars), __SENTINEL__.
+const EXTRACT_SCRIPT: &str = r#"
+(function () {
+  if (window.top !== window) return;
+  var start = Date.now(), last = Date.now(), sent = false;
+  try {
+    new PerformanceObserver(function () { last = Date.now(); }).observe({ entryTypes: ['resource'] });
+  } catch (e) {}
+  function report() {
+    if (sent) return;
+    sent = true;
+    var text = ((document.body && document.body.innerText) || '').slice(0, __LIMIT__);
+    var b64 = btoa(unescape(encodeURIComponent(text)));
+    window.location.href = 'https://__SENTINEL__/#' + b64;
+  }
+  (function poll() {
+    var now = Date.now();
+    if (now - start > __WAIT__ || (document.readyState === 'complete' && now - last > __IDLE__)) report();
+    else setTimeout(poll, 200);
+  })();
+})();
+"#;
+
+// ── Tauri command ──────────────────────────────────────────────────────────
+
+/// Fetch the text of a JavaScript-rendered page; falls back to the plain
+/// fetcher (fetch_url_content without llms.txt) when rendering fails.
+#[tauri::command]
+pub async fn fetch_url_rendered(
+    app_handle:   tauri::AppHandle,
+    url:          String,
+    max_chars:    Option<usize>,
+    timeout_secs: Option<u64>,
+) -> Result<String, AppError> {
+    let max = max_chars.unwrap_or(4_000);
+    if !(url.starts_with("http://") || url.starts_with("https://")) {
+        return Err(format!("Not an http(s) URL: {}", url).into());
+    }
+    let timeout = Duration::from_secs(timeout_secs.unwrap_or(DEFAULT_TIMEOUT_SECS).clamp(3, 120));
+
+    match render(&app_handle, &url, timeout).await {
+        Ok(text) if !text.trim().is_empty() => Ok(web_search::truncate_text(text, max)),
+        Ok(_) => {
+            log::warn!("fetch_url_rendered: {} rendered no text — using the plain fetcher", url);
+            web_search::fetch_page_text(&url, max).await.map_err(AppError::from)
+        }
+        Err(e) => {
+            log::warn!("fetch_url_rendered: {} — using the plain fetcher", e);
+            web_search::fetch_page_text(&url, max).await.map_err(AppError::from)
+        }
+    }
+}
+
+// ── Hidden window ──────────────────────────────────────────────────────────
+
+async fn render(app: &tauri::AppHandle, url: &str, timeout: Duration) -> Result<String, String> {
+    let (tx, rx) = tokio::sync::oneshot::channel::<String>();
+    let tx = Mutex::new(Some(tx));
+    let label = format!("render-{}", NEXT_WINDOW.fetch_add(1, Ordering::Relaxed));
+    let script = extract_script(timeout);
+
+    let window = tauri::WindowBuilder::new(
+        app,
+        label,
+        tauri::WindowUrl::External(url.parse().map_err(|e| format!("Invalid URL {}: {}", url, e))?),
+    )
+    .visible(false)
+    .inner_size(1280.0, 900.0)
+    .initialization_script(&script)
+    .on_navigation(move |nav| {
+        if nav.host_str() != Some(SENTINEL_HOST) {
+            return true;
+        }
+        if let Some(tx) = tx.lock().ok().and_then(|mut t| t.take()) {
+            let _ = tx.send(decode_fragment(nav.fragment().unwrap_or("")));
+        }
+        false
+    })
+    .build()
+    .map_err(|e| format!("Cannot open a render window: {}", e))?;
+
+    // A little past the script's own deadline, for pages that never finish loading
+    let result = tokio::time::timeout(timeout + Duration::from_secs(5), rx).await;
+    let _ = window.close();
+    match result {
+        Ok(Ok(text)) => Ok(text),
+        Ok(Err(_)) => Err(format!("render window for {} closed early", url)),
+        Err(_) => Err(format!("{} did not render within {} s", url, timeout.as_secs())),
+    }
+}
+
+fn extract_script(timeout: Duration) -> String {
+    EXTRACT_SCRIPT
+        .replace("__IDLE__", &IDLE_MS.to_string())
+        .replace("__WAIT__", &timeout.as_millis().to_string())
+        .replace("__LIMIT__", &MAX_PAGE_CHARS.to_string())
+        .replace("__SENTINEL__", SENTINEL_HOST)
+}
+
+/// The page text carried in the sentinel URL's fragment (base64 of UTF-8).
+fn decode_fragment(fragment: &str) -> String {
+    general_purpose::STANDARD
+        .decode(fragment.trim())
+        .map(|bytes| String::from_utf8_lossy(&bytes).into_owned())
+        .unwrap_or_default()
+}
+
+// ── Unit tests ─────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_script_placeholders_filled() {
+        let script = extract_script(Duration::from_secs(7));
+        assert!(!script.contains("__"));
+        assert!(script.contains("now - start > 7000"));
+        assert!(script.contains("https://rendered-text.invalid/#"));
+    }
+
+    #[test]
+    fn test_decode_fragment() {
+        let b64 = general_purpose::STANDARD.encode("Привет, world".as_bytes());
+        assert_eq!(decode_fragment(&b64), "Привет, world");
+        assert_eq!(decode_fragment("%%%"), "");
+    }
+}
@@ -0,0 +1,386 @@
+// path_summary.rs — cached one-paragraph summaries of files and directories
+//
+// A cheap project overview layer: every source file gets a 1–2 sentence
+// summary from the provider's cheapest model, every directory a summary
+// built from its entries' summaries. Results are cached in
+// `<app_data>/path_summaries.json` keyed by path and invalidated by content
+// hash (FNV-1a of the file bytes; a directory hashes its children's hashes),
+// so re-running after a small edit only re-summarizes the changed file and
+// the directories above it.
+//
+// Tauri commands exposed:
+//   summarize_path    → SummaryTree
+//   get_path_overview → cached summaries under a path, no model calls
+
+use crate::ai_bridge::{self, AiRequest};
+use crate::error::AppError;
+use crate::{conversation, project_indexer};
+use futures_util::future::BoxFuture;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+const CACHE_FILE: &str          = "path_summaries.json";
+const MAX_FILE_BYTES: u64       = 200_000;
+/// Characters of a file shown to the summary model
+const FILE_EXCERPT_CHARS: usize = 6_000;
+/// Entries per directory that get their own summary; the rest are listed
+const MAX_ENTRIES_PER_DIR: usize = 60;
+const DEFAULT_DEPTH: usize      = 2;
+
+const FILE_INSTRUCTIONS: &str = "Summarize the given source file in one or two sentences: \
+what it is for and its main types, functions or exports. No preamble.";
+const DIR_INSTRUCTIONS: &str = "Summarize the given directory in two or three sentences \
+from the summaries of its entries: what part of the project it holds and how it is \
+organised. No preamble.";
+
+/// Serializes read-modify-write of the cache file across concurrent calls.
+static CACHE_LOCK: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+// ── Types ──────────────────────────────────────────────────────────────────
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SummarizePathRequest {
+    pub path:      String,
+    /// Directory levels below `path` that get LLM summaries (default 2);
+    /// deeper directories are described by their file listing only
+    pub depth:     Option<usize>,
+    pub provider:  String,
+    pub api_key:   String,
+    /// Used as-is for the local provider; cloud providers use their cheap model
+    pub model:     Option<String>,
+    pub local_url: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct PathSummary {
+    pub path:     String,
+    /// "file" | "dir"
+    pub kind:     String,
+    pub summary:  String,
+    pub hash:     String,
+    /// Served from the cache without a model call
+    pub cached:   bool,
+    pub children: Vec<PathSummary>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SummaryTree {
+    pub root:      PathSummary,
+    /// Indented Markdown outline of the tree, ready to drop into a prompt
+    pub overview:  String,
+    pub generated: usize,
+    pub reused:    usize,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+struct CacheEntry {
+    hash:       String,
+    summary:    String,
+    updated_at: u64,
+}
+
+/// Tree node collected before any model call.
+struct Node {
+    path:     PathBuf,
+    is_dir:   bool,
+    hash:     String,
+    children: Vec<Node>,
+    /// Set for directories past `depth`: a plain listing stands in for a summary
+    listing:  Option<String>,
+}
+
+// ── Tauri commands ─────────────────────────────────────────────────────────
+
+#[tauri::command]
+pub async fn summarize_path(app_handle: tauri::AppHandle, req: SummarizePathRequest) -> Result<SummaryTree, AppError> {
+    let root_path = PathBuf::from(&req.path);
+    if !root_path.exists() {
+        return Err(format!("'{}' does not exist", req.path).into());
+    }
+    let depth = req.depth.unwrap_or(DEFAULT_DEPTH);
+    let root = tokio::task::spawn_blocking(move || collect(&root_path, depth))
+        .await
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("'{}' has no source files to summarize", req.path))?;
+
+    let cache_path = cache_path(&app_handle)?;
+    let _guard = CACHE_LOCK.lock().await;
+    let mut cache = load_cache(&cache_path);
+    let mut stats = (0usize, 0usize);
+
+    let result = summarize_node(&root, &req, &mut cache, &mut stats).await;
+    // keep whatever was generated even if a later call failed
+    save_cache(&cache_path, &cache)?;
+    let summary = result?;
+
+    log::info!("summarize_path: {} ({} generated, {} from cache)", req.path, stats.0, stats.1);
+    Ok(SummaryTree { overview: outline(&summary, 0), root: summary, generated: stats.0, reused: stats.1 })
+}
+
+/// Cached summaries under `path` as a flat list, without any model call —
+/// the overview layer the context packer draws from. None when nothing
+/// under `path` has been summarized yet.
+#[tauri::command]
+pub fn get_path_overview(app_handle: tauri::AppHandle, path: String) -> Option<String> {
+    overview(&app_handle, &path)
+}
+
+pub(crate) fn overview(app: &tauri::AppHandle, path: &str) -> Option<String> {
+    let cache = load_cache(&cache_path(app).ok()?);
+    let root = path.trim_end_matches(['/', '\\']);
+    let mut entries: Vec<(&String, &CacheEntry)> = cache.iter()
+        .filter(|(p, _)| p.as_str() == root || p.starts_with(&format!("{}{}", root, std::path::MAIN_SEPARATOR)))
+        .collect();
+    if entries.is_empty() {
+        return None;
+    }
+    entries.sort_by(|a, b| a.0.cmp(b.0));
+    Some(entries.iter()
+        .map(|(p, e)| {
+            let rel = p.strip_prefix(root).unwrap_or(p).trim_start_matches(['/', '\\']);
+            format!("- {}: {}", if rel.is_empty() { "." } else { rel }, e.summary)
+        })
+        .collect::<Vec<_>>()
+        .join("\n"))
+}
+
+// ── Tree walk ──────────────────────────────────────────────────────────────
+
+fn collect(path: &Path, depth: usize) -> Option<Node> {
+    if path.is_file() {
+        if !project_indexer::has_allowed_extension(path) {
+            return None;
+        }
+        let meta = std::fs::metadata(path).ok()?;
+        if meta.len() > MAX_FILE_BYTES {
+            return None;
+        }
+        let bytes = std::fs::read(path).ok()?;
+        return Some(Node { path: path.to_path_buf(), is_dir: false, hash: fnv1a(&bytes), children: vec![], listing: None });
+    }
+
+    let mut entries: Vec<PathBuf> = std::fs::read_dir(path).ok()?
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| !(p.is_dir() && project_indexer::is_ignored_dir(p)))
+        .collect();
+    entries.sort();
+
+    if depth == 0 {
+        // Past the depth limit: describe by file names only, hashed by name
+        let names: Vec<String> = entries.iter()
+            .filter(|p| p.is_dir() || project_indexer::has_allowed_extension(p))
+            .filter_map(|p| p.file_name().map(|n| {
+                let n = n.to_string_lossy();
+                if p.is_dir() { format!("{}/", n) } else { n.to_string() }
+            }))
+            .collect();
+        if names.is_empty() {
+            return None;
+        }
+        let listing = format!("Contains {}", names.join(", "));
+        return Some(Node { path: path.to_path_buf(), is_dir: true, hash: fnv1a(listing.as_bytes()), children: vec![], listing: Some(listing) });
+    }
+
+    let children: Vec<Node> = entries.iter()
+        .filter_map(|p| collect(p, depth - 1))
+        .take(MAX_ENTRIES_PER_DIR)
+        .collect();
+    if children.is_empty() {
+        return None;
+    }
+    let mut key = String::new();
+    for c in &children {
+        key.push_str(&format!("{}:{}\n", c.path.file_name().unwrap_or_default().to_string_lossy(), c.hash));
+    }
+    Some(Node { path: path.to_path_buf(), is_dir: true, hash: fnv1a(key.as_bytes()), children, listing: None })
+}
+
+// ── Summaries ──────────────────────────────────────────────────────────────
+
+/// Post-order: children first, so a directory prompt sees their summaries.
+fn summarize_node<'a>(
+    node:  &'a Node,
+    req:   &'a SummarizePathRequest,
+    cache: &'a mut HashMap<String, CacheEntry>,
+    stats: &'a mut (usize, usize),
+) -> BoxFuture<'a, Result<PathSummary, String>> {
+    Box::pin(async move {
+        let mut children = Vec::with_capacity(node.children.len());
+        for child in &node.children {
+            children.push(summarize_node(child, req, cache, stats).await?);
+        }
+
+        let key = node.path.to_string_lossy().to_string();
+        let hit = cache.get(&key).filter(|e| e.hash == node.hash).map(|e| e.summary.clone());
+        let cached = hit.is_some();
+        let summary = match (hit, &node.listing) {
+            (Some(s), _)       => { stats.1 += 1; s }
+            (None, Some(list)) => list.clone(),
+            (None, None)       => {
+                let prompt = if node.is_dir { dir_prompt(&node.path, &children) } else { file_prompt(&node.path)? };
+                let instructions = if node.is_dir { DIR_INSTRUCTIONS } else { FILE_INSTRUCTIONS };
+                let s = generate(req, prompt, instructions).await?;
+                stats.0 += 1;
+                cache.insert(key, CacheEntry { hash: node.hash.clone(), summary: s.clone(), updated_at: now_secs() });
+                s
+            }
+        };
+
+        Ok(PathSummary {
+            path: node.path.to_string_lossy().to_string(),
+            kind: if node.is_dir { "dir" } else { "file" }.into(),
+            summary,
+            hash: node.hash.clone(),
+            cached,
+            children,
+        })
+    })
+}
+
+fn file_prompt(path: &Path) -> Result<String, String> {
+    let raw = std::fs::read(path).map_err(|e| format!("Cannot read {}: {}", path.display(), e))?;
+    let text = String::from_utf8_lossy(&raw);
+    let mut end = text.len().min(FILE_EXCERPT_CHARS);
+    while !text.is_char_boundary(end) { end -= 1; }
+    let cut = if end < text.len() { "\n[… truncated …]" } else { "" };
+    Ok(format!("File: {}\n\n{}{}", path.display(), &text[..end], cut))
+}
+
+fn dir_prompt(path: &Path, children: &[PathSummary]) -> String {
+    let entries = children.iter()
+        .map(|c| {
+            let name = Path::new(&c.path).file_name().unwrap_or_default().to_string_lossy().to_string();
+            let slash = if c.kind == "dir" { "/" } else { "" };
+            format!("- {}{}: {}", name, slash, c.summary)
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    format!("Directory: {}\n\nEntries:\n{}", path.display(), entries)
+}
+
+async fn generate(req: &SummarizePathRequest, prompt: String, instructions: &str) -> Result<String, String> {
+    let ai_req = AiRequest {
+        api_key:       req.api_key.clone(),
+        prompt,
+        system_prompt: Some(instructions.to_string()),
+        image_base64:  None,
+        context_files: None,
+        model:         conversation::cheap_model(&req.provider, req.model.clone()),
+        max_tokens:    Some(160),
+        allow_secrets: false,
+        profile:       None,
+    };
+    let resp = ai_bridge::complete(&req.provider, ai_req, req.local_url.as_deref()).await?;
+    let text = resp.text.split_whitespace().collect::<Vec<_>>().join(" ");
+    if text.is_empty() {
+        return Err("Summary model returned an empty response".into());
+    }
+    Ok(text)
+}
+
+/// Indented Markdown outline of the summary tree.
+fn outline(node: &PathSummary, level: usize) -> String {
+    let name = if level == 0 {
+        node.path.clone()
+    } else {
+        Path::new(&node.path).file_name().unwrap_or_default().to_string_lossy().to_string()
+    };
+    let slash = if node.kind == "dir" && level > 0 { "/" } else { "" };
+    let mut out = format!("{}- **{}{}** — {}\n", "  ".repeat(level), name, slash, node.summary);
+    for c in &node.children {
+        out.push_str(&outline(c, level + 1));
+    }
+    out
+}
+
+// ── Cache ──────────────────────────────────────────────────────────────────
+
+fn cache_path(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+    app.path_resolver()
+        .app_data_dir()
+        .ok_or_else(|| "Cannot resolve app data directory".to_string())
+        .map(|p| p.join(CACHE_FILE))
+}
+
+fn load_cache(path: &Path) -> HashMap<String, CacheEntry> {
+    std::fs::read_to_string(path)
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
+fn save_cache(path: &Path, cache: &HashMap<String, CacheEntry>) -> Result<(), String> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    }
+    let json = serde_json::to_string(cache).map_err(|e| e.to_string())?;
+    let tmp = path.with_extension("json.tmp");
+    std::fs::write(&tmp, json).map_err(|e| format!("Failed to write summary cache: {}", e))?;
+    std::fs::rename(&tmp, path).map_err(|e| e.to_string())
+}
+
+fn now_secs() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// 64-bit FNV-1a — stable across builds, unlike std's DefaultHasher.
+pub(crate) fn fnv1a(bytes: &[u8]) -> String {
+    let mut h: u64 = 0xcbf2_9ce4_8422_2325;
+    for b in bytes {
+        h ^= *b as u64;
+        h = h.wrapping_mul(0x0100_0000_01b3);
+    }
+    format!("{:016x}", h)
+}
+
+// ── Unit tests ─────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fnv1a_known_values() {
+        assert_eq!(fnv1a(b""), "cbf29ce484222325");
+        assert_eq!(fnv1a(b"a"), "af63dc4c8601ec8c");
+    }
+
+    #[test]
+    fn test_collect_hashes_track_content() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("src/deep/deeper")).unwrap();
+        std::fs::create_dir_all(dir.path().join("node_modules")).unwrap();
+        std::fs::write(dir.path().join("src/main.rs"), "fn main() {}").unwrap();
+        std::fs::write(dir.path().join("src/deep/deeper/x.rs"), "// x").unwrap();
+        std::fs::write(dir.path().join("node_modules/y.js"), "y").unwrap();
+        std::fs::write(dir.path().join("image.png"), [0u8; 4]).unwrap();
+
+        let tree = collect(dir.path(), 2).unwrap();
+        assert_eq!(tree.children.len(), 1, "only src/ survives the filters");
+        let src = &tree.children[0];
+        let deep = src.children.iter().find(|c| c.is_dir).unwrap();
+        assert_eq!(deep.listing.as_deref(), Some("Contains deeper/"));
+
+        let before = tree.hash.clone();
+        std::fs::write(dir.path().join("src/main.rs"), "fn main() { run() }").unwrap();
+        assert_ne!(collect(dir.path(), 2).unwrap().hash, before);
+    }
+
+    #[test]
+    fn test_outline_indents_children() {
+        let leaf = PathSummary {
+            path: "/p/src/main.rs".into(), kind: "file".into(), summary: "Entry point.".into(),
+            hash: String::new(), cached: false, children: vec![],
+        };
+        let root = PathSummary {
+            path: "/p/src".into(), kind: "dir".into(), summary: "Sources.".into(),
+            hash: String::new(), cached: false, children: vec![leaf],
+        };
+        assert_eq!(outline(&root, 0), "- **/p/src** — Sources.\n  - **main.rs** — Entry point.\n");
+    }
+}
@@ -0,0 +1,255 @@
+// power.rs — battery / power-saver state
+//
+// Read natively on Windows (GetSystemPowerStatus), via `pmset` on macOS and
+// /sys/class/power_supply + power-profiles-daemon on Linux. While the machine
+// runs on battery or in power-saver mode the background pollers (cursor
+// tracker, layout / a11y watchers, checkpoints, watchdog) sleep `SLOW_FACTOR`
+// times longer via `scaled`, screen suggestions pause, and the UI asks before
+// starting a local GPU job.
+//
+// Tauri commands exposed:
+//   get_power_state → PowerState
+//
+// Events emitted:
+//   power-changed   → PowerState
+
+use serde::Serialize;
+use std::sync::Mutex;
+use std::time::Duration;
+
+const POLL_SECS: u64   = 30;
+/// Polling intervals are multiplied by this while saving power
+const SLOW_FACTOR: u32 = 4;
+
+static CURRENT: Mutex<Option<PowerState>> = Mutex::new(None);
+
+#[derive(Debug, Serialize, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PowerState {
+    /// Running from the battery (no AC adapter)
+    pub on_battery:      bool,
+    /// OS battery-saver / low-power mode is on
+    pub power_saver:     bool,
+    /// None on desktops without a battery
+    pub battery_percent: Option<u8>,
+    /// on_battery || power_saver — background work is throttled
+    pub saving:          bool,
+}
+
+impl PowerState {
+    fn new(on_battery: bool, power_saver: bool, battery_percent: Option<u8>) -> Self {
+        Self { on_battery, power_saver, battery_percent, saving: on_battery || power_saver }
+    }
+}
+
+/// Latest known state (reads it once if the watcher hasn't yet).
+pub(crate) fn current() -> PowerState {
+    if let Some(p) = CURRENT.lock().ok().and_then(|c| *c) {
+        return p;
+    }
+    let state = platform::read();
+    if let Ok(mut c) = CURRENT.lock() {
+        *c = Some(state);
+    }
+    state
+}
+
+/// `base` polling interval, stretched while saving power.
+pub(crate) fn scaled(base: Duration) -> Duration {
+    if current().saving { base * SLOW_FACTOR } else { base }
+}
+
+#[tauri::command]
+pub fn get_power_state() -> PowerState {
+    current()
+}
+
+/// Poll the power source and emit `power-changed` when it changes.
+pub fn spawn_watcher(app: tauri::AppHandle) {
+    use tauri::Manager;
+
+    std::thread::spawn(move || {
+        let mut last = current();
+        loop {
+            std::thread::sleep(Duration::from_secs(POLL_SECS));
+            let state = platform::read();
+            // Percent alone changes constantly; only report it with a mode switch
+            // or every 5 %
+            let moved = match (state.battery_percent, last.battery_percent) {
+                (Some(a), Some(b)) => a.abs_diff(b) >= 5,
+                (a, b) => a != b,
+            };
+            if let Ok(mut c) = CURRENT.lock() {
+                *c = Some(state);
+            }
+            if state.saving != last.saving || state.on_battery != last.on_battery
+                || state.power_saver != last.power_saver || moved
+            {
+                log::info!("power: {:?}", state);
+                let _ = app.emit_all("power-changed", state);
+                last = state;
+            }
+        }
+    });
+}
+
+// ═══════════════════════════════════════════════════════════════════════
+// macOS — pmset
+// ═══════════════════════════════════════════════════════════════════════
+#[cfg(target_os = "macos")]
+mod platform {
+    use super::PowerState;
+    use std::process::Command;
+
+    fn run(args: &[&str]) -> Option<String> {
+        let out = Command::new("pmset").args(args).output().ok()?;
+        out.status.success().then(|| String::from_utf8_lossy(&out.stdout).to_string())
+    }
+
+    pub fn read() -> PowerState {
+        let (on_battery, percent) = run(&["-g", "batt"]).map(|s| super::parse_pmset_batt(&s)).unwrap_or((false, None));
+        let power_saver = run(&["-g"])
+            .map(|s| s.lines().any(|l| {
+                let mut parts = l.split_whitespace();
+                parts.next() == Some("lowpowermode") && parts.next() == Some("1")
+            }))
+            .unwrap_or(false);
+        PowerState::new(on_battery, power_saver, percent)
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════
+// Windows — GetSystemPowerStatus
+// ═══════════════════════════════════════════════════════════════════════
+#[cfg(target_os = "windows")]
+mod platform {
+    use super::PowerState;
+    use windows::Win32::System::Power::{GetSystemPowerStatus, SYSTEM_POWER_STATUS};
+
+    pub fn read() -> PowerState {
+        let mut status = SYSTEM_POWER_STATUS::default();
+        if unsafe { GetSystemPowerStatus(&mut status) }.ok().is_err() {
+            return PowerState::default();
+        }
+        // ACLineStatus: 0 offline, 1 online, 255 unknown; 128 in BatteryFlag = no battery
+        let has_battery = status.BatteryFlag != 128 && status.BatteryFlag != 255;
+        PowerState::new(
+            status.ACLineStatus == 0,
+            status.SystemStatusFlag == 1,
+            (has_battery && status.BatteryLifePercent <= 100).then_some(status.BatteryLifePercent),
+        )
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════
+// Linux — /sys/class/power_supply, powerprofilesctl
+// ═══════════════════════════════════════════════════════════════════════
+#[cfg(all(not(target_os = "macos"), not(target_os = "windows")))]
+mod platform {
+    use super::PowerState;
+    use std::path::Path;
+    use std::process::Command;
+
+    fn attr(dir: &Path, name: &str) -> Option<String> {
+        std::fs::read_to_string(dir.join(name)).ok().map(|s| s.trim().to_string())
+    }
+
+    pub fn read() -> PowerState {
+        let supplies: Vec<super::Supply> = std::fs::read_dir("/sys/class/power_supply")
+            .map(|rd| rd.flatten().map(|e| {
+                let dir = e.path();
+                super::Supply {
+                    kind:     attr(&dir, "type").unwrap_or_default(),
+                    online:   attr(&dir, "online").map(|v| v == "1"),
+                    status:   attr(&dir, "status").unwrap_or_default(),
+                    capacity: attr(&dir, "capacity").and_then(|v| v.parse().ok()),
+                    scope:    attr(&dir, "scope").unwrap_or_default(),
+                }
+            }).collect())
+            .unwrap_or_default();
+        let (on_battery, percent) = super::battery_from_supplies(&supplies);
+
+        let power_saver = Command::new("powerprofilesctl").arg("get").output().ok()
+            .filter(|o| o.status.success())
+            .map(|o| String::from_utf8_lossy(&o.stdout).trim() == "power-saver")
+            .or_else(|| {
+                std::fs::read_to_string("/sys/firmware/acpi/platform_profile").ok()
+                    .map(|p| matches!(p.trim(), "low-power" | "quiet"))
+            })
+            .unwrap_or(false);
+        PowerState::new(on_battery, power_saver, percent)
+    }
+}
+
+/// One entry of /sys/class/power_supply.
+#[cfg_attr(any(target_os = "macos", target_os = "windows"), allow(dead_code))]
+struct Supply {
+    /// "Mains" | "Battery" | "USB" | …
+    kind:     String,
+    online:   Option<bool>,
+    /// "Charging" | "Discharging" | "Full" | "Not charging"
+    status:   String,
+    capacity: Option<u8>,
+    /// "Device" for mice / headsets — not the system battery
+    scope:    String,
+}
+
+/// On battery when no mains adapter is online and a system battery is
+/// discharging; percent is the first system battery's capacity.
+#[cfg_attr(any(target_os = "macos", target_os = "windows"), allow(dead_code))]
+fn battery_from_supplies(supplies: &[Supply]) -> (bool, Option<u8>) {
+    let batteries: Vec<&Supply> = supplies.iter()
+        .filter(|s| s.kind == "Battery" && s.scope != "Device")
+        .collect();
+    let mains_online = supplies.iter().any(|s| s.kind != "Battery" && s.online == Some(true));
+    let discharging = batteries.iter().any(|b| b.status == "Discharging");
+    (discharging && !mains_online, batteries.iter().find_map(|b| b.capacity))
+}
+
+/// `pmset -g batt`: "Now drawing from 'Battery Power'" … "\t85%; discharging".
+#[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+fn parse_pmset_batt(text: &str) -> (bool, Option<u8>) {
+    let on_battery = text.contains("'Battery Power'");
+    let percent = text.split_whitespace()
+        .find_map(|w| w.trim_end_matches(';').strip_suffix('%')?.parse().ok());
+    (on_battery, percent)
+}
+
+// ── Unit tests ─────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn supply(kind: &str, online: Option<bool>, status: &str, capacity: Option<u8>) -> Supply {
+        Supply { kind: kind.into(), online, status: status.into(), capacity, scope: String::new() }
+    }
+
+    #[test]
+    fn test_battery_from_supplies() {
+        let on_ac = [supply("Mains", Some(true), "", None), supply("Battery", None, "Charging", Some(60))];
+        assert_eq!(battery_from_supplies(&on_ac), (false, Some(60)));
+
+        let unplugged = [supply("Mains", Some(false), "", None), supply("Battery", None, "Discharging", Some(42))];
+        assert_eq!(battery_from_supplies(&unplugged), (true, Some(42)));
+
+        let mut mouse = supply("Battery", None, "Discharging", Some(10));
+        mouse.scope = "Device".into();
+        assert_eq!(battery_from_supplies(&[mouse]), (false, None));
+        assert_eq!(battery_from_supplies(&[]), (false, None));
+    }
+
+    #[test]
+    fn test_parse_pmset_batt() {
+        let batt = "Now drawing from 'Battery Power'\n -InternalBattery-0 (id=1234)\t85%; discharging; 4:10 remaining present: true\n";
+        assert_eq!(parse_pmset_batt(batt), (true, Some(85)));
+        let ac = "Now drawing from 'AC Power'\n -InternalBattery-0 (id=1234)\t100%; charged; 0:00 remaining\n";
+        assert_eq!(parse_pmset_batt(ac), (false, Some(100)));
+    }
+
+    #[test]
+    fn test_saving_follows_either_flag() {
+        assert!(PowerState::new(true, false, None).saving);
+        assert!(PowerState::new(false, true, None).saving);
+        assert!(!PowerState::new(false, false, Some(100)).saving);
+    }
+}
@@ -1,12 +1,22 @@
 // project_indexer.rs — walk a local directory and collect source files for RAG context
+//
+// Besides the fixed IGNORED_DIRS list, the walk honors the project's own
+// .gitignore files (nested ones included) and .git/info/exclude, so build
+// output and secrets the project already ignores never reach the prompt.
+use crate::error::AppError;
 use serde::{Deserialize, Serialize};
-use std::path::Path;
-use walkdir::WalkDir;
+use std::io::{BufRead, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use ignore::WalkBuilder;
 
 /// Hard limits to keep the LLM context window reasonable
 const MAX_FILE_SIZE_BYTES: u64  = 100_000; // 100 KB per file
 const MAX_FILE_CONTENT_CHARS: usize = 8_000;  // chars sent per file
-const MAX_TOTAL_FILES: usize     = 250;
+pub(crate) const MAX_TOTAL_FILES: usize = 250;
+/// Cap on lines returned by one read_file_lines call
+const MAX_LINES_PER_READ: usize  = 2_000;
+/// Bytes per row in hex dumps of binary windows
+const HEX_ROW_BYTES: usize       = 16;
 
 static ALLOWED_EXTENSIONS: &[&str] = &[
     // Systems / compiled
@@ -38,6 +48,15 @@ pub struct IndexedFile {
     pub truncated:  bool,
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FileLines {
+    pub content:     String,
+    /// 1-based, inclusive range actually returned
+    pub start_line:  usize,
+    pub end_line:    usize,
+    pub total_lines: usize,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct IndexResult {
     pub files:         Vec<IndexedFile>,
@@ -50,81 +69,31 @@ pub struct IndexResult {
 
 /// Recursively walk `dir_path` and return readable source files.
 #[tauri::command]
-pub async fn index_directory(dir_path: String) -> Result<IndexResult, String> {
+pub async fn index_directory(dir_path: String) -> Result<IndexResult, AppError> {
     let root = Path::new(&dir_path);
     if !root.exists() || !root.is_dir() {
-        return Err(format!("'{}' is not a valid directory", dir_path));
+        return Err(format!("'{}' is not a valid directory", dir_path).into());
     }
 
     let mut files:   Vec<IndexedFile> = Vec::new();
     let mut skipped: usize             = 0;
 
-    'walk: for entry in WalkDir::new(root)
-        .follow_links(false)
-        .into_iter()
-        .filter_entry(|e| e.depth() == 0 || !is_ignored_dir(e.path()))
-        .filter_map(|e| e.ok())
-    {
-        if !entry.file_type().is_file() {
-            continue;
-        }
-
+    'walk: for path in source_files(root, None) {
         // Enforce file count limit
         if files.len() >= MAX_TOTAL_FILES {
             skipped += 1;
             continue 'walk;
         }
 
-        let path = entry.path();
-        let ext  = path
-            .extension()
-            .and_then(|e| e.to_str())
-            .unwrap_or("")
-            .to_ascii_lowercase();
-
-        if !ALLOWED_EXTENSIONS.contains(&ext.as_str()) {
-            skipped += 1;
-            continue;
-        }
-
-        let meta = match entry.metadata() {
-            Ok(m)  => m,
-            Err(_) => { skipped += 1; continue; }
-        };
-
-        if meta.len() > MAX_FILE_SIZE_BYTES {
-            skipped += 1;
-            continue;
-        }
-
-        let raw = match std::fs::read_to_string(path) {
-            Ok(s)  => s,
-            Err(_) => { skipped += 1; continue; }
-        };
-
-        let truncated = raw.len() > MAX_FILE_CONTENT_CHARS;
-        let content   = if truncated {
-            format!(
-                "{}\n\n[… truncated at {} chars …]",
-                &raw[..MAX_FILE_CONTENT_CHARS],
-                MAX_FILE_CONTENT_CHARS
-            )
-        } else {
-            raw
-        };
-
         let relative = path
             .strip_prefix(root)
             .map(|p| p.to_string_lossy().replace('\\', "/"))
             .unwrap_or_else(|_| path.to_string_lossy().to_string());
 
-        files.push(IndexedFile {
-            path: relative,
-            content,
-            size_bytes: meta.len(),
-            extension: ext,
-            truncated,
-        });
+        match index_file(&path, relative) {
+            Some(file) => files.push(file),
+            None => skipped += 1,
+        }
     }
 
     let total = files.len();
@@ -141,28 +110,165 @@ pub async fn index_directory(dir_path: String) -> Result<IndexResult, String> {
     })
 }
 
+/// Files under `dir` the indexer walks: IGNORED_DIRS and .gitignore'd
+/// paths pruned, symlinks not followed. Extensions are checked by the caller.
+pub(crate) fn source_files(dir: &Path, max_depth: Option<usize>) -> impl Iterator<Item = PathBuf> {
+    walk(dir, max_depth)
+        .filter(|e| e.file_type().is_some_and(|t| t.is_file()))
+        .map(|e| e.into_path())
+}
+
+/// Directories the same walk enters, `dir` itself first.
+pub(crate) fn source_dirs(dir: &Path, max_depth: Option<usize>) -> impl Iterator<Item = PathBuf> {
+    walk(dir, max_depth)
+        .filter(|e| e.file_type().is_some_and(|t| t.is_dir()))
+        .map(|e| e.into_path())
+}
+
+fn walk(dir: &Path, max_depth: Option<usize>) -> impl Iterator<Item = ignore::DirEntry> {
+    WalkBuilder::new(dir)
+        .follow_links(false)
+        .max_depth(max_depth)
+        // Hidden directories are covered by IGNORED_DIRS; dotfiles such as
+        // .env.example stay eligible unless .gitignore says otherwise
+        .hidden(false)
+        .git_ignore(true)
+        .git_exclude(true)
+        .git_global(false)
+        // Honor a .gitignore even when the folder is not a git checkout
+        .require_git(false)
+        .filter_entry(|e| e.depth() == 0 || !is_ignored_dir(e.path()))
+        .build()
+        .filter_map(|e| e.ok())
+}
+
+/// A readable source file as an index entry listed under `display`;
+/// None for other extensions, oversized or non-UTF-8 files.
+pub(crate) fn index_file(path: &Path, display: String) -> Option<IndexedFile> {
+    let ext = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or("")
+        .to_ascii_lowercase();
+    if !has_allowed_extension(path) {
+        return None;
+    }
+    let meta = std::fs::metadata(path).ok()?;
+    if meta.len() > MAX_FILE_SIZE_BYTES {
+        return None;
+    }
+    let raw = std::fs::read_to_string(path).ok()?;
+
+    let truncated = raw.len() > MAX_FILE_CONTENT_CHARS;
+    let content   = if truncated {
+        format!(
+            "{}\n\n[… truncated at {} chars …]",
+            &raw[..MAX_FILE_CONTENT_CHARS],
+            MAX_FILE_CONTENT_CHARS
+        )
+    } else {
+        raw
+    };
+
+    Some(IndexedFile {
+        path: display,
+        content,
+        size_bytes: meta.len(),
+        extension: ext,
+        truncated,
+    })
+}
+
 /// Read a single file (up to MAX_FILE_SIZE_BYTES).
+/// With `offset` / `length` only that byte window is read, so regions of
+/// larger files can be inspected; binary windows come back as a hex dump.
 #[tauri::command]
-pub async fn read_file_content(file_path: String) -> Result<String, String> {
+pub async fn read_file_content(
+    file_path: String,
+    offset:    Option<u64>,
+    length:    Option<u64>,
+) -> Result<String, AppError> {
     let path = Path::new(&file_path);
     if !path.exists() {
-        return Err(format!("File not found: {}", file_path));
+        return Err(format!("File not found: {}", file_path).into());
     }
     let meta = std::fs::metadata(path).map_err(|e| e.to_string())?;
+
+    if offset.is_some() || length.is_some() {
+        let offset = offset.unwrap_or(0);
+        let length = length.unwrap_or(MAX_FILE_SIZE_BYTES).min(MAX_FILE_SIZE_BYTES);
+        return read_window(path, offset, length).map_err(AppError::from);
+    }
+
     if meta.len() > MAX_FILE_SIZE_BYTES {
         return Err(format!(
-            "File exceeds limit ({} KB). Max is {} KB.",
+            "File exceeds limit ({} KB). Max is {} KB — pass offset/length or use read_file_lines to read part of it.",
             meta.len() / 1_000,
             MAX_FILE_SIZE_BYTES / 1_000
-        ));
+        ).into());
     }
-    std::fs::read_to_string(path).map_err(|e| e.to_string())
+    Ok(std::fs::read_to_string(path)?)
+}
+
+/// Read lines `start..=end` (1-based) of a file of any size.
+#[tauri::command]
+pub async fn read_file_lines(file_path: String, start: usize, end: Option<usize>) -> Result<FileLines, AppError> {
+    let start = start.max(1);
+    if end.is_some_and(|e| e < start) {
+        return Err(format!("Invalid line range {}..{}", start, end.unwrap_or(0)).into());
+    }
+    let file = std::fs::File::open(&file_path)
+        .map_err(|e| format!("Cannot open '{}': {}", file_path, e))?;
+    let end = end.unwrap_or(usize::MAX).min(start.saturating_add(MAX_LINES_PER_READ - 1));
+
+    let mut content = String::new();
+    let mut last = start - 1;
+    let mut total = 0;
+    // split on b'\n' so invalid UTF-8 in one line does not abort the read
+    for line in std::io::BufReader::new(file).split(b'\n') {
+        let line = line.map_err(|e| e.to_string())?;
+        total += 1;
+        if total < start || total > end || content.len() >= MAX_FILE_SIZE_BYTES as usize {
+            continue;
+        }
+        content.push_str(String::from_utf8_lossy(&line).trim_end_matches('\r'));
+        content.push('\n');
+        last = total;
+    }
+    if start > total {
+        return Err(format!("Line {} is past the end of the file ({} lines)", start, total).into());
+    }
+    Ok(FileLines { content, start_line: start, end_line: last, total_lines: total })
+}
+
+fn read_window(path: &Path, offset: u64, length: u64) -> Result<String, String> {
+    let mut file = std::fs::File::open(path).map_err(|e| e.to_string())?;
+    file.seek(SeekFrom::Start(offset)).map_err(|e| e.to_string())?;
+    let mut buf = Vec::with_capacity(length as usize);
+    file.take(length).read_to_end(&mut buf).map_err(|e| e.to_string())?;
+
+    if buf.contains(&0) {
+        return Ok(hex_dump(&buf, offset));
+    }
+    Ok(String::from_utf8_lossy(&buf).into_owned())
+}
+
+/// `xxd`-style dump: offset, hex bytes, printable ASCII.
+fn hex_dump(bytes: &[u8], base: u64) -> String {
+    bytes.chunks(HEX_ROW_BYTES).enumerate().map(|(i, row)| {
+        let hex: Vec<String> = row.iter().map(|b| format!("{:02x}", b)).collect();
+        let ascii: String = row.iter()
+            .map(|&b| if b.is_ascii_graphic() || b == b' ' { b as char } else { '.' })
+            .collect();
+        format!("{:08x}  {:<width$}  {}\n", base + (i * HEX_ROW_BYTES) as u64, hex.join(" "), ascii,
+            width = HEX_ROW_BYTES * 3 - 1)
+    }).collect()
 }
 
 /// Write (overwrite or create) a file with the given content.
 /// Parent directories are created automatically.
 #[tauri::command]
-pub async fn write_file(file_path: String, content: String) -> Result<(), String> {
+pub async fn write_file(file_path: String, content: String) -> Result<(), AppError> {
     let path = Path::new(&file_path);
 
     // Safety: refuse to write outside any reasonable filesystem path
@@ -186,19 +292,19 @@ pub async fn write_file(file_path: String, content: String) -> Result<(), String
 /// Delete a single file from disk.
 /// Returns an error if the path does not exist or is a directory.
 #[tauri::command]
-pub async fn delete_file(file_path: String) -> Result<(), String> {
+pub async fn delete_file(file_path: String) -> Result<(), AppError> {
     if file_path.is_empty() {
         return Err("file_path must not be empty".into());
     }
     let path = Path::new(&file_path);
     if !path.exists() {
-        return Err(format!("File not found: {}", file_path));
+        return Err(format!("File not found: {}", file_path).into());
     }
     if path.is_dir() {
         return Err(format!(
             "'{}' is a directory — use delete_directory to remove directories",
             file_path
-        ));
+        ).into());
     }
     std::fs::remove_file(path)
         .map_err(|e| format!("Failed to delete '{}': {}", file_path, e))?;
@@ -214,23 +320,23 @@ pub async fn patch_file(
     file_path: String,
     old_text:  String,
     new_text:  String,
-) -> Result<(), String> {
+) -> Result<(), AppError> {
     let path = Path::new(&file_path);
     if !path.exists() {
-        return Err(format!("File not found: {}", file_path));
+        return Err(format!("File not found: {}", file_path).into());
     }
     let original = std::fs::read_to_string(path)
         .map_err(|e| format!("Failed to read '{}': {}", file_path, e))?;
 
     let count = original.matches(old_text.as_str()).count();
     if count == 0 {
-        return Err(format!("old_text not found in '{}'", file_path));
+        return Err(format!("old_text not found in '{}'", file_path).into());
     }
     if count > 1 {
         return Err(format!(
             "old_text matches {} times in '{}' — be more specific",
             count, file_path
-        ));
+        ).into());
     }
 
     let patched = original.replacen(old_text.as_str(), new_text.as_str(), 1);
@@ -244,10 +350,10 @@ pub async fn patch_file(
 /// List immediate children of a directory (shallow, one level).
 /// Returns entries with name, kind ("file"|"dir"), and size.
 #[tauri::command]
-pub async fn list_dir(dir_path: String) -> Result<Vec<DirEntry>, String> {
+pub async fn list_dir(dir_path: String) -> Result<Vec<DirEntry>, AppError> {
     let path = Path::new(&dir_path);
     if !path.exists() || !path.is_dir() {
-        return Err(format!("'{}' is not a valid directory", dir_path));
+        return Err(format!("'{}' is not a valid directory", dir_path).into());
     }
 
     let mut entries = Vec::new();
@@ -285,16 +391,16 @@ pub async fn list_dir(dir_path: String) -> Result<Vec<DirEntry>, String> {
 
 /// Create an empty directory (recursive).
 #[tauri::command]
-pub async fn create_dir_cmd(dir_path: String) -> Result<(), String> {
+pub async fn create_dir_cmd(dir_path: String) -> Result<(), AppError> {
     std::fs::create_dir_all(&dir_path)
-        .map_err(|e| format!("Failed to create directory '{}': {}", dir_path, e))
+        .map_err(|e| format!("Failed to create directory '{}': {}", dir_path, e).into())
 }
 
 /// Rename or move a file/directory.
 #[tauri::command]
-pub async fn rename_path(from_path: String, to_path: String) -> Result<(), String> {
+pub async fn rename_path(from_path: String, to_path: String) -> Result<(), AppError> {
     std::fs::rename(&from_path, &to_path)
-        .map_err(|e| format!("Failed to rename '{}' → '{}': {}", from_path, to_path, e))
+        .map_err(|e| format!("Failed to rename '{}' → '{}': {}", from_path, to_path, e).into())
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -307,7 +413,15 @@ pub struct DirEntry {
 
 // ── Helpers ──────────────────────────────────────────────────────────────
 
-fn is_ignored_dir(path: &Path) -> bool {
+/// Whether `path` has one of the source / doc extensions the indexer reads.
+pub(crate) fn has_allowed_extension(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .map(|e| ALLOWED_EXTENSIONS.contains(&e.to_ascii_lowercase().as_str()))
+        .unwrap_or(false)
+}
+
+pub(crate) fn is_ignored_dir(path: &Path) -> bool {
     path.file_name()
         .and_then(|n| n.to_str())
         .map(|name| {
@@ -363,6 +477,25 @@ mod tests {
         assert!(result.skipped_files >= 2); // big.rs + image.png
     }
 
+    #[tokio::test]
+    async fn test_index_directory_honors_gitignore() {
+        let tmp = make_temp_project();
+        let root = tmp.path();
+        std::fs::write(root.join(".gitignore"), "generated/\n*.secret.json\n").unwrap();
+        std::fs::create_dir_all(root.join("generated")).unwrap();
+        std::fs::write(root.join("generated").join("schema.rs"), "// generated").unwrap();
+        std::fs::write(root.join("api.secret.json"), "{}").unwrap();
+        std::fs::create_dir_all(root.join(".git").join("info")).unwrap();
+        std::fs::write(root.join(".git").join("info").join("exclude"), "notes.md\n").unwrap();
+        std::fs::write(root.join("notes.md"), "# scratch").unwrap();
+        std::fs::write(root.join("README.md"), "# Project").unwrap();
+
+        let result = index_directory(root.to_string_lossy().to_string()).await.unwrap();
+        let mut paths: Vec<&str> = result.files.iter().map(|f| f.path.as_str()).collect();
+        paths.sort_unstable();
+        assert_eq!(paths, ["README.md", "src/main.rs"]);
+    }
+
     #[tokio::test]
     async fn test_index_invalid_path() {
         let result = index_directory("/nonexistent/path/xyz".into()).await;
@@ -375,7 +508,7 @@ mod tests {
         let file = tmp.path().join("hello.ts");
         std::fs::write(&file, "export const x = 42;").unwrap();
 
-        let content = read_file_content(file.to_string_lossy().to_string())
+        let content = read_file_content(file.to_string_lossy().to_string(), None, None)
             .await
             .unwrap();
         assert_eq!(content.trim(), "export const x = 42;");
@@ -383,10 +516,50 @@ mod tests {
 
     #[tokio::test]
     async fn test_read_file_content_missing() {
-        let result = read_file_content("/no/such/file.ts".into()).await;
+        let result = read_file_content("/no/such/file.ts".into(), None, None).await;
         assert!(result.is_err());
     }
 
+    #[tokio::test]
+    async fn test_read_file_content_window_of_large_file() {
+        let tmp = tempfile::tempdir().unwrap();
+        let file = tmp.path().join("big.log");
+        let mut body = "a".repeat(MAX_FILE_SIZE_BYTES as usize);
+        body.push_str("NEEDLE");
+        std::fs::write(&file, body).unwrap();
+        let path = file.to_string_lossy().to_string();
+
+        assert!(read_file_content(path.clone(), None, None).await.is_err());
+        let window = read_file_content(path, Some(MAX_FILE_SIZE_BYTES), Some(6)).await.unwrap();
+        assert_eq!(window, "NEEDLE");
+    }
+
+    #[tokio::test]
+    async fn test_read_file_content_binary_window_is_hex() {
+        let tmp = tempfile::tempdir().unwrap();
+        let file = tmp.path().join("blob.bin");
+        std::fs::write(&file, [0x7f, b'E', b'L', b'F', 0, 1]).unwrap();
+        let dump = read_file_content(file.to_string_lossy().to_string(), Some(0), Some(16)).await.unwrap();
+        assert!(dump.starts_with("00000000  7f 45 4c 46 00 01"));
+        assert!(dump.trim_end().ends_with(".ELF.."));
+    }
+
+    #[tokio::test]
+    async fn test_read_file_lines_range() {
+        let tmp = tempfile::tempdir().unwrap();
+        let file = tmp.path().join("lines.txt");
+        std::fs::write(&file, "one\r\ntwo\nthree\nfour\n").unwrap();
+        let path = file.to_string_lossy().to_string();
+
+        let lines = read_file_lines(path.clone(), 2, Some(3)).await.unwrap();
+        assert_eq!(lines.content, "two\nthree\n");
+        assert_eq!((lines.start_line, lines.end_line, lines.total_lines), (2, 3, 4));
+
+        let tail = read_file_lines(path.clone(), 4, None).await.unwrap();
+        assert_eq!(tail.content, "four\n");
+        assert!(read_file_lines(path, 9, None).await.is_err());
+    }
+
     #[test]
     fn test_is_ignored_dir() {
         assert!(is_ignored_dir(Path::new("node_modules")));
@@ -0,0 +1,590 @@
+// project_vectors.rs — semantic search over a project's source files
+//
+// Shipping the first files of index_directory wholesale stops working once
+// a project outgrows a few dozen files. Here every source file under a root
+// (the indexer's walk rules, without its 250-file cap) is cut into
+// overlapping line windows, each window is embedded with ai_bridge::embed,
+// and semantic_search returns only the chunks closest to a query.
+//
+// Vectors live per root in `<app_data>/project_vectors/<hash>.sqlite`: chunk
+// text and line ranges in a plain table, embeddings in a sqlite-vec `vec0`
+// table searched by cosine distance, so a search reads only the hits and
+// embedded batches are written as they arrive. Every file keeps the hash of
+// its content; index_project_vectors re-embeds files changed since its last
+// run, update_project_vectors re-reads just the paths project_watch reported,
+// and semantic_search never touches the files. Switching the embedding model
+// re-embeds everything. Walks and database access run on the blocking pool.
+//
+// Tauri commands exposed:
+//   index_project_vectors(root, embed)           → VectorIndexStats
+//   update_project_vectors(root, paths, embed)   → VectorIndexStats
+//   semantic_search(root, query, top_k?, embed)  → Vec<ChunkHit>
+//   clear_project_vectors(root)
+
+use crate::ai_bridge;
+use crate::error::AppError;
+use crate::path_summary::fnv1a;
+use crate::project_indexer;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Component, Path, PathBuf};
+
+const STORE_DIR: &str         = "project_vectors";
+const MAX_FILES: usize        = 5_000;
+const MAX_FILE_BYTES: u64     = 200_000;
+/// Lines per chunk and lines shared by neighbouring chunks
+const CHUNK_LINES: usize      = 60;
+const CHUNK_OVERLAP: usize    = 10;
+/// Minified files put everything on a few lines; cap what one chunk embeds
+const CHUNK_MAX_CHARS: usize  = 2_400;
+/// Chunks per embeddings request
+const EMBED_BATCH: usize      = 64;
+const DEFAULT_TOP_K: usize    = 8;
+const MAX_TOP_K: usize        = 50;
+
+/// Serializes index updates; held across the embedding requests.
+static STORE_LOCK: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+// ── Types ──────────────────────────────────────────────────────────────────
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct EmbedSettings {
+    /// "openai" | "local"
+    pub provider: String,
+    pub api_key:  Option<String>,
+    pub model:    Option<String>,
+    /// Base URL of the OpenAI-compatible server for "local"
+    pub url:      Option<String>,
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct VectorIndexStats {
+    pub files:          usize,
+    pub chunks:         usize,
+    /// Files (re-)embedded by this update
+    pub embedded_files: usize,
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct ChunkHit {
+    pub path:       String,
+    /// 1-based, inclusive
+    pub start_line: usize,
+    pub end_line:   usize,
+    pub content:    String,
+    /// Cosine similarity to the query
+    pub score:      f32,
+}
+
+/// A file whose chunks wait for their embeddings.
+struct PendingFile {
+    path:   String,
+    hash:   String,
+    chunks: Vec<(usize, usize, String)>,
+}
+
+/// Files an update looks at.
+enum Scope {
+    /// Walk the whole root; stored files not found are dropped
+    All,
+    /// Only these paths relative to the root (a project_watch delta)
+    Paths(Vec<String>),
+}
+
+// ── Tauri commands ─────────────────────────────────────────────────────────
+
+/// Bring the vectors of `root` up to date with the files on disk.
+#[tauri::command]
+pub async fn index_project_vectors(
+    app_handle: tauri::AppHandle,
+    root:       String,
+    embed:      EmbedSettings,
+) -> Result<VectorIndexStats, AppError> {
+    let _guard = STORE_LOCK.lock().await;
+    Ok(refresh(&app_handle, &root, &embed, Scope::All).await?)
+}
+
+/// Re-read `paths` (relative to `root`, as get_index_delta reports them):
+/// changed files are re-embedded, deleted ones dropped.
+#[tauri::command]
+pub async fn update_project_vectors(
+    app_handle: tauri::AppHandle,
+    root:       String,
+    paths:      Vec<String>,
+    embed:      EmbedSettings,
+) -> Result<VectorIndexStats, AppError> {
+    let _guard = STORE_LOCK.lock().await;
+    Ok(refresh(&app_handle, &root, &embed, Scope::Paths(paths)).await?)
+}
+
+/// The `top_k` chunks under `root` most similar to `query` (default 8).
+#[tauri::command]
+pub async fn semantic_search(
+    app_handle: tauri::AppHandle,
+    root:       String,
+    query:      String,
+    top_k:      Option<usize>,
+    embed:      EmbedSettings,
+) -> Result<Vec<ChunkHit>, AppError> {
+    if query.trim().is_empty() {
+        return Err("Search query is empty".into());
+    }
+    let path = store_path(&app_handle, &root)?;
+    if !path.exists() {
+        return Err("Project vectors are not built yet — index the project first".into());
+    }
+    let model = embed_model_key(&embed);
+    let query_vec = embed_texts(&embed, &[query])
+        .await?
+        .into_iter()
+        .next()
+        .ok_or("Embeddings: empty response")?;
+    let top_k = top_k.unwrap_or(DEFAULT_TOP_K).clamp(1, MAX_TOP_K);
+
+    let hits = tokio::task::spawn_blocking(move || {
+        let db = VectorDb::open(&path).map_err(db_err)?;
+        if db.embed_model().map_err(db_err)?.as_deref() != Some(model.as_str()) {
+            return Err("Project vectors come from another embedding model — index the project again".to_string());
+        }
+        db.search(&query_vec, top_k).map_err(db_err)
+    })
+    .await??;
+    Ok(hits)
+}
+
+/// Drop the stored vectors of `root`.
+#[tauri::command]
+pub async fn clear_project_vectors(app_handle: tauri::AppHandle, root: String) -> Result<(), AppError> {
+    let _guard = STORE_LOCK.lock().await;
+    let path = store_path(&app_handle, &root)?;
+    for file in [path.with_extension("sqlite-wal"), path.with_extension("sqlite-shm"), path] {
+        if file.exists() {
+            std::fs::remove_file(&file).map_err(|e| format!("Failed to delete project vectors: {}", e))?;
+        }
+    }
+    Ok(())
+}
+
+// ── Indexing ───────────────────────────────────────────────────────────────
+
+/// Re-embed new and changed files in `scope`, forget deleted ones.
+async fn refresh(
+    app:   &tauri::AppHandle,
+    root:  &str,
+    embed: &EmbedSettings,
+    scope: Scope,
+) -> Result<VectorIndexStats, String> {
+    let dir = PathBuf::from(root);
+    if !dir.is_dir() {
+        return Err(format!("'{}' is not a valid directory", root));
+    }
+    let path = store_path(app, root)?;
+    let model = embed_model_key(embed);
+
+    let db_path = path.clone();
+    let stale = tokio::task::spawn_blocking(move || find_stale(&db_path, &dir, &model, scope))
+        .await
+        .map_err(|e| e.to_string())??;
+
+    let embedded_files = stale.len();
+    let mut batch: Vec<PendingFile> = Vec::new();
+    let mut batch_chunks = 0;
+    let mut stale = stale.into_iter().peekable();
+    while let Some(file) = stale.next() {
+        batch_chunks += file.chunks.len();
+        batch.push(file);
+        if batch_chunks >= EMBED_BATCH || stale.peek().is_none() {
+            // Stored batch by batch: after a failure the next update resumes
+            let vectors = embed_batch(&batch, embed).await?;
+            let (db_path, files) = (path.clone(), std::mem::take(&mut batch));
+            tokio::task::spawn_blocking(move || VectorDb::open(&db_path)?.store(files, vectors))
+                .await
+                .map_err(|e| e.to_string())?
+                .map_err(db_err)?;
+            batch_chunks = 0;
+        }
+    }
+
+    let stats = tokio::task::spawn_blocking(move || VectorDb::open(&path)?.stats())
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(db_err)?;
+    let stats = VectorIndexStats { embedded_files, ..stats };
+    log::info!(
+        "project vectors: '{}' — {} files, {} chunks ({} re-embedded)",
+        root, stats.files, stats.chunks, stats.embedded_files
+    );
+    Ok(stats)
+}
+
+/// Compare the files in `scope` with their stored hashes: drop vanished
+/// files from the store and return the ones to (re-)embed.
+fn find_stale(db_path: &Path, dir: &Path, model: &str, scope: Scope) -> Result<Vec<PendingFile>, String> {
+    if let Some(parent) = db_path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    let mut db = VectorDb::open(db_path).map_err(db_err)?;
+    // Vectors from another model can't be searched together with new ones
+    let scope = if db.reset_for_model(model).map_err(db_err)? { Scope::All } else { scope };
+    let stored = db.file_hashes().map_err(db_err)?;
+
+    let walked = matches!(scope, Scope::All);
+    let candidates: Vec<String> = match scope {
+        Scope::All => project_indexer::source_files(dir, None)
+            .filter(|p| project_indexer::has_allowed_extension(p))
+            .filter_map(|p| p.strip_prefix(dir).ok().map(|r| r.to_string_lossy().replace('\\', "/")))
+            .take(MAX_FILES)
+            .collect(),
+        Scope::Paths(paths) => paths.into_iter().filter(|p| is_relative(p)).collect(),
+    };
+
+    let mut seen: BTreeSet<&String> = BTreeSet::new();
+    let mut stale: Vec<PendingFile> = Vec::new();
+    for rel in &candidates {
+        let Some(text) = read_source(&dir.join(rel)) else { continue };
+        if !stored.contains_key(rel) && stored.len() + stale.len() >= MAX_FILES {
+            continue;
+        }
+        let hash = fnv1a(text.as_bytes());
+        if stored.get(rel) != Some(&hash) {
+            stale.push(PendingFile { path: rel.clone(), hash, chunks: chunk_lines(&text) });
+        }
+        seen.insert(rel);
+    }
+
+    let gone: Vec<&String> = if walked {
+        stored.keys().filter(|p| !seen.contains(p)).collect()
+    } else {
+        candidates.iter().filter(|p| !seen.contains(p)).collect()
+    };
+    db.remove_files(&gone).map_err(db_err)?;
+    Ok(stale)
+}
+
+/// Text of an indexable source file.
+fn read_source(path: &Path) -> Option<String> {
+    if !project_indexer::has_allowed_extension(path) {
+        return None;
+    }
+    let meta = std::fs::metadata(path).ok()?;
+    if !meta.is_file() || meta.len() > MAX_FILE_BYTES {
+        return None;
+    }
+    std::fs::read_to_string(path).ok()
+}
+
+/// Delta paths come from the frontend; keep them under the root.
+fn is_relative(path: &str) -> bool {
+    !path.is_empty() && Path::new(path).components().all(|c| matches!(c, Component::Normal(_)))
+}
+
+async fn embed_batch(files: &[PendingFile], embed: &EmbedSettings) -> Result<Vec<Vec<f32>>, String> {
+    // The path gives the model context a bare code window lacks
+    let texts: Vec<String> = files
+        .iter()
+        .flat_map(|f| f.chunks.iter().map(move |(_, _, text)| format!("{}\n{}", f.path, text)))
+        .collect();
+    if texts.is_empty() {
+        return Ok(Vec::new());
+    }
+    let vectors = embed_texts(embed, &texts).await?;
+    if vectors.len() != texts.len() {
+        return Err(format!("Embeddings: expected {} vectors, got {}", texts.len(), vectors.len()));
+    }
+    Ok(vectors)
+}
+
+async fn embed_texts(embed: &EmbedSettings, texts: &[String]) -> Result<Vec<Vec<f32>>, String> {
+    ai_bridge::embed(
+        &embed.provider,
+        embed.api_key.as_deref(),
+        embed.model.as_deref().filter(|m| !m.is_empty()),
+        embed.url.as_deref().filter(|u| !u.is_empty()),
+        texts,
+    )
+    .await
+}
+
+// ── Store ──────────────────────────────────────────────────────────────────
+
+/// One root's vectors. Connections are cheap to open, so every blocking
+/// task opens its own.
+struct VectorDb {
+    conn: Connection,
+}
+
+impl VectorDb {
+    fn open(path: &Path) -> rusqlite::Result<Self> {
+        static VEC_EXTENSION: std::sync::Once = std::sync::Once::new();
+        VEC_EXTENSION.call_once(|| unsafe {
+            // Loaded into every connection opened after this
+            rusqlite::ffi::sqlite3_auto_extension(Some(std::mem::transmute::<
+                *const (),
+                unsafe extern "C" fn(
+                    *mut rusqlite::ffi::sqlite3,
+                    *mut *mut std::os::raw::c_char,
+                    *const rusqlite::ffi::sqlite3_api_routines,
+                ) -> std::os::raw::c_int,
+            >(sqlite_vec::sqlite3_vec_init as *const ())));
+        });
+        let conn = Connection::open(path)?;
+        // WAL lets a search read while an update writes
+        conn.pragma_update(None, "journal_mode", "WAL")?;
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS meta   (key TEXT PRIMARY KEY, value TEXT NOT NULL);
+             CREATE TABLE IF NOT EXISTS files  (path TEXT PRIMARY KEY, hash TEXT NOT NULL);
+             CREATE TABLE IF NOT EXISTS chunks (
+                 id         INTEGER PRIMARY KEY,
+                 path       TEXT NOT NULL,
+                 start_line INTEGER NOT NULL,
+                 end_line   INTEGER NOT NULL,
+                 text       TEXT NOT NULL
+             );
+             CREATE INDEX IF NOT EXISTS chunks_path ON chunks (path);",
+        )?;
+        Ok(Self { conn })
+    }
+
+    /// Provider and model the vectors came from (embed_model_key).
+    fn embed_model(&self) -> rusqlite::Result<Option<String>> {
+        self.conn
+            .query_row("SELECT value FROM meta WHERE key = 'embed_model'", [], |r| r.get(0))
+            .optional()
+    }
+
+    /// Empty the store unless its vectors came from `model`; true if it did.
+    fn reset_for_model(&mut self, model: &str) -> rusqlite::Result<bool> {
+        if self.embed_model()?.as_deref() == Some(model) {
+            return Ok(false);
+        }
+        let tx = self.conn.transaction()?;
+        // vec0 fixes its dimensions at creation; the next store recreates it
+        tx.execute_batch("DELETE FROM files; DELETE FROM chunks; DROP TABLE IF EXISTS vec_chunks;")?;
+        tx.execute("INSERT OR REPLACE INTO meta (key, value) VALUES ('embed_model', ?1)", [model])?;
+        tx.commit()?;
+        Ok(true)
+    }
+
+    /// Relative path → content hash.
+    fn file_hashes(&self) -> rusqlite::Result<BTreeMap<String, String>> {
+        let mut stmt = self.conn.prepare("SELECT path, hash FROM files")?;
+        let rows = stmt.query_map([], |r| Ok((r.get(0)?, r.get(1)?)))?;
+        rows.collect()
+    }
+
+    fn remove_files(&mut self, paths: &[&String]) -> rusqlite::Result<()> {
+        let tx = self.conn.transaction()?;
+        for path in paths {
+            delete_file(&tx, path)?;
+        }
+        tx.commit()
+    }
+
+    /// Replace the chunks of `files`; `vectors` holds their embeddings in
+    /// chunk order.
+    fn store(&mut self, files: Vec<PendingFile>, vectors: Vec<Vec<f32>>) -> rusqlite::Result<()> {
+        let tx = self.conn.transaction()?;
+        if let Some(first) = vectors.first() {
+            tx.execute_batch(&format!(
+                "CREATE VIRTUAL TABLE IF NOT EXISTS vec_chunks USING vec0(embedding float[{}] distance_metric=cosine)",
+                first.len()
+            ))?;
+        }
+        let mut vectors = vectors.iter();
+        for file in files {
+            delete_file(&tx, &file.path)?;
+            tx.execute("INSERT INTO files (path, hash) VALUES (?1, ?2)", params![file.path, file.hash])?;
+            for ((start_line, end_line, text), embedding) in file.chunks.into_iter().zip(vectors.by_ref()) {
+                tx.execute(
+                    "INSERT INTO chunks (path, start_line, end_line, text) VALUES (?1, ?2, ?3, ?4)",
+                    params![file.path, start_line as i64, end_line as i64, text],
+                )?;
+                tx.execute(
+                    "INSERT INTO vec_chunks (rowid, embedding) VALUES (?1, ?2)",
+                    params![tx.last_insert_rowid(), vector_blob(embedding)],
+                )?;
+            }
+        }
+        tx.commit()
+    }
+
+    /// The `top_k` chunks nearest to `query`, best first.
+    fn search(&self, query: &[f32], top_k: usize) -> rusqlite::Result<Vec<ChunkHit>> {
+        if !has_vectors(&self.conn)? {
+            return Ok(Vec::new());
+        }
+        let mut stmt = self.conn.prepare(
+            "SELECT c.path, c.start_line, c.end_line, c.text, v.distance
+             FROM (SELECT rowid, distance FROM vec_chunks WHERE embedding MATCH ?1 AND k = ?2) v
+             JOIN chunks c ON c.id = v.rowid
+             ORDER BY v.distance",
+        )?;
+        let hits = stmt.query_map(params![vector_blob(query), top_k as i64], |r| {
+            Ok(ChunkHit {
+                path:       r.get(0)?,
+                start_line: r.get::<_, i64>(1)? as usize,
+                end_line:   r.get::<_, i64>(2)? as usize,
+                content:    r.get(3)?,
+                // Cosine distance is 1 − similarity
+                score:      1.0 - r.get::<_, f64>(4)? as f32,
+            })
+        })?;
+        hits.collect()
+    }
+
+    fn stats(&self) -> rusqlite::Result<VectorIndexStats> {
+        let count = |sql: &str| self.conn.query_row(sql, [], |r| r.get::<_, i64>(0)).map(|n| n as usize);
+        Ok(VectorIndexStats {
+            files:          count("SELECT COUNT(*) FROM files")?,
+            chunks:         count("SELECT COUNT(*) FROM chunks")?,
+            embedded_files: 0,
+        })
+    }
+}
+
+fn delete_file(conn: &Connection, path: &str) -> rusqlite::Result<()> {
+    if has_vectors(conn)? {
+        conn.execute("DELETE FROM vec_chunks WHERE rowid IN (SELECT id FROM chunks WHERE path = ?1)", [path])?;
+    }
+    conn.execute("DELETE FROM chunks WHERE path = ?1", [path])?;
+    conn.execute("DELETE FROM files WHERE path = ?1", [path])?;
+    Ok(())
+}
+
+/// The vector table only exists once something was embedded.
+fn has_vectors(conn: &Connection) -> rusqlite::Result<bool> {
+    conn.query_row("SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE name = 'vec_chunks')", [], |r| r.get(0))
+}
+
+/// sqlite-vec takes float32 vectors as little-endian blobs.
+fn vector_blob(v: &[f32]) -> Vec<u8> {
+    v.iter().flat_map(|x| x.to_le_bytes()).collect()
+}
+
+fn db_err(e: rusqlite::Error) -> String {
+    format!("Project vectors: {}", e)
+}
+
+// ── Helpers ────────────────────────────────────────────────────────────────
+
+fn store_path(app: &tauri::AppHandle, root: &str) -> Result<PathBuf, String> {
+    app.path_resolver()
+        .app_data_dir()
+        .ok_or_else(|| "Cannot resolve app data directory".to_string())
+        .map(|p| p.join(STORE_DIR).join(format!("{}.sqlite", fnv1a(root.as_bytes()))))
+}
+
+/// Vectors from different models are not comparable.
+fn embed_model_key(embed: &EmbedSettings) -> String {
+    embed.model
+        .as_deref()
+        .filter(|m| !m.is_empty())
+        .map_or_else(|| embed.provider.clone(), |m| format!("{}:{}", embed.provider, m))
+}
+
+/// Overlapping windows of CHUNK_LINES lines as (start, end, text), 1-based;
+/// whitespace-only windows are skipped.
+fn chunk_lines(text: &str) -> Vec<(usize, usize, String)> {
+    let lines: Vec<&str> = text.lines().collect();
+    let mut chunks = Vec::new();
+    let mut start = 0;
+    while start < lines.len() {
+        let end = (start + CHUNK_LINES).min(lines.len());
+        let body = lines[start..end].join("\n");
+        if !body.trim().is_empty() {
+            chunks.push((start + 1, end, body.chars().take(CHUNK_MAX_CHARS).collect()));
+        }
+        if end == lines.len() {
+            break;
+        }
+        start = end - CHUNK_OVERLAP;
+    }
+    chunks
+}
+
+// ── Unit tests ─────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pending(path: &str, hash: &str, lines: &[usize]) -> PendingFile {
+        PendingFile {
+            path:   path.into(),
+            hash:   hash.into(),
+            chunks: lines.iter().map(|&l| (l, l, format!("l{}", l))).collect(),
+        }
+    }
+
+    #[test]
+    fn test_chunk_lines_overlap() {
+        let text: String = (1..=130).map(|i| format!("line {}\n", i)).collect();
+        let spans: Vec<(usize, usize)> = chunk_lines(&text).iter().map(|(s, e, _)| (*s, *e)).collect();
+        assert_eq!(spans, [(1, 60), (51, 110), (101, 130)]);
+        assert!(chunk_lines(&text)[1].2.starts_with("line 51\n"));
+        assert!(chunk_lines("\n  \n\n").is_empty());
+        assert!(chunk_lines("").is_empty());
+    }
+
+    #[test]
+    fn test_search_orders_by_similarity() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut db = VectorDb::open(&dir.path().join("v.sqlite")).unwrap();
+        assert!(db.reset_for_model("local").unwrap());
+        assert!(db.search(&[0.0, 1.0], 2).unwrap().is_empty());
+        db.store(
+            vec![pending("a.rs", "1", &[1, 2]), pending("b.rs", "2", &[3])],
+            vec![vec![1.0, 0.0], vec![0.0, 1.0], vec![0.7, 0.7]],
+        )
+        .unwrap();
+
+        let hits = db.search(&[0.0, 1.0], 2).unwrap();
+        assert_eq!(hits.iter().map(|h| (h.path.as_str(), h.start_line)).collect::<Vec<_>>(), [("a.rs", 2), ("b.rs", 3)]);
+        assert!((hits[0].score - 1.0).abs() < 1e-5);
+        assert!(hits[0].score > hits[1].score);
+
+        // Re-storing a file replaces its chunks; removing drops them
+        db.store(vec![pending("a.rs", "3", &[5])], vec![vec![1.0, 0.0]]).unwrap();
+        db.remove_files(&[&"b.rs".to_string()]).unwrap();
+        let hits = db.search(&[0.0, 1.0], 5).unwrap();
+        assert_eq!(hits.iter().map(|h| (h.path.as_str(), h.start_line)).collect::<Vec<_>>(), [("a.rs", 5)]);
+        assert_eq!(db.file_hashes().unwrap(), BTreeMap::from([("a.rs".to_string(), "3".to_string())]));
+
+        assert!(!db.reset_for_model("local").unwrap());
+        assert!(db.reset_for_model("openai").unwrap());
+        assert_eq!(db.stats().unwrap(), VectorIndexStats { files: 0, chunks: 0, embedded_files: 0 });
+    }
+
+    #[test]
+    fn test_find_stale_skips_unchanged_files() {
+        let root = tempfile::tempdir().unwrap();
+        let store = tempfile::tempdir().unwrap();
+        let db_path = store.path().join("v.sqlite");
+        std::fs::write(root.path().join("a.rs"), "fn a() {}\n").unwrap();
+        std::fs::write(root.path().join("b.rs"), "fn b() {}\n").unwrap();
+
+        let stale = find_stale(&db_path, root.path(), "local", Scope::All).unwrap();
+        let mut paths: Vec<&str> = stale.iter().map(|f| f.path.as_str()).collect();
+        paths.sort();
+        assert_eq!(paths, ["a.rs", "b.rs"]);
+        VectorDb::open(&db_path).unwrap().store(stale, vec![vec![1.0, 0.0], vec![0.0, 1.0]]).unwrap();
+
+        std::fs::write(root.path().join("a.rs"), "fn a() { 1 }\n").unwrap();
+        std::fs::remove_file(root.path().join("b.rs")).unwrap();
+        let paths = vec!["a.rs".to_string(), "b.rs".to_string(), "../x.rs".to_string()];
+        let stale = find_stale(&db_path, root.path(), "local", Scope::Paths(paths)).unwrap();
+        assert_eq!(stale.iter().map(|f| f.path.as_str()).collect::<Vec<_>>(), ["a.rs"]);
+        assert_eq!(VectorDb::open(&db_path).unwrap().file_hashes().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_embed_model_key() {
+        let settings = |model: Option<&str>| EmbedSettings {
+            provider: "local".into(), api_key: None, model: model.map(String::from), url: None,
+        };
+        assert_eq!(embed_model_key(&settings(None)), "local");
+        assert_eq!(embed_model_key(&settings(Some(""))), "local");
+        assert_eq!(embed_model_key(&settings(Some("nomic-embed-text"))), "local:nomic-embed-text");
+    }
+}
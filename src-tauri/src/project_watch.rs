@@ -0,0 +1,377 @@
+// project_watch.rs — keep the project index warm while files change
+//
+// index_directory reads a whole tree once. After watch_project(root) a
+// notify watcher follows the same tree. Every directory the indexer would
+// enter gets a non-recursive watch of its own, so node_modules, target and
+// gitignored output never use up inotify watches; directories created later
+// are added as they appear. Event bursts (editors write a temp
+// file, rename it, touch it again) are debounced, changed files are re-read
+// with the indexer's own rules — IGNORED_DIRS, .gitignore, extension and
+// size limits — and deleted ones are dropped. Every affected path is
+// announced with a `project-file-changed` event; the frontend then pulls the
+// accumulated changes with get_index_delta instead of re-indexing.
+//
+// One project is watched at a time; watching another root replaces it.
+//
+// Tauri commands exposed:
+//   watch_project(root) → number of files tracked
+//   unwatch_project
+//   get_index_delta     → IndexDelta (changed / removed since the last call)
+
+use crate::error::AppError;
+use crate::project_indexer::{self, IndexedFile, MAX_TOTAL_FILES};
+use notify::event::ModifyKind;
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{Receiver, RecvTimeoutError};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Quiet period that ends an event burst
+const DEBOUNCE: Duration       = Duration::from_millis(300);
+/// A burst is processed after this long even if events keep coming
+const MAX_BATCH_WAIT: Duration = Duration::from_secs(2);
+/// Directory watches per project (Linux defaults to 8192 inotify watches
+/// per user, shared with editors and other tools)
+const MAX_WATCHED_DIRS: usize  = 4096;
+
+static WATCH: Mutex<Option<Watch>> = Mutex::new(None);
+/// Bumped per watch_project call so a replaced worker never writes into
+/// the index of its successor.
+static GENERATION: AtomicU64 = AtomicU64::new(0);
+
+struct Watch {
+    generation: u64,
+    /// As passed to watch_project; echoed in every delta
+    root:       String,
+    /// Dropping the watcher closes the event channel and ends the worker
+    watcher:    RecommendedWatcher,
+    /// Directories with a watch, counted against MAX_WATCHED_DIRS
+    watched:    usize,
+    index:      WatchedIndex,
+}
+
+#[derive(Debug, Default)]
+struct WatchedIndex {
+    /// Relative paths currently part of the index
+    known:   BTreeSet<String>,
+    changed: BTreeMap<String, IndexedFile>,
+    removed: BTreeSet<String>,
+}
+
+#[derive(Debug)]
+enum Update {
+    Changed(IndexedFile),
+    /// A file or a whole directory, relative to the root
+    Removed(String),
+}
+
+// ── Types ──────────────────────────────────────────────────────────────────
+
+#[derive(Debug, Serialize, Clone, Default)]
+pub struct IndexDelta {
+    pub root_path: String,
+    /// New or modified files, re-read from disk
+    pub changed:   Vec<IndexedFile>,
+    /// Relative paths that left the index (deleted, ignored or too large)
+    pub removed:   Vec<String>,
+}
+
+/// Payload of `project-file-changed`.
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct FileChange {
+    pub path:    String,
+    pub removed: bool,
+}
+
+// ── Tauri commands ─────────────────────────────────────────────────────────
+
+/// Start following `root`; replaces any previous watch.
+#[tauri::command]
+pub async fn watch_project(app_handle: tauri::AppHandle, root: String) -> Result<usize, AppError> {
+    let dir = std::fs::canonicalize(&root)
+        .ok()
+        .filter(|d| d.is_dir())
+        .ok_or_else(|| format!("'{}' is not a valid directory", root))?;
+
+    let known: BTreeSet<String> = project_indexer::source_files(&dir, None)
+        .filter(|p| project_indexer::has_allowed_extension(p))
+        .filter_map(|p| relative(&dir, &p))
+        .take(MAX_TOTAL_FILES)
+        .collect();
+
+    let (tx, rx) = std::sync::mpsc::channel();
+    let mut watcher = notify::recommended_watcher(tx)
+        .map_err(|e| format!("Cannot watch '{}': {}", root, e))?;
+    watcher
+        .watch(&dir, RecursiveMode::NonRecursive)
+        .map_err(|e| format!("Cannot watch '{}': {}", root, e))?;
+    let watched = 1 + watch_dirs(&mut watcher, project_indexer::source_dirs(&dir, None).skip(1), MAX_WATCHED_DIRS - 1);
+
+    let generation = GENERATION.fetch_add(1, Ordering::Relaxed) + 1;
+    let count = known.len();
+    *WATCH.lock().map_err(|e| e.to_string())? = Some(Watch {
+        generation,
+        root: root.clone(),
+        watcher,
+        watched,
+        index: WatchedIndex { known, ..Default::default() },
+    });
+    std::thread::spawn(move || worker(app_handle, dir, generation, rx));
+    log::info!("project watch: following '{}' ({} files, {} directories)", root, count, watched);
+    Ok(count)
+}
+
+#[tauri::command]
+pub fn unwatch_project() -> Result<(), AppError> {
+    if let Some(w) = WATCH.lock().map_err(|e| e.to_string())?.take() {
+        log::info!("project watch: stopped following '{}'", w.root);
+    }
+    Ok(())
+}
+
+/// Files changed and removed since the previous call (or since
+/// watch_project); the pending changes are cleared.
+#[tauri::command]
+pub fn get_index_delta() -> Result<IndexDelta, AppError> {
+    let mut guard = WATCH.lock().map_err(|e| e.to_string())?;
+    let watch = guard.as_mut().ok_or("No project is being watched")?;
+    let index = &mut watch.index;
+    Ok(IndexDelta {
+        root_path: watch.root.clone(),
+        changed:   std::mem::take(&mut index.changed).into_values().collect(),
+        removed:   std::mem::take(&mut index.removed).into_iter().collect(),
+    })
+}
+
+// ── Worker ─────────────────────────────────────────────────────────────────
+
+fn worker(app: tauri::AppHandle, root: PathBuf, generation: u64, rx: Receiver<notify::Result<notify::Event>>) {
+    use tauri::Manager;
+
+    // Ends when the watcher is dropped (unwatch / replaced) and the channel closes
+    while let Ok(first) = rx.recv() {
+        let mut paths = BTreeSet::new();
+        let mut created = BTreeSet::new();
+        collect_paths(&mut paths, &mut created, first);
+        let started = Instant::now();
+        while started.elapsed() < MAX_BATCH_WAIT {
+            match rx.recv_timeout(DEBOUNCE) {
+                Ok(event) => collect_paths(&mut paths, &mut created, event),
+                Err(RecvTimeoutError::Timeout) => break,
+                Err(RecvTimeoutError::Disconnected) => return,
+            }
+        }
+
+        // Disk reads happen before taking the lock
+        let updates: Vec<Update> = paths.iter().flat_map(|p| scan(&root, p)).collect();
+        let new_dirs: Vec<PathBuf> = created
+            .iter()
+            .filter(|p| is_visible_dir(&root, p))
+            .flat_map(|p| project_indexer::source_dirs(p, None))
+            .collect();
+        let changes = {
+            let Ok(mut guard) = WATCH.lock() else { return };
+            match guard.as_mut() {
+                Some(w) if w.generation == generation => {
+                    let budget = MAX_WATCHED_DIRS.saturating_sub(w.watched);
+                    w.watched += watch_dirs(&mut w.watcher, new_dirs, budget);
+                    apply(&mut w.index, updates)
+                }
+                _ => return,
+            }
+        };
+        for change in changes {
+            let _ = app.emit_all("project-file-changed", &change);
+        }
+    }
+}
+
+/// `created` gets the paths that may be new directories needing a watch.
+fn collect_paths(paths: &mut BTreeSet<PathBuf>, created: &mut BTreeSet<PathBuf>, event: notify::Result<notify::Event>) {
+    let event = match event {
+        Ok(e)  => e,
+        Err(e) => { log::warn!("project watch: {}", e); return; }
+    };
+    // Opening and reading files (our own re-reads included) changes nothing
+    if matches!(event.kind, EventKind::Access(_)) {
+        return;
+    }
+    if matches!(event.kind, EventKind::Create(_) | EventKind::Modify(ModifyKind::Name(_))) {
+        created.extend(event.paths.iter().cloned());
+    }
+    paths.extend(event.paths);
+}
+
+/// Add a non-recursive watch for each of `dirs`, at most `budget`; the
+/// number added.
+fn watch_dirs(watcher: &mut RecommendedWatcher, dirs: impl IntoIterator<Item = PathBuf>, budget: usize) -> usize {
+    let mut added = 0;
+    for dir in dirs {
+        if added >= budget {
+            log::warn!("project watch: directory limit ({}) reached, '{}' and later ones are not followed", MAX_WATCHED_DIRS, dir.display());
+            break;
+        }
+        match watcher.watch(&dir, RecursiveMode::NonRecursive) {
+            Ok(()) => added += 1,
+            Err(e) => log::warn!("project watch: cannot watch '{}': {}", dir.display(), e),
+        }
+    }
+    added
+}
+
+// ── Helpers ────────────────────────────────────────────────────────────────
+
+/// "src/main.rs" for `<root>/src/main.rs`; None for the root itself and
+/// paths outside it.
+fn relative(root: &Path, path: &Path) -> Option<String> {
+    let rel = path.strip_prefix(root).ok()?.to_string_lossy().replace('\\', "/");
+    (!rel.is_empty()).then_some(rel)
+}
+
+/// Whether `path` is a directory under `root` the indexer would enter.
+/// Listing the parent applies IGNORED_DIRS and the .gitignore rules.
+fn is_visible_dir(root: &Path, path: &Path) -> bool {
+    path.is_dir()
+        && relative(root, path).is_some()
+        && path.parent().is_some_and(|dir| project_indexer::source_dirs(dir, Some(1)).any(|d| d == path))
+}
+
+/// Index updates for one reported path.
+fn scan(root: &Path, path: &Path) -> Vec<Update> {
+    let Some(rel) = relative(root, path) else { return Vec::new() };
+    // Inside node_modules, .git, target, …: never indexed
+    let in_ignored_dir = Path::new(&rel)
+        .ancestors()
+        .skip(1)
+        .any(|dir| project_indexer::is_ignored_dir(dir));
+    if in_ignored_dir {
+        return Vec::new();
+    }
+
+    if path.is_dir() {
+        // Directories moved or copied in arrive as a single event
+        return project_indexer::source_files(path, None)
+            .filter_map(|p| relative(root, &p).and_then(|r| project_indexer::index_file(&p, r)))
+            .map(Update::Changed)
+            .collect();
+    }
+    // Listing the parent applies the .gitignore rules that govern this file
+    let visible = path.is_file()
+        && path.parent().is_some_and(|dir| project_indexer::source_files(dir, Some(1)).any(|p| p == path));
+    match visible.then(|| project_indexer::index_file(path, rel.clone())).flatten() {
+        Some(file) => vec![Update::Changed(file)],
+        None       => vec![Update::Removed(rel)],
+    }
+}
+
+/// Fold updates into the index; the events to emit.
+fn apply(index: &mut WatchedIndex, updates: Vec<Update>) -> Vec<FileChange> {
+    let mut changes = Vec::new();
+    for update in updates {
+        match update {
+            Update::Changed(file) => {
+                if !index.known.contains(&file.path) && index.known.len() >= MAX_TOTAL_FILES {
+                    continue;
+                }
+                index.known.insert(file.path.clone());
+                index.removed.remove(&file.path);
+                changes.push(FileChange { path: file.path.clone(), removed: false });
+                index.changed.insert(file.path.clone(), file);
+            }
+            Update::Removed(rel) => {
+                let prefix = format!("{}/", rel);
+                let gone: Vec<String> = index.known
+                    .iter()
+                    .filter(|p| **p == rel || p.starts_with(&prefix))
+                    .cloned()
+                    .collect();
+                for path in gone {
+                    index.known.remove(&path);
+                    index.changed.remove(&path);
+                    index.removed.insert(path.clone());
+                    changes.push(FileChange { path, removed: true });
+                }
+            }
+        }
+    }
+    changes
+}
+
+// ── Unit tests ─────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file(path: &str) -> IndexedFile {
+        IndexedFile {
+            path:       path.into(),
+            content:    String::new(),
+            size_bytes: 0,
+            extension:  "rs".into(),
+            truncated:  false,
+        }
+    }
+
+    #[test]
+    fn test_apply_tracks_changes_and_directory_removal() {
+        let mut index = WatchedIndex {
+            known: ["src/a.rs", "src/b.rs", "srcx.rs"].into_iter().map(String::from).collect(),
+            ..Default::default()
+        };
+        let changes = apply(&mut index, vec![
+            Update::Changed(file("src/a.rs")),
+            Update::Changed(file("lib.rs")),
+            Update::Removed("src".into()),
+            Update::Removed("missing.rs".into()),
+        ]);
+        assert_eq!(changes, vec![
+            FileChange { path: "src/a.rs".into(), removed: false },
+            FileChange { path: "lib.rs".into(),   removed: false },
+            FileChange { path: "src/a.rs".into(), removed: true },
+            FileChange { path: "src/b.rs".into(), removed: true },
+        ]);
+        assert_eq!(index.changed.keys().collect::<Vec<_>>(), ["lib.rs"]);
+        assert_eq!(index.removed.iter().collect::<Vec<_>>(), ["src/a.rs", "src/b.rs"]);
+        assert_eq!(index.known.iter().collect::<Vec<_>>(), ["lib.rs", "srcx.rs"]);
+    }
+
+    #[test]
+    fn test_scan_respects_indexer_rules() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path();
+        std::fs::write(root.join(".gitignore"), "secret.rs\n").unwrap();
+        std::fs::write(root.join("main.rs"), "fn main() {}").unwrap();
+        std::fs::write(root.join("secret.rs"), "const KEY: &str = \"\";").unwrap();
+        std::fs::create_dir_all(root.join("node_modules")).unwrap();
+        std::fs::write(root.join("node_modules").join("x.js"), "").unwrap();
+
+        assert!(matches!(scan(root, &root.join("main.rs"))[..], [Update::Changed(ref f)] if f.path == "main.rs"));
+        assert!(matches!(scan(root, &root.join("secret.rs"))[..], [Update::Removed(ref p)] if p == "secret.rs"));
+        assert!(matches!(scan(root, &root.join("gone.rs"))[..], [Update::Removed(ref p)] if p == "gone.rs"));
+        assert!(scan(root, &root.join("node_modules").join("x.js")).is_empty());
+        assert!(scan(root, root).is_empty());
+    }
+
+    #[test]
+    fn test_only_indexed_directories_are_watched() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path();
+        std::fs::write(root.join(".gitignore"), "dist/\n").unwrap();
+        for dir in ["src/ui", "dist/assets", "node_modules/react"] {
+            std::fs::create_dir_all(root.join(dir)).unwrap();
+        }
+
+        let mut dirs: Vec<PathBuf> = project_indexer::source_dirs(root, None).collect();
+        dirs.sort();
+        assert_eq!(dirs, [root.to_path_buf(), root.join("src"), root.join("src/ui")]);
+        assert!(is_visible_dir(root, &root.join("src/ui")));
+        assert!(!is_visible_dir(root, &root.join("dist")));
+        assert!(!is_visible_dir(root, &root.join("node_modules")));
+        assert!(!is_visible_dir(root, root));
+    }
+}
@@ -0,0 +1,388 @@
+// prompt_layers.rs — system prompt assembled from ordered, budgeted layers
+//
+// The system prompt is built here instead of being concatenated by the
+// frontend. Each layer has a kind, a place in the order and a token cap:
+//
+//   language — "respond only in X" directive (from the request)
+//   safety   — user-wide rules, kept in settings
+//   persona  — the active character card, else the global persona
+//   project  — instructions saved for the indexed project root
+//   tools    — instruction blocks for enabled tools (file editing, …)
+//
+// Settings (order, caps, persona / safety / project texts) live in
+// `<app_data>/prompt_layers.json`. When the layers together exceed the
+// total budget, the ones placed last are cut down first.
+//
+// Tauri commands exposed:
+//   get_prompt_layer_settings / save_prompt_layer_settings
+//   compose_system_prompt (spec) → ComposedPrompt
+
+use crate::conversation::estimate_tokens;
+use crate::error::AppError;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+const SETTINGS_FILE: &str = "prompt_layers.json";
+/// A layer squeezed below this is dropped rather than sent as a stub
+const MIN_LAYER_TOKENS: u32 = 32;
+
+static FILE_LOCK: Mutex<()> = Mutex::new(());
+
+const FILE_EDIT_INSTRUCTIONS: &str = "\
+## File-editing capability
+You have **full read/write access** to the selected project directory.
+You can:
+- **Modify** any existing file, including empty ones.
+- **Create** new files that do not yet exist.
+- **Create** new subdirectories (just use a path like `subdir/file.ts` and the directory will be made automatically).
+
+When you want to create or change a file, output **exactly** this block (one block per file):
+
+<<<FILE:relative/or/absolute/path>>>
+<full new file content here — may be empty if intentionally blank>
+<<<END_FILE>>>
+
+Rules:
+- Output the **complete** new file content, not a diff.
+- For empty files, just leave the body blank between the markers.
+- Use paths relative to the project root (as shown in the context), or absolute paths.
+- You may output multiple FILE blocks in one response.
+- Explain your changes in plain text outside the FILE blocks.
+- NEVER say you cannot create or edit a file — you always can.
+
+To **delete** a file, output exactly this line (no body needed):
+`<<<DELETE_FILE:relative/or/absolute/path>>>`
+
+- Use this only when a file should be permanently removed.
+- You may mix FILE and DELETE_FILE blocks in one response.";
+
+/// Instruction block for each tool name the frontend can enable.
+const TOOL_INSTRUCTIONS: &[(&str, &str)] = &[
+    ("file_edit", FILE_EDIT_INSTRUCTIONS),
+];
+
+// ── Types ──────────────────────────────────────────────────────────────────
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct LayerConfig {
+    /// "language" | "safety" | "persona" | "project" | "tools"
+    pub kind:       String,
+    pub enabled:    bool,
+    /// Cap for this layer alone
+    pub max_tokens: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct PromptLayerSettings {
+    /// Order of the layers in the prompt; later ones are trimmed first
+    pub layers:         Vec<LayerConfig>,
+    pub total_budget:   u32,
+    /// Persona used when no character card is active
+    pub global_persona: String,
+    pub safety:         String,
+    /// Project root → instructions for that project
+    pub projects:       BTreeMap<String, String>,
+}
+
+impl Default for PromptLayerSettings {
+    fn default() -> Self {
+        let layer = |kind: &str, max_tokens| LayerConfig { kind: kind.into(), enabled: true, max_tokens };
+        PromptLayerSettings {
+            layers: vec![
+                layer("language", 200),
+                layer("safety",   500),
+                layer("tools",    1_000),
+                layer("persona",  2_000),
+                layer("project",  1_500),
+            ],
+            total_budget:   4_000,
+            global_persona: String::new(),
+            safety:         String::new(),
+            projects:       BTreeMap::new(),
+        }
+    }
+}
+
+/// Character card fields, as imported from a V2 card.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct Persona {
+    pub name:          String,
+    pub system_prompt: String,
+    pub description:   String,
+    pub personality:   String,
+    pub scenario:      String,
+    pub mes_example:   String,
+}
+
+/// What the current request needs; the texts come from settings.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct PromptSpec {
+    /// Display name of the response language ("Russian (Русский)"); None = follow the user
+    pub language:     Option<String>,
+    /// Active character card; None = global persona
+    pub persona:      Option<Persona>,
+    pub project_root: Option<String>,
+    /// Tool names, e.g. ["file_edit"]
+    pub tools:        Vec<String>,
+    /// Overrides the settings' total budget
+    pub budget:       Option<u32>,
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct LayerReport {
+    pub kind:      String,
+    pub tokens:    u32,
+    pub truncated: bool,
+    /// Present in the settings and non-empty, but cut for budget
+    pub dropped:   bool,
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct ComposedPrompt {
+    /// None when every layer is empty
+    pub text:   Option<String>,
+    pub layers: Vec<LayerReport>,
+    pub tokens: u32,
+}
+
+// ── Storage ────────────────────────────────────────────────────────────────
+
+fn settings_path(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+    app.path_resolver()
+        .app_data_dir()
+        .ok_or_else(|| "Cannot resolve app data directory".to_string())
+        .map(|p| p.join(SETTINGS_FILE))
+}
+
+fn load(path: &Path) -> PromptLayerSettings {
+    std::fs::read_to_string(path)
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
+// ── Composition ────────────────────────────────────────────────────────────
+
+/// Raw text of one layer for this request.
+fn layer_text(kind: &str, settings: &PromptLayerSettings, spec: &PromptSpec) -> String {
+    match kind {
+        "language" => spec.language.as_deref().map(str::trim).filter(|l| !l.is_empty())
+            .map(|lang| format!(
+                "[SYSTEM LANGUAGE OVERRIDE — HIGHEST PRIORITY RULE: You MUST respond EXCLUSIVELY in {lang}. \
+                 This rule overrides EVERYTHING, including: the language of previous messages in conversation \
+                 history, the language the user wrote in, any language patterns you observe in the dialogue. \
+                 Every single word of your response — dialogue, narration, actions, thoughts, descriptions, \
+                 inner monologue — must be in {lang}. DO NOT write even a single word in any other language. \
+                 This applies to your CURRENT response and ALL future responses in this conversation.]"
+            ))
+            .unwrap_or_default(),
+        "safety" => settings.safety.trim().to_string(),
+        "persona" => match &spec.persona {
+            Some(p) => persona_text(p),
+            None    => settings.global_persona.trim().to_string(),
+        },
+        "project" => spec.project_root.as_deref()
+            .and_then(|root| settings.projects.get(root.trim_end_matches(['/', '\\'])))
+            .map(|s| s.trim().to_string())
+            .unwrap_or_default(),
+        "tools" => spec.tools.iter()
+            .filter_map(|t| TOOL_INSTRUCTIONS.iter().find(|(name, _)| name == t).map(|(_, text)| *text))
+            .collect::<Vec<_>>()
+            .join("\n\n"),
+        _ => String::new(),
+    }
+}
+
+fn persona_text(p: &Persona) -> String {
+    let mut lines = Vec::new();
+    if !p.name.trim().is_empty() {
+        lines.push(format!("You are {}.", p.name.trim()));
+    }
+    let fields = [
+        ("", &p.system_prompt),
+        ("Description: ", &p.description),
+        ("Personality: ", &p.personality),
+        ("Scenario: ", &p.scenario),
+        ("Example dialogue:\n", &p.mes_example),
+    ];
+    for (label, value) in fields {
+        if !value.trim().is_empty() {
+            lines.push(format!("{}{}", label, value.trim()));
+        }
+    }
+    lines.join("\n")
+}
+
+/// Cut `text` to about `max_tokens`, at a line or word break when one is near.
+fn truncate_tokens(text: &str, max_tokens: u32) -> String {
+    // One char is kept for the ellipsis so the result stays within budget
+    let max_chars = (max_tokens as usize * 4).saturating_sub(1);
+    let Some((cut, _)) = text.char_indices().nth(max_chars) else { return text.to_string() };
+    let head = &text[..cut];
+    let soft = head.rfind('\n').filter(|&i| i > cut * 3 / 4)
+        .or_else(|| head.rfind(' ').filter(|&i| i > cut * 3 / 4))
+        .unwrap_or(cut);
+    format!("{}…", head[..soft].trim_end())
+}
+
+fn compose(settings: &PromptLayerSettings, spec: &PromptSpec) -> ComposedPrompt {
+    // (kind, text, truncated) in prompt order, each within its own cap
+    let mut layers: Vec<(String, String, bool)> = settings.layers.iter()
+        .filter(|l| l.enabled)
+        .map(|l| {
+            let text = layer_text(&l.kind, settings, spec);
+            let capped = truncate_tokens(&text, l.max_tokens);
+            let truncated = capped != text;
+            (l.kind.clone(), capped, truncated)
+        })
+        .filter(|(_, text, _)| !text.is_empty())
+        .collect();
+
+    // Over the total: shrink from the back, dropping layers left with too little
+    let budget = spec.budget.unwrap_or(settings.total_budget);
+    let mut dropped = Vec::new();
+    let mut total: u32 = layers.iter().map(|(_, t, _)| estimate_tokens(t)).sum();
+    for i in (0..layers.len()).rev() {
+        if total <= budget {
+            break;
+        }
+        let tokens = estimate_tokens(&layers[i].1);
+        let keep = tokens.saturating_sub(total - budget);
+        if keep < MIN_LAYER_TOKENS {
+            dropped.push(layers[i].0.clone());
+            layers[i].1.clear();
+            total -= tokens;
+        } else {
+            layers[i].1 = truncate_tokens(&layers[i].1, keep);
+            layers[i].2 = true;
+            total = total - tokens + estimate_tokens(&layers[i].1);
+        }
+    }
+
+    let reports = layers.iter()
+        .map(|(kind, text, truncated)| LayerReport {
+            kind:      kind.clone(),
+            tokens:    estimate_tokens(text),
+            truncated: *truncated && !text.is_empty(),
+            dropped:   dropped.contains(kind),
+        })
+        .collect();
+    let parts: Vec<&str> = layers.iter().map(|(_, t, _)| t.as_str()).filter(|t| !t.is_empty()).collect();
+    ComposedPrompt {
+        text:   (!parts.is_empty()).then(|| parts.join("\n\n")),
+        layers: reports,
+        tokens: total,
+    }
+}
+
+// ── Tauri commands ─────────────────────────────────────────────────────────
+
+#[tauri::command]
+pub fn get_prompt_layer_settings(app_handle: tauri::AppHandle) -> Result<PromptLayerSettings, AppError> {
+    Ok(load(&settings_path(&app_handle)?))
+}
+
+/// Replace the settings. Unknown layer kinds are rejected; each kind may
+/// appear once.
+#[tauri::command]
+pub fn save_prompt_layer_settings(app_handle: tauri::AppHandle, mut settings: PromptLayerSettings) -> Result<(), AppError> {
+    let known = ["language", "safety", "persona", "project", "tools"];
+    for (i, l) in settings.layers.iter().enumerate() {
+        if !known.contains(&l.kind.as_str()) {
+            return Err(format!("Unknown prompt layer '{}'", l.kind).into());
+        }
+        if settings.layers[..i].iter().any(|o| o.kind == l.kind) {
+            return Err(format!("Prompt layer '{}' listed twice", l.kind).into());
+        }
+    }
+    settings.projects = settings.projects.into_iter()
+        .map(|(root, text)| (root.trim_end_matches(['/', '\\']).to_string(), text))
+        .filter(|(_, text)| !text.trim().is_empty())
+        .collect();
+
+    let path = settings_path(&app_handle)?;
+    let _guard = FILE_LOCK.lock().map_err(|e| e.to_string())?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    }
+    let json = serde_json::to_string_pretty(&settings).map_err(|e| e.to_string())?;
+    let tmp = path.with_extension("json.tmp");
+    std::fs::write(&tmp, json).map_err(|e| format!("Failed to write prompt layers: {}", e))?;
+    Ok(std::fs::rename(&tmp, &path)?)
+}
+
+/// Build the system prompt for one request; `text` goes in `system_prompt`.
+#[tauri::command]
+pub fn compose_system_prompt(app_handle: tauri::AppHandle, spec: PromptSpec) -> Result<ComposedPrompt, AppError> {
+    Ok(compose(&load(&settings_path(&app_handle)?), &spec))
+}
+
+// ── Unit tests ─────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(c: &ComposedPrompt) -> Vec<&str> {
+        c.layers.iter().filter(|l| !l.dropped).map(|l| l.kind.as_str()).collect()
+    }
+
+    #[test]
+    fn test_layers_in_order_and_empty_skipped() {
+        let mut settings = PromptLayerSettings { safety: "Never share credentials.".into(), ..Default::default() };
+        settings.projects.insert("/work/app".into(), "Use tabs.".into());
+        let spec = PromptSpec {
+            language:     Some("English".into()),
+            project_root: Some("/work/app/".into()),
+            tools:        vec!["file_edit".into(), "unknown".into()],
+            ..Default::default()
+        };
+        let c = compose(&settings, &spec);
+        assert_eq!(kinds(&c), ["language", "safety", "tools", "project"]);
+        let text = c.text.unwrap();
+        assert!(text.find("EXCLUSIVELY in English").unwrap() < text.find("Never share").unwrap());
+        assert!(text.ends_with("Use tabs."));
+
+        assert_eq!(compose(&PromptLayerSettings::default(), &PromptSpec::default()).text, None);
+    }
+
+    #[test]
+    fn test_persona_card_overrides_global() {
+        let settings = PromptLayerSettings { global_persona: "You are terse.".into(), ..Default::default() };
+        let card = Persona { name: "Aria".into(), personality: "cheerful".into(), ..Default::default() };
+        let spec = PromptSpec { persona: Some(card), ..Default::default() };
+        assert_eq!(compose(&settings, &spec).text.unwrap(), "You are Aria.\nPersonality: cheerful");
+        assert_eq!(compose(&settings, &PromptSpec::default()).text.unwrap(), "You are terse.");
+    }
+
+    #[test]
+    fn test_budget_trims_later_layers_first() {
+        let settings = PromptLayerSettings {
+            safety:         "rule ".repeat(100),   // ≈125 tokens
+            global_persona: "persona ".repeat(400), // ≈800 tokens
+            total_budget:   300,
+            ..Default::default()
+        };
+        let c = compose(&settings, &PromptSpec::default());
+        let persona = c.layers.iter().find(|l| l.kind == "persona").unwrap();
+        let safety = c.layers.iter().find(|l| l.kind == "safety").unwrap();
+        assert!(persona.truncated && !safety.truncated);
+        assert!(c.tokens <= 300);
+
+        let tight = compose(&settings, &PromptSpec { budget: Some(140), ..Default::default() });
+        assert_eq!(kinds(&tight), ["safety"]);
+        assert!(tight.layers.iter().any(|l| l.kind == "persona" && l.dropped));
+    }
+
+    #[test]
+    fn test_truncate_tokens_prefers_word_break() {
+        assert_eq!(truncate_tokens("short", 10), "short");
+        assert_eq!(truncate_tokens("alphabet soup is delicious", 4), "alphabet soup…");
+        assert_eq!(truncate_tokens("abcdefghijklmnopqrstuvwxyz", 2), "abcdefg…");
+    }
+}
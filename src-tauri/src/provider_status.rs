@@ -0,0 +1,169 @@
+// provider_status.rs — "is the provider up?" before the user sends a request
+//
+// Cloud providers are checked through their public status pages
+// (Atlassian Statuspage `/api/v2/status.json`); OpenRouter has no status
+// API, so its public model list is probed instead. Local servers are probed
+// for reachability. All checks run in parallel and results are cached for a
+// few minutes so the UI can poll freely.
+//
+// Tauri commands exposed:
+//   get_provider_status → Vec<ProviderStatus>
+
+use crate::http;
+use futures_util::future::join_all;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+const CACHE_TTL: Duration     = Duration::from_secs(180);
+const CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+const LOCAL_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// (provider id, Statuspage summary URL)
+const STATUS_PAGES: &[(&str, &str)] = &[
+    ("openai",    "https://status.openai.com/api/v2/status.json"),
+    ("anthropic", "https://status.anthropic.com/api/v2/status.json"),
+    ("deepseek",  "https://status.deepseek.com/api/v2/status.json"),
+];
+const OPENROUTER_PROBE: &str = "https://openrouter.ai/api/v1/models";
+
+static CACHE: Mutex<Vec<(String, Instant, ProviderStatus)>> = Mutex::new(Vec::new());
+
+// ── Types ──────────────────────────────────────────────────────────────────
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ProviderStatus {
+    /// "openai" | "anthropic" | … | "local:<url>"
+    pub provider:    String,
+    /// "operational" | "degraded" | "outage" | "maintenance" | "unreachable"
+    pub state:       String,
+    /// Status page description ("Partial System Outage") or error text
+    pub description: Option<String>,
+    pub latency_ms:  Option<u64>,
+}
+
+// ── Tauri command ──────────────────────────────────────────────────────────
+
+/// Status of every cloud provider plus the given local server URLs.
+#[tauri::command]
+pub async fn get_provider_status(local_urls: Option<Vec<String>>, refresh: Option<bool>) -> Vec<ProviderStatus> {
+    let mut targets: Vec<(String, String)> = STATUS_PAGES.iter()
+        .map(|(id, url)| (id.to_string(), url.to_string()))
+        .collect();
+    targets.push(("openrouter".into(), OPENROUTER_PROBE.into()));
+    for url in local_urls.unwrap_or_default().iter().map(|u| u.trim().trim_end_matches('/')) {
+        if !url.is_empty() {
+            targets.push((format!("local:{}", url), url.to_string()));
+        }
+    }
+
+    let refresh = refresh.unwrap_or(false);
+    join_all(targets.into_iter().map(|(id, url)| async move {
+        if !refresh {
+            if let Some(hit) = cached(&id) {
+                return hit;
+            }
+        }
+        let status = check(&id, &url).await;
+        if let Ok(mut cache) = CACHE.lock() {
+            cache.retain(|(p, ..)| p != &id);
+            cache.push((id, Instant::now(), status.clone()));
+        }
+        status
+    })).await
+}
+
+fn cached(provider: &str) -> Option<ProviderStatus> {
+    CACHE.lock().ok()?
+        .iter()
+        .find(|(p, at, _)| p == provider && at.elapsed() < CACHE_TTL)
+        .map(|(.., s)| s.clone())
+}
+
+// ── Checks ─────────────────────────────────────────────────────────────────
+
+async fn check(provider: &str, url: &str) -> ProviderStatus {
+    let is_status_page = STATUS_PAGES.iter().any(|(id, _)| *id == provider);
+    let timeout = if provider.starts_with("local:") { LOCAL_TIMEOUT } else { CHECK_TIMEOUT };
+    let unreachable = |description: String| ProviderStatus {
+        provider:    provider.to_string(),
+        state:       "unreachable".into(),
+        description: Some(description),
+        latency_ms:  None,
+    };
+
+    let client = match http::client(http::Timeout::Api) {
+        Ok(c)  => c,
+        Err(e) => return unreachable(e.to_string()),
+    };
+    let started = Instant::now();
+    let resp = match client.get(url).timeout(timeout).send().await {
+        Ok(r)  => r,
+        Err(e) => return unreachable(e.to_string()),
+    };
+    let latency_ms = Some(started.elapsed().as_millis() as u64);
+
+    if is_status_page {
+        let json: Value = resp.json().await.unwrap_or_default();
+        let (state, description) = statuspage_state(&json);
+        return ProviderStatus { provider: provider.to_string(), state: state.into(), description, latency_ms };
+    }
+
+    // Reachability probe: any HTTP answer from a local server means it is up
+    // (many return 404 on "/"); a cloud probe must succeed.
+    let status = resp.status();
+    let up = provider.starts_with("local:") || status.is_success();
+    ProviderStatus {
+        provider:    provider.to_string(),
+        state:       if up { "operational" } else if status.is_server_error() { "outage" } else { "degraded" }.into(),
+        description: (!status.is_success()).then(|| format!("HTTP {}", status)),
+        latency_ms,
+    }
+}
+
+/// Map a Statuspage `status.indicator` to our state names.
+fn statuspage_state(json: &Value) -> (&'static str, Option<String>) {
+    let description = json["status"]["description"].as_str().map(String::from);
+    let state = match json["status"]["indicator"].as_str() {
+        Some("none")                => "operational",
+        Some("minor")               => "degraded",
+        Some("major" | "critical")  => "outage",
+        Some("maintenance")         => "maintenance",
+        _                           => "unreachable",
+    };
+    (state, description)
+}
+
+// ── Unit tests ─────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_statuspage_indicator_mapping() {
+        let j = json!({ "status": { "indicator": "minor", "description": "Partially Degraded Service" } });
+        assert_eq!(statuspage_state(&j), ("degraded", Some("Partially Degraded Service".into())));
+        assert_eq!(statuspage_state(&json!({ "status": { "indicator": "critical" } })).0, "outage");
+        assert_eq!(statuspage_state(&json!({})).0, "unreachable");
+    }
+
+    #[test]
+    fn test_cache_returns_fresh_entries_only() {
+        let status = ProviderStatus {
+            provider:    "test-provider".into(),
+            state:       "operational".into(),
+            description: None,
+            latency_ms:  Some(12),
+        };
+        CACHE.lock().unwrap().push(("test-provider".into(), Instant::now(), status.clone()));
+        assert_eq!(cached("test-provider"), Some(status.clone()));
+
+        if let Some(stale) = Instant::now().checked_sub(CACHE_TTL * 2) {
+            CACHE.lock().unwrap().push(("test-stale".into(), stale, status));
+            assert_eq!(cached("test-stale"), None);
+        }
+    }
+}
@@ -0,0 +1,454 @@
+// query_prep.rs — lightweight local cleanup of web-search queries
+//
+// Runs before a query is dispatched to a search backend (opt-in via
+// `WebSearchRequest.preprocess`). No network, no LLM — just heuristics:
+//   • strip conversational filler ("can you please search for …")
+//   • quote error messages so engines match them verbatim
+//   • reduce pasted stack traces to the error line + a few key symbols
+//   • drop machine-specific noise (absolute paths, hex addresses, line:col)
+//   • clamp length / quoting per backend
+
+use regex::Regex;
+use std::sync::OnceLock;
+
+// ── Per-backend tuning ───────────────────────────────────────────────────
+
+/// How aggressively a query is shaped for a given backend.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BackendTuning {
+    /// Hard cap on the final query length (chars)
+    pub max_chars:  usize,
+    /// Maximum number of quoted phrases kept in the query
+    pub max_quoted: usize,
+    /// Whether the backend honours "exact phrase" quoting at all
+    pub quotes:     bool,
+}
+
+pub fn tuning_for(backend: &str) -> BackendTuning {
+    match backend {
+        // Brave caps q at 400 chars and supports phrase operators
+        "brave"      => BackendTuning { max_chars: 380, max_quoted: 2, quotes: true },
+        // Bing accepts long queries, but ranking degrades past a few hundred chars
+        "bing"       => BackendTuning { max_chars: 380, max_quoted: 2, quotes: true },
+        // Google ignores everything past 32 words
+        "serper" | "serpapi" => BackendTuning { max_chars: 250, max_quoted: 2, quotes: true },
+        // SearXNG fans out to several engines — keep it short so all of them accept it
+        "searxng"    => BackendTuning { max_chars: 200, max_quoted: 1, quotes: true },
+        // DDG Lite silently returns nothing for long, heavily quoted queries
+        "duckduckgo" => BackendTuning { max_chars: 150, max_quoted: 1, quotes: true },
+        // Stack Exchange matches every word against question text — long queries find nothing
+        "stackexchange" => BackendTuning { max_chars: 120, max_quoted: 1, quotes: true },
+        _            => BackendTuning { max_chars: 200, max_quoted: 1, quotes: true },
+    }
+}
+
+// ── Public entry point ───────────────────────────────────────────────────
+
+/// Clean up `query` for `backend`. Returns the original (trimmed) query when
+/// preprocessing would leave nothing useful behind.
+pub fn preprocess_query(query: &str, backend: &str) -> String {
+    let tuning = tuning_for(backend);
+    let trimmed = query.trim();
+    if trimmed.is_empty() {
+        return String::new();
+    }
+
+    let (quoted, rest) = if looks_like_stack_trace(trimmed) {
+        let key = stack_trace_key_phrases(trimmed);
+        (key.error_line.into_iter().collect::<Vec<_>>(), key.symbols.join(" "))
+    } else {
+        split_error_phrases(trimmed)
+    };
+
+    let rest = strip_filler(&strip_noise(&rest));
+
+    let mut parts: Vec<String> = Vec::new();
+    for phrase in quoted.iter().take(tuning.max_quoted) {
+        let phrase = strip_noise(phrase);
+        if phrase.is_empty() { continue; }
+        parts.push(if tuning.quotes { format!("\"{}\"", phrase) } else { phrase });
+    }
+    if !rest.is_empty() {
+        parts.push(rest);
+    }
+
+    let out = clamp_words(&parts.join(" "), tuning.max_chars);
+    if out.trim_matches('"').trim().is_empty() {
+        clamp_words(trimmed, tuning.max_chars)
+    } else {
+        out
+    }
+}
+
+// ── Error / stack-trace detection ─────────────────────────────────────────
+
+/// The searchable core of a stack trace.
+#[derive(Debug, Default, PartialEq)]
+pub struct StackTraceKey {
+    /// The error/exception message line (noise stripped)
+    pub error_line: Option<String>,
+    /// Up to three function / symbol names from the top frames
+    pub symbols:    Vec<String>,
+}
+
+/// True when the text has several lines that look like stack frames.
+pub fn looks_like_stack_trace(text: &str) -> bool {
+    let frame_re = frame_regex();
+    text.lines().filter(|l| frame_re.is_match(l)).count() >= 2
+}
+
+/// Extract the error line and top frame symbols from a stack trace.
+pub fn stack_trace_key_phrases(text: &str) -> StackTraceKey {
+    let frame_re = frame_regex();
+    let msg_re   = error_message_regex();
+    let error_re = error_line_regex();
+
+    // Prefer a real `Kind: message` line (Python puts it last, JS/Rust first);
+    // fall back to any line that merely mentions an error.
+    let lines: Vec<&str> = text.lines().map(str::trim).filter(|l| !frame_re.is_match(l)).collect();
+    let error_line = lines.iter()
+        .find(|l| msg_re.is_match(l))
+        .or_else(|| lines.iter().find(|l| error_re.is_match(l) && !l.starts_with("Traceback")))
+        .map(|l| strip_noise(l));
+
+    let mut symbols: Vec<String> = Vec::new();
+    for cap in frame_re.captures_iter(text) {
+        let sym = cap.iter().skip(1).flatten().next().map(|m| m.as_str().trim().to_string());
+        if let Some(sym) = sym {
+            // Runtime / std frames are noise for search purposes
+            if sym.is_empty() || is_runtime_symbol(&sym) || symbols.contains(&sym) { continue; }
+            symbols.push(sym);
+            if symbols.len() >= 3 { break; }
+        }
+    }
+
+    StackTraceKey { error_line, symbols }
+}
+
+/// Split a single-line query into (error phrases to quote, remaining text).
+fn split_error_phrases(query: &str) -> (Vec<String>, String) {
+    // Already-quoted phrases are kept as-is
+    static QUOTE_RE: OnceLock<Regex> = OnceLock::new();
+    let quote_re = cached(&QUOTE_RE, r#""([^"]{3,})""#);
+    let mut quoted: Vec<String> = quote_re
+        .captures_iter(query)
+        .filter_map(|c| c.get(1).map(|m| m.as_str().trim().to_string()))
+        .collect();
+    let rest = quote_re.replace_all(query, " ").to_string();
+
+    // `TypeError: x is not a function`, `error[E0382]: borrow of moved value`,
+    // `error TS2345: Argument of type …`
+    let msg_re = error_message_regex();
+    let rest = if let Some(m) = msg_re.find(&rest) {
+        quoted.push(m.as_str().trim().trim_end_matches(['.', ',']).to_string());
+        format!("{} {}", &rest[..m.start()], &rest[m.end()..])
+    } else {
+        rest
+    };
+
+    (quoted, rest)
+}
+
+/// Compiles a built-in pattern once into `cell`.
+fn cached(cell: &'static OnceLock<Regex>, pattern: &str) -> &'static Regex {
+    cell.get_or_init(|| Regex::new(pattern).expect("built-in query pattern"))
+}
+
+fn frame_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    cached(&RE, concat!(
+        r"(?m)^\s*(?:",
+        // JS / Java / C#:  at foo.bar (file.js:1:2)   |   at com.x.Y.method(Y.java:10)
+        r"at\s+(?:async\s+)?([A-Za-z_$][\w$.<>]*)\s*\(",
+        // Python:  File "x.py", line 3, in handler
+        r#"|File\s+"[^"]+",\s+line\s+\d+,\s+in\s+([\w<>]+)"#,
+        // Rust backtrace:  12: my_crate::module::func
+        r"|\d+:\s+(?:0x[0-9a-fA-F]+\s+-\s+)?([A-Za-z_][\w:<>]*)",
+        // Go:  main.handler(...)  followed by a file line
+        r"|([a-z_][\w]*\.[A-Za-z_][\w]*)\(.*\)\s*$",
+        r")",
+    ))
+}
+
+fn error_line_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    cached(&RE, r"(?i)(error|exception|panicked at|fatal|traceback|undefined|cannot|failed)")
+}
+
+fn error_message_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    cached(&RE, concat!(
+        r"(?:[A-Z][A-Za-z]*(?:Error|Exception)|error(?:\[E\d{4}\])?|error\s+TS\d{4}|panicked at)",
+        r":?\s+[^\n]{4,}",
+    ))
+}
+
+fn is_runtime_symbol(sym: &str) -> bool {
+    const PREFIXES: &[&str] = &[
+        "std::", "core::", "alloc::", "tokio::", "__rust", "rust_begin_unwind",
+        "node:", "Module.", "Object.<anonymous>", "processTicksAndRejections",
+        "java.", "sun.", "jdk.", "runtime.", "<module>",
+    ];
+    PREFIXES.iter().any(|p| sym.starts_with(p))
+}
+
+// ── Error signatures (search_error) ───────────────────────────────────────
+
+/// The essential, machine-independent part of a compiler error or exception.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ErrorSignature {
+    /// "rust" | "typescript" | "python" | "javascript" | "java" | "go" | "c/c++"
+    pub language: Option<&'static str>,
+    /// Error code or exception class, e.g. "E0382", "TS2345", "KeyError"
+    pub code:     Option<String>,
+    /// Generalized message (identifiers, paths and positions removed)
+    pub message:  String,
+    /// Top user-code frames when the text contains a stack trace
+    pub symbols:  Vec<String>,
+}
+
+/// Recognize common compiler-error / stack-trace formats in `text`.
+/// Returns None when nothing error-like is found.
+pub fn extract_error_signature(text: &str) -> Option<ErrorSignature> {
+    let text = text.trim();
+    if text.is_empty() { return None; }
+
+    let symbols = if looks_like_stack_trace(text) {
+        stack_trace_key_phrases(text).symbols
+    } else {
+        Vec::new()
+    };
+    let lines: Vec<&str> = text.lines().map(str::trim).collect();
+
+    for (re, lang) in signature_patterns() {
+        for line in &lines {
+            let Some(cap) = re.captures(line) else { continue };
+            let code = cap.get(1)
+                .map(|m| m.as_str().to_string())
+                .filter(|c| !c.is_empty() && c != "error");
+            let raw = cap.get(2).map(|m| m.as_str()).unwrap_or("");
+
+            let language = lang.or_else(|| guess_language(text, code.as_deref()));
+            let message  = generalize_message(raw);
+            if message.is_empty() && code.is_none() { continue; }
+
+            return Some(ErrorSignature { language, code, message, symbols });
+        }
+    }
+
+    // New-style Rust panic: `thread 'main' panicked at src/main.rs:2:5:` + message line
+    if let Some(i) = lines.iter().position(|l| l.contains("panicked at")) {
+        let message = generalize_message(lines.get(i + 1).copied().unwrap_or(""));
+        if !message.is_empty() {
+            return Some(ErrorSignature { language: Some("rust"), code: None, message, symbols });
+        }
+    }
+    None
+}
+
+/// (regex, language) — group 1 = code/class, group 2 = message.
+/// First match wins, most specific first.
+fn signature_patterns() -> &'static [(Regex, Option<&'static str>)] {
+    static PATTERNS: OnceLock<Vec<(Regex, Option<&'static str>)>> = OnceLock::new();
+    PATTERNS.get_or_init(|| {
+        [
+            (r"error\[(E\d{4})\]:\s*(.+)",                                    Some("rust")),
+            (r"error\s+(TS\d{4}):\s*(.+)",                                    Some("typescript")),
+            (r":\d+:\d+:\s+(?:fatal\s+)?(error):\s*(.+)",                     Some("c/c++")),
+            (r"^panic:\s*()(.+)",                                             Some("go")),
+            (r"panicked at '()([^']+)'",                                      Some("rust")),
+            (r"^(?:[\w$]+\.)*([A-Z][\w$]*(?:Error|Exception))(?::\s*(.*))?$", None),
+            (r"(?i)^()error:\s*(.+)",                                         None),
+        ]
+        .into_iter()
+        .map(|(pat, lang)| (Regex::new(pat).expect("built-in error pattern"), lang))
+        .collect()
+    })
+}
+
+/// Search queries for an error: Stack Overflow, GitHub issues, then the open web.
+pub fn error_search_queries(sig: &ErrorSignature) -> Vec<String> {
+    let mut core: Vec<String> = Vec::new();
+    if let Some(lang) = sig.language { core.push(lang.to_string()); }
+    if let Some(code) = &sig.code { core.push(code.clone()); }
+    if !sig.message.is_empty() { core.push(format!("\"{}\"", sig.message)); }
+    let core = core.join(" ");
+
+    let mut general = core.clone();
+    if let Some(sym) = sig.symbols.first() {
+        general.push(' ');
+        general.push_str(sym);
+    }
+
+    vec![
+        format!("{} site:stackoverflow.com", core),
+        format!("{} site:github.com issues", core),
+        general,
+    ]
+}
+
+fn guess_language(text: &str, code: Option<&str>) -> Option<&'static str> {
+    if text.contains("Traceback (most recent call last)") || text.contains(".py\", line") {
+        Some("python")
+    } else if text.contains(".java:") || code.map(|c| c.ends_with("Exception")).unwrap_or(false) {
+        Some("java")
+    } else if text.contains(".js:") || text.contains(".ts:") || text.contains("node:") {
+        Some("javascript")
+    } else {
+        None
+    }
+}
+
+/// Drop user-specific identifiers, quoted values and locations from a message
+/// so it matches other people's reports of the same error.
+fn generalize_message(raw: &str) -> String {
+    static QUOTED_RE: OnceLock<Regex> = OnceLock::new();
+    let quoted_re = cached(&QUOTED_RE, r#"`[^`]*`|'[^']*'|"[^"]*""#);
+    let s = quoted_re.replace_all(raw, " ");
+    let s = strip_noise(&s);
+    let s = s.trim().trim_end_matches([':', '.', ',']).trim().to_string();
+    let s: String = s.chars().take(120).collect();
+    s.trim().to_string()
+}
+
+// ── Text cleanup ──────────────────────────────────────────────────────────
+
+/// Remove machine-specific details that never match other people's reports.
+fn strip_noise(s: &str) -> String {
+    static PATH_RE: OnceLock<Regex> = OnceLock::new();
+    static LOC_RE: OnceLock<Regex> = OnceLock::new();
+    static HEX_RE: OnceLock<Regex> = OnceLock::new();
+    static WS_RE: OnceLock<Regex> = OnceLock::new();
+    // Only paths that start a token — leaves URLs (`https://host/a/b`) alone
+    let path_re = cached(&PATH_RE, r#"(^|[\s"'(])(?:[A-Za-z]:\\|/)(?:[^\s:"'()]+[/\\])+([^\s:"'()/\\]+)"#);
+    let s = path_re.replace_all(s, "$1$2");
+    // `:line[:col]` only after a source file name — ports and times stay
+    let loc_re = cached(&LOC_RE, concat!(
+        r"\b([\w.-]+\.(?:rs|[cm]?[jt]sx?|py|pyx|java|kt|scala|go|c|cc|cpp|cxx|h|hh|hpp|cs|fs|rb|php|swift|m|mm|dart|lua|exs?|erl|zig|vue|svelte))",
+        r":\d+(?::\d+)?\b",
+    ));
+    let s = loc_re.replace_all(&s, "$1");
+    let hex_re = cached(&HEX_RE, r"\b0x[0-9a-fA-F]{4,}\b");
+    let s = hex_re.replace_all(&s, "");
+    let ws_re = cached(&WS_RE, r"\s+");
+    ws_re.replace_all(s.trim(), " ").to_string()
+}
+
+/// Conversational lead-ins and politeness words that only dilute a query.
+static FILLER_PHRASES: &[&str] = &[
+    "can you please", "could you please", "can you", "could you", "would you",
+    "please", "search the web for", "search for", "look up",
+    "i want to know", "i need to know", "tell me", "help me", "hey",
+    "thanks", "thank you",
+];
+
+fn strip_filler(s: &str) -> String {
+    let mut words: Vec<&str> = s.split_whitespace().collect();
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for phrase in FILLER_PHRASES {
+            let pw: Vec<&str> = phrase.split(' ').collect();
+            if let Some(pos) = words.windows(pw.len()).position(|w| {
+                w.iter().zip(&pw).all(|(a, b)| a.trim_matches([',', '?', '!', '.']).eq_ignore_ascii_case(b))
+            }) {
+                words.drain(pos..pos + pw.len());
+                changed = true;
+            }
+        }
+    }
+    words.join(" ").trim_end_matches(['?', '!']).trim().to_string()
+}
+
+/// Cut to `max_chars` at a word boundary without breaking a quoted phrase open.
+fn clamp_words(s: &str, max_chars: usize) -> String {
+    if s.chars().count() <= max_chars {
+        return s.to_string();
+    }
+    let mut out = String::new();
+    for word in s.split_whitespace() {
+        if out.chars().count() + word.chars().count() + 1 > max_chars { break; }
+        if !out.is_empty() { out.push(' '); }
+        out.push_str(word);
+    }
+    if out.matches('"').count() % 2 == 1 {
+        out.push('"');
+    }
+    out
+}
+
+// ── Unit tests ────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strip_filler() {
+        assert_eq!(strip_filler("can you please search for rust async traits?"), "rust async traits");
+        assert_eq!(strip_filler("tokio select macro"), "tokio select macro");
+    }
+
+    #[test]
+    fn test_error_message_is_quoted() {
+        let q = preprocess_query("TypeError: foo is not a function in my react app", "brave");
+        assert!(q.starts_with("\"TypeError: foo is not a function"));
+    }
+
+    #[test]
+    fn test_stack_trace_reduced() {
+        let trace = "Traceback (most recent call last):\n  \
+            File \"/home/me/app/main.py\", line 10, in handle_request\n    x()\n  \
+            File \"/home/me/app/util.py\", line 3, in parse_body\n    \
+            KeyError: 'user_id'";
+        let key = stack_trace_key_phrases(trace);
+        assert_eq!(key.symbols, vec!["handle_request", "parse_body"]);
+        let q = preprocess_query(trace, "duckduckgo");
+        assert!(!q.contains("/home/me"));
+        assert!(q.contains("parse_body"));
+    }
+
+    #[test]
+    fn test_noise_stripped() {
+        assert_eq!(strip_noise("at /usr/src/app/index.js:12:5 0xdeadbeef"), "at index.js");
+        assert_eq!(strip_noise("panicked at src/main.rs:2:5"), "panicked at src/main.rs");
+        assert_eq!(strip_noise("connect ECONNREFUSED 127.0.0.1:5432"), "connect ECONNREFUSED 127.0.0.1:5432");
+        assert_eq!(strip_noise("localhost:8080 timed out at 12:30"), "localhost:8080 timed out at 12:30");
+    }
+
+    #[test]
+    fn test_extract_rust_compiler_error() {
+        let sig = extract_error_signature(
+            "error[E0382]: borrow of moved value: `config`\n --> src/main.rs:10:5"
+        ).unwrap();
+        assert_eq!(sig.language, Some("rust"));
+        assert_eq!(sig.code.as_deref(), Some("E0382"));
+        assert_eq!(sig.message, "borrow of moved value");
+    }
+
+    #[test]
+    fn test_extract_python_exception() {
+        let sig = extract_error_signature(
+            "Traceback (most recent call last):\n  File \"app.py\", line 3, in <module>\n\
+             ModuleNotFoundError: No module named 'requests'"
+        ).unwrap();
+        assert_eq!(sig.language, Some("python"));
+        assert_eq!(sig.code.as_deref(), Some("ModuleNotFoundError"));
+        assert_eq!(sig.message, "No module named");
+        let queries = error_search_queries(&sig);
+        assert!(queries[0].ends_with("site:stackoverflow.com"));
+        assert!(queries[1].contains("site:github.com"));
+    }
+
+    #[test]
+    fn test_extract_none_for_plain_text() {
+        assert!(extract_error_signature("how do I center a div").is_none());
+    }
+
+    #[test]
+    fn test_clamp_respects_backend() {
+        let long = "word ".repeat(100);
+        assert!(preprocess_query(&long, "duckduckgo").chars().count() <= 150);
+        assert!(preprocess_query(&long, "brave").chars().count() <= 380);
+    }
+}
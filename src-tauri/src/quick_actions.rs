@@ -0,0 +1,317 @@
+// quick_actions.rs — taskbar jump list (Windows) and dock menu (macOS)
+//
+// Both offer the same QUICK_ACTIONS, dispatched through the action registry
+// like hotkeys and the tray.
+//
+// A dock menu item runs inside the app and dispatches directly. A jump-list
+// task instead starts a new process with `--action <id>`: that process hands
+// the id to the running instance over a loopback socket and exits (see
+// forward_to_running), or — when nothing is running — starts normally and
+// runs the action once the window is up.
+//
+// macOS only shows a dock icon (and so the dock menu) while the app is not in
+// accessory mode; set_dock_visible switches between the two and follows the
+// overlay / windowed mode.
+
+use crate::actions;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{Ipv4Addr, SocketAddr, TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::time::Duration;
+use tauri::AppHandle;
+
+/// (action id, menu title)
+pub const QUICK_ACTIONS: &[(&str, &str)] = &[
+    ("capture_screen", "Capture screen"),
+    ("quick_ask",      "Quick ask"),
+    ("toggle_window",  "Show / hide window"),
+];
+
+const ACTION_ARG: &str = "--action";
+const FORWARD_TIMEOUT: Duration = Duration::from_millis(500);
+
+// ── Launch arguments / forwarding ──────────────────────────────────────────
+
+/// Quick action requested on the command line (`--action <id>`).
+pub fn action_from_args() -> Option<String> {
+    parse_action(std::env::args().skip(1))
+}
+
+fn parse_action(mut args: impl Iterator<Item = String>) -> Option<String> {
+    while let Some(arg) = args.next() {
+        let id = match arg.strip_prefix(ACTION_ARG) {
+            Some("") => args.next()?,
+            Some(rest) => match rest.strip_prefix('=') {
+                Some(id) => id.to_string(),
+                None => continue,
+            },
+            None => continue,
+        };
+        return QUICK_ACTIONS.iter().any(|(a, _)| *a == id).then_some(id);
+    }
+    None
+}
+
+/// "<port> <token>" of the running instance's listener.
+fn instance_file() -> PathBuf {
+    std::env::temp_dir().join("ai-assistant-instance")
+}
+
+/// Hand `action` to an already running instance. False when none answers,
+/// in which case the caller should start up normally.
+pub fn forward_to_running(action: &str) -> bool {
+    let Ok(info) = std::fs::read_to_string(instance_file()) else { return false };
+    let Some((port, token)) = info.trim().split_once(' ') else { return false };
+    let Ok(port) = port.parse::<u16>() else { return false };
+
+    let addr = SocketAddr::from((Ipv4Addr::LOCALHOST, port));
+    let Ok(mut stream) = TcpStream::connect_timeout(&addr, FORWARD_TIMEOUT) else { return false };
+    let _ = stream.set_read_timeout(Some(FORWARD_TIMEOUT));
+    if writeln!(stream, "{} {}", token, action).is_err() {
+        return false;
+    }
+    // The listener answers "ok" once the action is dispatched
+    let mut reply = String::new();
+    BufReader::new(stream).read_line(&mut reply).is_ok() && reply.trim() == "ok"
+}
+
+/// Accept actions forwarded by jump-list launches.
+fn spawn_listener(app: AppHandle) {
+    let listener = match TcpListener::bind((Ipv4Addr::LOCALHOST, 0)) {
+        Ok(l) => l,
+        Err(e) => { log::warn!("quick_actions: cannot listen for forwarded actions: {}", e); return; }
+    };
+    let Ok(port) = listener.local_addr().map(|a| a.port()) else { return };
+    let token = format!("{:x}{:x}", std::process::id(), std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or(0));
+    if let Err(e) = std::fs::write(instance_file(), format!("{} {}", port, token)) {
+        log::warn!("quick_actions: cannot write instance file: {}", e);
+        return;
+    }
+
+    std::thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let _ = stream.set_read_timeout(Some(FORWARD_TIMEOUT));
+            let mut line = String::new();
+            let mut reader = BufReader::new(&stream);
+            if reader.read_line(&mut line).is_err() {
+                continue;
+            }
+            let Some((got, action)) = line.trim().split_once(' ') else { continue };
+            if got != token || !QUICK_ACTIONS.iter().any(|(a, _)| *a == action) {
+                log::warn!("quick_actions: rejected forwarded request");
+                continue;
+            }
+            match actions::dispatch(&app, action, None) {
+                Ok(()) => { let _ = (&stream).write_all(b"ok\n"); }
+                Err(e) => log::warn!("quick_actions: {}", e),
+            }
+        }
+    });
+}
+
+// ── Setup ──────────────────────────────────────────────────────────────────
+
+/// Start the forwarding listener and install the platform menu.
+pub fn install(app: &AppHandle) {
+    spawn_listener(app.clone());
+    if let Err(e) = platform::install(app) {
+        log::warn!("quick_actions: {}", e);
+    }
+}
+
+/// Show or hide the macOS dock icon (accessory mode hides it, and with it
+/// the dock menu). No-op elsewhere.
+pub fn set_dock_visible(visible: bool) {
+    platform::set_dock_visible(visible);
+}
+
+// ═══════════════════════════════════════════════════════════════════════
+// Windows — ICustomDestinationList user tasks
+// ═══════════════════════════════════════════════════════════════════════
+
+#[cfg(target_os = "windows")]
+mod platform {
+    use super::{ACTION_ARG, QUICK_ACTIONS};
+    use windows::core::{ComInterface, HSTRING, PWSTR};
+    use windows::Win32::Storage::EnhancedStorage::PKEY_Title;
+    use windows::Win32::System::Com::StructuredStorage::{PropVariantClear, PROPVARIANT};
+    use windows::Win32::System::Com::{
+        CoCreateInstance, CoInitializeEx, CoTaskMemAlloc, CLSCTX_INPROC_SERVER, COINIT_APARTMENTTHREADED, VT_LPWSTR,
+    };
+    use windows::Win32::UI::Shell::Common::{IObjectArray, IObjectCollection};
+    use windows::Win32::UI::Shell::PropertiesSystem::IPropertyStore;
+    use windows::Win32::UI::Shell::{DestinationList, EnumerableObjectCollection, ICustomDestinationList, IShellLinkW, ShellLink};
+
+    pub fn install(_app: &tauri::AppHandle) -> Result<(), String> {
+        let exe = std::env::current_exe().map_err(|e| e.to_string())?;
+        let exe = HSTRING::from(exe.as_os_str());
+        unsafe {
+            // Already initialised on the UI thread by WebView2; the result only
+            // says which apartment we got
+            let _ = CoInitializeEx(None, COINIT_APARTMENTTHREADED);
+
+            let list: ICustomDestinationList = CoCreateInstance(&DestinationList, None, CLSCTX_INPROC_SERVER)
+                .map_err(|e| format!("jump list: {}", e))?;
+            let mut slots = 0u32;
+            let _removed: IObjectArray = list.BeginList(&mut slots).map_err(|e| format!("jump list: {}", e))?;
+            let tasks: IObjectCollection = CoCreateInstance(&EnumerableObjectCollection, None, CLSCTX_INPROC_SERVER)
+                .map_err(|e| format!("jump list: {}", e))?;
+
+            for (id, title) in QUICK_ACTIONS {
+                let link: IShellLinkW = CoCreateInstance(&ShellLink, None, CLSCTX_INPROC_SERVER)
+                    .map_err(|e| format!("jump list: {}", e))?;
+                link.SetPath(&exe).map_err(|e| e.to_string())?;
+                link.SetArguments(&HSTRING::from(format!("{} {}", ACTION_ARG, id))).map_err(|e| e.to_string())?;
+                link.SetIconLocation(&exe, 0).map_err(|e| e.to_string())?;
+                link.SetDescription(&HSTRING::from(*title)).map_err(|e| e.to_string())?;
+                set_title(&link.cast().map_err(|e| e.to_string())?, title)?;
+                tasks.AddObject(&link).map_err(|e| e.to_string())?;
+            }
+
+            let array: IObjectArray = tasks.cast().map_err(|e| e.to_string())?;
+            list.AddUserTasks(&array).map_err(|e| format!("jump list: {}", e))?;
+            list.CommitList().map_err(|e| format!("jump list: {}", e))?;
+        }
+        log::info!("quick_actions: jump list installed");
+        Ok(())
+    }
+
+    /// Jump-list entries take their label from PKEY_Title, not the link name.
+    unsafe fn set_title(store: &IPropertyStore, title: &str) -> Result<(), String> {
+        let wide: Vec<u16> = title.encode_utf16().chain(Some(0)).collect();
+        let buf = CoTaskMemAlloc(wide.len() * 2) as *mut u16;
+        if buf.is_null() {
+            return Err("jump list: out of memory".into());
+        }
+        std::ptr::copy_nonoverlapping(wide.as_ptr(), buf, wide.len());
+
+        let mut value = PROPVARIANT::default();
+        (*value.Anonymous.Anonymous).vt = VT_LPWSTR;
+        (*value.Anonymous.Anonymous).Anonymous.pwszVal = PWSTR(buf);
+        let result = store.SetValue(&PKEY_Title, &value).and_then(|_| store.Commit());
+        // Frees `buf`
+        let _ = PropVariantClear(&mut value);
+        result.map_err(|e| e.to_string())
+    }
+
+    pub fn set_dock_visible(_visible: bool) {}
+}
+
+// ═══════════════════════════════════════════════════════════════════════
+// macOS — applicationDockMenu: on the app delegate
+// ═══════════════════════════════════════════════════════════════════════
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use super::QUICK_ACTIONS;
+    use cocoa::appkit::{NSApp, NSApplicationActivationPolicy};
+    use cocoa::base::{id, nil};
+    use cocoa::foundation::NSString;
+    use objc::declare::ClassDecl;
+    use objc::runtime::{class_addMethod, Class, Object, Sel};
+    use objc::{class, msg_send, sel, sel_impl};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::OnceLock;
+
+    static APP: OnceLock<tauri::AppHandle> = OnceLock::new();
+    /// Retained NSMenu handed out by applicationDockMenu:
+    static MENU: AtomicUsize = AtomicUsize::new(0);
+
+    extern "C" fn dock_menu(_this: &Object, _sel: Sel, _app: id) -> id {
+        MENU.load(Ordering::SeqCst) as id
+    }
+
+    /// Menu item action; the item's tag is its index in QUICK_ACTIONS.
+    extern "C" fn run_quick_action(_this: &Object, _sel: Sel, sender: id) {
+        let tag: isize = unsafe { msg_send![sender, tag] };
+        let (Some(app), Some((action, _))) = (APP.get(), QUICK_ACTIONS.get(tag as usize)) else { return };
+        if let Err(e) = crate::actions::dispatch(app, action, None) {
+            log::warn!("quick_actions: {}", e);
+        }
+    }
+
+    pub fn install(app: &tauri::AppHandle) -> Result<(), String> {
+        let _ = APP.set(app.clone());
+        unsafe {
+            let mut decl = ClassDecl::new("AIAssistantQuickActionTarget", class!(NSObject))
+                .ok_or("dock menu: target class already registered")?;
+            decl.add_method(sel!(runQuickAction:), run_quick_action as extern "C" fn(&Object, Sel, id));
+            let target_class = decl.register();
+            let target: id = msg_send![target_class, new];
+
+            let menu: id = msg_send![class!(NSMenu), new];
+            for (i, (_, title)) in QUICK_ACTIONS.iter().enumerate() {
+                let title = NSString::alloc(nil).init_str(title);
+                let key = NSString::alloc(nil).init_str("");
+                let item: id = msg_send![class!(NSMenuItem), alloc];
+                let item: id = msg_send![item, initWithTitle: title action: sel!(runQuickAction:) keyEquivalent: key];
+                let _: () = msg_send![item, setTarget: target];
+                let _: () = msg_send![item, setTag: i as isize];
+                let _: () = msg_send![menu, addItem: item];
+            }
+            MENU.store(menu as usize, Ordering::SeqCst);
+
+            // tao owns the delegate; give its class the dock-menu hook
+            let delegate: id = msg_send![NSApp(), delegate];
+            if delegate.is_null() {
+                return Err("dock menu: no application delegate".into());
+            }
+            let class = (*delegate).class() as *const Class as *mut Class;
+            let imp: extern "C" fn(&Object, Sel, id) -> id = dock_menu;
+            class_addMethod(class, sel!(applicationDockMenu:), std::mem::transmute(imp), b"@@:@\0".as_ptr() as *const std::os::raw::c_char);
+        }
+        log::info!("quick_actions: dock menu installed");
+        Ok(())
+    }
+
+    pub fn set_dock_visible(visible: bool) {
+        let policy = if visible {
+            NSApplicationActivationPolicy::NSApplicationActivationPolicyRegular
+        } else {
+            NSApplicationActivationPolicy::NSApplicationActivationPolicyAccessory
+        };
+        unsafe { NSApp().setActivationPolicy_(policy); }
+    }
+}
+
+#[cfg(all(not(target_os = "macos"), not(target_os = "windows")))]
+mod platform {
+    /// Linux desktop entries declare their actions statically in the
+    /// .desktop file; `--action <id>` works there the same way.
+    pub fn install(_app: &tauri::AppHandle) -> Result<(), String> {
+        Ok(())
+    }
+
+    pub fn set_dock_visible(_visible: bool) {}
+}
+
+// ── Unit tests ─────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(a: &[&str]) -> impl Iterator<Item = String> {
+        a.iter().map(|s| s.to_string()).collect::<Vec<_>>().into_iter()
+    }
+
+    #[test]
+    fn test_parse_action() {
+        assert_eq!(parse_action(args(&["--action", "capture_screen"])).as_deref(), Some("capture_screen"));
+        assert_eq!(parse_action(args(&["--verbose", "--action=quick_ask"])).as_deref(), Some("quick_ask"));
+        assert_eq!(parse_action(args(&["--action", "quit"])), None); // not a quick action
+        assert_eq!(parse_action(args(&["--action"])), None);
+        assert_eq!(parse_action(args(&["--actions", "x"])), None);
+    }
+
+    #[test]
+    fn test_quick_actions_exist_in_registry() {
+        let all = actions::all();
+        for (id, _) in QUICK_ACTIONS {
+            assert!(all.iter().any(|a| a.id == *id), "unknown action {}", id);
+        }
+    }
+}
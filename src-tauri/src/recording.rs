@@ -0,0 +1,418 @@
+// recording.rs — lightweight screen recordings as PNG frame sequences
+//
+// A recording is a folder under <app-data>/recordings/<id>/ holding
+// frame_00000.png, frame_00001.png, … plus a recording.json manifest.
+// Frames come from the same full-screen capture path as the screenshot
+// commands, so the achievable frame rate is modest (a few fps) — enough to
+// show a UI interaction in an issue or chat.
+//
+// Tauri commands exposed:
+//   start_recording   → begins capturing on a background thread
+//   stop_recording    → stops it and returns the finished RecordingInfo
+//   list_recordings   → newest first
+//   delete_recording  → removes the folder
+//   export_recording  → gif | mp4 | frames (zip), returns the output path
+
+use crate::error::AppError;
+use crate::screen_capture;
+use crate::watchdog;
+use base64::{engine::general_purpose, Engine};
+use image::codecs::gif::{GifEncoder, Repeat};
+use image::{Delay, Frame, RgbaImage};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+const MANIFEST_FILE: &str = "recording.json";
+
+const DEFAULT_FPS: u32         = 4;
+const MAX_FPS: u32             = 15;
+const DEFAULT_MAX_SECONDS: u32 = 60;
+/// GIFs wider than this are downscaled unless the caller overrides it.
+const DEFAULT_GIF_MAX_WIDTH: u32 = 960;
+/// NeuQuant sampling factor handed to the GIF encoder (1 = best palette, 30 = fastest).
+const GIF_QUANT_SPEED: i32 = 10;
+
+/// The recording currently being captured, if any.
+static ACTIVE: Mutex<Option<ActiveRecording>> = Mutex::new(None);
+
+struct ActiveRecording {
+    id:     String,
+    stop:   Arc<AtomicBool>,
+    handle: std::thread::JoinHandle<Result<RecordingInfo, String>>,
+}
+
+// ── Types ──────────────────────────────────────────────────────────────────
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RecordingInfo {
+    pub id:          String,
+    /// Absolute path of the recording folder
+    pub path:        String,
+    /// Unix timestamp (seconds)
+    pub started_at:  u64,
+    /// Capture rate requested when recording
+    pub fps:         u32,
+    pub frame_count: usize,
+    pub width:       u32,
+    pub height:      u32,
+    pub duration_ms: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ExportRecordingRequest {
+    pub id:        String,
+    /// "gif" | "mp4" | "frames"
+    pub format:    String,
+    /// Playback rate; defaults to the capture rate (real time)
+    pub fps:       Option<u32>,
+    /// Downscale frames wider than this (gif default 960, others keep full size)
+    pub max_width: Option<u32>,
+}
+
+// ── Storage helpers ────────────────────────────────────────────────────────
+
+fn recordings_dir(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+    app.path_resolver()
+        .app_data_dir()
+        .ok_or_else(|| "Cannot resolve app data directory".to_string())
+        .map(|p| p.join("recordings"))
+}
+
+fn recording_path(app: &tauri::AppHandle, id: &str) -> Result<PathBuf, String> {
+    if id.is_empty() || !id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+        return Err(format!("Invalid recording id: {}", id));
+    }
+    let dir = recordings_dir(app)?.join(id);
+    if !dir.is_dir() {
+        return Err(format!("Recording not found: {}", id));
+    }
+    Ok(dir)
+}
+
+fn load_manifest(dir: &Path) -> Result<RecordingInfo, String> {
+    let s = std::fs::read_to_string(dir.join(MANIFEST_FILE))
+        .map_err(|e| format!("Cannot read recording manifest: {}", e))?;
+    serde_json::from_str(&s).map_err(|e| e.to_string())
+}
+
+fn frame_name(index: usize) -> String {
+    format!("frame_{:05}.png", index)
+}
+
+fn frame_paths(dir: &Path, count: usize) -> Vec<PathBuf> {
+    (0..count).map(|i| dir.join(frame_name(i))).filter(|p| p.is_file()).collect()
+}
+
+// ── Capture loop ───────────────────────────────────────────────────────────
+
+fn record_loop(dir: PathBuf, mut info: RecordingInfo, stop: Arc<AtomicBool>, max_frames: usize) -> Result<RecordingInfo, String> {
+    let interval = Duration::from_millis(frame_delay_ms(info.fps));
+    let started  = Instant::now();
+
+    while !stop.load(Ordering::Relaxed) && info.frame_count < max_frames {
+        let due = started + interval * info.frame_count as u32;
+        if let Some(wait) = due.checked_duration_since(Instant::now()) {
+            std::thread::sleep(wait);
+        }
+
+        let shot = match screen_capture::capture_frame() {
+            Ok(s)  => s,
+            Err(e) => { log::warn!("recording {}: capture failed: {}", info.id, e); break; }
+        };
+        let bytes = general_purpose::STANDARD.decode(&shot.base64).map_err(|e| e.to_string())?;
+        std::fs::write(dir.join(frame_name(info.frame_count)), bytes)
+            .map_err(|e| format!("Failed to write frame: {}", e))?;
+
+        if info.frame_count == 0 {
+            info.width  = shot.width;
+            info.height = shot.height;
+        }
+        info.frame_count += 1;
+    }
+
+    info.duration_ms = started.elapsed().as_millis() as u64;
+    let json = serde_json::to_string_pretty(&info).map_err(|e| e.to_string())?;
+    std::fs::write(dir.join(MANIFEST_FILE), json).map_err(|e| e.to_string())?;
+    log::info!("recording {}: {} frames in {} ms", info.id, info.frame_count, info.duration_ms);
+    Ok(info)
+}
+
+// ── Tauri commands ─────────────────────────────────────────────────────────
+
+/// Start recording the primary screen. Stops by itself after `max_seconds`
+/// (default 60) if `stop_recording` is not called first.
+#[tauri::command]
+pub fn start_recording(
+    app_handle:  tauri::AppHandle,
+    fps:         Option<u32>,
+    max_seconds: Option<u32>,
+) -> Result<RecordingInfo, AppError> {
+    let mut active = ACTIVE.lock().map_err(|e| e.to_string())?;
+    if active.as_ref().is_some_and(|a| !a.handle.is_finished()) {
+        return Err("A recording is already in progress".into());
+    }
+
+    let fps = fps.unwrap_or(DEFAULT_FPS).clamp(1, MAX_FPS);
+    let max_frames = (max_seconds.unwrap_or(DEFAULT_MAX_SECONDS) * fps) as usize;
+
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default();
+    let id  = format!("rec-{}", now.as_millis());
+    let dir = recordings_dir(&app_handle)?.join(&id);
+    std::fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+
+    let info = RecordingInfo {
+        id:          id.clone(),
+        path:        dir.to_string_lossy().to_string(),
+        started_at:  now.as_secs(),
+        fps,
+        frame_count: 0,
+        width:       0,
+        height:      0,
+        duration_ms: 0,
+    };
+
+    let stop = Arc::new(AtomicBool::new(false));
+    let handle = {
+        let (info, stop) = (info.clone(), stop.clone());
+        std::thread::spawn(move || record_loop(dir, info, stop, max_frames))
+    };
+    *active = Some(ActiveRecording { id, stop, handle });
+    Ok(info)
+}
+
+/// Stop the active recording and return its final manifest.
+#[tauri::command]
+pub async fn stop_recording() -> Result<RecordingInfo, AppError> {
+    let active = ACTIVE.lock().map_err(|e| e.to_string())?.take()
+        .ok_or("No recording in progress")?;
+    active.stop.store(true, Ordering::Relaxed);
+
+    let id = active.id;
+    tokio::task::spawn_blocking(move || active.handle.join())
+        .await?
+        .map_err(|_| format!("Recording thread for {} panicked", id))?
+        .map_err(AppError::from)
+}
+
+/// Id of the recording currently being captured, if any.
+pub(crate) fn active_recording_id() -> Option<String> {
+    ACTIVE.lock().ok()?
+        .as_ref()
+        .filter(|a| !a.handle.is_finished())
+        .map(|a| a.id.clone())
+}
+
+/// All finished recordings, newest first.
+#[tauri::command]
+pub fn list_recordings(app_handle: tauri::AppHandle) -> Result<Vec<RecordingInfo>, AppError> {
+    let root = recordings_dir(&app_handle)?;
+    let Ok(read) = std::fs::read_dir(&root) else { return Ok(vec![]) };
+
+    let mut out: Vec<RecordingInfo> = read
+        .filter_map(|e| e.ok())
+        .filter_map(|e| load_manifest(&e.path()).ok())
+        .collect();
+    out.sort_by(|a, b| b.started_at.cmp(&a.started_at));
+    Ok(out)
+}
+
+#[tauri::command]
+pub fn delete_recording(app_handle: tauri::AppHandle, id: String) -> Result<(), AppError> {
+    let active = ACTIVE.lock().map_err(|e| e.to_string())?;
+    if active.as_ref().is_some_and(|a| a.id == id && !a.handle.is_finished()) {
+        return Err("Stop the recording before deleting it".into());
+    }
+    let dir = recording_path(&app_handle, &id)?;
+    std::fs::remove_dir_all(&dir).map_err(|e| format!("Failed to delete recording: {}", e).into())
+}
+
+/// Export a recording as an animated GIF, an MP4 (requires ffmpeg on PATH)
+/// or a zip of the raw PNG frames. Returns the path of the written file.
+#[tauri::command]
+pub async fn export_recording(app_handle: tauri::AppHandle, req: ExportRecordingRequest) -> Result<String, AppError> {
+    let dir  = recording_path(&app_handle, &req.id)?;
+    let info = load_manifest(&dir)?;
+    let fps  = req.fps.unwrap_or(info.fps).clamp(1, 60);
+
+    let frames = frame_paths(&dir, info.frame_count);
+    if frames.is_empty() {
+        return Err("Recording has no frames".into());
+    }
+
+    let out = match req.format.as_str() {
+        "gif" => {
+            let out = dir.join(format!("{}.gif", info.id));
+            let max_width = req.max_width.unwrap_or(DEFAULT_GIF_MAX_WIDTH);
+            let target = out.clone();
+            tokio::task::spawn_blocking(move || encode_gif(&frames, &target, fps, max_width))
+                .await
+                .map_err(|e| e.to_string())??;
+            out
+        }
+        "mp4" => {
+            let out = dir.join(format!("{}.mp4", info.id));
+            encode_mp4(&dir, &out, fps, req.max_width).await?;
+            out
+        }
+        "frames" => {
+            let out = dir.join(format!("{}-frames.zip", info.id));
+            let target = out.clone();
+            tokio::task::spawn_blocking(move || zip_frames(&frames, &target))
+                .await
+                .map_err(|e| e.to_string())??;
+            out
+        }
+        other => return Err(format!("Unknown export format: {}", other).into()),
+    };
+
+    log::info!("export_recording: {} → {}", req.id, out.display());
+    Ok(out.to_string_lossy().to_string())
+}
+
+// ── Encoders ───────────────────────────────────────────────────────────────
+
+/// Animated GIF with a per-frame NeuQuant palette. Consecutive identical
+/// frames (idle screen) are merged into one longer frame, which keeps
+/// "click, wait, click" recordings small.
+fn encode_gif(frames: &[PathBuf], out: &Path, fps: u32, max_width: u32) -> Result<(), String> {
+    let file = std::fs::File::create(out).map_err(|e| format!("Cannot create {}: {}", out.display(), e))?;
+    let mut encoder = GifEncoder::new_with_speed(std::io::BufWriter::new(file), GIF_QUANT_SPEED);
+    encoder.set_repeat(Repeat::Infinite).map_err(|e| e.to_string())?;
+
+    let step = frame_delay_ms(fps) as u32;
+    let mut pending: Option<(RgbaImage, u32)> = None;
+    for path in frames {
+        let img = image::open(path).map_err(|e| format!("Cannot decode {}: {}", path.display(), e))?;
+        let (w, h) = scaled_size(img.width(), img.height(), max_width);
+        let rgba = if (w, h) == (img.width(), img.height()) {
+            img.to_rgba8()
+        } else {
+            img.resize_exact(w, h, image::imageops::FilterType::Triangle).to_rgba8()
+        };
+
+        match pending.as_mut() {
+            Some((prev, delay)) if *prev == rgba => *delay += step,
+            _ => {
+                if let Some((prev, delay)) = pending.replace((rgba, step)) {
+                    encoder.encode_frame(gif_frame(prev, delay)).map_err(|e| e.to_string())?;
+                }
+            }
+        }
+    }
+    if let Some((prev, delay)) = pending {
+        encoder.encode_frame(gif_frame(prev, delay)).map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+fn gif_frame(img: RgbaImage, delay_ms: u32) -> Frame {
+    Frame::from_parts(img, 0, 0, Delay::from_numer_denom_ms(delay_ms, 1))
+}
+
+async fn encode_mp4(dir: &Path, out: &Path, fps: u32, max_width: Option<u32>) -> Result<(), String> {
+    // libx264 + yuv420p need even dimensions
+    let scale = match max_width {
+        Some(w) => format!("scale='min({},iw)':-2", w),
+        None    => "scale=trunc(iw/2)*2:trunc(ih/2)*2".to_string(),
+    };
+    // -progress keeps ffmpeg talking to the watchdog during long encodes
+    let mut cmd = tokio::process::Command::new("ffmpeg");
+    cmd.args(["-y", "-loglevel", "error", "-nostats", "-progress", "pipe:1", "-framerate", &fps.to_string(), "-i"])
+        .arg(dir.join("frame_%05d.png"))
+        .args(["-vf", &scale, "-c:v", "libx264", "-pix_fmt", "yuv420p", "-movflags", "+faststart"])
+        .arg(out);
+    let output = watchdog::output("ffmpeg", &format!("MP4 export of {}", dir.display()), &mut cmd)
+        .await
+        .map_err(|e| match e.kind() {
+            std::io::ErrorKind::NotFound => "MP4 export requires ffmpeg on PATH".to_string(),
+            std::io::ErrorKind::Interrupted => {
+                let _ = std::fs::remove_file(out);
+                "ffmpeg was stopped (no output — it appeared to hang).".to_string()
+            }
+            _ => format!("Failed to run ffmpeg: {}", e),
+        })?;
+
+    if !output.status.success() {
+        return Err(format!("ffmpeg failed: {}", String::from_utf8_lossy(&output.stderr).trim()));
+    }
+    Ok(())
+}
+
+fn zip_frames(frames: &[PathBuf], out: &Path) -> Result<(), String> {
+    let file = std::fs::File::create(out).map_err(|e| format!("Cannot create {}: {}", out.display(), e))?;
+    let mut zip = zip::ZipWriter::new(file);
+    // PNGs are already compressed
+    let options = zip::write::FileOptions::default().compression_method(zip::CompressionMethod::Stored);
+
+    for path in frames {
+        let name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+        zip.start_file(name, options).map_err(|e| e.to_string())?;
+        let mut src = std::fs::File::open(path).map_err(|e| e.to_string())?;
+        std::io::copy(&mut src, &mut zip).map_err(|e| e.to_string())?;
+    }
+    zip.finish().map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+// ── Helpers ────────────────────────────────────────────────────────────────
+
+fn frame_delay_ms(fps: u32) -> u64 {
+    (1000 / fps.max(1)) as u64
+}
+
+/// Fit `width` into `max_width`, keeping the aspect ratio.
+fn scaled_size(width: u32, height: u32, max_width: u32) -> (u32, u32) {
+    if max_width == 0 || width <= max_width {
+        return (width, height);
+    }
+    let h = (height as u64 * max_width as u64 / width as u64).max(1) as u32;
+    (max_width, h)
+}
+
+// ── Unit tests ─────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scaled_size() {
+        assert_eq!(scaled_size(1920, 1080, 960), (960, 540));
+        assert_eq!(scaled_size(800, 600, 960), (800, 600));
+        assert_eq!(scaled_size(800, 600, 0), (800, 600));
+    }
+
+    #[test]
+    fn test_frame_delay() {
+        assert_eq!(frame_delay_ms(4), 250);
+        assert_eq!(frame_delay_ms(0), 1000);
+    }
+
+    #[test]
+    fn test_gif_merges_identical_frames() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        let red  = RgbaImage::from_pixel(8, 8, image::Rgba([255, 0, 0, 255]));
+        let blue = RgbaImage::from_pixel(8, 8, image::Rgba([0, 0, 255, 255]));
+        let mut paths = Vec::new();
+        for (i, img) in [&red, &red, &blue].into_iter().enumerate() {
+            let p = dir.join(frame_name(i));
+            img.save(&p).unwrap();
+            paths.push(p);
+        }
+
+        let out = dir.join("out.gif");
+        encode_gif(&paths, &out, 4, 960).unwrap();
+
+        use image::AnimationDecoder;
+        let decoder = image::codecs::gif::GifDecoder::new(std::fs::File::open(&out).unwrap()).unwrap();
+        let frames = decoder.into_frames().collect_frames().unwrap();
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[0].delay().numer_denom_ms(), (500, 1));
+    }
+}
@@ -0,0 +1,305 @@
+// reverse_image.rs — "have I seen this before?" image lookup
+//
+// Backends:
+//   bing      — Bing Visual Search API (requires Azure subscription key)
+//   saucenao  — SauceNAO (API key optional; anonymous use is rate-limited)
+//   local     — the capture history and gallery: the image is captioned and
+//               the caption embedded the way capture_history indexes
+//               captures, then compared with the stored capture embeddings
+//               and the generated images' prompts. Matches by content, so a
+//               dialog seen at another size, crop or theme is still found;
+//               offline when the vision and embedding models are local
+//               (run index_captures first so captures have embeddings)
+//
+// Tauri commands:
+//   reverse_image_search  → ReverseImageResponse
+
+use crate::ai_bridge;
+use crate::capture_history::{self, IndexCapturesRequest};
+use crate::error::AppError;
+use crate::{gallery, http};
+use base64::{engine::general_purpose, Engine};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Local matches below this cosine similarity (0–1) are not reported;
+/// captions of unrelated screenshots still share UI vocabulary.
+const LOCAL_MIN_SIMILARITY: f32 = 0.8;
+/// Newest generated images compared per local search.
+const LOCAL_MAX_GALLERY: usize = 200;
+
+// ── Public types ─────────────────────────────────────────────────────────
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ReverseImageRequest {
+    /// PNG/JPEG bytes, base64-encoded (no data: prefix)
+    pub image_base64: String,
+    /// "bing" | "saucenao" | "local"
+    pub backend:      String,
+    pub api_key:      Option<String>,
+    /// Vision and embedding models for the local backend — the ones
+    /// index_captures used, so the vectors compare
+    pub models:       Option<IndexCapturesRequest>,
+    pub max_results:  Option<usize>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ReverseImageMatch {
+    pub title:         String,
+    /// Web page hosting the image (empty for local matches)
+    pub url:           String,
+    pub thumbnail_url: Option<String>,
+    /// Absolute path of the matching file (local backend only)
+    pub path:          Option<String>,
+    /// 0.0–1.0 when the backend reports it
+    pub similarity:    Option<f32>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ReverseImageResponse {
+    pub matches: Vec<ReverseImageMatch>,
+    pub backend: String,
+}
+
+// ── HTTP client ───────────────────────────────────────────────────────────
+
+fn http_client() -> reqwest::Result<Client> {
+    http::client(http::Timeout::Api)
+}
+
+// ── Tauri command ─────────────────────────────────────────────────────────
+
+/// Find visually similar images on the web or in the local capture history.
+#[tauri::command]
+pub async fn reverse_image_search(app_handle: tauri::AppHandle, req: ReverseImageRequest) -> Result<ReverseImageResponse, AppError> {
+    let max = req.max_results.unwrap_or(10).min(50);
+    let bytes = general_purpose::STANDARD
+        .decode(req.image_base64.trim())
+        .map_err(|e| format!("Invalid base64 image: {}", e))?;
+
+    let matches = match req.backend.as_str() {
+        "bing"     => bing_visual_search(&bytes, req.api_key.as_deref().unwrap_or(""), max).await?,
+        "saucenao" => saucenao_search(&bytes, req.api_key.as_deref(), max).await?,
+        "local"    => {
+            let models = req.models.as_ref().ok_or("Local reverse search requires the caption and embedding models")?;
+            local_search(&app_handle, &req.image_base64, models, max).await?
+        }
+        other => return Err(format!("Unknown reverse image backend: {}", other).into()),
+    };
+
+    Ok(ReverseImageResponse { matches, backend: req.backend })
+}
+
+// ── Bing Visual Search ───────────────────────────────────────────────────
+
+async fn bing_visual_search(bytes: &[u8], api_key: &str, max: usize) -> Result<Vec<ReverseImageMatch>, String> {
+    if api_key.is_empty() {
+        return Err("Bing Visual Search requires an Azure subscription key".into());
+    }
+    let client = http_client().map_err(|e| e.to_string())?;
+
+    let part = reqwest::multipart::Part::bytes(bytes.to_vec())
+        .file_name("capture.png")
+        .mime_str("image/png")
+        .map_err(|e| e.to_string())?;
+    let form = reqwest::multipart::Form::new().part("image", part);
+
+    let resp = client
+        .post("https://api.bing.microsoft.com/v7.0/images/visualsearch")
+        .header("Ocp-Apim-Subscription-Key", api_key)
+        .multipart(form)
+        .send().await
+        .map_err(|e| format!("Bing Visual Search error: {}", e))?;
+
+    let status = resp.status();
+    let json: Value = resp.json().await.map_err(|e| e.to_string())?;
+    if !status.is_success() {
+        return Err(format!("Bing Visual Search {}: {}", status,
+            json["error"]["message"].as_str().unwrap_or("unknown error")));
+    }
+
+    // Pages that include this exact image are more useful than "similar" ones
+    let mut out = Vec::new();
+    for action_type in ["PagesIncluding", "VisualSearch"] {
+        for tag in json["tags"].as_array().unwrap_or(&vec![]) {
+            for action in tag["actions"].as_array().unwrap_or(&vec![]) {
+                if action["actionType"] != action_type { continue; }
+                for v in action["data"]["value"].as_array().unwrap_or(&vec![]) {
+                    if out.len() >= max { return Ok(out); }
+                    out.push(ReverseImageMatch {
+                        title:         v["name"].as_str().unwrap_or("").to_string(),
+                        url:           v["hostPageUrl"].as_str().unwrap_or("").to_string(),
+                        thumbnail_url: v["thumbnailUrl"].as_str().map(String::from),
+                        path:          None,
+                        similarity:    None,
+                    });
+                }
+            }
+        }
+    }
+    Ok(out)
+}
+
+// ── SauceNAO ─────────────────────────────────────────────────────────────
+
+async fn saucenao_search(bytes: &[u8], api_key: Option<&str>, max: usize) -> Result<Vec<ReverseImageMatch>, String> {
+    let client = http_client().map_err(|e| e.to_string())?;
+
+    let part = reqwest::multipart::Part::bytes(bytes.to_vec())
+        .file_name("capture.png")
+        .mime_str("image/png")
+        .map_err(|e| e.to_string())?;
+    let form = reqwest::multipart::Form::new().part("file", part);
+
+    let numres = max.to_string();
+    let mut query: Vec<(&str, &str)> = vec![("output_type", "2"), ("numres", &numres)];
+    if let Some(key) = api_key.filter(|k| !k.trim().is_empty()) {
+        query.push(("api_key", key));
+    }
+
+    let resp = client
+        .post("https://saucenao.com/search.php")
+        .query(&query)
+        .multipart(form)
+        .send().await
+        .map_err(|e| format!("SauceNAO error: {}", e))?;
+
+    let status = resp.status();
+    let json: Value = resp.json().await.map_err(|e| e.to_string())?;
+    if !status.is_success() || json["header"]["status"].as_i64().unwrap_or(0) < 0 {
+        return Err(format!("SauceNAO {}: {}", status,
+            json["header"]["message"].as_str().unwrap_or("unknown error")));
+    }
+
+    Ok(json["results"].as_array().unwrap_or(&vec![]).iter().take(max).map(|r| {
+        let data = &r["data"];
+        ReverseImageMatch {
+            title: data["title"].as_str()
+                .or_else(|| data["source"].as_str())
+                .or_else(|| r["header"]["index_name"].as_str())
+                .unwrap_or("")
+                .to_string(),
+            url: data["ext_urls"][0].as_str().unwrap_or("").to_string(),
+            thumbnail_url: r["header"]["thumbnail"].as_str().map(String::from),
+            path: None,
+            similarity: r["header"]["similarity"].as_str()
+                .and_then(|s| s.parse::<f32>().ok())
+                .map(|p| p / 100.0),
+        }
+    }).collect())
+}
+
+// ── Local history comparison ─────────────────────────────────────────────
+
+/// Caption the query image, embed the caption, and rank the captures
+/// embedded with the same model plus the newest generated images.
+async fn local_search(
+    app:          &tauri::AppHandle,
+    image_base64: &str,
+    models:       &IndexCapturesRequest,
+    max:          usize,
+) -> Result<Vec<ReverseImageMatch>, String> {
+    let provider = models.embed_provider.as_deref()
+        .ok_or("Local reverse search requires an embedding provider")?;
+    let caption = capture_history::caption(models, image_base64.trim().to_string()).await?;
+
+    let handle = app.clone();
+    let (captures, gallery) = tokio::task::spawn_blocking(move || {
+        (capture_history::load_entries(&handle), gallery::recent(&handle, LOCAL_MAX_GALLERY))
+    })
+    .await
+    .map_err(|e| e.to_string())?;
+    let embed_key = capture_history::embed_key(models);
+    let captures: Vec<_> = captures?
+        .into_iter()
+        .filter(|c| c.embedding.is_some() && c.embed_model == embed_key)
+        .collect();
+    let gallery: Vec<_> = gallery.into_iter().filter(|g| !gallery_text(g).trim().is_empty()).collect();
+
+    // Generated images are described by their prompts; one request embeds
+    // those together with the caption
+    let mut texts = vec![caption];
+    texts.extend(gallery.iter().map(|g| gallery_text(g).to_string()));
+    let vectors = ai_bridge::embed(
+        provider,
+        models.embed_api_key.as_deref(),
+        models.embed_model.as_deref(),
+        models.embed_url.as_deref(),
+        &texts,
+    )
+    .await?;
+    let (query, prompts) = vectors.split_first().ok_or("Embeddings: empty response")?;
+
+    let candidates = captures
+        .iter()
+        .filter_map(|c| {
+            let m = ReverseImageMatch {
+                title:         c.caption.clone().unwrap_or_else(|| c.id.clone()),
+                url:           String::new(),
+                thumbnail_url: None,
+                path:          Some(c.path.clone()),
+                similarity:    None,
+            };
+            Some((c.embedding.as_deref()?, m))
+        })
+        .chain(gallery.iter().zip(prompts).map(|(g, v)| {
+            let m = ReverseImageMatch {
+                title:         g.record.prompt.clone(),
+                url:           String::new(),
+                thumbnail_url: None,
+                path:          Some(g.path.clone()),
+                similarity:    None,
+            };
+            (v.as_slice(), m)
+        }));
+    Ok(best_matches(query, candidates, max))
+}
+
+/// What a generated image is compared by: the prompt the provider actually
+/// used when it rewrote it.
+fn gallery_text(g: &gallery::GeneratedImage) -> &str {
+    g.record.revised_prompt.as_deref().unwrap_or(&g.record.prompt)
+}
+
+/// Candidates at least LOCAL_MIN_SIMILARITY from `query`, best first.
+fn best_matches<'a>(
+    query:      &[f32],
+    candidates: impl Iterator<Item = (&'a [f32], ReverseImageMatch)>,
+    max:        usize,
+) -> Vec<ReverseImageMatch> {
+    let mut matches: Vec<ReverseImageMatch> = candidates
+        .filter_map(|(v, m)| {
+            let similarity = ai_bridge::cosine_similarity(query, v);
+            (similarity >= LOCAL_MIN_SIMILARITY).then_some(ReverseImageMatch { similarity: Some(similarity), ..m })
+        })
+        .collect();
+    matches.sort_by(|a, b| b.similarity.partial_cmp(&a.similarity).unwrap_or(std::cmp::Ordering::Equal));
+    matches.truncate(max);
+    matches
+}
+
+// ── Unit tests ────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candidate(title: &str) -> ReverseImageMatch {
+        ReverseImageMatch { title: title.into(), url: String::new(), thumbnail_url: None, path: None, similarity: None }
+    }
+
+    #[test]
+    fn test_best_matches_filters_and_orders() {
+        let (same, close, other) = (vec![1.0, 0.0], vec![0.95, 0.3], vec![0.0, 1.0]);
+        let candidates = vec![
+            (other.as_slice(), candidate("other")),
+            (close.as_slice(), candidate("close")),
+            (same.as_slice(),  candidate("same")),
+        ];
+        let hits = best_matches(&[1.0, 0.0], candidates.clone().into_iter(), 10);
+        assert_eq!(hits.iter().map(|m| m.title.as_str()).collect::<Vec<_>>(), ["same", "close"]);
+        assert!((hits[0].similarity.unwrap() - 1.0).abs() < 1e-6);
+        assert_eq!(best_matches(&[1.0, 0.0], candidates.into_iter(), 1).len(), 1);
+    }
+}
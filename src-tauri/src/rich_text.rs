@@ -0,0 +1,789 @@
+// rich_text.rs — HTML and RTF → Markdown
+//
+// Rich clipboard flavors (clipboard.rs) are converted here so content copied
+// from browsers, word processors and chat apps keeps its headings, lists,
+// links, emphasis, code blocks and tables when it is used as context.
+// Both converters are forgiving single-pass scanners: unknown tags and
+// control words are dropped, their text is kept.
+
+use regex::Regex;
+
+// ── HTML ───────────────────────────────────────────────────────────────────
+
+/// Elements whose content is never visible text.
+const HTML_SKIP: &[&str] = &["script", "style", "head", "title", "noscript", "template", "svg"];
+
+struct Tag<'a> {
+    name:    String,
+    closing: bool,
+    attrs:   &'a str,
+}
+
+/// Convert an HTML document or fragment to Markdown.
+pub(crate) fn html_to_markdown(html: &str) -> String {
+    let mut md = MdWriter::default();
+    let mut rest = fragment(html);
+    while !rest.is_empty() {
+        if let Some(comment) = rest.strip_prefix("<!--") {
+            rest = comment.find("-->").map_or("", |i| &comment[i + 3..]);
+            continue;
+        }
+        if rest.starts_with('<') {
+            if let Some((tag, after)) = rest.find('>').and_then(|end| parse_tag(&rest[1..end]).map(|t| (t, &rest[end + 1..]))) {
+                rest = after;
+                if !tag.closing && HTML_SKIP.contains(&tag.name.as_str()) {
+                    let close = format!("</{}", tag.name);
+                    rest = match rest.to_ascii_lowercase().find(&close) {
+                        Some(i) => rest[i..].find('>').map_or("", |j| &rest[i + j + 1..]),
+                        None => "",
+                    };
+                } else {
+                    md.tag(&tag);
+                }
+                continue;
+            }
+        }
+        // Text up to the next tag (a stray '<' is text)
+        let next = rest[1..].find('<').map_or(rest.len(), |i| i + 1);
+        md.text(&decode_entities(&rest[..next]));
+        rest = &rest[next..];
+    }
+    md.finish()
+}
+
+/// The copied part of a clipboard HTML document. Windows' CF_HTML and most
+/// browsers mark it with StartFragment / EndFragment comments.
+fn fragment(html: &str) -> &str {
+    match (html.find("<!--StartFragment-->"), html.find("<!--EndFragment-->")) {
+        (Some(s), Some(e)) if s < e => &html[s + "<!--StartFragment-->".len()..e],
+        _ => html,
+    }
+}
+
+/// `inner` is what's between '<' and '>'; None when it isn't a tag at all.
+fn parse_tag(inner: &str) -> Option<Tag<'_>> {
+    if inner.starts_with('!') || inner.starts_with('?') {
+        return Some(Tag { name: String::new(), closing: false, attrs: "" });
+    }
+    let (closing, body) = match inner.strip_prefix('/') {
+        Some(b) => (true, b),
+        None => (false, inner),
+    };
+    let name_len = body.find(|c: char| !c.is_ascii_alphanumeric()).unwrap_or(body.len());
+    if name_len == 0 || !body.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        return None;
+    }
+    Some(Tag {
+        name: body[..name_len].to_ascii_lowercase(),
+        closing,
+        attrs: &body[name_len..],
+    })
+}
+
+fn attr(attrs: &str, name: &str) -> Option<String> {
+    let re = Regex::new(&format!(r#"(?is)(?:^|\s){}\s*=\s*(?:"([^"]*)"|'([^']*)'|([^\s"'>]+))"#, name)).ok()?;
+    let caps = re.captures(attrs)?;
+    let value = caps.get(1).or(caps.get(2)).or(caps.get(3))?.as_str();
+    Some(decode_entities(value).trim().to_string())
+}
+
+fn decode_entities(s: &str) -> String {
+    if !s.contains('&') {
+        return s.to_string();
+    }
+    let mut out = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(amp) = rest.find('&') {
+        out.push_str(&rest[..amp]);
+        rest = &rest[amp..];
+        let decoded = rest[1..].find(';').filter(|&i| i <= 10).and_then(|i| {
+            let name = &rest[1..i + 1];
+            let c = match name {
+                "amp" => '&',
+                "lt" => '<',
+                "gt" => '>',
+                "quot" => '"',
+                "apos" => '\'',
+                "nbsp" => ' ',
+                "ndash" => '–',
+                "mdash" => '—',
+                "hellip" => '…',
+                "lsquo" => '‘',
+                "rsquo" => '’',
+                "ldquo" => '“',
+                "rdquo" => '”',
+                "bull" => '•',
+                "middot" => '·',
+                "copy" => '©',
+                "reg" => '®',
+                "trade" => '™',
+                "times" => '×',
+                _ => {
+                    let code = match name.strip_prefix("#x").or_else(|| name.strip_prefix("#X")) {
+                        Some(hex) => u32::from_str_radix(hex, 16).ok(),
+                        None => name.strip_prefix('#').and_then(|d| d.parse().ok()),
+                    };
+                    char::from_u32(code?)?
+                }
+            };
+            Some((c, i + 2))
+        });
+        match decoded {
+            Some((c, len)) => {
+                out.push(if c == '\u{a0}' { ' ' } else { c });
+                rest = &rest[len..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Markdown output with the block / inline state of the HTML walk.
+#[derive(Default)]
+struct MdWriter {
+    out:      String,
+    /// Whitespace seen since the last word, written before the next one
+    space:    bool,
+    pre:      usize,
+    quote:    usize,
+    table:    usize,
+    /// Open lists: None = bullets, Some(n) = next number
+    lists:    Vec<Option<u32>>,
+    /// Open inline markers / links: (tag, output length before, after opening)
+    inline:   Vec<(String, usize, usize)>,
+    links:    Vec<Option<String>>,
+    row:      usize,
+    cells:    usize,
+}
+
+impl MdWriter {
+    fn at_line_start(&self) -> bool {
+        self.out.is_empty() || self.out.ends_with('\n')
+    }
+
+    /// Start of an output line: blockquote markers.
+    fn line_prefix(&mut self) {
+        if self.at_line_start() && self.quote > 0 {
+            self.out.push_str(&"> ".repeat(self.quote));
+        }
+    }
+
+    fn push_inline(&mut self, s: &str) {
+        self.line_prefix();
+        if self.space && !self.out.ends_with([' ', '\n', '(', '[']) {
+            self.out.push(' ');
+        }
+        self.space = false;
+        self.out.push_str(s);
+    }
+
+    fn trim_trailing(&mut self) {
+        let len = self.out.trim_end_matches([' ', '\t']).len();
+        self.out.truncate(len);
+    }
+
+    fn newline(&mut self) {
+        self.space = false;
+        if self.table > 0 {
+            self.space = true;
+            return;
+        }
+        self.trim_trailing();
+        if !self.out.is_empty() && !self.out.ends_with('\n') {
+            self.out.push('\n');
+        }
+    }
+
+    fn block(&mut self) {
+        if self.table > 0 || !self.lists.is_empty() {
+            return self.newline();
+        }
+        self.newline();
+        if !self.out.is_empty() && !self.out.ends_with("\n\n") {
+            self.out.push('\n');
+        }
+    }
+
+    fn text(&mut self, text: &str) {
+        if self.pre > 0 {
+            self.line_prefix();
+            self.out.push_str(text);
+            return;
+        }
+        if text.starts_with(char::is_whitespace) {
+            self.space = true;
+        }
+        for (i, word) in text.split_whitespace().enumerate() {
+            if i > 0 {
+                self.space = true;
+            }
+            self.push_inline(word);
+        }
+        if text.ends_with(char::is_whitespace) {
+            self.space = true;
+        }
+    }
+
+    fn open_inline(&mut self, tag: &str, marker: &str) {
+        let before = self.out.len();
+        self.push_inline(marker);
+        self.inline.push((tag.to_string(), before, self.out.len()));
+    }
+
+    /// Closes the innermost `tag`; an empty span is removed entirely.
+    fn close_inline(&mut self, tag: &str, marker: &str) {
+        let Some(pos) = self.inline.iter().rposition(|(t, _, _)| t == tag) else { return };
+        let (_, before, after) = self.inline.remove(pos);
+        if self.out.len() == after {
+            self.out.truncate(before);
+        } else {
+            self.out.push_str(marker);
+        }
+    }
+
+    fn tag(&mut self, tag: &Tag) {
+        let name = tag.name.as_str();
+        if self.pre > 0 && name != "pre" {
+            if name == "br" {
+                self.out.push('\n');
+            }
+            return;
+        }
+        match (name, tag.closing) {
+            ("h1" | "h2" | "h3" | "h4" | "h5" | "h6", false) => {
+                self.block();
+                let level = name[1..].parse().unwrap_or(1);
+                if self.table == 0 {
+                    self.line_prefix();
+                    self.out.push_str(&"#".repeat(level));
+                    self.out.push(' ');
+                }
+            }
+            ("h1" | "h2" | "h3" | "h4" | "h5" | "h6" | "p" | "section" | "article" | "figure", _) => self.block(),
+            ("div" | "dt" | "dd" | "figcaption" | "header" | "footer", _) => self.newline(),
+            ("br", _) => self.newline(),
+            ("hr", false) => {
+                self.block();
+                self.line_prefix();
+                self.out.push_str("---");
+                self.block();
+            }
+            ("strong" | "b", false) => self.open_inline(name, "**"),
+            ("strong" | "b", true) => self.close_inline(name, "**"),
+            ("em" | "i", false) => self.open_inline(name, "*"),
+            ("em" | "i", true) => self.close_inline(name, "*"),
+            ("del" | "s" | "strike", false) => self.open_inline(name, "~~"),
+            ("del" | "s" | "strike", true) => self.close_inline(name, "~~"),
+            ("code" | "kbd" | "samp", false) => self.open_inline(name, "`"),
+            ("code" | "kbd" | "samp", true) => self.close_inline(name, "`"),
+            ("a", false) => {
+                let href = attr(tag.attrs, "href")
+                    .filter(|h| !h.is_empty() && !h.starts_with('#') && !h.to_ascii_lowercase().starts_with("javascript:"));
+                self.links.push(href);
+                self.open_inline("a", "[");
+            }
+            ("a", true) => {
+                let href = self.links.pop().flatten();
+                let Some(pos) = self.inline.iter().rposition(|(t, _, _)| t == "a") else { return };
+                let (_, before, after) = self.inline.remove(pos);
+                match href {
+                    _ if self.out.len() == after => self.out.truncate(before),
+                    Some(h) => self.out.push_str(&format!("]({})", h)),
+                    // no usable target — keep the text, drop the bracket
+                    None => { self.out.remove(after - 1); }
+                }
+            }
+            ("img", false) => {
+                let alt = attr(tag.attrs, "alt").unwrap_or_default();
+                match attr(tag.attrs, "src").filter(|s| !s.starts_with("data:")) {
+                    Some(src) => self.push_inline(&format!("![{}]({})", alt, src)),
+                    None if !alt.is_empty() => self.push_inline(&alt),
+                    None => {}
+                }
+            }
+            ("pre", false) => {
+                self.block();
+                self.line_prefix();
+                self.out.push_str("```\n");
+                self.pre += 1;
+            }
+            ("pre", true) if self.pre > 0 => {
+                self.pre -= 1;
+                if !self.out.ends_with('\n') {
+                    self.out.push('\n');
+                }
+                self.line_prefix();
+                self.out.push_str("```");
+                self.block();
+            }
+            ("blockquote", false) => {
+                self.block();
+                self.quote += 1;
+            }
+            ("blockquote", true) => {
+                self.block();
+                self.quote = self.quote.saturating_sub(1);
+            }
+            ("ul" | "ol", false) => {
+                if self.lists.is_empty() { self.block() } else { self.newline() }
+                let start = attr(tag.attrs, "start").and_then(|s| s.parse().ok()).unwrap_or(1);
+                self.lists.push((name == "ol").then_some(start));
+            }
+            ("ul" | "ol", true) => {
+                self.lists.pop();
+                self.block();
+            }
+            ("li", false) => {
+                self.newline();
+                self.line_prefix();
+                let depth = self.lists.len().max(1) - 1;
+                self.out.push_str(&"  ".repeat(depth));
+                let marker = match self.lists.last_mut() {
+                    Some(Some(n)) => { *n += 1; format!("{}. ", *n - 1) }
+                    _ => "- ".to_string(),
+                };
+                self.out.push_str(&marker);
+            }
+            ("li", true) => self.newline(),
+            ("table", false) => {
+                self.block();
+                self.table += 1;
+                self.row = 0;
+            }
+            ("table", true) => {
+                self.table = self.table.saturating_sub(1);
+                self.block();
+            }
+            ("tr", false) if self.table > 0 => {
+                self.trim_trailing();
+                if !self.out.is_empty() && !self.out.ends_with('\n') {
+                    self.out.push('\n');
+                }
+                self.line_prefix();
+                self.out.push('|');
+                self.cells = 0;
+                self.space = false;
+            }
+            ("tr", true) if self.table > 0 => {
+                self.row += 1;
+                if self.row == 1 && self.cells > 0 {
+                    self.out.push('\n');
+                    self.line_prefix();
+                    self.out.push('|');
+                    self.out.push_str(&" --- |".repeat(self.cells));
+                }
+            }
+            ("td" | "th", false) if self.table > 0 => {
+                self.out.push(' ');
+                self.space = false;
+            }
+            ("td" | "th", true) if self.table > 0 => {
+                self.trim_trailing();
+                self.out.push_str(" |");
+                self.cells += 1;
+                self.space = false;
+            }
+            _ => {}
+        }
+    }
+
+    fn finish(self) -> String {
+        tidy(&self.out)
+    }
+}
+
+/// Trailing spaces off every line, at most one blank line in a row.
+fn tidy(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut blank = 0;
+    for line in text.lines().map(str::trim_end) {
+        if line.is_empty() {
+            blank += 1;
+            continue;
+        }
+        if !out.is_empty() {
+            out.push_str(if blank > 0 { "\n\n" } else { "\n" });
+        }
+        blank = 0;
+        out.push_str(line);
+    }
+    out
+}
+
+// ── RTF ────────────────────────────────────────────────────────────────────
+
+/// Destinations whose text isn't document content.
+const RTF_SKIP: &[&str] = &[
+    "fonttbl", "colortbl", "stylesheet", "info", "pict", "header", "headerl", "headerr", "headerf",
+    "footer", "footerl", "footerr", "footerf", "listtable", "listoverridetable", "rsidtbl",
+    "generator", "xmlnstbl", "themedata", "colorschememapping", "latentstyles", "datastore",
+    "filetbl", "revtbl", "pgdsctbl", "object", "footnote",
+];
+
+#[derive(Clone, Copy, Default)]
+struct RtfState {
+    bold:   bool,
+    italic: bool,
+    skip:   bool,
+    /// Inside \listtext / \pntext: the bullet or number of a list item
+    marker: bool,
+    /// Inside \fldinst: field instruction (HYPERLINK "…")
+    fldinst: bool,
+    /// Closing this group ends a hyperlink's visible text
+    link:   bool,
+    /// \ucN — fallback characters following each \uN
+    uc:     usize,
+}
+
+#[derive(Default)]
+struct RtfWriter {
+    out:        String,
+    /// Markers currently written and not yet closed: (bold, italic)
+    emitted:    (bool, bool),
+    open_at:    [usize; 2],
+    marker_buf: String,
+    fldinst:    String,
+    link_urls:  Vec<String>,
+}
+
+impl RtfWriter {
+    fn close(&mut self, marker: &str, idx: usize) {
+        if self.out.len() == self.open_at[idx] {
+            self.out.truncate(self.out.len() - marker.len());
+            return;
+        }
+        // "**bold **" doesn't render — keep the spaces outside
+        let trimmed = self.out.trim_end_matches(' ').len();
+        let spaces = self.out.len() - trimmed;
+        self.out.truncate(trimmed);
+        self.out.push_str(marker);
+        self.out.push_str(&" ".repeat(spaces));
+    }
+
+    fn open(&mut self, marker: &str, idx: usize) {
+        self.out.push_str(marker);
+        self.open_at[idx] = self.out.len();
+    }
+
+    /// Bring the written emphasis markers in line with `want`.
+    fn sync(&mut self, want: (bool, bool)) {
+        if self.emitted.1 && (!want.1 || self.emitted.0 != want.0) {
+            self.close("*", 1);
+            self.emitted.1 = false;
+        }
+        if self.emitted.0 && !want.0 {
+            self.close("**", 0);
+            self.emitted.0 = false;
+        }
+        if !self.emitted.0 && want.0 {
+            self.open("**", 0);
+            self.emitted.0 = true;
+        }
+        if !self.emitted.1 && want.1 {
+            self.open("*", 1);
+            self.emitted.1 = true;
+        }
+    }
+
+    fn put(&mut self, state: &RtfState, c: char) {
+        if state.skip {
+            return;
+        }
+        if state.fldinst {
+            self.fldinst.push(c);
+        } else if state.marker {
+            self.marker_buf.push(c);
+        } else {
+            if !c.is_whitespace() {
+                self.sync((state.bold, state.italic));
+            }
+            self.out.push(c);
+        }
+    }
+
+    fn paragraph(&mut self) {
+        self.sync((false, false));
+        self.out.push('\n');
+    }
+}
+
+/// Convert an RTF document to Markdown. Non-Unicode text is read as
+/// Windows-1252, which covers what Western word processors emit.
+pub(crate) fn rtf_to_markdown(rtf: &str) -> String {
+    let chars: Vec<char> = rtf.chars().collect();
+    let mut w = RtfWriter::default();
+    let mut stack: Vec<RtfState> = Vec::new();
+    let mut state = RtfState { uc: 1, ..Default::default() };
+    let mut group_start = false;
+    let mut skip_fallback = 0usize;
+    let mut high_surrogate: Option<u16> = None;
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        i += 1;
+        match c {
+            '{' => {
+                stack.push(state);
+                state.link = false;
+                group_start = true;
+                continue;
+            }
+            '}' => {
+                let closed = state;
+                if closed.marker && !stack.last().is_some_and(|s| s.marker) {
+                    let m = std::mem::take(&mut w.marker_buf);
+                    let m = m.trim();
+                    let outer = stack.last().copied().unwrap_or_default();
+                    if !outer.skip {
+                        w.sync((false, false));
+                        if m.starts_with(|c: char| c.is_ascii_alphanumeric()) {
+                            w.out.push_str(m.trim_end_matches(['.', ')']));
+                            w.out.push_str(". ");
+                        } else {
+                            w.out.push_str("- ");
+                        }
+                    }
+                }
+                if closed.link {
+                    if let Some(url) = w.link_urls.pop() {
+                        w.sync((false, false));
+                        w.out.push_str(&format!("]({})", url));
+                    }
+                }
+                state = stack.pop().unwrap_or(state);
+                group_start = false;
+                continue;
+            }
+            '\r' | '\n' => continue,
+            '\\' => {}
+            _ => {
+                if skip_fallback > 0 {
+                    skip_fallback -= 1;
+                } else {
+                    w.put(&state, c);
+                }
+                group_start = false;
+                continue;
+            }
+        }
+
+        // Control symbol or word
+        let Some(&next) = chars.get(i) else { break };
+        if !next.is_ascii_alphabetic() {
+            i += 1;
+            match next {
+                '\\' | '{' | '}' => {
+                    if skip_fallback > 0 { skip_fallback -= 1 } else { w.put(&state, next) }
+                }
+                '\'' => {
+                    let hex: String = chars.iter().skip(i).take(2).collect();
+                    i += 2;
+                    if skip_fallback > 0 {
+                        skip_fallback -= 1;
+                    } else if let Ok(b) = u8::from_str_radix(&hex, 16) {
+                        w.put(&state, cp1252(b));
+                    }
+                }
+                '*' => state.skip = true,
+                '~' => w.put(&state, ' '),
+                '_' => w.put(&state, '-'),
+                '\n' | '\r' => w.paragraph(),
+                _ => {}
+            }
+            group_start = false;
+            continue;
+        }
+        let start = i;
+        while i < chars.len() && chars[i].is_ascii_alphabetic() {
+            i += 1;
+        }
+        let word: String = chars[start..i].iter().collect();
+        let num_start = i;
+        if i < chars.len() && chars[i] == '-' {
+            i += 1;
+        }
+        while i < chars.len() && chars[i].is_ascii_digit() {
+            i += 1;
+        }
+        let param: Option<i32> = chars[num_start..i].iter().collect::<String>().parse().ok();
+        if chars.get(i) == Some(&' ') {
+            i += 1;
+        }
+        let first_in_group = std::mem::replace(&mut group_start, false);
+
+        match word.as_str() {
+            "fldinst" => {
+                // \*\fldinst is the one ignorable destination with useful text
+                state.skip = stack.last().is_some_and(|s| s.skip);
+                state.fldinst = true;
+                w.fldinst.clear();
+            }
+            "fldrslt" => {
+                let url = hyperlink_target(&w.fldinst);
+                w.fldinst.clear();
+                if let (Some(url), false) = (url, state.skip) {
+                    w.sync((state.bold, state.italic));
+                    w.out.push('[');
+                    w.link_urls.push(url);
+                    state.link = true;
+                }
+            }
+            d if RTF_SKIP.contains(&d) && first_in_group => state.skip = true,
+            _ if state.skip => {}
+            "listtext" | "pntext" => state.marker = true,
+            "par" | "line" | "sect" | "page" => w.paragraph(),
+            "row" => w.paragraph(),
+            "cell" | "tab" => w.put(&state, ' '),
+            "b" => state.bold = param != Some(0),
+            "i" => state.italic = param != Some(0),
+            "plain" => {
+                state.bold = false;
+                state.italic = false;
+            }
+            "uc" => state.uc = param.unwrap_or(1).max(0) as usize,
+            "u" => {
+                let unit = param.unwrap_or(0) as i16 as u16;
+                skip_fallback = state.uc;
+                match (high_surrogate.take(), unit) {
+                    (_, 0xD800..=0xDBFF) => high_surrogate = Some(unit),
+                    (Some(hi), 0xDC00..=0xDFFF) => {
+                        if let Some(c) = char::decode_utf16([hi, unit]).next().and_then(Result::ok) {
+                            w.put(&state, c);
+                        }
+                    }
+                    (_, u) => {
+                        if let Some(c) = char::from_u32(u as u32) {
+                            w.put(&state, c);
+                        }
+                    }
+                }
+            }
+            "emdash" => w.put(&state, '—'),
+            "endash" => w.put(&state, '–'),
+            "bullet" => w.put(&state, '•'),
+            "lquote" => w.put(&state, '‘'),
+            "rquote" => w.put(&state, '’'),
+            "ldblquote" => w.put(&state, '“'),
+            "rdblquote" => w.put(&state, '”'),
+            "emspace" | "enspace" | "qmspace" => w.put(&state, ' '),
+            "bin" => i += param.unwrap_or(0).max(0) as usize,
+            _ => {}
+        }
+    }
+    w.sync((false, false));
+    rtf_paragraphs(&w.out)
+}
+
+/// The URL of a `HYPERLINK "…"` field instruction.
+fn hyperlink_target(instruction: &str) -> Option<String> {
+    let rest = instruction.trim().strip_prefix("HYPERLINK")?.trim();
+    let url = match rest.strip_prefix('"') {
+        Some(quoted) => quoted.split('"').next()?,
+        None => rest.split_whitespace().next()?,
+    };
+    Some(url.to_string()).filter(|u| !u.is_empty())
+}
+
+/// One RTF paragraph per line → Markdown paragraphs, keeping list items
+/// on consecutive lines.
+fn rtf_paragraphs(text: &str) -> String {
+    let is_item = |l: &str| {
+        l.starts_with("- ") || l.split_once(". ").is_some_and(|(n, _)| !n.is_empty() && n.len() <= 3 && n.chars().all(|c| c.is_ascii_alphanumeric()))
+    };
+    let lines: Vec<&str> = text.lines().map(str::trim).filter(|l| !l.is_empty()).collect();
+    let mut out = String::new();
+    for (n, line) in lines.iter().enumerate() {
+        if n > 0 {
+            out.push_str(if is_item(line) && is_item(lines[n - 1]) { "\n" } else { "\n\n" });
+        }
+        out.push_str(line);
+    }
+    out
+}
+
+/// Windows-1252 byte; the 0x80–0x9F range differs from Latin-1.
+fn cp1252(b: u8) -> char {
+    match b {
+        0x80 => '€',
+        0x85 => '…',
+        0x91 => '‘',
+        0x92 => '’',
+        0x93 => '“',
+        0x94 => '”',
+        0x95 => '•',
+        0x96 => '–',
+        0x97 => '—',
+        0x99 => '™',
+        0xA0 => ' ',
+        _ => b as char,
+    }
+}
+
+// ── Unit tests ─────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_html_blocks_and_inline() {
+        let html = r#"<html><head><style>p{}</style></head><body>
+            <h2>Install&nbsp;steps</h2>
+            <p>Run <code>cargo build</code> then <b>restart</b> the <a href="https://example.com/docs">docs server</a>.</p>
+            <ul><li>one</li><li>two<ol><li>a</li><li>b</li></ol></li></ul>
+            <pre>fn main() {
+    println!("&lt;hi&gt;");
+}</pre>
+            <blockquote><p>quoted <em>text</em></p></blockquote>
+            <script>alert(1)</script>
+        </body></html>"#;
+        assert_eq!(
+            html_to_markdown(html),
+            "## Install steps\n\n\
+             Run `cargo build` then **restart** the [docs server](https://example.com/docs).\n\n\
+             - one\n- two\n  1. a\n  2. b\n\n\
+             ```\nfn main() {\n    println!(\"<hi>\");\n}\n```\n\n\
+             > quoted *text*"
+        );
+    }
+
+    #[test]
+    fn test_html_tables_fragments_and_edge_cases() {
+        let html = "Version:0.9\r\nStartHTML:0000000105\r\n<html><body><!--StartFragment-->\
+            <table><tr><th>Name</th><th>Size</th></tr><tr><td>a.png</td><td>12 KB</td></tr></table>\
+            <p><b> </b>x &lt; 5 &amp;&#x41;&#66; <a href=\"#top\">top</a> <a>plain</a></p>\
+            <!--EndFragment--></body></html>";
+        assert_eq!(
+            html_to_markdown(html),
+            "| Name | Size |\n| --- | --- |\n| a.png | 12 KB |\n\nx < 5 &AB top plain"
+        );
+        assert_eq!(html_to_markdown("a < b and <3"), "a < b and <3");
+        assert_eq!(html_to_markdown("<p>one<br>two</p><hr><p>three</p>"), "one\ntwo\n\n---\n\nthree");
+    }
+
+    #[test]
+    fn test_rtf_to_markdown() {
+        let rtf = r#"{\rtf1\ansi\ansicpg1252{\fonttbl\f0\fswiss Helvetica;}{\colortbl;\red255\green255\blue255;}
+{\*\generator Writer;}\f0\fs24 Hello \b bold\b0  and {\i italic} caf\'e9\par
+{\field{\*\fldinst{HYPERLINK "https://example.com"}}{\fldrslt Example}}\par
+{\listtext\'95\tab}First\par
+{\listtext\'95\tab}Second\par
+Smile \u9786? done\par}"#;
+        assert_eq!(
+            rtf_to_markdown(rtf),
+            "Hello **bold** and *italic* café\n\n[Example](https://example.com)\n\n- First\n- Second\n\nSmile ☺ done"
+        );
+    }
+
+    #[test]
+    fn test_rtf_numbered_list_and_escapes() {
+        let rtf = r"{\rtf1{\pntext 1.\tab}Alpha\par{\pntext 2.\tab}Beta \{x\}\par\pard\b\i both\i0\b0\par}";
+        assert_eq!(rtf_to_markdown(rtf), "1. Alpha\n2. Beta {x}\n\n***both***");
+    }
+}
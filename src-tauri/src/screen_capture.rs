@@ -1,4 +1,5 @@
 // screen_capture.rs — platform-specific screen/window capture
+use crate::error::AppError;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -388,14 +389,21 @@ mod platform {
     }
 }
 
+// ── Crate-internal helpers ───────────────────────────────────────────────
+
+/// Full-screen capture for Rust-side consumers (screen recorder, watchers).
+pub(crate) fn capture_frame() -> anyhow::Result<CaptureResult> {
+    platform::capture_primary_screen()
+}
+
 // ── Public Tauri commands ────────────────────────────────────────────────
 
 #[tauri::command]
-pub async fn capture_screen() -> Result<CaptureResult, String> {
-    platform::capture_primary_screen().map_err(|e| e.to_string())
+pub async fn capture_screen() -> Result<CaptureResult, AppError> {
+    platform::capture_primary_screen().map_err(|e| e.to_string().into())
 }
 
 #[tauri::command]
-pub async fn capture_window_under_cursor() -> Result<CaptureResult, String> {
-    platform::capture_at_cursor().map_err(|e| e.to_string())
+pub async fn capture_window_under_cursor() -> Result<CaptureResult, AppError> {
+    platform::capture_at_cursor().map_err(|e| e.to_string().into())
 }
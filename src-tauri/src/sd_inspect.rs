@@ -0,0 +1,657 @@
+// sd_inspect.rs — identify an SD model file from its header, without loading it
+//
+// A FLUX checkpoint handed to the sd binary as if it were SD1.5, a LoRA picked
+// as the main model, or a 12 GB model on an 8 GB card only fails after a long
+// load. The header of a .safetensors file (JSON: tensor names, dtypes, byte
+// ranges, optional __metadata__) or a .gguf file (key/value metadata and the
+// tensor table) is enough to tell the architecture, whether a VAE and text
+// encoders are baked in, and how much memory the weights take.
+//
+// Tauri commands exposed:
+//   inspect_sd_model (path) → ModelInfo
+
+use crate::error::AppError;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::io::{BufReader, Read};
+use std::path::Path;
+
+/// safetensors headers are a few MB at most; anything bigger is not one
+const MAX_HEADER_BYTES: u64 = 100 * 1024 * 1024;
+const MAX_GGUF_ENTRIES: u64 = 1_000_000;
+const MAX_GGUF_STRING: u64  = 16 * 1024 * 1024;
+/// Metadata values are shortened to this (LoRA tag frequencies run to MBs)
+const MAX_META_CHARS: usize = 200;
+/// Above this estimate the UI suggests offload / tiling
+const VRAM_WARN_MB: u64     = 8 * 1024;
+/// First UNet convolution; its input channels tell inpainting models apart
+const CONV_IN: &[&str] = &[
+    "model.diffusion_model.input_blocks.0.0.weight",  // SD 1.x / 2.x / XL
+    "conv_in.weight",                                 // diffusers layout
+];
+
+// ── Types ──────────────────────────────────────────────────────────────────
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct ModelInfo {
+    /// "safetensors" | "gguf"
+    pub format:          &'static str,
+    /// "sd1" | "sd2" | "sdxl" | "sd3" | "flux" | "unknown"
+    pub base_model:      &'static str,
+    /// "checkpoint" (all-in-one) | "diffusion_model" (UNet / DiT only) |
+    /// "lora" | "vae" | "controlnet"
+    pub kind:            &'static str,
+    /// Most common tensor type ("F16", "BF16", "Q8_0", …)
+    pub precision:       Option<String>,
+    pub tensor_count:    usize,
+    pub weights_bytes:   u64,
+    pub has_vae:         bool,
+    /// Input channels of the first UNet conv: 9 for inpainting checkpoints
+    /// (latent + masked-image latent + mask), 4 otherwise; None without a UNet
+    pub in_channels:     Option<u64>,
+    /// Separate files the sd binary needs next to this one:
+    /// "vae" | "clip_l" | "clip_g" | "t5xxl"
+    pub required:        Vec<&'static str>,
+    /// Usual VAE for the architecture when none is baked in
+    pub recommended_vae: Option<&'static str>,
+    /// Rough VRAM for one default-resolution image, weights included
+    pub vram_mb:         u64,
+    /// Width × height the architecture was trained for, native size first
+    /// (empty for LoRA / VAE / ControlNet files and unknown models)
+    pub resolutions:     Vec<(u32, u32)>,
+    /// File metadata (safetensors __metadata__ / GGUF keys), values shortened
+    pub metadata:        BTreeMap<String, String>,
+    pub warnings:        Vec<String>,
+}
+
+#[derive(Debug, Default)]
+struct Header {
+    /// (name, type, bytes)
+    tensors:     Vec<(String, String, u64)>,
+    metadata:    BTreeMap<String, String>,
+    in_channels: Option<u64>,
+}
+
+// ── Tauri command ──────────────────────────────────────────────────────────
+
+#[tauri::command]
+pub async fn inspect_sd_model(path: String) -> Result<ModelInfo, AppError> {
+    tokio::task::spawn_blocking(move || inspect(Path::new(&path)))
+        .await?
+        .map_err(AppError::from)
+}
+
+pub(crate) fn inspect(path: &Path) -> Result<ModelInfo, String> {
+    let file = std::fs::File::open(path)
+        .map_err(|e| format!("Cannot open '{}': {}", path.display(), e))?;
+    let mut reader = BufReader::new(file);
+    let mut magic = [0u8; 8];
+    reader.read_exact(&mut magic).map_err(|_| "File is too short to be a model".to_string())?;
+
+    let (format, header) = if &magic[..4] == b"GGUF" {
+        ("gguf", read_gguf(&mut reader, &magic)?)
+    } else {
+        ("safetensors", read_safetensors(&mut reader, u64::from_le_bytes(magic))?)
+    };
+    Ok(describe(format, header))
+}
+
+// ── safetensors ────────────────────────────────────────────────────────────
+
+/// Parse the JSON header that follows the 8-byte little-endian length.
+fn read_safetensors(reader: &mut impl Read, len: u64) -> Result<Header, String> {
+    const NOT_SAFETENSORS: &str = "Not a .safetensors or .gguf file (.ckpt / .pth headers can't be read safely)";
+    if !(2..=MAX_HEADER_BYTES).contains(&len) {
+        return Err(NOT_SAFETENSORS.into());
+    }
+    let mut buf = vec![0u8; len as usize];
+    reader.read_exact(&mut buf).map_err(|_| "safetensors header is truncated".to_string())?;
+    if buf[0] != b'{' {
+        return Err(NOT_SAFETENSORS.into());
+    }
+    let json: serde_json::Map<String, serde_json::Value> = serde_json::from_slice(&buf)
+        .map_err(|e| format!("Invalid safetensors header: {}", e))?;
+
+    let mut header = Header::default();
+    for (name, value) in json {
+        if name == "__metadata__" {
+            if let Some(meta) = value.as_object() {
+                for (k, v) in meta {
+                    let v = v.as_str().map(str::to_string).unwrap_or_else(|| v.to_string());
+                    header.metadata.insert(k.clone(), clip(&v));
+                }
+            }
+            continue;
+        }
+        if CONV_IN.contains(&name.as_str()) {
+            // [out, in, kh, kw]
+            header.in_channels = value["shape"][1].as_u64();
+        }
+        let dtype = value["dtype"].as_str().unwrap_or("?").to_string();
+        let offsets = value["data_offsets"].as_array();
+        let bytes = match offsets.map(|o| (o.first().and_then(|v| v.as_u64()), o.get(1).and_then(|v| v.as_u64()))) {
+            Some((Some(start), Some(end))) => end.saturating_sub(start),
+            _ => 0,
+        };
+        header.tensors.push((name, dtype, bytes));
+    }
+    Ok(header)
+}
+
+// ── GGUF ───────────────────────────────────────────────────────────────────
+
+struct Gguf<'a, R: Read> {
+    r: &'a mut R,
+}
+
+impl<R: Read> Gguf<'_, R> {
+    fn bytes<const N: usize>(&mut self) -> Result<[u8; N], String> {
+        let mut b = [0u8; N];
+        self.r.read_exact(&mut b).map_err(|_| "GGUF header is truncated".to_string())?;
+        Ok(b)
+    }
+    fn u32(&mut self) -> Result<u32, String> { Ok(u32::from_le_bytes(self.bytes()?)) }
+    fn u64(&mut self) -> Result<u64, String> { Ok(u64::from_le_bytes(self.bytes()?)) }
+
+    fn skip(&mut self, n: u64) -> Result<(), String> {
+        let copied = std::io::copy(&mut Read::take(&mut *self.r, n), &mut std::io::sink()).map_err(|e| e.to_string())?;
+        if copied < n { Err("GGUF header is truncated".into()) } else { Ok(()) }
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        let len = self.u64()?;
+        if len > MAX_GGUF_STRING {
+            return Err("GGUF string too long".into());
+        }
+        let mut buf = vec![0u8; len as usize];
+        self.r.read_exact(&mut buf).map_err(|_| "GGUF header is truncated".to_string())?;
+        Ok(String::from_utf8_lossy(&buf).into_owned())
+    }
+
+    /// Read a value of metadata type `ty`; arrays are skipped and summarised.
+    fn value(&mut self, ty: u32) -> Result<String, String> {
+        Ok(match ty {
+            0  => self.bytes::<1>()?[0].to_string(),
+            1  => (self.bytes::<1>()?[0] as i8).to_string(),
+            2  => u16::from_le_bytes(self.bytes()?).to_string(),
+            3  => i16::from_le_bytes(self.bytes()?).to_string(),
+            4  => self.u32()?.to_string(),
+            5  => i32::from_le_bytes(self.bytes()?).to_string(),
+            6  => f32::from_le_bytes(self.bytes()?).to_string(),
+            7  => (self.bytes::<1>()?[0] != 0).to_string(),
+            8  => self.string()?,
+            9  => {
+                let item = self.u32()?;
+                let count = self.u64()?;
+                match value_size(item) {
+                    Some(size) => self.skip(size.saturating_mul(count))?,
+                    None => for _ in 0..count { self.value(item)?; },
+                }
+                format!("[{} items]", count)
+            }
+            10 => self.u64()?.to_string(),
+            11 => i64::from_le_bytes(self.bytes()?).to_string(),
+            12 => f64::from_le_bytes(self.bytes()?).to_string(),
+            _  => return Err(format!("Unknown GGUF value type {}", ty)),
+        })
+    }
+}
+
+/// Fixed size of a GGUF metadata value type (None for strings and arrays).
+fn value_size(ty: u32) -> Option<u64> {
+    match ty {
+        0 | 1 | 7 => Some(1),
+        2 | 3     => Some(2),
+        4 | 5 | 6 => Some(4),
+        10 | 11 | 12 => Some(8),
+        _ => None,
+    }
+}
+
+/// ggml tensor type → (name, bytes per block, elements per block)
+fn ggml_type(ty: u32) -> (&'static str, u64, u64) {
+    match ty {
+        0  => ("F32", 4, 1),
+        1  => ("F16", 2, 1),
+        2  => ("Q4_0", 18, 32),
+        3  => ("Q4_1", 20, 32),
+        6  => ("Q5_0", 22, 32),
+        7  => ("Q5_1", 24, 32),
+        8  => ("Q8_0", 34, 32),
+        10 => ("Q2_K", 84, 256),
+        11 => ("Q3_K", 110, 256),
+        12 => ("Q4_K", 144, 256),
+        13 => ("Q5_K", 176, 256),
+        14 => ("Q6_K", 210, 256),
+        15 => ("Q8_K", 292, 256),
+        30 => ("BF16", 2, 1),
+        _  => ("?", 1, 1),
+    }
+}
+
+/// Parse metadata and the tensor table; `magic` holds the first 8 bytes
+/// (magic + version) already read.
+fn read_gguf(reader: &mut impl Read, magic: &[u8; 8]) -> Result<Header, String> {
+    let version = u32::from_le_bytes([magic[4], magic[5], magic[6], magic[7]]);
+    if version < 2 {
+        return Err(format!("GGUF version {} is not supported", version));
+    }
+    let mut g = Gguf { r: reader };
+    let tensor_count = g.u64()?;
+    let kv_count = g.u64()?;
+    if tensor_count > MAX_GGUF_ENTRIES || kv_count > MAX_GGUF_ENTRIES {
+        return Err("GGUF header is corrupt".into());
+    }
+
+    let mut header = Header::default();
+    for _ in 0..kv_count {
+        let key = g.string()?;
+        let ty = g.u32()?;
+        let value = g.value(ty)?;
+        header.metadata.insert(key, clip(&value));
+    }
+    for _ in 0..tensor_count {
+        let name = g.string()?;
+        let dims = g.u32()?;
+        if dims > 8 {
+            return Err("GGUF header is corrupt".into());
+        }
+        let mut shape = Vec::with_capacity(dims as usize);
+        for _ in 0..dims {
+            shape.push(g.u64()?);
+        }
+        if CONV_IN.contains(&name.as_str()) && shape.len() == 4 {
+            // ggml lists dimensions innermost first: [kw, kh, in, out]
+            header.in_channels = Some(shape[2]);
+        }
+        let elements = shape.iter().fold(1u64, |n, d| n.saturating_mul(*d));
+        let (ty, block_bytes, block_len) = ggml_type(g.u32()?);
+        let _offset = g.u64()?;
+        header.tensors.push((name, ty.to_string(), elements.div_ceil(block_len).saturating_mul(block_bytes)));
+    }
+    Ok(header)
+}
+
+// ── Classification ─────────────────────────────────────────────────────────
+
+fn clip(s: &str) -> String {
+    if s.chars().count() <= MAX_META_CHARS {
+        return s.to_string();
+    }
+    let mut out: String = s.chars().take(MAX_META_CHARS).collect();
+    out.push('…');
+    out
+}
+
+/// Architecture from a metadata string such as "flux", "sdxl",
+/// "stable-diffusion-xl-v1-base" or "sd_v1".
+fn arch_from_name(name: &str) -> Option<&'static str> {
+    let n = name.to_ascii_lowercase();
+    if n.contains("flux") {
+        Some("flux")
+    } else if n.contains("sd3") || n.contains("diffusion-3") {
+        Some("sd3")
+    } else if n.contains("xl") {
+        Some("sdxl")
+    } else if n.contains("sd2") || n.contains("v2") {
+        Some("sd2")
+    } else if n.contains("sd1") || n.contains("v1") {
+        Some("sd1")
+    } else {
+        None
+    }
+}
+
+fn base_model(names: &[&str], metadata: &BTreeMap<String, String>) -> &'static str {
+    let declared = ["general.architecture", "modelspec.architecture", "ss_base_model_version"]
+        .iter()
+        .find_map(|k| metadata.get(*k).and_then(|v| arch_from_name(v)));
+    if let Some(arch) = declared {
+        return arch;
+    }
+    let has = |p: &str| names.iter().any(|n| n.contains(p));
+    if has("double_blocks.") || has("single_transformer_blocks.") {
+        "flux"
+    } else if has("joint_blocks.") || has("transformer_blocks.0.ff_context.") {
+        "sd3"
+    } else if has("label_emb.") || has("conditioner.embedders.1.") || has("add_embedding.") || has("lora_te2_") {
+        "sdxl"
+    } else if has("cond_stage_model.model.") {
+        "sd2"
+    } else if has("input_blocks.") || has("down_blocks.") || has("lora_unet_") {
+        "sd1"
+    } else {
+        "unknown"
+    }
+}
+
+fn kind(names: &[&str]) -> &'static str {
+    let has = |p: &str| names.iter().any(|n| n.contains(p));
+    if has("lora_up") || has("lora_down") || has(".lora_A.") || has(".lora_B.") {
+        "lora"
+    } else if has("control_model.") || has("input_hint_block.") || has("controlnet_cond_embedding.") {
+        "controlnet"
+    } else if !names.is_empty()
+        && names.iter().all(|n| ["encoder.", "decoder.", "quant_conv.", "post_quant_conv."].iter().any(|p| n.starts_with(p)))
+    {
+        "vae"
+    } else if has("cond_stage_model.") || has("conditioner.") || has("text_encoders.") {
+        "checkpoint"
+    } else {
+        "diffusion_model"
+    }
+}
+
+fn describe(format: &'static str, header: Header) -> ModelInfo {
+    let names: Vec<&str> = header.tensors.iter().map(|(n, ..)| n.as_str()).collect();
+    let base_model = base_model(&names, &header.metadata);
+    let kind = kind(&names);
+    let has = |p: &str| names.iter().any(|n| n.starts_with(p));
+    let has_vae = kind == "vae" || has("first_stage_model.") || has("vae.");
+
+    let mut required = Vec::new();
+    if matches!(kind, "checkpoint" | "diffusion_model") {
+        if !has_vae {
+            required.push("vae");
+        }
+        let encoders: &[&str] = match base_model {
+            "flux" => &["clip_l", "t5xxl"],
+            "sd3"  => &["clip_l", "clip_g", "t5xxl"],
+            _      => &[],
+        };
+        for enc in encoders {
+            if !has(&format!("text_encoders.{}.", enc)) {
+                required.push(*enc);
+            }
+        }
+    }
+    let recommended_vae = required.contains(&"vae").then(|| match base_model {
+        "sd1" | "sd2" => Some("vae-ft-mse-840000-ema-pruned.safetensors"),
+        "sdxl"        => Some("sdxl_vae.safetensors"),
+        "flux"        => Some("ae.safetensors"),
+        _             => None,
+    }).flatten();
+
+    let weights_bytes: u64 = header.tensors.iter().map(|(.., b)| b).sum();
+    let mut by_type: BTreeMap<&str, u64> = BTreeMap::new();
+    for (_, ty, bytes) in &header.tensors {
+        *by_type.entry(ty.as_str()).or_default() += bytes;
+    }
+    let precision = by_type.into_iter().max_by_key(|(_, b)| *b).map(|(t, _)| t.to_string());
+
+    // Activations + VAE decode at the architecture's native resolution
+    let overhead_mb = match (kind, base_model) {
+        ("lora" | "vae" | "controlnet", _) => 0,
+        (_, "sd1")  => 1_024,
+        (_, "sd2")  => 1_536,
+        (_, "sdxl" | "sd3") => 2_560,
+        (_, "flux") => 3_072,
+        _           => 1_024,
+    };
+    let vram_mb = weights_bytes.div_ceil(1024 * 1024) + overhead_mb;
+
+    let mut warnings = Vec::new();
+    if base_model == "unknown" {
+        warnings.push("Could not recognise the model architecture".into());
+    }
+    match kind {
+        "lora" | "vae" | "controlnet" => warnings.push(format!(
+            "This is a {} file, not a checkpoint — load it alongside a {} model",
+            if kind == "vae" { "VAE" } else if kind == "lora" { "LoRA" } else { "ControlNet" },
+            if base_model == "unknown" { "matching" } else { base_model },
+        )),
+        _ if !required.is_empty() => warnings.push(format!(
+            "Needs separate {} file{} to generate",
+            required.join(" + "),
+            if required.len() > 1 { "s" } else { "" },
+        )),
+        _ => {}
+    }
+    if kind != "lora" && vram_mb > VRAM_WARN_MB {
+        warnings.push(format!(
+            "Needs about {:.1} GB of VRAM — enable offload to CPU and VAE tiling on smaller GPUs",
+            vram_mb as f64 / 1024.0,
+        ));
+    }
+
+    let resolutions = match kind {
+        "checkpoint" | "diffusion_model" => resolution_presets(base_model).to_vec(),
+        _ => Vec::new(),
+    };
+
+    ModelInfo {
+        format,
+        base_model,
+        kind,
+        precision,
+        tensor_count: header.tensors.len(),
+        weights_bytes,
+        has_vae,
+        in_channels: header.in_channels,
+        required,
+        recommended_vae,
+        vram_mb,
+        resolutions,
+        metadata: header.metadata,
+        warnings,
+    }
+}
+
+// ── Resolutions ────────────────────────────────────────────────────────────
+
+/// Size rules of an architecture.
+struct SizeRule {
+    native:     u32,
+    min_side:   u32,
+    /// Above this the model repeats subjects and activations outgrow the
+    /// VRAM estimate — upscale afterwards instead
+    max_pixels: u64,
+    /// Latent / patch granularity: 8 for UNet models, 16 for the 2×2-patch DiTs
+    multiple:   u32,
+}
+
+fn size_rule(base_model: &str) -> SizeRule {
+    match base_model {
+        "sd1"  => SizeRule { native: 512,  min_side: 256, max_pixels: 1024 * 1024, multiple: 8 },
+        "sd2"  => SizeRule { native: 768,  min_side: 384, max_pixels: 1280 * 1280, multiple: 8 },
+        "sdxl" => SizeRule { native: 1024, min_side: 512, max_pixels: 1536 * 1536, multiple: 8 },
+        "sd3" | "flux" => SizeRule { native: 1024, min_side: 256, max_pixels: 2048 * 1024, multiple: 16 },
+        _      => SizeRule { native: 512,  min_side: 64,  max_pixels: u64::MAX, multiple: 8 },
+    }
+}
+
+fn resolution_presets(base_model: &str) -> &'static [(u32, u32)] {
+    match base_model {
+        "sd1" => &[(512, 512), (512, 768), (768, 512), (640, 640)],
+        "sd2" => &[(768, 768), (768, 1024), (1024, 768), (512, 512)],
+        "sdxl" | "sd3" | "flux" => &[
+            (1024, 1024), (896, 1152), (1152, 896), (832, 1216), (1216, 832), (768, 1344), (1344, 768),
+        ],
+        _ => &[],
+    }
+}
+
+/// Fails for a size the architecture can't produce, before the model is
+/// loaded: wrong granularity, too small, or far past what it was trained on.
+pub(crate) fn check_resolution(base_model: &str, width: u32, height: u32) -> Result<(), String> {
+    let rule = size_rule(base_model);
+    let name = base_model.to_uppercase();
+    let suggest = || {
+        let presets = resolution_presets(base_model);
+        if presets.is_empty() {
+            String::new()
+        } else {
+            let list: Vec<String> = presets.iter().take(5).map(|(w, h)| format!("{}×{}", w, h)).collect();
+            format!(" Try {}.", list.join(", "))
+        }
+    };
+    if width % rule.multiple != 0 || height % rule.multiple != 0 {
+        return Err(format!(
+            "{}×{} doesn't work with {} models: width and height must be multiples of {}.{}",
+            width, height, name, rule.multiple, suggest(),
+        ));
+    }
+    if width.min(height) < rule.min_side {
+        return Err(format!(
+            "{}×{} is too small for {} models (at least {} px per side; they are trained at {}×{}).{}",
+            width, height, name, rule.min_side, rule.native, rule.native, suggest(),
+        ));
+    }
+    if width as u64 * height as u64 > rule.max_pixels {
+        return Err(format!(
+            "{}×{} is too large for {} models (trained at {}×{}): expect duplicated subjects and \
+             running out of VRAM. Generate at a native size and upscale instead.{}",
+            width, height, name, rule.native, rule.native, suggest(),
+        ));
+    }
+    Ok(())
+}
+
+// ── Unit tests ─────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn safetensors(tensors: &[(&str, u64)], metadata: &[(&str, &str)]) -> Vec<u8> {
+        let mut json = serde_json::Map::new();
+        let mut offset = 0;
+        for (name, bytes) in tensors {
+            json.insert(name.to_string(), serde_json::json!({
+                "dtype": "F16", "shape": [bytes / 2], "data_offsets": [offset, offset + bytes],
+            }));
+            offset += bytes;
+        }
+        if !metadata.is_empty() {
+            let meta: serde_json::Map<_, _> = metadata.iter()
+                .map(|(k, v)| (k.to_string(), serde_json::Value::String(v.to_string())))
+                .collect();
+            json.insert("__metadata__".into(), meta.into());
+        }
+        let header = serde_json::to_vec(&json).unwrap();
+        let mut out = (header.len() as u64).to_le_bytes().to_vec();
+        out.extend(header);
+        out
+    }
+
+    fn parse(bytes: &[u8]) -> Result<ModelInfo, String> {
+        let mut r = bytes;
+        let mut magic = [0u8; 8];
+        r.read_exact(&mut magic).unwrap();
+        if &magic[..4] == b"GGUF" {
+            Ok(describe("gguf", read_gguf(&mut r, &magic)?))
+        } else {
+            Ok(describe("safetensors", read_safetensors(&mut r, u64::from_le_bytes(magic))?))
+        }
+    }
+
+    #[test]
+    fn test_sd15_checkpoint_with_vae() {
+        let info = parse(&safetensors(&[
+            ("model.diffusion_model.input_blocks.0.0.weight", 2 << 30),
+            ("cond_stage_model.transformer.text_model.embeddings.token_embedding.weight", 100),
+            ("first_stage_model.decoder.conv_in.weight", 100),
+        ], &[])).unwrap();
+        assert_eq!((info.base_model, info.kind), ("sd1", "checkpoint"));
+        assert!(info.has_vae && info.required.is_empty() && info.recommended_vae.is_none());
+        assert_eq!(info.precision.as_deref(), Some("F16"));
+        assert!(info.warnings.is_empty(), "{:?}", info.warnings);
+    }
+
+    #[test]
+    fn test_sdxl_without_vae() {
+        let info = parse(&safetensors(&[
+            ("model.diffusion_model.label_emb.0.0.weight", 100),
+            ("conditioner.embedders.1.model.ln_final.weight", 100),
+        ], &[])).unwrap();
+        assert_eq!((info.base_model, info.kind), ("sdxl", "checkpoint"));
+        assert_eq!(info.required, ["vae"]);
+        assert_eq!(info.recommended_vae, Some("sdxl_vae.safetensors"));
+    }
+
+    #[test]
+    fn test_flux_diffusion_model_needs_encoders_and_vram() {
+        let info = parse(&safetensors(&[("double_blocks.0.img_attn.qkv.weight", 12 << 30)], &[])).unwrap();
+        assert_eq!((info.base_model, info.kind), ("flux", "diffusion_model"));
+        assert_eq!(info.required, ["vae", "clip_l", "t5xxl"]);
+        assert!(info.vram_mb > 12 * 1024);
+        assert_eq!(info.warnings.len(), 2);
+    }
+
+    #[test]
+    fn test_lora_uses_metadata_base() {
+        let info = parse(&safetensors(
+            &[("lora_unet_down_blocks_0_attentions_0_proj_in.lora_up.weight", 100)],
+            &[("ss_base_model_version", "sdxl_base_v1-0"), ("ss_tag_frequency", &"x".repeat(5000))],
+        )).unwrap();
+        assert_eq!((info.base_model, info.kind), ("sdxl", "lora"));
+        assert!(info.required.is_empty());
+        assert!(info.metadata["ss_tag_frequency"].chars().count() <= MAX_META_CHARS + 1);
+    }
+
+    #[test]
+    fn test_gguf_metadata_and_tensors() {
+        let string = |s: &str| [(s.len() as u64).to_le_bytes().to_vec(), s.as_bytes().to_vec()].concat();
+        let mut g = b"GGUF".to_vec();
+        g.extend(3u32.to_le_bytes());
+        g.extend(1u64.to_le_bytes()); // tensors
+        g.extend(2u64.to_le_bytes()); // kv pairs
+        g.extend(string("general.architecture"));
+        g.extend(8u32.to_le_bytes());
+        g.extend(string("flux"));
+        g.extend(string("general.file_type"));
+        g.extend(9u32.to_le_bytes()); // array of u32
+        g.extend(4u32.to_le_bytes());
+        g.extend(2u64.to_le_bytes());
+        g.extend([0u8; 8]);
+        g.extend(string("double_blocks.0.img_attn.qkv.weight"));
+        g.extend(2u32.to_le_bytes());
+        g.extend(3072u64.to_le_bytes());
+        g.extend(1024u64.to_le_bytes());
+        g.extend(8u32.to_le_bytes()); // Q8_0
+        g.extend(0u64.to_le_bytes());
+
+        let info = parse(&g).unwrap();
+        assert_eq!((info.format, info.base_model), ("gguf", "flux"));
+        assert_eq!(info.precision.as_deref(), Some("Q8_0"));
+        assert_eq!(info.weights_bytes, 3072 * 1024 / 32 * 34);
+        assert_eq!(info.metadata["general.file_type"], "[2 items]");
+    }
+
+    #[test]
+    fn test_in_channels_from_conv_in() {
+        let json = serde_json::json!({
+            "model.diffusion_model.input_blocks.0.0.weight": {
+                "dtype": "F16", "shape": [320, 9, 3, 3], "data_offsets": [0, 51840],
+            },
+        }).to_string();
+        let mut bytes = (json.len() as u64).to_le_bytes().to_vec();
+        bytes.extend(json.as_bytes());
+        assert_eq!(parse(&bytes).unwrap().in_channels, Some(9));
+        assert_eq!(parse(&safetensors(&[("double_blocks.0.img_attn.qkv.weight", 100)], &[])).unwrap().in_channels, None);
+    }
+
+    #[test]
+    fn test_rejects_non_safetensors() {
+        let mut pickle = vec![0x80, 0x02, b'c', b'c', 0, 0, 0, 0];
+        pickle.extend([0u8; 64]);
+        assert!(parse(&pickle).is_err());
+        assert!(parse(&[8, 0, 0, 0, 0, 0, 0, 0, b'[', b']', 0, 0, 0, 0, 0, 0]).is_err());
+    }
+
+    #[test]
+    fn test_resolution_rules() {
+        for family in ["sd1", "sd2", "sdxl", "sd3", "flux"] {
+            for &(w, h) in resolution_presets(family) {
+                assert!(check_resolution(family, w, h).is_ok(), "{} {}x{}", family, w, h);
+            }
+        }
+        let err = check_resolution("sd1", 1536, 1536).unwrap_err();
+        assert!(err.contains("too large") && err.contains("512×512"), "{}", err);
+        assert!(check_resolution("flux", 1000, 1000).unwrap_err().contains("multiples of 16"));
+        assert!(check_resolution("sdxl", 1024, 256).unwrap_err().contains("too small"));
+        // Unknown models are only held to the latent granularity
+        assert!(check_resolution("unknown", 4096, 4096).is_ok());
+        assert!(check_resolution("unknown", 500, 512).is_err());
+    }
+}
@@ -0,0 +1,325 @@
+// sd_models.rs — fetch SD checkpoints from HuggingFace / CivitAI into the models dir
+//
+// Resolves a repo / model page to a file URL, its name and (when the API
+// publishes one) its SHA-256, then hands it to downloads.rs — which streams it
+// to `<file>.part` in parallel segments, resumes interrupted downloads and
+// verifies the checksum.
+//
+// Accepted sources:
+//   https://huggingface.co/<owner>/<repo>/blob|resolve/<rev>/<file>
+//   <owner>/<repo>/<file> or <owner>/<repo>   (HuggingFace shorthand)
+//   https://civitai.com/models/<id>[?modelVersionId=<v>]
+//   https://civitai.com/api/download/models/<versionId>
+//   any other direct https:// link
+//
+// Tauri commands exposed:
+//   download_sd_model → returns the saved path; progress arrives as
+//                       "download-progress" events with component "sd-model"
+
+use crate::downloads;
+use crate::error::AppError;
+use std::path::{Path, PathBuf};
+
+const HF_BASE: &str      = "https://huggingface.co";
+const CIVITAI_BASE: &str = "https://civitai.com";
+const MODEL_EXTENSIONS: &[&str] = &["safetensors", "gguf", "ckpt", "pth", "bin"];
+
+// ── Types ──────────────────────────────────────────────────────────────────
+
+/// Where a model comes from, before any API lookup.
+#[derive(Debug, Clone, PartialEq)]
+enum Source {
+    /// Direct file URL
+    Url(String),
+    /// HuggingFace repo without a file — the file is picked from the listing
+    HfRepo { repo: String, revision: String },
+    /// CivitAI model page; version None = latest
+    Civitai { model_id: u64, version_id: Option<u64> },
+}
+
+// ── Source resolution ──────────────────────────────────────────────────────
+
+fn parse_source(input: &str) -> Result<Source, String> {
+    let input = input.trim();
+    if input.is_empty() {
+        return Err("No model URL or repository given".into());
+    }
+    if !input.contains("://") {
+        // owner/repo[/path/to/file]
+        let parts: Vec<&str> = input.trim_matches('/').split('/').collect();
+        if parts.len() < 2 || parts.iter().any(|p| p.is_empty() || *p == "..") {
+            return Err(format!("'{}' is neither a URL nor a HuggingFace owner/repo", input));
+        }
+        let repo = format!("{}/{}", parts[0], parts[1]);
+        if parts.len() == 2 {
+            return Ok(Source::HfRepo { repo, revision: "main".into() });
+        }
+        return Ok(Source::Url(format!("{}/{}/resolve/main/{}", HF_BASE, repo, parts[2..].join("/"))));
+    }
+
+    let url = reqwest::Url::parse(input).map_err(|e| format!("Invalid URL '{}': {}", input, e))?;
+    let host = url.host_str().unwrap_or("").trim_start_matches("www.");
+    let segments: Vec<&str> = url.path_segments().map(|s| s.filter(|p| !p.is_empty()).collect()).unwrap_or_default();
+    match host {
+        "huggingface.co" | "hf.co" => match segments.as_slice() {
+            [owner, repo] | [owner, repo, "tree", _] => Ok(Source::HfRepo {
+                repo:     format!("{}/{}", owner, repo),
+                revision: segments.get(3).unwrap_or(&"main").to_string(),
+            }),
+            // The page view of a file → its raw download
+            [owner, repo, "blob" | "resolve", rev, file @ ..] if !file.is_empty() => Ok(Source::Url(
+                format!("{}/{}/{}/resolve/{}/{}", HF_BASE, owner, repo, rev, file.join("/"))
+            )),
+            _ => Ok(Source::Url(input.to_string())),
+        },
+        "civitai.com" => match segments.as_slice() {
+            ["models", id, ..] => Ok(Source::Civitai {
+                model_id:   id.parse().map_err(|_| format!("Bad CivitAI model id '{}'", id))?,
+                version_id: url.query_pairs()
+                    .find(|(k, _)| k == "modelVersionId")
+                    .and_then(|(_, v)| v.parse().ok()),
+            }),
+            _ => Ok(Source::Url(input.to_string())),
+        },
+        _ => Ok(Source::Url(input.to_string())),
+    }
+}
+
+/// Turn a source into (download URL, suggested file name, SHA-256) via the
+/// HuggingFace / CivitAI APIs where needed.
+async fn resolve(client: &reqwest::Client, source: Source, hf_token: Option<&str>, civitai_token: Option<&str>) -> Result<(String, Option<String>, Option<String>), String> {
+    match source {
+        Source::Url(url) => Ok((url, None, None)),
+        Source::HfRepo { repo, revision } => {
+            // blobs=true adds the LFS sha256 of every file to the listing
+            let mut req = client.get(format!("{}/api/models/{}/revision/{}?blobs=true", HF_BASE, repo, revision));
+            if let Some(t) = hf_token { req = req.bearer_auth(t); }
+            let info: serde_json::Value = req.send().await
+                .map_err(|e| format!("HuggingFace API error: {}", e))?
+                .error_for_status().map_err(|e| format!("HuggingFace API error: {}", e))?
+                .json().await.map_err(|e| e.to_string())?;
+            let files: Vec<&str> = info["siblings"].as_array().into_iter().flatten()
+                .filter_map(|s| s["rfilename"].as_str())
+                .collect();
+            let file = pick_repo_file(&files)?;
+            let sha256 = info["siblings"].as_array().into_iter().flatten()
+                .find(|s| s["rfilename"].as_str() == Some(file))
+                .and_then(|s| s["lfs"]["sha256"].as_str())
+                .map(String::from);
+            Ok((format!("{}/{}/resolve/{}/{}", HF_BASE, repo, revision, file), None, sha256))
+        }
+        Source::Civitai { model_id, version_id } => {
+            let mut req = client.get(format!("{}/api/v1/models/{}", CIVITAI_BASE, model_id));
+            if let Some(t) = civitai_token { req = req.bearer_auth(t); }
+            let info: serde_json::Value = req.send().await
+                .map_err(|e| format!("CivitAI API error: {}", e))?
+                .error_for_status().map_err(|e| format!("CivitAI API error: {}", e))?
+                .json().await.map_err(|e| e.to_string())?;
+            civitai_file(&info, version_id)
+        }
+    }
+}
+
+/// The single model file in a repo listing; a preference for .safetensors
+/// breaks ties between formats, several candidates of the same kind is an error.
+fn pick_repo_file<'a>(files: &[&'a str]) -> Result<&'a str, String> {
+    let models: Vec<&str> = files.iter().copied().filter(|f| has_model_extension(f)).collect();
+    for ext in MODEL_EXTENSIONS {
+        let matching: Vec<&str> = models.iter().copied().filter(|f| f.ends_with(&format!(".{}", ext))).collect();
+        match matching.as_slice() {
+            []     => continue,
+            [only] => return Ok(only),
+            many   => return Err(format!(
+                "The repository has several .{} files — pick one (owner/repo/<file>): {}",
+                ext, many.join(", ")
+            )),
+        }
+    }
+    Err("No model file (.safetensors / .gguf / .ckpt) found in the repository".into())
+}
+
+/// (download URL, file name, SHA-256) of the primary file of a CivitAI model version.
+fn civitai_file(info: &serde_json::Value, version_id: Option<u64>) -> Result<(String, Option<String>, Option<String>), String> {
+    let versions = info["modelVersions"].as_array().ok_or("CivitAI returned no model versions")?;
+    let version = match version_id {
+        Some(id) => versions.iter().find(|v| v["id"].as_u64() == Some(id))
+            .ok_or_else(|| format!("CivitAI model has no version {}", id))?,
+        None => versions.first().ok_or("CivitAI model has no versions")?,
+    };
+    let files = version["files"].as_array().ok_or("CivitAI version has no files")?;
+    let file = files.iter().find(|f| f["primary"].as_bool() == Some(true))
+        .or_else(|| files.first())
+        .ok_or("CivitAI version has no files")?;
+    let url = file["downloadUrl"].as_str().ok_or("CivitAI file has no download URL")?;
+    Ok((
+        url.to_string(),
+        file["name"].as_str().map(String::from),
+        file["hashes"]["SHA256"].as_str().map(String::from),
+    ))
+}
+
+fn has_model_extension(name: &str) -> bool {
+    Path::new(name).extension().and_then(|e| e.to_str())
+        .is_some_and(|e| MODEL_EXTENSIONS.contains(&e.to_ascii_lowercase().as_str()))
+}
+
+/// Strip directories and characters that are invalid in file names.
+fn sanitize_file_name(name: &str) -> Option<String> {
+    let base = name.rsplit(['/', '\\']).next().unwrap_or("");
+    let clean: String = base.chars()
+        .map(|c| if matches!(c, ':' | '*' | '?' | '"' | '<' | '>' | '|') || c.is_control() { '_' } else { c })
+        .collect();
+    let clean = clean.trim().trim_start_matches('.').to_string();
+    (!clean.is_empty()).then_some(clean)
+}
+
+// ── Tauri commands ─────────────────────────────────────────────────────────
+
+/// Downloads a checkpoint / LoRA / VAE into `dest_dir` and returns its path.
+/// A previous partial download of the same file is resumed. CivitAI needs
+/// `civitai_token` for most checkpoints; `hf_token` unlocks gated repos.
+/// `sha256` is only needed for direct links — HuggingFace and CivitAI
+/// publish the hash themselves.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn download_sd_model(
+    app_handle:    tauri::AppHandle,
+    url_or_repo:   String,
+    dest_dir:      String,
+    file_name:     Option<String>,
+    hf_token:      Option<String>,
+    civitai_token: Option<String>,
+    sha256:        Option<String>,
+) -> Result<String, AppError> {
+    let dest_dir = PathBuf::from(&dest_dir);
+    std::fs::create_dir_all(&dest_dir).map_err(|e| format!("Cannot create {}: {}", dest_dir.display(), e))?;
+    let hf_token = hf_token.as_deref().map(str::trim).filter(|t| !t.is_empty());
+    let civitai_token = civitai_token.as_deref().map(str::trim).filter(|t| !t.is_empty());
+
+    let client = downloads::client()?;
+    let source = parse_source(&url_or_repo)?;
+    println!("[SD] download_sd_model — {:?}", source);
+    let (url, api_name, api_sha256) = resolve(&client, source, hf_token, civitai_token).await?;
+
+    // The token goes to the host it belongs to only
+    let token = if url.starts_with(CIVITAI_BASE) {
+        civitai_token
+    } else if url.starts_with(HF_BASE) {
+        hf_token
+    } else {
+        None
+    };
+    let denied = |e: String| if !e.starts_with("Access denied") {
+        e
+    } else if url.starts_with(CIVITAI_BASE) {
+        format!("CivitAI refused the download ({}). Add a CivitAI API token in Settings → Image Generation.", e)
+    } else {
+        format!("{}. Gated HuggingFace models need an access token and accepted terms.", e)
+    };
+
+    // Name from the caller, the API, or the URL; failing those, from the
+    // response's Content-Disposition
+    let known_name = file_name.as_deref().and_then(sanitize_file_name)
+        .or_else(|| api_name.as_deref().and_then(sanitize_file_name))
+        .or_else(|| {
+            let last = reqwest::Url::parse(&url).ok()?.path_segments()?.last()?.to_string();
+            Some(last).filter(|l| has_model_extension(l)).and_then(|l| sanitize_file_name(&l))
+        });
+    let name = match known_name {
+        Some(n) => n,
+        None => {
+            let probe = downloads::probe(&client, &url, token).await?;
+            if probe.status == reqwest::StatusCode::UNAUTHORIZED || probe.status == reqwest::StatusCode::FORBIDDEN {
+                return Err(denied(format!("Access denied (HTTP {})", probe.status)).into());
+            }
+            probe.file_name.as_deref().and_then(sanitize_file_name)
+                .ok_or("Cannot tell the model's file name — pass file_name")?
+        }
+    };
+
+    let path = dest_dir.join(&name);
+    if path.exists() {
+        println!("[SD] Model already present at {:?} — skipping download", path);
+        return Ok(path.to_string_lossy().to_string());
+    }
+
+    let path = downloads::fetch(&app_handle, &client, downloads::Download {
+        url:       url.clone(),
+        dest:      path,
+        component: "sd-model",
+        bearer:    token.map(String::from),
+        sha256:    sha256.filter(|h| !h.trim().is_empty()).or(api_sha256),
+        segments:  downloads::DEFAULT_SEGMENTS,
+    }).await.map_err(denied)?;
+    println!("[SD] Model downloaded: {:?}", path);
+    Ok(path.to_string_lossy().to_string())
+}
+
+// ── Unit tests ─────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::downloads::{disposition_name, part_path};
+
+    #[test]
+    fn test_parse_huggingface_sources() {
+        assert_eq!(
+            parse_source("stabilityai/sdxl-turbo/sd_xl_turbo_1.0_fp16.safetensors").unwrap(),
+            Source::Url("https://huggingface.co/stabilityai/sdxl-turbo/resolve/main/sd_xl_turbo_1.0_fp16.safetensors".into())
+        );
+        assert_eq!(
+            parse_source("https://huggingface.co/runwayml/sd-v1-5/blob/fp16/v1-5.safetensors").unwrap(),
+            Source::Url("https://huggingface.co/runwayml/sd-v1-5/resolve/fp16/v1-5.safetensors".into())
+        );
+        assert_eq!(
+            parse_source("stabilityai/sdxl-turbo").unwrap(),
+            Source::HfRepo { repo: "stabilityai/sdxl-turbo".into(), revision: "main".into() }
+        );
+        assert!(parse_source("justaname").is_err());
+    }
+
+    #[test]
+    fn test_parse_civitai_sources() {
+        assert_eq!(
+            parse_source("https://civitai.com/models/4384/dreamshaper?modelVersionId=128713").unwrap(),
+            Source::Civitai { model_id: 4384, version_id: Some(128713) }
+        );
+        assert_eq!(
+            parse_source("https://civitai.com/api/download/models/128713").unwrap(),
+            Source::Url("https://civitai.com/api/download/models/128713".into())
+        );
+    }
+
+    #[test]
+    fn test_pick_repo_file() {
+        assert_eq!(pick_repo_file(&["README.md", "model.safetensors", "model.ckpt"]).unwrap(), "model.safetensors");
+        assert!(pick_repo_file(&["a.safetensors", "b.safetensors"]).unwrap_err().contains("a.safetensors, b.safetensors"));
+        assert!(pick_repo_file(&["README.md"]).is_err());
+    }
+
+    #[test]
+    fn test_civitai_primary_file() {
+        let info = serde_json::json!({ "modelVersions": [
+            { "id": 2, "files": [
+                { "name": "x.yaml", "downloadUrl": "https://civitai.com/api/download/models/2?type=Config" },
+                { "name": "dream.safetensors", "primary": true, "downloadUrl": "https://civitai.com/api/download/models/2",
+                  "hashes": { "SHA256": "ABC123" } },
+            ]},
+            { "id": 1, "files": [{ "name": "old.safetensors", "downloadUrl": "https://civitai.com/api/download/models/1" }] },
+        ]});
+        assert_eq!(civitai_file(&info, None).unwrap().1.as_deref(), Some("dream.safetensors"));
+        assert_eq!(civitai_file(&info, Some(1)).unwrap().0, "https://civitai.com/api/download/models/1");
+        assert_eq!(civitai_file(&info, None).unwrap().2.as_deref(), Some("ABC123"));
+        assert_eq!(civitai_file(&info, Some(1)).unwrap().2, None);
+        assert!(civitai_file(&info, Some(9)).is_err());
+    }
+
+    #[test]
+    fn test_file_name_helpers() {
+        assert_eq!(disposition_name(r#"attachment; filename="dream.safetensors""#).as_deref(), Some("dream.safetensors"));
+        assert_eq!(sanitize_file_name("../../etc/passwd").as_deref(), Some("passwd"));
+        assert_eq!(sanitize_file_name("a:b?.gguf").as_deref(), Some("a_b_.gguf"));
+        assert_eq!(part_path(Path::new("/m/x.safetensors")), Path::new("/m/x.safetensors.part"));
+    }
+}
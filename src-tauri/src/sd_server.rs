@@ -0,0 +1,631 @@
+// sd_server.rs — persistent stable-diffusion.cpp server (keeps the model loaded)
+//
+// Every CLI run reloads the checkpoint, which dominates generation time. In
+// persistent mode the `sd-server` binary from the same release is started
+// once per model / load configuration, kept warm, and plain txt2img requests
+// from run_local_sd are proxied to it over HTTP on 127.0.0.1. A monitor task
+// polls the process and its HTTP port; a crash or a server that stops
+// answering is restarted with the same configuration (at most MAX_RESTARTS
+// times per RESTART_WINDOW). Switching model, VAE or memory flags restarts it.
+//
+// Tauri commands exposed:
+//   start_sd_server(config)  → ServerStatus (pre-loads the model)
+//   stop_sd_server           → kills the server
+//   get_sd_server_status     → ServerStatus
+//
+// Events emitted:
+//   sd-server-status → ServerStatus
+//   sd-progress      → SdProgress (parsed from the server's output, as for the CLI)
+//
+// Each generation is a watchdog job whose activity is the server's output;
+// killing it from the job list stops the server, and the next request
+// starts a fresh one.
+
+use crate::error::AppError;
+use crate::local_sd::{self, LocalSdRequest, SdProgress};
+use crate::{gpu_devices, http, power, watchdog};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::process::Stdio;
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use tauri::Manager;
+use tokio::io::{AsyncRead, AsyncReadExt};
+use tokio::process::{Child, Command};
+
+const HEALTH_EVERY: Duration    = Duration::from_secs(5);
+const PROBE_TIMEOUT: Duration   = Duration::from_secs(3);
+/// Consecutive failed health probes before an idle server counts as hung
+const HEALTH_FAILURES: u32      = 3;
+/// Loading a large checkpoint on CPU can take many minutes
+const LOAD_TIMEOUT: Duration    = Duration::from_secs(15 * 60);
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30 * 60);
+const MAX_RESTARTS: usize       = 3;
+const RESTART_WINDOW: Duration  = Duration::from_secs(10 * 60);
+const LOG_LINES: usize          = 40;
+
+static STATUS: Mutex<ServerStatus> = Mutex::new(ServerStatus::stopped());
+/// A generation is in flight — the server can't answer health probes then
+static BUSY: AtomicBool = AtomicBool::new(false);
+static RESTARTS: AtomicU32 = AtomicU32::new(0);
+/// Bumped on every start / stop so a stale monitor task exits
+static EPOCH: AtomicU64 = AtomicU64::new(0);
+/// Watchdog activity of the generation in flight, touched by the pipe readers
+static ACTIVITY: Mutex<Option<watchdog::Activity>> = Mutex::new(None);
+
+fn server() -> &'static tokio::sync::Mutex<Option<Server>> {
+    static SERVER: OnceLock<tokio::sync::Mutex<Option<Server>>> = OnceLock::new();
+    SERVER.get_or_init(|| tokio::sync::Mutex::new(None))
+}
+
+// ── Types ──────────────────────────────────────────────────────────────────
+
+/// Everything that is fixed when the server loads the model. A request
+/// with a different configuration restarts the server.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct ServerConfig {
+    pub model_path:     String,
+    #[serde(default)]
+    pub gpu_backend:    String,
+    #[serde(default)]
+    pub gpu_device:     Option<u32>,
+    #[serde(default)]
+    pub vae_path:       Option<String>,
+    #[serde(default)]
+    pub threads:        u32,
+    #[serde(default)]
+    pub vae_on_cpu:     bool,
+    #[serde(default)]
+    pub vae_tiling:     bool,
+    #[serde(default)]
+    pub offload_to_cpu: bool,
+}
+
+/// sd-server builds disagree on the listen flags: current ones take
+/// --listen-ip / --listen-port, older ones --host / --port.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ArgStyle {
+    Listen,
+    HostPort,
+}
+
+struct Server {
+    config:  ServerConfig,
+    port:    u16,
+    child:   Child,
+    log:     Arc<Mutex<VecDeque<String>>>,
+    started: Instant,
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct ServerStatus {
+    /// "stopped" | "starting" | "ready" | "busy" | "restarting" | "failed"
+    pub state:       &'static str,
+    pub model_path:  Option<String>,
+    pub gpu_backend: Option<String>,
+    pub port:        Option<u16>,
+    pub uptime_secs: u64,
+    pub restarts:    u32,
+    pub last_error:  Option<String>,
+}
+
+impl ServerStatus {
+    const fn stopped() -> Self {
+        Self { state: "stopped", model_path: None, gpu_backend: None, port: None, uptime_secs: 0, restarts: 0, last_error: None }
+    }
+}
+
+impl ServerConfig {
+    pub(crate) fn from_request(gpu_backend: &str, req: &LocalSdRequest) -> Self {
+        Self {
+            model_path:     req.model_path.clone(),
+            gpu_backend:    gpu_backend.to_string(),
+            gpu_device:     req.gpu_device,
+            vae_path:       req.vae_path.clone().filter(|v| !v.trim().is_empty()),
+            threads:        req.threads.unwrap_or(0),
+            vae_on_cpu:     req.vae_on_cpu.unwrap_or(false),
+            vae_tiling:     req.vae_tiling.unwrap_or(false),
+            offload_to_cpu: req.offload_to_cpu.unwrap_or(false),
+        }
+    }
+
+    fn args(&self, port: u16, style: ArgStyle) -> Vec<String> {
+        let mut args = vec!["-m".to_string(), self.model_path.clone()];
+        match style {
+            ArgStyle::Listen   => args.extend(["--listen-ip".into(), "127.0.0.1".into(), "--listen-port".into(), port.to_string()]),
+            ArgStyle::HostPort => args.extend(["--host".into(), "127.0.0.1".into(), "--port".into(), port.to_string()]),
+        }
+        if self.threads > 0 {
+            args.extend(["-t".into(), self.threads.to_string()]);
+        }
+        if let Some(vae) = &self.vae_path {
+            args.extend(["--vae".into(), vae.clone()]);
+        }
+        if self.vae_on_cpu { args.push("--vae-on-cpu".into()); }
+        if self.vae_tiling { args.push("--vae-tiling".into()); }
+        if self.offload_to_cpu { args.push("--offload-to-cpu".into()); }
+        args
+    }
+}
+
+// ── Public API ─────────────────────────────────────────────────────────────
+
+/// The server only does plain txt2img; inpainting, ControlNet, upscaling and
+/// raw extra flags still go through the CLI.
+pub(crate) fn supports(req: &LocalSdRequest) -> bool {
+    let given = |v: &Option<String>| v.as_deref().is_some_and(|s| !s.trim().is_empty());
+    !given(&req.init_image_base64)
+        && !given(&req.mask_base64)
+        && !given(&req.control_net_path)
+        && !given(&req.control_image_base64)
+        && !given(&req.upscale_model_path)
+        && !given(&req.extra_args)
+}
+
+/// Generates through the warm server (starting it, or swapping the model,
+/// first). Returns base64 PNG like run_local_sd.
+pub(crate) async fn txt2img(app: &tauri::AppHandle, gpu_backend: &str, req: &LocalSdRequest) -> Result<String, String> {
+    let base = ensure(app, ServerConfig::from_request(gpu_backend, req)).await?;
+    let pid = server().lock().await.as_ref().and_then(|s| s.child.id());
+    let job = watchdog::register("sd", &req.prompt, pid);
+    set_activity(Some(job.activity()));
+    let t_start = Instant::now();
+    BUSY.store(true, Ordering::Relaxed);
+    update_status(app, |s| s.state = "busy");
+    let mut result = tokio::select! {
+        result = request_image(&base, req) => result,
+        _ = job.killed() => {
+            BUSY.store(false, Ordering::Relaxed);
+            set_activity(None);
+            stop_sd_server(app.clone()).await;
+            println!("[SD server] KILLED by user after {:.1}s", t_start.elapsed().as_secs_f32());
+            return Err("sd-server was stopped (no output — it appeared to hang). The next generation restarts it.".into());
+        }
+    };
+    BUSY.store(false, Ordering::Relaxed);
+    set_activity(None);
+    if let Err(e) = &result {
+        if let Some(s) = server().lock().await.as_ref() {
+            result = Err(format!("{}\n\nServer output:\n{}", e, log_tail(&s.log)));
+        }
+    }
+    update_status(app, |s| {
+        if s.state == "busy" { s.state = "ready"; }
+        if let Err(e) = &result { s.last_error = Some(e.clone()); }
+    });
+    if result.is_ok() {
+        println!("[SD server] SUCCESS — elapsed {:.1}s (model already loaded)", t_start.elapsed().as_secs_f32());
+    }
+    result
+}
+
+/// Kills the server on app exit; kill_on_drop does the rest.
+pub fn shutdown() {
+    EPOCH.fetch_add(1, Ordering::Relaxed);
+    if let Ok(mut guard) = server().try_lock() {
+        guard.take();
+    }
+}
+
+// ── Tauri commands ─────────────────────────────────────────────────────────
+
+#[tauri::command]
+pub async fn start_sd_server(app_handle: tauri::AppHandle, config: ServerConfig) -> Result<ServerStatus, AppError> {
+    let mut config = config;
+    if config.gpu_backend.is_empty() {
+        config.gpu_backend = "cpu".into();
+    }
+    config.gpu_backend = config.gpu_backend.to_lowercase();
+    ensure(&app_handle, config).await?;
+    Ok(get_sd_server_status())
+}
+
+#[tauri::command]
+pub async fn stop_sd_server(app_handle: tauri::AppHandle) -> ServerStatus {
+    EPOCH.fetch_add(1, Ordering::Relaxed);
+    if let Some(mut s) = server().lock().await.take() {
+        let _ = s.child.kill().await;
+        println!("[SD server] stopped");
+    }
+    RESTARTS.store(0, Ordering::Relaxed);
+    set_status(&app_handle, ServerStatus::stopped());
+    ServerStatus::stopped()
+}
+
+#[tauri::command]
+pub fn get_sd_server_status() -> ServerStatus {
+    STATUS.lock().map(|s| s.clone()).unwrap_or_else(|_| ServerStatus::stopped())
+}
+
+// ── Lifecycle ──────────────────────────────────────────────────────────────
+
+/// Returns the base URL of a running server for `config`, starting or
+/// restarting it as needed.
+async fn ensure(app: &tauri::AppHandle, config: ServerConfig) -> Result<String, String> {
+    let mut guard = server().lock().await;
+    if let Some(s) = guard.as_mut() {
+        if s.config == config && matches!(s.child.try_wait(), Ok(None)) {
+            return Ok(base_url(s.port));
+        }
+        println!("[SD server] configuration changed — restarting");
+        let _ = s.child.kill().await;
+        *guard = None;
+    }
+    let epoch = EPOCH.fetch_add(1, Ordering::Relaxed) + 1;
+    RESTARTS.store(0, Ordering::Relaxed);
+    let started = launch(app, &config).await;
+    let s = match started {
+        Ok(s) => s,
+        Err(e) => {
+            set_status(app, ServerStatus { state: "failed", last_error: Some(e.clone()), ..ServerStatus::stopped() });
+            return Err(e);
+        }
+    };
+    let url = base_url(s.port);
+    set_status(app, ready_status(&s));
+    *guard = Some(s);
+    spawn_monitor(app.clone(), epoch);
+    Ok(url)
+}
+
+async fn launch(app: &tauri::AppHandle, config: &ServerConfig) -> Result<Server, String> {
+    let bin = local_sd::get_sd_server_path_for(app, &config.gpu_backend)?;
+    if !bin.exists() {
+        return Err(format!(
+            "No sd-server for the {} backend. Delete and re-download the binary in \
+             Settings → Image Generation → Native SD (older downloads didn't keep the server), \
+             or turn off persistent mode.",
+            config.gpu_backend.to_uppercase()
+        ));
+    }
+    let data_dir = local_sd::get_sd_data_dir(app)?;
+    update_status(app, |s| {
+        s.state = "starting";
+        s.model_path = Some(config.model_path.clone());
+        s.gpu_backend = Some(config.gpu_backend.clone());
+    });
+
+    let mut last_error = String::new();
+    for style in [ArgStyle::Listen, ArgStyle::HostPort] {
+        let port = free_port()?;
+        let mut cmd = Command::new(&bin);
+        cmd.args(config.args(port, style))
+           .stdin(Stdio::null())
+           .stdout(Stdio::piped())
+           .stderr(Stdio::piped())
+           .kill_on_drop(true);
+        local_sd::apply_runtime_env(&mut cmd, &data_dir, &config.gpu_backend);
+        gpu_devices::apply_gpu_device(&mut cmd, &config.gpu_backend, config.gpu_device);
+        println!("[SD server] starting {} on port {} ({:?})", bin.display(), port, style);
+
+        let mut child = cmd.spawn().map_err(|e| format!("Failed to start sd-server: {}", e))?;
+        let log = Arc::new(Mutex::new(VecDeque::new()));
+        pipe_output(app.clone(), child.stderr.take(), log.clone());
+        pipe_output(app.clone(), child.stdout.take(), log.clone());
+
+        match wait_ready(&mut child, port).await {
+            Ok(()) => {
+                println!("[SD server] ready on port {}", port);
+                return Ok(Server { config: config.clone(), port, child, log, started: Instant::now() });
+            }
+            Err(e) => {
+                let _ = child.kill().await;
+                let tail = log_tail(&log);
+                last_error = format!("sd-server failed to start: {}\n\nProcess output:\n{}", e, tail);
+                if style == ArgStyle::Listen && rejected_args(&tail) {
+                    println!("[SD server] listen flags rejected — retrying with --host / --port");
+                    continue;
+                }
+                break;
+            }
+        }
+    }
+    Err(last_error)
+}
+
+/// Waits until the HTTP port answers (any status) or the process exits.
+async fn wait_ready(child: &mut Child, port: u16) -> Result<(), String> {
+    let client = http::client(http::Timeout::Api).map_err(|e| e.to_string())?;
+    let deadline = Instant::now() + LOAD_TIMEOUT;
+    while Instant::now() < deadline {
+        if let Ok(Some(status)) = child.try_wait() {
+            return Err(format!("exited with {}", status));
+        }
+        if client.get(base_url(port)).timeout(PROBE_TIMEOUT).send().await.is_ok() {
+            return Ok(());
+        }
+        tokio::time::sleep(Duration::from_millis(500)).await;
+    }
+    Err(format!("the model did not finish loading within {} minutes", LOAD_TIMEOUT.as_secs() / 60))
+}
+
+/// Restarts the server when the process dies or an idle server stops
+/// answering; gives up after MAX_RESTARTS within RESTART_WINDOW.
+fn spawn_monitor(app: tauri::AppHandle, epoch: u64) {
+    tokio::spawn(async move {
+        let client = match http::client(http::Timeout::Api) {
+            Ok(c) => c,
+            Err(_) => return,
+        };
+        let mut failures = 0u32;
+        let mut restarts: VecDeque<Instant> = VecDeque::new();
+        loop {
+            tokio::time::sleep(power::scaled(HEALTH_EVERY)).await;
+            if EPOCH.load(Ordering::Relaxed) != epoch {
+                return;
+            }
+            let (exited, port, config, uptime) = {
+                let mut guard = server().lock().await;
+                let Some(s) = guard.as_mut() else { return };
+                (s.child.try_wait().ok().flatten(), s.port, s.config.clone(), s.started.elapsed())
+            };
+            update_status(&app, |s| s.uptime_secs = uptime.as_secs());
+
+            let reason = match exited {
+                Some(status) => Some(format!("sd-server exited unexpectedly ({})", status)),
+                // A long generation keeps the server from answering probes
+                None if BUSY.load(Ordering::Relaxed) => { failures = 0; None }
+                None if client.get(base_url(port)).timeout(PROBE_TIMEOUT).send().await.is_ok() => { failures = 0; None }
+                None => {
+                    failures += 1;
+                    (failures >= HEALTH_FAILURES).then(|| "sd-server stopped responding".to_string())
+                }
+            };
+            let Some(reason) = reason else { continue };
+            println!("[SD server] {} — restarting", reason);
+
+            let mut guard = server().lock().await;
+            if EPOCH.load(Ordering::Relaxed) != epoch {
+                return;
+            }
+            if let Some(mut old) = guard.take() {
+                let _ = old.child.kill().await;
+            }
+            restarts.retain(|t| t.elapsed() < RESTART_WINDOW);
+            if restarts.len() >= MAX_RESTARTS {
+                let msg = format!("{} — gave up after {} restarts in {} minutes", reason, MAX_RESTARTS, RESTART_WINDOW.as_secs() / 60);
+                set_status(&app, ServerStatus { state: "failed", last_error: Some(msg), ..ServerStatus::stopped() });
+                return;
+            }
+            restarts.push_back(Instant::now());
+            RESTARTS.fetch_add(1, Ordering::Relaxed);
+            update_status(&app, |s| { s.state = "restarting"; s.last_error = Some(reason.clone()); });
+
+            match launch(&app, &config).await {
+                Ok(s) => {
+                    failures = 0;
+                    let mut status = ready_status(&s);
+                    status.last_error = Some(reason);
+                    set_status(&app, status);
+                    *guard = Some(s);
+                }
+                Err(e) => {
+                    set_status(&app, ServerStatus { state: "failed", last_error: Some(e), ..ServerStatus::stopped() });
+                    return;
+                }
+            }
+        }
+    });
+}
+
+// ── HTTP ───────────────────────────────────────────────────────────────────
+
+async fn request_image(base: &str, req: &LocalSdRequest) -> Result<String, String> {
+    // The shared client's timeout is far too short for a generation
+    let client = http::client(http::Timeout::Api).map_err(|e| e.to_string())?;
+    let (w, h) = (req.width.unwrap_or(512), req.height.unwrap_or(512));
+    let params = serde_json::json!({
+        "negative_prompt": req.negative_prompt.as_deref().unwrap_or(""),
+        "width":           w,
+        "height":          h,
+        "sample_steps":    req.steps.unwrap_or(20),
+        "cfg_scale":       req.cfg_scale.unwrap_or(7.0),
+        "seed":            req.seed.unwrap_or(-1),
+        "sample_method":   req.sampler.as_deref().unwrap_or("euler_a"),
+        "batch_count":     1,
+    });
+
+    // Current servers speak the OpenAI images API and take sd.cpp's own
+    // parameters inside the prompt; older ones only have /txt2img
+    let body = serde_json::json!({
+        "prompt":        format!("{}<sd_cpp_extra_args>{}</sd_cpp_extra_args>", req.prompt, params),
+        "n":             1,
+        "size":          format!("{}x{}", w, h),
+        "output_format": "png",
+    });
+    let send_err = |e: reqwest::Error| format!("sd-server request failed: {}", e);
+    let mut resp = client.post(format!("{}/v1/images/generations", base)).timeout(REQUEST_TIMEOUT).json(&body).send().await.map_err(send_err)?;
+    if resp.status() == reqwest::StatusCode::NOT_FOUND {
+        let mut legacy = params.clone();
+        legacy["prompt"] = serde_json::Value::String(req.prompt.clone());
+        resp = client.post(format!("{}/txt2img", base)).timeout(REQUEST_TIMEOUT).json(&legacy).send().await.map_err(send_err)?;
+    }
+    let status = resp.status();
+    if !status.is_success() {
+        let text = resp.text().await.unwrap_or_default();
+        return Err(format!("sd-server returned HTTP {}: {}", status, text.chars().take(300).collect::<String>()));
+    }
+    let json: serde_json::Value = resp.json().await.map_err(|e| format!("sd-server sent invalid JSON: {}", e))?;
+    extract_image(&json).ok_or_else(|| "sd-server returned no image".to_string())
+}
+
+// ── Helpers ────────────────────────────────────────────────────────────────
+
+/// First image of either response shape: `{data: [{b64_json}]}` (OpenAI
+/// API) or `[{data}]` (/txt2img), without any data-URL prefix.
+fn extract_image(json: &serde_json::Value) -> Option<String> {
+    let b64 = json["data"][0]["b64_json"].as_str()
+        .or_else(|| json[0]["data"].as_str())
+        .or_else(|| json["images"][0].as_str())?;
+    let b64 = b64.split_once("base64,").map_or(b64, |(_, d)| d);
+    (!b64.is_empty()).then(|| b64.to_string())
+}
+
+/// Output of a server that didn't understand --listen-ip / --listen-port.
+fn rejected_args(output: &str) -> bool {
+    let lower = output.to_lowercase();
+    (lower.contains("unknown") || lower.contains("invalid") || lower.contains("unrecognized"))
+        && (lower.contains("argument") || lower.contains("option") || lower.contains("listen"))
+}
+
+fn base_url(port: u16) -> String {
+    format!("http://127.0.0.1:{}", port)
+}
+
+fn free_port() -> Result<u16, String> {
+    std::net::TcpListener::bind("127.0.0.1:0")
+        .and_then(|l| l.local_addr())
+        .map(|a| a.port())
+        .map_err(|e| format!("No free local port for sd-server: {}", e))
+}
+
+fn ready_status(s: &Server) -> ServerStatus {
+    ServerStatus {
+        state:       "ready",
+        model_path:  Some(s.config.model_path.clone()),
+        gpu_backend: Some(s.config.gpu_backend.clone()),
+        port:        Some(s.port),
+        uptime_secs: 0,
+        restarts:    RESTARTS.load(Ordering::Relaxed),
+        last_error:  None,
+    }
+}
+
+fn set_status(app: &tauri::AppHandle, status: ServerStatus) {
+    update_status(app, |s| *s = status);
+}
+
+fn update_status(app: &tauri::AppHandle, f: impl FnOnce(&mut ServerStatus)) {
+    let snapshot = match STATUS.lock() {
+        Ok(mut s) => {
+            let before = s.clone();
+            f(&mut s);
+            s.restarts = RESTARTS.load(Ordering::Relaxed);
+            // Uptime ticks alone aren't worth an event
+            let changed = ServerStatus { uptime_secs: before.uptime_secs, ..s.clone() } != before;
+            changed.then(|| s.clone())
+        }
+        Err(_) => None,
+    };
+    if let Some(s) = snapshot {
+        let _ = app.emit_all("sd-server-status", s);
+    }
+}
+
+/// Drains a server pipe: keeps the last LOG_LINES for error reports and
+/// turns progress lines into `sd-progress` events. sd prints progress with
+/// \r, so lines split on both \r and \n.
+fn pipe_output<R: AsyncRead + Unpin + Send + 'static>(app: tauri::AppHandle, reader: Option<R>, log: Arc<Mutex<VecDeque<String>>>) {
+    let Some(mut reader) = reader else { return };
+    tokio::spawn(async move {
+        let mut raw = Vec::<u8>::with_capacity(256);
+        let mut tmp = [0u8; 256];
+        let mut progress = SdProgress::default();
+        loop {
+            let n = match reader.read(&mut tmp).await {
+                Ok(0) | Err(_) => break,
+                Ok(n) => n,
+            };
+            if let Some(activity) = ACTIVITY.lock().ok().and_then(|a| a.clone()) {
+                activity.touch();
+            }
+            for &b in &tmp[..n] {
+                if b != b'\n' && b != b'\r' {
+                    raw.push(b);
+                    continue;
+                }
+                if raw.is_empty() {
+                    continue;
+                }
+                let line = String::from_utf8_lossy(&raw).to_string();
+                raw.clear();
+                if BUSY.load(Ordering::Relaxed) {
+                    let _ = app.emit_all("sd-progress", progress.update(&line));
+                }
+                if let Ok(mut log) = log.lock() {
+                    if log.len() >= LOG_LINES {
+                        log.pop_front();
+                    }
+                    log.push_back(line);
+                }
+            }
+        }
+    });
+}
+
+fn set_activity(activity: Option<watchdog::Activity>) {
+    if let Ok(mut slot) = ACTIVITY.lock() {
+        *slot = activity;
+    }
+}
+
+fn log_tail(log: &Mutex<VecDeque<String>>) -> String {
+    let lines: Vec<String> = log.lock().map(|l| l.iter().cloned().collect()).unwrap_or_default();
+    if lines.is_empty() { "(no output captured)".into() } else { lines.join("\n") }
+}
+
+// ── Unit tests ─────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request() -> LocalSdRequest {
+        serde_json::from_value(serde_json::json!({ "model_path": "/m/a.safetensors", "prompt": "a cat" })).unwrap()
+    }
+
+    #[test]
+    fn test_args_per_style() {
+        let mut req = request();
+        req.vae_tiling = Some(true);
+        req.threads = Some(8);
+        let config = ServerConfig::from_request("vulkan", &req);
+        let args = config.args(4321, ArgStyle::Listen);
+        assert_eq!(&args[..6], ["-m", "/m/a.safetensors", "--listen-ip", "127.0.0.1", "--listen-port", "4321"]);
+        assert!(args.contains(&"--vae-tiling".to_string()) && args.contains(&"8".to_string()));
+        assert!(config.args(1, ArgStyle::HostPort).contains(&"--host".to_string()));
+    }
+
+    #[test]
+    fn test_config_change_detection() {
+        let req = request();
+        let a = ServerConfig::from_request("cpu", &req);
+        let mut other = request();
+        other.prompt = "a dog".into();
+        other.seed = Some(5);
+        // Per-image settings don't restart the server
+        assert_eq!(a, ServerConfig::from_request("cpu", &other));
+        other.vae_on_cpu = Some(true);
+        assert_ne!(a, ServerConfig::from_request("cpu", &other));
+        assert_ne!(a, ServerConfig::from_request("cuda", &req));
+        let mut second_gpu = request();
+        second_gpu.gpu_device = Some(1);
+        assert_ne!(a, ServerConfig::from_request("cpu", &second_gpu));
+    }
+
+    #[test]
+    fn test_supports_plain_txt2img_only() {
+        let mut req = request();
+        assert!(supports(&req));
+        req.extra_args = Some("  ".into());
+        assert!(supports(&req));
+        req.mask_base64 = Some("AAAA".into());
+        assert!(!supports(&req));
+    }
+
+    #[test]
+    fn test_extract_image_shapes() {
+        let openai = serde_json::json!({ "created": 1, "data": [{ "b64_json": "iVBORw0" }] });
+        assert_eq!(extract_image(&openai).as_deref(), Some("iVBORw0"));
+        let legacy = serde_json::json!([{ "width": 512, "height": 512, "channel": 3, "data": "data:image/png;base64,iVBOR" }]);
+        assert_eq!(extract_image(&legacy).as_deref(), Some("iVBOR"));
+        assert_eq!(extract_image(&serde_json::json!({ "data": [] })), None);
+    }
+
+    #[test]
+    fn test_rejected_args() {
+        assert!(rejected_args("error: unknown argument: --listen-ip"));
+        assert!(!rejected_args("CUDA error: out of memory"));
+    }
+}
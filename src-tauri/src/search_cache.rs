@@ -0,0 +1,183 @@
+// search_cache.rs — on-disk cache of web search responses
+//
+// Agent loops and retried messages send the same query again within minutes,
+// and every repeat costs a request against a rate-limited (or paid) search
+// API. web_search / search_and_fetch / image_search store each response in
+// <app-data>/search_cache/<sha256 of backend + query + options>.json and
+// reuse it until the request's TTL runs out (default one hour, 0 = bypass).
+// An entry found expired is deleted, and every store sweeps out entries
+// older than MAX_AGE_SECS, so the folder doesn't grow with every query ever
+// made. Caching is best-effort: unreadable or unwritable entries only mean
+// a fresh search.
+//
+// Tauri commands exposed:
+//   clear_search_cache → number of entries removed
+
+use crate::error::AppError;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+
+/// TTL when the request doesn't set one
+pub(crate) const DEFAULT_TTL_SECS: u64 = 60 * 60;
+/// Entries older than this are swept whatever TTL they were stored for
+const MAX_AGE_SECS: u64 = 7 * 24 * 60 * 60;
+
+#[derive(Serialize, Deserialize)]
+struct Entry {
+    /// Unix seconds
+    stored_at: u64,
+    value:     Value,
+}
+
+/// An entry's age without its value, for the sweep.
+#[derive(Deserialize)]
+struct Stamp {
+    stored_at: u64,
+}
+
+// ── Tauri command ──────────────────────────────────────────────────────────
+
+/// Removes every cached search response.
+#[tauri::command]
+pub fn clear_search_cache(app_handle: tauri::AppHandle) -> Result<usize, AppError> {
+    let dir = cache_dir(&app_handle)?;
+    Ok(clear_in(&dir))
+}
+
+// ── Cache access ───────────────────────────────────────────────────────────
+
+/// Cache key of a request. Queries differing only in case or spacing share
+/// an entry.
+pub(crate) fn key(backend: &str, query: &str, options: &[&str]) -> String {
+    let query = query.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase();
+    let mut hasher = Sha256::new();
+    for part in [backend, query.as_str()].into_iter().chain(options.iter().copied()) {
+        hasher.update(part.as_bytes());
+        hasher.update([0x1f]);
+    }
+    hasher.finalize().iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// The cached value for `key` if it is younger than `ttl_secs`.
+pub(crate) fn get<T: DeserializeOwned>(app: &tauri::AppHandle, key: &str, ttl_secs: u64) -> Option<T> {
+    if ttl_secs == 0 {
+        return None;
+    }
+    get_in(&cache_dir(app).ok()?, key, ttl_secs, now_secs())
+}
+
+pub(crate) fn put<T: Serialize>(app: &tauri::AppHandle, key: &str, value: &T) {
+    let result = cache_dir(app).and_then(|dir| put_in(&dir, key, value, now_secs()));
+    if let Err(e) = result {
+        log::debug!("search cache: not stored: {}", e);
+    }
+}
+
+fn cache_dir(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+    app.path_resolver()
+        .app_data_dir()
+        .ok_or_else(|| "Cannot resolve app data directory".to_string())
+        .map(|p| p.join("search_cache"))
+}
+
+fn get_in<T: DeserializeOwned>(dir: &Path, key: &str, ttl_secs: u64, now: u64) -> Option<T> {
+    let path = dir.join(format!("{}.json", key));
+    let entry: Entry = serde_json::from_str(&std::fs::read_to_string(&path).ok()?).ok()?;
+    if now.saturating_sub(entry.stored_at) >= ttl_secs {
+        let _ = std::fs::remove_file(&path);
+        return None;
+    }
+    serde_json::from_value(entry.value).ok()
+}
+
+fn put_in<T: Serialize>(dir: &Path, key: &str, value: &T, now: u64) -> Result<(), String> {
+    std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    let value = serde_json::to_value(value).map_err(|e| e.to_string())?;
+    let json = serde_json::to_string(&Entry { stored_at: now, value }).map_err(|e| e.to_string())?;
+    let path = dir.join(format!("{}.json", key));
+    let tmp = path.with_extension("json.tmp");
+    std::fs::write(&tmp, json).map_err(|e| e.to_string())?;
+    std::fs::rename(&tmp, &path).map_err(|e| e.to_string())?;
+    let swept = sweep_in(dir, now);
+    if swept > 0 {
+        log::debug!("search cache: swept {} old entries", swept);
+    }
+    Ok(())
+}
+
+/// Deletes entries stored MAX_AGE_SECS or more before `now`.
+fn sweep_in(dir: &Path, now: u64) -> usize {
+    let Ok(read) = std::fs::read_dir(dir) else { return 0 };
+    read.flatten()
+        .filter(|e| e.path().extension().is_some_and(|x| x == "json"))
+        .filter(|e| {
+            std::fs::read_to_string(e.path()).ok()
+                .and_then(|s| serde_json::from_str::<Stamp>(&s).ok())
+                .is_some_and(|stamp| now.saturating_sub(stamp.stored_at) >= MAX_AGE_SECS)
+        })
+        .filter(|e| std::fs::remove_file(e.path()).is_ok())
+        .count()
+}
+
+fn clear_in(dir: &Path) -> usize {
+    let Ok(read) = std::fs::read_dir(dir) else { return 0 };
+    read.flatten()
+        .filter(|e| e.path().extension().is_some_and(|x| x == "json" || x == "tmp"))
+        .filter(|e| std::fs::remove_file(e.path()).is_ok())
+        .count()
+}
+
+fn now_secs() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+// ── Unit tests ─────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_key_normalizes_query() {
+        assert_eq!(key("brave", "Rust  async\ttraits", &["5"]), key("brave", "rust async traits", &["5"]));
+        assert_ne!(key("brave", "rust", &["5"]), key("bing", "rust", &["5"]));
+        assert_ne!(key("brave", "rust", &["5"]), key("brave", "rust", &["8"]));
+        assert_eq!(key("brave", "rust", &[]).len(), 64);
+    }
+
+    #[test]
+    fn test_entries_expire_and_clear() {
+        let dir = tempfile::tempdir().unwrap();
+        let k = key("duckduckgo", "tauri", &[]);
+        put_in(dir.path(), &k, &vec!["a".to_string()], 1_000).unwrap();
+
+        assert_eq!(get_in::<Vec<String>>(dir.path(), &k, 60, 1_059), Some(vec!["a".to_string()]));
+        assert_eq!(get_in::<Vec<String>>(dir.path(), &key("duckduckgo", "other", &[]), 60, 1_000), None);
+        assert_eq!(clear_in(dir.path()), 1);
+
+        // An expired hit deletes the file
+        put_in(dir.path(), &k, &vec!["a".to_string()], 1_000).unwrap();
+        assert_eq!(get_in::<Vec<String>>(dir.path(), &k, 60, 1_060), None);
+        assert_eq!(get_in::<Vec<String>>(dir.path(), &k, 600, 1_060), None);
+        assert_eq!(clear_in(dir.path()), 0);
+    }
+
+    #[test]
+    fn test_put_sweeps_old_entries() {
+        let dir = tempfile::tempdir().unwrap();
+        let (old, fresh) = (key("brave", "old", &[]), key("brave", "fresh", &[]));
+        put_in(dir.path(), &old, &1, 1_000).unwrap();
+        put_in(dir.path(), &fresh, &2, 1_000 + MAX_AGE_SECS - 1).unwrap();
+        assert_eq!(get_in::<i32>(dir.path(), &old, u64::MAX, 0), Some(1));
+
+        put_in(dir.path(), &key("brave", "new", &[]), &3, 1_000 + MAX_AGE_SECS).unwrap();
+        assert_eq!(get_in::<i32>(dir.path(), &old, u64::MAX, 0), None);
+        assert_eq!(get_in::<i32>(dir.path(), &fresh, u64::MAX, 0), Some(2));
+    }
+}
@@ -0,0 +1,207 @@
+// session.rs — crash recovery checkpoints
+//
+// The in-flight state that would be lost on a crash — open conversation,
+// unsent draft, jobs still running — is written to <app-data>/session.json
+// every few seconds. A clean exit marks the file as such; if the previous
+// run did not get that far, its checkpoint is kept for the UI to offer a
+// restore.
+//
+// Tauri commands exposed:
+//   save_draft        (conversation_id?, draft)
+//   set_pending_jobs  (jobs) — frontend-owned jobs (image generation, …)
+//   get_recovery      → Option<SessionCheckpoint> from a crashed run
+//   dismiss_recovery
+
+use crate::error::AppError;
+use crate::{focus_session, power, recording, watchdog};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+const CHECKPOINT_FILE: &str = "session.json";
+const CHECKPOINT_SECS: u64  = 10;
+
+static STATE: Mutex<Option<SessionCheckpoint>> = Mutex::new(None);
+/// Checkpoint left behind by a run that did not exit cleanly.
+static RECOVERED: Mutex<Option<SessionCheckpoint>> = Mutex::new(None);
+static PATH: Mutex<Option<PathBuf>> = Mutex::new(None);
+
+// ── Types ──────────────────────────────────────────────────────────────────
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct PendingJob {
+    /// "image_gen" | "recording" | "focus_session" | …
+    pub kind:   String,
+    pub id:     String,
+    pub detail: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct SessionCheckpoint {
+    pub conversation_id: Option<String>,
+    pub draft:           String,
+    #[serde(default)]
+    pub pending_jobs:    Vec<PendingJob>,
+    /// Unix timestamp (seconds) of the last write
+    pub updated_at:      u64,
+    #[serde(default)]
+    pub clean_exit:      bool,
+}
+
+impl SessionCheckpoint {
+    /// Whether there is anything worth offering to restore.
+    fn has_content(&self) -> bool {
+        self.conversation_id.is_some() || !self.draft.trim().is_empty() || !self.pending_jobs.is_empty()
+    }
+}
+
+fn unix_now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+// ── Storage ────────────────────────────────────────────────────────────────
+
+fn load(path: &Path) -> Option<SessionCheckpoint> {
+    serde_json::from_str(&std::fs::read_to_string(path).ok()?).ok()
+}
+
+/// Write via a temp file + rename so a crash mid-write never leaves a
+/// truncated checkpoint.
+fn store(path: &Path, checkpoint: &SessionCheckpoint) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(checkpoint).map_err(|e| e.to_string())?;
+    let tmp = path.with_extension("json.tmp");
+    std::fs::write(&tmp, json).map_err(|e| e.to_string())?;
+    std::fs::rename(&tmp, path).map_err(|e| e.to_string())
+}
+
+/// Jobs owned by the backend, read from their modules at checkpoint time.
+fn backend_jobs() -> Vec<PendingJob> {
+    let mut jobs = Vec::new();
+    if let Some(id) = recording::active_recording_id() {
+        jobs.push(PendingJob { kind: "recording".into(), id, detail: None });
+    }
+    let focus = focus_session::get_focus_session_status();
+    if let (true, Some(id)) = (focus.running, focus.id) {
+        let detail = Some(format!("{} s remaining", focus.remaining_seconds));
+        jobs.push(PendingJob { kind: "focus_session".into(), id, detail });
+    }
+    jobs.extend(watchdog::list_jobs().into_iter().map(|j| PendingJob {
+        kind:   j.kind,
+        id:     j.id,
+        detail: Some(j.label),
+    }));
+    jobs
+}
+
+fn update(f: impl FnOnce(&mut SessionCheckpoint)) -> Result<(), String> {
+    let mut state = STATE.lock().map_err(|e| e.to_string())?;
+    f(state.get_or_insert_with(SessionCheckpoint::default));
+    Ok(())
+}
+
+fn checkpoint_now() {
+    let Some(path) = PATH.lock().ok().and_then(|p| p.clone()) else { return };
+    let Some(mut checkpoint) = STATE.lock().ok().and_then(|s| s.clone()) else { return };
+    checkpoint.pending_jobs.extend(backend_jobs());
+    checkpoint.updated_at = unix_now();
+    if let Err(e) = store(&path, &checkpoint) {
+        log::warn!("session: checkpoint failed: {}", e);
+    }
+}
+
+// ── Lifecycle ──────────────────────────────────────────────────────────────
+
+/// Pick up a checkpoint left by a crashed run, then keep writing new ones.
+pub fn spawn_checkpointer(app: &tauri::AppHandle) {
+    let Some(dir) = app.path_resolver().app_data_dir() else {
+        log::warn!("session: cannot resolve app data directory, recovery disabled");
+        return;
+    };
+    let _ = std::fs::create_dir_all(&dir);
+    let path = dir.join(CHECKPOINT_FILE);
+
+    if let Some(previous) = load(&path).filter(|c| !c.clean_exit && c.has_content()) {
+        log::info!("session: previous run did not exit cleanly, recovery available");
+        if let Ok(mut r) = RECOVERED.lock() {
+            *r = Some(previous);
+        }
+    }
+    if let Ok(mut s) = STATE.lock() {
+        *s = Some(SessionCheckpoint::default());
+    }
+    if let Ok(mut p) = PATH.lock() {
+        *p = Some(path);
+    }
+    checkpoint_now();
+
+    std::thread::spawn(|| loop {
+        std::thread::sleep(power::scaled(std::time::Duration::from_secs(CHECKPOINT_SECS)));
+        checkpoint_now();
+    });
+}
+
+/// Record that the app is shutting down normally.
+pub fn mark_clean_exit() {
+    let _ = update(|c| c.clean_exit = true);
+    checkpoint_now();
+}
+
+// ── Tauri commands ─────────────────────────────────────────────────────────
+
+#[tauri::command]
+pub fn save_draft(conversation_id: Option<String>, draft: String) -> Result<(), AppError> {
+    update(|c| {
+        c.conversation_id = conversation_id;
+        c.draft = draft;
+    }).map_err(AppError::from)
+}
+
+#[tauri::command]
+pub fn set_pending_jobs(jobs: Vec<PendingJob>) -> Result<(), AppError> {
+    update(|c| c.pending_jobs = jobs).map_err(AppError::from)
+}
+
+#[tauri::command]
+pub fn get_recovery() -> Option<SessionCheckpoint> {
+    RECOVERED.lock().ok().and_then(|r| r.clone())
+}
+
+#[tauri::command]
+pub fn dismiss_recovery() {
+    if let Ok(mut r) = RECOVERED.lock() {
+        *r = None;
+    }
+}
+
+// ── Unit tests ─────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_store_and_load_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(CHECKPOINT_FILE);
+        let checkpoint = SessionCheckpoint {
+            conversation_id: Some("conv-1".into()),
+            draft: "half-written question".into(),
+            pending_jobs: vec![PendingJob { kind: "image_gen".into(), id: "job-7".into(), detail: None }],
+            updated_at: 42,
+            clean_exit: false,
+        };
+        store(&path, &checkpoint).unwrap();
+        assert_eq!(load(&path), Some(checkpoint));
+        assert!(!path.with_extension("json.tmp").exists());
+    }
+
+    #[test]
+    fn test_has_content() {
+        assert!(!SessionCheckpoint::default().has_content());
+        assert!(!SessionCheckpoint { draft: "  \n".into(), ..Default::default() }.has_content());
+        assert!(SessionCheckpoint { draft: "hi".into(), ..Default::default() }.has_content());
+    }
+}
@@ -0,0 +1,235 @@
+// site_cookies.rs — per-domain cookies for the page fetcher
+//
+// Docs portals, wikis and issue trackers behind a login answer the plain
+// fetcher with a sign-in page. The user pastes the session cookie of such a
+// site once (the `Cookie` request header as copied from the browser's dev
+// tools); fetch_url_content then sends it with every request to that domain
+// and its subdomains, and folds `Set-Cookie` answers back in so refreshed
+// session tokens are kept. Only domains the user added are ever stored.
+//
+// Cookies live in `<app_data>/site_cookies.json`. They are credentials:
+// the list command returns domains only, never the values.
+//
+// Tauri commands exposed:
+//   list_site_cookies  → Vec<SiteCookieInfo>
+//   save_site_cookie(domain, cookie)
+//   delete_site_cookie(domain)
+
+use crate::error::AppError;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+const COOKIES_FILE: &str = "site_cookies.json";
+
+/// Serializes read-modify-write of the cookies file.
+static FILE_LOCK: Mutex<()> = Mutex::new(());
+
+// ── Types ──────────────────────────────────────────────────────────────────
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+struct SiteCookie {
+    /// Bare host, e.g. "wiki.example.com"; also matches subdomains
+    domain:     String,
+    /// `name=value; name2=value2`
+    cookie:     String,
+    #[serde(default)]
+    updated_at: u64,
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct SiteCookieInfo {
+    pub domain:     String,
+    /// Number of cookies stored for the domain
+    pub count:      usize,
+    pub updated_at: u64,
+}
+
+// ── Storage ────────────────────────────────────────────────────────────────
+
+fn cookies_path(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+    app.path_resolver()
+        .app_data_dir()
+        .ok_or_else(|| "Cannot resolve app data directory".to_string())
+        .map(|p| p.join(COOKIES_FILE))
+}
+
+fn load_all(path: &Path) -> Vec<SiteCookie> {
+    std::fs::read_to_string(path)
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
+fn save_all(path: &Path, cookies: &[SiteCookie]) -> Result<(), String> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    }
+    let json = serde_json::to_string_pretty(cookies).map_err(|e| e.to_string())?;
+    let tmp = path.with_extension("json.tmp");
+    std::fs::write(&tmp, json).map_err(|e| format!("Failed to write site cookies: {}", e))?;
+    std::fs::rename(&tmp, path).map_err(|e| e.to_string())
+}
+
+/// Cookie header for `url`: the entry of the most specific stored domain.
+pub(crate) fn cookie_for(app: &tauri::AppHandle, url: &str) -> Option<String> {
+    let host = host_of(url)?;
+    let all = load_all(&cookies_path(app).ok()?);
+    best_match(&all, &host).map(|c| c.cookie.clone()).filter(|c| !c.is_empty())
+}
+
+/// Fold a response's `Set-Cookie` values into the entry used for `url`.
+/// Does nothing for domains without an entry.
+pub(crate) fn remember(app: &tauri::AppHandle, url: &str, set_cookies: &[String]) {
+    if set_cookies.is_empty() {
+        return;
+    }
+    let (Some(host), Ok(path)) = (host_of(url), cookies_path(app)) else { return };
+    let Ok(_guard) = FILE_LOCK.lock() else { return };
+    let mut all = load_all(&path);
+    let Some(domain) = best_match(&all, &host).map(|c| c.domain.clone()) else { return };
+    let Some(entry) = all.iter_mut().find(|c| c.domain == domain) else { return };
+    let merged = merge_set_cookies(&entry.cookie, set_cookies);
+    if merged != entry.cookie {
+        entry.cookie = merged;
+        entry.updated_at = now_secs();
+        if let Err(e) = save_all(&path, &all) {
+            log::warn!("site cookies: {}", e);
+        }
+    }
+}
+
+// ── Tauri commands ─────────────────────────────────────────────────────────
+
+#[tauri::command]
+pub fn list_site_cookies(app_handle: tauri::AppHandle) -> Result<Vec<SiteCookieInfo>, AppError> {
+    let mut all: Vec<SiteCookieInfo> = load_all(&cookies_path(&app_handle)?)
+        .into_iter()
+        .map(|c| SiteCookieInfo { count: parse_pairs(&c.cookie).len(), domain: c.domain, updated_at: c.updated_at })
+        .collect();
+    all.sort_by(|a, b| a.domain.cmp(&b.domain));
+    Ok(all)
+}
+
+/// Store (or replace) the cookie header for `domain`.
+#[tauri::command]
+pub fn save_site_cookie(app_handle: tauri::AppHandle, domain: String, cookie: String) -> Result<(), AppError> {
+    let domain = normalize_domain(&domain).ok_or("Enter a domain such as wiki.example.com")?;
+    let cookie = cookie.trim().trim_start_matches("Cookie:").trim().to_string();
+    if parse_pairs(&cookie).is_empty() {
+        return Err("Cookie must look like name=value; name2=value2".into());
+    }
+    let path = cookies_path(&app_handle)?;
+    let _guard = FILE_LOCK.lock().map_err(|e| e.to_string())?;
+    let mut all = load_all(&path);
+    all.retain(|c| c.domain != domain);
+    all.push(SiteCookie { domain, cookie, updated_at: now_secs() });
+    save_all(&path, &all).map_err(AppError::from)
+}
+
+#[tauri::command]
+pub fn delete_site_cookie(app_handle: tauri::AppHandle, domain: String) -> Result<(), AppError> {
+    let domain = normalize_domain(&domain).unwrap_or_default();
+    let path = cookies_path(&app_handle)?;
+    let _guard = FILE_LOCK.lock().map_err(|e| e.to_string())?;
+    let mut all = load_all(&path);
+    let before = all.len();
+    all.retain(|c| c.domain != domain);
+    if all.len() == before {
+        return Err(format!("No cookies stored for {}", domain).into());
+    }
+    save_all(&path, &all).map_err(AppError::from)
+}
+
+// ── Helpers ────────────────────────────────────────────────────────────────
+
+/// "https://www.Wiki.example.com/x" → "wiki.example.com"
+fn normalize_domain(domain: &str) -> Option<String> {
+    let d = domain.trim().to_ascii_lowercase();
+    let d = d.split_once("://").map_or(d.as_str(), |(_, rest)| rest);
+    let d = d.split(['/', '?', '#', ':']).next().unwrap_or("");
+    let d = d.strip_prefix("www.").unwrap_or(d).trim_matches('.');
+    (!d.is_empty()).then(|| d.to_string())
+}
+
+fn host_of(url: &str) -> Option<String> {
+    url.split_once("://").and_then(|(_, rest)| normalize_domain(rest))
+}
+
+/// The entry for `host` itself or its closest parent domain.
+fn best_match<'a>(all: &'a [SiteCookie], host: &str) -> Option<&'a SiteCookie> {
+    all.iter()
+        .filter(|c| host == c.domain || host.ends_with(&format!(".{}", c.domain)))
+        .max_by_key(|c| c.domain.len())
+}
+
+/// `name=value` pairs of a Cookie header, in order.
+fn parse_pairs(cookie: &str) -> Vec<(String, String)> {
+    cookie
+        .split(';')
+        .filter_map(|p| {
+            let (name, value) = p.split_once('=')?;
+            let name = name.trim();
+            (!name.is_empty()).then(|| (name.to_string(), value.trim().to_string()))
+        })
+        .collect()
+}
+
+/// `existing` with each Set-Cookie applied: new names appended, known
+/// names updated, names set to expire (Max-Age=0 or an empty value) removed.
+fn merge_set_cookies(existing: &str, set_cookies: &[String]) -> String {
+    let mut pairs = parse_pairs(existing);
+    for header in set_cookies {
+        let mut parts = header.split(';');
+        let Some((name, value)) = parts.next().and_then(|p| p.split_once('=')) else { continue };
+        let (name, value) = (name.trim(), value.trim());
+        if name.is_empty() {
+            continue;
+        }
+        let expired = value.is_empty()
+            || parts.any(|attr| attr.trim().eq_ignore_ascii_case("max-age=0"));
+        pairs.retain(|(n, _)| n != name);
+        if !expired {
+            pairs.push((name.to_string(), value.to_string()));
+        }
+    }
+    pairs.iter().map(|(n, v)| format!("{}={}", n, v)).collect::<Vec<_>>().join("; ")
+}
+
+fn now_secs() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+// ── Unit tests ─────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(domain: &str, cookie: &str) -> SiteCookie {
+        SiteCookie { domain: domain.into(), cookie: cookie.into(), updated_at: 0 }
+    }
+
+    #[test]
+    fn test_best_match_prefers_most_specific_domain() {
+        let all = [entry("example.com", "a=1"), entry("wiki.example.com", "b=2")];
+        assert_eq!(best_match(&all, "wiki.example.com").map(|c| c.cookie.as_str()), Some("b=2"));
+        assert_eq!(best_match(&all, "docs.example.com").map(|c| c.cookie.as_str()), Some("a=1"));
+        assert_eq!(best_match(&all, "notexample.com"), None);
+        assert_eq!(host_of("https://WWW.Wiki.Example.com:8443/page?x=1").as_deref(), Some("wiki.example.com"));
+    }
+
+    #[test]
+    fn test_merge_set_cookies() {
+        let merged = merge_set_cookies("session=old; theme=dark; tracking=1", &[
+            "session=new; Path=/; HttpOnly".to_string(),
+            "csrf=abc; Secure".to_string(),
+            "tracking=; Max-Age=0".to_string(),
+            "=broken".to_string(),
+        ]);
+        assert_eq!(merged, "theme=dark; session=new; csrf=abc");
+    }
+}
@@ -0,0 +1,155 @@
+// startup.rs — cold-start profiling
+//
+// The window appears once `setup` returns, so setup only does what the
+// first frame needs (cursor tracker, crash-recovery state, a jump-list
+// launch action). Hotkeys, pollers, the jump list and the audit settings
+// run on a background thread right after (see main.rs). Every phase is
+// timed from process start, and the frontend reports its first paint so
+// the whole path to a visible window can be inspected.
+//
+// Tauri commands exposed:
+//   get_startup_timings → StartupTimings
+//   mark_first_paint    — called once by the frontend after its first render
+
+use serde::Serialize;
+use std::sync::{Mutex, OnceLock};
+use std::time::Instant;
+
+/// Process start → first paint budget
+const TARGET_MS: u64 = 500;
+
+static START: OnceLock<Instant> = OnceLock::new();
+static PHASES: Mutex<Vec<StartupPhase>> = Mutex::new(Vec::new());
+static SETUP_DONE_MS: OnceLock<u64> = OnceLock::new();
+static FIRST_PAINT_MS: OnceLock<u64> = OnceLock::new();
+
+// ── Types ──────────────────────────────────────────────────────────────────
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct StartupPhase {
+    pub name:        &'static str,
+    /// Milliseconds from process start
+    pub start_ms:    u64,
+    pub duration_ms: u64,
+    /// Ran after setup, off the window's critical path
+    pub deferred:    bool,
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct StartupTimings {
+    pub phases:         Vec<StartupPhase>,
+    pub setup_done_ms:  Option<u64>,
+    pub first_paint_ms: Option<u64>,
+    pub target_ms:      u64,
+    /// None until the frontend has painted
+    pub within_target:  Option<bool>,
+    /// Blocking phases, slowest first — what to defer next
+    pub slowest:        Vec<&'static str>,
+}
+
+// ── Recording ──────────────────────────────────────────────────────────────
+
+/// Pin the reference instant; first thing in `main`.
+pub fn mark_process_start() {
+    START.get_or_init(Instant::now);
+}
+
+fn since_start() -> u64 {
+    START.get_or_init(Instant::now).elapsed().as_millis() as u64
+}
+
+fn record<T>(name: &'static str, deferred: bool, f: impl FnOnce() -> T) -> T {
+    let start_ms = since_start();
+    let started = Instant::now();
+    let out = f();
+    let phase = StartupPhase { name, start_ms, duration_ms: started.elapsed().as_millis() as u64, deferred };
+    log::debug!("startup: {} took {} ms", name, phase.duration_ms);
+    if let Ok(mut p) = PHASES.lock() {
+        p.push(phase);
+    }
+    out
+}
+
+/// Time a phase that blocks the window (runs inside setup).
+pub fn timed<T>(name: &'static str, f: impl FnOnce() -> T) -> T {
+    record(name, false, f)
+}
+
+/// Time a phase run on the deferred-init thread.
+pub fn deferred<T>(name: &'static str, f: impl FnOnce() -> T) -> T {
+    record(name, true, f)
+}
+
+pub fn mark_setup_done() {
+    let ms = since_start();
+    if SETUP_DONE_MS.set(ms).is_ok() {
+        log::info!("startup: setup done after {} ms", ms);
+    }
+}
+
+fn summarize(phases: Vec<StartupPhase>, setup_done_ms: Option<u64>, first_paint_ms: Option<u64>) -> StartupTimings {
+    let mut blocking: Vec<&StartupPhase> = phases.iter().filter(|p| !p.deferred).collect();
+    blocking.sort_by_key(|p| std::cmp::Reverse(p.duration_ms));
+    let slowest = blocking.iter().take(3).map(|p| p.name).collect();
+    StartupTimings {
+        setup_done_ms,
+        first_paint_ms,
+        target_ms: TARGET_MS,
+        within_target: first_paint_ms.map(|ms| ms <= TARGET_MS),
+        slowest,
+        phases,
+    }
+}
+
+// ── Tauri commands ─────────────────────────────────────────────────────────
+
+#[tauri::command]
+pub fn get_startup_timings() -> StartupTimings {
+    let phases = PHASES.lock().map(|p| p.clone()).unwrap_or_default();
+    summarize(phases, SETUP_DONE_MS.get().copied(), FIRST_PAINT_MS.get().copied())
+}
+
+#[tauri::command]
+pub fn mark_first_paint() {
+    let ms = since_start();
+    // Only the first call counts (StrictMode / hot reload call again)
+    if FIRST_PAINT_MS.set(ms).is_err() {
+        return;
+    }
+    if ms > TARGET_MS {
+        log::warn!("startup: window visible after {} ms (target {} ms)", ms, TARGET_MS);
+    } else {
+        log::info!("startup: window visible after {} ms", ms);
+    }
+}
+
+// ── Unit tests ─────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn phase(name: &'static str, duration_ms: u64, deferred: bool) -> StartupPhase {
+        StartupPhase { name, start_ms: 0, duration_ms, deferred }
+    }
+
+    #[test]
+    fn test_summarize_ranks_blocking_phases() {
+        let t = summarize(
+            vec![phase("session", 12, false), phase("hotkeys", 300, true), phase("tracker", 1, false), phase("macos", 40, false)],
+            Some(60),
+            Some(420),
+        );
+        assert_eq!(t.slowest, vec!["macos", "session", "tracker"]);
+        assert_eq!(t.within_target, Some(true));
+        assert_eq!(summarize(vec![], Some(60), Some(900)).within_target, Some(false));
+        assert_eq!(summarize(vec![], None, None).within_target, None);
+    }
+
+    #[test]
+    fn test_timed_records_phase() {
+        assert_eq!(timed("test_phase", || 7), 7);
+        let phases = PHASES.lock().unwrap();
+        assert!(phases.iter().any(|p| p.name == "test_phase" && !p.deferred));
+    }
+}
@@ -0,0 +1,211 @@
+// style_presets.rs — named looks for image generation
+//
+// A preset bundles the parts of a request that make a "style": words put
+// before and after the prompt, a negative prompt, and optionally sampler,
+// CFG scale and size. Presets live in `<app_data>/style_presets.json`;
+// generate_image (image_gen.rs) and run_local_sd (local_sd.rs) take a
+// `style` id and apply it the same way for every provider — settings a
+// provider has no parameter for (sampler on DALL-E, …) are skipped there.
+//
+// Tauri commands exposed:
+//   list_style_presets  → Vec<StylePreset>
+//   save_style_preset   → StylePreset (id assigned on first save)
+//   delete_style_preset
+
+use crate::error::AppError;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+const PRESETS_FILE: &str = "style_presets.json";
+
+/// Serializes read-modify-write of the presets file.
+static FILE_LOCK: Mutex<()> = Mutex::new(());
+
+// ── Types ──────────────────────────────────────────────────────────────────
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+pub struct StylePreset {
+    /// Empty on first save; assigned by save_style_preset
+    #[serde(default)]
+    pub id:              String,
+    pub name:            String,
+    #[serde(default)]
+    pub prompt_prefix:   String,
+    #[serde(default)]
+    pub prompt_suffix:   String,
+    /// Added to the request's own negative prompt
+    #[serde(default)]
+    pub negative_prompt: String,
+    /// stable-diffusion.cpp sampler name ("euler_a", "dpm++2m", …)
+    pub sampler:         Option<String>,
+    pub cfg_scale:       Option<f32>,
+    pub width:           Option<u32>,
+    pub height:          Option<u32>,
+    #[serde(default)]
+    pub updated_at:      u64,
+}
+
+impl StylePreset {
+    /// "prefix, prompt, suffix" without doubled or dangling commas.
+    pub(crate) fn prompt(&self, prompt: &str) -> String {
+        [self.prompt_prefix.as_str(), prompt, self.prompt_suffix.as_str()]
+            .iter()
+            .map(|p| p.trim().trim_matches(',').trim())
+            .filter(|p| !p.is_empty())
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    /// The request's negative prompt plus the preset's, unless it already has it.
+    pub(crate) fn negative(&self, negative: Option<&str>) -> Option<String> {
+        let own = negative.map(str::trim).filter(|n| !n.is_empty());
+        let preset = Some(self.negative_prompt.trim()).filter(|n| !n.is_empty());
+        match (own, preset) {
+            (Some(o), Some(p)) if o.to_lowercase().contains(&p.to_lowercase()) => Some(o.to_string()),
+            (Some(o), Some(p)) => Some(format!("{}, {}", o.trim_end_matches(','), p)),
+            (o, p) => o.or(p).map(String::from),
+        }
+    }
+}
+
+// ── Storage ────────────────────────────────────────────────────────────────
+
+fn presets_path(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+    app.path_resolver()
+        .app_data_dir()
+        .ok_or_else(|| "Cannot resolve app data directory".to_string())
+        .map(|p| p.join(PRESETS_FILE))
+}
+
+fn load_all(path: &Path) -> Vec<StylePreset> {
+    std::fs::read_to_string(path)
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
+fn save_all(path: &Path, presets: &[StylePreset]) -> Result<(), String> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    }
+    let json = serde_json::to_string_pretty(presets).map_err(|e| e.to_string())?;
+    let tmp = path.with_extension("json.tmp");
+    std::fs::write(&tmp, json).map_err(|e| format!("Failed to write style presets: {}", e))?;
+    std::fs::rename(&tmp, path).map_err(|e| e.to_string())
+}
+
+/// The preset a request names; None / "" = no style.
+pub(crate) fn resolve(app: &tauri::AppHandle, id: Option<&str>) -> Result<Option<StylePreset>, String> {
+    let Some(id) = id.filter(|i| !i.is_empty()) else { return Ok(None) };
+    load_all(&presets_path(app)?)
+        .into_iter()
+        .find(|p| p.id == id)
+        .map(Some)
+        .ok_or_else(|| format!("Style preset not found: {}", id))
+}
+
+// ── Tauri commands ─────────────────────────────────────────────────────────
+
+#[tauri::command]
+pub fn list_style_presets(app_handle: tauri::AppHandle) -> Result<Vec<StylePreset>, AppError> {
+    let mut all = load_all(&presets_path(&app_handle)?);
+    all.sort_by(|a, b| a.name.to_lowercase().cmp(&b.name.to_lowercase()));
+    Ok(all)
+}
+
+/// Create or replace a preset.
+#[tauri::command]
+pub fn save_style_preset(app_handle: tauri::AppHandle, preset: StylePreset) -> Result<StylePreset, AppError> {
+    let preset = validate(preset)?;
+    let path = presets_path(&app_handle)?;
+    let _guard = FILE_LOCK.lock().map_err(|e| e.to_string())?;
+    let mut all = load_all(&path);
+    all.retain(|p| p.id != preset.id);
+    all.push(preset.clone());
+    save_all(&path, &all)?;
+    Ok(preset)
+}
+
+#[tauri::command]
+pub fn delete_style_preset(app_handle: tauri::AppHandle, id: String) -> Result<(), AppError> {
+    let path = presets_path(&app_handle)?;
+    let _guard = FILE_LOCK.lock().map_err(|e| e.to_string())?;
+    let mut all = load_all(&path);
+    let before = all.len();
+    all.retain(|p| p.id != id);
+    if all.len() == before {
+        return Err(format!("Style preset not found: {}", id).into());
+    }
+    save_all(&path, &all).map_err(AppError::from)
+}
+
+// ── Validation ─────────────────────────────────────────────────────────────
+
+fn validate(mut preset: StylePreset) -> Result<StylePreset, String> {
+    preset.name = preset.name.trim().to_string();
+    if preset.name.is_empty() {
+        return Err("Style preset name is required".into());
+    }
+    if preset.cfg_scale.is_some_and(|c| !(0.0..=30.0).contains(&c)) {
+        return Err("CFG scale must be between 0 and 30".into());
+    }
+    for size in [preset.width, preset.height].into_iter().flatten() {
+        if !(64..=4096).contains(&size) || size % 8 != 0 {
+            return Err(format!("Size {} must be a multiple of 8 between 64 and 4096", size));
+        }
+    }
+    preset.sampler = preset.sampler.map(|s| s.trim().to_string()).filter(|s| !s.is_empty());
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default();
+    if preset.id.is_empty() {
+        preset.id = format!("style-{}", now.as_millis());
+    }
+    preset.updated_at = now.as_secs();
+    Ok(preset)
+}
+
+// ── Unit tests ─────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn preset() -> StylePreset {
+        StylePreset {
+            name:            "Watercolor".into(),
+            prompt_prefix:   "watercolor painting,".into(),
+            prompt_suffix:   "soft edges, paper texture".into(),
+            negative_prompt: "photo, 3d render".into(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_prompt_joins_without_stray_commas() {
+        let p = preset();
+        assert_eq!(p.prompt("a fox, "), "watercolor painting, a fox, soft edges, paper texture");
+        assert_eq!(StylePreset::default().prompt(" a fox "), "a fox");
+    }
+
+    #[test]
+    fn test_negative_merges_once() {
+        let p = preset();
+        assert_eq!(p.negative(None).as_deref(), Some("photo, 3d render"));
+        assert_eq!(p.negative(Some("blurry,")).as_deref(), Some("blurry, photo, 3d render"));
+        assert_eq!(p.negative(Some("blurry, Photo, 3D render")).as_deref(), Some("blurry, Photo, 3D render"));
+        assert_eq!(StylePreset::default().negative(Some("  ")), None);
+    }
+
+    #[test]
+    fn test_validate() {
+        let saved = validate(StylePreset { name: "  Noir ".into(), sampler: Some(" ".into()), ..preset() }).unwrap();
+        assert!(saved.id.starts_with("style-"));
+        assert_eq!(saved.name, "Noir");
+        assert_eq!(saved.sampler, None);
+        assert!(validate(StylePreset { name: " ".into(), ..preset() }).is_err());
+        assert!(validate(StylePreset { width: Some(500), ..preset() }).is_err());
+        assert!(validate(StylePreset { cfg_scale: Some(50.0), ..preset() }).is_err());
+    }
+}
@@ -0,0 +1,360 @@
+// suggestions.rs — idle-aware "want me to explain this?" hints
+//
+// When enabled, a background thread periodically captures the screen and,
+// once the picture has stopped changing (the user is reading, not typing),
+// OCRs it with a local Tesseract install and scores the text for error
+// dialogs / stack traces. A hit above the threshold emits a subtle
+// `suggestion-available` event; the frontend decides how to show it.
+//
+// Privacy:
+//   • off by default, never persisted — every app start needs an explicit opt-in
+//   • screenshots and OCR text stay in memory and are dropped after scoring;
+//     nothing is written to disk or sent over the network
+//   • screens that look sensitive (password prompts, payment / banking
+//     details, private browsing, 2FA codes) are skipped entirely and their
+//     hash is remembered so the same screen is not OCR'd again
+//   • the event carries only a one-line headline, never the screen text
+//
+// Tauri commands exposed:
+//   start_suggestions   → starts the watcher thread
+//   stop_suggestions    → stops it
+//   snooze_suggestions  → no hints for N minutes
+//   dismiss_suggestion  → never suggest this headline again (this session)
+
+use crate::error::AppError;
+use crate::power;
+use crate::query_prep;
+use crate::reverse_image::{dhash, hash_similarity};
+use crate::screen_capture;
+use base64::{engine::general_purpose, Engine};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::io::Write;
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+const DEFAULT_INTERVAL_SECS: u64 = 15;
+const MIN_INTERVAL_SECS: u64     = 5;
+const DEFAULT_SETTLE_TICKS: u32  = 2;
+const DEFAULT_MIN_GAP_SECS: u64  = 300;
+const DEFAULT_MAX_PER_HOUR: u32  = 4;
+const DEFAULT_MIN_SCORE: f32     = 0.6;
+/// Frames at least this similar count as "the screen did not change".
+const STABLE_SIMILARITY: f32     = 0.97;
+/// The same headline is not offered again within this window.
+const REPEAT_WINDOW: Duration    = Duration::from_secs(60 * 60);
+const HEADLINE_MAX_CHARS: usize  = 160;
+
+static WATCHER: Mutex<Option<Watcher>> = Mutex::new(None);
+/// Headlines the user dismissed; survives stop/start within one session.
+static DISMISSED: Mutex<Vec<String>> = Mutex::new(Vec::new());
+/// Unix seconds until which hints are snoozed.
+static SNOOZED_UNTIL: Mutex<u64> = Mutex::new(0);
+
+struct Watcher {
+    stop: Arc<AtomicBool>,
+}
+
+// ── Types ──────────────────────────────────────────────────────────────────
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct SuggestionConfig {
+    /// Seconds between captures (default 15, minimum 5)
+    pub interval_secs:  Option<u64>,
+    /// Consecutive unchanged captures required before OCR (default 2)
+    pub settle_ticks:   Option<u32>,
+    /// Minimum seconds between two hints (default 300)
+    pub min_gap_secs:   Option<u64>,
+    pub max_per_hour:   Option<u32>,
+    /// 0.0–1.0, hints below this are dropped (default 0.6)
+    pub min_score:      Option<f32>,
+    /// Path to the tesseract binary (default: found on PATH)
+    pub tesseract_path: Option<String>,
+    /// Tesseract language(s), e.g. "eng" or "eng+rus" (default "eng")
+    pub ocr_lang:       Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Suggestion {
+    /// Currently always "explain_error"
+    pub kind:     String,
+    /// One-line error headline, noise stripped
+    pub headline: String,
+    pub language: Option<String>,
+    pub score:    f32,
+}
+
+// ── Tauri commands ─────────────────────────────────────────────────────────
+
+#[tauri::command]
+pub fn start_suggestions(app_handle: tauri::AppHandle, config: SuggestionConfig) -> Result<(), AppError> {
+    let tesseract = config.tesseract_path.clone().unwrap_or_else(|| "tesseract".into());
+    Command::new(&tesseract)
+        .arg("--version")
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .map_err(|_| format!("Tesseract OCR not found ('{}'). Install it to enable suggestions.", tesseract))?;
+
+    let mut watcher = WATCHER.lock().map_err(|e| e.to_string())?;
+    if let Some(w) = watcher.take() {
+        w.stop.store(true, Ordering::Relaxed);
+    }
+    let stop = Arc::new(AtomicBool::new(false));
+    let flag = stop.clone();
+    std::thread::spawn(move || watch_loop(app_handle, config, flag));
+    *watcher = Some(Watcher { stop });
+    log::info!("suggestions: watcher started");
+    Ok(())
+}
+
+#[tauri::command]
+pub fn stop_suggestions() -> Result<(), AppError> {
+    if let Some(w) = WATCHER.lock().map_err(|e| e.to_string())?.take() {
+        w.stop.store(true, Ordering::Relaxed);
+        log::info!("suggestions: watcher stopped");
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub fn snooze_suggestions(minutes: u64) -> Result<(), AppError> {
+    *SNOOZED_UNTIL.lock().map_err(|e| e.to_string())? = unix_now() + minutes * 60;
+    Ok(())
+}
+
+#[tauri::command]
+pub fn dismiss_suggestion(headline: String) -> Result<(), AppError> {
+    let mut dismissed = DISMISSED.lock().map_err(|e| e.to_string())?;
+    if !dismissed.contains(&headline) {
+        dismissed.push(headline);
+    }
+    Ok(())
+}
+
+// ── Watcher loop ───────────────────────────────────────────────────────────
+
+fn watch_loop(app: tauri::AppHandle, config: SuggestionConfig, stop: Arc<AtomicBool>) {
+    use tauri::Manager;
+
+    let interval  = Duration::from_secs(config.interval_secs.unwrap_or(DEFAULT_INTERVAL_SECS).max(MIN_INTERVAL_SECS));
+    let settle    = config.settle_ticks.unwrap_or(DEFAULT_SETTLE_TICKS);
+    let min_score = config.min_score.unwrap_or(DEFAULT_MIN_SCORE);
+    let mut throttle = Throttle::new(
+        Duration::from_secs(config.min_gap_secs.unwrap_or(DEFAULT_MIN_GAP_SECS)),
+        config.max_per_hour.unwrap_or(DEFAULT_MAX_PER_HOUR),
+    );
+
+    let mut last_hash: Option<u64>     = None;
+    let mut stable_ticks               = 0u32;
+    let mut analyzed_hash: Option<u64> = None;
+    let mut sensitive: Vec<u64>        = Vec::new();
+
+    while !stop.load(Ordering::Relaxed) {
+        std::thread::sleep(interval);
+        if stop.load(Ordering::Relaxed) { break; }
+        if unix_now() < SNOOZED_UNTIL.lock().map(|s| *s).unwrap_or(0) { continue; }
+        // Capture + OCR every tick is the costliest background work; skip it on battery
+        if power::current().saving { continue; }
+
+        let Some((bytes, hash)) = capture_with_hash() else { continue };
+
+        // Idle gate: only look at screens that have stopped changing
+        let same = last_hash.map(|h| hash_similarity(h, hash) >= STABLE_SIMILARITY).unwrap_or(false);
+        stable_ticks = if same { stable_ticks + 1 } else { 0 };
+        last_hash = Some(hash);
+        if stable_ticks < settle { continue; }
+
+        // Don't OCR the same still screen twice, nor any screen seen as sensitive
+        let seen = |h: &u64| hash_similarity(*h, hash) >= STABLE_SIMILARITY;
+        if analyzed_hash.as_ref().is_some_and(seen) || sensitive.iter().any(seen) { continue; }
+        analyzed_hash = Some(hash);
+
+        let text = match ocr(&bytes, &config) {
+            Ok(t)  => t,
+            Err(e) => { log::warn!("suggestions: OCR failed: {}", e); continue; }
+        };
+        drop(bytes);
+
+        if is_sensitive(&text) {
+            sensitive.push(hash);
+            continue;
+        }
+        let Some(suggestion) = score_text(&text) else { continue };
+        drop(text);
+
+        if suggestion.score < min_score { continue; }
+        let dismissed = DISMISSED.lock().map(|d| d.contains(&suggestion.headline)).unwrap_or(false);
+        if dismissed || !throttle.allow(&suggestion.headline, Instant::now()) { continue; }
+
+        log::info!("suggestions: offering hint (score {:.2})", suggestion.score);
+        let _ = app.emit_all("suggestion-available", &suggestion);
+    }
+}
+
+fn capture_with_hash() -> Option<(Vec<u8>, u64)> {
+    let shot  = screen_capture::capture_frame().ok()?;
+    let bytes = general_purpose::STANDARD.decode(&shot.base64).ok()?;
+    let img   = image::load_from_memory(&bytes).ok()?;
+    Some((bytes, dhash(&img)))
+}
+
+/// Run Tesseract on PNG bytes via stdin/stdout — no temp files.
+fn ocr(png: &[u8], config: &SuggestionConfig) -> Result<String, String> {
+    let bin  = config.tesseract_path.as_deref().unwrap_or("tesseract");
+    let lang = config.ocr_lang.as_deref().unwrap_or("eng");
+    let mut child = Command::new(bin)
+        .args(["stdin", "stdout", "-l", lang, "--psm", "3"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| e.to_string())?;
+
+    child.stdin.take().ok_or("tesseract stdin unavailable")?
+        .write_all(png)
+        .map_err(|e| e.to_string())?;
+    let out = child.wait_with_output().map_err(|e| e.to_string())?;
+    if !out.status.success() {
+        return Err(format!("tesseract exited with {}", out.status));
+    }
+    Ok(String::from_utf8_lossy(&out.stdout).to_string())
+}
+
+// ── Scoring & gating ───────────────────────────────────────────────────────
+
+/// Screens that must never be analyzed.
+fn is_sensitive(text: &str) -> bool {
+    let re = Regex::new(concat!(
+        r"(?i)\b(password|passcode|passphrase|pin code|one-time code|verification code|",
+        r"2fa|authenticator|seed phrase|recovery phrase|private key|cvv|cvc|card number|",
+        r"iban|routing number|incognito|inprivate|private browsing)\b",
+    )).unwrap();
+    // 13–19 digit runs (optionally grouped) look like payment card numbers
+    let card_re = Regex::new(r"\b(?:\d[ -]?){13,19}\b").unwrap();
+    re.is_match(text) || card_re.is_match(text)
+}
+
+/// Score OCR text for "there is an error worth explaining on screen".
+/// Returns None when nothing error-like is present.
+fn score_text(text: &str) -> Option<Suggestion> {
+    let signature = query_prep::extract_error_signature(text);
+    let trace     = query_prep::looks_like_stack_trace(text);
+    if signature.is_none() && !trace {
+        return None;
+    }
+
+    let mut score = 0.0f32;
+    if trace { score += 0.5; }
+    if let Some(sig) = &signature {
+        score += 0.4;
+        if sig.code.is_some() { score += 0.1; }
+    }
+    let keyword_re = Regex::new(r"(?i)\b(error|exception|failed|fatal|traceback|panicked)\b").unwrap();
+    if text.lines().filter(|l| keyword_re.is_match(l)).count() >= 3 {
+        score += 0.1;
+    }
+
+    let headline = match &signature {
+        Some(sig) => {
+            let mut parts: Vec<&str> = Vec::new();
+            if let Some(code) = &sig.code { parts.push(code); }
+            if !sig.message.is_empty() { parts.push(&sig.message); }
+            parts.join(": ")
+        }
+        None => query_prep::stack_trace_key_phrases(text).error_line.unwrap_or_default(),
+    };
+    let headline: String = headline.chars().take(HEADLINE_MAX_CHARS).collect();
+    if headline.trim().is_empty() {
+        return None;
+    }
+
+    Some(Suggestion {
+        kind:     "explain_error".into(),
+        headline,
+        language: signature.and_then(|s| s.language).map(String::from),
+        score:    score.min(1.0),
+    })
+}
+
+/// Rate limiting: a minimum gap between hints, an hourly cap, and no repeat
+/// of the same headline within `REPEAT_WINDOW`.
+struct Throttle {
+    min_gap:      Duration,
+    max_per_hour: u32,
+    shown:        VecDeque<Instant>,
+    seen:         HashMap<String, Instant>,
+}
+
+impl Throttle {
+    fn new(min_gap: Duration, max_per_hour: u32) -> Self {
+        Self { min_gap, max_per_hour, shown: VecDeque::new(), seen: HashMap::new() }
+    }
+
+    fn allow(&mut self, key: &str, now: Instant) -> bool {
+        let hour = Duration::from_secs(60 * 60);
+        while self.shown.front().is_some_and(|t| now.duration_since(*t) >= hour) {
+            self.shown.pop_front();
+        }
+        self.seen.retain(|_, t| now.duration_since(*t) < REPEAT_WINDOW);
+
+        if self.seen.contains_key(key) { return false; }
+        if self.shown.back().is_some_and(|t| now.duration_since(*t) < self.min_gap) { return false; }
+        if self.shown.len() as u32 >= self.max_per_hour { return false; }
+
+        self.shown.push_back(now);
+        self.seen.insert(key.to_string(), now);
+        true
+    }
+}
+
+fn unix_now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+// ── Unit tests ─────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PY_TRACE: &str = "Traceback (most recent call last):\n  \
+        File \"app.py\", line 10, in main\n    run()\n  \
+        File \"app.py\", line 6, in run\n    data['x']\nKeyError: 'x'";
+
+    #[test]
+    fn test_score_stack_trace_above_threshold() {
+        let s = score_text(PY_TRACE).unwrap();
+        assert!(s.score >= DEFAULT_MIN_SCORE);
+        assert!(s.headline.starts_with("KeyError"));
+    }
+
+    #[test]
+    fn test_score_plain_text_is_none() {
+        assert!(score_text("Meeting notes\nDiscuss roadmap for Q3").is_none());
+    }
+
+    #[test]
+    fn test_sensitive_screens_are_gated() {
+        assert!(is_sensitive("Enter your Password to continue"));
+        assert!(is_sensitive("Card 4111 1111 1111 1111 exp 12/29"));
+        assert!(!is_sensitive(PY_TRACE));
+    }
+
+    #[test]
+    fn test_throttle_gap_repeat_and_cap() {
+        let mut t = Throttle::new(Duration::from_secs(60), 2);
+        let t0 = Instant::now();
+        assert!(t.allow("a", t0));
+        assert!(!t.allow("b", t0 + Duration::from_secs(10)));   // gap
+        assert!(!t.allow("a", t0 + Duration::from_secs(120)));  // repeat
+        assert!(t.allow("b", t0 + Duration::from_secs(120)));
+        assert!(!t.allow("c", t0 + Duration::from_secs(300)));  // hourly cap
+    }
+}
@@ -0,0 +1,504 @@
+// translation.rs — one-click translation of answers
+//
+// Backends:
+//   deepl                   — DeepL API (free keys ending in ":fx" use the
+//                             api-free host); fenced code blocks are kept
+//                             out of the request and re-inserted verbatim
+//   any ai_bridge provider  — the configured LLM, told to leave code alone
+//
+// `translate` is the document pipeline on top of the same backends: it
+// picks the best of the configured providers, splits long text into
+// paragraph-aligned chunks, enforces a glossary (glossary.rs) and reports
+// chunks that fail the consistency checks.
+//
+// Tauri commands exposed:
+//   translate_text → TranslateResponse
+//   translate      → TranslationResult
+//
+// Events emitted:
+//   translation-progress → { chunk, total }
+
+use crate::ai_bridge::{self, AiRequest};
+use crate::error::AppError;
+use crate::glossary::{self, Glossary};
+use crate::{audit, http};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+/// Chunk size for document translation (~1.5k tokens of English)
+const CHUNK_CHARS: usize = 6_000;
+/// Tail of the previous translated chunk shown to the LLM for continuity
+const CONTINUITY_CHARS: usize = 600;
+
+/// Provider preference without a glossary: DeepL first, then the strongest
+/// LLMs. With a glossary the LLMs move ahead of DeepL, which cannot be told
+/// about terminology.
+const PROVIDER_RANK: &[&str] = &["deepl", "claude", "openai", "deepseek", "openrouter", "together", "local"];
+
+// ── Types ──────────────────────────────────────────────────────────────────
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TranslateRequest {
+    pub text:        String,
+    /// Language name or ISO code ("German", "de", "pt-BR")
+    pub target_lang: String,
+    /// Auto-detected when None
+    pub source_lang: Option<String>,
+    /// "deepl" | "openai" | "claude" | "deepseek" | "openrouter" | "together" | "local"
+    pub provider:    String,
+    pub api_key:     String,
+    pub model:       Option<String>,
+    /// Local server URL (local provider only)
+    pub local_url:   Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TranslatorConfig {
+    /// "deepl" or any ai_bridge provider
+    pub provider:  String,
+    pub api_key:   String,
+    pub model:     Option<String>,
+    pub local_url: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct TranslationIssue {
+    /// 0-based chunk index
+    pub chunk:  usize,
+    /// "glossary" | "length" | "code_blocks"
+    pub kind:   String,
+    pub detail: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TranslationResult {
+    pub text:     String,
+    pub provider: String,
+    pub chunks:   usize,
+    /// Consistency-check failures that survived the retry
+    pub issues:   Vec<TranslationIssue>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TranslateResponse {
+    pub text:                 String,
+    /// Reported by DeepL only
+    pub detected_source_lang: Option<String>,
+    pub provider:             String,
+}
+
+// ── Tauri command ──────────────────────────────────────────────────────────
+
+#[tauri::command]
+pub async fn translate_text(req: TranslateRequest) -> Result<TranslateResponse, AppError> {
+    if req.text.trim().is_empty() {
+        return Ok(TranslateResponse { text: req.text, detected_source_lang: None, provider: req.provider });
+    }
+    if req.target_lang.trim().is_empty() {
+        return Err("Target language is required".into());
+    }
+
+    let (text, detected) = match req.provider.as_str() {
+        "deepl" => translate_deepl(&req).await?,
+        _       => (translate_llm(&req, "").await?, None),
+    };
+    log::info!("translate_text: {} chars → {} via {}", req.text.len(), req.target_lang, req.provider);
+    Ok(TranslateResponse { text, detected_source_lang: detected, provider: req.provider })
+}
+
+/// Translate a document of any length with the best of `providers`,
+/// enforcing the glossary `glossary_id` if given.
+#[tauri::command]
+pub async fn translate(
+    window:      tauri::Window,
+    app_handle:  tauri::AppHandle,
+    text:        String,
+    target_lang: String,
+    glossary_id: Option<String>,
+    source_lang: Option<String>,
+    providers:   Vec<TranslatorConfig>,
+) -> Result<TranslationResult, AppError> {
+    if target_lang.trim().is_empty() {
+        return Err("Target language is required".into());
+    }
+    let glossary = match glossary_id.as_deref().filter(|id| !id.is_empty()) {
+        Some(id) => Some(glossary::load(&app_handle, id)?),
+        None     => None,
+    };
+    let cfg = pick_provider(&providers, glossary.is_some())
+        .ok_or("No translation provider configured")?
+        .clone();
+
+    let chunks = chunk_text(&text, CHUNK_CHARS);
+    let mut out = String::with_capacity(text.len() * 5 / 4);
+    let mut issues = Vec::new();
+    let mut previous = String::new();
+
+    for (i, chunk) in chunks.iter().enumerate() {
+        let _ = window.emit("translation-progress", json!({ "chunk": i + 1, "total": chunks.len() }));
+        let body = chunk.trim();
+        if body.is_empty() {
+            out.push_str(chunk);
+            continue;
+        }
+        let req = TranslateRequest {
+            text:        body.to_string(),
+            target_lang: target_lang.clone(),
+            source_lang: source_lang.clone(),
+            provider:    cfg.provider.clone(),
+            api_key:     cfg.api_key.clone(),
+            model:       cfg.model.clone(),
+            local_url:   cfg.local_url.clone(),
+        };
+        let translated = translate_chunk(&req, glossary.as_ref(), &previous, i, &mut issues).await?;
+
+        // keep the chunk's surrounding whitespace so paragraphs join as in the source
+        let lead = &chunk[..chunk.len() - chunk.trim_start().len()];
+        let trail = &chunk[chunk.trim_end().len()..];
+        out.push_str(lead);
+        out.push_str(&translated);
+        out.push_str(trail);
+        previous = translated;
+    }
+
+    log::info!("translate: {} chars in {} chunks → {} via {} ({} issues)",
+        text.len(), chunks.len(), target_lang, cfg.provider, issues.len());
+    Ok(TranslationResult { text: out, provider: cfg.provider, chunks: chunks.len(), issues })
+}
+
+/// Translate one chunk and run the consistency checks. An LLM that misses
+/// glossary terms gets one corrective retry.
+async fn translate_chunk(
+    req:      &TranslateRequest,
+    glossary: Option<&Glossary>,
+    previous: &str,
+    index:    usize,
+    issues:   &mut Vec<TranslationIssue>,
+) -> Result<String, String> {
+    let terms = glossary.map(|g| g.terms_in(&req.text)).unwrap_or_default();
+
+    if req.provider == "deepl" {
+        let (text, _) = translate_deepl(req).await?;
+        issues.extend(check_chunk(index, &req.text, &text, &glossary::missing_terms(&terms, &text)));
+        return Ok(text);
+    }
+
+    let mut extra = String::new();
+    if !terms.is_empty() {
+        extra.push_str(&format!(
+            "\n\nUse exactly these translations for the following terms:\n{}",
+            glossary::prompt_block(&terms)
+        ));
+    }
+    if !previous.is_empty() {
+        let mut start = previous.len().saturating_sub(CONTINUITY_CHARS);
+        while !previous.is_char_boundary(start) { start += 1; }
+        extra.push_str(&format!(
+            "\n\nThe text continues a document. The previous part was translated as follows; \
+             keep terminology and tone consistent with it:\n\"\"\"\n{}\n\"\"\"",
+            &previous[start..]
+        ));
+    }
+
+    let mut text = translate_llm(req, &extra).await?;
+    let mut missing = glossary::missing_terms(&terms, &text);
+    if !missing.is_empty() {
+        log::info!("translate: chunk {} missed {} glossary terms, retrying", index, missing.len());
+        let retry = format!(
+            "{}\n\nA previous attempt ignored required terminology. Every one of these \
+             translations must appear in your output:\n{}",
+            extra, glossary::prompt_block(&missing)
+        );
+        text = translate_llm(req, &retry).await?;
+        missing = glossary::missing_terms(&terms, &text);
+    }
+    issues.extend(check_chunk(index, &req.text, &text, &missing));
+    Ok(text)
+}
+
+/// Best configured provider: ranked by PROVIDER_RANK, skipping entries
+/// without a key (local needs none).
+fn pick_provider(providers: &[TranslatorConfig], has_glossary: bool) -> Option<&TranslatorConfig> {
+    let usable = |c: &&TranslatorConfig| c.provider == "local" || !c.api_key.trim().is_empty();
+    let rank = |c: &TranslatorConfig| {
+        let r = PROVIDER_RANK.iter().position(|p| *p == c.provider).unwrap_or(PROVIDER_RANK.len());
+        if has_glossary && c.provider == "deepl" { PROVIDER_RANK.len() } else { r }
+    };
+    providers.iter().filter(usable).min_by_key(|c| rank(c))
+}
+
+/// Consistency checks for one translated chunk.
+fn check_chunk(
+    index:      usize,
+    source:     &str,
+    translated: &str,
+    missing:    &[&glossary::GlossaryTerm],
+) -> Vec<TranslationIssue> {
+    let issue = |kind: &str, detail: String| TranslationIssue { chunk: index, kind: kind.into(), detail };
+    let mut out = Vec::new();
+    for t in missing {
+        out.push(issue("glossary", format!("\"{}\" should be translated as \"{}\"", t.source, t.target)));
+    }
+    // Real translations stay within a few times the source length; anything
+    // outside suggests a truncated or padded answer.
+    let ratio = translated.chars().count() as f32 / source.chars().count().max(1) as f32;
+    if !(0.3..=3.0).contains(&ratio) {
+        out.push(issue("length", format!("translation is {:.1}× the source length", ratio)));
+    }
+    let fences = |s: &str| s.matches("```").count();
+    if fences(source) != fences(translated) {
+        out.push(issue("code_blocks", format!(
+            "{} code fences in the source, {} in the translation", fences(source), fences(translated)
+        )));
+    }
+    out
+}
+
+/// Split `text` into consecutive slices of at most ~`max` chars, breaking at
+/// paragraph boundaries outside code blocks, then at line ends, then between
+/// sentences. Concatenating the chunks gives back `text`.
+fn chunk_text(text: &str, max: usize) -> Vec<&str> {
+    let mut pieces = Vec::new();
+    for (code, seg) in split_code_blocks(text) {
+        if code {
+            pieces.push(seg);
+        } else {
+            split_at_all(seg, "\n\n", &mut pieces);
+        }
+    }
+
+    let mut chunks = Vec::new();
+    let mut start = 0;
+    let mut end = 0;
+    for piece in pieces.iter().flat_map(|p| split_long(p, max)) {
+        if end > start && end - start + piece.len() > max {
+            chunks.push(&text[start..end]);
+            start = end;
+        }
+        end += piece.len();
+    }
+    if end > start {
+        chunks.push(&text[start..end]);
+    }
+    chunks
+}
+
+/// Split after every occurrence of `sep`, keeping the separator.
+fn split_at_all<'a>(s: &'a str, sep: &str, out: &mut Vec<&'a str>) {
+    let mut rest = s;
+    while let Some(pos) = rest.find(sep) {
+        let (head, tail) = rest.split_at(pos + sep.len());
+        out.push(head);
+        rest = tail;
+    }
+    if !rest.is_empty() { out.push(rest); }
+}
+
+/// Break a piece longer than `max` at line ends, then sentence ends.
+fn split_long(piece: &str, max: usize) -> Vec<&str> {
+    if piece.len() <= max {
+        return vec![piece];
+    }
+    for sep in ["\n", ". "] {
+        let mut parts = Vec::new();
+        split_at_all(piece, sep, &mut parts);
+        if parts.len() > 1 {
+            return parts.into_iter().flat_map(|p| split_long(p, max)).collect();
+        }
+    }
+    vec![piece]
+}
+
+// ── LLM ────────────────────────────────────────────────────────────────────
+
+/// `extra` is appended to the system prompt (glossary, continuity context).
+async fn translate_llm(req: &TranslateRequest, extra: &str) -> Result<String, String> {
+    let source = req.source_lang.as_deref()
+        .map(|s| format!(" from {}", s))
+        .unwrap_or_default();
+    let system = format!(
+        "You are a translator. Translate the user's text{} into {}. Preserve Markdown \
+         formatting, line breaks, URLs, file paths and inline code. Do not translate the \
+         contents of code blocks. Output only the translation, with no preamble or notes.{}",
+        source, req.target_lang.trim(), extra
+    );
+    let ai_req = AiRequest {
+        api_key:       req.api_key.clone(),
+        prompt:        req.text.clone(),
+        system_prompt: Some(system),
+        image_base64:  None,
+        context_files: None,
+        model:         req.model.clone(),
+        // Translations run a little longer than the source in most languages
+        max_tokens:    Some((crate::conversation::estimate_tokens(&req.text) * 2).clamp(256, 8_192)),
+        allow_secrets: false,
+        profile:       None,
+    };
+    let resp = ai_bridge::complete(&req.provider, ai_req, req.local_url.as_deref()).await?;
+    let text = resp.text.trim();
+    if text.is_empty() {
+        return Err("Translation returned an empty response".into());
+    }
+    Ok(text.to_string())
+}
+
+// ── DeepL ──────────────────────────────────────────────────────────────────
+
+async fn translate_deepl(req: &TranslateRequest) -> Result<(String, Option<String>), String> {
+    if req.api_key.trim().is_empty() {
+        return Err("DeepL API key required".into());
+    }
+    let host = if req.api_key.trim().ends_with(":fx") { "api-free.deepl.com" } else { "api.deepl.com" };
+
+    let segments = split_code_blocks(&req.text);
+    let prose: Vec<&str> = segments.iter().filter(|(code, _)| !code).map(|(_, s)| *s).collect();
+
+    let mut body = json!({
+        "text":                prose,
+        "target_lang":         deepl_lang(&req.target_lang, true),
+        "preserve_formatting": true,
+    });
+    if let Some(src) = req.source_lang.as_deref().filter(|s| !s.trim().is_empty()) {
+        body["source_lang"] = json!(deepl_lang(src, false));
+    }
+
+    let client = http::client(http::Timeout::Api).map_err(|e| e.to_string())?;
+    let request = client.post(format!("https://{}/v2/translate", host))
+        .header("Authorization", format!("DeepL-Auth-Key {}", req.api_key.trim()))
+        .json(&body);
+    let (status, text) = audit::send("deepl", request).await
+        .map_err(|e| format!("DeepL request failed: {}", e))?;
+    let json: Value = serde_json::from_str(&text).unwrap_or_default();
+    if !status.is_success() {
+        return Err(format!("DeepL {}: {}", status, json["message"].as_str().unwrap_or("unknown error")));
+    }
+
+    let translations = json["translations"].as_array().cloned().unwrap_or_default();
+    if translations.len() != prose.len() {
+        return Err("DeepL returned an unexpected number of translations".into());
+    }
+    let detected = translations.first()
+        .and_then(|t| t["detected_source_language"].as_str())
+        .map(String::from);
+
+    let mut translated = translations.iter().map(|t| t["text"].as_str().unwrap_or(""));
+    let text = segments.iter()
+        .map(|(code, s)| if *code { *s } else { translated.next().unwrap_or(*s) })
+        .collect();
+    Ok((text, detected))
+}
+
+/// DeepL language code. English and Portuguese need a variant as target.
+fn deepl_lang(lang: &str, target: bool) -> String {
+    const NAMES: &[(&str, &str)] = &[
+        ("english", "EN"), ("german", "DE"), ("french", "FR"), ("spanish", "ES"),
+        ("italian", "IT"), ("portuguese", "PT"), ("russian", "RU"), ("ukrainian", "UK"),
+        ("polish", "PL"), ("dutch", "NL"), ("japanese", "JA"), ("chinese", "ZH"),
+        ("korean", "KO"), ("turkish", "TR"),
+    ];
+    let lower = lang.trim().to_lowercase();
+    let code = NAMES.iter()
+        .find(|(name, _)| *name == lower)
+        .map(|(_, c)| c.to_string())
+        .unwrap_or_else(|| lower.replace('_', "-").to_uppercase());
+
+    if !target {
+        // source languages take the bare code
+        return code.split('-').next().unwrap_or(&code).to_string();
+    }
+    match code.as_str() {
+        "EN" => "EN-US".into(),
+        "PT" => "PT-BR".into(),
+        _    => code,
+    }
+}
+
+/// Split Markdown into (is_code, text) runs; fenced blocks include their fences.
+fn split_code_blocks(text: &str) -> Vec<(bool, &str)> {
+    let mut out = Vec::new();
+    let mut rest = text;
+    while let Some(open) = rest.find("```") {
+        let after_open = open + 3;
+        let Some(close_rel) = rest[after_open..].find("```") else { break };
+        let mut close = after_open + close_rel + 3;
+        // keep the newline after the closing fence with the code block
+        if rest[close..].starts_with('\n') { close += 1; }
+        if open > 0 { out.push((false, &rest[..open])); }
+        out.push((true, &rest[open..close]));
+        rest = &rest[close..];
+    }
+    if !rest.is_empty() { out.push((false, rest)); }
+    out
+}
+
+// ── Unit tests ─────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_code_blocks() {
+        let text = "Run this:\n```sh\ncargo build\n```\nThen check the output.";
+        let segs = split_code_blocks(text);
+        assert_eq!(segs, vec![
+            (false, "Run this:\n"),
+            (true,  "```sh\ncargo build\n```\n"),
+            (false, "Then check the output."),
+        ]);
+        assert_eq!(segs.iter().map(|(_, s)| *s).collect::<String>(), text);
+    }
+
+    #[test]
+    fn test_unclosed_fence_is_prose() {
+        assert_eq!(split_code_blocks("a ``` b"), vec![(false, "a ``` b")]);
+    }
+
+    fn cfg(provider: &str, key: &str) -> TranslatorConfig {
+        TranslatorConfig { provider: provider.into(), api_key: key.into(), model: None, local_url: None }
+    }
+
+    #[test]
+    fn test_pick_provider() {
+        let all = vec![cfg("openai", "k"), cfg("deepl", "k:fx"), cfg("claude", ""), cfg("local", "")];
+        assert_eq!(pick_provider(&all, false).unwrap().provider, "deepl");
+        assert_eq!(pick_provider(&all, true).unwrap().provider, "openai");
+        assert_eq!(pick_provider(&[cfg("deepl", "k")], true).unwrap().provider, "deepl");
+        assert!(pick_provider(&[cfg("claude", " ")], false).is_none());
+    }
+
+    #[test]
+    fn test_chunk_text_roundtrips_and_respects_limit() {
+        let para = "Lorem ipsum dolor sit amet. ".repeat(10);
+        let text = format!("{p}\n\n```rust\nfn main() {{}}\n\n// x\n```\n{p}\n\n{p}", p = para.trim());
+        let chunks = chunk_text(&text, 400);
+        assert_eq!(chunks.concat(), text);
+        assert!(chunks.len() > 1);
+        // the code block, blank line included, stays in one chunk
+        assert!(chunks.iter().any(|c| c.contains("```rust\nfn main() {}\n\n// x\n```")));
+
+        let long = "Sentence number one. ".repeat(100);
+        let chunks = chunk_text(&long, 300);
+        assert_eq!(chunks.concat(), long);
+        assert!(chunks.iter().all(|c| c.len() <= 300));
+    }
+
+    #[test]
+    fn test_check_chunk() {
+        let term = glossary::GlossaryTerm { source: "merge".into(), target: "zusammenführen".into(), case_sensitive: false };
+        let issues = check_chunk(2, "Please merge ```x```", "Bitte mergen", &[&term]);
+        let kinds: Vec<&str> = issues.iter().map(|i| i.kind.as_str()).collect();
+        assert_eq!(kinds, ["glossary", "code_blocks"]);
+        assert!(issues.iter().all(|i| i.chunk == 2));
+        assert!(check_chunk(0, "Hello world", "Hallo Welt", &[]).is_empty());
+    }
+
+    #[test]
+    fn test_deepl_lang() {
+        assert_eq!(deepl_lang("en", true), "EN-US");
+        assert_eq!(deepl_lang("pt_br", true), "PT-BR");
+        assert_eq!(deepl_lang("de", true), "DE");
+        assert_eq!(deepl_lang("en-GB", false), "EN");
+        assert_eq!(deepl_lang("Russian", true), "RU");
+    }
+}
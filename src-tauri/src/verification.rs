@@ -0,0 +1,242 @@
+// verification.rs — optional second pass that critiques an answer
+//
+// A verifier model (usually a cheaper one than the answering model) reads
+// the question, the context the answer was given and the answer itself, and
+// lists claims the context does not support. The result travels with the
+// response as `AiResponse.verification`; streamed answers call
+// verify_answer after "ai-stream-done".
+//
+// Tauri commands exposed:
+//   verify_answer             → Verification
+//   analyze_with_verification → AiResponse (with `verification` filled in)
+
+use crate::ai_bridge::{self, AiRequest, AiResponse};
+use crate::error::AppError;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Context handed to the verifier is capped so a large RAG payload does not
+/// make the check cost more than the answer.
+const MAX_CONTEXT_CHARS: usize = 24_000;
+
+const VERIFIER_INSTRUCTIONS: &str = "You are a fact-checker. You are given a question, \
+the context that was available to answer it, and an answer. Check every factual claim in \
+the answer against the context. A claim is unsupported if the context does not state or \
+directly imply it, or contradicts it. General knowledge that needs no source is fine. \
+Reply with JSON only, no prose, in this shape:\n\
+{\"verdict\": \"supported\" | \"partially_supported\" | \"unsupported\", \
+\"issues\": [{\"claim\": \"<quoted claim>\", \"problem\": \"<why it is unsupported>\", \
+\"severity\": \"low\" | \"medium\" | \"high\"}]}\n\
+Use an empty issues list when everything is supported.";
+
+// ── Types ──────────────────────────────────────────────────────────────────
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct VerifierConfig {
+    /// "openai" | "claude" | "deepseek" | "openrouter" | "together" | "local"
+    pub provider:  String,
+    pub api_key:   String,
+    pub model:     Option<String>,
+    /// Local server URL (local provider only)
+    pub local_url: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct VerifyRequest {
+    pub question:      String,
+    pub answer:        String,
+    pub context_files: Option<Vec<String>>,
+    pub verifier:      VerifierConfig,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct VerificationIssue {
+    /// The claim as quoted by the verifier
+    pub claim:    String,
+    pub problem:  String,
+    /// "low" | "medium" | "high"
+    pub severity: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Verification {
+    /// "supported" | "partially_supported" | "unsupported" | "unknown"
+    pub verdict: String,
+    pub issues:  Vec<VerificationIssue>,
+    /// Model that ran the check
+    pub model:   String,
+    /// Set when the check itself failed; the answer is still returned
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error:   Option<String>,
+}
+
+// ── Tauri commands ─────────────────────────────────────────────────────────
+
+#[tauri::command]
+pub async fn verify_answer(req: VerifyRequest) -> Result<Verification, AppError> {
+    if req.answer.trim().is_empty() {
+        return Err("Nothing to verify".into());
+    }
+    verify(&req.question, &req.answer, req.context_files.as_deref(), &req.verifier).await.map_err(AppError::from)
+}
+
+/// Answer with `provider`, then (if a verifier is configured) check the
+/// answer. A failed check never fails the answer — it comes back as
+/// verdict "unknown" with `error` set.
+#[tauri::command]
+pub async fn analyze_with_verification(
+    provider:  String,
+    request:   AiRequest,
+    local_url: Option<String>,
+    verifier:  Option<VerifierConfig>,
+) -> Result<AiResponse, AppError> {
+    let question = request.prompt.clone();
+    let context = request.context_files.clone();
+    let mut resp = ai_bridge::complete(&provider, request, local_url.as_deref()).await?;
+
+    if let Some(verifier) = verifier.filter(|_| !resp.text.trim().is_empty()) {
+        let verification = match verify(&question, &resp.text, context.as_deref(), &verifier).await {
+            Ok(v)  => v,
+            Err(e) => {
+                log::warn!("verification failed: {}", e);
+                Verification {
+                    verdict: "unknown".into(),
+                    issues:  Vec::new(),
+                    model:   verifier.model.clone().unwrap_or_default(),
+                    error:   Some(e),
+                }
+            }
+        };
+        resp.verification = Some(verification);
+    }
+    Ok(resp)
+}
+
+// ── Verifier call ──────────────────────────────────────────────────────────
+
+async fn verify(
+    question: &str,
+    answer:   &str,
+    context:  Option<&[String]>,
+    verifier: &VerifierConfig,
+) -> Result<Verification, String> {
+    let ai_req = AiRequest {
+        api_key:       verifier.api_key.clone(),
+        prompt:        verifier_prompt(question, answer, context),
+        system_prompt: Some(VERIFIER_INSTRUCTIONS.to_string()),
+        image_base64:  None,
+        context_files: None,
+        model:         verifier.model.clone(),
+        max_tokens:    Some(1_024),
+        allow_secrets: false,
+        profile:       None,
+    };
+    let resp = ai_bridge::complete(&verifier.provider, ai_req, verifier.local_url.as_deref()).await?;
+    let verification = parse_verification(&resp.text, &resp.model);
+    log::info!("verification: {} ({} issues) via {}",
+        verification.verdict, verification.issues.len(), resp.model);
+    Ok(verification)
+}
+
+fn verifier_prompt(question: &str, answer: &str, context: Option<&[String]>) -> String {
+    let mut ctx = context.unwrap_or_default().join("\n\n");
+    if ctx.trim().is_empty() {
+        ctx = "(no context was provided — flag claims that would need a source)".into();
+    } else if ctx.len() > MAX_CONTEXT_CHARS {
+        let mut cut = MAX_CONTEXT_CHARS;
+        while !ctx.is_char_boundary(cut) { cut -= 1; }
+        ctx.truncate(cut);
+        ctx.push_str("\n…[context truncated]");
+    }
+    format!("## Question\n{}\n\n## Context\n{}\n\n## Answer\n{}", question.trim(), ctx, answer.trim())
+}
+
+/// Pull the verifier's JSON out of its reply. Models often wrap it in a code
+/// fence or add a sentence around it, so everything between the first `{`
+/// and the last `}` is tried; anything unparseable becomes "unknown".
+fn parse_verification(text: &str, model: &str) -> Verification {
+    let unknown = |error: &str| Verification {
+        verdict: "unknown".into(),
+        issues:  Vec::new(),
+        model:   model.to_string(),
+        error:   Some(error.to_string()),
+    };
+    let (Some(start), Some(end)) = (text.find('{'), text.rfind('}')) else {
+        return unknown("Verifier did not return JSON");
+    };
+    if end < start {
+        return unknown("Verifier did not return JSON");
+    }
+    let Ok(json) = serde_json::from_str::<Value>(&text[start..=end]) else {
+        return unknown("Verifier returned malformed JSON");
+    };
+
+    let issues: Vec<VerificationIssue> = json["issues"].as_array()
+        .map(|items| items.iter().filter_map(|i| {
+            let claim = i["claim"].as_str()?.trim();
+            if claim.is_empty() { return None; }
+            let severity = match i["severity"].as_str().map(str::to_lowercase).as_deref() {
+                Some(s @ ("low" | "medium" | "high")) => s.to_string(),
+                _                                     => "medium".into(),
+            };
+            Some(VerificationIssue {
+                claim:    claim.to_string(),
+                problem:  i["problem"].as_str().unwrap_or("").trim().to_string(),
+                severity,
+            })
+        }).collect())
+        .unwrap_or_default();
+
+    let verdict = match json["verdict"].as_str().map(|v| v.trim().to_lowercase()).as_deref() {
+        Some(v @ ("supported" | "partially_supported" | "unsupported")) => v.to_string(),
+        // an unrecognised verdict is inferred from the issue list
+        _ if issues.is_empty() => "supported".into(),
+        _                      => "partially_supported".into(),
+    };
+    Verification { verdict, issues, model: model.to_string(), error: None }
+}
+
+// ── Unit tests ─────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_fenced_json() {
+        let reply = "Here is my assessment:\n```json\n{\"verdict\": \"partially_supported\", \"issues\": [\
+            {\"claim\": \"The API was added in v2\", \"problem\": \"Context only mentions v3\", \"severity\": \"HIGH\"}]}\n```";
+        let v = parse_verification(reply, "gpt-4o-mini");
+        assert_eq!(v.verdict, "partially_supported");
+        assert_eq!(v.issues.len(), 1);
+        assert_eq!(v.issues[0].severity, "high");
+        assert_eq!(v.model, "gpt-4o-mini");
+        assert!(v.error.is_none());
+    }
+
+    #[test]
+    fn test_parse_infers_missing_verdict() {
+        let v = parse_verification(r#"{"issues": []}"#, "m");
+        assert_eq!(v.verdict, "supported");
+        let v = parse_verification(r#"{"issues": [{"claim": "x", "problem": "y"}]}"#, "m");
+        assert_eq!(v.verdict, "partially_supported");
+        assert_eq!(v.issues[0].severity, "medium");
+    }
+
+    #[test]
+    fn test_parse_garbage_is_unknown() {
+        let v = parse_verification("Looks fine to me.", "m");
+        assert_eq!(v.verdict, "unknown");
+        assert!(v.error.is_some());
+        assert_eq!(parse_verification("} nope {", "m").verdict, "unknown");
+    }
+
+    #[test]
+    fn test_prompt_truncates_large_context() {
+        let ctx = vec!["é".repeat(MAX_CONTEXT_CHARS)];
+        let prompt = verifier_prompt("q", "a", Some(&ctx));
+        assert!(prompt.contains("[context truncated]"));
+        assert!(prompt.len() < MAX_CONTEXT_CHARS + 200);
+        assert!(verifier_prompt("q", "a", None).contains("no context was provided"));
+    }
+}
@@ -0,0 +1,555 @@
+// video_gen.rs — short text-to-video clips
+//
+// Backends:
+//   native_sd — stable-diffusion.cpp's vid_gen mode (WAN 2.1 / 2.2): the same
+//               binary, GPU queue slot and `sd-progress` stream as run_local_sd.
+//               WAN ships as a bare diffusion model, so its VAE and umt5 text
+//               encoder are required; an init image plus clip_vision_path
+//               turns it into image-to-video.
+//   webui     — Automatic1111 / Forge with the AnimateDiff extension: txt2img
+//               with the AnimateDiff always-on script, polled for progress
+//               like generate_image's local_sd provider.
+//
+// sd.cpp writes an MJPG AVI and AnimateDiff answers with a GIF. GIF output
+// is built from the AVI's JPEG frames here; MP4 output is converted by
+// ffmpeg, which must be on PATH. Clips are saved to <app-data>/videos/.
+//
+// Tauri commands exposed:
+//   run_local_video(req) → VideoResult { path, format, frames, fps, seed }
+
+use crate::error::AppError;
+use crate::local_sd::{self, SdProgress, TempFiles};
+use crate::{disk, gen_metadata, gpu_devices, http, image_gen, image_queue, watchdog};
+use base64::{engine::general_purpose, Engine};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::path::{Path, PathBuf};
+use tokio::process::Command;
+
+/// WAN generates 4n+1 frames; 33 is about two seconds at its native 16 fps
+const WAN_DEFAULT_FRAMES: u32 = 33;
+const WAN_DEFAULT_FPS: u32 = 16;
+const ANIMATEDIFF_DEFAULT_FRAMES: u32 = 16;
+const ANIMATEDIFF_DEFAULT_FPS: u32 = 8;
+/// Longest clip either backend is asked for
+const MAX_FRAMES: u32 = 121;
+/// An AnimateDiff run takes minutes, far past the image-gen client timeout
+const WEBUI_VIDEO_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(20 * 60);
+
+// ── Types ──────────────────────────────────────────────────────────────────
+
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct VideoRequest {
+    /// "native_sd" (default) | "webui"
+    #[serde(default)]
+    pub backend:           Option<String>,
+    pub prompt:            String,
+    #[serde(default)]
+    pub negative_prompt:   Option<String>,
+    #[serde(default)]
+    pub width:             Option<u32>,
+    #[serde(default)]
+    pub height:            Option<u32>,
+    /// Clip length; rounded down to 4n+1 for WAN
+    #[serde(default)]
+    pub frames:            Option<u32>,
+    #[serde(default)]
+    pub fps:               Option<u32>,
+    #[serde(default)]
+    pub steps:             Option<u32>,
+    #[serde(default)]
+    pub cfg_scale:         Option<f32>,
+    /// None / negative = random
+    #[serde(default)]
+    pub seed:              Option<i64>,
+    #[serde(default)]
+    pub sampler:           Option<String>,
+    /// "gif" (default) | "mp4" (needs ffmpeg)
+    #[serde(default)]
+    pub format:            Option<String>,
+    /// Caller-chosen id for image-queue events and cancel_queued_image
+    #[serde(default)]
+    pub queue_id:          Option<String>,
+
+    // ── native_sd ──
+    /// WAN diffusion model (.safetensors / .gguf)
+    #[serde(default)]
+    pub model_path:        Option<String>,
+    /// WAN VAE (wan_2.1_vae / wan2.2_vae)
+    #[serde(default)]
+    pub vae_path:          Option<String>,
+    /// umt5-xxl text encoder
+    #[serde(default)]
+    pub t5xxl_path:        Option<String>,
+    /// CLIP vision encoder, for image-to-video with WAN 2.1 I2V models
+    #[serde(default)]
+    pub clip_vision_path:  Option<String>,
+    /// First frame for image-to-video (base64); resized to width×height
+    #[serde(default)]
+    pub init_image_base64: Option<String>,
+    /// Sampling flow shift (--flow-shift), default 3.0
+    #[serde(default)]
+    pub flow_shift:        Option<f32>,
+    #[serde(default)]
+    pub gpu_backend:       Option<String>,
+    #[serde(default)]
+    pub gpu_device:        Option<u32>,
+    #[serde(default)]
+    pub threads:           Option<u32>,
+    #[serde(default)]
+    pub vae_tiling:        Option<bool>,
+    #[serde(default)]
+    pub offload_to_cpu:    Option<bool>,
+
+    // ── webui ──
+    /// WebUI base URL (default http://127.0.0.1:7860)
+    #[serde(default)]
+    pub url:               Option<String>,
+    /// AnimateDiff motion module, e.g. "mm_sd15_v3.safetensors"
+    #[serde(default)]
+    pub motion_module:     Option<String>,
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct VideoResult {
+    /// Absolute path of the saved clip
+    pub path:   String,
+    /// "gif" | "mp4"
+    pub format: String,
+    pub frames: u32,
+    pub fps:    u32,
+    pub seed:   i64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum VideoFormat {
+    Gif,
+    Mp4,
+}
+
+impl VideoFormat {
+    fn parse(format: Option<&str>) -> Result<Self, String> {
+        match format.map(|f| f.trim().to_lowercase()).as_deref() {
+            None | Some("") | Some("gif") => Ok(VideoFormat::Gif),
+            Some("mp4") => Ok(VideoFormat::Mp4),
+            Some(other) => Err(format!("Unsupported video format \"{}\" (use gif or mp4)", other)),
+        }
+    }
+
+    fn ext(self) -> &'static str {
+        match self {
+            VideoFormat::Gif => "gif",
+            VideoFormat::Mp4 => "mp4",
+        }
+    }
+}
+
+// ── Tauri command ──────────────────────────────────────────────────────────
+
+/// Generates a short clip and saves it under <app-data>/videos/.
+/// Emits `sd-progress` while it runs (phase "saving" while the clip is encoded).
+#[tauri::command]
+pub async fn run_local_video(
+    window:     tauri::Window,
+    app_handle: tauri::AppHandle,
+    mut req:    VideoRequest,
+) -> Result<VideoResult, AppError> {
+    if req.prompt.trim().is_empty() {
+        return Err("A prompt is required".into());
+    }
+    let format = VideoFormat::parse(req.format.as_deref())?;
+    if !req.seed.is_some_and(|s| s >= 0) {
+        req.seed = Some(local_sd::random_seed());
+    }
+    let seed = req.seed.unwrap_or_default();
+    let webui = match req.backend.as_deref().unwrap_or("native_sd") {
+        "native_sd" => false,
+        "webui"     => true,
+        other => return Err(format!("Unknown video backend: {}", other).into()),
+    };
+
+    let (bytes, frames, fps) = if webui {
+        let _slot = image_queue::acquire(&app_handle, req.queue_id.clone(), "video", &req.prompt).await?;
+        webui_video(&app_handle, &req).await?
+    } else {
+        native_video(&window, &app_handle, &req, format).await?
+    };
+
+    let _ = window.emit("sd-progress", SdProgress {
+        phase: "saving".into(),
+        line:  format!("Encoding {} frames as {}…", frames, format.ext().to_uppercase()),
+        ..Default::default()
+    });
+    let path = save_clip(&app_handle, bytes, format).await?;
+    println!("[video] saved {} ({} frames @ {} fps)", path.display(), frames, fps);
+    Ok(VideoResult {
+        path:   path.to_string_lossy().to_string(),
+        format: format.ext().into(),
+        frames,
+        fps,
+        seed,
+    })
+}
+
+// ── stable-diffusion.cpp (WAN) ─────────────────────────────────────────────
+
+/// Runs sd in vid_gen mode; returns the clip in `format` (GIF already
+/// encoded, MP4 still as the AVI for save_clip to convert).
+async fn native_video(
+    window: &tauri::Window,
+    app:    &tauri::AppHandle,
+    req:    &VideoRequest,
+    format: VideoFormat,
+) -> Result<(Clip, u32, u32), String> {
+    let gpu_backend = req.gpu_backend.as_deref().unwrap_or("cpu").to_lowercase();
+    let bin = local_sd::get_sd_bin_path_for(app, &gpu_backend)?;
+    if !bin.exists() {
+        return Err(format!("stable-diffusion.cpp {} binary not installed.", gpu_backend.to_uppercase()));
+    }
+    let model = required_file(req.model_path.as_deref(), "WAN model (model_path)")?;
+    let vae = required_file(req.vae_path.as_deref(), "WAN VAE (vae_path)")?;
+    let t5xxl = required_file(req.t5xxl_path.as_deref(), "umt5-xxl text encoder (t5xxl_path)")?;
+
+    let frames = wan_frames(req.frames.unwrap_or(WAN_DEFAULT_FRAMES));
+    let fps = req.fps.unwrap_or(WAN_DEFAULT_FPS).clamp(1, 60);
+    let (w, h) = (req.width.unwrap_or(832), req.height.unwrap_or(480));
+    let _slot = image_queue::acquire(app, req.queue_id.clone(), "video", &req.prompt).await?;
+
+    let out_path = std::env::temp_dir().join(format!(
+        "sd_video_{}.avi",
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis()
+    ));
+    let mut cmd = Command::new(&bin);
+    cmd.arg("-M").arg("vid_gen")
+       .arg("--diffusion-model").arg(model)
+       .arg("--vae").arg(vae)
+       .arg("--t5xxl").arg(t5xxl)
+       .arg("-p").arg(&req.prompt)
+       .arg("-o").arg(&out_path)
+       .arg("-W").arg(w.to_string())
+       .arg("-H").arg(h.to_string())
+       .arg("--video-frames").arg(frames.to_string())
+       .arg("--fps").arg(fps.to_string())
+       .arg("--steps").arg(req.steps.unwrap_or(20).to_string())
+       .arg("--cfg-scale").arg(format!("{:.1}", req.cfg_scale.unwrap_or(6.0)))
+       .arg("--flow-shift").arg(format!("{:.1}", req.flow_shift.unwrap_or(3.0)))
+       .arg("--sampling-method").arg(req.sampler.as_deref().filter(|s| !s.is_empty()).unwrap_or("euler"));
+    if let Some(seed) = req.seed {
+        cmd.arg("-s").arg(seed.to_string());
+    }
+    if let Some(neg) = req.negative_prompt.as_deref().filter(|n| !n.trim().is_empty()) {
+        cmd.arg("-n").arg(neg);
+    }
+    let threads = req.threads.unwrap_or(0);
+    if threads > 0 {
+        cmd.arg("-t").arg(threads.to_string());
+    }
+    if req.vae_tiling.unwrap_or(false) {
+        cmd.arg("--vae-tiling");
+    }
+    if req.offload_to_cpu.unwrap_or(false) {
+        cmd.arg("--offload-to-cpu");
+    }
+    gpu_devices::apply_gpu_device(&mut cmd, &gpu_backend, req.gpu_device);
+
+    let mut temp_files = TempFiles(Vec::new());
+    if let Some(init) = &req.init_image_base64 {
+        let init_path = temp_files.add(local_sd::write_temp_image(init, "video_init", Some((w, h)), false)?);
+        cmd.arg("-i").arg(&init_path);
+        if let Some(clip_vision) = req.clip_vision_path.as_deref().filter(|p| !p.trim().is_empty()) {
+            cmd.arg("--clip_vision").arg(required_file(Some(clip_vision), "CLIP vision encoder (clip_vision_path)")?);
+        }
+        println!("[video] image-to-video from {}", init_path.display());
+    }
+
+    let label = format!("video {}f: {}", frames, req.prompt.chars().take(80).collect::<String>());
+    let avi = local_sd::run_sd_output(window, app, cmd, &bin, &gpu_backend, &out_path, &label).await?;
+    let clip = match format {
+        VideoFormat::Mp4 => Clip::Avi(avi),
+        VideoFormat::Gif => {
+            let gif = tokio::task::spawn_blocking(move || gif_from_avi(&avi, fps))
+                .await
+                .map_err(|e| e.to_string())??;
+            Clip::Gif(gif)
+        }
+    };
+    Ok((clip, frames, fps))
+}
+
+fn required_file(path: Option<&str>, what: &str) -> Result<String, String> {
+    let path = path.map(str::trim).filter(|p| !p.is_empty()).ok_or_else(|| format!("Video generation needs a {}", what))?;
+    if !Path::new(path).exists() {
+        return Err(format!("{} not found: {}", what, path));
+    }
+    Ok(path.to_string())
+}
+
+/// Largest 4n+1 frame count ≤ `n` (WAN's temporal VAE packs 4 frames per latent).
+fn wan_frames(n: u32) -> u32 {
+    let n = n.clamp(5, MAX_FRAMES);
+    n - (n - 1) % 4
+}
+
+// ── Automatic1111 / Forge AnimateDiff ──────────────────────────────────────
+
+async fn webui_video(app: &tauri::AppHandle, req: &VideoRequest) -> Result<(Clip, u32, u32), String> {
+    let base_url = image_gen::webui_base_url(req.url.as_deref());
+    let frames = req.frames.unwrap_or(ANIMATEDIFF_DEFAULT_FRAMES).clamp(8, MAX_FRAMES);
+    let fps = req.fps.unwrap_or(ANIMATEDIFF_DEFAULT_FPS).clamp(1, 60);
+    let body = animatediff_body(req, frames, fps);
+
+    let client = http::client(http::Timeout::ImageGen).map_err(|e| e.to_string())?;
+    let watcher = tokio::spawn(image_gen::watch_webui_progress(app.clone(), base_url.clone()));
+    let resp = client
+        .post(format!("{}/sdapi/v1/txt2img", base_url))
+        .timeout(WEBUI_VIDEO_TIMEOUT)
+        .json(&body)
+        .send()
+        .await;
+    watcher.abort();
+    let resp = resp.map_err(|e| {
+        format!(
+            "Cannot reach local SD server at {} — {}.\n\
+             Make sure Automatic1111/Forge is running with --api and the AnimateDiff extension.",
+            base_url, e
+        )
+    })?;
+    let status = resp.status();
+    let json: Value = resp.json().await.map_err(|e| e.to_string())?;
+    if !status.is_success() {
+        return Err(format!("Local SD {}: {}", status, json));
+    }
+    let gif = animatediff_gif(&json)?;
+    Ok((Clip::Gif(gif), frames, fps))
+}
+
+/// `/sdapi/v1/txt2img` payload with the AnimateDiff script enabled.
+fn animatediff_body(req: &VideoRequest, frames: u32, fps: u32) -> Value {
+    json!({
+        "prompt":          req.prompt,
+        "negative_prompt": req.negative_prompt.as_deref().unwrap_or("blurry, low quality, distorted, deformed"),
+        "steps":           req.steps.unwrap_or(20),
+        "cfg_scale":       req.cfg_scale.unwrap_or(7.0),
+        "width":           req.width.unwrap_or(512),
+        "height":          req.height.unwrap_or(512),
+        "sampler_name":    req.sampler.as_deref().filter(|s| !s.is_empty()).map_or("Euler a".into(), gen_metadata::a1111_sampler_name),
+        "seed":            req.seed.unwrap_or(-1),
+        "save_images":     false,
+        "send_images":     true,
+        "alwayson_scripts": {
+            "AnimateDiff": {
+                "args": [{
+                    "enable":       true,
+                    "model":        req.motion_module.as_deref().filter(|m| !m.trim().is_empty()).unwrap_or("mm_sd15_v3.safetensors"),
+                    "video_length": frames,
+                    "fps":          fps,
+                    "format":       ["GIF"],
+                }]
+            }
+        },
+    })
+}
+
+/// The GIF among the response images (AnimateDiff puts it first, followed
+/// by the individual frames on some versions).
+fn animatediff_gif(json: &Value) -> Result<Vec<u8>, String> {
+    let images = json["images"].as_array().ok_or("No images array in SD response")?;
+    images
+        .iter()
+        .filter_map(Value::as_str)
+        .filter_map(|raw| general_purpose::STANDARD.decode(raw.split_once("base64,").map_or(raw, |(_, d)| d).trim()).ok())
+        .find(|bytes| bytes.starts_with(b"GIF8"))
+        .ok_or_else(|| "The WebUI returned no GIF — is the AnimateDiff extension installed and enabled?".into())
+}
+
+// ── Encoding / storage ─────────────────────────────────────────────────────
+
+/// A finished clip before it is written to the videos folder.
+enum Clip {
+    Gif(Vec<u8>),
+    /// sd.cpp's MJPG AVI, still to be converted to MP4
+    Avi(Vec<u8>),
+}
+
+/// JPEG frames of an MJPG AVI: the `##dc` / `##db` chunks of its movi list.
+fn avi_frames(avi: &[u8]) -> Vec<&[u8]> {
+    let mut frames = Vec::new();
+    let Some(movi) = avi.windows(4).position(|w| w == b"movi") else { return frames };
+    let mut pos = movi + 4;
+    while pos + 8 <= avi.len() {
+        let id = &avi[pos..pos + 4];
+        let size = u32::from_le_bytes([avi[pos + 4], avi[pos + 5], avi[pos + 6], avi[pos + 7]]) as usize;
+        let start = pos + 8;
+        let Some(end) = start.checked_add(size).filter(|e| *e <= avi.len()) else { break };
+        match id {
+            b"idx1" => break,
+            // LIST rec: the chunks inside are walked like top-level ones
+            b"LIST" => {
+                pos = start + 4;
+                continue;
+            }
+            _ if id[2..] == *b"dc" || id[2..] == *b"db" => frames.push(&avi[start..end]),
+            _ => {}
+        }
+        pos = end + (size & 1);
+    }
+    frames
+}
+
+fn gif_from_avi(avi: &[u8], fps: u32) -> Result<Vec<u8>, String> {
+    let frames = avi_frames(avi)
+        .into_iter()
+        .map(|jpeg| image::load_from_memory(jpeg).map(|img| img.to_rgba8()))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Cannot decode video frame: {}", e))?;
+    if frames.is_empty() {
+        return Err("sd finished but the video has no frames.".into());
+    }
+    encode_gif(frames, fps)
+}
+
+fn encode_gif(frames: Vec<image::RgbaImage>, fps: u32) -> Result<Vec<u8>, String> {
+    use image::codecs::gif::{GifEncoder, Repeat};
+    let delay = image::Delay::from_numer_denom_ms(1000, fps.max(1));
+    let mut out = Vec::new();
+    {
+        let mut encoder = GifEncoder::new_with_speed(&mut out, 10);
+        encoder.set_repeat(Repeat::Infinite).map_err(|e| e.to_string())?;
+        for frame in frames {
+            encoder
+                .encode_frame(image::Frame::from_parts(frame, 0, 0, delay))
+                .map_err(|e| format!("GIF encoding failed: {}", e))?;
+        }
+    }
+    Ok(out)
+}
+
+fn videos_dir(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+    app.path_resolver()
+        .app_data_dir()
+        .ok_or_else(|| "Cannot resolve app data directory".to_string())
+        .map(|p| p.join("videos"))
+}
+
+/// Writes the clip as <app-data>/videos/vid-<ms>.<gif|mp4>.
+async fn save_clip(app: &tauri::AppHandle, clip: Clip, format: VideoFormat) -> Result<PathBuf, String> {
+    let dir = videos_dir(app)?;
+    std::fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    let stamp = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis();
+    let path = dir.join(format!("vid-{}.{}", stamp, format.ext()));
+
+    let (source, ext) = match (clip, format) {
+        (Clip::Gif(bytes), VideoFormat::Gif) => {
+            disk::ensure_space(&dir, bytes.len() as u64, "the video")?;
+            std::fs::write(&path, &bytes).map_err(|e| format!("Failed to save video: {}", e))?;
+            return Ok(path);
+        }
+        (Clip::Gif(bytes), VideoFormat::Mp4) => (bytes, "gif"),
+        (Clip::Avi(bytes), _) => (bytes, "avi"),
+    };
+    disk::ensure_space(&dir, source.len() as u64 * 2, "the video")?;
+    let mut temp_files = TempFiles(Vec::new());
+    let input = temp_files.add(std::env::temp_dir().join(format!("sd_video_{}.{}", stamp, ext)));
+    std::fs::write(&input, &source).map_err(|e| e.to_string())?;
+    to_mp4(&input, &path).await?;
+    Ok(path)
+}
+
+async fn to_mp4(input: &Path, output: &Path) -> Result<(), String> {
+    // -progress keeps ffmpeg talking to the watchdog during long encodes
+    let mut cmd = Command::new("ffmpeg");
+    cmd.args(["-y", "-loglevel", "error", "-nostats", "-progress", "pipe:1", "-i"])
+        .arg(input)
+        // x264 wants even dimensions
+        .args(["-vf", "scale=trunc(iw/2)*2:trunc(ih/2)*2", "-c:v", "libx264", "-pix_fmt", "yuv420p", "-movflags", "+faststart"])
+        .arg(output);
+    let out = watchdog::output("ffmpeg", &format!("MP4 conversion of {}", input.display()), &mut cmd)
+        .await
+        .map_err(|e| match e.kind() {
+            std::io::ErrorKind::NotFound => "MP4 output needs ffmpeg on PATH — install it or choose GIF.".to_string(),
+            std::io::ErrorKind::Interrupted => {
+                let _ = std::fs::remove_file(output);
+                "ffmpeg was stopped (no output — it appeared to hang).".to_string()
+            }
+            _ => format!("Failed to start ffmpeg: {}", e),
+        })?;
+    if !out.status.success() {
+        let _ = std::fs::remove_file(output);
+        return Err(format!("ffmpeg failed: {}", String::from_utf8_lossy(&out.stderr).trim()));
+    }
+    Ok(())
+}
+
+// ── Unit tests ─────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chunk(id: &[u8; 4], data: &[u8]) -> Vec<u8> {
+        let mut out = id.to_vec();
+        out.extend((data.len() as u32).to_le_bytes());
+        out.extend(data);
+        if data.len() % 2 == 1 {
+            out.push(0);
+        }
+        out
+    }
+
+    #[test]
+    fn test_wan_frames() {
+        assert_eq!(wan_frames(33), 33);
+        assert_eq!(wan_frames(36), 33);
+        assert_eq!(wan_frames(1), 5);
+        assert_eq!(wan_frames(1000), 121);
+    }
+
+    #[test]
+    fn test_avi_frames_and_gif() {
+        let mut jpeg = Vec::new();
+        image::DynamicImage::new_rgb8(4, 4)
+            .write_to(&mut std::io::Cursor::new(&mut jpeg), image::ImageFormat::Jpeg)
+            .unwrap();
+        let mut movi = b"movi".to_vec();
+        movi.extend(chunk(b"00dc", &jpeg));
+        movi.extend(chunk(b"01wb", b"abc"));
+        movi.extend(chunk(b"00dc", &jpeg));
+        let mut avi = b"RIFF\0\0\0\0AVI ".to_vec();
+        avi.extend(chunk(b"LIST", &movi));
+        avi.extend(chunk(b"idx1", &[0; 16]));
+
+        let frames = avi_frames(&avi);
+        assert_eq!(frames, vec![jpeg.as_slice(), jpeg.as_slice()]);
+        let gif = gif_from_avi(&avi, 16).unwrap();
+        assert!(gif.starts_with(b"GIF89a"));
+        assert!(gif_from_avi(b"RIFF", 16).is_err());
+    }
+
+    #[test]
+    fn test_animatediff_body_and_response() {
+        let req = VideoRequest { prompt: "waves".into(), seed: Some(7), ..Default::default() };
+        let body = animatediff_body(&req, 16, 8);
+        let args = &body["alwayson_scripts"]["AnimateDiff"]["args"][0];
+        assert_eq!(args["video_length"], 16);
+        assert_eq!(args["model"], "mm_sd15_v3.safetensors");
+        assert_eq!(body["seed"], 7);
+
+        let png = general_purpose::STANDARD.encode(b"\x89PNG....");
+        let gif = general_purpose::STANDARD.encode(b"GIF89a....");
+        assert_eq!(animatediff_gif(&json!({ "images": [png, gif] })).unwrap(), b"GIF89a....");
+        assert!(animatediff_gif(&json!({ "images": [png] })).is_err());
+    }
+
+    #[test]
+    fn test_video_format() {
+        assert_eq!(VideoFormat::parse(None), Ok(VideoFormat::Gif));
+        assert_eq!(VideoFormat::parse(Some("MP4")), Ok(VideoFormat::Mp4));
+        assert!(VideoFormat::parse(Some("webm")).is_err());
+    }
+}
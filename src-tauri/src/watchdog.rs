@@ -0,0 +1,287 @@
+// watchdog.rs — stall detection for long-running child processes
+//
+// A job registers itself while its child process runs and touches its
+// activity stamp whenever the child prints something: sd CLI generations
+// (local_sd, video_gen), requests to the persistent sd-server (sd_server —
+// a kill stops the server), and ffmpeg encodes in recording and video_gen,
+// which run through `output` below. A monitor thread flags jobs that have
+// been silent for longer than the stall timeout. The UI can then kill the
+// job; retrying is simply re-issuing the original command once the kill
+// has returned.
+//
+// Tauri commands exposed:
+//   list_jobs             → Vec<JobStatus>
+//   kill_job              (id)
+//   set_watchdog_timeout  (minutes)
+//
+// Events emitted:
+//   job-stalled  → JobStatus (once per stall)
+
+use crate::error::AppError;
+use crate::power;
+use serde::Serialize;
+use std::process::Stdio;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use tauri::Manager;
+use tokio::io::{AsyncRead, AsyncReadExt};
+use tokio::process::Command;
+use tokio::sync::Notify;
+
+const CHECK_SECS: u64 = 15;
+const DEFAULT_STALL_SECS: u64 = 5 * 60;
+
+static STALL_SECS: AtomicU64 = AtomicU64::new(DEFAULT_STALL_SECS);
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+static JOBS: Mutex<Vec<WatchedJob>> = Mutex::new(Vec::new());
+
+struct WatchedJob {
+    id:       String,
+    kind:     String,
+    label:    String,
+    pid:      Option<u32>,
+    started:  u64,
+    activity: Activity,
+    kill:     Arc<Notify>,
+    stalled:  bool,
+}
+
+// ── Types ──────────────────────────────────────────────────────────────────
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct JobStatus {
+    pub id:           String,
+    /// "sd" | "ffmpeg" | …
+    pub kind:         String,
+    pub label:        String,
+    pub pid:          Option<u32>,
+    pub running_secs: u64,
+    /// Seconds since the child last produced output
+    pub idle_secs:    u64,
+    pub stalled:      bool,
+}
+
+/// Last-output timestamp shared with the tasks reading a child's pipes.
+#[derive(Clone)]
+pub(crate) struct Activity(Arc<AtomicU64>);
+
+impl Activity {
+    pub fn touch(&self) {
+        self.0.store(unix_now(), Ordering::Relaxed);
+    }
+
+    fn idle_secs(&self, now: u64) -> u64 {
+        now.saturating_sub(self.0.load(Ordering::Relaxed))
+    }
+}
+
+/// Registration of a running job; unregisters on drop.
+pub(crate) struct JobHandle {
+    id:       String,
+    activity: Activity,
+    kill:     Arc<Notify>,
+}
+
+impl JobHandle {
+    pub fn activity(&self) -> Activity {
+        self.activity.clone()
+    }
+
+    /// Resolves when the user asks to kill this job.
+    pub async fn killed(&self) {
+        self.kill.notified().await
+    }
+}
+
+impl Drop for JobHandle {
+    fn drop(&mut self) {
+        if let Ok(mut jobs) = JOBS.lock() {
+            jobs.retain(|j| j.id != self.id);
+        }
+    }
+}
+
+fn unix_now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+// ── Registry ───────────────────────────────────────────────────────────────
+
+/// Start watching a job; keep the handle alive for as long as the child runs.
+pub(crate) fn register(kind: &str, label: &str, pid: Option<u32>) -> JobHandle {
+    let id = format!("{}-{}", kind, NEXT_ID.fetch_add(1, Ordering::Relaxed));
+    let activity = Activity(Arc::new(AtomicU64::new(unix_now())));
+    let kill = Arc::new(Notify::new());
+    if let Ok(mut jobs) = JOBS.lock() {
+        jobs.push(WatchedJob {
+            id:       id.clone(),
+            kind:     kind.to_string(),
+            label:    label.chars().take(120).collect(),
+            pid,
+            started:  unix_now(),
+            activity: activity.clone(),
+            kill:     kill.clone(),
+            stalled:  false,
+        });
+    }
+    JobHandle { id, activity, kill }
+}
+
+/// `Command::output` for a watched child: every chunk it prints counts as
+/// activity, and kill_job kills it (an `Interrupted` error).
+pub(crate) async fn output(kind: &str, label: &str, cmd: &mut Command) -> std::io::Result<std::process::Output> {
+    let mut child = cmd.stdout(Stdio::piped()).stderr(Stdio::piped()).kill_on_drop(true).spawn()?;
+    let job = register(kind, label, child.id());
+    let stdout = drain(child.stdout.take(), job.activity());
+    let stderr = drain(child.stderr.take(), job.activity());
+    let status = tokio::select! {
+        status = child.wait() => status?,
+        _ = job.killed() => {
+            let _ = child.kill().await;
+            return Err(std::io::Error::new(std::io::ErrorKind::Interrupted, "stopped from the job list"));
+        }
+    };
+    Ok(std::process::Output {
+        status,
+        stdout: stdout.await.unwrap_or_default(),
+        stderr: stderr.await.unwrap_or_default(),
+    })
+}
+
+/// Collects a pipe, touching `activity` on every read.
+fn drain<R: AsyncRead + Unpin + Send + 'static>(reader: Option<R>, activity: Activity) -> tokio::task::JoinHandle<Vec<u8>> {
+    tokio::spawn(async move {
+        let mut out = Vec::new();
+        let Some(mut reader) = reader else { return out };
+        let mut buf = [0u8; 4096];
+        while let Ok(n @ 1..) = reader.read(&mut buf).await {
+            activity.touch();
+            out.extend_from_slice(&buf[..n]);
+        }
+        out
+    })
+}
+
+fn status(job: &WatchedJob, now: u64) -> JobStatus {
+    JobStatus {
+        id:           job.id.clone(),
+        kind:         job.kind.clone(),
+        label:        job.label.clone(),
+        pid:          job.pid,
+        running_secs: now.saturating_sub(job.started),
+        idle_secs:    job.activity.idle_secs(now),
+        stalled:      job.stalled,
+    }
+}
+
+/// Flag jobs that just crossed the stall timeout; returns them once each.
+/// A job that prints again is un-flagged and can stall again later.
+fn check_stalls(now: u64, limit: u64) -> Vec<JobStatus> {
+    let Ok(mut jobs) = JOBS.lock() else { return vec![] };
+    let mut newly = Vec::new();
+    for job in jobs.iter_mut() {
+        let idle = job.activity.idle_secs(now) >= limit;
+        if idle && !job.stalled {
+            job.stalled = true;
+            newly.push(status(job, now));
+        } else if !idle {
+            job.stalled = false;
+        }
+    }
+    newly
+}
+
+/// Check registered jobs periodically and emit `job-stalled`.
+pub fn spawn_monitor(app: tauri::AppHandle) {
+    std::thread::spawn(move || loop {
+        std::thread::sleep(power::scaled(std::time::Duration::from_secs(CHECK_SECS)));
+        for job in check_stalls(unix_now(), STALL_SECS.load(Ordering::Relaxed)) {
+            log::warn!("watchdog: {} ({}) silent for {} s", job.id, job.label, job.idle_secs);
+            let _ = app.emit_all("job-stalled", &job);
+        }
+    });
+}
+
+// ── Tauri commands ─────────────────────────────────────────────────────────
+
+#[tauri::command]
+pub fn list_jobs() -> Vec<JobStatus> {
+    let now = unix_now();
+    JOBS.lock().map(|jobs| jobs.iter().map(|j| status(j, now)).collect()).unwrap_or_default()
+}
+
+/// Ask the job's owner to kill its child; the job's command then returns an error.
+#[tauri::command]
+pub fn kill_job(id: String) -> Result<(), AppError> {
+    let jobs = JOBS.lock().map_err(|e| e.to_string())?;
+    let job = jobs.iter().find(|j| j.id == id).ok_or_else(|| format!("No running job {}", id))?;
+    log::info!("watchdog: killing {} ({})", job.id, job.label);
+    job.kill.notify_one();
+    Ok(())
+}
+
+#[tauri::command]
+pub fn set_watchdog_timeout(minutes: u64) -> Result<(), AppError> {
+    if minutes == 0 {
+        return Err("Watchdog timeout must be at least one minute".into());
+    }
+    let secs = minutes.checked_mul(60).ok_or_else(|| format!("Watchdog timeout of {} minutes is too large", minutes))?;
+    STALL_SECS.store(secs, Ordering::Relaxed);
+    Ok(())
+}
+
+// ── Unit tests ─────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stall_flagged_once_and_cleared_by_output() {
+        let job = register("test-stall", "sleepy child", None);
+        let later = unix_now() + 600;
+
+        let flagged = check_stalls(later, 300);
+        assert!(flagged.iter().any(|j| j.id == job.id));
+        assert!(!check_stalls(later, 300).iter().any(|j| j.id == job.id));
+
+        job.activity().touch();
+        check_stalls(unix_now(), 300);
+        assert!(list_jobs().iter().any(|j| j.id == job.id && !j.stalled));
+    }
+
+    #[test]
+    fn test_handle_drop_unregisters() {
+        let job = register("test-drop", "short job", Some(42));
+        let id = job.id.clone();
+        assert!(list_jobs().iter().any(|j| j.id == id && j.pid == Some(42)));
+        drop(job);
+        assert!(!list_jobs().iter().any(|j| j.id == id));
+        assert!(kill_job(id).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_kill_wakes_owner() {
+        let job = register("test-kill", "hung child", None);
+        kill_job(job.id.clone()).unwrap();
+        tokio::time::timeout(std::time::Duration::from_secs(1), job.killed()).await.unwrap();
+    }
+
+    #[test]
+    fn test_timeout_bounds() {
+        assert!(set_watchdog_timeout(0).is_err());
+        assert!(set_watchdog_timeout(u64::MAX).unwrap_err().message.contains("too large"));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_output_collects_and_unregisters() {
+        let out = output("test-output", "echo", Command::new("sh").args(["-c", "echo out; echo err >&2"])).await.unwrap();
+        assert!(out.status.success());
+        assert_eq!((out.stdout.as_slice(), out.stderr.as_slice()), (&b"out\n"[..], &b"err\n"[..]));
+        assert!(!list_jobs().iter().any(|j| j.kind == "test-output"));
+    }
+}
@@ -3,17 +3,59 @@
 // Backends:
 //   duckduckgo  — HTML scraping of html.duckduckgo.com (real results, no key)
 //   brave       — Brave Search API (requires free API key)
+//   bing        — Bing Web Search API v7 (Azure resource key), with market /
+//                 UI-language selection
+//   serper      — Google results via serper.dev (API key)
+//   serpapi     — Google results via SerpAPI (API key)
+//                 Both put the answer box / knowledge graph, when Google shows
+//                 one, into a synthetic first result.
 //   searxng     — self-hosted SearXNG instance
+//   stackexchange — Stack Overflow questions via the Stack Exchange API (key
+//                 optional); `content` carries the accepted or top-voted
+//                 answer as markdown
 //
 // Extra commands:
 //   fetch_url_content  — fetch a single URL and extract plain text
+//                        (prefers llms.txt / llms-full.txt on docs sites,
+//                        the GitHub API for github.com URLs); takes extra
+//                        request headers and sends the site's stored
+//                        cookies (site_cookies.rs);
+//                        page_render.rs renders JavaScript-only pages
 //   search_and_fetch   — search + parallel-fetch top-N pages for deep RAG
-
+//                        (`fetch_count` pages, `fetch_concurrency` at a time,
+//                        whatever arrived when `fetch_budget_ms` runs out)
+//   search_error       — recognize a compiler error / stack trace and run
+//                        targeted Stack Overflow + GitHub issues searches
+//   image_search       — image vertical of Brave / SearXNG: thumbnails (as
+//                        URLs, or inlined as data: URIs) for reference images
+//   set_fetch_user_agent — User-Agent for page fetches (None = browser default)
+//
+// `language` / `region` (or a combined `market` such as "de-DE") select the
+// result locale: DuckDuckGo kl, Brave search_lang / country, Bing mkt,
+// Google hl / gl, SearXNG language.
+//
+// web_search, search_and_fetch and image_search answer repeated queries from
+// the on-disk cache (search_cache.rs) within `cache_ttl_secs`. web_search and
+// search_and_fetch take a 1-based `page`, mapped to each backend's own paging
+// (Brave page offset, Bing / SerpAPI result offset, Serper page, SearXNG
+// pageno, DuckDuckGo Lite `s`). `include_domains` / `exclude_domains` become
+// site: / -site: operators for the API backends; every backend's results are
+// then post-filtered by host, which is the only filter DuckDuckGo gets.
+// With `rewrite` set, the configured LLM first turns the question into 2–3
+// focused queries; they run in parallel and the results are merged.
+// Results are deduplicated by canonical URL (AMP / mobile variants, tracking
+// parameters, trailing slashes) and, unless `rerank` is false, over-fetched
+// and re-ranked by BM25 of title + snippet against the query before being cut
+// to `max_results`.
+
+use crate::ai_bridge::{self, AiRequest};
+use crate::error::AppError;
+use crate::{http, search_cache, site_cookies};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use regex::Regex;
-use std::time::Duration;
 
 // ── Public types ─────────────────────────────────────────────────────────
 
@@ -35,6 +77,56 @@ pub struct WebSearchRequest {
     pub max_results:   Option<usize>,
     /// Fetch page content for top results when true
     pub fetch_content: Option<bool>,
+    /// Run local query cleanup (filler words, error quoting, stack traces) first
+    pub preprocess:    Option<bool>,
+    /// Market such as "en-US" or "de-DE" (default en-US)
+    #[serde(default)]
+    pub market:        Option<String>,
+    /// Result language, e.g. "de"; overrides the language part of `market`
+    #[serde(default)]
+    pub language:      Option<String>,
+    /// Result region, e.g. "AT"; overrides the region part of `market`
+    #[serde(default)]
+    pub region:        Option<String>,
+    /// Reuse a cached response younger than this (default 1 h, 0 = always search)
+    #[serde(default)]
+    pub cache_ttl_secs: Option<u64>,
+    /// Result page, 1-based (default 1): page 2 holds the `max_results`
+    /// results after the first page's
+    #[serde(default)]
+    pub page:          Option<usize>,
+    /// Only results from these domains (and their subdomains), e.g. "docs.rs"
+    #[serde(default)]
+    pub include_domains: Vec<String>,
+    /// Never results from these domains
+    #[serde(default)]
+    pub exclude_domains: Vec<String>,
+    /// Let this LLM rewrite the question into several search queries first
+    #[serde(default)]
+    pub rewrite:       Option<QueryRewriter>,
+    /// Re-rank results by relevance to the query (default true)
+    #[serde(default)]
+    pub rerank:        Option<bool>,
+    /// Results whose pages are fetched when fetching content (default 3)
+    #[serde(default)]
+    pub fetch_count:       Option<usize>,
+    /// Pages fetched at the same time for this request (default 3)
+    #[serde(default)]
+    pub fetch_concurrency: Option<usize>,
+    /// Time for all page fetches together; slower pages are dropped (default 12 s)
+    #[serde(default)]
+    pub fetch_budget_ms:   Option<u64>,
+}
+
+/// Model used for multi-query expansion.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct QueryRewriter {
+    /// "openai" | "claude" | "deepseek" | "openrouter" | "together" | "local"
+    pub provider:  String,
+    #[serde(default)]
+    pub api_key:   String,
+    pub model:     Option<String>,
+    pub local_url: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -44,97 +136,607 @@ pub struct WebSearchResponse {
     pub query:   String,
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ErrorSearchRequest {
+    /// Raw error output: compiler message, exception, or full stack trace
+    pub text:        String,
+    pub backend:     String,
+    pub api_key:     Option<String>,
+    pub base_url:    Option<String>,
+    pub max_results: Option<usize>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ImageSearchRequest {
+    pub query:             String,
+    /// "brave" | "searxng"
+    pub backend:           String,
+    pub api_key:           Option<String>,
+    pub base_url:          Option<String>,
+    pub max_results:       Option<usize>,
+    /// Download the thumbnails and return them as data: URIs, for webviews
+    /// that can't load remote images (CSP) or to hand straight to image gen
+    #[serde(default)]
+    pub inline_thumbnails: Option<bool>,
+    /// Reuse a cached response younger than this (default 1 h, 0 = always search)
+    #[serde(default)]
+    pub cache_ttl_secs:    Option<u64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ImageSearchResult {
+    pub title:            String,
+    /// Page the image appears on
+    pub page_url:         String,
+    /// Full-size image
+    pub image_url:        String,
+    pub thumbnail_url:    String,
+    /// data: URI of the thumbnail (inline_thumbnails only)
+    pub thumbnail_base64: Option<String>,
+    pub width:            Option<u32>,
+    pub height:           Option<u32>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ImageSearchResponse {
+    pub results: Vec<ImageSearchResult>,
+    pub backend: String,
+    pub query:   String,
+}
+
+/// Thumbnails larger than this are left as URLs
+const MAX_THUMBNAIL_BYTES: usize = 512 * 1024;
+
 // ── HTTP clients ──────────────────────────────────────────────────────────
 
 fn http_client() -> reqwest::Result<Client> {
-    Client::builder()
-        .timeout(Duration::from_secs(20))
-        .user_agent(
-            "Mozilla/5.0 (X11; Linux x86_64) AppleWebKit/537.36 \
-             (KHTML, like Gecko) Chrome/122.0.0.0 Safari/537.36",
-        )
-        .build()
+    http::client(http::Timeout::Search)
 }
 
 fn http_client_page() -> reqwest::Result<Client> {
-    Client::builder()
-        .timeout(Duration::from_secs(8))
-        .user_agent(
-            "Mozilla/5.0 (X11; Linux x86_64) AppleWebKit/537.36 \
-             (KHTML, like Gecko) Chrome/122.0.0.0 Safari/537.36",
-        )
-        .build()
+    http::client(http::Timeout::Page)
 }
 
 // ── Tauri commands ────────────────────────────────────────────────────────
 
 /// Perform a web search, optionally also fetching page content.
 #[tauri::command]
-pub async fn web_search(req: WebSearchRequest) -> Result<WebSearchResponse, String> {
+pub async fn web_search(app_handle: tauri::AppHandle, req: WebSearchRequest) -> Result<WebSearchResponse, AppError> {
     let max   = req.max_results.unwrap_or(5).min(10);
     let fetch = req.fetch_content.unwrap_or(false);
+    let query = effective_query(&req);
+    let key   = cache_key(&req, &query, max, if fetch { "fetch" } else { "web" });
+    if let Some(hit) = cached_response(&app_handle, &key, req.cache_ttl_secs) {
+        return Ok(hit);
+    }
 
-    let mut resp = dispatch_search(&req.backend, &req.query, req.api_key.as_deref(),
-                                   req.base_url.as_deref(), max).await?;
+    let mut resp = search_expanded(&req, &query, max).await?;
 
     if fetch && !resp.results.is_empty() {
-        resp.results = fetch_results_content(resp.results, 3).await;
+        resp.results = fetch_results_content(resp.results, fetch_plan(&req)).await;
+    }
+    if !resp.results.is_empty() {
+        search_cache::put(&app_handle, &key, &resp);
     }
     Ok(resp)
 }
 
 /// Fetch readable plain text from a single URL.
+/// For docs-site landing pages the curated `llms-full.txt` / `llms.txt` is
+/// returned when the site publishes one (disable with `prefer_llms_txt: false`).
+/// `headers` (e.g. Authorization) are sent with the request, together with
+/// the cookies stored for the site.
 #[tauri::command]
-pub async fn fetch_url_content(url: String, max_chars: Option<usize>) -> Result<String, String> {
-    fetch_page_text(&url, max_chars.unwrap_or(4_000)).await
+pub async fn fetch_url_content(
+    app_handle:      tauri::AppHandle,
+    url:             String,
+    max_chars:       Option<usize>,
+    prefer_llms_txt: Option<bool>,
+    headers:         Option<std::collections::HashMap<String, String>>,
+) -> Result<String, AppError> {
+    let max = max_chars.unwrap_or(4_000);
+    let headers = request_headers(headers.unwrap_or_default(), site_cookies::cookie_for(&app_handle, &url))?;
+    if let Some(target) = crate::github_fetch::parse_github_url(&url) {
+        match crate::github_fetch::fetch_target(&target, None).await {
+            Ok(text) => return Ok(truncate_text(text, max)),
+            Err(e) => log::warn!("fetch_url_content: {} — scraping the page instead", e),
+        }
+    }
+    if prefer_llms_txt.unwrap_or(true) && is_docs_landing(&url) {
+        if let Some(text) = fetch_llms_txt(&url, max, &headers).await {
+            return Ok(text);
+        }
+    }
+    let (text, set_cookies) = fetch_page(&url, max, headers).await?;
+    site_cookies::remember(&app_handle, &url, &set_cookies);
+    Ok(text)
 }
 
 /// Search and automatically fetch page content for top 3 results in parallel.
 #[tauri::command]
-pub async fn search_and_fetch(req: WebSearchRequest) -> Result<WebSearchResponse, String> {
-    let max = req.max_results.unwrap_or(5).min(10);
-    let mut resp = dispatch_search(&req.backend, &req.query, req.api_key.as_deref(),
-                                   req.base_url.as_deref(), max).await?;
-    resp.results = fetch_results_content(resp.results, 3).await;
+pub async fn search_and_fetch(app_handle: tauri::AppHandle, req: WebSearchRequest) -> Result<WebSearchResponse, AppError> {
+    let max   = req.max_results.unwrap_or(5).min(10);
+    let query = effective_query(&req);
+    let key   = cache_key(&req, &query, max, "fetch");
+    if let Some(hit) = cached_response(&app_handle, &key, req.cache_ttl_secs) {
+        return Ok(hit);
+    }
+    let mut resp = search_expanded(&req, &query, max).await?;
+    resp.results = fetch_results_content(resp.results, fetch_plan(&req)).await;
+    if !resp.results.is_empty() {
+        search_cache::put(&app_handle, &key, &resp);
+    }
+    Ok(resp)
+}
+
+/// Search for a pasted error message.
+/// Extracts the essential message/code/symbols, runs Stack Overflow,
+/// GitHub issues and open-web searches concurrently, and merges the results.
+#[tauri::command]
+pub async fn search_error(req: ErrorSearchRequest) -> Result<WebSearchResponse, AppError> {
+    use crate::query_prep::{error_search_queries, extract_error_signature};
+
+    let max = req.max_results.unwrap_or(8).min(15);
+    let sig = extract_error_signature(&req.text)
+        .ok_or("No recognizable error message found in the text")?;
+    let queries = error_search_queries(&sig);
+    log::info!("search_error: {:?} → {} queries", sig.code, queries.len());
+
+    let mut set = tokio::task::JoinSet::new();
+    for (i, q) in queries.iter().enumerate() {
+        let (backend, q) = (req.backend.clone(), q.clone());
+        let (key, base) = (req.api_key.clone(), req.base_url.clone());
+        set.spawn(async move {
+            (i, dispatch_search(&backend, &q, key.as_deref(), base.as_deref(), None, max, 1).await)
+        });
+    }
+
+    let mut lists: Vec<Vec<SearchResult>> = vec![Vec::new(); queries.len()];
+    let mut last_err: Option<String> = None;
+    // A panicked query loses only its own results
+    while let Some(joined) = set.join_next().await {
+        match joined {
+            Ok((i, Ok(r)))  => lists[i] = r.results,
+            Ok((i, Err(e))) => { log::warn!("search_error query {} failed: {}", i, e); last_err = Some(e); }
+            Err(e)          => log::warn!("search_error query task failed: {}", e),
+        }
+    }
+
+    let results = merge_results(lists, max);
+    if results.is_empty() {
+        if let Some(e) = last_err { return Err(e.into()); }
+    }
+
+    let query = match &sig.code {
+        Some(code) if !sig.message.is_empty() => format!("{}: {}", code, sig.message),
+        Some(code) => code.clone(),
+        None       => sig.message.clone(),
+    };
+    Ok(WebSearchResponse { results, backend: req.backend, query })
+}
+
+/// Search for images; thumbnails are inlined when `inline_thumbnails` is set.
+#[tauri::command]
+pub async fn image_search(app_handle: tauri::AppHandle, req: ImageSearchRequest) -> Result<ImageSearchResponse, AppError> {
+    let max = req.max_results.unwrap_or(12).min(30);
+    let inline = req.inline_thumbnails.unwrap_or(false);
+    let key = search_cache::key(&req.backend, &req.query, &[
+        "images", &max.to_string(), req.base_url.as_deref().unwrap_or(""), if inline { "inline" } else { "" },
+    ]);
+    if let Some(hit) = cached_response(&app_handle, &key, req.cache_ttl_secs) {
+        return Ok(hit);
+    }
+    let mut results = match req.backend.as_str() {
+        "brave"   => search_brave_images(&req.query, req.api_key.as_deref().unwrap_or(""), max).await?,
+        "searxng" => search_searxng_images(&req.query, req.base_url.as_deref().unwrap_or("http://localhost:8080"), max).await?,
+        other     => return Err(format!("Image search is not available for backend: {}", other).into()),
+    };
+    if inline {
+        results = inline_thumbnails(results).await;
+    }
+    let resp = ImageSearchResponse { results, backend: req.backend, query: req.query };
+    if !resp.results.is_empty() {
+        search_cache::put(&app_handle, &key, &resp);
+    }
     Ok(resp)
 }
 
 // ── Dispatch ──────────────────────────────────────────────────────────────
 
-async fn dispatch_search(
+/// Interleave several ranked result lists (round-robin), dropping duplicate URLs.
+pub(crate) fn merge_results(lists: Vec<Vec<SearchResult>>, max: usize) -> Vec<SearchResult> {
+    let mut seen = std::collections::HashSet::new();
+    let mut out  = Vec::new();
+    let mut iters: Vec<_> = lists.into_iter().map(|l| l.into_iter()).collect();
+    loop {
+        let mut progressed = false;
+        for it in iters.iter_mut() {
+            if let Some(r) = it.next() {
+                progressed = true;
+                let key = canonical_url(&r.url);
+                if !r.url.is_empty() && seen.insert(key) && out.len() < max {
+                    out.push(r);
+                }
+            }
+        }
+        if !progressed || out.len() >= max { break; }
+    }
+    out
+}
+
+/// Cache key of a web search: everything that changes the results, not the key.
+fn cache_key(req: &WebSearchRequest, query: &str, max: usize, mode: &str) -> String {
+    search_cache::key(&req.backend, query, &[
+        mode,
+        &max.to_string(),
+        &page(req).to_string(),
+        req.base_url.as_deref().unwrap_or(""),
+        &request_market(req).unwrap_or_default(),
+        &req.include_domains.join(","),
+        &req.exclude_domains.join(","),
+        &req.rewrite.as_ref().map_or(String::new(), |r| format!("{}/{}", r.provider, r.model.as_deref().unwrap_or(""))),
+        if req.rerank.unwrap_or(true) { "rerank" } else { "" },
+        &fetch_plan(req).count.to_string(),
+    ])
+}
+
+/// dispatch_search with the request's page and domain filters applied.
+async fn run_search(req: &WebSearchRequest, query: &str, max: usize) -> Result<WebSearchResponse, String> {
+    let include = normalize_domains(&req.include_domains);
+    let exclude = normalize_domains(&req.exclude_domains);
+    let scoped = if req.backend == "duckduckgo" {
+        query.to_string()
+    } else {
+        with_site_operators(query, &include, &exclude)
+    };
+    // Re-ranking needs candidates beyond the first `max` to choose from
+    let rerank = req.rerank.unwrap_or(true);
+    let fetch_n = if rerank { (max * 2).min(RERANK_POOL) } else { max };
+    let market = request_market(req);
+    let mut resp = dispatch_search(&req.backend, &scoped, req.api_key.as_deref(),
+                                   req.base_url.as_deref(), market.as_deref(), fetch_n, page(req)).await?;
+    resp.results.retain(|r| domain_allowed(&r.url, &include, &exclude));
+    resp.results = dedupe_results(resp.results);
+    if rerank {
+        resp.results = rerank_results(resp.results, query);
+    }
+    resp.results.truncate(max);
+    Ok(resp)
+}
+
+/// run_search, fanned out over LLM-written queries when `rewrite` is set.
+/// Falls back to the query as typed when the model fails or answers nothing.
+async fn search_expanded(req: &WebSearchRequest, query: &str, max: usize) -> Result<WebSearchResponse, String> {
+    let Some(llm) = &req.rewrite else {
+        return run_search(req, query, max).await;
+    };
+    let queries = match rewrite_queries(llm, query).await {
+        Ok(q) if !q.is_empty() => q,
+        Ok(_) => return run_search(req, query, max).await,
+        Err(e) => {
+            log::warn!("web_search: query rewriting failed ({}) — searching as typed", e);
+            return run_search(req, query, max).await;
+        }
+    };
+    log::info!("web_search: expanded into {:?}", queries);
+
+    let outcomes = futures_util::future::join_all(queries.iter().map(|q| run_search(req, q, max))).await;
+    let mut lists = Vec::with_capacity(outcomes.len());
+    let mut last_err: Option<String> = None;
+    for (q, res) in queries.iter().zip(outcomes) {
+        match res {
+            Ok(r)  => lists.push(r.results),
+            Err(e) => { log::warn!("web_search: '{}' failed: {}", q, e); last_err = Some(e); }
+        }
+    }
+    let results = merge_results(lists, max);
+    if results.is_empty() {
+        if let Some(e) = last_err { return Err(e); }
+    }
+    Ok(WebSearchResponse { results, backend: req.backend.clone(), query: queries.join(" | ") })
+}
+
+/// The request's locale as a market ("de-AT"): `language` / `region`
+/// override the matching half of `market`. None leaves the backend default.
+fn request_market(req: &WebSearchRequest) -> Option<String> {
+    let language = req.language.as_deref().map(str::trim).filter(|l| !l.is_empty());
+    let region = req.region.as_deref().map(str::trim).filter(|r| !r.is_empty());
+    if language.is_none() && region.is_none() {
+        return req.market.clone().filter(|m| !m.trim().is_empty());
+    }
+    let (mkt, lang) = bing_market(req.market.as_deref());
+    let base_region = mkt.rsplit('-').next().unwrap_or("US").to_string();
+    Some(format!(
+        "{}-{}",
+        language.unwrap_or(lang.as_str()).to_ascii_lowercase(),
+        region.unwrap_or(base_region.as_str()).to_ascii_uppercase()
+    ))
+}
+
+/// Requested page, 1-based; backends stop paging after 10 pages.
+fn page(req: &WebSearchRequest) -> usize {
+    req.page.unwrap_or(1).clamp(1, 10)
+}
+
+fn cached_response<T: serde::de::DeserializeOwned>(app: &tauri::AppHandle, key: &str, ttl_secs: Option<u64>) -> Option<T> {
+    let hit = search_cache::get(app, key, ttl_secs.unwrap_or(search_cache::DEFAULT_TTL_SECS));
+    if hit.is_some() {
+        log::info!("web_search: served from cache");
+    }
+    hit
+}
+
+/// The query actually sent to the backend — preprocessed when requested.
+fn effective_query(req: &WebSearchRequest) -> String {
+    if !req.preprocess.unwrap_or(false) {
+        return req.query.clone();
+    }
+    let cleaned = crate::query_prep::preprocess_query(&req.query, &req.backend);
+    if cleaned != req.query {
+        log::info!("web_search: query rewritten → {}", cleaned);
+    }
+    cleaned
+}
+
+pub(crate) async fn dispatch_search(
     backend: &str,
     query:   &str,
     api_key: Option<&str>,
     base_url: Option<&str>,
+    market:  Option<&str>,
     max:     usize,
+    page:    usize,
 ) -> Result<WebSearchResponse, String> {
     match backend {
-        "brave"      => search_brave(query, api_key.unwrap_or(""), max).await,
-        "searxng"    => search_searxng(query, base_url.unwrap_or("http://localhost:8080"), max).await,
-        "duckduckgo" => search_duckduckgo(query, max).await,
+        "brave"      => search_brave(query, api_key.unwrap_or(""), market, max, page).await,
+        "bing"       => search_bing(query, api_key.unwrap_or(""), market, max, page).await,
+        "serper"     => search_serper(query, api_key.unwrap_or(""), market, max, page).await,
+        "serpapi"    => search_serpapi(query, api_key.unwrap_or(""), market, max, page).await,
+        "searxng"    => search_searxng(query, base_url.unwrap_or("http://localhost:8080"), market, max, page).await,
+        "duckduckgo" => search_duckduckgo(query, market, max, page).await,
+        "stackexchange" => search_stackexchange(query, api_key.unwrap_or(""), max, page).await,
         other        => Err(format!("Unknown search backend: {}", other)),
     }
 }
 
+// ── LLM query expansion ──────────────────────────────────────────────────
+
+const REWRITE_INSTRUCTIONS: &str = "You turn a user's question into web search queries. \
+Write 2 or 3 short, focused queries (keywords, not sentences) that together cover what the \
+user needs. Keep error messages, names and version numbers exactly as given. Reply with one \
+query per line and nothing else.";
+const MAX_REWRITES: usize = 3;
+
+async fn rewrite_queries(llm: &QueryRewriter, question: &str) -> Result<Vec<String>, String> {
+    let ai_req = AiRequest {
+        api_key:       llm.api_key.clone(),
+        prompt:        question.to_string(),
+        system_prompt: Some(REWRITE_INSTRUCTIONS.to_string()),
+        image_base64:  None,
+        context_files: None,
+        model:         llm.model.clone(),
+        max_tokens:    Some(200),
+        allow_secrets: false,
+        profile:       None,
+    };
+    let resp = ai_bridge::complete(&llm.provider, ai_req, llm.local_url.as_deref())
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(parse_rewritten_queries(&resp.text))
+}
+
+/// Queries from the model's reply: one per line (bullets, numbering and
+/// wrapping quotes removed) or a JSON array of strings. Duplicates dropped.
+fn parse_rewritten_queries(text: &str) -> Vec<String> {
+    let text = text.trim().trim_start_matches("```json").trim_start_matches("```").trim_end_matches("```").trim();
+    let lines: Vec<String> = match serde_json::from_str::<Vec<String>>(text) {
+        Ok(list) => list,
+        Err(_) => text.lines().map(str::to_string).collect(),
+    };
+    let mut seen = std::collections::HashSet::new();
+    lines
+        .iter()
+        .map(|l| {
+            let l = l.trim().trim_start_matches(['-', '*', '•']).trim_start();
+            let l = match l.find(|c: char| !c.is_ascii_digit()) {
+                Some(i) if i > 0 && l[i..].starts_with(['.', ')']) => l[i + 1..].trim(),
+                _ => l,
+            };
+            l.strip_prefix('"').and_then(|q| q.strip_suffix('"')).unwrap_or(l).trim().to_string()
+        })
+        .filter(|q| !q.is_empty() && !q.ends_with(':') && seen.insert(q.to_lowercase()))
+        .take(MAX_REWRITES)
+        .collect()
+}
+
+// ── Deduplication / re-ranking ───────────────────────────────────────────
+
+/// Results fetched for re-ranking, at most
+const RERANK_POOL: usize = 20;
+/// BM25 parameters (the usual defaults)
+const BM25_K1: f64 = 1.2;
+const BM25_B: f64 = 0.75;
+/// Share of the final score from BM25; the rest is the backend's own order
+const RELEVANCE_WEIGHT: f64 = 0.6;
+/// Query parameters that never change the page (plus every utm_*)
+const TRACKING_PARAMS: &[&str] = &["fbclid", "gclid", "ref_src", "amp", "outputType"];
+
+/// Identity of a page across the URL variants engines return: scheme,
+/// www. / m. / amp. hosts, Google and ampproject AMP caches, /amp paths,
+/// tracking parameters, fragments and trailing slashes are ignored.
+fn canonical_url(url: &str) -> String {
+    let url = url.trim();
+    let rest = url.split_once("://").map_or(url, |(_, r)| r);
+    let rest = rest.split('#').next().unwrap_or("");
+    let rest = match rest.split_once('/') {
+        Some((host, path)) if host.eq_ignore_ascii_case("www.google.com") && path.starts_with("amp/s/") => &path[6..],
+        Some((host, path)) if host.ends_with(".cdn.ampproject.org") && (path.starts_with("c/s/") || path.starts_with("v/s/")) => &path[4..],
+        _ => rest,
+    };
+    let (location, params) = rest.split_once('?').unwrap_or((rest, ""));
+    let (host, path) = location.split_once('/').unwrap_or((location, ""));
+    let host = host.to_ascii_lowercase();
+    let host = ["www.", "m.", "mobile.", "amp."]
+        .iter()
+        .find_map(|p| host.strip_prefix(p))
+        .unwrap_or(host.as_str());
+    let path = path.trim_end_matches('/');
+    let path = if path == "amp" { "" } else { path };
+    let path = path.strip_suffix("/amp").or_else(|| path.strip_suffix(".amp")).unwrap_or(path);
+    let params: Vec<&str> = params
+        .split('&')
+        .filter(|p| {
+            let key = p.split('=').next().unwrap_or("");
+            !key.is_empty() && !key.starts_with("utm_") && !TRACKING_PARAMS.contains(&key)
+        })
+        .collect();
+    if params.is_empty() {
+        format!("{}/{}", host, path)
+    } else {
+        format!("{}/{}?{}", host, path, params.join("&"))
+    }
+}
+
+/// Drops later results whose canonical URL was already seen.
+fn dedupe_results(results: Vec<SearchResult>) -> Vec<SearchResult> {
+    let mut seen = std::collections::HashSet::new();
+    results
+        .into_iter()
+        .filter(|r| r.url.is_empty() || seen.insert(canonical_url(&r.url)))
+        .collect()
+}
+
+/// Orders results by BM25 of title + snippet against `query`, blended with
+/// the backend's ranking so a keyword-stuffed snippet can't jump the list
+/// on its own. Ties keep the backend's order.
+fn rerank_results(results: Vec<SearchResult>, query: &str) -> Vec<SearchResult> {
+    let mut terms = tokens(query);
+    terms.sort();
+    terms.dedup();
+    if terms.is_empty() || results.len() < 2 {
+        return results;
+    }
+    let docs: Vec<Vec<String>> = results.iter().map(|r| tokens(&format!("{} {}", r.title, r.snippet))).collect();
+    let n = docs.len() as f64;
+    let avg_len = (docs.iter().map(Vec::len).sum::<usize>() as f64 / n).max(1.0);
+    let bm25: Vec<f64> = docs
+        .iter()
+        .map(|doc| {
+            terms.iter().map(|t| {
+                let tf = doc.iter().filter(|w| *w == t).count() as f64;
+                if tf == 0.0 {
+                    return 0.0;
+                }
+                let df = docs.iter().filter(|d| d.contains(t)).count() as f64;
+                let idf = ((n - df + 0.5) / (df + 0.5) + 1.0).ln();
+                idf * tf * (BM25_K1 + 1.0) / (tf + BM25_K1 * (1.0 - BM25_B + BM25_B * doc.len() as f64 / avg_len))
+            }).sum()
+        })
+        .collect();
+    let top = bm25.iter().copied().fold(0.0, f64::max);
+    if top <= 0.0 {
+        return results;
+    }
+    let mut scored: Vec<(f64, SearchResult)> = results
+        .into_iter()
+        .enumerate()
+        .map(|(i, r)| {
+            let prior = 1.0 - i as f64 / n;
+            (RELEVANCE_WEIGHT * bm25[i] / top + (1.0 - RELEVANCE_WEIGHT) * prior, r)
+        })
+        .collect();
+    scored.sort_by(|a, b| b.0.total_cmp(&a.0));
+    scored.into_iter().map(|(_, r)| r).collect()
+}
+
+/// Lowercase words of two or more characters.
+fn tokens(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|w| w.chars().count() >= 2)
+        .map(str::to_lowercase)
+        .collect()
+}
+
+// ── Domain filters ───────────────────────────────────────────────────────
+
+/// Bare lowercase hosts: "https://www.Docs.rs/x" → "docs.rs".
+fn normalize_domains(domains: &[String]) -> Vec<String> {
+    domains
+        .iter()
+        .filter_map(|d| {
+            let d = d.trim().to_ascii_lowercase();
+            let d = d.split_once("://").map_or(d.as_str(), |(_, rest)| rest);
+            let d = d.split(['/', '?', '#', ':']).next().unwrap_or("");
+            let d = d.strip_prefix("www.").unwrap_or(d).trim_matches('.');
+            (!d.is_empty()).then(|| d.to_string())
+        })
+        .collect()
+}
+
+/// `query (site:a OR site:b) -site:c` — understood by Brave, Bing, Google and
+/// the engines behind SearXNG.
+fn with_site_operators(query: &str, include: &[String], exclude: &[String]) -> String {
+    let mut out = query.trim().to_string();
+    match include {
+        [] => {}
+        [one] => out.push_str(&format!(" site:{}", one)),
+        many => {
+            let sites: Vec<_> = many.iter().map(|d| format!("site:{}", d)).collect();
+            out.push_str(&format!(" ({})", sites.join(" OR ")));
+        }
+    }
+    for d in exclude {
+        out.push_str(&format!(" -site:{}", d));
+    }
+    out
+}
+
+/// Host of `url` is (a subdomain of) an included domain and of no excluded one.
+fn domain_allowed(url: &str, include: &[String], exclude: &[String]) -> bool {
+    if include.is_empty() && exclude.is_empty() {
+        return true;
+    }
+    let Some(host) = url_host(url) else { return false };
+    let matches = |d: &String| host == *d || host.ends_with(&format!(".{}", d));
+    (include.is_empty() || include.iter().any(matches)) && !exclude.iter().any(matches)
+}
+
+fn url_host(url: &str) -> Option<String> {
+    let rest = url.split_once("://")?.1;
+    let authority = rest.split(['/', '?', '#']).next()?;
+    let host = authority.rsplit('@').next()?.split(':').next()?;
+    (!host.is_empty()).then(|| host.to_ascii_lowercase())
+}
+
 // ── DuckDuckGo (HTML scrape + instant-answer fallback) ───────────────────
 
-async fn search_duckduckgo(query: &str, max: usize) -> Result<WebSearchResponse, String> {
-    match ddg_html_search(query, max).await {
+async fn search_duckduckgo(query: &str, market: Option<&str>, max: usize, page: usize) -> Result<WebSearchResponse, String> {
+    match ddg_html_search(query, market, max, page).await {
         Ok(r) if !r.results.is_empty() => return Ok(r),
+        // The instant-answer API has no further pages
+        Ok(r) if page > 1 => return Ok(r),
         Ok(_)  => log::warn!("DDG HTML returned 0 results, using instant-answer fallback"),
         Err(e) => log::warn!("DDG HTML error: {} — using instant-answer fallback", e),
     }
     ddg_instant_answer(query, max).await
 }
 
-async fn ddg_html_search(query: &str, max: usize) -> Result<WebSearchResponse, String> {
+async fn ddg_html_search(query: &str, market: Option<&str>, max: usize, page: usize) -> Result<WebSearchResponse, String> {
     let client = http_client().map_err(|e| e.to_string())?;
 
-    // Use DDG Lite with GET — more browser-transparent than POST, avoids bot checks
-    let url = format!(
-        "https://lite.duckduckgo.com/lite/?q={}&kl=en-us",
-        percent_encode_query(query)
+    // Use DDG Lite with GET — more browser-transparent than POST, avoids bot checks.
+    // kl is region-language: "us-en", "de-de"
+    let (mkt, lang) = bing_market(market);
+    let region = mkt.rsplit('-').next().unwrap_or("US").to_ascii_lowercase();
+    let mut url = format!(
+        "https://lite.duckduckgo.com/lite/?q={}&kl={}-{}",
+        percent_encode_query(query), region, lang
     );
+    // s = results to skip, dc = 1-based index of the first one shown
+    let skip = page_offset(page, max);
+    if skip > 0 {
+        url.push_str(&format!("&s={}&dc={}", skip, skip + 1));
+    }
     let html = client
         .get(&url)
         .header("Accept",          "text/html,application/xhtml+xml,application/xml;q=0.9,*/*;q=0.8")
@@ -155,6 +757,11 @@ async fn ddg_html_search(query: &str, max: usize) -> Result<WebSearchResponse, S
     })
 }
 
+/// Results before `page` (1-based) at `per_page` results each.
+fn page_offset(page: usize, per_page: usize) -> usize {
+    page.saturating_sub(1) * per_page
+}
+
 /// Encode a query string for use in a URL query parameter.
 fn percent_encode_query(s: &str) -> String {
     s.chars()
@@ -264,11 +871,13 @@ async fn ddg_instant_answer(query: &str, max: usize) -> Result<WebSearchResponse
 
 // ── Brave Search ─────────────────────────────────────────────────────────
 
-async fn search_brave(query: &str, api_key: &str, max: usize) -> Result<WebSearchResponse, String> {
+async fn search_brave(query: &str, api_key: &str, market: Option<&str>, max: usize, page: usize) -> Result<WebSearchResponse, String> {
     if api_key.is_empty() {
         return Err("Brave Search requires an API key (free tier at brave.com/search/api)".into());
     }
 
+    let (mkt, lang) = bing_market(market);
+    let country = mkt.rsplit('-').next().unwrap_or("US").to_string();
     let client = http_client().map_err(|e| e.to_string())?;
 
     let resp: Value = client
@@ -276,7 +885,14 @@ async fn search_brave(query: &str, api_key: &str, max: usize) -> Result<WebSearc
         .header("Accept",          "application/json")
         .header("Accept-Encoding", "gzip")
         .header("X-Subscription-Token", api_key)
-        .query(&[("q", query), ("count", &max.to_string()), ("search_lang", "en")])
+        // Brave's offset counts pages of `count` results (0–9)
+        .query(&[
+            ("q", query),
+            ("count", &max.to_string()),
+            ("offset", &(page - 1).to_string()),
+            ("search_lang", &lang),
+            ("country", &country),
+        ])
         .send().await.map_err(|e| format!("Brave Search error: {}", e))?
         .json().await.map_err(|e| e.to_string())?;
 
@@ -298,9 +914,297 @@ async fn search_brave(query: &str, api_key: &str, max: usize) -> Result<WebSearc
     Ok(WebSearchResponse { results, backend: "brave".into(), query: query.into() })
 }
 
+// ── Bing Web Search ──────────────────────────────────────────────────────
+
+async fn search_bing(query: &str, api_key: &str, market: Option<&str>, max: usize, page: usize) -> Result<WebSearchResponse, String> {
+    if api_key.is_empty() {
+        return Err("Bing Search requires an Azure resource key (Bing Search v7 in the Azure portal)".into());
+    }
+    let (mkt, lang) = bing_market(market);
+
+    let client = http_client().map_err(|e| e.to_string())?;
+    let resp: Value = client
+        .get("https://api.bing.microsoft.com/v7.0/search")
+        .header("Ocp-Apim-Subscription-Key", api_key)
+        .query(&[
+            ("q", query),
+            ("count", &max.to_string()),
+            ("offset", &page_offset(page, max).to_string()),
+            ("mkt", &mkt),
+            ("setLang", &lang),
+            ("responseFilter", "Webpages"),
+            ("textFormat", "Raw"),
+        ])
+        .send().await.map_err(|e| format!("Bing Search error: {}", e))?
+        .json().await.map_err(|e| e.to_string())?;
+
+    if let Some(msg) = resp["error"]["message"].as_str() {
+        return Err(format!("Bing API error: {}", msg));
+    }
+
+    let results = resp["webPages"]["value"]
+        .as_array().unwrap_or(&vec![])
+        .iter().take(max)
+        .map(|r| SearchResult {
+            title:   r["name"].as_str().unwrap_or("").to_string(),
+            url:     r["url"].as_str().unwrap_or("").to_string(),
+            snippet: r["snippet"].as_str().unwrap_or("").to_string(),
+            content: None,
+        })
+        .collect();
+
+    Ok(WebSearchResponse { results, backend: "bing".into(), query: query.into() })
+}
+
+/// (mkt, setLang) for a market like "de-de" → ("de-DE", "de"); en-US when
+/// missing or malformed.
+fn bing_market(market: Option<&str>) -> (String, String) {
+    let parsed = market
+        .map(str::trim)
+        .and_then(|m| m.split_once(['-', '_']))
+        .filter(|(lang, region)| {
+            lang.len() == 2 && region.len() == 2
+                && lang.chars().chain(region.chars()).all(|c| c.is_ascii_alphabetic())
+        });
+    match parsed {
+        Some((lang, region)) => {
+            let lang = lang.to_ascii_lowercase();
+            (format!("{}-{}", lang, region.to_ascii_uppercase()), lang)
+        }
+        None => ("en-US".into(), "en".into()),
+    }
+}
+
+// ── Google via Serper / SerpAPI ──────────────────────────────────────────
+
+async fn search_serper(query: &str, api_key: &str, market: Option<&str>, max: usize, page: usize) -> Result<WebSearchResponse, String> {
+    if api_key.is_empty() {
+        return Err("Serper requires an API key (serper.dev)".into());
+    }
+    let (mkt, lang) = bing_market(market);
+    let region = mkt.rsplit('-').next().unwrap_or("US").to_ascii_lowercase();
+
+    let client = http_client().map_err(|e| e.to_string())?;
+    let resp: Value = client
+        .post("https://google.serper.dev/search")
+        .header("X-API-KEY", api_key)
+        .json(&serde_json::json!({ "q": query, "num": max, "page": page, "gl": region, "hl": lang }))
+        .send().await.map_err(|e| format!("Serper error: {}", e))?
+        .json().await.map_err(|e| e.to_string())?;
+
+    if let Some(msg) = resp["message"].as_str() {
+        return Err(format!("Serper API error: {}", msg));
+    }
+    let results = google_results(&resp["answerBox"], &resp["knowledgeGraph"], &resp["organic"], max);
+    Ok(WebSearchResponse { results, backend: "serper".into(), query: query.into() })
+}
+
+async fn search_serpapi(query: &str, api_key: &str, market: Option<&str>, max: usize, page: usize) -> Result<WebSearchResponse, String> {
+    if api_key.is_empty() {
+        return Err("SerpAPI requires an API key (serpapi.com)".into());
+    }
+    let (mkt, lang) = bing_market(market);
+    let region = mkt.rsplit('-').next().unwrap_or("US").to_ascii_lowercase();
+
+    let client = http_client().map_err(|e| e.to_string())?;
+    let resp: Value = client
+        .get("https://serpapi.com/search.json")
+        .query(&[
+            ("engine", "google"),
+            ("q", query),
+            ("num", &max.to_string()),
+            ("start", &page_offset(page, max).to_string()),
+            ("gl", &region),
+            ("hl", &lang),
+            ("api_key", api_key),
+        ])
+        .send().await.map_err(|e| format!("SerpAPI error: {}", e))?
+        .json().await.map_err(|e| e.to_string())?;
+
+    if let Some(msg) = resp["error"].as_str() {
+        return Err(format!("SerpAPI error: {}", msg));
+    }
+    let results = google_results(&resp["answer_box"], &resp["knowledge_graph"], &resp["organic_results"], max);
+    Ok(WebSearchResponse { results, backend: "serpapi".into(), query: query.into() })
+}
+
+/// Organic Google results (`title` / `link` / `snippet`, the same in both
+/// APIs), led by the featured result when there is one.
+fn google_results(answer_box: &Value, knowledge_graph: &Value, organic: &Value, max: usize) -> Vec<SearchResult> {
+    let featured = google_featured(answer_box, knowledge_graph);
+    let organic = organic
+        .as_array().unwrap_or(&vec![])
+        .iter()
+        .map(|r| SearchResult {
+            title:   r["title"].as_str().unwrap_or("").to_string(),
+            url:     r["link"].as_str().unwrap_or("").to_string(),
+            snippet: r["snippet"].as_str().unwrap_or("").to_string(),
+            content: None,
+        })
+        .filter(|r| !r.url.is_empty())
+        .collect::<Vec<_>>();
+    featured.into_iter().chain(organic).take(max).collect()
+}
+
+/// Answer box and knowledge graph folded into one result: the direct answer
+/// first, then the entity description and its listed attributes.
+fn google_featured(answer_box: &Value, knowledge_graph: &Value) -> Option<SearchResult> {
+    let text = |v: &Value, key: &str| v[key].as_str().map(str::trim).filter(|s| !s.is_empty()).map(String::from);
+
+    let mut parts = Vec::new();
+    if let Some(answer) = text(answer_box, "answer").or_else(|| text(answer_box, "snippet")) {
+        parts.push(answer);
+    }
+    if let Some(description) = text(knowledge_graph, "description") {
+        parts.push(description);
+    }
+    if let Some(attributes) = knowledge_graph["attributes"].as_object() {
+        let lines: Vec<String> = attributes
+            .iter()
+            .filter_map(|(k, v)| v.as_str().map(|v| format!("{}: {}", k, v)))
+            .take(6)
+            .collect();
+        if !lines.is_empty() {
+            parts.push(lines.join("\n"));
+        }
+    }
+    if parts.is_empty() {
+        return None;
+    }
+
+    let kg_title = text(knowledge_graph, "title").map(|t| match text(knowledge_graph, "type") {
+        Some(kind) => format!("{} ({})", t, kind),
+        None => t,
+    });
+    let title = text(answer_box, "title").or(kg_title).unwrap_or_else(|| "Google answer".into());
+    let url = text(answer_box, "link")
+        .or_else(|| text(knowledge_graph, "descriptionLink"))
+        .or_else(|| text(&knowledge_graph["source"], "link"))
+        .or_else(|| text(knowledge_graph, "website"))
+        .unwrap_or_default();
+    Some(SearchResult { title, url, snippet: parts.join("\n\n"), content: None })
+}
+
+// ── Stack Exchange ───────────────────────────────────────────────────────
+
+const STACKEXCHANGE_API: &str = "https://api.stackexchange.com/2.3";
+const STACKEXCHANGE_SITE: &str = "stackoverflow";
+
+/// API filter adding `body_markdown` to answers, created once per run
+static ANSWER_FILTER: std::sync::OnceLock<String> = std::sync::OnceLock::new();
+
+async fn search_stackexchange(query: &str, api_key: &str, max: usize, page: usize) -> Result<WebSearchResponse, String> {
+    let mut params = vec![
+        ("q",        query.to_string()),
+        ("site",     STACKEXCHANGE_SITE.to_string()),
+        ("order",    "desc".to_string()),
+        ("sort",     "relevance".to_string()),
+        ("answers",  "1".to_string()),
+        ("pagesize", max.to_string()),
+        ("page",     page.to_string()),
+    ];
+    if !api_key.is_empty() {
+        params.push(("key", api_key.to_string()));
+    }
+    let resp = stackexchange_get("/search/advanced", &params).await?;
+    let questions: Vec<&Value> = resp["items"].as_array().into_iter().flatten().take(max).collect();
+    if questions.is_empty() {
+        return Ok(WebSearchResponse { results: vec![], backend: "stackexchange".into(), query: query.into() });
+    }
+
+    // One request for the answers of every question on the page
+    let ids: Vec<String> = questions.iter().filter_map(|q| q["question_id"].as_u64()).map(|id| id.to_string()).collect();
+    let mut params = vec![
+        ("site",     STACKEXCHANGE_SITE.to_string()),
+        ("order",    "desc".to_string()),
+        ("sort",     "votes".to_string()),
+        ("pagesize", "100".to_string()),
+        ("filter",   answer_filter().await?),
+    ];
+    if !api_key.is_empty() {
+        params.push(("key", api_key.to_string()));
+    }
+    let answers = match stackexchange_get(&format!("/questions/{}/answers", ids.join(";")), &params).await {
+        Ok(a) => a,
+        Err(e) => {
+            log::warn!("web_search: {} — returning questions without answers", e);
+            Value::Null
+        }
+    };
+
+    let results = questions.iter().map(|q| stackexchange_result(q, &answers)).collect();
+    Ok(WebSearchResponse { results, backend: "stackexchange".into(), query: query.into() })
+}
+
+/// The API gzips every response, whatever Accept-Encoding says.
+async fn stackexchange_get(path: &str, params: &[(&str, String)]) -> Result<Value, String> {
+    let client = http_client().map_err(|e| e.to_string())?;
+    let bytes = client
+        .get(format!("{}{}", STACKEXCHANGE_API, path))
+        .query(params)
+        .send().await.map_err(|e| format!("Stack Exchange error: {}", e))?
+        .bytes().await.map_err(|e| format!("Stack Exchange error: {}", e))?;
+    let body = if bytes.starts_with(&[0x1f, 0x8b]) {
+        let mut out = Vec::new();
+        std::io::Read::read_to_end(&mut flate2::read::GzDecoder::new(&bytes[..]), &mut out)
+            .map_err(|e| format!("Stack Exchange error: bad gzip body: {}", e))?;
+        out
+    } else {
+        bytes.to_vec()
+    };
+    let resp: Value = serde_json::from_slice(&body).map_err(|e| format!("Stack Exchange error: {}", e))?;
+    if let Some(msg) = resp["error_message"].as_str() {
+        return Err(format!("Stack Exchange error: {}", msg));
+    }
+    Ok(resp)
+}
+
+async fn answer_filter() -> Result<String, String> {
+    if let Some(f) = ANSWER_FILTER.get() {
+        return Ok(f.clone());
+    }
+    let resp = stackexchange_get("/filters/create", &[
+        ("include", "answer.body_markdown".to_string()),
+        ("base",    "default".to_string()),
+        ("unsafe",  "false".to_string()),
+    ]).await?;
+    let filter = resp["items"][0]["filter"].as_str().ok_or("Stack Exchange error: no filter returned")?;
+    Ok(ANSWER_FILTER.get_or_init(|| filter.to_string()).clone())
+}
+
+/// A question with its accepted answer, or the top-voted one, as content.
+fn stackexchange_result(question: &Value, answers: &Value) -> SearchResult {
+    let id = question["question_id"].as_u64();
+    let mut own: Vec<&Value> = answers["items"]
+        .as_array().into_iter().flatten()
+        .filter(|a| a["question_id"].as_u64() == id)
+        .collect();
+    own.sort_by_key(|a| (!a["is_accepted"].as_bool().unwrap_or(false), -a["score"].as_i64().unwrap_or(0)));
+
+    let tags: Vec<&str> = question["tags"].as_array().into_iter().flatten().filter_map(Value::as_str).collect();
+    let content = own.first().and_then(|a| {
+        let body = decode_entities(a["body_markdown"].as_str()?);
+        let label = if a["is_accepted"].as_bool().unwrap_or(false) { "Accepted answer" } else { "Top answer" };
+        Some(format!("{} (score {}):\n\n{}", label, a["score"].as_i64().unwrap_or(0), body.trim()))
+    });
+    SearchResult {
+        title:   decode_entities(question["title"].as_str().unwrap_or("")),
+        url:     question["link"].as_str().unwrap_or("").to_string(),
+        snippet: format!(
+            "{} votes · {} answers{} · {}",
+            question["score"].as_i64().unwrap_or(0),
+            question["answer_count"].as_u64().unwrap_or(0),
+            if question["accepted_answer_id"].is_u64() { " (accepted)" } else { "" },
+            tags.join(", "),
+        ),
+        content,
+    }
+}
+
 // ── SearXNG ──────────────────────────────────────────────────────────────
 
-async fn search_searxng(query: &str, base_url: &str, max: usize) -> Result<WebSearchResponse, String> {
+async fn search_searxng(query: &str, base_url: &str, market: Option<&str>, max: usize, page: usize) -> Result<WebSearchResponse, String> {
+    let language = market.map_or_else(|| "en".to_string(), |m| bing_market(Some(m)).0);
     let client = http_client().map_err(|e| e.to_string())?;
     let url = format!("{}/search", base_url.trim_end_matches('/'));
 
@@ -309,8 +1213,9 @@ async fn search_searxng(query: &str, base_url: &str, max: usize) -> Result<WebSe
         .query(&[
             ("q",        query),
             ("format",   "json"),
-            ("language", "en"),
+            ("language", &language),
             ("engines",  "google,bing,duckduckgo,brave"),
+            ("pageno",   &page.to_string()),
         ])
         .send().await
         .map_err(|e| format!("SearXNG error: {} — is the server running at {}?", e, base_url))?
@@ -331,17 +1236,168 @@ async fn search_searxng(query: &str, base_url: &str, max: usize) -> Result<WebSe
     Ok(WebSearchResponse { results, backend: "searxng".into(), query: query.into() })
 }
 
+// ── Image search ─────────────────────────────────────────────────────────
+
+async fn search_brave_images(query: &str, api_key: &str, max: usize) -> Result<Vec<ImageSearchResult>, String> {
+    if api_key.is_empty() {
+        return Err("Brave Search requires an API key (free tier at brave.com/search/api)".into());
+    }
+    let client = http_client().map_err(|e| e.to_string())?;
+    let resp: Value = client
+        .get("https://api.search.brave.com/res/v1/images/search")
+        .header("Accept",               "application/json")
+        .header("X-Subscription-Token", api_key)
+        .query(&[("q", query), ("count", &max.to_string()), ("safesearch", "strict")])
+        .send().await.map_err(|e| format!("Brave Search error: {}", e))?
+        .json().await.map_err(|e| e.to_string())?;
+
+    if let Some(msg) = resp["message"].as_str() {
+        return Err(format!("Brave API error: {}", msg));
+    }
+    Ok(parse_brave_images(&resp, max))
+}
+
+fn parse_brave_images(resp: &Value, max: usize) -> Vec<ImageSearchResult> {
+    resp["results"]
+        .as_array().unwrap_or(&vec![])
+        .iter()
+        .filter_map(|r| {
+            let image_url = r["properties"]["url"].as_str().filter(|u| !u.is_empty())?;
+            Some(ImageSearchResult {
+                title:            r["title"].as_str().unwrap_or("").to_string(),
+                page_url:         r["url"].as_str().unwrap_or("").to_string(),
+                image_url:        image_url.to_string(),
+                thumbnail_url:    r["thumbnail"]["src"].as_str().unwrap_or(image_url).to_string(),
+                thumbnail_base64: None,
+                width:            r["properties"]["width"].as_u64().map(|w| w as u32),
+                height:           r["properties"]["height"].as_u64().map(|h| h as u32),
+            })
+        })
+        .take(max)
+        .collect()
+}
+
+async fn search_searxng_images(query: &str, base_url: &str, max: usize) -> Result<Vec<ImageSearchResult>, String> {
+    let client = http_client().map_err(|e| e.to_string())?;
+    let url = format!("{}/search", base_url.trim_end_matches('/'));
+    let resp: Value = client
+        .get(&url)
+        .query(&[("q", query), ("format", "json"), ("categories", "images"), ("safesearch", "2")])
+        .send().await
+        .map_err(|e| format!("SearXNG error: {} — is the server running at {}?", e, base_url))?
+        .json().await
+        .map_err(|e| format!("SearXNG returned invalid JSON (non-JSON format?): {}", e))?;
+    Ok(parse_searxng_images(&resp, max))
+}
+
+fn parse_searxng_images(resp: &Value, max: usize) -> Vec<ImageSearchResult> {
+    resp["results"]
+        .as_array().unwrap_or(&vec![])
+        .iter()
+        .filter_map(|r| {
+            let image_url = r["img_src"].as_str().filter(|u| !u.is_empty())?;
+            // "1920 x 1080" / "1920×1080" when the engine reports it
+            let (width, height) = r["resolution"]
+                .as_str()
+                .and_then(|res| res.split_once(['x', '×']))
+                .and_then(|(w, h)| Some((w.trim().parse::<u32>().ok()?, h.trim().parse::<u32>().ok()?)))
+                .unzip();
+            Some(ImageSearchResult {
+                title:            r["title"].as_str().unwrap_or("").to_string(),
+                page_url:         r["url"].as_str().unwrap_or("").to_string(),
+                image_url:        image_url.to_string(),
+                thumbnail_url:    r["thumbnail_src"].as_str().filter(|t| !t.is_empty()).unwrap_or(image_url).to_string(),
+                thumbnail_base64: None,
+                width,
+                height,
+            })
+        })
+        .take(max)
+        .collect()
+}
+
+/// Fetches every thumbnail in parallel; ones that fail or aren't images keep
+/// only their URL.
+async fn inline_thumbnails(mut results: Vec<ImageSearchResult>) -> Vec<ImageSearchResult> {
+    use base64::{engine::general_purpose, Engine};
+    use tokio::task::JoinSet;
+    let Ok(client) = http_client_page() else { return results };
+    let mut set: JoinSet<(usize, Option<String>)> = JoinSet::new();
+
+    for (i, r) in results.iter().enumerate() {
+        let (client, url) = (client.clone(), r.thumbnail_url.clone());
+        set.spawn(async move {
+            let bytes = match client.get(&url).send().await {
+                Ok(resp) if resp.status().is_success() => resp.bytes().await.ok(),
+                _ => None,
+            };
+            let data_uri = bytes
+                .filter(|b| b.len() <= MAX_THUMBNAIL_BYTES)
+                .and_then(|b| {
+                    let mime = match image::guess_format(&b).ok()? {
+                        image::ImageFormat::Png  => "image/png",
+                        image::ImageFormat::Jpeg => "image/jpeg",
+                        image::ImageFormat::WebP => "image/webp",
+                        image::ImageFormat::Gif  => "image/gif",
+                        _ => return None,
+                    };
+                    Some(format!("data:{};base64,{}", mime, general_purpose::STANDARD.encode(&b)))
+                });
+            (i, data_uri)
+        });
+    }
+    // A failed download leaves only its own result without a thumbnail
+    while let Some(joined) = set.join_next().await {
+        match joined {
+            Ok((idx, data_uri)) => results[idx].thumbnail_base64 = data_uri,
+            Err(e)              => log::warn!("thumbnail task failed: {}", e),
+        }
+    }
+    results
+}
+
 // ── Page content fetcher ─────────────────────────────────────────────────
 
-async fn fetch_page_text(url: &str, max_chars: usize) -> Result<String, String> {
+/// User-Agent override for page fetches; None keeps the browser default
+static FETCH_USER_AGENT: std::sync::Mutex<Option<String>> = std::sync::Mutex::new(None);
+
+/// Set the User-Agent page fetches send (fetch_url_content, fetched search
+/// results). Some sites serve full text only to their own crawler or a
+/// specific browser; None / empty restores the default.
+#[tauri::command]
+pub fn set_fetch_user_agent(user_agent: Option<String>) {
+    if let Ok(mut ua) = FETCH_USER_AGENT.lock() {
+        *ua = user_agent.map(|u| u.trim().to_string()).filter(|u| !u.is_empty());
+    }
+}
+
+fn with_user_agent(req: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+    match FETCH_USER_AGENT.lock().ok().and_then(|ua| ua.clone()) {
+        Some(ua) => req.header(reqwest::header::USER_AGENT, ua),
+        None     => req,
+    }
+}
+
+pub(crate) async fn fetch_page_text(url: &str, max_chars: usize) -> Result<String, String> {
+    fetch_page(url, max_chars, HeaderMap::new()).await.map(|(text, _)| text)
+}
+
+/// Page text plus the response's Set-Cookie values.
+async fn fetch_page(url: &str, max_chars: usize, headers: HeaderMap) -> Result<(String, Vec<String>), String> {
     let client = http_client_page().map_err(|e| e.to_string())?;
 
-    let response = client
-        .get(url)
+    let response = with_user_agent(client.get(url))
         .header("Accept", "text/html,application/xhtml+xml,text/plain")
+        .headers(headers)
         .send().await
         .map_err(|e| format!("Fetch failed for {}: {}", url, e))?;
 
+    let set_cookies: Vec<String> = response.headers()
+        .get_all(reqwest::header::SET_COOKIE)
+        .iter()
+        .filter_map(|v| v.to_str().ok().map(str::to_string))
+        .collect();
+
     let ct = response.headers()
         .get("content-type")
         .and_then(|v| v.to_str().ok())
@@ -357,28 +1413,164 @@ async fn fetch_page_text(url: &str, max_chars: usize) -> Result<String, String>
 
     let text = if ct.contains("json") { html } else { html_to_text(&html) };
 
-    Ok(if text.chars().count() > max_chars {
-        text.chars().take(max_chars).collect::<String>() + "\n[... truncated ...]"
-    } else {
-        text
-    })
+    Ok((truncate_text(text, max_chars), set_cookies))
 }
 
-/// Fetch content for the first `fetch_n` results in parallel.
-async fn fetch_results_content(mut results: Vec<SearchResult>, fetch_n: usize) -> Vec<SearchResult> {
-    use tokio::task::JoinSet;
-    let mut set: JoinSet<(usize, Result<String, String>)> = JoinSet::new();
+/// Caller-supplied headers over the stored cookie; rejects names or values
+/// that aren't valid HTTP.
+fn request_headers(custom: std::collections::HashMap<String, String>, cookie: Option<String>) -> Result<HeaderMap, String> {
+    let mut map = HeaderMap::new();
+    if let Some(cookie) = cookie {
+        let value = HeaderValue::from_str(&cookie).map_err(|_| "Stored cookie is not a valid header value".to_string())?;
+        map.insert(reqwest::header::COOKIE, value);
+    }
+    for (name, value) in custom {
+        let name = HeaderName::from_bytes(name.trim().as_bytes()).map_err(|_| format!("Invalid header name: {}", name))?;
+        let value = HeaderValue::from_str(value.trim()).map_err(|_| format!("Invalid value for header {}", name))?;
+        map.insert(name, value);
+    }
+    Ok(map)
+}
+
+// ── llms.txt detection ───────────────────────────────────────────────────
+
+/// Paths that count as a docs-site entry point. Deep links keep the normal
+/// HTML pipeline — the curated file describes the whole site, not one page.
+static DOCS_LANDING_PATHS: &[&str] = &["", "docs", "documentation", "guide", "reference"];
+
+/// True when `url` points at a site root or a top-level docs section.
+fn is_docs_landing(url: &str) -> bool {
+    let Some((_, rest)) = url.split_once("://") else { return false };
+    let path = rest
+        .split(['?', '#'])
+        .next()
+        .unwrap_or("")
+        .split_once('/')
+        .map(|(_, p)| p)
+        .unwrap_or("")
+        .trim_matches('/');
+    DOCS_LANDING_PATHS.contains(&path.to_ascii_lowercase().as_str())
+}
+
+/// Scheme + host (+ port) of `url`, e.g. "https://docs.example.com".
+fn url_origin(url: &str) -> Option<String> {
+    let (scheme, rest) = url.split_once("://")?;
+    let host = rest.split(['/', '?', '#']).next()?;
+    if host.is_empty() { return None; }
+    Some(format!("{}://{}", scheme, host))
+}
 
-    for (i, r) in results.iter().enumerate().take(fetch_n) {
-        let url = r.url.clone();
-        set.spawn(async move { (i, fetch_page_text(&url, 3_500).await) });
+/// The curated text at the site origin: `llms-full.txt` when it fits in
+/// `max_chars`, else the `llms.txt` index (a complete overview beats the
+/// first pages of the full dump), else the truncated full file.
+async fn fetch_llms_txt(url: &str, max_chars: usize, headers: &HeaderMap) -> Option<String> {
+    let origin = url_origin(url)?;
+    let client = http_client_page().ok()?;
+
+    let full = fetch_llms_file(&client, &format!("{}/llms-full.txt", origin), headers).await;
+    let fits = full.as_ref().is_some_and(|t| t.chars().count() <= max_chars);
+    let index = if fits { None } else { fetch_llms_file(&client, &format!("{}/llms.txt", origin), headers).await };
+    let (name, text) = pick_llms_text(full, index, max_chars)?;
+    log::info!("fetch_url_content: using {}/{} for {}", origin, name, url);
+    Some(text)
+}
+
+/// Which of the two files fetch_llms_txt returns, truncated to `max_chars`.
+fn pick_llms_text(full: Option<String>, index: Option<String>, max_chars: usize) -> Option<(&'static str, String)> {
+    let (name, text) = match (full, index) {
+        (Some(full), _) if full.chars().count() <= max_chars => ("llms-full.txt", full),
+        (_, Some(index))   => ("llms.txt", index),
+        (Some(full), None) => ("llms-full.txt", full),
+        (None, None)       => return None,
+    };
+    Some((name, truncate_text(text, max_chars)))
+}
+
+/// Body of a plain-text file, or None when it is missing or the server
+/// answers with HTML (SPA catch-all routes return 200 + index.html for
+/// unknown paths).
+async fn fetch_llms_file(client: &Client, candidate: &str, headers: &HeaderMap) -> Option<String> {
+    let request = with_user_agent(client.get(candidate))
+        .header("Accept", "text/plain,text/markdown")
+        .headers(headers.clone());
+    let resp = match request.send().await {
+        Ok(r) if r.status().is_success() => r,
+        Ok(_)  => return None,
+        Err(e) => { log::debug!("llms.txt probe {}: {}", candidate, e); return None; }
+    };
+    let ct = resp.headers()
+        .get("content-type")
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("");
+    if ct.contains("html") { return None; }
+
+    let text = resp.text().await.ok()?;
+    let trimmed = text.trim_start();
+    (!trimmed.is_empty() && !trimmed.starts_with('<')).then_some(text)
+}
+
+/// Page fetches in flight across all searches at once
+const MAX_CONCURRENT_FETCHES: usize = 6;
+static FETCH_SLOTS: tokio::sync::Semaphore = tokio::sync::Semaphore::const_new(MAX_CONCURRENT_FETCHES);
+
+/// How many result pages to fetch, how many at a time, and for how long.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct FetchPlan {
+    pub count:       usize,
+    pub concurrency: usize,
+    pub budget:      std::time::Duration,
+}
+
+impl Default for FetchPlan {
+    fn default() -> Self {
+        Self { count: 3, concurrency: 3, budget: std::time::Duration::from_secs(12) }
     }
+}
 
-    while let Some(Ok((idx, res))) = set.join_next().await {
-        match res {
-            Ok(text) if !text.is_empty() => { results[idx].content = Some(text); }
-            Err(e) => log::debug!("Page fetch [{}]: {}", idx, e),
-            _ => {}
+fn fetch_plan(req: &WebSearchRequest) -> FetchPlan {
+    let default = FetchPlan::default();
+    FetchPlan {
+        count:       req.fetch_count.unwrap_or(default.count).min(10),
+        concurrency: req.fetch_concurrency.unwrap_or(default.concurrency).clamp(1, MAX_CONCURRENT_FETCHES),
+        budget:      req.fetch_budget_ms.map_or(default.budget, |ms| std::time::Duration::from_millis(ms.clamp(500, 60_000))),
+    }
+}
+
+/// Fetch content for the first `plan.count` results, `plan.concurrency` at
+/// a time. Fetches still running when the budget expires are dropped
+/// (aborting their requests); those results keep only their snippet.
+pub(crate) async fn fetch_results_content(mut results: Vec<SearchResult>, plan: FetchPlan) -> Vec<SearchResult> {
+    use futures_util::StreamExt;
+
+    let jobs: Vec<(usize, String)> = results.iter().take(plan.count).map(|r| r.url.clone()).enumerate().collect();
+    let total = jobs.len();
+    let mut pending = futures_util::stream::iter(jobs.into_iter().map(|(i, url)| async move {
+        let _slot = FETCH_SLOTS.acquire().await;
+        (i, fetch_page_text(&url, 3_500).await)
+    }))
+    .buffer_unordered(plan.concurrency.max(1));
+
+    let deadline = tokio::time::sleep(plan.budget);
+    tokio::pin!(deadline);
+    let mut finished = 0;
+    loop {
+        tokio::select! {
+            next = pending.next() => match next {
+                Some((idx, res)) => {
+                    finished += 1;
+                    match res {
+                        Ok(text) if !text.is_empty() => { results[idx].content = Some(text); }
+                        Err(e) => log::debug!("Page fetch [{}]: {}", idx, e),
+                        _ => {}
+                    }
+                }
+                None => break,
+            },
+            _ = &mut deadline => {
+                log::info!("Page fetch budget of {} ms spent — {} of {} pages fetched",
+                           plan.budget.as_millis(), finished, total);
+                break;
+            }
         }
     }
     results
@@ -424,6 +1616,25 @@ fn strip_html_tags(s: &str) -> String {
         .to_string()
 }
 
+/// Undo the HTML escaping of API text fields (`&amp;` last, so "&amp;lt;"
+/// stays "&lt;").
+fn decode_entities(s: &str) -> String {
+    s.replace("&lt;",   "<")
+     .replace("&gt;",   ">")
+     .replace("&quot;", "\"")
+     .replace("&#39;",  "'")
+     .replace("&amp;",  "&")
+}
+
+/// Cut `text` to `max_chars` characters, appending a truncation marker.
+pub(crate) fn truncate_text(text: String, max_chars: usize) -> String {
+    if text.chars().count() > max_chars {
+        text.chars().take(max_chars).collect::<String>() + "\n[... truncated ...]"
+    } else {
+        text
+    }
+}
+
 fn percent_decode(s: &str) -> String {
     let s = s.replace('+', " ");
     let mut out = String::with_capacity(s.len());
@@ -445,3 +1656,234 @@ fn percent_decode(s: &str) -> String {
     }
     out
 }
+
+// ── Unit tests ─────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_page_offset() {
+        assert_eq!(page_offset(1, 8), 0);
+        assert_eq!(page_offset(3, 8), 16);
+        assert_eq!(page_offset(0, 8), 0);
+    }
+
+    #[test]
+    fn test_domain_filters() {
+        let include = normalize_domains(&["https://www.Docs.rs/std".to_string(), " ".to_string(), "rust-lang.org".to_string()]);
+        assert_eq!(include, vec!["docs.rs", "rust-lang.org"]);
+        let exclude = normalize_domains(&["blog.rust-lang.org".to_string()]);
+
+        assert_eq!(with_site_operators("tokio select", &include[..1], &[]), "tokio select site:docs.rs");
+        assert_eq!(
+            with_site_operators("tokio", &include, &exclude),
+            "tokio (site:docs.rs OR site:rust-lang.org) -site:blog.rust-lang.org"
+        );
+
+        assert!(domain_allowed("https://docs.rs/tokio", &include, &exclude));
+        assert!(domain_allowed("https://doc.rust-lang.org/std", &include, &exclude));
+        assert!(!domain_allowed("https://blog.rust-lang.org/2024", &include, &exclude));
+        assert!(!domain_allowed("https://notdocs.rs/x", &include, &exclude));
+        assert!(!domain_allowed("", &include, &[]));
+        assert!(domain_allowed("", &[], &[]));
+    }
+
+    #[test]
+    fn test_stackexchange_prefers_accepted_answer() {
+        let question = serde_json::json!({
+            "question_id": 1, "title": "Why &quot;borrowed value&quot;?", "link": "https://stackoverflow.com/q/1",
+            "score": 12, "answer_count": 2, "accepted_answer_id": 11, "tags": ["rust", "borrow-checker"],
+        });
+        let answers = serde_json::json!({ "items": [
+            { "question_id": 1, "score": 40, "is_accepted": false, "body_markdown": "Top" },
+            { "question_id": 1, "score": 9,  "is_accepted": true,  "body_markdown": "Use `&amp;mut T` &lt;here&gt;" },
+            { "question_id": 2, "score": 99, "is_accepted": true,  "body_markdown": "Other question" },
+        ]});
+        let r = stackexchange_result(&question, &answers);
+        assert_eq!(r.title, "Why \"borrowed value\"?");
+        assert_eq!(r.snippet, "12 votes · 2 answers (accepted) · rust, borrow-checker");
+        assert_eq!(r.content.as_deref(), Some("Accepted answer (score 9):\n\nUse `&mut T` <here>"));
+
+        let r = stackexchange_result(&question, &Value::Null);
+        assert_eq!(r.content, None);
+    }
+
+    #[test]
+    fn test_parse_rewritten_queries() {
+        let reply = "Here are the queries:\n1. tauri v1 hidden window\n2) \"WebviewWindow visible false\"\n- Tauri v1 hidden window\n* wry headless";
+        assert_eq!(
+            parse_rewritten_queries(reply),
+            vec!["tauri v1 hidden window", "WebviewWindow visible false", "wry headless"]
+        );
+        assert_eq!(parse_rewritten_queries("```json\n[\"a b\", \"c\"]\n```"), vec!["a b", "c"]);
+        assert_eq!(parse_rewritten_queries("2024 rust release notes"), vec!["2024 rust release notes"]);
+        assert!(parse_rewritten_queries("  \n").is_empty());
+    }
+
+    #[test]
+    fn test_canonical_url() {
+        let base = canonical_url("https://example.com/post/42");
+        assert_eq!(base, "example.com/post/42");
+        for variant in [
+            "http://www.example.com/post/42/",
+            "https://m.example.com/post/42#comments",
+            "https://example.com/post/42/amp?utm_source=x&amp=1",
+            "https://www.google.com/amp/s/example.com/post/42",
+            "https://example-com.cdn.ampproject.org/c/s/example.com/post/42",
+        ] {
+            assert_eq!(canonical_url(variant), base, "{}", variant);
+        }
+        assert_eq!(canonical_url("https://example.com/search?q=rust&utm_medium=a"), "example.com/search?q=rust");
+        assert_ne!(canonical_url("https://example.com/Post/42"), base);
+    }
+
+    #[test]
+    fn test_rerank_results() {
+        let result = |title: &str, snippet: &str| SearchResult {
+            title: title.into(), url: format!("https://{}.dev", title.len()), snippet: snippet.into(), content: None,
+        };
+        let results = vec![
+            result("Cooking pasta", "Boil water and add salt."),
+            result("Rust news", "Weekly updates."),
+            result("Rust borrow checker explained", "How the borrow checker tracks lifetimes in Rust."),
+        ];
+        let ranked = rerank_results(results, "rust borrow checker");
+        assert_eq!(ranked[0].title, "Rust borrow checker explained");
+        assert_eq!(ranked.len(), 3);
+
+        let same = vec![result("a", ""), result("bb", "")];
+        assert_eq!(rerank_results(same, "zzz").iter().map(|r| r.title.as_str()).collect::<Vec<_>>(), vec!["a", "bb"]);
+    }
+
+    #[test]
+    fn test_request_market() {
+        let req = |market: Option<&str>, language: Option<&str>, region: Option<&str>| WebSearchRequest {
+            query: String::new(), backend: "brave".into(), api_key: None, base_url: None, max_results: None,
+            fetch_content: None, preprocess: None, market: market.map(Into::into),
+            language: language.map(Into::into), region: region.map(Into::into), cache_ttl_secs: None,
+            page: None, include_domains: vec![], exclude_domains: vec![], rewrite: None, rerank: None,
+            fetch_count: None, fetch_concurrency: None, fetch_budget_ms: None,
+        };
+        assert_eq!(request_market(&req(None, None, None)), None);
+        assert_eq!(request_market(&req(Some("fr-FR"), None, None)).as_deref(), Some("fr-FR"));
+        assert_eq!(request_market(&req(Some("de-DE"), None, Some("at"))).as_deref(), Some("de-AT"));
+        assert_eq!(request_market(&req(None, Some("JA"), None)).as_deref(), Some("ja-US"));
+    }
+
+    #[test]
+    fn test_request_headers() {
+        let custom = std::collections::HashMap::from([
+            ("Authorization".to_string(), "Bearer abc".to_string()),
+        ]);
+        let map = request_headers(custom, Some("session=1".into())).unwrap();
+        assert_eq!(map["authorization"], "Bearer abc");
+        assert_eq!(map["cookie"], "session=1");
+
+        // An explicit Cookie header replaces the stored one
+        let custom = std::collections::HashMap::from([("Cookie".to_string(), "session=2".to_string())]);
+        assert_eq!(request_headers(custom, Some("session=1".into())).unwrap()["cookie"], "session=2");
+
+        let bad = std::collections::HashMap::from([("Bad Name".to_string(), "x".to_string())]);
+        assert!(request_headers(bad, None).is_err());
+    }
+
+    #[test]
+    fn test_fetch_plan_clamps() {
+        let mut req: WebSearchRequest = serde_json::from_value(serde_json::json!({
+            "query": "q", "backend": "brave", "api_key": null, "base_url": null, "max_results": null,
+            "fetch_content": true, "preprocess": null,
+        })).unwrap();
+        assert_eq!(fetch_plan(&req), FetchPlan::default());
+        req.fetch_count = Some(50);
+        req.fetch_concurrency = Some(0);
+        req.fetch_budget_ms = Some(10);
+        let plan = fetch_plan(&req);
+        assert_eq!((plan.count, plan.concurrency, plan.budget.as_millis()), (10, 1, 500));
+    }
+
+    #[test]
+    fn test_bing_market() {
+        assert_eq!(bing_market(Some("de_de")), ("de-DE".to_string(), "de".to_string()));
+        assert_eq!(bing_market(Some("english")), ("en-US".to_string(), "en".to_string()));
+        assert_eq!(bing_market(None).0, "en-US");
+    }
+
+    #[test]
+    fn test_google_featured_result_leads() {
+        let answer_box = json!({ "title": "Eiffel Tower height", "answer": "330 m", "link": "https://example.org/eiffel" });
+        let kg = json!({
+            "title": "Eiffel Tower", "type": "Tower",
+            "description": "Wrought-iron lattice tower in Paris.",
+            "attributes": { "Opened": "31 March 1889" },
+        });
+        let organic = json!([{ "title": "Eiffel Tower - Wikipedia", "link": "https://en.wikipedia.org/wiki/Eiffel_Tower", "snippet": "…" }]);
+        let results = google_results(&answer_box, &kg, &organic, 5);
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].title, "Eiffel Tower height");
+        assert_eq!(results[0].url, "https://example.org/eiffel");
+        assert_eq!(results[0].snippet, "330 m\n\nWrought-iron lattice tower in Paris.\n\nOpened: 31 March 1889");
+        assert_eq!(results[1].url, "https://en.wikipedia.org/wiki/Eiffel_Tower");
+
+        // SerpAPI knowledge graph: link under source, no answer box
+        let kg = json!({ "title": "Rust", "type": "Programming language", "description": "A language.", "source": { "link": "https://rust-lang.org" } });
+        let featured = google_featured(&Value::Null, &kg).unwrap();
+        assert_eq!((featured.title.as_str(), featured.url.as_str()), ("Rust (Programming language)", "https://rust-lang.org"));
+        assert!(google_featured(&Value::Null, &Value::Null).is_none());
+    }
+
+    #[test]
+    fn test_parse_image_results() {
+        let brave = json!({ "results": [
+            { "title": "Lighthouse", "url": "https://example.org/page",
+              "thumbnail": { "src": "https://imgs.search.brave.com/t.jpg" },
+              "properties": { "url": "https://example.org/full.jpg", "width": 1600, "height": 900 } },
+            { "title": "no image", "url": "https://example.org/x", "properties": {} },
+        ]});
+        let results = parse_brave_images(&brave, 10);
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].thumbnail_url, "https://imgs.search.brave.com/t.jpg");
+        assert_eq!((results[0].width, results[0].height), (Some(1600), Some(900)));
+
+        let searxng = json!({ "results": [
+            { "title": "A", "url": "https://a.example/p", "img_src": "https://a.example/i.png", "resolution": "800 x 600" },
+            { "title": "B", "url": "https://b.example/p", "img_src": "https://b.example/i.png", "thumbnail_src": "https://b.example/t.png" },
+        ]});
+        let results = parse_searxng_images(&searxng, 10);
+        assert_eq!((results[0].thumbnail_url.as_str(), results[0].width), ("https://a.example/i.png", Some(800)));
+        assert_eq!((results[1].thumbnail_url.as_str(), results[1].height), ("https://b.example/t.png", None));
+    }
+
+    #[test]
+    fn test_is_docs_landing() {
+        assert!(is_docs_landing("https://docs.example.com"));
+        assert!(is_docs_landing("https://example.com/"));
+        assert!(is_docs_landing("https://example.com/Docs/"));
+        assert!(is_docs_landing("https://example.com:8443/guide?lang=en#top"));
+        assert!(!is_docs_landing("https://example.com/docs/getting-started"));
+        assert!(!is_docs_landing("https://example.com/blog?page=docs"));
+        assert!(!is_docs_landing("example.com/docs"));
+    }
+
+    #[test]
+    fn test_url_origin() {
+        assert_eq!(url_origin("https://docs.example.com/a/b?q=1").as_deref(), Some("https://docs.example.com"));
+        assert_eq!(url_origin("http://localhost:3000#intro").as_deref(), Some("http://localhost:3000"));
+        assert_eq!(url_origin("https://example.com?x=/y").as_deref(), Some("https://example.com"));
+        assert_eq!(url_origin("https:///path"), None);
+        assert_eq!(url_origin("not a url"), None);
+    }
+
+    #[test]
+    fn test_pick_llms_text_prefers_what_fits() {
+        let (long, short) = ("x".repeat(50), "index".to_string());
+        assert_eq!(pick_llms_text(Some("full".into()), None, 10), Some(("llms-full.txt", "full".to_string())));
+        assert_eq!(pick_llms_text(Some(long.clone()), Some(short.clone()), 10), Some(("llms.txt", short)));
+        let (name, text) = pick_llms_text(Some(long), None, 10).unwrap();
+        assert_eq!(name, "llms-full.txt");
+        assert!(text.ends_with("[... truncated ...]"));
+        assert_eq!(pick_llms_text(None, None, 10), None);
+    }
+}
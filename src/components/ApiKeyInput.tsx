@@ -1,6 +1,7 @@
 import { useState } from "react";
 import { invoke } from "@tauri-apps/api/tauri";
 import { useAssistantStore, AiProvider } from "../store/assistantStore";
+import { errorMessage } from "../utils/errors";
 
 const MODELS: Record<AiProvider, string[]> = {
   openai: ["gpt-4o", "gpt-4o-mini", "gpt-4-turbo", "o1-preview"],
@@ -26,6 +27,14 @@ const MODELS: Record<AiProvider, string[]> = {
     "mistralai/mistral-large",
     "x-ai/grok-2-1212",
   ],
+  together: [
+    "meta-llama/Llama-3.3-70B-Instruct-Turbo",
+    "meta-llama/Llama-3.2-11B-Vision-Instruct-Turbo",
+    "Qwen/Qwen2.5-72B-Instruct-Turbo",
+    "Qwen/Qwen2-VL-72B-Instruct",
+    "deepseek-ai/DeepSeek-V3",
+    "mistralai/Mixtral-8x22B-Instruct-v0.1",
+  ],
   local: [
     "local-model",
     "llama-3.2-3b-instruct",
@@ -46,7 +55,10 @@ const LOCAL_PRESETS = [
 export default function ApiKeyInput() {
   const {
     apiKey, setApiKey, provider, setProvider, model, setModel,
+    fallbackModel, setFallbackModel,
     localUrl, setLocalUrl,
+    moderationMode, moderationTerms, moderationApiKey, setModeration,
+    moderationProfile, moderationProfiles, switchModerationProfile,
   } = useAssistantStore();
 
   const [open,         setOpen]         = useState(!apiKey && provider !== "local");
@@ -82,10 +94,10 @@ export default function ApiKeyInput() {
         <div className="px-3 pb-3 space-y-2.5">
           {/* Provider toggle — row 1: cloud providers */}
           <div className="flex gap-1.5">
-            {(["openai", "claude", "deepseek", "openrouter"] as AiProvider[]).map((p) => (
+            {(["openai", "claude", "deepseek", "openrouter", "together"] as AiProvider[]).map((p) => (
               <button
                 key={p}
-                onClick={() => { setProvider(p); setModel(MODELS[p][0]); }}
+                onClick={() => { setProvider(p); setModel(MODELS[p][0]); setFallbackModel(""); }}
                 className={[
                   "flex-1 py-1.5 rounded-lg text-[11px] font-semibold transition-colors",
                   provider === p
@@ -93,7 +105,7 @@ export default function ApiKeyInput() {
                     : "bg-white/10 text-white/50 hover:bg-white/20",
                 ].join(" ")}
               >
-                {p === "openai" ? "OpenAI" : p === "claude" ? "Claude" : p === "deepseek" ? "DeepSeek" : "OpenRouter"}
+                {p === "openai" ? "OpenAI" : p === "claude" ? "Claude" : p === "deepseek" ? "DeepSeek" : p === "together" ? "Together" : "OpenRouter"}
               </button>
             ))}
           </div>
@@ -173,7 +185,7 @@ export default function ApiKeyInput() {
                         setModel(models[0]);
                       }
                     } catch (e) {
-                      setDetectedModels([`Error: ${e}`]);
+                      setDetectedModels([`Error: ${errorMessage(e)}`]);
                     } finally {
                       setDetecting(false);
                     }
@@ -266,6 +278,21 @@ export default function ApiKeyInput() {
                 ))}
               </select>
 
+              {/* Retried with when a prompt overflows the model's context */}
+              <input
+                type="text"
+                value={fallbackModel}
+                onChange={(e) => setFallbackModel(e.target.value)}
+                list="fallback-model-suggestions"
+                placeholder="long-context fallback model (optional)"
+                spellCheck={false}
+                className="w-full bg-white/10 rounded-lg px-2.5 py-1.5 text-[11px]
+                  placeholder-white/25 focus:outline-none focus:ring-1 focus:ring-blue-500"
+              />
+              <datalist id="fallback-model-suggestions">
+                {MODELS[provider].filter((m) => m !== model).map((m) => <option key={m} value={m} />)}
+              </datalist>
+
               {/* API Key */}
               <div className="relative">
                 <input
@@ -298,6 +325,59 @@ export default function ApiKeyInput() {
               </p>
             </>
           )}
+
+          {/* ── Moderation of cloud-bound prompts ── */}
+          <div className="pt-1 space-y-1.5">
+            <div className="flex items-center justify-between">
+              <span className="text-[10px] text-white/50">Moderation before sending</span>
+              <select
+                value={moderationMode}
+                onChange={(e) => setModeration({ moderationMode: e.target.value as "off" | "local" | "openai" })}
+                className="bg-white/10 rounded px-1.5 py-0.5 text-[10px] focus:outline-none"
+              >
+                <option value="off" className="bg-gray-800">Off</option>
+                <option value="local" className="bg-gray-800">Blocked terms</option>
+                <option value="openai" className="bg-gray-800">OpenAI moderation</option>
+              </select>
+            </div>
+            {/* Settings are kept per profile — e.g. one per person on a shared machine */}
+            <input
+              key={moderationProfile}
+              type="text"
+              defaultValue={moderationProfile}
+              onBlur={(e) => switchModerationProfile(e.target.value)}
+              onKeyDown={(e) => { if (e.key === "Enter") e.currentTarget.blur(); }}
+              list="moderation-profiles"
+              placeholder="Profile"
+              spellCheck={false}
+              className="w-full bg-white/10 rounded-lg px-2.5 py-1.5 text-[11px]
+                placeholder-white/25 focus:outline-none focus:ring-1 focus:ring-blue-500"
+            />
+            <datalist id="moderation-profiles">
+              {Object.keys(moderationProfiles).filter((p) => p !== moderationProfile).map((p) => <option key={p} value={p} />)}
+            </datalist>
+            {moderationMode !== "off" && (
+              <input
+                type="text"
+                value={moderationTerms}
+                onChange={(e) => setModeration({ moderationTerms: e.target.value })}
+                placeholder="Blocked terms, comma-separated"
+                className="w-full bg-white/10 rounded-lg px-2.5 py-1.5 text-[11px]
+                  placeholder-white/25 focus:outline-none focus:ring-1 focus:ring-blue-500"
+              />
+            )}
+            {moderationMode === "openai" && (
+              <input
+                type="password"
+                value={moderationApiKey}
+                onChange={(e) => setModeration({ moderationApiKey: e.target.value })}
+                placeholder="OpenAI key for moderation (sk-…)"
+                spellCheck={false}
+                className="w-full bg-white/10 rounded-lg px-2.5 py-1.5 text-[11px]
+                  placeholder-white/25 focus:outline-none focus:ring-1 focus:ring-blue-500"
+              />
+            )}
+          </div>
         </div>
       )}
     </div>
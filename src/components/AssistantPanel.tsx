@@ -1,5 +1,6 @@
 import { useRef, useEffect, useState, useCallback } from "react";
 import { invoke } from "@tauri-apps/api/tauri";
+import { listen } from "@tauri-apps/api/event";
 import { useAssistantStore, LANGUAGE_NAMES } from "../store/assistantStore";
 import ApiKeyInput from "./ApiKeyInput";
 import ScreenshotPreview from "./ScreenshotPreview";
@@ -9,6 +10,7 @@ import WebSearchToggle from "./WebSearchToggle";
 import PromptLibrary from "./PromptLibrary";
 import CharacterImport from "./CharacterImport";
 import ChatHistory from "./ChatHistory";
+import CommandPalette from "./CommandPalette";
 import ImageGenSettings from "./ImageGenSettings";
 import StFormatText, { ST_FORMAT_EXAMPLES } from "./StFormatText";
 import FileTree from "./FileTree";
@@ -16,6 +18,15 @@ import ImageGallery from "./ImageGallery";
 
 type Tab = "chat" | "files" | "images";
 
+interface ShortcutEntry {
+  action: string;
+  title: string;
+  accelerator: string;
+  scope: "global" | "window";
+  customized: boolean;
+  registered: boolean;
+}
+
 const PANEL_WIDTH = 420;
 
 export default function AssistantPanel() {
@@ -33,27 +44,45 @@ export default function AssistantPanel() {
     isGeneratingImage, lastGeneratedImage, clearGeneratedImage, generateImage,
     isStreaming, streamingText,
     imageGenCustomPrompt,
-    sdGenProgress,
+    sdGenProgress, sdPreview, imageQueuePosition,
+    imageGenProvider, imageGenUrl,
+    settingsOpen: cfgOpen, setSettingsOpen,
   } = useAssistantStore();
 
   const [activeTab, setActiveTab] = useState<Tab>("chat");
 
   const scrollRef      = useRef<HTMLDivElement>(null);
   const [confirmClear,  setConfirmClear]  = useState(false);
-  const [cfgOpen,       setCfgOpen]       = useState(false);
   const [promptsOpen,   setPromptsOpen]   = useState(false);
   const [historyOpen,   setHistoryOpen]   = useState(false);
   const [charsOpen,     setCharsOpen]     = useState(false);
   const [langOpen,      setLangOpen]      = useState(false);
+  const [paletteOpen,   setPaletteOpen]   = useState(false);
+  const [shortcuts,     setShortcuts]     = useState<ShortcutEntry[]>([]);
 
-  const togglePanel = (name: "cfg" | "history" | "prompts" | "chars") => {
-    setCfgOpen(name === "cfg" ? (o) => !o : false);
+  const togglePanel = (name: "cfg" | "history" | "prompts" | "chars" | "palette") => {
+    setSettingsOpen(name === "cfg" ? !cfgOpen : false);
+    setPaletteOpen(name === "palette" ? (o) => !o : false);
     setHistoryOpen(name === "history" ? (o) => !o : false);
     setPromptsOpen(name === "prompts" ? (o) => !o : false);
     setCharsOpen(name === "chars" ? (o) => !o : false);
     setLangOpen(false);
   };
 
+  // ── Shortcut cheatsheet (actual bindings, incl. user rebinding) ──────
+  useEffect(() => {
+    invoke<ShortcutEntry[]>("get_shortcut_cheatsheet").then(setShortcuts).catch(() => {});
+    const unlisten = listen<ShortcutEntry[]>("hotkeys-changed", (e) => setShortcuts(e.payload));
+    return () => { unlisten.then((f) => f()); };
+  }, []);
+
+  const keyOf = (action: string) =>
+    shortcuts.find((s) => s.action === action && s.accelerator)?.accelerator;
+  const withKey = (label: string, action: string) => {
+    const key = keyOf(action);
+    return key ? `${label} (${key})` : label;
+  };
+
   // ── Report panel left-X to Rust so cursor tracker can use it ──────────
   useEffect(() => {
     const report = () => {
@@ -108,10 +137,14 @@ export default function AssistantPanel() {
   useEffect(() => {
     const onKey = (e: KeyboardEvent) => {
       if (e.ctrlKey && e.key === "v") pasteFromClipboard();
+      if ((e.ctrlKey || e.metaKey) && e.key === "k") {
+        e.preventDefault();
+        togglePanel("palette");
+      }
     };
     window.addEventListener("keydown", onKey);
     return () => window.removeEventListener("keydown", onKey);
-  }, [pasteFromClipboard]);
+  }, [pasteFromClipboard]); // eslint-disable-line react-hooks/exhaustive-deps
 
   // ── Session clear ──────────────────────────────────────────────────────
   const handleClearSession = () => {
@@ -137,7 +170,7 @@ export default function AssistantPanel() {
         <div className="fixed top-3 right-4 flex items-center gap-1.5 pointer-events-none">
           <span className="w-1.5 h-1.5 rounded-full bg-yellow-400/70 animate-pulse" />
           <span className="text-[9px] font-mono tracking-widest text-white/20 uppercase">
-            ghost · Alt+M
+            ghost{keyOf("toggle_click_through") && ` · ${keyOf("toggle_click_through")}`}
           </span>
         </div>
       )}
@@ -170,6 +203,18 @@ export default function AssistantPanel() {
 
           {/* Controls */}
           <div className="flex items-center gap-1 pointer-events-auto">
+            {/* Command palette */}
+            <button
+              onClick={() => togglePanel("palette")}
+              title="Commands (Ctrl+K)"
+              className={[
+                "text-[10px] px-2 py-1 rounded transition-colors font-mono",
+                paletteOpen ? "bg-white/15 text-white" : "bg-white/5 text-white/40 hover:bg-white/10 hover:text-white/70",
+              ].join(" ")}
+            >
+              ⌘
+            </button>
+
             {/* Config toggle */}
             <button
               onClick={() => togglePanel("cfg")}
@@ -296,7 +341,7 @@ export default function AssistantPanel() {
             {/* Ghost mode toggle */}
             <button
               onClick={toggleGhostMode}
-              title="Ghost mode — fully transparent (Alt+M)"
+              title={withKey("Ghost mode — fully transparent", "toggle_click_through")}
               className="text-[10px] px-2.5 py-1 rounded font-mono transition-colors
                 bg-white/5 text-white/40 hover:bg-yellow-500/20 hover:text-yellow-300"
             >
@@ -344,6 +389,9 @@ export default function AssistantPanel() {
         {/* ── Chat history panel ────────────────────────────────────────── */}
         {historyOpen && <ChatHistory onClose={() => setHistoryOpen(false)} />}
 
+        {/* ── Command palette ──────────────────────────────────────────── */}
+        {paletteOpen && <CommandPalette onClose={() => setPaletteOpen(false)} />}
+
         {/* ── Config panel (collapsible) ────────────────────────────────── */}
         {cfgOpen && (
           <div className="shrink-0 px-3 pt-3 pb-2 space-y-2
@@ -440,7 +488,10 @@ export default function AssistantPanel() {
               <span className="text-3xl opacity-40">🤖</span>
               <p className="text-xs">Screenshot or ask anything.</p>
               <p className="text-[10px] text-white/15">
-                Ctrl+Enter send · Ctrl+V paste · Alt+M ghost · Alt+Shift+S capture
+                {shortcuts
+                  .filter((s) => s.accelerator && s.registered)
+                  .map((s) => `${s.accelerator} ${s.title.toLowerCase()}`)
+                  .join(" · ")}
               </p>
               {archivedChats.length > 0 && (
                 <button
@@ -532,13 +583,30 @@ export default function AssistantPanel() {
             <div className="flex items-center gap-2">
               <span className="animate-spin text-[11px]">⚙️</span>
               <span className="text-[10px] text-emerald-300/80 font-medium flex-1">
-                {sdGenProgress ? "Generating…" : "Preparing…"}
+                {imageQueuePosition
+                  ? `Queued — ${imageQueuePosition === 1 ? "next" : `#${imageQueuePosition}`} for the GPU`
+                  : sdGenProgress?.phase
+                  ? `${sdGenProgress.phase[0].toUpperCase()}${sdGenProgress.phase.slice(1)}…`
+                  : sdGenProgress ? "Generating…" : "Preparing…"}
               </span>
               {sdGenProgress?.total != null && sdGenProgress.total > 0 && (
                 <span className="text-[9px] text-white/50 font-mono shrink-0">
-                  {sdGenProgress.step}/{sdGenProgress.total} steps
+                  {sdGenProgress.step}/{sdGenProgress.total}
+                  {sdGenProgress.phase === "sampling" ? " steps" : ""}
+                  {sdGenProgress.eta_seconds != null && sdGenProgress.step < sdGenProgress.total
+                    ? ` · ~${Math.ceil(sdGenProgress.eta_seconds)}s left`
+                    : ""}
                 </span>
               )}
+              {imageGenProvider === "local_sd" && !imageQueuePosition && (
+                <button
+                  onClick={() => invoke("interrupt_webui_generation", { url: imageGenUrl || null }).catch(console.error)}
+                  className="text-[9px] px-1.5 py-0.5 rounded text-red-300/70 hover:text-red-300 hover:bg-red-500/10 shrink-0"
+                  title="Interrupt the WebUI job"
+                >
+                  Stop
+                </button>
+              )}
             </div>
             {sdGenProgress?.total != null && sdGenProgress.total > 0 ? (
               <>
@@ -549,6 +617,13 @@ export default function AssistantPanel() {
                   />
                 </div>
                 <p className="text-[9px] text-white/30 font-mono truncate">{sdGenProgress.line}</p>
+                {sdPreview && (
+                  <img
+                    src={`data:image/jpeg;base64,${sdPreview}`}
+                    alt="Preview"
+                    className="mx-auto max-h-32 rounded-lg opacity-90"
+                  />
+                )}
               </>
             ) : (
               <div className="w-full h-1.5 bg-white/10 rounded-full overflow-hidden">
@@ -642,6 +717,7 @@ export default function AssistantPanel() {
 
           <textarea
             data-st
+            data-prompt-input
             value={prompt}
             onChange={(e) => setPrompt(e.target.value)}
             onKeyDown={handleKeyDown}
@@ -660,7 +736,7 @@ export default function AssistantPanel() {
             <button
               onClick={() => triggerCapture()}
               disabled={isCapturing}
-              title="Capture screen (Alt+Shift+S)"
+              title={withKey("Capture screen", "capture_screen")}
               className="flex-none flex items-center justify-center gap-1
                 bg-white/[0.06] hover:bg-white/[0.12] rounded-xl px-3 py-2 text-[11px]
                 text-white/50 hover:text-white/80 transition-colors disabled:opacity-40"
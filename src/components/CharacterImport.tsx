@@ -22,6 +22,7 @@ import {
   parseCharacterCardJson,
 } from "../store/assistantStore";
 import StFormatText, { ST_FORMAT_EXAMPLES } from "./StFormatText";
+import { errorMessage } from "../utils/errors";
 
 export default function CharacterImport() {
   const {
@@ -83,7 +84,7 @@ export default function CharacterImport() {
         addCharacter(card);
         imported++;
       } catch (err) {
-        errors.push(`${filePath}: ${String(err)}`);
+        errors.push(`${filePath}: ${errorMessage(err)}`);
       }
     }
 
@@ -0,0 +1,147 @@
+/**
+ * CommandPalette — slide-down panel listing the backend action registry.
+ *
+ * Features:
+ *  - Actions come from `list_actions` (same ids the hotkeys and tray use)
+ *  - Filter by title or keyword, ↑/↓ to pick, Enter to run via `run_action`
+ *  - Actions that take an argument (switch model) ask for it inline
+ *  - Shows the bound global hotkey next to each action
+ */
+import { useState, useRef, useEffect } from "react";
+import { invoke } from "@tauri-apps/api/tauri";
+
+interface ActionInfo {
+  id:        string;
+  title:     string;
+  keywords:  string[];
+  takes_arg: boolean;
+  hotkey:    string | null;
+}
+
+interface Props {
+  onClose: () => void;
+}
+
+export default function CommandPalette({ onClose }: Props) {
+  const [actions,  setActions]  = useState<ActionInfo[]>([]);
+  const [query,    setQuery]    = useState("");
+  const [selected, setSelected] = useState(0);
+  /** Action waiting for its argument */
+  const [pending,  setPending]  = useState<ActionInfo | null>(null);
+  const [arg,      setArg]      = useState("");
+  const [error,    setError]    = useState<string | null>(null);
+
+  const inputRef = useRef<HTMLInputElement>(null);
+
+  useEffect(() => {
+    invoke<ActionInfo[]>("list_actions").then(setActions).catch((err) => setError(String(err)));
+  }, []);
+
+  useEffect(() => {
+    inputRef.current?.focus();
+  }, [pending]);
+
+  const q = query.trim().toLowerCase();
+  const filtered = actions.filter((a) =>
+    !q ||
+    a.title.toLowerCase().includes(q) ||
+    a.keywords.some((k) => k.toLowerCase().includes(q))
+  );
+
+  // ── Run ──────────────────────────────────────────────────────────────
+  const run = async (action: ActionInfo, value?: string) => {
+    if (action.takes_arg && value === undefined) {
+      setPending(action);
+      setArg("");
+      return;
+    }
+    // Close first: toggle_window / quit act on the window this panel lives in
+    onClose();
+    try {
+      await invoke("run_action", { id: action.id, arg: value ?? null });
+    } catch (err) {
+      console.error(`Action ${action.id} failed:`, err);
+    }
+  };
+
+  const handleKeyDown = (e: React.KeyboardEvent<HTMLInputElement>) => {
+    if (e.key === "Escape") {
+      e.preventDefault();
+      if (pending) setPending(null); else onClose();
+    } else if (pending) {
+      if (e.key === "Enter" && arg.trim()) run(pending, arg.trim());
+    } else if (e.key === "ArrowDown") {
+      e.preventDefault();
+      setSelected((i) => Math.min(i + 1, filtered.length - 1));
+    } else if (e.key === "ArrowUp") {
+      e.preventDefault();
+      setSelected((i) => Math.max(i - 1, 0));
+    } else if (e.key === "Enter" && filtered[selected]) {
+      run(filtered[selected]);
+    }
+  };
+
+  return (
+    <div className="shrink-0 border-b border-white/[0.07] bg-gray-950/40">
+      {/* ── Header ─────────────────────────────────────────────────── */}
+      <div className="flex items-center justify-between px-3 pt-2.5 pb-1.5">
+        <span className="text-[11px] font-semibold text-white/70">
+          ⌘ {pending ? pending.title : "Commands"}
+        </span>
+        <button
+          onClick={onClose}
+          className="text-[11px] text-white/30 hover:text-white/70 px-1.5 py-0.5 rounded
+            transition-colors"
+        >
+          ✕
+        </button>
+      </div>
+
+      {/* ── Query / argument ─────────────────────────────────────────── */}
+      <div className="px-3 pb-2">
+        <input
+          ref={inputRef}
+          value={pending ? arg : query}
+          onChange={(e) => {
+            if (pending) { setArg(e.target.value); return; }
+            setQuery(e.target.value);
+            setSelected(0);
+          }}
+          onKeyDown={handleKeyDown}
+          placeholder={pending ? "Model id, e.g. gpt-4o — Enter to run" : "Type a command…"}
+          className="w-full bg-white/[0.05] rounded-lg px-2.5 py-1.5 text-[10px]
+            text-white/70 placeholder-white/20 focus:outline-none
+            focus:ring-1 focus:ring-white/20"
+        />
+      </div>
+
+      {/* ── Action list ──────────────────────────────────────────────── */}
+      {!pending && (
+        <div className="max-h-64 overflow-y-auto scrollbar-thin scrollbar-thumb-white/10 px-2 pb-2 space-y-0.5">
+          {error && <p className="text-[10px] text-red-400/70 text-center py-4">{error}</p>}
+          {!error && filtered.length === 0 && (
+            <p className="text-[10px] text-white/20 text-center py-4">No matching commands.</p>
+          )}
+
+          {filtered.map((action, i) => (
+            <button
+              key={action.id}
+              onClick={() => run(action)}
+              onMouseEnter={() => setSelected(i)}
+              className={[
+                "w-full flex items-center justify-between gap-2 rounded-lg px-2 py-1.5 text-left",
+                "text-[11px] transition-colors",
+                i === selected ? "bg-white/10 text-white" : "text-white/60 hover:bg-white/[0.05]",
+              ].join(" ")}
+            >
+              <span className="truncate">{action.title}{action.takes_arg && "…"}</span>
+              {action.hotkey && (
+                <span className="shrink-0 text-[9px] font-mono text-white/30">{action.hotkey}</span>
+              )}
+            </button>
+          ))}
+        </div>
+      )}
+    </div>
+  );
+}
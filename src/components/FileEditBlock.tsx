@@ -11,6 +11,7 @@ import ReactMarkdown from "react-markdown";
 import remarkGfm from "remark-gfm";
 import { useAssistantStore, parseFileEdits, type FileEdit } from "../store/assistantStore";
 import StFormatText from "./StFormatText";
+import { errorMessage } from "../utils/errors";
 
 interface Props {
   text: string;
@@ -217,7 +218,7 @@ function DeleteCard({ filePath }: { filePath: string }) {
   useEffect(() => {
     deleteFile(filePath, indexedRoot || undefined)
       .then(() => setStatus("done"))
-      .catch((e) => { setErrMsg(String(e)); setStatus("error"); });
+      .catch((e) => { setErrMsg(errorMessage(e)); setStatus("error"); });
   // eslint-disable-next-line react-hooks/exhaustive-deps
   }, []);
 
@@ -257,7 +258,7 @@ function EditCard({ edit }: { edit: FileEdit }) {
   useEffect(() => {
     applyEdit(edit.filePath, edit.content, indexedRoot || undefined)
       .then(() => setStatus("done"))
-      .catch((e) => { setErrMsg(String(e)); setStatus("error"); });
+      .catch((e) => { setErrMsg(errorMessage(e)); setStatus("error"); });
   // eslint-disable-next-line react-hooks/exhaustive-deps
   }, []);
 
@@ -1,9 +1,11 @@
 import { useState } from "react";
 import { openDirSafe } from "../utils/dialog";
 import { useAssistantStore } from "../store/assistantStore";
+import { errorMessage } from "../utils/errors";
 
 export default function FileIndexer() {
-  const { indexedFiles, indexedRoot, indexDirectory, clearIndex } = useAssistantStore();
+  const { indexedFiles, indexedRoot, indexDirectory, clearIndex, semanticContext, setSemanticContext } =
+    useAssistantStore();
   const [loading,  setLoading]  = useState(false);
   const [error,    setError]    = useState<string | null>(null);
   const [expanded, setExpanded] = useState(false);
@@ -18,7 +20,7 @@ export default function FileIndexer() {
       await indexDirectory(selected);
       setExpanded(true);
     } catch (e) {
-      setError(String(e));
+      setError(errorMessage(e));
     } finally {
       setLoading(false);
     }
@@ -58,6 +60,18 @@ export default function FileIndexer() {
           >
             {loading ? "⚙️ Indexing…" : "Browse"}
           </button>
+          {indexedFiles.length > 0 && (
+            <button
+              onClick={() => setSemanticContext(!semanticContext)}
+              className={[
+                "text-[10px] px-2 py-1 rounded transition-colors",
+                semanticContext ? "bg-blue-500/40 text-blue-200" : "bg-white/10 hover:bg-white/20 text-white/50",
+              ].join(" ")}
+              title="Send only the code most relevant to each prompt (needs OpenAI or a local server with an embedding model)"
+            >
+              Semantic
+            </button>
+          )}
           {indexedFiles.length > 0 && (
             <button
               onClick={() => { clearIndex(); setExpanded(false); }}
@@ -15,6 +15,7 @@ import { useState, useCallback, useRef } from "react";
 import type { ReactNode } from "react";
 import { invoke } from "@tauri-apps/api/tauri";
 import { open as openDialog } from "@tauri-apps/api/dialog";
+import { errorMessage } from "../utils/errors";
 
 interface DirEntry {
   name:       string;
@@ -120,7 +121,7 @@ export default function FileTree({ rootPath: initialRoot, onRootChange, classNam
       setEditingPath(path);
       setEditContent(content);
     } catch (e) {
-      setMsg(`Read error: ${e}`);
+      setMsg(`Read error: ${errorMessage(e)}`);
     }
   }, []);
 
@@ -133,7 +134,7 @@ export default function FileTree({ rootPath: initialRoot, onRootChange, classNam
       setMsg("✓ Saved");
       setTimeout(() => setMsg(null), 1800);
     } catch (e) {
-      setMsg(`Save failed: ${e}`);
+      setMsg(`Save failed: ${errorMessage(e)}`);
     } finally {
       setSaving(false);
     }
@@ -149,7 +150,7 @@ export default function FileTree({ rootPath: initialRoot, onRootChange, classNam
       await refresh();
       await openFile(filePath);
     } catch (e) {
-      setMsg(`Create failed: ${e}`);
+      setMsg(`Create failed: ${errorMessage(e)}`);
     }
   }, [refresh, openFile]);
 
@@ -162,7 +163,7 @@ export default function FileTree({ rootPath: initialRoot, onRootChange, classNam
       await invoke("create_dir_cmd", { dirPath });
       await refresh();
     } catch (e) {
-      setMsg(`Create dir failed: ${e}`);
+      setMsg(`Create dir failed: ${errorMessage(e)}`);
     }
   }, [refresh]);
 
@@ -181,7 +182,7 @@ export default function FileTree({ rootPath: initialRoot, onRootChange, classNam
       if (editingPath === node.path) { setEditingPath(null); setEditContent(""); }
       await refresh();
     } catch (e) {
-      setMsg(`Delete failed: ${e}`);
+      setMsg(`Delete failed: ${errorMessage(e)}`);
     }
   }, [refresh, editingPath]);
 
@@ -203,7 +204,7 @@ export default function FileTree({ rootPath: initialRoot, onRootChange, classNam
       if (editingPath === node.path) setEditingPath(dest);
       await refresh();
     } catch (e) {
-      setMsg(`Rename failed: ${e}`);
+      setMsg(`Rename failed: ${errorMessage(e)}`);
     }
   }, [renameValue, refresh, editingPath]);
 
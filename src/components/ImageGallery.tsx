@@ -47,6 +47,15 @@ function Lightbox({ img, onClose }: { img: GeneratedImage; onClose: () => void }
             {img.revisedPrompt ?? img.prompt}
           </p>
           <div className="flex gap-1 shrink-0">
+            {img.seed != null && (
+              <button
+                onClick={() => navigator.clipboard.writeText(String(img.seed))}
+                className="text-[10px] px-2 py-1 rounded bg-white/10 hover:bg-white/20 text-white/50 hover:text-white transition-colors font-mono"
+                title="Copy seed"
+              >
+                🎲 {img.seed}
+              </button>
+            )}
             <button
               onClick={() => navigator.clipboard.writeText(img.revisedPrompt ?? img.prompt)}
               className="text-[10px] px-2 py-1 rounded bg-white/10 hover:bg-white/20 text-white/50 hover:text-white transition-colors"
@@ -2,29 +2,35 @@
  * ImageGenSettings — configuration accordion for the image generation subsystem.
  *
  * Supported providers:
- *   dalle       — OpenAI DALL-E 3 (uses OpenAI API key or a dedicated key)
+ *   dalle       — OpenAI DALL-E 3 / gpt-image-1 (uses OpenAI API key or a dedicated key)
  *   stability   — Stability AI stable-image-core (requires Stability API key)
  *   together    — Together AI FLUX/SDXL (requires Together AI API key; free tier available)
  *   openrouter  — OpenRouter FLUX.1.1-Pro (uses OpenRouter key)
+ *   replicate   — any Replicate model, `owner/name` or `owner/name:version`
+ *   fal         — fal.ai queue API (FLUX by default), any endpoint id
  *   local_sd    — Local Automatic1111 / FORGE WebUI (no key needed)
  */
 
-import { useState } from "react";
+import { useState, useEffect } from "react";
 import { invoke } from "@tauri-apps/api/tauri";
 import { useAssistantStore, type ImageGenProvider } from "../store/assistantStore";
 import LocalSdPanel from "./LocalSdPanel";
+import { errorMessage } from "../utils/errors";
 
 const PROVIDERS: { id: ImageGenProvider; label: string; desc: string }[] = [
   { id: "dalle",      label: "DALL-E 3",      desc: "OpenAI — high quality" },
   { id: "stability",  label: "Stability AI",   desc: "Stable Image Core" },
   { id: "together",   label: "Together AI",    desc: "FLUX free tier" },
   { id: "openrouter", label: "OpenRouter",     desc: "FLUX.1.1-Pro" },
+  { id: "replicate",  label: "Replicate",      desc: "Any hosted model" },
+  { id: "fal",        label: "fal.ai",         desc: "FLUX via queue, live progress" },
+  { id: "imagen",     label: "Google Imagen",  desc: "Imagen 3 via Gemini API" },
   { id: "local_sd",   label: "Local SD",       desc: "A1111 / FORGE API" },
   { id: "native_sd",  label: "Native SD",      desc: "stable-diffusion.cpp (★ no WebUI needed)" },
 ];
 
 const DEFAULT_MODELS: Record<ImageGenProvider, string[]> = {
-  dalle:      ["dall-e-3", "dall-e-2"],
+  dalle:      ["dall-e-3", "dall-e-2", "gpt-image-1"],
   stability:  ["core"],
   together:   [
     "black-forest-labs/FLUX.1-schnell-Free",
@@ -32,15 +38,59 @@ const DEFAULT_MODELS: Record<ImageGenProvider, string[]> = {
     "black-forest-labs/FLUX.1.1-pro",
     "stabilityai/stable-diffusion-xl-base-1.0",
   ],
+  imagen:     ["imagen-3.0-generate-002"],
   openrouter: [
     "black-forest-labs/flux-1.1-pro",
     "black-forest-labs/flux-schnell",
     "stability-ai/sdxl",
   ],
+  replicate:  [
+    "black-forest-labs/flux-schnell",
+    "black-forest-labs/flux-dev",
+    "black-forest-labs/flux-1.1-pro",
+    "stability-ai/stable-diffusion-3.5-large",
+    "ideogram-ai/ideogram-v2",
+  ],
+  fal:        [
+    "fal-ai/flux/schnell",
+    "fal-ai/flux/dev",
+    "fal-ai/flux-pro/v1.1",
+    "fal-ai/flux-pro/v1.1-ultra",
+  ],
   local_sd:   [],
   native_sd:  [],
 };
 
+/** style_presets.rs StylePreset */
+interface StylePreset {
+  id: string;
+  name: string;
+  prompt_prefix: string;
+  prompt_suffix: string;
+  negative_prompt: string;
+  sampler: string | null;
+  cfg_scale: number | null;
+  width: number | null;
+  height: number | null;
+}
+
+const EMPTY_STYLE: StylePreset = {
+  id: "", name: "", prompt_prefix: "", prompt_suffix: "", negative_prompt: "",
+  sampler: null, cfg_scale: null, width: null, height: null,
+};
+
+const WEBUI_SAMPLERS = [
+  "DPM++ 2M Karras", "DPM++ SDE Karras", "DPM++ 2M SDE Karras",
+  "Euler a", "Euler", "DDIM", "UniPC", "LCM",
+];
+
+/** gpt-image-1 option rows: label, values, store key */
+const GPT_IMAGE_OPTIONS = [
+  ["Quality",    ["auto", "low", "medium", "high"]],
+  ["Background", ["auto", "transparent", "opaque"]],
+  ["Format",     ["png", "jpeg", "webp"]],
+] as const;
+
 const SIZE_PRESETS = [
   { label: "512²",    w: 512,  h: 512  },
   { label: "768²",    w: 768,  h: 768  },
@@ -58,12 +108,54 @@ export default function ImageGenSettings() {
     imageGenWidth,      setImageGenWidth,
     imageGenHeight,     setImageGenHeight,
     imageGenCustomPrompt, setImageGenCustomPrompt,
+    imageGenStyleId,    setImageGenStyleId,
+    imageGenSeed,       setImageGenSeed,
+    imageGenNegPrompt,  setImageGenNegPrompt,
+    openaiImageQuality,    setOpenaiImageQuality,
+    openaiImageBackground, setOpenaiImageBackground,
+    openaiImageFormat,     setOpenaiImageFormat,
+    webuiSteps,         setWebuiSteps,
+    webuiCfg,           setWebuiCfg,
+    webuiNegPrompt,     setWebuiNegPrompt,
+    webuiSampler,       setWebuiSampler,
+    webuiSeed,          setWebuiSeed,
+    webuiHiresScale,    setWebuiHiresScale,
+    webuiHiresUpscaler, setWebuiHiresUpscaler,
+    webuiHiresDenoise,  setWebuiHiresDenoise,
   } = useAssistantStore();
 
   const [open,     setOpen]     = useState(false);
   const [showKey,  setShowKey]  = useState(false);
   const [loadingModels, setLoadingModels] = useState(false);
   const [sdModels, setSdModels] = useState<string[]>([]);
+  const [styles,   setStyles]   = useState<StylePreset[]>([]);
+  const [editing,  setEditing]  = useState<StylePreset | null>(null);
+  const [styleErr, setStyleErr] = useState<string | null>(null);
+
+  const loadStyles = () =>
+    invoke<StylePreset[]>("list_style_presets").then(setStyles).catch(console.error);
+
+  useEffect(() => { if (open) loadStyles(); }, [open]);
+
+  const saveStyle = async () => {
+    if (!editing) return;
+    try {
+      const saved = await invoke<StylePreset>("save_style_preset", { preset: editing });
+      setEditing(null);
+      setStyleErr(null);
+      setImageGenStyleId(saved.id);
+      loadStyles();
+    } catch (e) {
+      setStyleErr(errorMessage(e));
+    }
+  };
+
+  const deleteStyle = async (id: string) => {
+    await invoke("delete_style_preset", { id }).catch(console.error);
+    if (imageGenStyleId === id) setImageGenStyleId("");
+    setEditing(null);
+    loadStyles();
+  };
 
   const loadSdModels = async () => {
     setLoadingModels(true);
@@ -83,6 +175,12 @@ export default function ImageGenSettings() {
   const needsUrl  = imageGenProvider === "local_sd";
   const isNative  = imageGenProvider === "native_sd";
   const hasConfig = isNative ? true : (needsKey ? !!imageGenApiKey : !!imageGenUrl);
+  const isGptImage = imageGenProvider === "dalle" && imageGenModel.startsWith("gpt-image");
+  const gptImageValue: Record<string, [string, (v: string) => void]> = {
+    Quality:    [openaiImageQuality,    setOpenaiImageQuality],
+    Background: [openaiImageBackground, setOpenaiImageBackground],
+    Format:     [openaiImageFormat,     setOpenaiImageFormat],
+  };
 
   return (
     <div className="bg-white/5 rounded-xl overflow-hidden">
@@ -139,6 +237,9 @@ export default function ImageGenSettings() {
                 {imageGenProvider === "dalle"      ? "OpenAI API Key"     :
                  imageGenProvider === "stability"  ? "Stability AI Key"   :
                  imageGenProvider === "together"   ? "Together AI Key"    :
+                 imageGenProvider === "replicate"  ? "Replicate API Token" :
+                 imageGenProvider === "fal"        ? "fal.ai API Key"     :
+                 imageGenProvider === "imagen"     ? "Gemini API Key"     :
                                                      "OpenRouter API Key"}
               </p>
               <div className="flex gap-1">
@@ -150,6 +251,9 @@ export default function ImageGenSettings() {
                     imageGenProvider === "dalle"    ? "sk-…"    :
                     imageGenProvider === "stability"? "sk-…"    :
                     imageGenProvider === "together" ? "…"       :
+                    imageGenProvider === "replicate"? "r8_…"    :
+                    imageGenProvider === "fal"      ? "key_id:secret" :
+                    imageGenProvider === "imagen"   ? "AIza…"   :
                                                      "sk-or-…"
                   }
                   className="flex-1 bg-white/[0.06] rounded-lg px-2 py-1.5 text-[11px]
@@ -220,6 +324,89 @@ export default function ImageGenSettings() {
                   ))}
                 </div>
               )}
+
+              {/* Sampling — sent as-is to /sdapi/v1/txt2img */}
+              <div className="grid grid-cols-3 gap-1">
+                {([
+                  ["Steps", webuiSteps, (v: number) => setWebuiSteps(Math.max(1, Math.round(v) || 25))],
+                  ["CFG",   webuiCfg,   (v: number) => setWebuiCfg(v || 7)],
+                  ["Seed",  webuiSeed,  (v: number) => setWebuiSeed(Number.isFinite(v) ? Math.round(v) : -1)],
+                ] as [string, number, (v: number) => void][]).map(([label, value, onSet]) => (
+                  <label key={label} className="space-y-0.5">
+                    <span className="text-[9px] text-white/30 uppercase tracking-wider">{label}</span>
+                    <input
+                      type="number"
+                      value={value}
+                      onChange={(e) => onSet(parseFloat(e.target.value))}
+                      className="w-full bg-white/[0.06] rounded-lg px-2 py-1 text-[11px]
+                        text-white/80 focus:outline-none focus:ring-1 focus:ring-emerald-500/50"
+                    />
+                  </label>
+                ))}
+              </div>
+              <input
+                type="text"
+                value={webuiSampler}
+                onChange={(e) => setWebuiSampler(e.target.value)}
+                list="webui-samplers"
+                placeholder="Sampler (DPM++ 2M Karras)"
+                className="w-full bg-white/[0.06] rounded-lg px-2 py-1.5 text-[11px]
+                  text-white/80 placeholder-white/20
+                  focus:outline-none focus:ring-1 focus:ring-emerald-500/50"
+              />
+              <datalist id="webui-samplers">
+                {WEBUI_SAMPLERS.map((s) => <option key={s} value={s} />)}
+              </datalist>
+              <input
+                type="text"
+                value={webuiNegPrompt}
+                onChange={(e) => setWebuiNegPrompt(e.target.value)}
+                placeholder="Negative prompt"
+                className="w-full bg-white/[0.06] rounded-lg px-2 py-1.5 text-[11px]
+                  text-white/80 placeholder-white/20
+                  focus:outline-none focus:ring-1 focus:ring-emerald-500/50"
+              />
+
+              {/* Hires. fix */}
+              <div className="flex items-center gap-1">
+                <span className="text-[9px] text-white/30 uppercase tracking-wider mr-1">Hires fix</span>
+                {[0, 1.5, 2].map((f) => (
+                  <button
+                    key={f}
+                    onClick={() => setWebuiHiresScale(f)}
+                    className={[
+                      "text-[9px] px-2 py-0.5 rounded font-mono transition-colors",
+                      webuiHiresScale === f
+                        ? "bg-emerald-500/30 text-emerald-200"
+                        : "bg-white/10 text-white/50 hover:bg-white/20 hover:text-white",
+                    ].join(" ")}
+                  >
+                    {f === 0 ? "off" : `×${f}`}
+                  </button>
+                ))}
+              </div>
+              {webuiHiresScale > 1 && (
+                <div className="flex gap-1">
+                  <input
+                    type="text"
+                    value={webuiHiresUpscaler}
+                    onChange={(e) => setWebuiHiresUpscaler(e.target.value)}
+                    placeholder="Upscaler (Latent)"
+                    className="flex-1 bg-white/[0.06] rounded-lg px-2 py-1 text-[11px]
+                      text-white/80 placeholder-white/20
+                      focus:outline-none focus:ring-1 focus:ring-emerald-500/50"
+                  />
+                  <input
+                    type="number"
+                    value={webuiHiresDenoise}
+                    min={0} max={1} step={0.05}
+                    onChange={(e) => setWebuiHiresDenoise(Math.min(1, Math.max(0, parseFloat(e.target.value) || 0)))}
+                    title="Denoising strength"
+                    className="w-16 bg-white/[0.06] rounded-lg px-2 py-1 text-[11px]
+                      text-white/80 focus:outline-none focus:ring-1 focus:ring-emerald-500/50"
+                  />
+                </div>
+              )}
             </div>
           )}
 
@@ -269,9 +456,165 @@ export default function ImageGenSettings() {
                   );
                 })}
               </div>
+              {(imageGenProvider === "replicate" || imageGenProvider === "fal") && (
+                <input
+                  value={imageGenModel}
+                  onChange={(e) => setImageGenModel(e.target.value.trim())}
+                  placeholder={imageGenProvider === "fal" ? "fal-ai/… endpoint id" : "owner/model or owner/model:version"}
+                  className="mt-1 w-full bg-white/[0.06] rounded-lg px-2 py-1 text-[10px] font-mono
+                    text-white/80 placeholder-white/20
+                    focus:outline-none focus:ring-1 focus:ring-emerald-500/50"
+                />
+              )}
+              {isGptImage && (
+                <div className="mt-1.5 space-y-1">
+                  {GPT_IMAGE_OPTIONS.map(([label, values]) => {
+                    const [value, onSet] = gptImageValue[label];
+                    return (
+                      <div key={label} className="flex items-center gap-1">
+                        <span className="w-16 text-[9px] text-white/30 uppercase tracking-wider">{label}</span>
+                        {values.map((v) => (
+                          <button
+                            key={v}
+                            onClick={() => onSet(v)}
+                            disabled={label === "Format" && v === "jpeg" && openaiImageBackground === "transparent"}
+                            className={[
+                              "text-[9px] px-2 py-0.5 rounded font-mono transition-colors disabled:opacity-30",
+                              value === v
+                                ? "bg-emerald-500/30 text-emerald-200"
+                                : "bg-white/10 text-white/50 hover:bg-white/20 hover:text-white",
+                            ].join(" ")}
+                          >
+                            {v}
+                          </button>
+                        ))}
+                      </div>
+                    );
+                  })}
+                </div>
+              )}
             </div>
           )}
 
+          {/* Seed / negative prompt for cloud providers (local_sd has its own above) */}
+          {needsKey && !isNative && (
+            <div className="flex gap-1">
+              <input
+                type="number"
+                value={imageGenSeed}
+                onChange={(e) => {
+                  const v = parseInt(e.target.value, 10);
+                  setImageGenSeed(Number.isFinite(v) ? v : -1);
+                }}
+                title="Seed (-1 = random)"
+                disabled={imageGenProvider === "dalle" || imageGenProvider === "openrouter" || imageGenProvider === "imagen"}
+                className="w-24 bg-white/[0.06] rounded-lg px-2 py-1 text-[11px]
+                  text-white/80 focus:outline-none focus:ring-1 focus:ring-emerald-500/50 disabled:opacity-30"
+              />
+              <input
+                type="text"
+                value={imageGenNegPrompt}
+                onChange={(e) => setImageGenNegPrompt(e.target.value)}
+                placeholder="Negative prompt"
+                className="flex-1 bg-white/[0.06] rounded-lg px-2 py-1 text-[11px]
+                  text-white/80 placeholder-white/20
+                  focus:outline-none focus:ring-1 focus:ring-emerald-500/50"
+              />
+            </div>
+          )}
+
+          {/* Style presets — applied by the backend to every provider */}
+          <div>
+            <p className="text-[9px] text-white/30 mb-1 uppercase tracking-wider">Style</p>
+            <div className="flex flex-wrap gap-1">
+              {[{ ...EMPTY_STYLE, name: "None" }, ...styles].map((st) => (
+                <button
+                  key={st.id || "none"}
+                  onClick={() => setImageGenStyleId(st.id)}
+                  onDoubleClick={() => st.id && setEditing(st)}
+                  title={st.id ? "Double-click to edit" : undefined}
+                  className={[
+                    "text-[9px] px-2 py-0.5 rounded transition-colors",
+                    imageGenStyleId === st.id
+                      ? "bg-emerald-500/30 text-emerald-200"
+                      : "bg-white/10 text-white/50 hover:bg-white/20 hover:text-white",
+                  ].join(" ")}
+                >
+                  {st.name}
+                </button>
+              ))}
+              <button
+                onClick={() => setEditing({ ...EMPTY_STYLE })}
+                className="text-[9px] px-2 py-0.5 rounded bg-white/10 text-white/50 hover:bg-white/20 hover:text-white"
+              >
+                + New
+              </button>
+            </div>
+            {editing && (
+              <div className="mt-1.5 space-y-1 bg-white/[0.04] rounded-lg p-2">
+                {([
+                  ["name",            "Name"],
+                  ["prompt_prefix",   "Before the prompt (e.g. watercolor painting)"],
+                  ["prompt_suffix",   "After the prompt (e.g. soft light, film grain)"],
+                  ["negative_prompt", "Negative prompt"],
+                ] as [keyof StylePreset, string][]).map(([field, placeholder]) => (
+                  <input
+                    key={field}
+                    type="text"
+                    value={(editing[field] as string) ?? ""}
+                    onChange={(e) => setEditing({ ...editing, [field]: e.target.value })}
+                    placeholder={placeholder}
+                    className="w-full bg-white/10 text-white text-[10px] rounded px-2 py-1
+                      placeholder-white/20 outline-none focus:ring-1 focus:ring-emerald-500/50"
+                  />
+                ))}
+                <div className="flex gap-1">
+                  {([
+                    ["cfg_scale", "CFG"],
+                    ["width",     "Width"],
+                    ["height",    "Height"],
+                  ] as [keyof StylePreset, string][]).map(([field, placeholder]) => (
+                    <input
+                      key={field}
+                      type="number"
+                      value={(editing[field] as number | null) ?? ""}
+                      onChange={(e) => setEditing({
+                        ...editing,
+                        [field]: e.target.value === "" ? null : Number(e.target.value),
+                      })}
+                      placeholder={placeholder}
+                      className="w-1/3 bg-white/10 text-white text-[10px] rounded px-2 py-1
+                        placeholder-white/20 outline-none focus:ring-1 focus:ring-emerald-500/50"
+                    />
+                  ))}
+                </div>
+                {styleErr && <p className="text-[9px] text-red-300">{styleErr}</p>}
+                <div className="flex gap-1 justify-end">
+                  {editing.id && (
+                    <button
+                      onClick={() => deleteStyle(editing.id)}
+                      className="text-[9px] px-2 py-0.5 rounded text-red-300/70 hover:text-red-300"
+                    >
+                      Delete
+                    </button>
+                  )}
+                  <button
+                    onClick={() => { setEditing(null); setStyleErr(null); }}
+                    className="text-[9px] px-2 py-0.5 rounded text-white/40 hover:text-white/70"
+                  >
+                    Cancel
+                  </button>
+                  <button
+                    onClick={saveStyle}
+                    className="text-[9px] px-2 py-0.5 rounded bg-emerald-500/30 text-emerald-200 hover:bg-emerald-500/40"
+                  >
+                    Save
+                  </button>
+                </div>
+              </div>
+            )}
+          </div>
+
           {/* Size presets — hidden for native_sd (sizes set per run inside LocalSdPanel) */}
           {!isNative && (
           <div>
@@ -8,6 +8,7 @@ import { invoke } from "@tauri-apps/api/tauri";
 import { listen } from "@tauri-apps/api/event";
 import { useAssistantStore, NativeSdGpuBackend } from "../store/assistantStore";
 import { openDirSafe, openFileSafe } from "../utils/dialog";
+import { errorMessage } from "../utils/errors";
 
 // ── Types ──────────────────────────────────────────────────────────────────
 
@@ -21,6 +22,96 @@ interface DownloadProgress {
   progress: number;
 }
 
+/** Shared "download-progress" event from downloads.rs */
+interface ArtifactProgress {
+  id: string;
+  component: string;
+  file: string;
+  state: "downloading" | "paused" | "verifying" | "done" | "failed" | "cancelled";
+  status: string;
+  progress: number;
+  downloaded: number;
+  total: number | null;
+  bytes_per_sec: number;
+  segments: number;
+  resumable: boolean;
+}
+
+interface SdServerStatus {
+  state: "stopped" | "starting" | "ready" | "busy" | "restarting" | "failed";
+  model_path: string | null;
+  port: number | null;
+  uptime_secs: number;
+  restarts: number;
+  last_error: string | null;
+}
+
+interface DiskUsage {
+  path: string;
+  total_bytes: number;
+  free_bytes: number;
+  used_bytes: number;
+}
+
+/** read_generation_metadata result (gen_metadata.rs) */
+interface GenerationMetadata {
+  source: "a1111" | "forge" | "comfyui";
+  prompt: string;
+  negative_prompt: string;
+  seed: number | null;
+  steps: number | null;
+  cfg_scale: number | null;
+  sampler: string | null;
+  sd_sampler: string | null;
+  width: number | null;
+  height: number | null;
+  model: string | null;
+}
+
+/** get_gpu_info device (gpu_devices.rs) */
+interface GpuDevice {
+  backend: "cuda" | "vulkan" | "rocm";
+  index: number;
+  name: string;
+  memory_mb: number | null;
+}
+
+interface GpuLibStatus {
+  found: boolean;
+  path: string | null;
+  gpu?: string | null;
+  suggestion: string | null;
+}
+
+interface ModelInfo {
+  format: "safetensors" | "gguf";
+  base_model: "sd1" | "sd2" | "sdxl" | "sd3" | "flux" | "unknown";
+  kind: "checkpoint" | "diffusion_model" | "lora" | "vae" | "controlnet";
+  precision: string | null;
+  vram_mb: number;
+  /** [width, height] pairs for the architecture, native first */
+  resolutions: [number, number][];
+  has_vae: boolean;
+  required: string[];
+  recommended_vae: string | null;
+  warnings: string[];
+}
+
+const IS_MAC = navigator.userAgent.includes("Mac");
+
+/** Backends with a release build for this OS: [id, label, description] */
+const GPU_BACKENDS: [NativeSdGpuBackend, string, string][] = IS_MAC
+  ? [
+      ["cpu",    "CPU",    "Universal, no GPU needed"],
+      ["metal",  "Metal",  "Apple Silicon GPU — fastest on Mac"],
+    ]
+  : [
+      ["cpu",    "CPU",    "Universal, no GPU needed"],
+      ["cuda",   "CUDA",   "NVIDIA GPU — fastest"],
+      ["vulkan", "Vulkan", "AMD / Intel / NVIDIA"],
+      ["rocm",   "ROCm",   "AMD GPU via HIP"],
+    ];
+
 const SAMPLERS = [
   { id: "euler",    label: "Euler" },
   { id: "euler_a",  label: "Euler A" },
@@ -45,23 +136,61 @@ export default function LocalSdPanel() {
     nativeSdSampler,    setNativeSdSampler,
     nativeSdSeed,       setNativeSdSeed,
     nativeSdGpuBackend,   setNativeSdGpuBackend,
+    nativeSdGpuDevice,    setNativeSdGpuDevice,
     nativeSdThreads,      setNativeSdThreads,
     nativeSdQualityTags,  setNativeSdQualityTags,
     nativeSdNsfw,         setNativeSdNsfw,
     nativeSdVaeOnCpu,     setNativeSdVaeOnCpu,
     nativeSdVaeTiling,    setNativeSdVaeTiling,
     nativeSdOffloadToCpu, setNativeSdOffloadToCpu,
+    nativeSdServerMode,   setNativeSdServerMode,
+    nativeSdVaePath,      setNativeSdVaePath,
+    nativeSdClipLPath,    setNativeSdClipLPath,
+    nativeSdClipGPath,    setNativeSdClipGPath,
+    nativeSdT5xxlPath,    setNativeSdT5xxlPath,
+    nativeSdTaesdPath,    setNativeSdTaesdPath,
     imageGenWidth,        setImageGenWidth,
     imageGenHeight,       setImageGenHeight,
+    downloadLimitMbps,    setDownloadLimitMbps,
   } = useAssistantStore();
 
   const [binStatus,   setBinStatus]   = useState<BinaryStatus | null>(null);
   const [downloading, setDownloading] = useState(false);
   const [dlProgress,  setDlProgress]  = useState<DownloadProgress | null>(null);
+  const [dlId,        setDlId]        = useState<string | null>(null);
+  const [dlPaused,    setDlPaused]    = useState(false);
+  const [dlResumable, setDlResumable] = useState(false);
   const [modelFiles,  setModelFiles]  = useState<string[]>([]);
   const [scanning,    setScanning]    = useState(false);
   const [error,       setError]       = useState<string | null>(null);
-  const [cudaLibsOk,  setCudaLibsOk]  = useState<null | { found: boolean; path: string | null; suggestion: string | null }>(null);
+  const [gpuLibs,     setGpuLibs]     = useState<GpuLibStatus | null>(null);
+  const [gpus,        setGpus]        = useState<GpuDevice[]>([]);
+  const [modelInfo,   setModelInfo]   = useState<ModelInfo | null>(null);
+  const [modelsDisk,  setModelsDisk]  = useState<DiskUsage | null>(null);
+  const [server,      setServer]      = useState<SdServerStatus | null>(null);
+  const [reused,      setReused]      = useState<GenerationMetadata | null>(null);
+
+  // ── Persistent sd-server status ──────────────────────────────────────────
+  useEffect(() => {
+    invoke<SdServerStatus>("get_sd_server_status").then(setServer).catch(() => {});
+    const un = listen<SdServerStatus>("sd-server-status", (ev) => setServer(ev.payload));
+    return () => { un.then((f) => f()); };
+  }, []);
+
+  const warmServer = () => {
+    if (!nativeSdModelPath) return;
+    invoke<SdServerStatus>("start_sd_server", {
+      config: {
+        model_path:     nativeSdModelPath,
+        gpu_backend:    nativeSdGpuBackend,
+        gpu_device:     nativeSdGpuDevice,
+        threads:        nativeSdThreads,
+        vae_on_cpu:     nativeSdVaeOnCpu,
+        vae_tiling:     nativeSdVaeTiling,
+        offload_to_cpu: nativeSdOffloadToCpu,
+      },
+    }).then(setServer).catch((e) => setError(errorMessage(e)));
+  };
 
   // ── Init: check binary status ────────────────────────────────────────────
   useEffect(() => {
@@ -72,23 +201,46 @@ export default function LocalSdPanel() {
   // ↑ Re-runs whenever the backend selector changes so the UI correctly
   //   reflects whether *that specific* backend binary is already downloaded.
 
-  // ── Check CUDA system libs when CUDA backend is selected ─────────────────
+  // ── Check CUDA / ROCm system libs when that backend is selected ──────────
   useEffect(() => {
-    if (nativeSdGpuBackend !== "cuda") { setCudaLibsOk(null); return; }
-    invoke<{ found: boolean; path: string | null; suggestion: string | null }>("check_cuda_libs")
-      .then(setCudaLibsOk)
-      .catch(() => setCudaLibsOk(null));
+    const check = nativeSdGpuBackend === "cuda" ? "check_cuda_libs"
+      : nativeSdGpuBackend === "rocm" ? "check_rocm_libs" : null;
+    if (!check) { setGpuLibs(null); return; }
+    invoke<GpuLibStatus>(check)
+      .then(setGpuLibs)
+      .catch(() => setGpuLibs(null));
   }, [nativeSdGpuBackend]);
 
+  // ── List GPUs once; the picker shows those of the selected backend ─────
+  useEffect(() => {
+    invoke<{ devices: GpuDevice[] }>("get_gpu_info")
+      .then((info) => setGpus(info.devices))
+      .catch(() => setGpus([]));
+  }, []);
+  const backendGpus = gpus.filter((g) => g.backend === nativeSdGpuBackend);
+
   // ── Scan models when dir changes ─────────────────────────────────────────
   useEffect(() => {
+    setModelsDisk(null);
     if (!nativeSdModelsDir) return;
+    invoke<DiskUsage>("get_disk_usage", { path: nativeSdModelsDir })
+      .then(setModelsDisk)
+      .catch(() => setModelsDisk(null));
     setScanning(true);
     invoke<string[]>("list_local_sd_models", { modelsDir: nativeSdModelsDir })
       .then((files) => { setModelFiles(files); setScanning(false); })
       .catch(() => setScanning(false));
   }, [nativeSdModelsDir]);
 
+  // ── Inspect the selected model's header ──────────────────────────────────
+  useEffect(() => {
+    setModelInfo(null);
+    if (!/\.(safetensors|gguf)$/i.test(nativeSdModelPath)) return;
+    invoke<ModelInfo>("inspect_sd_model", { path: nativeSdModelPath })
+      .then(setModelInfo)
+      .catch(() => setModelInfo(null));
+  }, [nativeSdModelPath]);
+
   // ── Download binary ───────────────────────────────────────────────────────
   const downloadBinary = async () => {
     setDownloading(true);
@@ -98,8 +250,20 @@ export default function LocalSdPanel() {
     const unlisten = await listen<DownloadProgress>("sd-download-progress", (ev) => {
       setDlProgress(ev.payload);
     });
+    // Byte progress of the archive itself, mapped into the 5–78 % window
+    const unlistenBytes = await listen<ArtifactProgress>("download-progress", (ev) => {
+      const p = ev.payload;
+      if (p.component !== "sd-binary" || (p.state !== "downloading" && p.state !== "paused")) return;
+      setDlId(p.id);
+      setDlPaused(p.state === "paused");
+      setDlResumable(p.resumable);
+      setDlProgress({ status: p.status, progress: 5 + Math.round(p.progress * 0.73) });
+    });
 
     try {
+      await invoke("set_download_limit", {
+        bytesPerSec: downloadLimitMbps > 0 ? Math.round(downloadLimitMbps * 1_048_576) : null,
+      });
       await invoke("download_sd_binary", { backendPref: nativeSdGpuBackend });
       const status = await invoke<BinaryStatus>("get_sd_binary_status", { backendPref: nativeSdGpuBackend });
       setBinStatus(status);
@@ -107,16 +271,27 @@ export default function LocalSdPanel() {
     } catch (e) {
       // Keep dlProgress visible so user sees where it stopped; show error text.
       setDlProgress((prev) => prev
-        ? { ...prev, status: `❌ Failed: ${String(e).slice(0, 120)}` }
-        : { status: `❌ ${String(e).slice(0, 120)}`, progress: 0 }
+        ? { ...prev, status: `❌ Failed: ${errorMessage(e).slice(0, 120)}` }
+        : { status: `❌ ${errorMessage(e).slice(0, 120)}`, progress: 0 }
       );
-      setError(String(e));
+      setError(errorMessage(e));
     } finally {
       unlisten();
+      unlistenBytes();
+      setDlId(null);
+      setDlPaused(false);
       setDownloading(false);
     }
   };
 
+  const cancelDownload = () => {
+    if (dlId) invoke("cancel_download", { id: dlId }).catch(console.error);
+  };
+
+  const togglePause = () => {
+    if (dlId) invoke(dlPaused ? "resume_download" : "pause_download", { id: dlId }).catch(console.error);
+  };
+
   // ── Browse models directory ───────────────────────────────────────────────
   const browseModelsDir = async () => {
     const selected = await openDirSafe("Select models folder");
@@ -132,6 +307,38 @@ export default function LocalSdPanel() {
     if (typeof selected === "string") setNativeSdModelPath(selected);
   };
 
+  // ── Browse VAE / text encoder file ────────────────────────────────────────
+  const browseComponentFile = async (set: (p: string) => void) => {
+    const selected = await openFileSafe({
+      multiple: false,
+      filters: [{ name: "Model components", extensions: ["safetensors", "gguf", "bin"] }],
+    });
+    if (typeof selected === "string") set(selected);
+  };
+
+  // ── Reuse parameters from an A1111 / Forge / ComfyUI image ────────────────
+  const reuseFromImage = async () => {
+    const selected = await openFileSafe({
+      multiple: false,
+      filters: [{ name: "Images", extensions: ["png", "jpg", "jpeg", "webp"] }],
+    });
+    if (typeof selected !== "string") return;
+    try {
+      const meta = await invoke<GenerationMetadata>("read_generation_metadata", { pathOrBase64: selected });
+      if (meta.negative_prompt)  setNativeSdNegPrompt(meta.negative_prompt);
+      if (meta.steps != null)    setNativeSdSteps(meta.steps);
+      if (meta.cfg_scale != null) setNativeSdCfg(meta.cfg_scale);
+      if (meta.seed != null)     setNativeSdSeed(meta.seed);
+      if (meta.sd_sampler)       setNativeSdSampler(meta.sd_sampler);
+      if (meta.width && meta.height) { setImageGenWidth(meta.width); setImageGenHeight(meta.height); }
+      setReused(meta);
+      setError(null);
+    } catch (e) {
+      setReused(null);
+      setError(errorMessage(e));
+    }
+  };
+
   const shortPath = (p: string) => p.split(/[\\/]/).slice(-1)[0] ?? p;
   // ── Delete binary ─────────────────────────────────────────────────────────
   const deleteBinary = async () => {
@@ -141,7 +348,7 @@ export default function LocalSdPanel() {
       setDlProgress(null);
       setError(null);
     } catch (e) {
-      setError(String(e));
+      setError(errorMessage(e));
     }
   };
   return (
@@ -151,12 +358,8 @@ export default function LocalSdPanel() {
         <p className="text-[9px] uppercase tracking-wider text-white/30 font-medium">
           GPU / compute backend
         </p>
-        <div className="grid grid-cols-3 gap-1">
-          {([
-            ["cpu",    "CPU",    "Universal, no GPU needed"],
-            ["cuda",   "CUDA",   "NVIDIA GPU — fastest"],
-            ["vulkan", "Vulkan", "AMD / Intel / NVIDIA"],
-          ] as [NativeSdGpuBackend, string, string][]).map(([id, label, desc]) => (
+        <div className="grid grid-cols-4 gap-1">
+          {GPU_BACKENDS.map(([id, label, desc]) => (
             <button
               key={id}
               onClick={() => setNativeSdGpuBackend(id)}
@@ -177,10 +380,33 @@ export default function LocalSdPanel() {
             ? "⚡ On Linux, selects the Vulkan build (no native Linux CUDA release exists). Works on NVIDIA, AMD, Intel via Vulkan API."
             : nativeSdGpuBackend === "vulkan"
             ? "⚡ Vulkan build: works on AMD, Intel & NVIDIA GPUs. Recommended for NVIDIA on Linux."
+            : nativeSdGpuBackend === "metal"
+            ? "⚡ Metal build: runs on the Apple Silicon GPU with unified memory."
+            : nativeSdGpuBackend === "rocm"
+            ? "⚡ ROCm (HIP) build for AMD GPUs. Needs the ROCm runtime; falls back to the Vulkan build if the release has no ROCm binary for your platform."
             : "💻 CPU build: universal, no GPU required. Slowest option."
           }
         </p>
 
+        {/* Device picker — only worth showing with more than one GPU */}
+        {backendGpus.length > 1 && (
+          <div className="flex items-center gap-2">
+            <span className="text-[9px] text-white/40 shrink-0">Device</span>
+            <select
+              value={nativeSdGpuDevice ?? ""}
+              onChange={(e) => setNativeSdGpuDevice(e.target.value === "" ? null : Number(e.target.value))}
+              className="flex-1 bg-white/[0.06] rounded-md px-2 py-1 text-[10px] text-white/80 outline-none"
+            >
+              <option value="">Default</option>
+              {backendGpus.map((g) => (
+                <option key={g.index} value={g.index}>
+                  #{g.index} {g.name}{g.memory_mb ? ` · ${(g.memory_mb / 1024).toFixed(0)} GB` : ""}
+                </option>
+              ))}
+            </select>
+          </div>
+        )}
+
         {/* Linux CUDA → Vulkan info box */}
         {nativeSdGpuBackend === "cuda" && (
           <div className="rounded-lg bg-amber-500/10 border border-amber-500/30 p-2">
@@ -193,6 +419,19 @@ export default function LocalSdPanel() {
           </div>
         )}
 
+        {/* CUDA / ROCm runtime diagnostics */}
+        {gpuLibs && (
+          gpuLibs.found ? (
+            <p className="text-[9px] text-emerald-400/60 font-mono truncate" title={gpuLibs.path ?? ""}>
+              ✓ runtime: {gpuLibs.path}{gpuLibs.gpu ? ` · ${gpuLibs.gpu}` : ""}
+            </p>
+          ) : gpuLibs.suggestion && (
+            <div className="rounded-lg bg-red-500/10 border border-red-500/30 p-2">
+              <p className="text-[9px] text-white/50 whitespace-pre-wrap">{gpuLibs.suggestion}</p>
+            </div>
+          )
+        )}
+
         {binStatus?.installed && (
           <div className="flex items-center justify-between">
             <p className="text-[9px] text-emerald-400/60">
@@ -233,7 +472,9 @@ export default function LocalSdPanel() {
               <span className="text-white/50">
                 {nativeSdGpuBackend === "cuda"
                   ? "Vulkan (NVIDIA/AMD/Intel GPU on Linux)"
-                  : nativeSdGpuBackend === "vulkan" ? "Vulkan (GPU)" : "CPU"}
+                  : nativeSdGpuBackend === "vulkan" ? "Vulkan (GPU)"
+                  : nativeSdGpuBackend === "rocm" ? "ROCm (AMD GPU)"
+                  : nativeSdGpuBackend === "metal" ? "Metal (Apple GPU)" : "CPU"}
               </span>{" "}
               binary from{" "}
               <span className="text-white/50">leejet/stable-diffusion.cpp</span> releases
@@ -247,6 +488,32 @@ export default function LocalSdPanel() {
             >
               {downloading ? "⟳ Downloading…" : "⬇ Download binary"}
             </button>
+            <div className="flex items-center gap-2 text-[9px] text-white/40">
+              <label htmlFor="dl-limit">Speed limit (MB/s, 0 = none)</label>
+              <input
+                id="dl-limit"
+                type="number"
+                min={0}
+                step={0.5}
+                value={downloadLimitMbps}
+                onChange={(e) => setDownloadLimitMbps(Math.max(0, Number(e.target.value) || 0))}
+                disabled={downloading}
+                className="w-14 bg-white/5 border border-white/10 rounded px-1 py-0.5 text-white/70"
+              />
+              {downloading && dlId && dlResumable && (
+                <button onClick={togglePause} className="ml-auto text-blue-300/70 hover:text-blue-300">
+                  {dlPaused ? "▶ Resume" : "⏸ Pause"}
+                </button>
+              )}
+              {downloading && dlId && (
+                <button
+                  onClick={cancelDownload}
+                  className={`${dlResumable ? "" : "ml-auto "}text-red-300/70 hover:text-red-300`}
+                >
+                  ✕ Cancel (resumable)
+                </button>
+              )}
+            </div>
             {dlProgress && (
               <div className="space-y-1">
                 <div className="flex justify-between text-[9px] text-white/40">
@@ -293,6 +560,12 @@ export default function LocalSdPanel() {
         </div>
         <p className="text-[9px] text-white/20">
           Folder containing .safetensors / .gguf / .ckpt model files — searched recursively.
+          {modelsDisk && (
+            <span className={modelsDisk.free_bytes < 8 * 1024 ** 3 ? " text-amber-300/70" : ""}>
+              {" "}{(modelsDisk.free_bytes / 1024 ** 3).toFixed(1)} GB free
+              {modelsDisk.free_bytes < 8 * 1024 ** 3 && " — not enough for most SDXL / FLUX checkpoints"}.
+            </span>
+          )}
         </p>
       </div>
 
@@ -351,8 +624,91 @@ export default function LocalSdPanel() {
             <span className="text-[9px] text-white/30 font-mono truncate">
               {shortPath(nativeSdModelPath)}
             </span>
+            {modelInfo && (
+              <span className="text-[9px] text-white/40 font-mono flex-none">
+                {modelInfo.base_model.toUpperCase()} · {modelInfo.precision ?? "?"} · ~{(modelInfo.vram_mb / 1024).toFixed(1)} GB
+              </span>
+            )}
           </div>
         )}
+        {modelInfo?.warnings.map((w) => (
+          <p key={w} className="text-[9px] text-amber-400/80">⚠ {w}</p>
+        ))}
+        {modelInfo?.recommended_vae && (
+          <p className="text-[9px] text-white/30">
+            Suggested VAE: <span className="font-mono">{modelInfo.recommended_vae}</span>
+          </p>
+        )}
+      </div>
+
+      {/* ── VAE / text encoders (FLUX, SD3) ── */}
+      {(modelInfo?.base_model === "flux" || modelInfo?.base_model === "sd3" || modelInfo?.required.length
+        || nativeSdVaePath || nativeSdClipLPath || nativeSdT5xxlPath) && (
+        <div className="space-y-1.5">
+          <p className="text-[9px] text-white/30 uppercase tracking-wider">VAE / text encoders</p>
+          {([
+            ["vae",    "VAE",    nativeSdVaePath,   setNativeSdVaePath,   "ae.safetensors"],
+            ["clip_l", "CLIP-L", nativeSdClipLPath, setNativeSdClipLPath, "clip_l.safetensors"],
+            ...(modelInfo?.base_model === "sd3" || nativeSdClipGPath
+              ? [["clip_g", "CLIP-G", nativeSdClipGPath, setNativeSdClipGPath, "clip_g.safetensors"]]
+              : []),
+            ["t5xxl",  "T5-XXL", nativeSdT5xxlPath, setNativeSdT5xxlPath, "t5xxl_fp16.safetensors"],
+          ] as [string, string, string, (p: string) => void, string][]).map(([id, label, value, set, hint]) => (
+            <div key={id} className="flex items-center gap-1">
+              <span className={[
+                "w-12 text-[9px] font-mono flex-none",
+                modelInfo?.required.includes(id) && !value ? "text-amber-400/80" : "text-white/40",
+              ].join(" ")}>
+                {label}
+              </span>
+              <input
+                type="text"
+                value={value}
+                onChange={(e) => set(e.target.value)}
+                placeholder={hint}
+                className="flex-1 bg-white/[0.06] rounded-lg px-2 py-1 text-[10px]
+                  text-white/80 placeholder-white/20
+                  focus:outline-none focus:ring-1 focus:ring-emerald-500/50"
+              />
+              <button
+                onClick={() => browseComponentFile(set)}
+                className="px-2 py-0.5 bg-white/[0.06] rounded-lg text-[11px]
+                  text-white/40 hover:text-white/70 transition-colors"
+              >
+                📄
+              </button>
+            </div>
+          ))}
+          {modelInfo?.base_model === "flux" && (
+            <p className="text-[9px] text-white/30">FLUX runs at CFG 1 — the CFG scale and negative prompt are ignored.</p>
+          )}
+        </div>
+      )}
+
+      {/* ── Live preview (TAESD) ── */}
+      <div className="space-y-1">
+        <p className="text-[9px] text-white/30 uppercase tracking-wider">Live preview (TAESD)</p>
+        <div className="flex gap-1">
+          <input
+            type="text"
+            value={nativeSdTaesdPath}
+            onChange={(e) => setNativeSdTaesdPath(e.target.value)}
+            placeholder={modelInfo?.base_model === "sdxl" ? "taesdxl.safetensors" : "taesd.safetensors"}
+            className="flex-1 bg-white/[0.06] rounded-lg px-2 py-1 text-[10px]
+              text-white/80 placeholder-white/20
+              focus:outline-none focus:ring-1 focus:ring-emerald-500/50"
+          />
+          <button
+            onClick={() => browseComponentFile(setNativeSdTaesdPath)}
+            className="px-2 py-0.5 bg-white/[0.06] rounded-lg text-[11px]
+              text-white/40 hover:text-white/70 transition-colors"
+          >
+            📄
+          </button>
+        </div>
+        <p className="text-[9px] text-white/20">
+          Shows the image forming every few steps. Must match the model family; not used with "Keep model loaded".
+        </p>
       </div>
 
       {/* ── Inference settings ── */}
@@ -525,6 +881,54 @@ export default function LocalSdPanel() {
             ].join(" ")} />
           </button>
         </div>
+        {/* Persistent server toggle */}
+        <div className="col-span-2 space-y-1">
+          <div className="flex items-center justify-between">
+            <div>
+              <p className={["text-[9px] uppercase tracking-wider font-semibold", nativeSdServerMode ? "text-emerald-400" : "text-white/30"].join(" ")}>Keep model loaded</p>
+              <p className="text-[9px] text-white/20">sd-server · seconds per image instead of reloading each time</p>
+            </div>
+            <button
+              onClick={() => setNativeSdServerMode(!nativeSdServerMode)}
+              className={[
+                "relative w-8 h-4 rounded-full transition-colors",
+                nativeSdServerMode ? "bg-emerald-500/60" : "bg-white/10",
+              ].join(" ")}
+            >
+              <span className={[
+                "absolute top-0.5 w-3 h-3 rounded-full bg-white transition-transform",
+                nativeSdServerMode ? "translate-x-4" : "translate-x-0.5",
+              ].join(" ")} />
+            </button>
+          </div>
+          {nativeSdServerMode && (
+            <div className="flex items-center gap-2 text-[9px] text-white/40">
+              <span className={
+                server?.state === "ready" || server?.state === "busy" ? "text-emerald-300/80"
+                  : server?.state === "failed" ? "text-red-300/80" : ""
+              }>
+                {server?.state ?? "stopped"}
+                {server && server.restarts > 0 && ` · ${server.restarts} restart${server.restarts > 1 ? "s" : ""}`}
+              </span>
+              {(!server || server.state === "stopped" || server.state === "failed") ? (
+                <button onClick={warmServer} disabled={!nativeSdModelPath}
+                  className="ml-auto text-emerald-300/70 hover:text-emerald-300 disabled:opacity-40">
+                  ▶ Load now
+                </button>
+              ) : (
+                <button onClick={() => invoke("stop_sd_server").catch(console.error)}
+                  className="ml-auto text-white/40 hover:text-white/70">
+                  ■ Stop
+                </button>
+              )}
+            </div>
+          )}
+          {nativeSdServerMode && server?.last_error && (
+            <p className="text-[9px] text-red-300/60 whitespace-pre-wrap max-h-20 overflow-y-auto">
+              {server.last_error.slice(0, 400)}
+            </p>
+          )}
+        </div>
         {/* Threads */}
         <div className="space-y-1 col-span-2">
           <p className="text-[9px] text-white/30 uppercase tracking-wider">CPU Threads (0 = auto)</p>
@@ -550,14 +954,17 @@ export default function LocalSdPanel() {
           >
             ⚡ Fast
           </button>
-          {([
-            [512,  512,  "512²"],
-            [512,  768,  "512×768"],
-            [768,  512,  "768×512"],
-            [768,  768,  "768²"],
-            [1024, 1024, "1024²"],
-            [1024, 1280, "4:5"],
-          ] as [number, number, string][]).map(([w, h, label]) => (
+          {(modelInfo?.resolutions.length
+            ? modelInfo.resolutions.map(([w, h]) => [w, h, w === h ? `${w}²` : `${w}×${h}`])
+            : [
+                [512,  512,  "512²"],
+                [512,  768,  "512×768"],
+                [768,  512,  "768×512"],
+                [768,  768,  "768²"],
+                [1024, 1024, "1024²"],
+                [1024, 1280, "4:5"],
+              ]
+          ).map(([w, h, label]) => (
             <button
               key={label}
               onClick={() => { setImageGenWidth(w); setImageGenHeight(h); }}
@@ -572,6 +979,13 @@ export default function LocalSdPanel() {
             </button>
           ))}
         </div>
+        {modelInfo && modelInfo.resolutions.length > 0
+          && !modelInfo.resolutions.some(([w, h]) => w === imageGenWidth && h === imageGenHeight) && (
+          <p className="text-[9px] text-amber-400/80">
+            {imageGenWidth}×{imageGenHeight} isn't a native {modelInfo.base_model.toUpperCase()} size —
+            {" "}{modelInfo.resolutions[0][0]}×{modelInfo.resolutions[0][1]} is what it was trained for
+          </p>
+        )}
       </div>
 
       {/* Negative prompt */}
@@ -588,6 +1002,37 @@ export default function LocalSdPanel() {
         />
       </div>
 
+      {/* Reuse parameters */}
+      <div className="space-y-1.5">
+        <button
+          onClick={reuseFromImage}
+          className="w-full py-1.5 rounded-lg text-[10px] bg-white/[0.06] hover:bg-white/10
+            text-white/60 hover:text-white/90 transition-colors"
+        >
+          🧬 Reuse parameters from image…
+        </button>
+        {reused && (
+          <div className="bg-white/[0.04] rounded-lg p-2 space-y-1">
+            <p className="text-[9px] text-white/40">
+              Applied from {reused.source}
+              {reused.model ? ` · ${reused.model}` : ""}
+              {reused.sampler && !reused.sd_sampler ? ` · sampler "${reused.sampler}" has no local equivalent` : ""}
+            </p>
+            {reused.prompt && (
+              <div className="flex items-start gap-2">
+                <p className="flex-1 text-[10px] text-white/70 break-words line-clamp-3">{reused.prompt}</p>
+                <button
+                  onClick={() => navigator.clipboard.writeText(reused.prompt).catch(console.error)}
+                  className="text-[9px] text-emerald-300/70 hover:text-emerald-300 shrink-0"
+                >
+                  Copy prompt
+                </button>
+              </div>
+            )}
+          </div>
+        )}
+      </div>
+
       {/* Error */}
       {error && (
         <div className="bg-red-500/10 border border-red-500/20 rounded-lg p-2">
@@ -1,18 +1,49 @@
 import { useState } from "react";
-import { useAssistantStore } from "../store/assistantStore";
+import { invoke } from "@tauri-apps/api/tauri";
+import { useAssistantStore, type SearchBackend } from "../store/assistantStore";
+
+/** Backends that need an API key: input placeholder and where to get one */
+const KEYED_BACKENDS: Partial<Record<SearchBackend, { placeholder: string; hint: string }>> = {
+  brave:   { placeholder: "Brave Search API key (free tier available)", hint: "Free tier: 2 000 queries/month → brave.com/search/api" },
+  bing:    { placeholder: "Azure Bing Search v7 resource key",         hint: "Uses the key of a Bing Search resource in the Azure portal (billed to your Azure credits)." },
+  serper:  { placeholder: "serper.dev API key",                        hint: "Google results with answer box & knowledge graph → serper.dev" },
+  serpapi: { placeholder: "SerpAPI key",                               hint: "Google results with answer box & knowledge graph → serpapi.com" },
+  stackexchange: { placeholder: "Stack Exchange key (optional)",       hint: "Stack Overflow questions with the accepted answer. A key raises the quota from 300 to 10 000 requests/day → stackapps.com" },
+};
+
+/** "docs.rs, python.org" → ["docs.rs", "python.org"] */
+const splitDomains = (text: string) =>
+  text.split(/[\s,]+/).map((d) => d.trim()).filter(Boolean);
+
+const BACKEND_LABELS: Record<SearchBackend, string> = {
+  duckduckgo: "🦆 DDG",
+  brave:      "🦁 Brave",
+  bing:       "🅱 Bing",
+  serper:     "🅖 Serper",
+  serpapi:    "🅖 SerpAPI",
+  searxng:    "🔍 SearXNG",
+  stackexchange: "📚 SO",
+};
 
 export default function WebSearchToggle() {
   const {
     webSearchEnabled, setWebSearchEnabled,
     searchBackend,    setSearchBackend,
     searchApiKey,     setSearchApiKey,
+    searchMarket,     setSearchMarket,
     searxngUrl,       setSearxngUrl,
     fetchPageContent, setFetchPageContent,
     searchMaxResults, setSearchMaxResults,
+    searchCacheMinutes, setSearchCacheMinutes,
+    searchIncludeDomains, setSearchIncludeDomains,
+    searchExcludeDomains, setSearchExcludeDomains,
+    searchRewriteQueries, setSearchRewriteQueries,
+    fetchUserAgent,   setFetchUserAgent,
   } = useAssistantStore();
 
   const [expanded, setExpanded] = useState(false);
   const [showKey,  setShowKey]  = useState(false);
+  const [cleared,  setCleared]  = useState<number | null>(null);
 
   return (
     <div className="bg-white/5 rounded-xl overflow-hidden">
@@ -53,8 +84,8 @@ export default function WebSearchToggle() {
       {expanded && (
         <div className="px-3 pb-3 space-y-2">
           {/* Backend selector */}
-          <div className="flex gap-1">
-            {(["duckduckgo", "brave", "searxng"] as const).map((b) => (
+          <div className="grid grid-cols-3 gap-1">
+            {(Object.keys(BACKEND_LABELS) as SearchBackend[]).map((b) => (
               <button
                 key={b}
                 onClick={() => setSearchBackend(b)}
@@ -65,20 +96,20 @@ export default function WebSearchToggle() {
                     : "bg-white/10 text-white/50 hover:bg-white/20",
                 ].join(" ")}
               >
-                {b === "duckduckgo" ? "🦆 DDG" : b === "brave" ? "🦁 Brave" : "🔍 SearXNG"}
+                {BACKEND_LABELS[b]}
               </button>
             ))}
           </div>
 
           {/* Backend-specific config */}
-          {searchBackend === "brave" && (
+          {KEYED_BACKENDS[searchBackend] && (
             <>
               <div className="relative">
                 <input
                   type={showKey ? "text" : "password"}
                   value={searchApiKey}
                   onChange={(e) => setSearchApiKey(e.target.value)}
-                  placeholder="Brave Search API key (free tier available)"
+                  placeholder={KEYED_BACKENDS[searchBackend]!.placeholder}
                   className="w-full bg-white/10 rounded-lg px-2.5 py-1.5 text-[11px] pr-8
                     placeholder-white/25 focus:outline-none focus:ring-1 focus:ring-green-500"
                 />
@@ -91,10 +122,7 @@ export default function WebSearchToggle() {
                   {showKey ? "🙈" : "👁"}
                 </button>
               </div>
-              <p className="text-[9px] text-white/30">
-                Free tier: 2 000 queries/month →{" "}
-                <span className="text-green-300/60">brave.com/search/api</span>
-              </p>
+              <p className="text-[9px] text-white/30">{KEYED_BACKENDS[searchBackend]!.hint}</p>
             </>
           )}
 
@@ -122,6 +150,39 @@ export default function WebSearchToggle() {
             </p>
           )}
 
+          {/* ─ Result language / region ─ */}
+          {searchBackend !== "stackexchange" && (
+            <input
+              type="text"
+              value={searchMarket}
+              onChange={(e) => setSearchMarket(e.target.value.trim())}
+              placeholder="Language-region, e.g. en-US, de-DE"
+              title="Language and region of the results"
+              className="w-full bg-white/10 rounded-lg px-2.5 py-1.5 text-[11px]
+                font-mono placeholder-white/25 focus:outline-none focus:ring-1 focus:ring-green-500"
+            />
+          )}
+
+          {/* ─ Domain filters ─ */}
+          <div className="space-y-1 pt-1">
+            <input
+              type="text"
+              defaultValue={searchIncludeDomains.join(", ")}
+              onBlur={(e) => setSearchIncludeDomains(splitDomains(e.target.value))}
+              placeholder="Only sites, e.g. docs.rs, developer.mozilla.org"
+              className="w-full bg-white/10 rounded-lg px-2.5 py-1.5 text-[11px]
+                font-mono placeholder-white/25 focus:outline-none focus:ring-1 focus:ring-green-500"
+            />
+            <input
+              type="text"
+              defaultValue={searchExcludeDomains.join(", ")}
+              onBlur={(e) => setSearchExcludeDomains(splitDomains(e.target.value))}
+              placeholder="Exclude sites, e.g. pinterest.com"
+              className="w-full bg-white/10 rounded-lg px-2.5 py-1.5 text-[11px]
+                font-mono placeholder-white/25 focus:outline-none focus:ring-1 focus:ring-green-500"
+            />
+          </div>
+
           {/* ─ Fetch page content toggle ─ */}
           <div className="flex items-center justify-between pt-1">
             <span className="text-[10px] text-white/50">
@@ -145,6 +206,40 @@ export default function WebSearchToggle() {
             </button>
           </div>
 
+          {fetchPageContent && (
+            <input
+              type="text"
+              value={fetchUserAgent}
+              onChange={(e) => setFetchUserAgent(e.target.value)}
+              placeholder="User agent for page fetches (default: desktop Chrome)"
+              className="w-full bg-white/10 rounded-lg px-2.5 py-1.5 text-[11px]
+                font-mono placeholder-white/25 focus:outline-none focus:ring-1 focus:ring-green-500"
+            />
+          )}
+
+          {/* ─ LLM query expansion toggle ─ */}
+          <div className="flex items-center justify-between">
+            <span className="text-[10px] text-white/50">
+              Expand query
+              <span className="block text-[9px] text-white/25">Model writes 2–3 searches, results merged</span>
+            </span>
+            <button
+              onClick={() => setSearchRewriteQueries(!searchRewriteQueries)}
+              className={[
+                "relative w-9 h-5 rounded-full transition-colors duration-200 shrink-0",
+                searchRewriteQueries ? "bg-blue-500" : "bg-white/20",
+              ].join(" ")}
+              title="Let the chat model rewrite the question into focused search queries"
+            >
+              <span
+                className={[
+                  "absolute top-0.5 w-4 h-4 bg-white rounded-full shadow transition-transform duration-200",
+                  searchRewriteQueries ? "translate-x-4" : "translate-x-0.5",
+                ].join(" ")}
+              />
+            </button>
+          </div>
+
           {/* ─ Result count ─ */}
           <div className="flex items-center justify-between">
             <span className="text-[10px] text-white/50">Results</span>
@@ -165,6 +260,37 @@ export default function WebSearchToggle() {
               ))}
             </div>
           </div>
+
+          {/* ─ Cache ─ */}
+          <div className="flex items-center justify-between">
+            <span className="text-[10px] text-white/50">
+              Cache
+              <span className="block text-[9px] text-white/25">Repeat queries skip the API</span>
+            </span>
+            <div className="flex gap-1">
+              {([[0, "off"], [15, "15m"], [60, "1h"], [1440, "1d"]] as const).map(([n, label]) => (
+                <button
+                  key={n}
+                  onClick={() => setSearchCacheMinutes(n)}
+                  className={[
+                    "px-1.5 py-0.5 rounded text-[10px] font-semibold transition-colors",
+                    searchCacheMinutes === n
+                      ? "bg-blue-600 text-white"
+                      : "bg-white/10 text-white/50 hover:bg-white/20",
+                  ].join(" ")}
+                >
+                  {label}
+                </button>
+              ))}
+              <button
+                onClick={() => invoke<number>("clear_search_cache").then(setCleared).catch(() => setCleared(0))}
+                title="Delete all cached search results"
+                className="px-1.5 py-0.5 rounded text-[10px] bg-white/10 text-white/50 hover:bg-white/20"
+              >
+                {cleared === null ? "🗑" : `✓ ${cleared}`}
+              </button>
+            </div>
+          </div>
         </div>
       )}
     </div>
@@ -8,7 +8,7 @@ import { useAssistantStore } from "../store/assistantStore";
  * Call once at the top-level App component.
  */
 export function useTauriEvents() {
-  const { setClickThrough, setGhostMode, triggerCapture, windowMode } = useAssistantStore();
+  const { setClickThrough, setGhostMode, setModel, triggerCapture, windowMode } = useAssistantStore();
 
   // ── Safety: reset volatile loading state on every mount ────────────────
   // `isLoading` is not persisted, but can get stuck at `true` after a hot
@@ -17,6 +17,11 @@ export function useTauriEvents() {
     useAssistantStore.setState({ isLoading: false });
   }, []);
 
+  // ── Backend keeps moderation settings in memory; push the persisted ones ─
+  useEffect(() => {
+    useAssistantStore.getState().syncModeration().catch(() => {});
+  }, []);
+
   // ── Restore last-used window mode on startup ────────────────────────────
   useEffect(() => {
     invoke("set_window_mode", {
@@ -42,6 +47,29 @@ export function useTauriEvents() {
       triggerCapture();
     }).then((fn) => unlisteners.push(fn));
 
+    // Dispatched through the Rust action registry (palette / hotkeys / tray)
+    listen("quick-ask", () => {
+      document.querySelector<HTMLTextAreaElement>("textarea[data-prompt-input]")?.focus();
+    }).then((fn) => unlisteners.push(fn));
+
+    listen("paste-context", () => {
+      useAssistantStore.getState().ingestClipboard().catch((err) => console.error("Paste as context failed:", err));
+      document.querySelector<HTMLTextAreaElement>("textarea[data-prompt-input]")?.focus();
+    }).then((fn) => unlisteners.push(fn));
+
+    listen("open-settings", () => {
+      useAssistantStore.getState().setSettingsOpen(true);
+    }).then((fn) => unlisteners.push(fn));
+
+    // project_watch.rs: files under the indexed root changed on disk
+    listen("project-file-changed", () => {
+      useAssistantStore.getState().syncIndexDelta().catch(() => {});
+    }).then((fn) => unlisteners.push(fn));
+
+    listen<string>("switch-model", (e) => {
+      setModel(e.payload);
+    }).then((fn) => unlisteners.push(fn));
+
     return () => unlisteners.forEach((fn) => fn());
   }, []); // eslint-disable-line react-hooks/exhaustive-deps
 }
@@ -2,6 +2,7 @@ import { create } from "zustand";
 import { persist, createJSONStorage } from "zustand/middleware";
 import { invoke } from "@tauri-apps/api/tauri";
 import { listen } from "@tauri-apps/api/event";
+import { errorMessage, errorWithHint, isCancelled } from "../utils/errors";
 
 // Module-level cancel hook — not stored in Zustand state (not serialisable)
 let _cancelFn: (() => void) | null = null;
@@ -33,18 +34,41 @@ try { localStorage.removeItem("ai-assistant-v1"); } catch { /* ignore */ }
 
 // ── Types ──────────────────────────────────────────────────────────────────
 
-export type AiProvider = "openai" | "claude" | "deepseek" | "openrouter" | "local";
-export type ImageGenProvider = "dalle" | "stability" | "together" | "local_sd" | "openrouter" | "native_sd";
-export type NativeSdGpuBackend = "cpu" | "cuda" | "vulkan";
+export type AiProvider = "openai" | "claude" | "deepseek" | "openrouter" | "together" | "local";
+export type SearchBackend = "brave" | "bing" | "serper" | "serpapi" | "searxng" | "duckduckgo" | "stackexchange";
+export type ImageGenProvider = "dalle" | "stability" | "together" | "local_sd" | "openrouter" | "replicate" | "fal" | "imagen" | "native_sd";
+export type NativeSdGpuBackend = "cpu" | "cuda" | "vulkan" | "rocm" | "metal";
 
 export interface GeneratedImage {
   base64: string;
   format: string;
   prompt: string;
   revisedPrompt?: string;
+  /** Seed the provider used; absent for DALL-E / OpenRouter */
+  seed?: number;
   timestamp: number;
 }
 
+/** Payload of the native SD "sd-progress" event */
+export interface SdProgress {
+  phase: "" | "queued" | "loading" | "encoding" | "sampling" | "decoding" | "upscaling" | "saving";
+  step: number;
+  total: number;
+  eta_seconds: number | null;
+  line: string;
+}
+
+/** Entry of the GPU image queue ("image-queue-changed" / "image-queue-position") */
+export interface ImageQueueEntry {
+  id: string;
+  kind: "sd" | "upscale" | "webui" | "video";
+  label: string;
+  /** 0 = running, 1 = next, … */
+  position: number;
+  running: boolean;
+  queued_secs: number;
+}
+
 // ── Prompt Library ─────────────────────────────────────────────────────────
 
 export interface SavedPrompt {
@@ -183,6 +207,14 @@ export interface FileEdit {
   applied: boolean;
 }
 
+/** Moderation settings of one profile (moderation.rs) */
+export interface ModerationSettings {
+  mode:   "off" | "local" | "openai";
+  /** Comma-separated blocked terms */
+  terms:  string;
+  apiKey: string;
+}
+
 /**
  * Parse all <<<FILE:path>>> … <<<END_FILE>>> blocks from an AI message.
  * Returns an array of FileEdit objects (applied = false by default).
@@ -197,38 +229,6 @@ export function parseFileEdits(text: string): FileEdit[] {
   return edits;
 }
 
-// System-prompt suffix injected when files are indexed
-const FILE_EDIT_INSTRUCTIONS = `
-
----
-## File-editing capability
-You have **full read/write access** to the selected project directory.
-You can:
-- **Modify** any existing file, including empty ones.
-- **Create** new files that do not yet exist.
-- **Create** new subdirectories (just use a path like \`subdir/file.ts\` and the directory will be made automatically).
-
-When you want to create or change a file, output **exactly** this block (one block per file):
-
-<<<FILE:relative/or/absolute/path>>>
-<full new file content here — may be empty if intentionally blank>
-<<<END_FILE>>>
-
-Rules:
-- Output the **complete** new file content, not a diff.
-- For empty files, just leave the body blank between the markers.
-- Use paths relative to the project root (as shown in the context), or absolute paths.
-- You may output multiple FILE blocks in one response.
-- Explain your changes in plain text outside the FILE blocks.
-- NEVER say you cannot create or edit a file — you always can.
-
-To **delete** a file, output exactly this line (no body needed):
-\`<<<DELETE_FILE:relative/or/absolute/path>>>\`
-
-- Use this only when a file should be permanently removed.
-- You may mix FILE and DELETE_FILE blocks in one response.
-`;
-
 // ── Response-language map ─────────────────────────────────────────────────
 export const LANGUAGE_NAMES: Record<string, string> = {
   en: "English",
@@ -257,6 +257,9 @@ interface AssistantState {
   /** Window mode: 'overlay' = fullscreen transparent, 'windowed' = floating panel */
   windowMode: "overlay" | "windowed";
   setWindowMode: (mode: "overlay" | "windowed") => void;
+  /** Settings panel visibility (not persisted; also opened by the open_settings action) */
+  settingsOpen: boolean;
+  setSettingsOpen: (open: boolean) => void;
   // ── API configuration (persisted) ────────────────────────────────────
   apiKey:   string;
   setApiKey: (k: string) => void;
@@ -307,6 +310,16 @@ interface AssistantState {
   indexedRoot:    string;
   indexDirectory: (path: string) => Promise<void>;
   clearIndex:     () => void;
+  /** Pull files changed on disk since the last sync (watch_project) into the index */
+  syncIndexDelta: () => Promise<void>;
+  /** Send the chunks semantic_search ranks closest to the prompt instead of the first files */
+  semanticContext:    boolean;
+  setSemanticContext: (v: boolean) => void;
+  /** (Re-)embed the indexed project for semantic_search (index_project_vectors) */
+  indexVectors: () => Promise<void>;
+  /** Attach the clipboard to the next prompt: image → vision, files → index,
+   *  URL / code / text → appended to the prompt (type detection in Rust) */
+  ingestClipboard: () => Promise<void>;
 
   // ── File editing ─────────────────────────────────────────────────────
   /** Apply a full-file replacement (or creation) at filePath */
@@ -318,10 +331,29 @@ interface AssistantState {
   // ── Web Search ───────────────────────────────────────────────────────────
   webSearchEnabled: boolean;
   setWebSearchEnabled: (v: boolean) => void;
-  searchBackend: "brave" | "searxng" | "duckduckgo";
-  setSearchBackend: (b: "brave" | "searxng" | "duckduckgo") => void;
+  searchBackend: SearchBackend;
+  setSearchBackend: (b: SearchBackend) => void;
+  /** API key of the selected keyed backend (Brave, Bing, Serper, SerpAPI) */
   searchApiKey: string;
   setSearchApiKey: (k: string) => void;
+  /** Result language-region for every backend, e.g. "en-US" */
+  searchMarket: string;
+  setSearchMarket: (m: string) => void;
+  /** How long identical searches are answered from the disk cache (0 = off) */
+  searchCacheMinutes: number;
+  setSearchCacheMinutes: (n: number) => void;
+  /** Restrict results to these domains (and subdomains); empty = any */
+  searchIncludeDomains: string[];
+  setSearchIncludeDomains: (d: string[]) => void;
+  /** Drop results from these domains */
+  searchExcludeDomains: string[];
+  setSearchExcludeDomains: (d: string[]) => void;
+  /** Have the chat model split the question into 2–3 search queries first */
+  searchRewriteQueries: boolean;
+  setSearchRewriteQueries: (v: boolean) => void;
+  /** User-Agent for fetched pages; empty = backend default */
+  fetchUserAgent: string;
+  setFetchUserAgent: (ua: string) => void;
   searxngUrl: string;
   setSearxngUrl: (url: string) => void;
   /** Fetch full page content for top search results (slower but much better context) */
@@ -350,6 +382,23 @@ interface AssistantState {
   responseLanguage: string;
   setResponseLanguage: (lang: string) => void;
 
+  // ── Moderation (moderation.rs) ───────────────────────────────────────────
+  /** Check cloud-bound prompts before sending: "off" | "local" (blocked terms) | "openai" */
+  moderationMode:     "off" | "local" | "openai";
+  /** Comma-separated terms that block a prompt (all modes except "off") */
+  moderationTerms:    string;
+  /** OpenAI key for the moderation endpoint ("openai" mode) */
+  moderationApiKey:   string;
+  setModeration: (patch: Partial<Pick<AssistantState, "moderationMode" | "moderationTerms" | "moderationApiKey">>) => void;
+  /** Profile the settings above belong to; cloud requests are checked under it */
+  moderationProfile:  string;
+  /** Saved settings of the other profiles, by name */
+  moderationProfiles: Record<string, ModerationSettings>;
+  /** Save the current settings under their profile and load `name`'s */
+  switchModerationProfile: (name: string) => void;
+  /** Push every profile's moderation settings to the backend (on change and at startup) */
+  syncModeration: () => Promise<void>;
+
   // ── UI preferences ───────────────────────────────────────────────────────
   /** Chat text size in px (10–22). Default 14. */
   fontSize: number;
@@ -357,6 +406,9 @@ interface AssistantState {
   /** Max output tokens sent to the AI (null = provider default ~2048). */
   maxTokens: number | null;
   setMaxTokens: (n: number | null) => void;
+  /** Model a streamed request is retried with when the prompt overflows (empty = trim context instead). */
+  fallbackModel: string;
+  setFallbackModel: (m: string) => void;
 
   // ── Image generation ────────────────────────────────────────────────────
   imageGenProvider: ImageGenProvider;
@@ -376,7 +428,11 @@ interface AssistantState {
   imageGenHeight: number;
   setImageGenHeight: (n: number) => void;
   /** Native SD step-by-step progress (null when not running) */
-  sdGenProgress: { line: string; step: number; total: number } | null;
+  sdGenProgress: SdProgress | null;
+  /** Latest TAESD preview frame of the running native SD job (base64 JPEG) */
+  sdPreview: string | null;
+  /** Place of the current local generation in the GPU queue (null = running or idle) */
+  imageQueuePosition: number | null;
   /** Is an image currently being generated? */
   isGeneratingImage: boolean;
   /** Last successfully generated image, ready to display */
@@ -389,6 +445,44 @@ interface AssistantState {
   /** Optional prompt override — if set, skips the auto-generation step */
   imageGenCustomPrompt: string;
   setImageGenCustomPrompt: (p: string) => void;
+  /** Style preset id applied to every provider ("" = none) */
+  imageGenStyleId: string;
+  setImageGenStyleId: (id: string) => void;
+  // ── Cloud provider generation settings ─────────────────────────
+  /** Fixed seed for cloud providers (-1 = random) */
+  imageGenSeed: number;
+  setImageGenSeed: (n: number) => void;
+  imageGenNegPrompt: string;
+  setImageGenNegPrompt: (p: string) => void;
+  // ── OpenAI gpt-image-1 options (dalle provider) ────────────────
+  /** "auto" | "low" | "medium" | "high" */
+  openaiImageQuality: string;
+  setOpenaiImageQuality: (q: string) => void;
+  /** "auto" | "transparent" | "opaque" */
+  openaiImageBackground: string;
+  setOpenaiImageBackground: (b: string) => void;
+  /** "png" | "jpeg" | "webp" */
+  openaiImageFormat: string;
+  setOpenaiImageFormat: (f: string) => void;
+  // ── A1111 / Forge WebUI (local_sd) generation settings ─────────
+  webuiSteps: number;
+  setWebuiSteps: (n: number) => void;
+  webuiCfg: number;
+  setWebuiCfg: (n: number) => void;
+  webuiNegPrompt: string;
+  setWebuiNegPrompt: (p: string) => void;
+  /** A1111 sampler name ("DPM++ 2M Karras") */
+  webuiSampler: string;
+  setWebuiSampler: (s: string) => void;
+  webuiSeed: number;
+  setWebuiSeed: (n: number) => void;
+  /** Hires. fix upscale factor (0 = off) */
+  webuiHiresScale: number;
+  setWebuiHiresScale: (n: number) => void;
+  webuiHiresUpscaler: string;
+  setWebuiHiresUpscaler: (u: string) => void;
+  webuiHiresDenoise: number;
+  setWebuiHiresDenoise: (n: number) => void;
   // ── Native SD (stable-diffusion.cpp) settings ──────────────────
   nativeSdModelPath: string;
   setNativeSdModelPath: (p: string) => void;
@@ -404,12 +498,18 @@ interface AssistantState {
   setNativeSdSampler: (s: string) => void;
   nativeSdSeed: number;
   setNativeSdSeed: (n: number) => void;
-  /** GPU backend for stable-diffusion.cpp: "cpu" | "cuda" | "vulkan" */
+  /** GPU backend for stable-diffusion.cpp: "cpu" | "cuda" | "vulkan" | "rocm" | "metal" */
   nativeSdGpuBackend: NativeSdGpuBackend;
   setNativeSdGpuBackend: (b: NativeSdGpuBackend) => void;
+  /** GPU index within the backend (get_gpu_info); null = driver default */
+  nativeSdGpuDevice: number | null;
+  setNativeSdGpuDevice: (n: number | null) => void;
   /** CPU thread count for stable-diffusion.cpp (0 = auto) */
   nativeSdThreads: number;
   setNativeSdThreads: (n: number) => void;
+  /** Cap for binary / model downloads in MB/s (0 = unlimited) */
+  downloadLimitMbps: number;
+  setDownloadLimitMbps: (n: number) => void;
   /** Prepend quality booster tags (score_9, masterpiece…) to the generated prompt */
   nativeSdQualityTags: boolean;
   setNativeSdQualityTags: (v: boolean) => void;
@@ -425,6 +525,22 @@ interface AssistantState {
   /** Pass --offload-to-cpu: model weights stored in RAM, loaded to VRAM on demand (prevents OOM on load) */
   nativeSdOffloadToCpu: boolean;
   setNativeSdOffloadToCpu: (v: boolean) => void;
+  /** Keep sd-server running with the model loaded between generations */
+  nativeSdServerMode: boolean;
+  setNativeSdServerMode: (v: boolean) => void;
+  /** Separate VAE (--vae); needed by FLUX / SD3 files without a baked-in one */
+  nativeSdVaePath: string;
+  setNativeSdVaePath: (p: string) => void;
+  /** Text encoders for FLUX (clip_l + t5xxl) and SD3 (clip_l + clip_g + t5xxl) */
+  nativeSdClipLPath: string;
+  setNativeSdClipLPath: (p: string) => void;
+  nativeSdClipGPath: string;
+  setNativeSdClipGPath: (p: string) => void;
+  nativeSdT5xxlPath: string;
+  setNativeSdT5xxlPath: (p: string) => void;
+  /** Tiny AutoEncoder for live previews while sampling ("" = no preview) */
+  nativeSdTaesdPath: string;
+  setNativeSdTaesdPath: (p: string) => void;
   /** Generate an image that represents the current chat context */
   generateImage: () => Promise<void>;
 }
@@ -470,6 +586,77 @@ function trimToSentenceBoundary(text: string, maxTokens: number | null): string
   return text;
 }
 
+/**
+ * Track `queueId`'s place in the backend GPU queue while a local image job
+ * waits; position 0 means it started.
+ */
+function listenQueuePosition(
+  queueId: string,
+  set: (partial: Partial<AssistantState>) => void,
+): Promise<() => void> {
+  return listen<ImageQueueEntry>("image-queue-position", (ev) => {
+    if (ev.payload.id === queueId) set({ imageQueuePosition: ev.payload.position || null });
+  });
+}
+
+/** A1111 / Forge sampling options for a local_sd generate_image request. */
+function webuiParams(s: AssistantState) {
+  return {
+    steps:           s.webuiSteps,
+    cfg_scale:       s.webuiCfg,
+    negative_prompt: s.webuiNegPrompt || null,
+    sampler:         s.webuiSampler || null,
+    seed:            s.webuiSeed,
+    hires: s.webuiHiresScale > 1
+      ? { scale: s.webuiHiresScale, upscaler: s.webuiHiresUpscaler || null, denoising_strength: s.webuiHiresDenoise }
+      : null,
+  };
+}
+
+/** Seed / negative prompt for a cloud generate_image request. */
+function cloudParams(s: AssistantState) {
+  return {
+    negative_prompt: s.imageGenNegPrompt || null,
+    seed:            s.imageGenSeed,
+  };
+}
+
+/** Embedding backend for project_vectors: OpenAI when that is the provider, else the local server. */
+function embedParams(s: AssistantState) {
+  return s.provider === "openai"
+    ? { provider: "openai", api_key: s.apiKey, model: null, url: null }
+    : { provider: "local", api_key: null, model: null, url: s.localUrl.replace(/\/v1\/.*$/, "") };
+}
+
+/** gpt-image-1 options for a dalle generate_image request. */
+function gptImageParams(s: AssistantState) {
+  return {
+    quality:       s.openaiImageQuality,
+    background:    s.openaiImageBackground,
+    output_format: s.openaiImageFormat,
+  };
+}
+
+interface PowerState {
+  on_battery: boolean;
+  power_saver: boolean;
+  battery_percent: number | null;
+  saving: boolean;
+}
+
+/**
+ * Ask before a local diffusion job runs on battery / power saver — it keeps
+ * the GPU (or every CPU core) busy for minutes. Resolves true to go ahead.
+ */
+async function confirmLocalJobOnBattery(): Promise<boolean> {
+  const power = await invoke<PowerState>("get_power_state").catch(() => null);
+  if (!power?.saving) return true;
+  const why = power.on_battery
+    ? `on battery${power.battery_percent != null ? ` (${power.battery_percent}%)` : ""}`
+    : "in power-saver mode";
+  return confirm(`Your computer is ${why}. Local image generation drains the battery quickly. Generate anyway?`);
+}
+
 // ── Store ──────────────────────────────────────────────────────────────────
 
 export const useAssistantStore = create<AssistantState>()(
@@ -497,6 +684,8 @@ export const useAssistantStore = create<AssistantState>()(
       setFontSize: (n) => set({ fontSize: Math.max(10, Math.min(22, n)) }),
       maxTokens: null,
       setMaxTokens: (n) => set({ maxTokens: n }),
+      fallbackModel: "",
+      setFallbackModel: (m) => set({ fallbackModel: m }),
 
       // ── Image generation ────────────────────────────────────────────
       imageGenProvider: "dalle",
@@ -512,6 +701,8 @@ export const useAssistantStore = create<AssistantState>()(
       imageGenHeight: 512,
       setImageGenHeight: (n) => set({ imageGenHeight: n }),
       sdGenProgress: null,
+      sdPreview: null,
+      imageQueuePosition: null,
       isGeneratingImage: false,
       lastGeneratedImage: null,
       clearGeneratedImage: () => set({ lastGeneratedImage: null }),
@@ -520,6 +711,38 @@ export const useAssistantStore = create<AssistantState>()(
       clearGallery: () => set({ imageGallery: [] }),
       imageGenCustomPrompt: "",
       setImageGenCustomPrompt: (p) => set({ imageGenCustomPrompt: p }),
+      imageGenStyleId: "",
+      setImageGenStyleId: (id) => set({ imageGenStyleId: id }),
+      imageGenSeed: -1,
+      setImageGenSeed: (n) => set({ imageGenSeed: n }),
+      imageGenNegPrompt: "",
+      setImageGenNegPrompt: (p) => set({ imageGenNegPrompt: p }),
+      openaiImageQuality: "auto",
+      setOpenaiImageQuality: (q) => set({ openaiImageQuality: q }),
+      openaiImageBackground: "auto",
+      // JPEG has no alpha channel, so a transparent background switches to PNG
+      setOpenaiImageBackground: (b) => set((s) => ({
+        openaiImageBackground: b,
+        openaiImageFormat: b === "transparent" && s.openaiImageFormat === "jpeg" ? "png" : s.openaiImageFormat,
+      })),
+      openaiImageFormat: "png",
+      setOpenaiImageFormat: (f) => set({ openaiImageFormat: f }),
+      webuiSteps: 25,
+      setWebuiSteps: (n) => set({ webuiSteps: n }),
+      webuiCfg: 7,
+      setWebuiCfg: (n) => set({ webuiCfg: n }),
+      webuiNegPrompt: "blurry, low quality, distorted, deformed",
+      setWebuiNegPrompt: (p) => set({ webuiNegPrompt: p }),
+      webuiSampler: "DPM++ 2M Karras",
+      setWebuiSampler: (s) => set({ webuiSampler: s }),
+      webuiSeed: -1,
+      setWebuiSeed: (n) => set({ webuiSeed: n }),
+      webuiHiresScale: 0,
+      setWebuiHiresScale: (n) => set({ webuiHiresScale: n }),
+      webuiHiresUpscaler: "Latent",
+      setWebuiHiresUpscaler: (u) => set({ webuiHiresUpscaler: u }),
+      webuiHiresDenoise: 0.5,
+      setWebuiHiresDenoise: (n) => set({ webuiHiresDenoise: n }),
 
       // ── Native SD state ──────────────────────────────────────────
       nativeSdModelPath: "",
@@ -537,9 +760,13 @@ export const useAssistantStore = create<AssistantState>()(
       nativeSdSeed: -1,
       setNativeSdSeed: (n) => set({ nativeSdSeed: n }),
       nativeSdGpuBackend: "cpu" as NativeSdGpuBackend,
-      setNativeSdGpuBackend: (b) => set({ nativeSdGpuBackend: b }),
+      setNativeSdGpuBackend: (b) => set({ nativeSdGpuBackend: b, nativeSdGpuDevice: null }),
+      nativeSdGpuDevice: null,
+      setNativeSdGpuDevice: (n) => set({ nativeSdGpuDevice: n }),
       nativeSdThreads: 0,
       setNativeSdThreads: (n) => set({ nativeSdThreads: n }),
+      downloadLimitMbps: 0,
+      setDownloadLimitMbps: (n) => set({ downloadLimitMbps: n }),
       nativeSdQualityTags: true,
       setNativeSdQualityTags: (v) => set({ nativeSdQualityTags: v }),
       nativeSdNsfw: false,
@@ -550,6 +777,21 @@ export const useAssistantStore = create<AssistantState>()(
       setNativeSdVaeTiling: (v) => set({ nativeSdVaeTiling: v }),
       nativeSdOffloadToCpu: true,
       setNativeSdOffloadToCpu: (v) => set({ nativeSdOffloadToCpu: v }),
+      nativeSdServerMode: false,
+      setNativeSdServerMode: (v) => {
+        set({ nativeSdServerMode: v });
+        if (!v) invoke("stop_sd_server").catch(console.error);
+      },
+      nativeSdVaePath: "",
+      setNativeSdVaePath: (p) => set({ nativeSdVaePath: p }),
+      nativeSdClipLPath: "",
+      setNativeSdClipLPath: (p) => set({ nativeSdClipLPath: p }),
+      nativeSdClipGPath: "",
+      setNativeSdClipGPath: (p) => set({ nativeSdClipGPath: p }),
+      nativeSdT5xxlPath: "",
+      setNativeSdT5xxlPath: (p) => set({ nativeSdT5xxlPath: p }),
+      nativeSdTaesdPath: "",
+      setNativeSdTaesdPath: (p) => set({ nativeSdTaesdPath: p }),
 
       generateImage: async () => {
         const {
@@ -649,6 +891,7 @@ export const useAssistantStore = create<AssistantState>()(
             provider === "openai"     ? "analyze_with_openai"     :
             provider === "claude"     ? "analyze_with_claude"     :
             provider === "deepseek"   ? "analyze_with_deepseek"   :
+            provider === "together"   ? "analyze_with_together"   :
             provider === "local"      ? "analyze_with_local"      :
                                         "analyze_with_openrouter";
 
@@ -668,6 +911,7 @@ export const useAssistantStore = create<AssistantState>()(
           // ── Step 2: generate the image ────────────────────────────────
           let imageBase64: string;
           let revisedPrompt: string | undefined;
+          let imageSeed: number | undefined;
           let imageFormat = "png";
 
           if (imageGenProvider === "native_sd") {
@@ -675,11 +919,15 @@ export const useAssistantStore = create<AssistantState>()(
             const {
               nativeSdModelPath, nativeSdSteps, nativeSdCfg,
               nativeSdNegPrompt, nativeSdSampler, nativeSdSeed,
-              nativeSdGpuBackend, nativeSdThreads,
+              nativeSdGpuBackend, nativeSdGpuDevice, nativeSdThreads,
               nativeSdVaeOnCpu, nativeSdVaeTiling, nativeSdOffloadToCpu,
+              nativeSdServerMode,
+              nativeSdVaePath, nativeSdClipLPath, nativeSdClipGPath, nativeSdT5xxlPath,
+              nativeSdTaesdPath,
               imageGenWidth: w, imageGenHeight: h,
             } = get();
             if (!nativeSdModelPath) throw new Error("Native SD: no model selected. Go to Settings → Image Generation → Native SD.");
+            if (!(await confirmLocalJobOnBattery())) throw new Error("Cancelled — running on battery.");
 
             console.group("%c[SD] run_local_sd request", "color:#34d399;font-weight:bold");
             console.log("provider  :", "native_sd (stable-diffusion.cpp)");
@@ -693,17 +941,14 @@ export const useAssistantStore = create<AssistantState>()(
             console.groupEnd();
 
             // Subscribe to step-by-step progress events from sd binary
-            const unlistenSdProg = await listen<{ line: string }>("sd-progress", (ev) => {
-              const line = ev.payload.line;
-              const m = line.match(/(\d+)\s*\/\s*(\d+)/);
-              set({
-                sdGenProgress: {
-                  line,
-                  step:  m ? parseInt(m[1]) : 0,
-                  total: m ? parseInt(m[2]) : 0,
-                },
-              });
+            const unlistenSdProg = await listen<SdProgress>("sd-progress", (ev) => {
+              set({ sdGenProgress: ev.payload });
             });
+            const unlistenPreview = await listen<{ seq: number; image_base64: string }>("sd-preview", (ev) => {
+              set({ sdPreview: ev.payload.image_base64 });
+            });
+            const queueId = `sd-${Date.now()}`;
+            const unlistenQueue = await listenQueuePosition(queueId, set);
 
             const sdStart = Date.now();
             try {
@@ -718,13 +963,21 @@ export const useAssistantStore = create<AssistantState>()(
                   cfg_scale:       nativeSdCfg,
                   seed:            nativeSdSeed,
                   sampler:         nativeSdSampler,
-                  vae_path:        null,
+                  vae_path:        nativeSdVaePath || null,
+                  clip_l_path:     nativeSdClipLPath || null,
+                  clip_g_path:     nativeSdClipGPath || null,
+                  t5xxl_path:      nativeSdT5xxlPath || null,
+                  taesd_path:      nativeSdTaesdPath || null,
                   threads:         nativeSdThreads,
                   extra_args:      null,
                   gpu_backend:     nativeSdGpuBackend,
+                  gpu_device:      nativeSdGpuDevice,
                   vae_on_cpu:      nativeSdVaeOnCpu,
                   vae_tiling:      nativeSdVaeTiling,
                   offload_to_cpu:  nativeSdOffloadToCpu,
+                  queue_id:        queueId,
+                  server_mode:     nativeSdServerMode,
+                  style:           get().imageGenStyleId || null,
                 },
               });
               console.log(`%c[SD] ✓ generation complete (${((Date.now()-sdStart)/1000).toFixed(1)}s)`,
@@ -734,7 +987,9 @@ export const useAssistantStore = create<AssistantState>()(
               throw sdErr;
             } finally {
               unlistenSdProg();
-              set({ sdGenProgress: null });
+              unlistenPreview();
+              unlistenQueue();
+              set({ sdGenProgress: null, sdPreview: null, imageQueuePosition: null });
             }
           } else {
             // Cloud / local WebUI providers
@@ -752,24 +1007,49 @@ export const useAssistantStore = create<AssistantState>()(
             console.groupEnd();
 
             const cloudStart = Date.now();
-            const result = await invoke<{ image_base64: string; revised_prompt?: string; format: string }>(
-              "generate_image",
-              {
-                req: {
-                  prompt:    visualPrompt,
-                  provider:  imageGenProvider,
-                  api_key:   resolvedKey || null,
-                  model:     imageGenModel || null,
-                  url:       imageGenUrl || null,
-                  width:     imageGenWidth,
-                  height:    imageGenHeight,
-                },
-              }
-            );
-            console.log(`%c[IMG] ✓ received (${((Date.now()-cloudStart)/1000).toFixed(1)}s) format=${result.format}`,
+            const queueId = `img-${Date.now()}`;
+            const unlistenQueue = await listenQueuePosition(queueId, set);
+            // The WebUI and fal.ai backends report progress with the native SD events
+            const isWebui = imageGenProvider === "local_sd";
+            const reportsProgress = isWebui || imageGenProvider === "fal";
+            const unlistenSdProg = reportsProgress
+              ? await listen<SdProgress>("sd-progress", (ev) => set({ sdGenProgress: ev.payload }))
+              : () => {};
+            const unlistenPreview = isWebui
+              ? await listen<{ seq: number; image_base64: string }>("sd-preview", (ev) => set({ sdPreview: ev.payload.image_base64 }))
+              : () => {};
+            type ImageGenResult = { image_base64: string; revised_prompt?: string; format: string; seed: number | null };
+            let result: ImageGenResult;
+            try {
+              result = await invoke<ImageGenResult>(
+                "generate_image",
+                {
+                  req: {
+                    prompt:    visualPrompt,
+                    provider:  imageGenProvider,
+                    api_key:   resolvedKey || null,
+                    model:     imageGenModel || null,
+                    url:       imageGenUrl || null,
+                    width:     imageGenWidth,
+                    height:    imageGenHeight,
+                    queue_id:  queueId,
+                    style:     get().imageGenStyleId || null,
+                    ...(imageGenProvider === "local_sd" ? webuiParams(get()) : cloudParams(get())),
+                    ...(imageGenProvider === "dalle" && imageGenModel.startsWith("gpt-image") ? gptImageParams(get()) : {}),
+                  },
+                }
+              );
+            } finally {
+              unlistenQueue();
+              unlistenSdProg();
+              unlistenPreview();
+              set({ imageQueuePosition: null, sdGenProgress: null, sdPreview: null });
+            }
+            console.log(`%c[IMG] ✓ received (${((Date.now()-cloudStart)/1000).toFixed(1)}s) format=${result.format} seed=${result.seed ?? "n/a"}`,
               "color:#60a5fa;font-weight:bold");
             imageBase64    = result.image_base64;
             revisedPrompt  = result.revised_prompt;
+            imageSeed      = result.seed ?? undefined;
             imageFormat    = result.format;
           }
 
@@ -778,6 +1058,7 @@ export const useAssistantStore = create<AssistantState>()(
             format:        imageFormat,
             prompt:        visualPrompt,
             revisedPrompt: revisedPrompt,
+            seed:          imageSeed,
             timestamp:     Date.now(),
           };
           set((s) => ({
@@ -787,7 +1068,7 @@ export const useAssistantStore = create<AssistantState>()(
         } catch (err) {
           const errMsg: ChatMessage = {
             id: crypto.randomUUID(), role: "assistant", timestamp: Date.now(),
-            text: `**Image generation failed:** ${String(err)}`,
+            text: `**Image generation failed:** ${errorWithHint(err)}`,
           };
           set((s) => ({ messages: [...s.messages, errMsg] }));
         } finally {
@@ -801,6 +1082,8 @@ export const useAssistantStore = create<AssistantState>()(
         set({ windowMode: mode });
         invoke("set_window_mode", { windowed: mode === "windowed", onTop: null }).catch(console.error);
       },
+      settingsOpen: false,
+      setSettingsOpen: (open) => set({ settingsOpen: open }),
       // ── API config ─────────────────────────────────────────────────
       apiKey:  "",
       setApiKey: (k) => set({ apiKey: k }),
@@ -826,7 +1109,7 @@ export const useAssistantStore = create<AssistantState>()(
           const errMsg: ChatMessage = {
             id:        crypto.randomUUID(),
             role:      "assistant",
-            text:      `**Screenshot failed:** ${String(err)}\n\nMake sure \`grim\` (Wayland) or \`scrot\` / \`import\` (X11) is installed:\n\`\`\`bash\n# Fedora\nsudo dnf install grim ImageMagick\n# Ubuntu\nsudo apt install grim imagemagick\n# Arch\nsudo pacman -S grim imagemagick\n\`\`\``,
+            text:      `**Screenshot failed:** ${errorMessage(err)}\n\nMake sure \`grim\` (Wayland) or \`scrot\` / \`import\` (X11) is installed:\n\`\`\`bash\n# Fedora\nsudo dnf install grim ImageMagick\n# Ubuntu\nsudo apt install grim imagemagick\n# Arch\nsudo pacman -S grim imagemagick\n\`\`\``,
             timestamp: Date.now(),
           };
           set((s) => ({ messages: [...s.messages, errMsg] }));
@@ -888,34 +1171,63 @@ export const useAssistantStore = create<AssistantState>()(
 
         try {
           // Build RAG context blocks (max 20 files, 3 KB each)
-          const contextFiles = indexedFiles
+          let contextFiles = indexedFiles
             .slice(0, 20)
             .map((f) => `### ${f.path}\n\`\`\`${f.extension}\n${f.content.slice(0, 3_000)}\n\`\`\``);
 
+          // ── Semantic project context (project_vectors.rs) ──────────
+          // Vectors are kept current by indexVectors and syncIndexDelta;
+          // on failure the first files above are sent as before
+          if (get().semanticContext && indexedRoot && userMsg.text.trim()) {
+            try {
+              const embed = embedParams(get());
+              const hits = await Promise.race([
+                invoke<Array<{ path: string; start_line: number; end_line: number; content: string }>>(
+                  "semantic_search",
+                  { root: indexedRoot, query: userMsg.text.slice(0, 2_000), topK: 12, embed }
+                ),
+                masterCancel,
+              ]);
+              if (hits.length > 0) {
+                contextFiles = hits.map((h) =>
+                  `### ${h.path} (lines ${h.start_line}–${h.end_line})\n\`\`\`${h.path.split(".").pop() ?? ""}\n${h.content}\n\`\`\``);
+              }
+            } catch (err) {
+              if (isCancelled(err)) throw err; // propagate stop
+              console.warn("Semantic search failed, sending the first files:", err);
+            }
+          }
+
           // Build conversation history from previous messages (last 10 turns = 20 messages)
-          const historyMessages = messages.slice(-20);
-          const historyBlock = historyMessages.length > 0
-            ? historyMessages
-                .map((m) => `${m.role === "user" ? "User" : "Assistant"}: ${m.text}`)
-                .join("\n\n") + "\n\n"
-            : "";
+          const historyTurns = messages
+            .slice(-20)
+            .map((m) => `${m.role === "user" ? "User" : "Assistant"}: ${m.text}`);
+          const historyBlock = historyTurns.length > 0 ? historyTurns.join("\n\n") + "\n\n" : "";
 
-          // Build full prompt: history + current message + file-editing instructions
-          const currentText = indexedRoot
-            ? userMsg.text + FILE_EDIT_INSTRUCTIONS
-            : userMsg.text;
+          // Build full prompt: history + current message
+          const currentText = userMsg.text;
 
           // ── Web search (if enabled) ──────────────────────────────
           let webSearchContext = "";
           if (webSearchEnabled && prompt.trim()) {
             try {
-              const { fetchPageContent, searchMaxResults } = get();
+              const { fetchPageContent, searchMaxResults, fetchUserAgent } = get();
               const command = fetchPageContent ? "search_and_fetch" : "web_search";
+              if (fetchPageContent) {
+                await invoke("set_fetch_user_agent", { userAgent: fetchUserAgent.trim() || null });
+              }
               const searchReq = {
                 query:         userMsg.text.slice(0, 300).trim(),
                 backend:       searchBackend,
-                api_key:       searchBackend === "brave" ? searchApiKey : null,
+                api_key:       searchBackend === "searxng" || searchBackend === "duckduckgo" ? null : searchApiKey,
                 base_url:      searchBackend === "searxng" ? searxngUrl : null,
+                market:        get().searchMarket,
+                cache_ttl_secs: get().searchCacheMinutes * 60,
+                include_domains: get().searchIncludeDomains,
+                exclude_domains: get().searchExcludeDomains,
+                rewrite:       get().searchRewriteQueries
+                  ? { provider, api_key: apiKey || "", model, local_url: localUrl || null }
+                  : null,
                 max_results:   searchMaxResults,
                 fetch_content: fetchPageContent,
               };
@@ -939,9 +1251,9 @@ export const useAssistantStore = create<AssistantState>()(
                 webSearchContext += "\n---\nAnswer using the search results above. Cite source URLs when relevant. Prefer information from fetched page content over snippets.\n";
               }
             } catch (err) {
-              if (String(err).includes("__CANCELLED__")) throw err; // propagate stop
+              if (isCancelled(err)) throw err; // propagate stop
               console.warn("Web search failed:", err);
-              webSearchContext = `\n\n⚠️ Web search failed: ${String(err)}\n`;
+              webSearchContext = `\n\n⚠️ Web search failed: ${errorMessage(err)}\n`;
             }
           }
 
@@ -949,34 +1261,42 @@ export const useAssistantStore = create<AssistantState>()(
             ? `\n[Отвечай исключительно на ${LANGUAGE_NAMES[responseLanguage] ?? responseLanguage}]`
             : "";
 
+          const turnPrompt = `${currentText}${webSearchContext}${langSuffix}`;
           const fullPrompt = historyBlock
-            ? `[Conversation history]\n${historyBlock}[Current message]\nUser: ${currentText}${webSearchContext}${langSuffix}`
-            : `${currentText}${webSearchContext}${langSuffix}`;
+            ? `[Conversation history]\n${historyBlock}[Current message]\nUser: ${turnPrompt}`
+            : turnPrompt;
 
-          // ── Build character system prompt (sent as a real system message) ──
+          // ── System prompt: layered and budgeted in Rust (prompt_layers.rs) ──
           const activeChar = activeCharacterId ? characters.find((c) => c.id === activeCharacterId) : null;
-          const langPrefix = responseLanguage !== "auto"
-            ? `[SYSTEM LANGUAGE OVERRIDE — HIGHEST PRIORITY RULE: You MUST respond EXCLUSIVELY in ${LANGUAGE_NAMES[responseLanguage] ?? responseLanguage}. This rule overrides EVERYTHING, including: the language of previous messages in conversation history, the language the user wrote in, any language patterns you observe in the dialogue. Every single word of your response — dialogue, narration, actions, thoughts, descriptions, inner monologue — must be in ${LANGUAGE_NAMES[responseLanguage] ?? responseLanguage}. DO NOT write even a single word in any other language. This applies to your CURRENT response and ALL future responses in this conversation.]\n\n`
-            : "";
-          const charSystemPrompt: string | null = activeChar
-            ? langPrefix + [
-                `You are ${activeChar.name}.`,
-                activeChar.system_prompt,
-                activeChar.description && `Description: ${activeChar.description}`,
-                activeChar.personality && `Personality: ${activeChar.personality}`,
-                activeChar.scenario    && `Scenario: ${activeChar.scenario}`,
-                activeChar.mes_example && `Example dialogue:\n${activeChar.mes_example}`,
-              ].filter(Boolean).join("\n")
-            : (langPrefix || null);
+          const composed = await invoke<{ text: string | null }>("compose_system_prompt", {
+            spec: {
+              language:     responseLanguage !== "auto" ? (LANGUAGE_NAMES[responseLanguage] ?? responseLanguage) : null,
+              persona:      activeChar ? {
+                name:          activeChar.name,
+                system_prompt: activeChar.system_prompt,
+                description:   activeChar.description,
+                personality:   activeChar.personality,
+                scenario:      activeChar.scenario,
+                mes_example:   activeChar.mes_example,
+              } : null,
+              project_root: indexedRoot || null,
+              tools:        indexedRoot ? ["file_edit"] : [],
+            },
+          });
+          const systemPrompt: string | null = composed.text;
 
           const finalPrompt = fullPrompt;
 
           // ── Shared request payload ────────────────────────────────
+          // History goes separately so analyze_stream can trim it on overflow
           const streamPayload = {
             provider,
             api_key:       apiKey || null,
-            prompt:        finalPrompt,
-            system_prompt: charSystemPrompt,
+            prompt:        turnPrompt,
+            history:       historyTurns.length ? historyTurns : null,
+            fallback_model: get().fallbackModel.trim() || null,
+            profile:       get().moderationProfile,
+            system_prompt: systemPrompt,
             image_base64:  capturedImage ?? null,
             context_files: contextFiles.length ? contextFiles : null,
             model,
@@ -993,13 +1313,17 @@ export const useAssistantStore = create<AssistantState>()(
 
             let unlistenToken: (() => void) | null = null;
             let unlistenDone:  (() => void) | null = null;
+            let unlistenTrim:  (() => void) | null = null;
+            /** Set when analyze_stream retried a prompt that overflowed the model */
+            let trimNote = "";
 
             try {
               const cleanup = () => {
-                const t = unlistenToken; const d = unlistenDone;
-                unlistenToken = null; unlistenDone = null;
+                const t = unlistenToken; const d = unlistenDone; const tr = unlistenTrim;
+                unlistenToken = null; unlistenDone = null; unlistenTrim = null;
                 t && (t as () => void)();
                 d && (d as () => void)();
+                tr && (tr as () => void)();
               };
 
               // Promise that resolves when the stream is finished
@@ -1016,9 +1340,19 @@ export const useAssistantStore = create<AssistantState>()(
                       resolve(event.payload.text ?? get().streamingText);
                     }
                   }),
-                ]).then(([ulToken, ulDone]) => {
+                  listen<{ dropped_files: number; dropped_turns: number; switched_model: string | null }>(
+                    "ai-context-trimmed",
+                    (event) => {
+                      const r = event.payload;
+                      trimNote = r.switched_model
+                        ? `Context too long for ${model} — answered by ${r.switched_model}.`
+                        : `Context too long — retried without ${r.dropped_files} context file(s) and ${r.dropped_turns} earlier message(s).`;
+                    }
+                  ),
+                ]).then(([ulToken, ulDone, ulTrim]) => {
                   unlistenToken = ulToken;
                   unlistenDone  = ulDone;
+                  unlistenTrim  = ulTrim;
                   // Now invoke after listeners are registered
                   invoke("analyze_stream", { req: streamPayload }).catch(reject);
                 }).catch(reject);
@@ -1030,7 +1364,7 @@ export const useAssistantStore = create<AssistantState>()(
               const assistantMsg: ChatMessage = {
                 id:        crypto.randomUUID(),
                 role:      "assistant",
-                text:      trimToSentenceBoundary(finalText, maxTokens),
+                text:      trimToSentenceBoundary(finalText, maxTokens) + (trimNote ? `\n\n_${trimNote}_` : ""),
                 timestamp: Date.now(),
               };
               set((s) => ({
@@ -1040,10 +1374,11 @@ export const useAssistantStore = create<AssistantState>()(
                 streamingText: "",
               }));
             } catch (err) {
-              const t = unlistenToken; const d = unlistenDone;
+              const t = unlistenToken; const d = unlistenDone; const tr = unlistenTrim;
               // TypeScript narrowing guard for captured mutable references
               t && (t as () => void)();
               d && (d as () => void)();
+              tr && (tr as () => void)();
               set({ isStreaming: false, streamingText: "" });
               throw err; // re-throw so outer catch handles it
             }
@@ -1053,6 +1388,7 @@ export const useAssistantStore = create<AssistantState>()(
               provider === "openai"   ? "analyze_with_openai"   :
               provider === "claude"   ? "analyze_with_claude"   :
               provider === "deepseek" ? "analyze_with_deepseek" :
+              provider === "together" ? "analyze_with_together" :
               provider === "local"    ? "analyze_with_local"    :
                                        "analyze_with_openrouter";
 
@@ -1061,7 +1397,7 @@ export const useAssistantStore = create<AssistantState>()(
                   base_url:      localUrl,
                   api_key:       apiKey || null,
                   prompt:        finalPrompt,
-                  system_prompt: charSystemPrompt,
+                  system_prompt: systemPrompt,
                   image_base64:  capturedImage ?? null,
                   context_files: contextFiles.length ? contextFiles : null,
                   model,
@@ -1070,11 +1406,12 @@ export const useAssistantStore = create<AssistantState>()(
               : {
                   api_key:       apiKey,
                   prompt:        finalPrompt,
-                  system_prompt: charSystemPrompt,
+                  system_prompt: systemPrompt,
                   image_base64:  capturedImage ?? null,
                   context_files: contextFiles.length ? contextFiles : null,
                   model,
                   max_tokens:    maxTokens ?? null,
+                  profile:       get().moderationProfile,
                 };
 
             const result = await Promise.race([
@@ -1091,13 +1428,13 @@ export const useAssistantStore = create<AssistantState>()(
             set((s) => ({ messages: [...s.messages, assistantMsg], capturedImage: null }));
           }
         } catch (err) {
-          if (String(err).includes("__CANCELLED__")) {
+          if (isCancelled(err)) {
             // User cancelled — no error message needed
           } else {
             const errMsg: ChatMessage = {
               id:        crypto.randomUUID(),
               role:      "assistant",
-              text:      `**Error:** ${String(err)}`,
+              text:      `**Error:** ${errorWithHint(err)}`,
               timestamp: Date.now(),
             };
             set((s) => ({ messages: [...s.messages, errMsg] }));
@@ -1198,8 +1535,61 @@ export const useAssistantStore = create<AssistantState>()(
           console.error("Index failed:", err);
           throw err;
         }
+        // Keep the index in sync with later edits; indexing works without it
+        invoke("watch_project", { root: path }).catch((err) => console.warn("Project watch failed:", err));
+        if (get().semanticContext) void get().indexVectors();
+      },
+      clearIndex: () => {
+        set({ indexedFiles: [], indexedRoot: "" });
+        invoke("unwatch_project").catch(() => {});
+      },
+      semanticContext: false,
+      setSemanticContext: (v) => {
+        set({ semanticContext: v });
+        if (v && get().indexedRoot) void get().indexVectors();
+      },
+      indexVectors: async () => {
+        const root = get().indexedRoot;
+        try {
+          await invoke("index_project_vectors", { root, embed: embedParams(get()) });
+        } catch (err) {
+          console.warn("Project vector indexing failed:", err);
+        }
+      },
+      syncIndexDelta: async () => {
+        const delta = await invoke<{ root_path: string; changed: IndexedFile[]; removed: string[] }>("get_index_delta");
+        const s0 = get();
+        const paths = [...delta.changed.map((f) => f.path), ...delta.removed];
+        if (s0.semanticContext && delta.root_path === s0.indexedRoot && paths.length > 0) {
+          invoke("update_project_vectors", { root: delta.root_path, paths, embed: embedParams(s0) })
+            .catch((err) => console.warn("Project vector update failed:", err));
+        }
+        set((s) => {
+          if (delta.root_path !== s.indexedRoot) return {};
+          const changed = new Map(delta.changed.map((f) => [f.path, f]));
+          const removed = new Set(delta.removed);
+          const kept = s.indexedFiles.filter((f) => !removed.has(f.path)).map((f) => changed.get(f.path) ?? f);
+          const known = new Set(kept.map((f) => f.path));
+          return { indexedFiles: [...kept, ...delta.changed.filter((f) => !known.has(f.path))] };
+        });
+      },
+      ingestClipboard: async () => {
+        type Ingest =
+          | { kind: "image"; image_base64: string }
+          | { kind: "files"; files: IndexedFile[]; skipped: number }
+          | { kind: "url" | "code" | "text"; context: string };
+        const res = await invoke<Ingest>("ingest_clipboard");
+        if (res.kind === "image") {
+          set({ capturedImage: res.image_base64 });
+        } else if (res.kind === "files") {
+          set((s) => {
+            const known = new Set(s.indexedFiles.map((f) => f.path));
+            return { indexedFiles: [...s.indexedFiles, ...res.files.filter((f) => !known.has(f.path))] };
+          });
+        } else {
+          set((s) => ({ prompt: s.prompt.trim() ? `${s.prompt.trimEnd()}\n\n${res.context}` : res.context }));
+        }
       },
-      clearIndex: () => set({ indexedFiles: [], indexedRoot: "" }),
 
       // ── Web Search ────────────────────────────────────────────────
       webSearchEnabled: false,
@@ -1208,6 +1598,18 @@ export const useAssistantStore = create<AssistantState>()(
       setSearchBackend: (b) => set({ searchBackend: b }),
       searchApiKey: "",
       setSearchApiKey: (k) => set({ searchApiKey: k }),
+      searchMarket: "en-US",
+      setSearchMarket: (m) => set({ searchMarket: m }),
+      searchCacheMinutes: 60,
+      setSearchCacheMinutes: (n) => set({ searchCacheMinutes: n }),
+      searchIncludeDomains: [],
+      setSearchIncludeDomains: (d) => set({ searchIncludeDomains: d }),
+      searchExcludeDomains: [],
+      setSearchExcludeDomains: (d) => set({ searchExcludeDomains: d }),
+      searchRewriteQueries: false,
+      setSearchRewriteQueries: (v) => set({ searchRewriteQueries: v }),
+      fetchUserAgent: "",
+      setFetchUserAgent: (ua) => set({ fetchUserAgent: ua }),
       searxngUrl: "http://localhost:8080",
       setSearxngUrl: (url) => set({ searxngUrl: url }),      fetchPageContent: false,
       setFetchPageContent: (v) => set({ fetchPageContent: v }),
@@ -1246,6 +1648,56 @@ export const useAssistantStore = create<AssistantState>()(
       responseLanguage:    "ru",
       setResponseLanguage: (lang) => set({ responseLanguage: lang }),
 
+      // ── Moderation ──────────────────────────────────────────────────
+      moderationMode:   "off",
+      moderationTerms:  "",
+      moderationApiKey: "",
+      moderationProfile:  "default",
+      moderationProfiles: {},
+      setModeration: (patch) => {
+        set(patch);
+        get().syncModeration().catch((err) => console.warn("Moderation settings rejected:", err));
+      },
+      switchModerationProfile: (name) => {
+        const s = get();
+        const next = name.trim() || "default";
+        if (next === s.moderationProfile) return;
+        const profiles = {
+          ...s.moderationProfiles,
+          [s.moderationProfile]: { mode: s.moderationMode, terms: s.moderationTerms, apiKey: s.moderationApiKey },
+        };
+        const loaded = profiles[next] ?? { mode: "off", terms: "", apiKey: "" };
+        set({
+          moderationProfiles: profiles,
+          moderationProfile:  next,
+          moderationMode:     loaded.mode,
+          moderationTerms:    loaded.terms,
+          moderationApiKey:   loaded.apiKey,
+        });
+        get().syncModeration().catch((err) => console.warn("Moderation settings rejected:", err));
+      },
+      syncModeration: async () => {
+        const { moderationMode, moderationTerms, moderationApiKey, moderationProfile, moderationProfiles } = get();
+        const profiles: Record<string, ModerationSettings> = {
+          ...moderationProfiles,
+          [moderationProfile]: { mode: moderationMode, terms: moderationTerms, apiKey: moderationApiKey },
+        };
+        await Promise.all(Object.entries(profiles).map(([profile, p]) =>
+          invoke("set_moderation_config", {
+            profile,
+            active: profile === moderationProfile,
+            config: {
+              mode:          p.mode,
+              api_key:       p.apiKey || null,
+              categories:    [],
+              threshold:     null,
+              blocked_terms: p.terms.split(",").map((t) => t.trim()).filter(Boolean),
+              patterns:      [],
+            },
+          })
+        ));
+      },
+
       // ── File editing ───────────────────────────────────────────────
       applyEdit: async (filePath, content, rootPath) => {
         // Resolve relative paths against the indexed project root
@@ -1335,6 +1787,13 @@ export const useAssistantStore = create<AssistantState>()(
           webSearchEnabled:  s.webSearchEnabled,
           searchBackend:     s.searchBackend,
           searchApiKey:      s.searchApiKey,
+          searchMarket:      s.searchMarket,
+          searchCacheMinutes: s.searchCacheMinutes,
+          searchIncludeDomains: s.searchIncludeDomains,
+          searchExcludeDomains: s.searchExcludeDomains,
+          searchRewriteQueries: s.searchRewriteQueries,
+          fetchUserAgent:    s.fetchUserAgent,
+          semanticContext:   s.semanticContext,
           searxngUrl:        s.searxngUrl,
           fetchPageContent:  s.fetchPageContent,
           searchMaxResults:  s.searchMaxResults,
@@ -1343,9 +1802,15 @@ export const useAssistantStore = create<AssistantState>()(
           characters: s.characters.map(({ avatarBase64: _av, ...c }) => c as CharacterCard),
           activeCharacterId: s.activeCharacterId,
           responseLanguage:  s.responseLanguage,
+          moderationMode:    s.moderationMode,
+          moderationTerms:   s.moderationTerms,
+          moderationApiKey:  s.moderationApiKey,
+          moderationProfile:  s.moderationProfile,
+          moderationProfiles: s.moderationProfiles,
           windowMode:        s.windowMode,
           fontSize:          s.fontSize,
           maxTokens:         s.maxTokens,
+          fallbackModel:     s.fallbackModel,
           // Keep only the 50 most recent messages (no images)
           messages:          stripImages(s.messages.slice(-50)),
           activeSessionId:   s.activeSessionId,
@@ -1361,6 +1826,22 @@ export const useAssistantStore = create<AssistantState>()(
           imageGenUrl:       s.imageGenUrl,
           imageGenWidth:     s.imageGenWidth,
           imageGenHeight:    s.imageGenHeight,
+          imageGenStyleId:   s.imageGenStyleId,
+          imageGenSeed:      s.imageGenSeed,
+          imageGenNegPrompt: s.imageGenNegPrompt,
+          // gpt-image-1 options
+          openaiImageQuality:    s.openaiImageQuality,
+          openaiImageBackground: s.openaiImageBackground,
+          openaiImageFormat:     s.openaiImageFormat,
+          // A1111 / Forge settings
+          webuiSteps:         s.webuiSteps,
+          webuiCfg:           s.webuiCfg,
+          webuiNegPrompt:     s.webuiNegPrompt,
+          webuiSampler:       s.webuiSampler,
+          webuiSeed:          s.webuiSeed,
+          webuiHiresScale:    s.webuiHiresScale,
+          webuiHiresUpscaler: s.webuiHiresUpscaler,
+          webuiHiresDenoise:  s.webuiHiresDenoise,
           // Native SD settings
           nativeSdModelPath: s.nativeSdModelPath,
           nativeSdModelsDir: s.nativeSdModelsDir,
@@ -1370,9 +1851,17 @@ export const useAssistantStore = create<AssistantState>()(
           nativeSdSampler:    s.nativeSdSampler,
           nativeSdSeed:       s.nativeSdSeed,
           nativeSdGpuBackend: s.nativeSdGpuBackend,
+          nativeSdGpuDevice:  s.nativeSdGpuDevice,
           nativeSdVaeOnCpu:     s.nativeSdVaeOnCpu,
           nativeSdVaeTiling:    s.nativeSdVaeTiling,
           nativeSdOffloadToCpu: s.nativeSdOffloadToCpu,
+          nativeSdServerMode:   s.nativeSdServerMode,
+          nativeSdVaePath:      s.nativeSdVaePath,
+          nativeSdClipLPath:    s.nativeSdClipLPath,
+          nativeSdClipGPath:    s.nativeSdClipGPath,
+          nativeSdT5xxlPath:    s.nativeSdT5xxlPath,
+          nativeSdTaesdPath:    s.nativeSdTaesdPath,
+          downloadLimitMbps:    s.downloadLimitMbps,
         };
       },
     }
@@ -0,0 +1,57 @@
+/**
+ * errors.ts — helpers for errors rejected by Tauri commands.
+ *
+ * Commands reject with a structured AppError (src-tauri/src/error.rs):
+ *   { kind, message, provider, retryable, hint }
+ * Frontend code also throws plain Errors / strings (e.g. the "__CANCELLED__"
+ * sentinel of the stop button), so every helper accepts `unknown`.
+ */
+
+export type ErrorKind =
+  | "auth"
+  | "rate_limit"
+  | "network"
+  | "timeout"
+  | "validation"
+  | "not_found"
+  | "cancelled"
+  | "provider"
+  | "io"
+  | "internal";
+
+export interface AppError {
+  kind:      ErrorKind;
+  message:   string;
+  provider:  string | null;
+  retryable: boolean;
+  hint:      string | null;
+}
+
+export function isAppError(e: unknown): e is AppError {
+  return typeof e === "object" && e !== null
+    && typeof (e as AppError).kind === "string"
+    && typeof (e as AppError).message === "string";
+}
+
+/** Human-readable text of any rejection value. */
+export function errorMessage(e: unknown): string {
+  if (isAppError(e)) return e.message;
+  if (e instanceof Error) return e.message;
+  return String(e);
+}
+
+/** The error kind, or null for errors that did not come from a command. */
+export function errorKind(e: unknown): ErrorKind | null {
+  return isAppError(e) ? e.kind : null;
+}
+
+/** True for a request stopped by the user (stop button / cancel_ai_request). */
+export function isCancelled(e: unknown): boolean {
+  return errorKind(e) === "cancelled" || errorMessage(e).includes("__CANCELLED__");
+}
+
+/** Message followed by the backend's hint, if it sent one. */
+export function errorWithHint(e: unknown): string {
+  const hint = isAppError(e) ? e.hint : null;
+  return hint ? `${errorMessage(e)}\n\n_${hint}_` : errorMessage(e);
+}